@@ -0,0 +1,90 @@
+//! Accounting and rate limiting of the bandwidth used to download artifacts.
+//!
+//! The rate limit is shared by every download in the process, so a Supervisor updating many
+//! services at once stays within it as a whole rather than per download.
+
+use prometheus::IntCounter;
+use std::{sync::{atomic::{AtomicU64,
+                          Ordering},
+                 Mutex},
+          time::{Duration,
+                 Instant}};
+
+lazy_static! {
+    static ref DOWNLOADED_BYTES: IntCounter =
+        register_int_counter!("hab_api_client_download_bytes_total",
+                              "Total number of bytes of artifacts downloaded").unwrap();
+    /// The time at which the rate limit allows the next chunk to be downloaded
+    static ref NEXT_DOWNLOAD: Mutex<Option<Instant>> = Mutex::new(None);
+}
+
+/// The download rate limit in bytes per second; 0 means unlimited
+static DOWNLOAD_RATE_LIMIT: AtomicU64 = AtomicU64::new(0);
+
+/// Limit the combined rate of all artifact downloads to `bytes_per_second`, or remove the limit
+/// with `None`.
+pub fn set_download_rate_limit(bytes_per_second: Option<u64>) {
+    DOWNLOAD_RATE_LIMIT.store(bytes_per_second.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// The current download rate limit in bytes per second, if any
+pub fn download_rate_limit() -> Option<u64> {
+    match DOWNLOAD_RATE_LIMIT.load(Ordering::Relaxed) {
+        0 => None,
+        limit => Some(limit),
+    }
+}
+
+/// Account for a downloaded chunk of `len` bytes, waiting as long as the rate limit requires
+/// before returning.
+pub(crate) async fn downloaded(len: usize) {
+    DOWNLOADED_BYTES.inc_by(len as u64);
+    if let Some(limit) = download_rate_limit() {
+        let delay = {
+            let mut next_download = NEXT_DOWNLOAD.lock().expect("NEXT_DOWNLOAD lock poisoned");
+            reserve(&mut next_download, Instant::now(), len, limit)
+        };
+        if delay > Duration::from_secs(0) {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Reserve the time needed to transfer `len` bytes at `limit` bytes per second, starting when
+/// the previous reservation ends. Returns how long to wait, from `now`, until the end of the
+/// reservation.
+fn reserve(next_download: &mut Option<Instant>, now: Instant, len: usize, limit: u64) -> Duration {
+    let start = match *next_download {
+        Some(next) if next > now => next,
+        _ => now,
+    };
+    let end = start + Duration::from_secs_f64(len as f64 / limit as f64);
+    *next_download = Some(end);
+    end - now
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reservations_are_paced_at_the_limit() {
+        let now = Instant::now();
+        let mut next_download = None;
+        assert_eq!(reserve(&mut next_download, now, 500, 1000),
+                   Duration::from_millis(500));
+        // A second chunk queues up behind the first
+        assert_eq!(reserve(&mut next_download, now, 1000, 1000),
+                   Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn idle_time_is_not_saved_up() {
+        let now = Instant::now();
+        let mut next_download = Some(now);
+        let later = now + Duration::from_secs(10);
+        assert_eq!(reserve(&mut next_download, later, 2000, 1000),
+                   Duration::from_secs(2));
+        assert_eq!(next_download, Some(later + Duration::from_secs(2)));
+    }
+}
@@ -1,4 +1,5 @@
-use crate::{error::{Error,
+use crate::{bandwidth,
+            error::{Error,
                     Result},
             hab_http::ApiClient,
             response,
@@ -18,6 +19,7 @@ use bytes::BytesMut;
 use futures::{io::AllowStdIo,
               stream::TryStreamExt};
 use habitat_core::{crypto::keys::AnonymousBox,
+                   env,
                    fs::{AtomicWriter,
                         Permissions,
                         DEFAULT_CACHED_ARTIFACT_PERMISSIONS,
@@ -138,6 +140,9 @@ impl BuilderAPIClient {
         where U: IntoUrl
     {
         let mut endpoint = endpoint.into_url().map_err(Error::ReqwestError)?;
+        if env::is_offline() {
+            return Err(Error::Offline(endpoint.to_string()));
+        }
         if !endpoint.cannot_be_a_base() && endpoint.path() == "/" {
             endpoint.set_path(DEFAULT_API_PATH);
         }
@@ -174,14 +179,20 @@ impl BuilderAPIClient {
                           -> Result<PathBuf> {
         debug!("Downloading file to path: {}", dst_path.display());
         let resp = self.maybe_add_authz(rb, token).send().await?;
-        let resp = response::ok_if(resp, &[StatusCode::OK]).await?;
+        let mut resp = response::ok_if(resp, &[StatusCode::OK]).await?;
 
         fs::create_dir_all(&dst_path)?;
         let file_name = response::get_header(&resp, X_FILENAME)?;
         let dst_file_path = dst_path.join(file_name);
         let w = AtomicWriter::new_with_permissions(&dst_file_path, permissions)?;
         let content_length = response::get_header(&resp, CONTENT_LENGTH);
-        let mut body = Cursor::new(resp.bytes().await?);
+        // Read the body a chunk at a time so that it can be accounted for and rate limited
+        let mut bytes = Vec::new();
+        while let Some(chunk) = resp.chunk().await? {
+            bandwidth::downloaded(chunk.len()).await;
+            bytes.extend_from_slice(&chunk);
+        }
+        let mut body = Cursor::new(bytes);
         // Blocking IO is used because of `DisplayProgress` which relies on the `Write` trait.
         task::spawn_blocking(move || {
             w.with_writer(|mut f| {
@@ -78,6 +78,7 @@ pub enum Error {
     UrlParseError(url::ParseError),
     WriteSyncFailed,
     NotSupported,
+    Offline(String),
     TokioJoinError(JoinError),
 }
 
@@ -121,6 +122,11 @@ impl fmt::Display for Error {
                 "Could not write to destination; perhaps the disk is full?".to_string()
             }
             Error::NotSupported => "The specified operation is not supported.".to_string(),
+            Error::Offline(ref url) => {
+                format!("Unable to contact Builder at {}, network access is disabled in offline \
+                         mode",
+                        url)
+            }
             Error::TokioJoinError(ref e) => format!("{}", e),
         };
         write!(f, "{}", msg)
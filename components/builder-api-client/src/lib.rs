@@ -5,8 +5,12 @@ use habitat_core::{self as hab_core,
                                        TabularText}};
 use habitat_http_client as hab_http;
 
+#[macro_use]
+extern crate lazy_static;
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate prometheus;
 
 #[macro_use]
 extern crate serde_derive;
@@ -14,6 +18,7 @@ extern crate serde_derive;
 #[macro_use]
 extern crate serde_json;
 
+pub mod bandwidth;
 pub mod builder;
 pub mod error;
 pub mod response;
@@ -6,17 +6,33 @@ use crate::{error::{Error,
                     Result},
             message,
             rumor::{departure::Departure,
+                    fleet_manifest::FleetManifest,
                     service_config::ServiceConfig,
                     service_file::ServiceFile,
                     Rumor},
+            tls::{self,
+                  Channel,
+                  ClientStream,
+                  RingTls},
             ZMQ_CONTEXT};
 use habitat_core::{crypto::keys::RingKey,
                    service::ServiceGroup};
+use std::{net::SocketAddr,
+          time::SystemTime};
 
-/// Holds a ZMQ Push socket, and an optional ring encryption key.
+/// The member ID of the rumors created by the client
+pub const MEMBER_ID: &str = "butterflyclient";
+
+/// Holds a ZMQ Push socket, and an optional ring encryption key, or a TLS connection in a ring
+/// using TLS.
 pub struct Client {
-    socket:   zmq::Socket,
-    ring_key: Option<RingKey>,
+    connection: Connection,
+    ring_key:   Option<RingKey>,
+}
+
+enum Connection {
+    Zmq(zmq::Socket),
+    Tls(Box<ClientStream>),
 }
 
 impl Client {
@@ -35,9 +51,19 @@ impl Client {
               .expect("Failure to set the ZMQ push socket hwm");
         socket.set_sndtimeo(500)
               .expect("Failure to set the ZMQ send timeout");
+        socket.set_ipv6(true)
+              .expect("Failure to enable IPv6 on the ZMQ push socket");
         let to_addr = format!("tcp://{}", addr);
         socket.connect(&to_addr).map_err(Error::ZmqConnectError)?;
-        Ok(Client { socket, ring_key })
+        Ok(Client { connection: Connection::Zmq(socket),
+                    ring_key })
+    }
+
+    /// Connect this client to the address, in a ring using TLS.
+    pub fn with_tls(addr: SocketAddr, tls: &RingTls) -> Result<Client> {
+        let stream = tls.connect(addr, Channel::Gossip)?;
+        Ok(Client { connection: Connection::Tls(Box::new(stream)),
+                    ring_key:   None, })
     }
 
     /// Create a departure notification and send it to the server.
@@ -46,6 +72,21 @@ impl Client {
         self.send(&departure)
     }
 
+    /// Create a fleet manifest and send it to the server.
+    pub fn send_fleet_manifest(&mut self,
+                               incarnation: u64,
+                               signer: &str,
+                               signature: &[u8],
+                               body: &[u8])
+                               -> Result<()> {
+        let fm = FleetManifest::new(MEMBER_ID,
+                                    incarnation,
+                                    signer,
+                                    signature.to_vec(),
+                                    body.to_vec());
+        self.send(&fm)
+    }
+
     /// Create a service configuration and send it to the server.
     pub fn send_service_config(&mut self,
                                service_group: ServiceGroup,
@@ -53,9 +94,22 @@ impl Client {
                                config: &[u8],
                                encrypted: bool)
                                -> Result<()> {
-        let mut sc = ServiceConfig::new("butterflyclient", service_group, config.to_vec());
+        self.send_scheduled_service_config(service_group, incarnation, config, encrypted, None)
+    }
+
+    /// Create a service configuration that members apply at `activate_at` by their clocks, or as
+    /// soon as they receive it without one, and send it to the server.
+    pub fn send_scheduled_service_config(&mut self,
+                                         service_group: ServiceGroup,
+                                         incarnation: u64,
+                                         config: &[u8],
+                                         encrypted: bool,
+                                         activate_at: Option<SystemTime>)
+                                         -> Result<()> {
+        let mut sc = ServiceConfig::new(MEMBER_ID, service_group, config.to_vec());
         sc.incarnation = incarnation;
         sc.encrypted = encrypted;
+        sc.activate_at = activate_at;
         self.send(&sc)
     }
 
@@ -69,7 +123,7 @@ impl Client {
                                 -> Result<()>
         where S: Into<String>
     {
-        let mut sf = ServiceFile::new("butterflyclient", service_group, filename, body.to_vec());
+        let mut sf = ServiceFile::new(MEMBER_ID, service_group, filename, body.to_vec());
         sf.incarnation = incarnation;
         sf.encrypted = encrypted;
         self.send(&sf)
@@ -80,7 +134,10 @@ impl Client {
         where T: Rumor
     {
         let bytes = rumor.write_to_bytes()?;
-        let wire_msg = message::generate_wire(bytes, self.ring_key.as_ref())?;
-        self.socket.send(&wire_msg, 0).map_err(Error::ZmqSendError)
+        let wire_msg = message::generate_wire(bytes, self.ring_key.as_ref(), None)?;
+        match &mut self.connection {
+            Connection::Zmq(socket) => socket.send(&wire_msg, 0).map_err(Error::ZmqSendError),
+            Connection::Tls(stream) => Ok(tls::write_message(stream, &wire_msg)?),
+        }
     }
 }
@@ -13,6 +13,7 @@ pub enum Error {
     BadDataPath(PathBuf, io::Error),
     BadDatFile(PathBuf, io::Error),
     CannotBind(io::Error),
+    Compression(String),
     DatFileIO(PathBuf, io::Error),
     DecodeError(prost::DecodeError),
     EncodeError(prost::EncodeError),
@@ -20,14 +21,18 @@ pub enum Error {
     IncarnationIO(PathBuf, io::Error),
     IncarnationParse(PathBuf, num::ParseIntError),
     InvalidRumorShareLimit,
+    InvalidSwimTiming(String),
     NonExistentRumor(String, String),
     OsError(io::Error),
     ProtocolMismatch(&'static str),
+    RingTls(String),
     ServiceConfigDecode(String, toml::de::Error),
     ServiceConfigNotUtf8(String, str::Utf8Error),
+    ServiceFileChunks(String, String),
     SocketCloneError,
     SocketSetReadTimeout(io::Error),
     SocketSetWriteTimeout(io::Error),
+    Stun(String),
     Timeout(String),
     UnknownMember(String),
     ZmqConnectError(zmq::Error),
@@ -49,6 +54,9 @@ impl fmt::Display for Error {
                         err)
             }
             Error::CannotBind(ref err) => format!("Cannot bind to port: {:?}", err),
+            Error::Compression(ref msg) => {
+                format!("Cannot compress or decompress payload: {}", msg)
+            }
             Error::DatFileIO(ref path, ref err) => {
                 format!("Error reading or writing to DatFile, {}, {}",
                         path.display(),
@@ -71,6 +79,7 @@ impl fmt::Display for Error {
             Error::InvalidRumorShareLimit => {
                 "Rumor share limit should be a positive integer".to_string()
             }
+            Error::InvalidSwimTiming(ref msg) => format!("Invalid SWIM protocol timing: {}", msg),
             Error::NonExistentRumor(ref member_id, ref rumor_id) => {
                 format!("Non existent rumor asked to be written to bytes: {} {}",
                         member_id, rumor_id)
@@ -80,12 +89,16 @@ impl fmt::Display for Error {
                 format!("Received an unsupported or bad protocol message. Missing field: {}",
                         field)
             }
+            Error::RingTls(ref msg) => format!("Cannot set up TLS for the ring: {}", msg),
             Error::ServiceConfigDecode(ref sg, ref err) => {
                 format!("Cannot decode service config: group={}, {:?}", sg, err)
             }
             Error::ServiceConfigNotUtf8(ref sg, ref err) => {
                 format!("Cannot read service configuration: group={}, {}", sg, err)
             }
+            Error::ServiceFileChunks(ref filename, ref reason) => {
+                format!("Cannot reassemble service file {}: {}", filename, reason)
+            }
             Error::SocketCloneError => "Cannot clone the underlying UDP socket".to_string(),
             Error::SocketSetReadTimeout(ref err) => {
                 format!("Cannot set UDP socket read timeout: {}", err)
@@ -93,6 +106,7 @@ impl fmt::Display for Error {
             Error::SocketSetWriteTimeout(ref err) => {
                 format!("Cannot set UDP socket write timeout: {}", err)
             }
+            Error::Stun(ref msg) => format!("Cannot detect address with STUN: {}", msg),
             Error::Timeout(ref msg) => format!("Timed out {}", msg),
             Error::UnknownMember(ref member_id) => format!("Unknown member ID: {}", member_id),
             Error::ZmqConnectError(ref err) => format!("Cannot connect ZMQ socket: {}", err),
@@ -0,0 +1,195 @@
+//! The ring keys a server encrypts and decrypts its messages with.
+//!
+//! A keyring holds every revision of the ring key, so that the key can be rotated without the
+//! members of the ring losing touch with each other. Messages encrypted with any revision in the
+//! keyring are accepted. Messages are sent encrypted with the newest revision that has been in the
+//! keyring for `RingKeyActivationDelay`, which gives a new revision time to reach every member's
+//! keyring before any member sends with it. The revisions a server starts with are expected to be
+//! in every member's keyring already, and are sent with right away.
+
+use habitat_core::{crypto::keys::{Key,
+                                  RingKey},
+                   error::{Error,
+                           Result}};
+use std::{sync::Mutex,
+          time::{Duration,
+                 Instant}};
+
+habitat_core::env_config_duration!(
+    /// How long a ring key revision has to be in the keyring before messages are sent with it
+    RingKeyActivationDelay,
+    HAB_RING_KEY_ACTIVATION_DELAY_SECS => from_secs,
+    Duration::from_secs(5 * 60));
+
+#[derive(Debug)]
+pub struct Keyring {
+    activation_delay: Duration,
+    /// Each revision in the keyring, oldest first, and when it was added. The revisions the
+    /// server started with have no time, as they are sent with right away.
+    keys:             Mutex<Vec<(RingKey, Option<Instant>)>>,
+}
+
+impl Keyring {
+    pub fn new(key: RingKey) -> Self {
+        Self::with_activation_delay(key, RingKeyActivationDelay::configured_value().into())
+    }
+
+    pub fn with_activation_delay(key: RingKey, activation_delay: Duration) -> Self {
+        Keyring { activation_delay,
+                  keys: Mutex::new(vec![(key, None)]) }
+    }
+
+    /// Add the revisions `keys` the server starts with, which are sent with right away. Returns
+    /// whether the revisions changed.
+    pub fn load(&self, keys: Vec<RingKey>) -> bool { self.replace(keys, None) }
+
+    /// Replace the revisions in the keyring with `keys`. Revisions that were already in the keyring
+    /// keep the time they were added at. Returns whether the revisions changed.
+    ///
+    /// The keyring is never emptied; when `keys` is empty it is left as it is.
+    pub fn update(&self, keys: Vec<RingKey>, now: Instant) -> bool { self.replace(keys, Some(now)) }
+
+    fn replace(&self, mut keys: Vec<RingKey>, now: Option<Instant>) -> bool {
+        if keys.is_empty() {
+            return false;
+        }
+        keys.sort_by_key(|key| key.named_revision().revision().clone());
+        let mut current = self.keys.lock().expect("Keyring lock poisoned");
+        let updated: Vec<_> = keys.into_iter()
+                                  .map(|key| {
+                                      let added_at = current.iter()
+                                                            .find(|(existing, _)| *existing == key)
+                                                            .map_or(now, |(_, added_at)| *added_at);
+                                      (key, added_at)
+                                  })
+                                  .collect();
+        let changed = updated.len() != current.len()
+                      || updated.iter()
+                                .zip(current.iter())
+                                .any(|((key, _), (existing, _))| key != existing);
+        *current = updated;
+        changed
+    }
+
+    /// The revision messages are sent encrypted with
+    pub fn sending_key(&self, now: Instant) -> RingKey {
+        let keys = self.keys.lock().expect("Keyring lock poisoned");
+        keys.iter()
+            .rev()
+            .find(|(_, added_at)| {
+                added_at.map_or(true, |added_at| {
+                            now.saturating_duration_since(added_at) >= self.activation_delay
+                        })
+            })
+            .or_else(|| keys.first())
+            .map(|(key, _)| key.clone())
+            .expect("Keyring is never empty")
+    }
+
+    /// Decrypt a message encrypted with the revision named `revision`, or, for messages from
+    /// members that do not name the revision, with any revision in the keyring.
+    pub fn decrypt(&self,
+                   revision: Option<&str>,
+                   nonce: &[u8],
+                   ciphertext: &[u8])
+                   -> Result<Vec<u8>> {
+        let keys = self.keys.lock().expect("Keyring lock poisoned");
+        match revision {
+            Some(revision) => {
+                keys.iter()
+                    .find(|(key, _)| key.named_revision().to_string() == revision)
+                    .ok_or_else(|| {
+                        Error::CryptoError(format!("Ring key {} is not in the keyring", revision))
+                    })?
+                    .0
+                    .decrypt(nonce, ciphertext)
+            }
+            None => {
+                keys.iter()
+                    .rev()
+                    .find_map(|(key, _)| key.decrypt(nonce, ciphertext).ok())
+                    .ok_or_else(|| {
+                        Error::CryptoError("No ring key could decrypt the message".to_string())
+                    })
+            }
+        }
+    }
+
+    /// The named revisions in the keyring, oldest first
+    pub fn revisions(&self) -> Vec<String> {
+        self.keys
+            .lock()
+            .expect("Keyring lock poisoned")
+            .iter()
+            .map(|(key, _)| key.named_revision().to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn revisions() -> (RingKey, RingKey) {
+        let old = RingKey::new("beyonce");
+        // Revisions are timestamps with a resolution of a second
+        thread::sleep(Duration::from_millis(1100));
+        (old, RingKey::new("beyonce"))
+    }
+
+    #[test]
+    fn new_revisions_are_sent_with_once_activated() {
+        let (old, new) = revisions();
+        let delay = Duration::from_secs(60);
+        let keyring = Keyring::with_activation_delay(old.clone(), delay);
+        let start = Instant::now();
+        assert!(keyring.update(vec![new.clone(), old.clone()], start));
+        assert!(!keyring.update(vec![old.clone(), new.clone()], start));
+
+        assert_eq!(keyring.sending_key(start), old);
+        assert_eq!(keyring.sending_key(start + delay), new);
+
+        assert!(keyring.update(vec![new.clone()], start + delay));
+        assert_eq!(keyring.revisions(), vec![new.named_revision().to_string()]);
+        assert!(!keyring.update(vec![], start + delay));
+    }
+
+    #[test]
+    fn revisions_the_keyring_starts_with_are_sent_with_right_away() {
+        let (old, new) = revisions();
+        let keyring = Keyring::with_activation_delay(new.clone(), Duration::from_secs(60));
+        let start = Instant::now();
+        assert!(keyring.load(vec![old.clone(), new.clone()]));
+        assert_eq!(keyring.sending_key(start), new);
+
+        // Revisions added later still wait to be activated
+        thread::sleep(Duration::from_millis(1100));
+        let newest = RingKey::new("beyonce");
+        assert!(keyring.update(vec![old, new.clone(), newest], start));
+        assert_eq!(keyring.sending_key(start), new);
+    }
+
+    #[test]
+    fn messages_decrypt_with_any_revision() {
+        let (old, new) = revisions();
+        let keyring = Keyring::with_activation_delay(old.clone(), Duration::from_secs(60));
+        keyring.update(vec![old.clone(), new.clone()], Instant::now());
+
+        let (nonce, ciphertext) = old.encrypt(b"Ringonit");
+        assert_eq!(keyring.decrypt(None, &nonce, &ciphertext).unwrap(),
+                   b"Ringonit");
+        let (nonce, ciphertext) = new.encrypt(b"Ringonit");
+        let revision = new.named_revision().to_string();
+        assert_eq!(keyring.decrypt(Some(&revision), &nonce, &ciphertext)
+                          .unwrap(),
+                   b"Ringonit");
+
+        let other = RingKey::new("jayz");
+        let (nonce, ciphertext) = other.encrypt(b"Ringonit");
+        assert!(keyring.decrypt(None, &nonce, &ciphertext).is_err());
+        let revision = other.named_revision().to_string();
+        assert!(keyring.decrypt(Some(&revision), &nonce, &ciphertext)
+                       .is_err());
+    }
+}
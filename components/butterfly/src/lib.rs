@@ -39,12 +39,15 @@ extern crate serde_derive;
 
 pub mod client;
 pub mod error;
+pub mod keyring;
 pub mod member;
 pub mod message;
 pub mod protocol;
 pub mod rumor;
 pub mod server;
+pub mod stun;
 pub mod swim;
+pub mod tls;
 
 pub use crate::server::Server;
 use std::cell::UnsafeCell;
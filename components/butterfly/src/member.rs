@@ -3,6 +3,7 @@
 pub use crate::protocol::swim::Health;
 use crate::{error::{Error,
                     Result},
+            message::Compression,
             protocol::{self,
                        newscast,
                        swim as proto,
@@ -14,7 +15,8 @@ use habitat_common::sync::{Lock,
                            ReadGuard,
                            WriteGuard};
 use habitat_core::util::ToI64;
-use prometheus::IntGaugeVec;
+use prometheus::{IntCounterVec,
+                 IntGaugeVec};
 use rand::{seq::{IteratorRandom,
                  SliceRandom},
            thread_rng};
@@ -26,9 +28,14 @@ use serde::{de,
             Serialize,
             Serializer};
 use std::{collections::{hash_map,
-                        HashMap},
+                        HashMap,
+                        HashSet,
+                        VecDeque},
           fmt,
-          net::SocketAddr,
+          net::{AddrParseError,
+                IpAddr,
+                Ipv4Addr,
+                SocketAddr},
           num::ParseIntError,
           ops::Add,
           result,
@@ -39,14 +46,30 @@ use std::{collections::{hash_map,
                  Instant}};
 use uuid::Uuid;
 
-/// How many nodes do we target when we need to run PingReq.
-const PINGREQ_TARGETS: usize = 5;
+habitat_core::env_config_int!(/// The number of times the health of a member has to change within
+                              /// `FlapDetectionWindow` for it to be considered flapping.
+                              #[derive(Debug)]
+                              FlapDetectionThreshold,
+                              usize,
+                              HAB_SWIM_FLAP_THRESHOLD,
+                              4);
+
+habitat_core::env_config_duration!(/// How far back changes in the health of a member are
+                                   /// counted towards `FlapDetectionThreshold`.
+                                   #[derive(Debug)]
+                                   FlapDetectionWindow,
+                                   HAB_SWIM_FLAP_WINDOW_SECS => from_secs,
+                                   Duration::from_secs(300));
 
 lazy_static! {
     static ref PEER_HEALTH_COUNT: IntGaugeVec =
         register_int_gauge_vec!("hab_butterfly_peer_health_total",
                                 "Number of butterfly peers",
                                 &["health"]).unwrap();
+    static ref PEER_HEALTH_TRANSITIONS: IntCounterVec =
+        register_int_counter_vec!("hab_butterfly_peer_health_transitions_total",
+                                  "Total number of times a butterfly peer entered each health",
+                                  &["health"]).unwrap();
 }
 
 /// Wraps a `u64` to represent the "incarnation number" of a
@@ -121,17 +144,72 @@ impl<'de> Deserialize<'de> for Incarnation {
 // This is a Uuid type turned to a string
 pub type UuidSimple = String;
 
+/// A feature of newer members that changes what the other members have to agree on, which is only
+/// used once every member of the ring advertises it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Leader election candidates are ranked by the leader affinity of their service
+    LeaderAffinity,
+    /// Service configurations are held back until the time they are scheduled for
+    ScheduledConfig,
+    /// Service files too large for a single rumor are reassembled from chunks
+    ChunkedServiceFiles,
+}
+
+impl Capability {
+    /// The capabilities of this member
+    pub const ALL: &'static [Capability] = &[Capability::LeaderAffinity,
+                                             Capability::ScheduledConfig,
+                                             Capability::ChunkedServiceFiles];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::LeaderAffinity => "leader_affinity",
+            Capability::ScheduledConfig => "scheduled_config",
+            Capability::ChunkedServiceFiles => "chunked_service_files",
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.name()) }
+}
+
+impl FromStr for Capability {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Capability::ALL.iter()
+                       .copied()
+                       .find(|capability| capability.name() == s)
+                       .ok_or(Error::ProtocolMismatch("capabilities"))
+    }
+}
+
 /// A member in the swim group. Passes most of its functionality along to the internal protobuf
 /// representation.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Member {
-    pub id:          String,
-    pub incarnation: Incarnation,
-    pub address:     String,
-    pub swim_port:   u16,
-    pub gossip_port: u16,
-    pub persistent:  bool,
-    pub departed:    bool,
+    pub id:           String,
+    pub incarnation:  Incarnation,
+    pub address:      String,
+    pub swim_port:    u16,
+    pub gossip_port:  u16,
+    pub persistent:   bool,
+    pub departed:     bool,
+    /// The failure domain (e.g. a datacenter) the member runs in, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone:         Option<String>,
+    /// The codecs the member accepts compressed payloads in, in order of preference
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compression:  Vec<Compression>,
+    /// The capabilities the member advertises
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<Capability>,
+    /// The version of the Supervisor the member runs, if it reports one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version:      Option<String>,
 }
 
 impl Member {
@@ -142,30 +220,113 @@ impl Member {
     /// This function panics if the address is un-parseable. In practice, it shouldn't be
     /// un-parseable, since its set from the inbound socket directly.
     pub fn swim_socket_address(&self) -> SocketAddr {
-        let address_str = format!("{}:{}", self.address, self.swim_port);
-        match address_str.parse() {
+        match socket_address(&self.address, self.swim_port) {
             Ok(addr) => addr,
             Err(e) => {
                 panic!("Cannot parse member {:?} address: {}", self, e);
             }
         }
     }
+
+    /// Record the address a message from this member was received from as its address.
+    ///
+    /// Members only send an address of their own when they advertise one, e.g. because they are
+    /// behind NAT and the address their messages appear to come from is not reachable. Those
+    /// members keep their advertised address.
+    pub fn set_observed_address(&mut self, addr: SocketAddr) {
+        if self.address.is_empty() {
+            self.address = unmap_ipv4(addr).ip().to_string();
+        }
+    }
+
+    /// Whether this member runs in `zone`. Members without a zone, and members compared to no
+    /// zone, are taken to be in every zone, so a ring that does not use zones is not zone-aware.
+    pub fn in_zone(&self, zone: Option<&str>) -> bool {
+        match (self.zone.as_deref(), zone) {
+            (Some(own_zone), Some(zone)) => own_zone == zone,
+            _ => true,
+        }
+    }
+}
+
+/// Keep the members of `members` that run in `zone`, and at most `cross_zone` of the others, in
+/// order. This keeps the gossip sent to other zones to a few members per round, which gossip it on
+/// within their zone in turn.
+pub fn limit_cross_zone(members: Vec<Member>,
+                        zone: Option<&str>,
+                        cross_zone: usize)
+                        -> Vec<Member> {
+    let mut remaining = cross_zone;
+    members.into_iter()
+           .filter(|member| {
+               if member.in_zone(zone) {
+                   true
+               } else if remaining > 0 {
+                   remaining -= 1;
+                   true
+               } else {
+                   false
+               }
+           })
+           .collect()
+}
+
+/// Combine a member's address and port into a socket address. IPv6 addresses may optionally be
+/// enclosed in square brackets.
+pub fn socket_address(address: &str, port: u16) -> result::Result<SocketAddr, AddrParseError> {
+    let address = address.strip_prefix('[')
+                         .and_then(|a| a.strip_suffix(']'))
+                         .unwrap_or(address);
+    address.parse::<IpAddr>()
+           .map(|ip| SocketAddr::new(ip, port))
+}
+
+/// The canonical form of a member's address, so that every member of a ring records the same
+/// address for a member however it learned of it: IPv6 addresses without square brackets and in
+/// their shortest form, and IPv4-mapped IPv6 addresses as plain IPv4. Anything that is not an IP
+/// address, including an empty address, is left as it is.
+pub fn canonical_address(address: &str) -> String {
+    match socket_address(address, 0) {
+        Ok(addr) => unmap_ipv4(addr).ip().to_string(),
+        Err(_) => address.to_string(),
+    }
+}
+
+/// Dual-stack sockets report IPv4 peers as IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`).
+/// Convert those back to plain IPv4 so a member has the same address however we listen.
+pub fn unmap_ipv4(addr: SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V6(v6) => {
+            match v6.ip().segments() {
+                [0, 0, 0, 0, 0, 0xffff, high, low] => {
+                    let ip = Ipv4Addr::from(u32::from(high) << 16 | u32::from(low));
+                    SocketAddr::new(IpAddr::V4(ip), v6.port())
+                }
+                _ => addr,
+            }
+        }
+        SocketAddr::V4(_) => addr,
+    }
 }
 
 impl Default for Member {
     fn default() -> Self {
-        Member { id:          Uuid::new_v4().to_simple_ref().to_string(),
-                 incarnation: Incarnation::default(),
+        Member { id:           Uuid::new_v4().to_simple_ref().to_string(),
+                 incarnation:  Incarnation::default(),
                  // TODO (CM): DANGER DANGER DANGER
                  // This is a lousy default, and suggests that the notion
                  // of a "default Member" doesn't make much sense.
                  //
                  // (Port numbers of 0 are also problematic.)
-                 address:     String::default(),
-                 swim_port:   0,
-                 gossip_port: 0,
-                 persistent:  false,
-                 departed:    false, }
+                 address:      String::default(),
+                 swim_port:    0,
+                 gossip_port:  0,
+                 persistent:   false,
+                 departed:     false,
+                 zone:         None,
+                 compression:  Vec::new(),
+                 capabilities: Vec::new(),
+                 version:      None, }
     }
 }
 
@@ -183,13 +344,23 @@ impl<'a> From<&'a &'a Member> for RumorKey {
 
 impl From<Member> for proto::Member {
     fn from(value: Member) -> Self {
-        proto::Member { id:          Some(value.id),
-                        incarnation: Some(value.incarnation.to_u64()),
-                        address:     Some(value.address),
-                        swim_port:   Some(value.swim_port.into()),
-                        gossip_port: Some(value.gossip_port.into()),
-                        persistent:  Some(value.persistent),
-                        departed:    Some(value.departed), }
+        proto::Member { id:           Some(value.id),
+                        incarnation:  Some(value.incarnation.to_u64()),
+                        address:      Some(canonical_address(&value.address)),
+                        swim_port:    Some(value.swim_port.into()),
+                        gossip_port:  Some(value.gossip_port.into()),
+                        persistent:   Some(value.persistent),
+                        departed:     Some(value.departed),
+                        zone:         value.zone,
+                        compression:  value.compression
+                                           .iter()
+                                           .map(|codec| codec.name().to_string())
+                                           .collect(),
+                        capabilities: value.capabilities
+                                           .iter()
+                                           .map(|capability| capability.name().to_string())
+                                           .collect(),
+                        version:      value.version, }
     }
 }
 
@@ -291,16 +462,28 @@ impl FromProto<proto::Member> for Member {
                     // a value of `None`. We ultimately need to either _not_
                     // generate meaningless default values, or tease apart the
                     // two uses of our Member protobuf, or both.
-                    address: proto.address.unwrap_or_default(),
-
-                    swim_port:   proto.swim_port
-                                      .and_then(as_port)
-                                      .ok_or(Error::ProtocolMismatch("swim-port"))?,
-                    gossip_port: proto.gossip_port
-                                      .and_then(as_port)
-                                      .ok_or(Error::ProtocolMismatch("gossip-port"))?,
-                    persistent:  proto.persistent.unwrap_or(false),
-                    departed:    proto.departed.unwrap_or(false), })
+                    address: canonical_address(&proto.address.unwrap_or_default()),
+
+                    swim_port:    proto.swim_port
+                                       .and_then(as_port)
+                                       .ok_or(Error::ProtocolMismatch("swim-port"))?,
+                    gossip_port:  proto.gossip_port
+                                       .and_then(as_port)
+                                       .ok_or(Error::ProtocolMismatch("gossip-port"))?,
+                    persistent:   proto.persistent.unwrap_or(false),
+                    departed:     proto.departed.unwrap_or(false),
+                    zone:         proto.zone,
+                    // Codecs of newer members that this one doesn't know are left out
+                    compression:  proto.compression
+                                       .iter()
+                                       .filter_map(|codec| codec.parse().ok())
+                                       .collect(),
+                    // As are their capabilities
+                    capabilities: proto.capabilities
+                                       .iter()
+                                       .filter_map(|capability| capability.parse().ok())
+                                       .collect(),
+                    version:      proto.version, })
     }
 }
 
@@ -325,11 +508,47 @@ impl FromProto<newscast::Rumor> for Membership {
 }
 
 mod member_list {
+    use super::{FlapDetectionThreshold,
+                FlapDetectionWindow};
+    use habitat_core::env::Config;
+    use std::{collections::VecDeque,
+              time::{Duration,
+                     Instant}};
+
     #[derive(Clone, Debug)]
     pub struct Entry {
-        pub member:            super::Member,
-        pub health:            super::Health,
-        pub health_updated_at: std::time::Instant,
+        pub member:             super::Member,
+        pub health:             super::Health,
+        pub health_updated_at:  Instant,
+        /// When the health of the member last changed, oldest first
+        pub health_transitions: VecDeque<Instant>,
+    }
+
+    impl Entry {
+        /// Record that the health of the member changed at `now`, forgetting changes too old
+        /// to count towards flapping.
+        pub fn record_health_transition(&mut self, now: Instant) {
+            let window: Duration = FlapDetectionWindow::configured_value().into();
+            let threshold = FlapDetectionThreshold::configured_value().0;
+            self.health_transitions.push_back(now);
+            while self.health_transitions.len() > threshold
+                  || self.health_transitions
+                         .front()
+                         .map_or(false, |t| now.duration_since(*t) > window)
+            {
+                self.health_transitions.pop_front();
+            }
+        }
+
+        /// Has the health of the member changed often enough recently to consider it flapping?
+        pub fn is_flapping(&self, now: Instant) -> bool {
+            let window: Duration = FlapDetectionWindow::configured_value().into();
+            let recent = self.health_transitions
+                             .iter()
+                             .filter(|t| now.duration_since(**t) <= window)
+                             .count();
+            recent >= FlapDetectionThreshold::configured_value().0
+        }
     }
 }
 
@@ -506,18 +725,25 @@ impl MemberList {
             hash_map::Entry::Occupied(mut entry) => {
                 let val = entry.get_mut();
                 if incoming.newer_or_less_healthy_than(val.member.incarnation, val.health) {
-                    *val = member_list::Entry { member:            incoming.member,
-                                                health:            incoming.health,
-                                                health_updated_at: Instant::now(), };
+                    let now = Instant::now();
+                    if incoming.health != val.health {
+                        val.record_health_transition(now);
+                        PEER_HEALTH_TRANSITIONS.with_label_values(&[&incoming.health.to_string()])
+                                               .inc();
+                    }
+                    val.member = incoming.member;
+                    val.health = incoming.health;
+                    val.health_updated_at = now;
                     true
                 } else {
                     false
                 }
             }
             hash_map::Entry::Vacant(entry) => {
-                entry.insert(member_list::Entry { member:            incoming.member,
-                                                  health:            incoming.health,
-                                                  health_updated_at: Instant::now(), });
+                entry.insert(member_list::Entry { member:             incoming.member,
+                                                  health:             incoming.health,
+                                                  health_updated_at:  Instant::now(),
+                                                  health_transitions: VecDeque::new(), });
                 true
             }
         };
@@ -583,6 +809,17 @@ impl MemberList {
             .map(|member_list::Entry { health, .. }| *health)
     }
 
+    /// Whether every member that hasn't departed advertises `capability`.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn all_advertise_mlr(&self, capability: Capability) -> bool {
+        self.read_entries()
+            .values()
+            .filter(|entry| entry.health != Health::Departed)
+            .all(|entry| entry.member.capabilities.contains(&capability))
+    }
+
     /// Returns the health of the member, blocking for a limited timeout
     ///
     /// # Locking (see locking.md)
@@ -630,6 +867,19 @@ impl MemberList {
         member.persistent && self.health_of_mlr(member) == Some(Health::Confirmed)
     }
 
+    /// Returns true if a member listening for SWIM at `addr` is alive or suspect.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn alive_at_mlr(&self, addr: SocketAddr) -> bool {
+        self.read_entries().values().any(|entry| {
+                                        matches!(entry.health, Health::Alive | Health::Suspect)
+                                        && socket_address(&entry.member.address,
+                                                          entry.member.swim_port).ok()
+                                           == Some(addr)
+                                    })
+    }
+
     /// Returns a protobuf membership record for the given member id.
     ///
     /// # Locking (see locking.md)
@@ -668,7 +918,12 @@ impl MemberList {
         members
     }
 
-    /// Takes a function whose first argument is a member, and calls it for every pingreq target.
+    /// Takes a function whose first argument is a member, and calls it for up to `fanout` pingreq
+    /// targets.
+    ///
+    /// Members in the target's zone are preferred, since they reach the target without crossing
+    /// the link between zones, so a slow or lossy link between this member's zone and the target's
+    /// does not get the target suspected.
     ///
     /// # Locking (see locking.md)
     /// * `MemberList::entries` (read)
@@ -677,17 +932,27 @@ impl MemberList {
     pub fn with_pingreq_targets_mlr(&self,
                                     sending_member_id: &str,
                                     target_member_id: &str,
+                                    fanout: usize,
                                     mut with_closure: impl FnMut(&Member)) {
-        for member_list::Entry { member, .. } in
-            self.read_entries()
-                .values()
-                .filter(|member_list::Entry { member, health, .. }| {
-                    member.id != sending_member_id
-                    && member.id != target_member_id
-                    && *health == Health::Alive
-                })
-                .choose_multiple(&mut thread_rng(), PINGREQ_TARGETS)
-        {
+        let entries = self.read_entries();
+        let target_zone =
+            entries.get(target_member_id)
+                   .and_then(|member_list::Entry { member, .. }| member.zone.as_deref());
+        let (mut targets, elsewhere): (Vec<_>, Vec<_>) =
+            entries.values()
+                   .filter(|member_list::Entry { member, health, .. }| {
+                       member.id != sending_member_id
+                       && member.id != target_member_id
+                       && *health == Health::Alive
+                   })
+                   .map(|member_list::Entry { member, .. }| member)
+                   .partition(|member| member.in_zone(target_zone));
+        let mut rng = thread_rng();
+        targets.shuffle(&mut rng);
+        targets.truncate(fanout);
+        let remaining = fanout - targets.len();
+        targets.extend(elsewhere.into_iter().choose_multiple(&mut rng, remaining));
+        for member in targets {
             with_closure(member);
         }
     }
@@ -774,12 +1039,10 @@ impl MemberList {
             self.write_entries()
                 .iter_mut()
                 .filter_map(|(id, v)| {
-                    let member_list::Entry { health,
-                                             health_updated_at,
-                                             .. } = v;
-                    if *health == precursor_health && now >= *health_updated_at + timeout {
-                        *health = expiring_to;
-                        *health_updated_at = now;
+                    if v.health == precursor_health && now >= v.health_updated_at + timeout {
+                        v.health = expiring_to;
+                        v.health_updated_at = now;
+                        v.record_health_transition(now);
                         Some(id.clone())
                     } else {
                         None
@@ -789,11 +1052,47 @@ impl MemberList {
 
         if !expired.is_empty() {
             self.increment_update_counter();
+            PEER_HEALTH_TRANSITIONS.with_label_values(&[&expiring_to.to_string()])
+                                   .inc_by(expired.len() as u64);
         }
 
         expired
     }
 
+    /// The IDs of the members that have departed and, with a `ttl`, of those that have been
+    /// `Confirmed` for at least `ttl`.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn members_gone_mlr(&self, ttl: Option<Duration>) -> HashSet<String> {
+        let now = Instant::now();
+        self.read_entries()
+            .iter()
+            .filter(|(_, entry)| {
+                match (entry.health, ttl) {
+                    (Health::Departed, _) => true,
+                    (Health::Confirmed, Some(ttl)) => now >= entry.health_updated_at + ttl,
+                    _ => false,
+                }
+            })
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// The IDs of the members whose health changed at least `HAB_SWIM_FLAP_THRESHOLD` times
+    /// within the last `HAB_SWIM_FLAP_WINDOW_SECS` seconds.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn flapping_member_ids_mlr(&self) -> HashSet<String> {
+        let now = Instant::now();
+        self.read_entries()
+            .iter()
+            .filter(|(_, entry)| entry.is_flapping(now))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
     /// # Locking (see locking.md)
     /// * `MemberList::entries` (read)
     pub fn contains_member_mlr(&self, member_id: &str) -> bool {
@@ -838,13 +1137,61 @@ impl<'a> Serialize for MemberProxy<'a> {
     fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
         where S: Serializer
     {
-        let mut strukt = serializer.serialize_struct("member", 6)?;
+        let mut strukt = serializer.serialize_struct("member", 7)?;
         strukt.serialize_field("address", &self.0.address)?;
         strukt.serialize_field("gossip_port", &self.0.gossip_port)?;
         strukt.serialize_field("incarnation", &self.0.incarnation)?;
         strukt.serialize_field("persistent", &self.0.persistent)?;
         strukt.serialize_field("swim_port", &self.0.swim_port)?;
         strukt.serialize_field("health", &self.1)?;
+        if let Some(zone) = &self.0.zone {
+            strukt.serialize_field("zone", zone)?;
+        }
+        strukt.end()
+    }
+}
+
+/// This proxy wraps a MemberList for the Supervisor's /butterfly/debug HTTP API endpoint. Along
+/// with each member, it presents how long the member has had its current health, and how often
+/// that health changed recently.
+pub struct MemberListDebugProxy<'a>(&'a MemberList);
+
+impl<'a> MemberListDebugProxy<'a> {
+    pub fn new(m: &'a MemberList) -> Self { MemberListDebugProxy(m) }
+}
+
+impl<'a> Serialize for MemberListDebugProxy<'a> {
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let map = self.0.read_entries();
+
+        let mut strukt = serializer.serialize_struct("member_list", 2)?;
+        let members: HashMap<_, _> = map.iter()
+                                        .map(|(id, entry)| (id, MemberDebugProxy(entry)))
+                                        .collect();
+        strukt.serialize_field("members", &members)?;
+        strukt.serialize_field("update_counter", &self.0.get_update_counter())?;
+        strukt.end()
+    }
+}
+
+struct MemberDebugProxy<'a>(&'a member_list::Entry);
+
+impl<'a> Serialize for MemberDebugProxy<'a> {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let member_list::Entry { member,
+                                 health,
+                                 health_updated_at,
+                                 health_transitions, } = self.0;
+        let mut strukt = serializer.serialize_struct("member", 3)?;
+        strukt.serialize_field("member", &MemberProxy::new(member, health))?;
+        strukt.serialize_field("health_age_secs", &health_updated_at.elapsed().as_secs())?;
+        strukt.serialize_field("recent_health_changes", &health_transitions.len())?;
         strukt.end()
     }
 }
@@ -872,8 +1219,20 @@ mod tests {
     }
 
     mod member {
-        use crate::member::{Incarnation,
-                            Member};
+        use crate::{member::{canonical_address,
+                             limit_cross_zone,
+                             unmap_ipv4,
+                             Capability,
+                             Incarnation,
+                             Member},
+                    message::Compression,
+                    protocol::{swim as proto,
+                               FromProto}};
+
+        fn member_in(zone: Option<&str>) -> Member {
+            Member { zone: zone.map(str::to_string),
+                     ..Default::default() }
+        }
 
         // Sets the uuid to simple, and the incarnation to the default.
         #[test]
@@ -882,6 +1241,98 @@ mod tests {
             assert_eq!(member.id.len(), 32);
             assert_eq!(member.incarnation, Incarnation::default());
         }
+
+        #[test]
+        fn swim_socket_address_supports_ipv6() {
+            let member = Member { address: String::from("2001:db8::1"),
+                                  swim_port: 9638,
+                                  ..Default::default() };
+            assert_eq!(member.swim_socket_address(),
+                       "[2001:db8::1]:9638".parse().unwrap());
+
+            let member = Member { address: String::from("[::1]"),
+                                  swim_port: 9638,
+                                  ..Default::default() };
+            assert_eq!(member.swim_socket_address(), "[::1]:9638".parse().unwrap());
+        }
+
+        #[test]
+        fn addresses_are_canonical_on_the_wire() {
+            assert_eq!(canonical_address("[2001:DB8:0::1]"), "2001:db8::1");
+            assert_eq!(canonical_address("::ffff:10.0.0.1"), "10.0.0.1");
+            assert_eq!(canonical_address("10.0.0.1"), "10.0.0.1");
+            assert_eq!(canonical_address(""), "");
+
+            let member = Member { address: String::from("[2001:db8:0:0::1]"),
+                                  swim_port: 9638,
+                                  gossip_port: 9638,
+                                  ..Default::default() };
+            let proto = proto::Member::from(member);
+            assert_eq!(proto.address.as_deref(), Some("2001:db8::1"));
+
+            let proto = proto::Member { address: Some(String::from("[::FFFF:10.0.0.1]")),
+                                        ..proto };
+            assert_eq!(Member::from_proto(proto).unwrap().address, "10.0.0.1");
+        }
+
+        #[test]
+        fn accepted_codecs_survive_the_wire() {
+            let member = Member { compression: vec![Compression::Lz4, Compression::Zstd],
+                                  ..Default::default() };
+            let proto = proto::Member::from(member.clone());
+            assert_eq!(proto.compression, vec!["lz4", "zstd"]);
+            assert_eq!(Member::from_proto(proto).unwrap(), member);
+
+            let proto = proto::Member { compression: vec![String::from("brotli"),
+                                                          String::from("zstd")],
+                                        ..proto::Member::from(member) };
+            assert_eq!(Member::from_proto(proto).unwrap().compression,
+                       vec![Compression::Zstd]);
+        }
+
+        #[test]
+        fn advertised_capabilities_survive_the_wire() {
+            let member = Member { capabilities: vec![Capability::LeaderAffinity],
+                                  ..Default::default() };
+            let proto = proto::Member::from(member.clone());
+            assert_eq!(proto.capabilities, vec!["leader_affinity"]);
+            assert_eq!(Member::from_proto(proto).unwrap(), member);
+
+            let proto = proto::Member { capabilities: vec![String::from("time_travel")],
+                                        ..proto::Member::from(member) };
+            assert!(Member::from_proto(proto).unwrap().capabilities.is_empty());
+        }
+
+        #[test]
+        fn unmap_ipv4_only_changes_ipv4_mapped_addresses() {
+            assert_eq!(unmap_ipv4("[::ffff:10.0.0.1]:9638".parse().unwrap()),
+                       "10.0.0.1:9638".parse().unwrap());
+            assert_eq!(unmap_ipv4("[::1]:9638".parse().unwrap()),
+                       "[::1]:9638".parse().unwrap());
+            assert_eq!(unmap_ipv4("10.0.0.1:9638".parse().unwrap()),
+                       "10.0.0.1:9638".parse().unwrap());
+        }
+
+        #[test]
+        fn members_without_a_zone_are_in_every_zone() {
+            assert!(member_in(Some("east")).in_zone(Some("east")));
+            assert!(!member_in(Some("east")).in_zone(Some("west")));
+            assert!(member_in(None).in_zone(Some("west")));
+            assert!(member_in(Some("east")).in_zone(None));
+        }
+
+        #[test]
+        fn limit_cross_zone_keeps_the_zone_and_a_few_others() {
+            let members = vec![member_in(Some("west")),
+                               member_in(Some("east")),
+                               member_in(Some("west")),
+                               member_in(None),
+                               member_in(Some("south"))];
+            let kept = limit_cross_zone(members.clone(), Some("east"), 1);
+            assert_eq!(kept,
+                       vec![members[0].clone(), members[1].clone(), members[3].clone()]);
+            assert_eq!(limit_cross_zone(members.clone(), None, 0), members);
+        }
     }
 
     mod membership {
@@ -906,11 +1357,13 @@ mod tests {
     }
 
     mod member_list {
-        use crate::member::{Health,
+        use crate::member::{Capability,
+                            Health,
                             Member,
                             MemberList,
-                            Membership,
-                            PINGREQ_TARGETS};
+                            Membership};
+
+        const PINGREQ_TARGETS: usize = 5;
 
         fn populated_member_list(size: u64) -> MemberList {
             let ml = MemberList::new();
@@ -933,6 +1386,28 @@ mod tests {
             assert_eq!(ml.len_mlr(), 4);
         }
 
+        #[test]
+        fn members_whose_health_keeps_changing_are_flapping() {
+            let ml = MemberList::new();
+            let mut member = Member::default();
+            ml.insert_mlw(member.clone(), Health::Alive);
+            for _ in 0..2 {
+                assert!(ml.flapping_member_ids_mlr().is_empty());
+                ml.insert_mlw(member.clone(), Health::Suspect);
+                member.incarnation = member.incarnation + 1;
+                ml.insert_mlw(member.clone(), Health::Alive);
+            }
+            // Alive -> Suspect -> Alive -> Suspect -> Alive
+            assert!(ml.flapping_member_ids_mlr().contains(&member.id));
+
+            // Hearing about the same health again is not a transition
+            let other = Member::default();
+            for _ in 0..5 {
+                ml.insert_mlw(other.clone(), Health::Alive);
+            }
+            assert!(!ml.flapping_member_ids_mlr().contains(&other.id));
+        }
+
         #[test]
         fn check_list() {
             let ml = populated_member_list(1000);
@@ -968,7 +1443,9 @@ mod tests {
                   let from = i.next().unwrap();
                   let target = i.nth(1).unwrap();
                   let mut counter: usize = 0;
-                  ml.with_pingreq_targets_mlr(&from.id, &target.id, |_m| counter += 1);
+                  ml.with_pingreq_targets_mlr(&from.id, &target.id, PINGREQ_TARGETS, |_m| {
+                        counter += 1
+                    });
                   assert_eq!(counter, PINGREQ_TARGETS);
               });
         }
@@ -980,7 +1457,7 @@ mod tests {
                   let from = i.next().unwrap();
                   let target = i.nth(1).unwrap();
                   let mut excluded_appears: bool = false;
-                  ml.with_pingreq_targets_mlr(&from.id, &target.id, |m| {
+                  ml.with_pingreq_targets_mlr(&from.id, &target.id, PINGREQ_TARGETS, |m| {
                         if m.id == from.id {
                             excluded_appears = true
                         }
@@ -996,7 +1473,7 @@ mod tests {
                   let from = i.next().unwrap();
                   let target = i.nth(1).unwrap();
                   let mut excluded_appears: bool = false;
-                  ml.with_pingreq_targets_mlr(&from.id, &target.id, |m| {
+                  ml.with_pingreq_targets_mlr(&from.id, &target.id, PINGREQ_TARGETS, |m| {
                         if m.id == target.id {
                             excluded_appears = true
                         }
@@ -1005,6 +1482,28 @@ mod tests {
               });
         }
 
+        #[test]
+        fn pingreq_targets_prefer_the_target_zone() {
+            let ml = MemberList::new();
+            let in_zone = |zone: &str| {
+                Member { zone: Some(zone.to_string()),
+                         ..Default::default() }
+            };
+            let from = in_zone("east");
+            let target = in_zone("west");
+            ml.insert_mlw(from.clone(), Health::Alive);
+            ml.insert_mlw(target.clone(), Health::Alive);
+            for _ in 0..PINGREQ_TARGETS {
+                ml.insert_mlw(in_zone("east"), Health::Alive);
+                ml.insert_mlw(in_zone("west"), Health::Alive);
+            }
+            let mut zones = Vec::new();
+            ml.with_pingreq_targets_mlr(&from.id, &target.id, PINGREQ_TARGETS, |m| {
+                  zones.push(m.zone.clone())
+              });
+            assert_eq!(zones, vec![Some(String::from("west")); PINGREQ_TARGETS]);
+        }
+
         #[test]
         fn pingreq_targets_minimum_viable_pingreq_size_is_three() {
             let ml = populated_member_list(3);
@@ -1012,7 +1511,9 @@ mod tests {
                   let from = i.next().unwrap();
                   let target = i.nth(1).unwrap();
                   let mut counter: isize = 0;
-                  ml.with_pingreq_targets_mlr(&from.id, &target.id, |_m| counter += 1);
+                  ml.with_pingreq_targets_mlr(&from.id, &target.id, PINGREQ_TARGETS, |_m| {
+                        counter += 1
+                    });
                   assert_eq!(counter, 1);
               });
         }
@@ -1026,6 +1527,39 @@ mod tests {
             assert_eq!(ml.health_of_mlr(&mcheck), Some(Health::Alive));
         }
 
+        #[test]
+        fn capabilities_are_used_once_every_remaining_member_advertises_them() {
+            let ml = MemberList::new();
+            let capable = Member { capabilities: vec![Capability::LeaderAffinity],
+                                   ..Default::default() };
+            let old = Member::default();
+            assert!(ml.all_advertise_mlr(Capability::LeaderAffinity));
+
+            ml.insert_mlw(capable, Health::Alive);
+            ml.insert_mlw(old.clone(), Health::Confirmed);
+            assert!(!ml.all_advertise_mlr(Capability::LeaderAffinity));
+
+            ml.insert_mlw(old, Health::Departed);
+            assert!(ml.all_advertise_mlr(Capability::LeaderAffinity));
+        }
+
+        #[test]
+        fn only_alive_and_suspect_members_are_alive_at_their_address() {
+            let ml = MemberList::new();
+            let member = Member { address: String::from("10.0.0.5"),
+                                  swim_port: 9638,
+                                  ..Default::default() };
+            let addr = member.swim_socket_address();
+            assert!(!ml.alive_at_mlr(addr));
+
+            ml.insert_mlw(member.clone(), Health::Suspect);
+            assert!(ml.alive_at_mlr(addr));
+            assert!(!ml.alive_at_mlr("10.0.0.6:9638".parse().unwrap()));
+
+            ml.insert_mlw(member, Health::Confirmed);
+            assert!(!ml.alive_at_mlr(addr));
+        }
+
         /// Tests of MemberList::insert
         mod insert {
             use crate::member::{Health,
@@ -1485,6 +2019,32 @@ mod tests {
                            "Member should have a health of Departed after timing out");
             }
 
+            #[test]
+            fn members_are_gone_once_confirmed_for_the_ttl() {
+                let ml = MemberList::new();
+                let member_one = Member::default();
+                let ttl = Duration::from_secs(1);
+
+                assert!(ml.insert_mlw(member_one.clone(), Health::Alive));
+                assert!(ml.members_gone_mlr(Some(Duration::from_secs(0))).is_empty(),
+                        "Alive members are never gone");
+
+                assert!(ml.insert_mlw(member_one.clone(), Health::Confirmed));
+                assert!(ml.members_gone_mlr(Some(ttl)).is_empty(),
+                        "Members aren't gone until they've been Confirmed for the TTL");
+
+                thread::sleep(ttl);
+
+                assert!(ml.members_gone_mlr(Some(ttl)).contains(&member_one.id),
+                        "Members are gone once they've been Confirmed for the TTL");
+                assert!(ml.members_gone_mlr(None).is_empty(),
+                        "Confirmed members are never gone without a TTL");
+
+                assert!(ml.insert_mlw(member_one.clone(), Health::Departed));
+                assert!(ml.members_gone_mlr(None).contains(&member_one.id),
+                        "Departed members are gone right away");
+            }
+
             #[test]
             fn suspect_timeout_is_appropriately_selective() {
                 let ml = MemberList::new();
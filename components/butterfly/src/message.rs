@@ -1,18 +1,167 @@
 use bytes::BytesMut;
-use habitat_core::crypto::keys::RingKey;
+use habitat_core::crypto::keys::{Key,
+                                 RingKey};
 use prost::Message;
+use std::{convert::TryFrom,
+          fmt,
+          io::Read,
+          str::FromStr};
 
+pub use crate::protocol::Compression;
 use crate::{error::{Error,
                     Result},
+            keyring::Keyring,
+            member::Member,
             protocol::Wire};
 
-pub fn generate_wire(payload: Vec<u8>, ring_key: Option<&RingKey>) -> Result<Vec<u8>> {
+/// The most a compressed payload may decompress to. Anything claiming to be larger is rejected
+/// rather than allocated for.
+const MAX_UNCOMPRESSED_LEN: u64 = 64 * 1024 * 1024;
+
+/// The most a byte of LZ4 can decompress to, as a length is extended by at most 255 per byte
+const LZ4_MAX_RATIO: usize = 255;
+
+habitat_core::env_config!(
+    /// The codecs gossip payloads are compressed with, in order of preference, e.g. `zstd,lz4`.
+    /// A member advertises the codecs of its own setting, and is only sent payloads compressed
+    /// with one of them, so none, the default, turns compression off both ways. Payloads are
+    /// decompressed whatever the codec, as long as it is a known one.
+    #[derive(Clone, Debug)]
+    pub GossipCompression,
+    Vec<Compression>,
+    HAB_GOSSIP_COMPRESSION,
+    Vec::new(),
+    Error,
+    s,
+    s.split(',')
+     .map(str::trim)
+     .filter(|codec| !codec.is_empty())
+     .map(str::parse)
+     .collect::<Result<_>>()
+     .map(Self));
+
+habitat_core::env_config_int!(/// Payloads smaller than this are sent uncompressed, as
+                              /// compressing them saves too little to be worth it.
+                              #[derive(Debug)]
+                              CompressionThresholdBytes,
+                              usize,
+                              HAB_GOSSIP_COMPRESSION_THRESHOLD_BYTES,
+                              1024);
+
+lazy_static! {
+    static ref COMPRESSION: Vec<Compression> = GossipCompression::configured_value().into();
+    static ref COMPRESSION_THRESHOLD: usize = CompressionThresholdBytes::configured_value().into();
+}
+
+impl Compression {
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Zstd => "zstd",
+            Compression::Lz4 => "lz4",
+        }
+    }
+
+    fn compress(self, payload: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::Zstd => {
+                zstd::bulk::compress(payload, 0).map_err(|e| Error::Compression(e.to_string()))
+            }
+            Compression::Lz4 => Ok(lz4_flex::compress(payload)),
+        }
+    }
+
+    fn decompress(self, payload: &[u8], len: usize) -> Result<Vec<u8>> {
+        // The length is the sender's claim, so nothing is allocated for it up front that the
+        // payload could not really decompress to
+        let decompressed = match self {
+            Compression::Zstd => {
+                let mut decompressed = Vec::new();
+                zstd::stream::read::Decoder::with_buffer(payload)
+                    .and_then(|decoder| {
+                        decoder.take(len as u64 + 1).read_to_end(&mut decompressed)
+                    })
+                    .map(|_| decompressed)
+                    .map_err(|e| e.to_string())
+            }
+            Compression::Lz4 if len > payload.len().saturating_mul(LZ4_MAX_RATIO) => {
+                Err(format!("{} bytes cannot decompress to {}",
+                            payload.len(),
+                            len))
+            }
+            Compression::Lz4 => lz4_flex::decompress(payload, len).map_err(|e| e.to_string()),
+        };
+        let decompressed = decompressed.map_err(Error::Compression)?;
+        if decompressed.len() != len {
+            return Err(Error::Compression(format!("{} payload decompressed to \
+                                                   {} bytes rather than {}",
+                                                  self,
+                                                  decompressed.len(),
+                                                  len)));
+        }
+        Ok(decompressed)
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.name()) }
+}
+
+impl FromStr for Compression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "zstd" => Ok(Compression::Zstd),
+            "lz4" => Ok(Compression::Lz4),
+            _ => Err(Error::Compression(format!("unknown codec '{}'", s))),
+        }
+    }
+}
+
+/// The codecs this member accepts compressed payloads in, to advertise to the other members
+pub fn accepted_compression() -> Vec<Compression> { COMPRESSION.clone() }
+
+/// The codec to compress the payloads sent to `member` with, negotiated with the codecs it
+/// accepts. Members that accept none, including those that predate compression, are sent
+/// uncompressed payloads.
+pub fn compression_for(member: &Member) -> Option<Compression> {
+    negotiate(&COMPRESSION, &member.compression)
+}
+
+/// The first of `ours` that is one of `theirs`
+fn negotiate(ours: &[Compression], theirs: &[Compression]) -> Option<Compression> {
+    ours.iter().copied().find(|codec| theirs.contains(codec))
+}
+
+pub fn generate_wire(payload: Vec<u8>,
+                     ring_key: Option<&RingKey>,
+                     compression: Option<Compression>)
+                     -> Result<Vec<u8>> {
+    generate_wire_above(payload, ring_key, compression, *COMPRESSION_THRESHOLD)
+}
+
+fn generate_wire_above(payload: Vec<u8>,
+                       ring_key: Option<&RingKey>,
+                       compression: Option<Compression>,
+                       threshold: usize)
+                       -> Result<Vec<u8>> {
     let mut wire = Wire::default();
+    let mut payload = payload;
+    if let Some(codec) = compression.filter(|_| payload.len() >= threshold) {
+        let compressed = codec.compress(&payload)?;
+        // Payloads that don't compress are better sent as they are
+        if compressed.len() < payload.len() {
+            wire.set_compression(codec);
+            wire.uncompressed_len = Some(payload.len() as u64);
+            payload = compressed;
+        }
+    }
     if let Some(ring_key) = ring_key {
         wire.encrypted = Some(true);
         let (nonce, encrypted_payload) = ring_key.encrypt(&payload);
         wire.nonce = Some(nonce);
         wire.payload = Some(encrypted_payload);
+        wire.key_revision = Some(ring_key.named_revision().to_string());
     } else {
         wire.payload = Some(payload);
     }
@@ -21,14 +170,88 @@ pub fn generate_wire(payload: Vec<u8>, ring_key: Option<&RingKey>) -> Result<Vec
     Ok(buf.to_vec())
 }
 
-pub fn unwrap_wire(payload: &[u8], ring_key: Option<&RingKey>) -> Result<Vec<u8>> {
+pub fn unwrap_wire(payload: &[u8], keyring: Option<&Keyring>) -> Result<Vec<u8>> {
     let wire = Wire::decode(payload)?;
     let payload = wire.payload
                       .ok_or(Error::ProtocolMismatch("missing payload"))?;
-    if let Some(ring_key) = ring_key {
+    let payload = if let Some(keyring) = keyring {
         let nonce = wire.nonce.ok_or(Error::ProtocolMismatch("missing nonce"))?;
-        Ok(ring_key.decrypt(&nonce, &payload)?)
+        keyring.decrypt(wire.key_revision.as_deref(), &nonce, &payload)?
     } else {
-        Ok(payload)
+        payload
+    };
+    match wire.compression {
+        None => Ok(payload),
+        Some(codec) => {
+            let codec = Compression::from_i32(codec).ok_or(Error::ProtocolMismatch("compression"))?;
+            let len = wire.uncompressed_len
+                          .filter(|len| *len <= MAX_UNCOMPRESSED_LEN)
+                          .and_then(|len| usize::try_from(len).ok())
+                          .ok_or(Error::ProtocolMismatch("uncompressed-len"))?;
+            codec.decompress(&payload, len)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload() -> Vec<u8> { "[config]\nlisten = \"0.0.0.0\"\n".repeat(100).into_bytes() }
+
+    #[test]
+    fn payloads_round_trip_through_every_codec() {
+        for codec in &[None, Some(Compression::Zstd), Some(Compression::Lz4)] {
+            let wire = generate_wire_above(payload(), None, *codec, 0).unwrap();
+            assert_eq!(unwrap_wire(&wire, None).unwrap(), payload());
+            let compressed = Wire::decode(wire.as_slice()).unwrap().compression.is_some();
+            assert_eq!(compressed, codec.is_some());
+        }
+    }
+
+    #[test]
+    fn payloads_below_the_threshold_are_not_compressed() {
+        let wire =
+            generate_wire_above(payload(), None, Some(Compression::Zstd), 1_000_000).unwrap();
+        let wire = Wire::decode(wire.as_slice()).unwrap();
+        assert_eq!(wire.compression, None);
+        assert_eq!(wire.payload.unwrap(), payload());
+    }
+
+    #[test]
+    fn payloads_claiming_to_decompress_too_large_are_rejected() {
+        let wire = generate_wire_above(payload(), None, Some(Compression::Lz4), 0).unwrap();
+        let mut wire = Wire::decode(wire.as_slice()).unwrap();
+        wire.uncompressed_len = Some(MAX_UNCOMPRESSED_LEN + 1);
+        let mut buf = BytesMut::with_capacity(wire.encoded_len());
+        wire.encode(&mut buf).unwrap();
+        assert!(unwrap_wire(&buf, None).is_err());
+    }
+
+    #[test]
+    fn payloads_claiming_more_than_they_decompress_to_are_rejected() {
+        for codec in &[Compression::Zstd, Compression::Lz4] {
+            let compressed = codec.compress(&payload()).unwrap();
+            let too_large = MAX_UNCOMPRESSED_LEN as usize;
+            assert!(codec.decompress(&compressed, payload().len() + 1).is_err());
+            assert!(codec.decompress(&compressed, too_large).is_err());
+        }
+    }
+
+    #[test]
+    fn the_first_of_our_codecs_they_accept_is_used() {
+        use super::Compression::{Lz4,
+                                 Zstd};
+        assert_eq!(negotiate(&[Zstd, Lz4], &[Lz4, Zstd]), Some(Zstd));
+        assert_eq!(negotiate(&[Zstd, Lz4], &[Lz4]), Some(Lz4));
+        assert_eq!(negotiate(&[Zstd], &[]), None);
+        assert_eq!(negotiate(&[], &[Zstd, Lz4]), None);
+    }
+
+    #[test]
+    fn codecs_are_parsed() {
+        assert_eq!(" zstd, LZ4 ".parse::<GossipCompression>().unwrap().0,
+                   vec![Compression::Zstd, Compression::Lz4]);
+        assert!("gzip".parse::<GossipCompression>().is_err());
     }
 }
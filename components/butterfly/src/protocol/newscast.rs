@@ -3,8 +3,10 @@ use std::fmt;
 use crate::rumor::{departure::Departure as CDeparture,
                    election::{Election as CElection,
                               ElectionUpdate as CElectionUpdate},
+                   fleet_manifest::FleetManifest as CFleetManifest,
                    service::Service as CService,
-                   service_config::ServiceConfig as CServiceConfig,
+                   service_config::{self,
+                                    ServiceConfig as CServiceConfig},
                    service_file::ServiceFile as CServiceFile};
 
 include!(concat!(env!("OUT_DIR"), "/butterfly.newscast.rs"));
@@ -24,6 +26,7 @@ impl fmt::Display for RumorType {
             RumorType::Fake2 => "fake2",
             RumorType::ElectionUpdate => "election-update",
             RumorType::Departure => "departure",
+            RumorType::FleetManifest => "fleet-manifest",
         };
 
         write!(f, "{}", value)
@@ -42,12 +45,15 @@ impl From<CDeparture> for Rumor {
 
 impl From<CElection> for Rumor {
     fn from(value: CElection) -> Self {
-        let payload = Election { member_id:     Some(value.member_id.clone()),
-                                 service_group: Some(value.service_group.to_string()),
-                                 term:          Some(value.term),
-                                 suitability:   Some(value.suitability),
-                                 status:        Some(value.status as i32),
-                                 votes:         value.votes.clone(), };
+        let payload = Election { member_id:            Some(value.member_id.clone()),
+                                 service_group:        Some(value.service_group.to_string()),
+                                 term:                 Some(value.term),
+                                 suitability:          Some(value.suitability),
+                                 status:               Some(value.status as i32),
+                                 votes:                value.votes.clone(),
+                                 affinity:             Some(value.affinity.score),
+                                 affinity_breaks_ties: Some(value.affinity.breaks_ties),
+                                 affinity_preferences: Some(value.affinity.preferences.clone()), };
         Rumor { r#type:  RumorType::Election as i32,
                 tag:     Vec::default(),
                 from_id: Some(value.member_id),
@@ -57,12 +63,15 @@ impl From<CElection> for Rumor {
 
 impl From<CElectionUpdate> for Rumor {
     fn from(value: CElectionUpdate) -> Self {
-        let payload = Election { member_id:     Some(value.member_id.clone()),
-                                 service_group: Some(value.service_group.to_string()),
-                                 term:          Some(value.term),
-                                 suitability:   Some(value.suitability),
-                                 status:        Some(value.status as i32),
-                                 votes:         value.votes.clone(), };
+        let payload = Election { member_id:            Some(value.member_id.clone()),
+                                 service_group:        Some(value.service_group.to_string()),
+                                 term:                 Some(value.term),
+                                 suitability:          Some(value.suitability),
+                                 status:               Some(value.status as i32),
+                                 votes:                value.votes.clone(),
+                                 affinity:             Some(value.affinity.score),
+                                 affinity_breaks_ties: Some(value.affinity.breaks_ties),
+                                 affinity_preferences: Some(value.affinity.preferences.clone()), };
         Rumor { r#type:  RumorType::ElectionUpdate as i32,
                 tag:     Vec::default(),
                 from_id: Some(value.member_id.clone()),
@@ -70,6 +79,19 @@ impl From<CElectionUpdate> for Rumor {
     }
 }
 
+impl From<CFleetManifest> for Rumor {
+    fn from(value: CFleetManifest) -> Self {
+        let payload = FleetManifest { incarnation: Some(value.incarnation),
+                                      signer:      Some(value.signer),
+                                      signature:   Some(value.signature),
+                                      body:        Some(value.body), };
+        Rumor { r#type:  RumorType::FleetManifest as i32,
+                tag:     Vec::default(),
+                from_id: Some(value.from_id),
+                payload: Some(RumorPayload::FleetManifest(payload)), }
+    }
+}
+
 impl From<CService> for Rumor {
     fn from(value: CService) -> Self {
         let payload = Service { member_id:     Some(value.member_id.clone()),
@@ -78,7 +100,9 @@ impl From<CService> for Rumor {
                                 initialized:   Some(value.initialized),
                                 pkg:           Some(value.pkg),
                                 cfg:           Some(value.cfg),
-                                sys:           Some(value.sys.into()), };
+                                sys:           Some(value.sys.into()),
+                                maintenance:   Some(value.maintenance),
+                                unload:        value.unload.map(Into::into), };
         Rumor { r#type:  RumorType::Service as i32,
                 tag:     Vec::default(),
                 from_id: Some(value.member_id),
@@ -88,10 +112,12 @@ impl From<CService> for Rumor {
 
 impl From<CServiceConfig> for Rumor {
     fn from(value: CServiceConfig) -> Self {
-        let payload = ServiceConfig { service_group: Some(value.service_group.to_string()),
-                                      incarnation:   Some(value.incarnation),
-                                      encrypted:     Some(value.encrypted),
-                                      config:        Some(value.config), };
+        let payload = ServiceConfig { service_group:  Some(value.service_group.to_string()),
+                                      incarnation:    Some(value.incarnation),
+                                      encrypted:      Some(value.encrypted),
+                                      config:         Some(value.config),
+                                      activate_at_ms: value.activate_at
+                                                           .map(service_config::activate_at_ms), };
         Rumor { r#type:  RumorType::ServiceConfig as i32,
                 tag:     Vec::default(),
                 from_id: Some(value.from_id),
@@ -101,14 +127,11 @@ impl From<CServiceConfig> for Rumor {
 
 impl From<CServiceFile> for Rumor {
     fn from(value: CServiceFile) -> Self {
-        let payload = ServiceFile { service_group: Some(value.service_group.to_string()),
-                                    incarnation:   Some(value.incarnation),
-                                    encrypted:     Some(value.encrypted),
-                                    filename:      Some(value.filename),
-                                    body:          Some(value.body), };
+        let from_id = value.from_id.clone();
+        let payload = ServiceFile::from(value);
         Rumor { r#type:  RumorType::ServiceFile as i32,
                 tag:     Vec::default(),
-                from_id: Some(value.from_id),
+                from_id: Some(from_id),
                 payload: Some(RumorPayload::ServiceFile(payload)), }
     }
 }
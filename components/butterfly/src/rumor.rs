@@ -9,6 +9,7 @@
 pub mod dat_file;
 pub mod departure;
 pub mod election;
+pub mod fleet_manifest;
 pub mod heat;
 pub mod service;
 pub mod service_config;
@@ -35,6 +36,7 @@ use std::{collections::{hash_map::Entry,
 pub use self::{departure::Departure,
                election::{Election,
                           ElectionUpdate},
+               fleet_manifest::FleetManifest,
                service::Service,
                service_config::ServiceConfig,
                service_file::ServiceFile,
@@ -56,6 +58,7 @@ pub enum RumorKind {
     Departure(Departure),
     Election(Election),
     ElectionUpdate(ElectionUpdate),
+    FleetManifest(FleetManifest),
     Membership(Membership),
     Service(Box<Service>), // Boxed due to clippy::large_enum_variant
     ServiceConfig(ServiceConfig),
@@ -68,6 +71,9 @@ impl From<RumorKind> for RumorPayload {
             RumorKind::Departure(departure) => RumorPayload::Departure(departure.into()),
             RumorKind::Election(election) => RumorPayload::Election(election.into()),
             RumorKind::ElectionUpdate(election) => RumorPayload::Election(election.into()),
+            RumorKind::FleetManifest(fleet_manifest) => {
+                RumorPayload::FleetManifest(fleet_manifest.into())
+            }
             RumorKind::Membership(membership) => RumorPayload::Member(membership.into()),
             RumorKind::Service(service) => RumorPayload::Service((*service).into()),
             RumorKind::ServiceConfig(service_config) => {
@@ -135,6 +141,14 @@ impl<'a, T: Rumor> From<&'a T> for RumorKey {
     fn from(rumor: &'a T) -> RumorKey { RumorKey::new(rumor.kind(), rumor.id(), rumor.key()) }
 }
 
+/// How many rumors a `RumorStore` holds, and how many bytes they take up encoded, which is roughly
+/// what they add to the rumor file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RumorStoreSize {
+    pub rumors: usize,
+    pub bytes:  usize,
+}
+
 type RumorSubMap<T> = HashMap<RumorKeyId, T>;
 type RumorMap<T> = HashMap<RumorKeyKey, RumorSubMap<T>>;
 
@@ -301,6 +315,27 @@ mod storage {
             let mut list = self.list.write();
             list.get_mut(key).and_then(|r| r.remove(id));
         }
+
+        /// Remove every rumor for which `keep` returns false, along with the service groups left
+        /// without any rumors, and return how many rumors were removed.
+        ///
+        /// # Locking (see locking.md)
+        /// * `RumorStore::list` (write)
+        pub fn retain_rsw(&self, mut keep: impl FnMut(&T) -> bool) -> usize {
+            let mut list = self.list.write();
+            let mut removed = 0;
+            for rumors in list.values_mut() {
+                let before = rumors.len();
+                rumors.retain(|_, rumor| keep(rumor));
+                removed += before - rumors.len();
+            }
+            list.retain(|_, rumors| !rumors.is_empty());
+            if removed > 0 {
+                list.shrink_to_fit();
+                self.increment_update_counter();
+            }
+            removed
+        }
     }
 
     impl<R: Rumor> RumorStore<R> {
@@ -332,6 +367,17 @@ mod storage {
             }
             result
         }
+
+        /// # Locking (see locking.md)
+        /// * `RumorStore::list` (read)
+        pub fn size_rsr(&self) -> RumorStoreSize {
+            let mut size = RumorStoreSize::default();
+            for rumor in self.lock_rsr().rumors() {
+                size.rumors += 1;
+                size.bytes += rumor.write_to_bytes().map_or(0, |bytes| bytes.len());
+            }
+            size
+        }
     }
 
     impl<T> Default for RumorStore<T> {
@@ -488,6 +534,7 @@ impl RumorEnvelope {
             RumorType::ElectionUpdate => {
                 RumorKind::ElectionUpdate(ElectionUpdate::from_proto(proto)?)
             }
+            RumorType::FleetManifest => RumorKind::FleetManifest(FleetManifest::from_proto(proto)?),
             RumorType::Member => RumorKind::Membership(Membership::from_proto(proto)?),
             RumorType::Service => RumorKind::Service(Box::new(Service::from_proto(proto)?)),
             RumorType::ServiceConfig => RumorKind::ServiceConfig(ServiceConfig::from_proto(proto)?),
@@ -618,7 +665,8 @@ mod tests {
         use super::*;
         use crate::{error::Error,
                     rumor::{Rumor,
-                            RumorStore}};
+                            RumorStore,
+                            RumorStoreSize}};
 
         #[test]
         fn insert_adds_rumor_when_empty() {
@@ -709,5 +757,38 @@ mod tests {
               .service_group(&key)
               .map_rumor(&member_id, |o| assert_eq!(o.id, member_id));
         }
+
+        #[test]
+        fn retain_removes_rumors_and_empty_service_groups() {
+            let rs = RumorStore::default();
+            let f1 = FakeRumor::default();
+            let f2 = FakeRumor { key: String::from("other"),
+                                 ..FakeRumor::default() };
+            let f2_id = f2.id.clone();
+            rs.insert_rsw(f1);
+            rs.insert_rsw(f2);
+            let update_counter = rs.get_update_counter();
+
+            assert_eq!(rs.retain_rsw(|rumor| rumor.id != f2_id), 1);
+            assert_eq!(rs.lock_rsr().len(), 1);
+            assert!(!rs.lock_rsr().contains_key("other"));
+            assert_eq!(rs.get_update_counter(), update_counter + 1);
+
+            assert_eq!(rs.retain_rsw(|_| true), 0);
+            assert_eq!(rs.get_update_counter(), update_counter + 1);
+        }
+
+        #[test]
+        fn size_counts_rumors_and_their_encoded_bytes() {
+            let rs = RumorStore::default();
+            assert_eq!(rs.size_rsr(), RumorStoreSize::default());
+            rs.insert_rsw(FakeRumor { id:  "foo".to_string(),
+                                      key: "bar".to_string(), });
+            rs.insert_rsw(FakeRumor { id:  "baz".to_string(),
+                                      key: "bar".to_string(), });
+            assert_eq!(rs.size_rsr(),
+                       RumorStoreSize { rumors: 2,
+                                        bytes:  14, });
+        }
     }
 }
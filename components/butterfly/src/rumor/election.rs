@@ -20,7 +20,8 @@ use crate::{error::{Error,
                     Rumor,
                     RumorPayload,
                     RumorType}};
-use std::{fmt,
+use std::{cmp::Ordering,
+          fmt,
           ops::{Deref,
                 DerefMut}};
 
@@ -34,12 +35,28 @@ pub trait ElectionRumor: ConstIdRumor {
 
 pub type Term = u64;
 
+/// How strongly a candidate is preferred as the leader of its service group by the leader
+/// affinity of its service.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LeaderAffinity {
+    /// The preferences the candidate matches, weighted by their order so that matching an earlier
+    /// preference outweighs matching all of the later ones
+    pub score:       u64,
+    /// Whether the score only breaks ties between equally suitable candidates, rather than
+    /// ranking ahead of suitability
+    pub breaks_ties: bool,
+    /// The preferences the score was computed from, separated by commas. Scores computed from
+    /// different preferences can't be compared.
+    pub preferences: String,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Election {
     pub member_id:     String,
     pub service_group: String,
     pub term:          u64,
     pub suitability:   u64,
+    pub affinity:      LeaderAffinity,
     pub status:        ElectionStatus,
     pub votes:         Vec<String>,
 }
@@ -68,6 +85,7 @@ impl Election {
                    service_group: service_group.into(),
                    term,
                    suitability,
+                   affinity: LeaderAffinity::default(),
                    status: if has_quorum {
                        ElectionStatus::Running
                    } else {
@@ -98,6 +116,20 @@ impl Election {
 
     /// Sets the status of the election to "NoQuorum"
     pub fn no_quorum(&mut self) { self.status = ElectionStatus::NoQuorum; }
+
+    /// Compare the candidates of two elections by their leader affinity and suitability, leaving
+    /// ties to the member ID. The affinity only breaks ties if either candidate says so, which
+    /// keeps the comparison symmetric when members disagree, and is ignored when the candidates
+    /// were scored by different preferences.
+    fn cmp_candidates(&self, other: &Election) -> Ordering {
+        if self.affinity.preferences != other.affinity.preferences {
+            self.suitability.cmp(&other.suitability)
+        } else if self.affinity.breaks_ties || other.affinity.breaks_ties {
+            (self.suitability, self.affinity.score).cmp(&(other.suitability, other.affinity.score))
+        } else {
+            (self.affinity.score, self.suitability).cmp(&(other.affinity.score, other.suitability))
+        }
+    }
 }
 
 impl ElectionRumor for Election {
@@ -114,6 +146,7 @@ impl PartialEq for Election {
         self.service_group == other.service_group
         && self.member_id == other.member_id
         && self.suitability == other.suitability
+        && self.affinity == other.affinity
         && self.votes == other.votes
         && self.status == other.status
         && self.term == other.term
@@ -136,6 +169,11 @@ impl FromProto<ProtoRumor> for Election {
                                             .ok_or(Error::ProtocolMismatch("service-group"))?,
                       term:          payload.term.unwrap_or(0),
                       suitability:   payload.suitability.unwrap_or(0),
+                      affinity:      LeaderAffinity { score:       payload.affinity.unwrap_or(0),
+                                                      breaks_ties: payload.affinity_breaks_ties
+                                                                          .unwrap_or(false),
+                                                      preferences: payload.affinity_preferences
+                                                                          .unwrap_or_default(), },
                       status:        payload.status
                                             .and_then(ElectionStatus::from_i32)
                                             .unwrap_or(ElectionStatus::Running),
@@ -145,12 +183,15 @@ impl FromProto<ProtoRumor> for Election {
 
 impl From<Election> for newscast::Election {
     fn from(value: Election) -> Self {
-        newscast::Election { member_id:     Some(value.member_id),
-                             service_group: Some(value.service_group.to_string()),
-                             term:          Some(value.term),
-                             suitability:   Some(value.suitability),
-                             status:        Some(value.status as i32),
-                             votes:         value.votes, }
+        newscast::Election { member_id:            Some(value.member_id),
+                             service_group:        Some(value.service_group.to_string()),
+                             term:                 Some(value.term),
+                             suitability:          Some(value.suitability),
+                             status:               Some(value.status as i32),
+                             votes:                value.votes,
+                             affinity:             Some(value.affinity.score),
+                             affinity_breaks_ties: Some(value.affinity.breaks_ties),
+                             affinity_preferences: Some(value.affinity.preferences), }
     }
 }
 
@@ -172,11 +213,11 @@ impl Rumor for Election {
         } else if self.term > other.term {
             debug!("stored rumor represents a newer term than received; keep sharing it");
             true
-        } else if self.suitability > other.suitability {
+        } else if self.cmp_candidates(&other) == Ordering::Greater {
             debug!("stored rumor is more suitable; take received rumor's votes and share");
             self.steal_votes(&mut other);
             true
-        } else if other.suitability > self.suitability {
+        } else if self.cmp_candidates(&other) == Ordering::Less {
             debug!("received rumor is more suitable; take stored rumor's votes, replace stored \
                     and share");
             other.steal_votes(self);
@@ -289,6 +330,7 @@ impl ConstIdRumor for ElectionUpdate {
 mod tests {
     use crate::rumor::{election::{Election,
                                   ElectionUpdate,
+                                  LeaderAffinity,
                                   Term},
                        ConstIdRumor as _,
                        Rumor,
@@ -379,4 +421,49 @@ mod tests {
         assert_eq!(e1.member_id, "d");
         assert_eq!(e1.votes.len(), 4);
     }
+
+    fn create_election_with_affinity(member_id: &str,
+                                     suitability: u64,
+                                     score: u64,
+                                     breaks_ties: bool)
+                                     -> Election {
+        let mut election = create_election(member_id, suitability);
+        election.affinity = LeaderAffinity { score,
+                                             breaks_ties,
+                                             preferences: String::from("disk=ssd") };
+        election
+    }
+
+    #[test]
+    fn merge_prefers_affinity_over_suitability() {
+        let mut e1 = create_election_with_affinity("a", 5, 0, false);
+        let e2 = create_election_with_affinity("b", 1, 2, false);
+        let e3 = create_election_with_affinity("c", 9, 1, false);
+        assert!(e1.merge(e2));
+        assert!(e1.merge(e3));
+        assert_eq!(e1.member_id, "b");
+        assert_eq!(e1.votes.len(), 3);
+    }
+
+    #[test]
+    fn merge_breaks_suitability_ties_with_affinity() {
+        let mut e1 = create_election_with_affinity("a", 5, 0, true);
+        let e2 = create_election_with_affinity("b", 1, 2, true);
+        let e3 = create_election_with_affinity("c", 5, 1, true);
+        let e4 = create_election_with_affinity("d", 5, 0, true);
+        assert!(e1.merge(e2));
+        assert!(e1.merge(e3));
+        assert!(e1.merge(e4));
+        assert_eq!(e1.member_id, "c");
+        assert_eq!(e1.votes.len(), 4);
+    }
+
+    #[test]
+    fn merge_ignores_affinity_scored_by_other_preferences() {
+        let mut e1 = create_election_with_affinity("a", 5, 0, false);
+        let mut e2 = create_election_with_affinity("b", 1, 2, false);
+        e2.affinity.preferences = String::from("zone=us-east-1a");
+        assert!(e1.merge(e2));
+        assert_eq!(e1.member_id, "a");
+    }
 }
@@ -0,0 +1,182 @@
+//! The FleetManifest rumor.
+//!
+//! Holds the signed manifest describing which service groups should run on which Supervisors in
+//! the ring. There is only ever one manifest per ring; the one with the highest incarnation wins.
+//! Butterfly rejects manifests that are not signed at all, but does not check the signature
+//! itself, that is left to the Supervisors which act on the manifest.
+
+use crate::{error::{Error,
+                    Result},
+            protocol::{self,
+                       newscast::{self,
+                                  Rumor as ProtoRumor},
+                       FromProto},
+            rumor::{ConstIdRumor,
+                    Rumor,
+                    RumorPayload,
+                    RumorType}};
+use std::{cmp::Ordering,
+          fmt,
+          mem};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FleetManifest {
+    pub from_id:     String,
+    pub incarnation: u64,
+    /// The named revision of the origin signing key the manifest was signed with
+    pub signer:      String,
+    pub signature:   Vec<u8>,
+    pub body:        Vec<u8>,
+}
+
+impl fmt::Display for FleetManifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f,
+               "FleetManifest i/{} m/{} s/{}",
+               self.incarnation, self.from_id, self.signer)
+    }
+}
+
+impl PartialOrd for FleetManifest {
+    fn partial_cmp(&self, other: &FleetManifest) -> Option<Ordering> {
+        Some(self.incarnation.cmp(&other.incarnation))
+    }
+}
+
+impl PartialEq for FleetManifest {
+    fn eq(&self, other: &FleetManifest) -> bool {
+        self.incarnation == other.incarnation
+        && self.signer == other.signer
+        && self.signature == other.signature
+        && self.body == other.body
+    }
+}
+
+impl FleetManifest {
+    /// Creates a new FleetManifest.
+    pub fn new<S1, S2>(member_id: S1,
+                       incarnation: u64,
+                       signer: S2,
+                       signature: Vec<u8>,
+                       body: Vec<u8>)
+                       -> Self
+        where S1: Into<String>,
+              S2: Into<String>
+    {
+        FleetManifest { from_id: member_id.into(),
+                        incarnation,
+                        signer: signer.into(),
+                        signature,
+                        body }
+    }
+}
+
+impl protocol::Message<ProtoRumor> for FleetManifest {
+    const MESSAGE_ID: &'static str = "FleetManifest";
+}
+
+impl FromProto<ProtoRumor> for FleetManifest {
+    fn from_proto(rumor: ProtoRumor) -> Result<Self> {
+        let payload = match rumor.payload.ok_or(Error::ProtocolMismatch("payload"))? {
+            RumorPayload::FleetManifest(payload) => payload,
+            _ => panic!("from-bytes fleet-manifest"),
+        };
+        // An unsigned manifest would displace the signed one for nothing, as no Supervisor acts
+        // on it
+        let signer = payload.signer
+                            .filter(|signer| !signer.is_empty())
+                            .ok_or(Error::ProtocolMismatch("signer"))?;
+        let signature = payload.signature
+                               .filter(|signature| !signature.is_empty())
+                               .ok_or(Error::ProtocolMismatch("signature"))?;
+        Ok(FleetManifest { from_id: rumor.from_id.ok_or(Error::ProtocolMismatch("from-id"))?,
+                           incarnation: payload.incarnation.unwrap_or(0),
+                           signer,
+                           signature,
+                           body: payload.body.unwrap_or_default() })
+    }
+}
+
+impl From<FleetManifest> for newscast::FleetManifest {
+    fn from(value: FleetManifest) -> Self {
+        newscast::FleetManifest { incarnation: Some(value.incarnation),
+                                  signer:      Some(value.signer),
+                                  signature:   Some(value.signature),
+                                  body:        Some(value.body), }
+    }
+}
+
+impl Rumor for FleetManifest {
+    /// The manifest with the higher incarnation wins.
+    fn merge(&mut self, mut other: FleetManifest) -> bool {
+        if *self >= other {
+            false
+        } else {
+            mem::swap(self, &mut other);
+            true
+        }
+    }
+
+    fn kind(&self) -> RumorType { RumorType::FleetManifest }
+
+    fn id(&self) -> &str { Self::const_id() }
+
+    fn key(&self) -> &str { Self::const_id() }
+}
+
+impl ConstIdRumor for FleetManifest {
+    fn const_id() -> &'static str { "fleet_manifest" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rumor::RumorStore;
+
+    fn create_fleet_manifest(incarnation: u64, body: &str) -> FleetManifest {
+        FleetManifest::new("timmeh",
+                           incarnation,
+                           "acme-20200101000000",
+                           Vec::from("signature"),
+                           Vec::from(body))
+    }
+
+    #[test]
+    fn only_the_latest_fleet_manifest_is_kept() {
+        let rs = RumorStore::default();
+        rs.insert_rsw(create_fleet_manifest(1, "first"));
+        rs.insert_rsw(create_fleet_manifest(2, "second"));
+        rs.insert_rsw(create_fleet_manifest(0, "stale"));
+
+        let list = rs.lock_rsr();
+        assert_eq!(list.len(), 1);
+
+        let sub_list = list.get(FleetManifest::const_id()).unwrap();
+        assert_eq!(sub_list.len(), 1);
+
+        let fm = sub_list.get(FleetManifest::const_id()).unwrap();
+        assert_eq!(fm.body, Vec::<u8>::from("second"));
+    }
+
+    #[test]
+    fn unsigned_fleet_manifests_are_rejected() {
+        let signed = create_fleet_manifest(1, "signed");
+        assert!(FleetManifest::from_proto(ProtoRumor::from(signed.clone())).is_ok());
+
+        let mut unsigned = signed.clone();
+        unsigned.signature = Vec::new();
+        assert!(FleetManifest::from_proto(ProtoRumor::from(unsigned)).is_err());
+        let mut unsigned = signed;
+        unsigned.signer = String::new();
+        assert!(FleetManifest::from_proto(ProtoRumor::from(unsigned)).is_err());
+    }
+
+    #[test]
+    fn merge_returns_false_if_nothing_changed() {
+        let mut fm1 = create_fleet_manifest(1, "yep");
+        let fm1_check = fm1.clone();
+        assert!(!fm1.merge(create_fleet_manifest(1, "yep")));
+        assert!(!fm1.merge(create_fleet_manifest(0, "nope")));
+        assert_eq!(fm1, fm1_check);
+    }
+}
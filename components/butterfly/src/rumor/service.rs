@@ -2,6 +2,7 @@
 //!
 //! Service rumors declare that a given `Server` is running this Service.
 
+pub use crate::protocol::newscast::coordinated_unload::Phase as UnloadPhase;
 use crate::{error::{Error,
                     Result},
             protocol::{self,
@@ -16,6 +17,7 @@ use serde::{ser::SerializeStruct,
             Serialize,
             Serializer};
 use std::{cmp::Ordering,
+          collections::HashMap,
           fmt,
           mem,
           result,
@@ -30,6 +32,11 @@ pub struct Service {
     pub pkg:           String,
     pub cfg:           Vec<u8>,
     pub sys:           SysInfo,
+    /// Whether the service has been put in maintenance on the member. Its members keep running
+    /// their health checks but are left out of what its consumers see.
+    pub maintenance:   bool,
+    /// The part the member takes in an unload of the service coordinated across its group, if any
+    pub unload:        Option<CoordinatedUnload>,
 }
 
 impl fmt::Display for Service {
@@ -45,7 +52,7 @@ impl Serialize for Service {
     fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
         where S: Serializer
     {
-        let mut strukt = serializer.serialize_struct("service", 7)?;
+        let mut strukt = serializer.serialize_struct("service", 9)?;
         let cfg: toml::value::Table = toml::from_slice(&self.cfg).unwrap_or_default();
         strukt.serialize_field("member_id", &self.member_id)?;
         strukt.serialize_field("service_group", &self.service_group)?;
@@ -54,6 +61,8 @@ impl Serialize for Service {
         strukt.serialize_field("cfg", &cfg)?;
         strukt.serialize_field("sys", &self.sys)?;
         strukt.serialize_field("initialized", &self.initialized)?;
+        strukt.serialize_field("maintenance", &self.maintenance)?;
+        strukt.serialize_field("unload", &self.unload)?;
         strukt.end()
     }
 }
@@ -99,6 +108,8 @@ impl Service {
                   initialized: false,
                   pkg: package.to_string(),
                   sys,
+                  maintenance: false,
+                  unload: None,
                   cfg: cfg.map(|v| {
                               // Directly serializing a toml::value::Table can lead to an error
                               // Wrapping it in a toml::value::Value makes this operation safe
@@ -132,7 +143,11 @@ impl FromProto<newscast::Rumor> for Service {
                      cfg:           payload.cfg.unwrap_or_default(),
                      sys:           payload.sys
                                            .ok_or(Error::ProtocolMismatch("sys"))
-                                           .and_then(SysInfo::from_proto)?, })
+                                           .and_then(SysInfo::from_proto)?,
+                     maintenance:   payload.maintenance.unwrap_or(false),
+                     unload:        payload.unload
+                                           .map(CoordinatedUnload::from_proto)
+                                           .transpose()?, })
     }
 }
 
@@ -144,7 +159,9 @@ impl From<Service> for newscast::Service {
                             initialized:   Some(value.initialized),
                             pkg:           Some(value.pkg),
                             cfg:           Some(value.cfg),
-                            sys:           Some(value.sys.into()), }
+                            sys:           Some(value.sys.into()),
+                            maintenance:   Some(value.maintenance),
+                            unload:        value.unload.map(Into::into), }
     }
 }
 
@@ -167,6 +184,31 @@ impl Rumor for Service {
     fn key(&self) -> &str { self.service_group.as_ref() }
 }
 
+/// The part a member takes in an unload of its service coordinated across the service group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CoordinatedUnload {
+    /// Identifies the unload, so that members that drained for an earlier one are not taken to
+    /// have drained for this one
+    pub id:    String,
+    pub phase: UnloadPhase,
+}
+
+impl FromProto<newscast::CoordinatedUnload> for CoordinatedUnload {
+    fn from_proto(proto: newscast::CoordinatedUnload) -> Result<Self> {
+        Ok(CoordinatedUnload { id:    proto.id.ok_or(Error::ProtocolMismatch("id"))?,
+                               phase: proto.phase
+                                           .and_then(UnloadPhase::from_i32)
+                                           .ok_or(Error::ProtocolMismatch("phase"))?, })
+    }
+}
+
+impl From<CoordinatedUnload> for newscast::CoordinatedUnload {
+    fn from(value: CoordinatedUnload) -> Self {
+        newscast::CoordinatedUnload { id:    Some(value.id),
+                                      phase: Some(value.phase as i32), }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct SysInfo {
     pub ip:                String,
@@ -177,6 +219,15 @@ pub struct SysInfo {
     pub http_gateway_port: u32,
     pub ctl_gateway_ip:    String,
     pub ctl_gateway_port:  u32,
+    /// The failure domain the Supervisor was started in, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zone:              Option<String>,
+    /// The labels the Supervisor was started with
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub labels:            HashMap<String, String>,
+    /// The version of the Supervisor, if it is recent enough to report it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version:           Option<String>,
 }
 
 impl Default for SysInfo {
@@ -188,7 +239,10 @@ impl Default for SysInfo {
                   http_gateway_ip:   "127.0.0.1".to_string(),
                   http_gateway_port: 0,
                   ctl_gateway_ip:    "127.0.0.1".to_string(),
-                  ctl_gateway_port:  0, }
+                  ctl_gateway_port:  0,
+                  zone:              None,
+                  labels:            HashMap::new(),
+                  version:           None, }
     }
 }
 
@@ -202,7 +256,10 @@ impl FromProto<newscast::SysInfo> for SysInfo {
                      http_gateway_ip:   proto.http_gateway_ip.unwrap_or_default(),
                      http_gateway_port: proto.http_gateway_port.unwrap_or_default(),
                      ctl_gateway_ip:    proto.ctl_gateway_ip.unwrap_or_default(),
-                     ctl_gateway_port:  proto.ctl_gateway_port.unwrap_or_default(), })
+                     ctl_gateway_port:  proto.ctl_gateway_port.unwrap_or_default(),
+                     zone:              proto.zone,
+                     labels:            proto.labels,
+                     version:           proto.version, })
     }
 }
 
@@ -215,7 +272,10 @@ impl From<SysInfo> for newscast::SysInfo {
                             http_gateway_ip:   Some(value.http_gateway_ip),
                             http_gateway_port: Some(value.http_gateway_port),
                             ctl_gateway_ip:    Some(value.ctl_gateway_ip),
-                            ctl_gateway_port:  Some(value.ctl_gateway_port), }
+                            ctl_gateway_port:  Some(value.ctl_gateway_port),
+                            zone:              value.zone,
+                            labels:            value.labels,
+                            version:           value.version, }
     }
 }
 
@@ -228,9 +288,13 @@ mod tests {
                                  PackageIdent},
                        service::ServiceGroup};
 
-    use super::Service;
-    use crate::rumor::{service::SysInfo,
-                       Rumor};
+    use super::{CoordinatedUnload,
+                Service,
+                UnloadPhase};
+    use crate::{protocol::{newscast,
+                           FromProto},
+                rumor::{service::SysInfo,
+                        Rumor}};
 
     fn create_service(member_id: &str) -> Service {
         let pkg = PackageIdent::from_str("core/neurosis/1.2.3/20161208121212").unwrap();
@@ -316,6 +380,16 @@ mod tests {
         assert_eq!(s1, s1_check);
     }
 
+    #[test]
+    fn coordinated_unload_is_carried_by_the_rumor() {
+        let mut service = create_service("adam");
+        service.unload = Some(CoordinatedUnload { id:    "unload-1".to_string(),
+                                                  phase: UnloadPhase::Drained, });
+        let rumor = newscast::Rumor::from(service.clone());
+        let decoded = Service::from_proto(rumor).unwrap();
+        assert_eq!(decoded.unload, service.unload);
+    }
+
     #[test]
     #[should_panic]
     fn service_package_name_mismatch() {
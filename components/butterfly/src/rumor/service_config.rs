@@ -20,7 +20,10 @@ use std::{borrow::Cow,
           fmt,
           mem,
           str::{self,
-                FromStr}};
+                FromStr},
+          time::{Duration,
+                 SystemTime,
+                 UNIX_EPOCH}};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ServiceConfig {
@@ -29,6 +32,9 @@ pub struct ServiceConfig {
     pub incarnation:   u64,
     pub encrypted:     bool,
     pub config:        Vec<u8>, // TODO: make this a String
+    /// When members apply the configuration by their clocks, rather than as soon as they
+    /// receive it
+    pub activate_at:   Option<SystemTime>,
 }
 
 impl fmt::Display for ServiceConfig {
@@ -55,6 +61,7 @@ impl PartialEq for ServiceConfig {
         && self.incarnation == other.incarnation
         && self.encrypted == other.encrypted
         && self.config == other.config
+        && self.activate_at == other.activate_at
     }
 }
 
@@ -67,7 +74,14 @@ impl ServiceConfig {
                         service_group,
                         incarnation: 0,
                         encrypted: false,
-                        config }
+                        config,
+                        activate_at: None }
+    }
+
+    /// Whether the configuration is to be applied by `now`
+    pub fn is_active(&self, now: SystemTime) -> bool {
+        self.activate_at
+            .map_or(true, |activate_at| activate_at <= now)
     }
 
     pub fn config(&self, key_cache: &KeyCache) -> Result<toml::value::Table> {
@@ -115,19 +129,29 @@ impl FromProto<ProtoRumor> for ServiceConfig {
                                       })?,
                            incarnation:   payload.incarnation.unwrap_or(0),
                            encrypted:     payload.encrypted.unwrap_or(false),
-                           config:        payload.config.unwrap_or_default(), })
+                           config:        payload.config.unwrap_or_default(),
+                           activate_at:   payload.activate_at_ms
+                                                 .map(|ms| UNIX_EPOCH + Duration::from_millis(ms)), })
     }
 }
 
 impl From<ServiceConfig> for newscast::ServiceConfig {
     fn from(value: ServiceConfig) -> Self {
-        newscast::ServiceConfig { service_group: Some(value.service_group.to_string()),
-                                  incarnation:   Some(value.incarnation),
-                                  encrypted:     Some(value.encrypted),
-                                  config:        Some(value.config), }
+        newscast::ServiceConfig { service_group:  Some(value.service_group.to_string()),
+                                  incarnation:    Some(value.incarnation),
+                                  encrypted:      Some(value.encrypted),
+                                  config:         Some(value.config),
+                                  activate_at_ms: value.activate_at.map(activate_at_ms), }
     }
 }
 
+/// The milliseconds since the Unix epoch of `time`, which are gossiped instead of the time itself
+pub(crate) fn activate_at_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 impl Rumor for ServiceConfig {
     /// Follows a simple pattern; if we have a newer incarnation than the one we already have, the
     /// new one wins. So far, these never change.
@@ -250,6 +274,33 @@ mod tests {
         assert_eq!(s1, s1_check);
     }
 
+    #[test]
+    fn service_configs_with_different_activation_times_are_not_equal() {
+        let s1 = create_service_config("adam", "yep");
+        let mut s2 = create_service_config("adam", "yep");
+        s2.activate_at = Some(UNIX_EPOCH + Duration::from_secs(1_717_207_200));
+        assert_ne!(s1, s2);
+    }
+
+    #[test]
+    fn service_configs_are_active_from_their_activation_time() {
+        let mut s1 = create_service_config("adam", "yep");
+        let now = SystemTime::now();
+        assert!(s1.is_active(now));
+
+        s1.activate_at = Some(now + Duration::from_secs(60 * 60));
+        assert!(!s1.is_active(now));
+        assert!(s1.is_active(now + Duration::from_secs(60 * 60)));
+    }
+
+    #[test]
+    fn activation_time_round_trips_through_the_protocol() {
+        let mut s1 = create_service_config("adam", "yep");
+        s1.activate_at = Some(UNIX_EPOCH + Duration::from_millis(1_717_207_200_500));
+        let rumor = ProtoRumor::from(s1.clone());
+        assert_eq!(ServiceConfig::from_proto(rumor).unwrap(), s1);
+    }
+
     #[test]
     fn config_comes_back_as_a_toml_value() {
         let s1 = create_service_config("adam", "yep=1");
@@ -1,6 +1,14 @@
 //! The ServiceFile rumor.
 //!
 //! Holds the toml configuration injected for a service.
+//!
+//! Files larger than a single rumor should carry are gossiped as chunks. Every chunk is a rumor of
+//! its own, identified by the filename and the index of the chunk, and carries a checksum of its
+//! body and of the whole file, so the file can be reassembled and verified once every chunk of an
+//! incarnation has arrived.
+//!
+//! A file can be targeted at some of the members of its service group, by member ID or by
+//! Supervisor labels, and be marked as a template to be rendered by the members receiving it.
 
 use crate::{error::{Error,
                     Result},
@@ -11,14 +19,58 @@ use crate::{error::{Error,
             rumor::{Rumor,
                     RumorPayload,
                     RumorType}};
-use habitat_core::{crypto::keys::{KeyCache,
-                                  SignedBox},
+use habitat_core::{crypto::{keys::{KeyCache,
+                                   SignedBox},
+                            Blake2bHash},
                    service::ServiceGroup};
 use std::{cmp::Ordering,
+          collections::HashMap,
           fmt,
           mem,
           str::FromStr};
 
+/// The position of a chunk in a chunked service file, and the checksums to verify it with
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Chunk {
+    pub index:         u32,
+    pub count:         u32,
+    /// The hex encoded BLAKE2b hash of the body of the chunk
+    pub checksum:      String,
+    /// The hex encoded BLAKE2b hash of the whole file
+    pub file_checksum: String,
+}
+
+/// The members of a service group a service file is meant for. A file without a target is meant
+/// for every member.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Target {
+    /// The IDs of the members the file is meant for
+    pub member_ids: Vec<String>,
+    /// The `KEY=VALUE` labels a Supervisor must all have for the file to be meant for it
+    pub labels:     Vec<String>,
+}
+
+impl Target {
+    pub fn is_empty(&self) -> bool { self.member_ids.is_empty() && self.labels.is_empty() }
+
+    /// Whether the file is meant for the member `member_id` of a Supervisor with `labels`, which
+    /// it is if the member is targeted by its ID or the Supervisor has all of the target labels.
+    pub fn includes(&self, member_id: &str, labels: &HashMap<String, String>) -> bool {
+        if self.is_empty() || self.member_ids.iter().any(|id| id == member_id) {
+            return true;
+        }
+        !self.labels.is_empty()
+        && self.labels.iter().all(|label| {
+                                 match label.split_once('=') {
+                                     Some((key, value)) => {
+                                         labels.get(key).map(String::as_str) == Some(value)
+                                     }
+                                     None => false,
+                                 }
+                             })
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ServiceFile {
     pub from_id:       String,
@@ -27,6 +79,14 @@ pub struct ServiceFile {
     pub encrypted:     bool,
     pub filename:      String,
     pub body:          Vec<u8>,
+    #[serde(skip_serializing_if = "Target::is_empty")]
+    pub target:        Target,
+    /// Whether the file is rendered as a template by the members receiving it
+    pub template:      bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chunk:             Option<Chunk>,
+    #[serde(skip)]
+    id:                String,
 }
 
 impl fmt::Display for ServiceFile {
@@ -54,6 +114,9 @@ impl PartialEq for ServiceFile {
         && self.encrypted == other.encrypted
         && self.filename == other.filename
         && self.body == other.body
+        && self.target == other.target
+        && self.template == other.template
+        && self.chunk == other.chunk
     }
 }
 
@@ -67,12 +130,85 @@ impl ServiceFile {
         where S1: Into<String>,
               S2: Into<String>
     {
+        let filename = filename.into();
         ServiceFile { from_id: member_id.into(),
                       service_group,
                       incarnation: 0,
                       encrypted: false,
-                      filename: filename.into(),
-                      body }
+                      id: filename.clone(),
+                      filename,
+                      body,
+                      target: Target::default(),
+                      template: false,
+                      chunk: None }
+    }
+
+    /// Creates a new ServiceFile holding one chunk of the file `filename`.
+    pub fn new_chunk<S1, S2>(member_id: S1,
+                             service_group: ServiceGroup,
+                             filename: S2,
+                             chunk: Chunk,
+                             body: Vec<u8>)
+                             -> Self
+        where S1: Into<String>,
+              S2: Into<String>
+    {
+        let mut service_file = Self::new(member_id, service_group, filename, body);
+        service_file.id = chunk_id(&service_file.filename, chunk.index);
+        service_file.chunk = Some(chunk);
+        service_file
+    }
+
+    /// The position and checksums of this chunk, if this is a chunk of a larger file.
+    pub fn chunk(&self) -> Option<&Chunk> { self.chunk.as_ref() }
+
+    /// Reassembles a file from all the chunks of one of its incarnations, verifying the checksum
+    /// of every chunk and of the whole file. The chunks may be given in any order.
+    pub fn assemble<'a, I>(chunks: I) -> Result<ServiceFile>
+        where I: IntoIterator<Item = &'a ServiceFile>
+    {
+        let mut chunks = chunks.into_iter()
+                               .filter_map(|sf| sf.chunk().map(|chunk| (chunk, sf)))
+                               .collect::<Vec<_>>();
+        chunks.sort_by_key(|(chunk, _)| chunk.index);
+        let (first_chunk, first) = *chunks.first()
+                                          .ok_or(Error::ProtocolMismatch("service-file-chunk"))?;
+        let invalid = |reason: String| Error::ServiceFileChunks(first.filename.clone(), reason);
+
+        if chunks.len() != first_chunk.count as usize {
+            return Err(invalid(format!("{} of {} chunks present",
+                                       chunks.len(),
+                                       first_chunk.count)));
+        }
+        let mut body = Vec::new();
+        for (index, (chunk, sf)) in chunks.iter().enumerate() {
+            if chunk.index as usize != index
+               || chunk.count != first_chunk.count
+               || chunk.file_checksum != first_chunk.file_checksum
+               || sf.incarnation != first.incarnation
+               || sf.filename != first.filename
+            {
+                return Err(invalid(format!("chunk {} does not match the other chunks",
+                                           chunk.index)));
+            }
+            if !checksum_matches(&sf.body, &chunk.checksum) {
+                return Err(invalid(format!("checksum mismatch for chunk {}", chunk.index)));
+            }
+            body.extend_from_slice(&sf.body);
+        }
+        if !checksum_matches(&body, &first_chunk.file_checksum) {
+            return Err(invalid(String::from("checksum mismatch for the reassembled file")));
+        }
+
+        let mut service_file = ServiceFile::new(first.from_id.clone(),
+                                                first.service_group.clone(),
+                                                first.filename.clone(),
+                                                body);
+        service_file.incarnation = first.incarnation;
+        service_file.encrypted = first.encrypted;
+        service_file.target = first.target.clone();
+        service_file.template = first.template;
+        Ok(service_file)
     }
 
     /// Return the body of the service file as a stream of bytes. Always returns a new copy, due to
@@ -95,6 +231,16 @@ impl ServiceFile {
     }
 }
 
+/// The rumor ID of chunk `index` of `filename`. A filename never contains a `/`, so the ID of a
+/// chunk cannot be the ID of an unchunked file.
+fn chunk_id(filename: &str, index: u32) -> String { format!("{}/{}", filename, index) }
+
+fn checksum_matches(body: &[u8], checksum: &str) -> bool {
+    checksum.parse::<Blake2bHash>()
+            .map(|expected| Blake2bHash::from_bytes(body) == expected)
+            .unwrap_or(false)
+}
+
 impl protocol::Message<ProtoRumor> for ServiceFile {
     const MESSAGE_ID: &'static str = "ServiceFile";
 }
@@ -105,26 +251,57 @@ impl FromProto<ProtoRumor> for ServiceFile {
             RumorPayload::ServiceFile(payload) => payload,
             _ => panic!("from-bytes service-config"),
         };
-        Ok(ServiceFile { from_id:       rumor.from_id.ok_or(Error::ProtocolMismatch("from-id"))?,
+        let chunk = match payload.chunk_count {
+            Some(count) => {
+                Some(Chunk { index: payload.chunk_index
+                                           .ok_or(Error::ProtocolMismatch("chunk-index"))?,
+                             count,
+                             checksum: payload.chunk_checksum
+                                              .ok_or(Error::ProtocolMismatch("chunk-checksum"))?,
+                             file_checksum:
+                                 payload.file_checksum
+                                        .ok_or(Error::ProtocolMismatch("file-checksum"))? })
+            }
+            None => None,
+        };
+        let filename = payload.filename
+                              .ok_or(Error::ProtocolMismatch("filename"))?;
+        let id = match chunk {
+            Some(ref chunk) => chunk_id(&filename, chunk.index),
+            None => filename.clone(),
+        };
+        Ok(ServiceFile { from_id: rumor.from_id.ok_or(Error::ProtocolMismatch("from-id"))?,
                          service_group:
                              payload.service_group
                                     .ok_or(Error::ProtocolMismatch("service-group"))
                                     .and_then(|s| ServiceGroup::from_str(&s).map_err(Error::from))?,
-                         incarnation:   payload.incarnation.unwrap_or(0),
-                         encrypted:     payload.encrypted.unwrap_or(false),
-                         filename:      payload.filename
-                                               .ok_or(Error::ProtocolMismatch("filename"))?,
-                         body:          payload.body.unwrap_or_default(), })
+                         incarnation: payload.incarnation.unwrap_or(0),
+                         encrypted: payload.encrypted.unwrap_or(false),
+                         filename,
+                         body: payload.body.unwrap_or_default(),
+                         target: Target { member_ids: payload.target_member_ids,
+                                          labels:     payload.target_labels, },
+                         template: payload.template.unwrap_or(false),
+                         chunk,
+                         id })
     }
 }
 
 impl From<ServiceFile> for newscast::ServiceFile {
     fn from(value: ServiceFile) -> Self {
-        newscast::ServiceFile { service_group: Some(value.service_group.to_string()),
-                                incarnation:   Some(value.incarnation),
-                                encrypted:     Some(value.encrypted),
-                                filename:      Some(value.filename),
-                                body:          Some(value.body), }
+        let chunk = value.chunk;
+        newscast::ServiceFile { service_group:     Some(value.service_group.to_string()),
+                                incarnation:       Some(value.incarnation),
+                                encrypted:         Some(value.encrypted),
+                                filename:          Some(value.filename),
+                                body:              Some(value.body),
+                                target_member_ids: value.target.member_ids,
+                                target_labels:     value.target.labels,
+                                template:          Some(value.template),
+                                chunk_index:       chunk.as_ref().map(|c| c.index),
+                                chunk_count:       chunk.as_ref().map(|c| c.count),
+                                chunk_checksum:    chunk.as_ref().map(|c| c.checksum.clone()),
+                                file_checksum:     chunk.map(|c| c.file_checksum), }
     }
 }
 
@@ -142,7 +319,7 @@ impl Rumor for ServiceFile {
 
     fn kind(&self) -> RumorType { RumorType::ServiceFile }
 
-    fn id(&self) -> &str { &self.filename }
+    fn id(&self) -> &str { &self.id }
 
     fn key(&self) -> &str { &self.service_group }
 }
@@ -233,4 +410,103 @@ mod tests {
                                                                  the body"),
                    String::from("tcp-backlog = 128"));
     }
+
+    fn create_chunks(filename: &str, body: &[u8], chunk_size: usize) -> Vec<ServiceFile> {
+        let file_checksum = Blake2bHash::from_bytes(body).to_string();
+        let count = ((body.len() + chunk_size - 1) / chunk_size) as u32;
+        body.chunks(chunk_size)
+            .enumerate()
+            .map(|(index, chunk_body)| {
+                let chunk = Chunk { index: index as u32,
+                                    count,
+                                    checksum: Blake2bHash::from_bytes(chunk_body).to_string(),
+                                    file_checksum: file_checksum.clone() };
+                let mut sf = ServiceFile::new_chunk("adam",
+                                                    ServiceGroup::new("neurosis",
+                                                                      "production",
+                                                                      None).unwrap(),
+                                                    filename,
+                                                    chunk,
+                                                    chunk_body.to_vec());
+                sf.incarnation = 2;
+                sf
+            })
+            .collect()
+    }
+
+    #[test]
+    fn targets_survive_the_protocol() {
+        let mut s1 = create_service_file("adam", "yep", "port = {{cfg.port}}");
+        s1.target = Target { member_ids: vec![String::from("member-a")],
+                             labels:     vec![String::from("role=cache")], };
+        s1.template = true;
+        let decoded = ServiceFile::from_proto(ProtoRumor::from(s1.clone())).unwrap();
+        assert_eq!(decoded, s1);
+    }
+
+    #[test]
+    fn targets_include_members_by_id_or_labels() {
+        let labels = vec![(String::from("role"), String::from("cache")),
+                          (String::from("disk"), String::from("ssd"))].into_iter()
+                                                                      .collect();
+        let target = |member_ids: &[&str], target_labels: &[&str]| {
+            Target { member_ids: member_ids.iter().map(ToString::to_string).collect(),
+                     labels:     target_labels.iter().map(ToString::to_string).collect(), }
+        };
+        assert!(target(&[], &[]).includes("member-a", &labels));
+        assert!(target(&["member-a"], &[]).includes("member-a", &labels));
+        assert!(!target(&["member-b"], &[]).includes("member-a", &labels));
+        assert!(target(&["member-b"], &["role=cache", "disk=ssd"]).includes("member-a", &labels));
+        assert!(!target(&[], &["role=cache", "disk=hdd"]).includes("member-a", &labels));
+        assert!(!target(&[], &["role"]).includes("member-a", &labels));
+    }
+
+    #[test]
+    fn chunks_are_separate_rumors() {
+        let chunks = create_chunks("cert.pem", b"0123456789", 4);
+        let ids = chunks.iter().map(Rumor::id).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["cert.pem/0", "cert.pem/1", "cert.pem/2"]);
+        assert_eq!(create_service_file("adam", "cert.pem", "x").id(),
+                   "cert.pem");
+    }
+
+    #[test]
+    fn chunks_survive_the_protocol() {
+        let chunk = create_chunks("cert.pem", b"0123456789", 4).remove(1);
+        let decoded = ServiceFile::from_proto(ProtoRumor::from(chunk.clone())).unwrap();
+        assert_eq!(decoded, chunk);
+        assert_eq!(decoded.id(), "cert.pem/1");
+    }
+
+    #[test]
+    fn assemble_reassembles_the_chunks_in_any_order() {
+        let mut chunks = create_chunks("cert.pem", b"0123456789", 4);
+        chunks.reverse();
+        let file = ServiceFile::assemble(&chunks).unwrap();
+        assert_eq!(file.body, b"0123456789".to_vec());
+        assert_eq!(file.incarnation, 2);
+        assert_eq!(file.id(), "cert.pem");
+        assert!(file.chunk().is_none());
+    }
+
+    #[test]
+    fn assemble_fails_for_missing_chunks() {
+        let mut chunks = create_chunks("cert.pem", b"0123456789", 4);
+        chunks.remove(1);
+        assert!(ServiceFile::assemble(&chunks).is_err());
+    }
+
+    #[test]
+    fn assemble_fails_for_a_corrupt_chunk() {
+        let mut chunks = create_chunks("cert.pem", b"0123456789", 4);
+        chunks[1].body = b"4567".iter().rev().cloned().collect();
+        assert!(ServiceFile::assemble(&chunks).is_err());
+    }
+
+    #[test]
+    fn assemble_fails_for_chunks_of_different_incarnations() {
+        let mut chunks = create_chunks("cert.pem", b"0123456789", 4);
+        chunks[2].incarnation = 3;
+        assert!(ServiceFile::assemble(&chunks).is_err());
+    }
 }
@@ -1,34 +1,54 @@
 //! The Butterfly server.
 //!
 //! Creates `Server` structs, that hold everything we need to run the SWIM and Gossip protocol.
-//! Winds up with 5 separate threads - inbound (incoming connections), outbound (the Probe
+//! Winds up with 6 separate threads - inbound (incoming connections), outbound (the Probe
 //! protocol), expire (turning Suspect members into Confirmed members), push (the fan-out rumors),
-//! and pull (the inbound receipt of rumors.).
+//! pull (the inbound receipt of rumors.), and compact (removing rumors nothing refers to).
 
+mod clock_skew;
+mod compact;
+mod denylist;
+mod election_dampener;
 mod expire;
 mod inbound;
 mod incarnation_store;
+mod listen;
 mod outbound;
 mod pull;
 mod push;
+mod rate_limit;
+mod round_trips;
 pub mod timing;
 
-use self::{incarnation_store::IncarnationStore,
+use self::{clock_skew::ClockOffsets,
+           denylist::Denylist,
+           election_dampener::ElectionDampener,
+           incarnation_store::IncarnationStore,
+           rate_limit::OutboundLimiter,
+           round_trips::RoundTrips,
            sync::Myself};
+pub use self::{clock_skew::ClockSkew,
+               denylist::DenylistEntry};
 use crate::{error::{Error,
                     Result},
-            member::{Health,
+            keyring::Keyring,
+            member::{Capability,
+                     Health,
                      Incarnation,
                      Member,
                      MemberList,
-                     MemberListProxy},
+                     MemberListDebugProxy,
+                     MemberListProxy,
+                     Membership},
             message,
             rumor::{dat_file::{DatFileReader,
                                DatFileWriter},
                     departure::Departure,
                     election::{Election,
                                ElectionRumor,
-                               ElectionUpdate},
+                               ElectionUpdate,
+                               LeaderAffinity},
+                    fleet_manifest::FleetManifest,
                     heat::sync::RumorHeat,
                     service::Service,
                     service_config::ServiceConfig,
@@ -38,30 +58,42 @@ use crate::{error::{Error,
                     RumorKey,
                     RumorStore,
                     RumorStoreProxy,
+                    RumorStoreSize,
                     RumorType},
-            swim::Ack};
+            swim::Ack,
+            tls::{self,
+                  RingTls}};
 use habitat_common::{liveliness_checker,
                      sync::Lock,
                      FeatureFlag};
-use habitat_core::crypto::keys::RingKey;
+use habitat_core::{crypto::keys::RingKey,
+                   dns,
+                   service::ServiceGroup};
 use prometheus::{HistogramTimer,
                  HistogramVec,
+                 IntCounterVec,
                  IntGauge};
 use serde::{ser::SerializeStruct,
             Serialize,
             Serializer};
+use socket2::{Domain,
+              Protocol,
+              Socket,
+              Type};
 use std::{collections::{HashMap,
                         HashSet},
           fmt::{self,
                 Debug},
           fs,
           io,
-          net::{SocketAddr,
+          net::{IpAddr,
+                SocketAddr,
                 ToSocketAddrs,
                 UdpSocket},
           path::{Path,
                  PathBuf},
           result,
+          str::FromStr,
           sync::{atomic::{AtomicBool,
                           AtomicIsize,
                           Ordering},
@@ -71,7 +103,8 @@ use std::{collections::{HashMap,
                  Mutex},
           thread,
           time::{Duration,
-                 Instant}};
+                 Instant,
+                 SystemTime}};
 
 /// The maximum number of other members we should notify when we shut
 /// down and leave the ring.
@@ -85,6 +118,10 @@ lazy_static! {
         register_histogram_vec!("hab_butterfly_election_duration_seconds",
                                 "How long it takes to complete an election",
                                 &["service_group"]).unwrap();
+    static ref BANDWIDTH: IntCounterVec =
+        register_int_counter_vec!("hab_butterfly_bandwidth_bytes_total",
+                                  "Total number of bytes sent and received over SWIM and gossip",
+                                  &["protocol", "direction"]).unwrap();
 }
 
 // We need this here to track how long it takes to complete an election. We need to store the timer
@@ -101,6 +138,16 @@ type AckSender = mpsc::Sender<(SocketAddr, Ack)>;
 
 pub trait Suitability: Debug + Send + Sync {
     fn suitability_for_msr(&self, service_group: &str) -> u64;
+
+    /// How strongly this member is preferred as the leader of the service group, which ranks
+    /// ahead of its suitability unless it only breaks ties, given its mean `round_trip` to the
+    /// other members
+    fn leader_affinity_for_msr(&self,
+                               _service_group: &str,
+                               _round_trip: Option<Duration>)
+                               -> LeaderAffinity {
+        LeaderAffinity::default()
+    }
 }
 
 pub(crate) mod sync {
@@ -270,33 +317,45 @@ pub(crate) mod sync {
 /// The server struct. Is thread-safe.
 #[derive(Debug)]
 pub struct Server {
-    name:                     Arc<String>,
-    member_id:                Arc<String>,
+    name: Arc<String>,
+    member_id: Arc<String>,
     // TODO (CM): This is currently public because butterfly tests
     // depends on it being so. Refactor so it can be private.
-    myself:                   Arc<Myself>,
-    pub member_list:          Arc<MemberList>,
-    ring_key:                 Arc<Option<RingKey>>,
-    rumor_heat:               Arc<RumorHeat>,
-    pub service_store:        RumorStore<Service>,
+    myself: Arc<Myself>,
+    pub member_list: Arc<MemberList>,
+    keyring: Arc<Option<Keyring>>,
+    tls: Arc<Option<RingTls>>,
+    organization: Arc<Option<String>>,
+    zone: Arc<Option<String>>,
+    rumor_heat: Arc<RumorHeat>,
+    outbound_limiter: Arc<OutboundLimiter>,
+    pub service_store: RumorStore<Service>,
     pub service_config_store: RumorStore<ServiceConfig>,
-    pub service_file_store:   RumorStore<ServiceFile>,
-    pub election_store:       RumorStore<Election>,
-    pub update_store:         RumorStore<ElectionUpdate>,
-    pub departure_store:      RumorStore<Departure>,
-    swim_addr:                SocketAddr,
-    gossip_addr:              SocketAddr,
-    suitability_lookup:       Arc<dyn Suitability>,
-    data_path:                Option<PathBuf>,
-    dat_file:                 Option<Arc<Mutex<DatFileWriter>>>,
-    socket:                   Option<UdpSocket>,
-    departed:                 Arc<AtomicBool>,
+    pub service_file_store: RumorStore<ServiceFile>,
+    pub election_store: RumorStore<Election>,
+    pub update_store: RumorStore<ElectionUpdate>,
+    pub departure_store: RumorStore<Departure>,
+    pub fleet_manifest_store: RumorStore<FleetManifest>,
+    swim_addr: SocketAddr,
+    gossip_addr: SocketAddr,
+    suitability_lookup: Arc<dyn Suitability>,
+    data_path: Option<PathBuf>,
+    dat_file: Option<Arc<Mutex<DatFileWriter>>>,
+    socket: Option<UdpSocket>,
+    departed: Arc<AtomicBool>,
     // These are all here for testing support
-    pause:                    Arc<AtomicBool>,
-    swim_rounds:              Arc<AtomicIsize>,
-    gossip_rounds:            Arc<AtomicIsize>,
-    block_list:               Arc<Lock<HashSet<String>>>,
-    election_timers:          Arc<Mutex<HashMap<String, ElectionTimer>>>,
+    pause: Arc<AtomicBool>,
+    swim_rounds: Arc<AtomicIsize>,
+    gossip_rounds: Arc<AtomicIsize>,
+    block_list: Arc<Lock<HashSet<String>>>,
+    denylist: Arc<Lock<Denylist>>,
+    election_timers: Arc<Mutex<HashMap<String, ElectionTimer>>>,
+    election_dampener: Arc<ElectionDampener>,
+    update_dampener: Arc<ElectionDampener>,
+    clock_offsets: Arc<ClockOffsets>,
+    round_trips: Arc<RoundTrips>,
+    rumor_ttl: Arc<Option<Duration>>,
+    observer: bool,
 }
 
 impl Clone for Server {
@@ -305,14 +364,19 @@ impl Clone for Server {
                  member_id:            self.member_id.clone(),
                  myself:               self.myself.clone(),
                  member_list:          self.member_list.clone(),
-                 ring_key:             self.ring_key.clone(),
+                 keyring:              self.keyring.clone(),
+                 tls:                  self.tls.clone(),
+                 organization:         self.organization.clone(),
+                 zone:                 self.zone.clone(),
                  rumor_heat:           self.rumor_heat.clone(),
+                 outbound_limiter:     self.outbound_limiter.clone(),
                  service_store:        self.service_store.clone(),
                  service_config_store: self.service_config_store.clone(),
                  service_file_store:   self.service_file_store.clone(),
                  election_store:       self.election_store.clone(),
                  update_store:         self.update_store.clone(),
                  departure_store:      self.departure_store.clone(),
+                 fleet_manifest_store: self.fleet_manifest_store.clone(),
                  swim_addr:            self.swim_addr,
                  gossip_addr:          self.gossip_addr,
                  suitability_lookup:   self.suitability_lookup.clone(),
@@ -323,19 +387,28 @@ impl Clone for Server {
                  swim_rounds:          self.swim_rounds.clone(),
                  gossip_rounds:        self.gossip_rounds.clone(),
                  block_list:           self.block_list.clone(),
+                 denylist:             self.denylist.clone(),
                  socket:               None,
-                 election_timers:      self.election_timers.clone(), }
+                 election_timers:      self.election_timers.clone(),
+                 election_dampener:    self.election_dampener.clone(),
+                 update_dampener:      self.update_dampener.clone(),
+                 clock_offsets:        self.clock_offsets.clone(),
+                 round_trips:          self.round_trips.clone(),
+                 rumor_ttl:            self.rumor_ttl.clone(),
+                 observer:             self.observer, }
     }
 }
 
 impl Server {
     /// Create a new server, bound to the `addr`, hosting a particular `member`, and with a
-    /// ring_key if you want encryption on the wire, and an optional server name.
+    /// ring_key if you want encryption on the wire, an optional organization to scope service
+    /// rumors to, and an optional server name.
     #[allow(clippy::too_many_arguments)]
     pub fn new(swim_addr: SocketAddr,
                gossip_addr: SocketAddr,
                mut member: Member,
                ring_key: Option<RingKey>,
+               organization: Option<String>,
                name: Option<String>,
                // TODO (CM): having data_path as optional is only something
                // that's used in testing, but it cascades outward and
@@ -349,10 +422,18 @@ impl Server {
 
         match (maybe_swim_socket_addr, maybe_gossip_socket_addr) {
             (Ok(Some(swim_socket_addr)), Ok(Some(gossip_socket_addr))) => {
-                member.swim_port = swim_socket_addr.port();
-                member.gossip_port = gossip_socket_addr.port();
+                // A member which advertises its own address (see
+                // `Member::set_observed_address`) is reached on the ports it advertises
+                // rather than the ones it listens on.
+                if member.address.is_empty() {
+                    member.swim_port = swim_socket_addr.port();
+                    member.gossip_port = gossip_socket_addr.port();
+                }
+                member.compression = message::accepted_compression();
+                member.capabilities = Capability::ALL.to_vec();
 
                 let member_id = member.id.clone();
+                let zone = member.zone.clone();
 
                 // TODO (CM): This None really wants to go away. Not
                 // currently setting a IncarnationStore, because it
@@ -367,14 +448,19 @@ impl Server {
                             member_id: Arc::new(member_id),
                             myself: Arc::new(myself),
                             member_list: Arc::new(MemberList::new()),
-                            ring_key: Arc::new(ring_key),
+                            keyring: Arc::new(ring_key.map(Keyring::new)),
+                            tls: Arc::new(None),
+                            organization: Arc::new(organization),
+                            zone: Arc::new(zone),
                             rumor_heat: Arc::default(),
+                            outbound_limiter: Arc::default(),
                             service_store: RumorStore::default(),
                             service_config_store: RumorStore::default(),
                             service_file_store: RumorStore::default(),
                             election_store: RumorStore::default(),
                             update_store: RumorStore::default(),
                             departure_store: RumorStore::default(),
+                            fleet_manifest_store: RumorStore::default(),
                             swim_addr: swim_socket_addr,
                             gossip_addr: gossip_socket_addr,
                             suitability_lookup,
@@ -385,8 +471,15 @@ impl Server {
                             swim_rounds: Arc::new(AtomicIsize::new(0)),
                             gossip_rounds: Arc::new(AtomicIsize::new(0)),
                             block_list: Arc::new(Lock::new(HashSet::new())),
+                            denylist: Arc::default(),
                             socket: None,
-                            election_timers: Arc::new(Mutex::new(HashMap::new())) })
+                            election_timers: Arc::new(Mutex::new(HashMap::new())),
+                            election_dampener: Arc::default(),
+                            update_dampener: Arc::default(),
+                            clock_offsets: Arc::default(),
+                            round_trips: Arc::default(),
+                            rumor_ttl: Arc::new(None),
+                            observer: false })
             }
             (Err(e), _) | (_, Err(e)) => Err(Error::CannotBind(e)),
             (Ok(None), _) | (_, Ok(None)) => {
@@ -464,22 +557,32 @@ impl Server {
             }
 
             let dat_path = path.join(format!("{}.rst", &self.member_id));
-            let mut reader = DatFileReader::read_or_create_rsr_mlr(dat_path.clone(),
-                                                                   &self.member_list,
-                                                                   &self.service_store,
-                                                                   &self.service_config_store,
-                                                                   &self.service_file_store,
-                                                                   &self.election_store,
-                                                                   &self.update_store,
-                                                                   &self.departure_store)?;
-
-            match reader.read_into_rsw_mlw_rhw_msr(self) {
-                Ok(_) => {
-                    debug!("Successfully ingested rumors from {}",
-                           reader.path().display())
-                }
+            let read = DatFileReader::read_or_create_rsr_mlr(dat_path.clone(),
+                                                             &self.member_list,
+                                                             &self.service_store,
+                                                             &self.service_config_store,
+                                                             &self.service_file_store,
+                                                             &self.election_store,
+                                                             &self.update_store,
+                                                             &self.departure_store)
+                .and_then(|mut reader| reader.read_into_rsw_mlw_rhw_msr(self));
+
+            match read {
+                Ok(_) => debug!("Successfully ingested rumors from {}", dat_path.display()),
                 Err(Error::DatFileIO(path, err)) => error!("{}", Error::DatFileIO(path, err)),
-                Err(err) => return Err(err),
+                Err(err) => {
+                    // A corrupt rumor file must not keep the Supervisor from starting. It is
+                    // set aside for inspection and the rumors it held are gossiped back to us
+                    // by the rest of the ring.
+                    let corrupt_path = dat_path.with_extension("rst.corrupt");
+                    error!("Unable to read rumors from {}, moving it to {}: {}",
+                           dat_path.display(),
+                           corrupt_path.display(),
+                           err);
+                    fs::rename(&dat_path, &corrupt_path).map_err(|e| {
+                                                            Error::DatFileIO(dat_path.clone(), e)
+                                                        })?;
+                }
             };
 
             let writer = DatFileWriter::new(dat_path);
@@ -495,7 +598,7 @@ impl Server {
             }
         }
 
-        let socket = UdpSocket::bind(self.swim_addr)?;
+        let socket = bind_swim_socket(self.swim_addr)?;
         socket.set_read_timeout(Some(Duration::from_millis(1000)))
               .map_err(Error::SocketSetReadTimeout)?;
         socket.set_write_timeout(Some(Duration::from_millis(1000)))
@@ -503,10 +606,32 @@ impl Server {
 
         self.socket = Some(clone_socket(&socket)?);
 
-        inbound::spawn_thread(format!("inbound-{}", self.name()),
-                              self.clone(),
-                              clone_socket(&socket)?,
-                              tx_outbound)?;
+        // A ring using TLS receives SWIM messages and rumors over TLS connections instead. The
+        // SWIM socket is still bound, but nothing received on it is read.
+        if self.uses_tls() {
+            let socket = Arc::new(clone_socket(&socket)?);
+            let swim_listener = tls::bind(self.swim_addr).map_err(Error::CannotBind)?;
+            listen::spawn_thread(format!("listen-swim-{}", self.name()),
+                                 self.clone(),
+                                 socket.clone(),
+                                 tx_outbound.clone(),
+                                 swim_listener)?;
+            // Connections to the gossip port arrive on the SWIM listener when they share one
+            if self.gossip_addr != self.swim_addr {
+                let gossip_listener = tls::bind(self.gossip_addr).map_err(Error::CannotBind)?;
+                listen::spawn_thread(format!("listen-gossip-{}", self.name()),
+                                     self.clone(),
+                                     socket,
+                                     tx_outbound,
+                                     gossip_listener)?;
+            }
+        } else {
+            inbound::spawn_thread(format!("inbound-{}", self.name()),
+                                  self.clone(),
+                                  clone_socket(&socket)?,
+                                  tx_outbound)?;
+            pull::spawn_thread(format!("pull-{}", self.name()), self.clone())?;
+        }
 
         outbound::spawn_thread(format!("outbound-{}", self.name()),
                                self.clone(),
@@ -518,7 +643,7 @@ impl Server {
                              self.clone(),
                              timing.clone())?;
 
-        pull::spawn_thread(format!("pull-{}", self.name()), self.clone())?;
+        compact::spawn_thread(format!("compact-{}", self.name()), self.clone())?;
 
         push::spawn_thread(format!("push-{}", self.name()),
                            self.clone(),
@@ -560,6 +685,112 @@ impl Server {
         self.block_list.read().contains(member_id)
     }
 
+    /// Refuse all traffic from the members `entry` matches. Returns whether the entry was not
+    /// already on the denylist.
+    ///
+    /// # Locking (see locking.md)
+    /// * `Server::denylist` (write)
+    pub fn add_to_denylist_sdlw(&self, entry: DenylistEntry) -> bool {
+        self.denylist.write().insert(entry)
+    }
+
+    /// Refuse all traffic from the members `entry` matches, and depart the ones already in the
+    /// member list. Returns whether the entry was not already on the denylist.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (write)
+    /// * `Server::denylist` (write)
+    /// * `RumorHeat::inner` (write)
+    pub fn deny_mlw_sdlw_rhw(&self, entry: DenylistEntry) -> bool {
+        let denied_ids: Vec<String> =
+            self.member_list
+                .check_list_mlr(&self.member_id)
+                .into_iter()
+                .filter(|member| entry.matches(&member.id, member.address.parse().ok()))
+                .map(|member| member.id)
+                .collect();
+        let inserted = self.add_to_denylist_sdlw(entry.clone());
+        for member_id in denied_ids {
+            warn!("Departing member {} - it is denied by {}", member_id, entry);
+            self.member_list.set_departed_mlw(&member_id);
+            self.rumor_heat.lock_rhw().purge(&member_id);
+            self.outbound_limiter.forget(&member_id);
+            self.rumor_heat
+                .lock_rhw()
+                .start_hot_rumor(RumorKey::new(RumorType::Member, &member_id, ""));
+        }
+        inserted
+    }
+
+    /// Take `entry` off the denylist. Members it departed stay departed, but may rejoin the ring
+    /// under a new member ID. Returns whether the entry was on the denylist.
+    ///
+    /// # Locking (see locking.md)
+    /// * `Server::denylist` (write)
+    pub fn remove_from_denylist_sdlw(&self, entry: &DenylistEntry) -> bool {
+        self.denylist.write().remove(entry)
+    }
+
+    /// # Locking (see locking.md)
+    /// * `Server::denylist` (read)
+    pub fn denylist_sdlr(&self) -> Vec<DenylistEntry> { self.denylist.read().entries() }
+
+    /// Check if traffic from a given member ID, received from a given address, is refused.
+    ///
+    /// # Locking (see locking.md)
+    /// * `Server::denylist` (read)
+    fn is_denied_sdlr(&self, member_id: &str, address: IpAddr) -> bool {
+        self.denylist.read().denies(member_id, Some(address))
+    }
+
+    /// Check if gossip from a given member ID is refused, by its ID or the address it is known
+    /// by. The gossip socket does not tell which address a rumor came from.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    /// * `Server::denylist` (read)
+    fn is_sender_denied_mlr_sdlr(&self, member_id: &str) -> bool {
+        let address = self.member_list
+                          .get_cloned_mlr(member_id)
+                          .and_then(|member| member.address.parse().ok());
+        self.denylist.read().denies(member_id, address)
+    }
+
+    /// Check if a member gossiped about, by its ID or the address it advertises, is refused. Word
+    /// of a denied member's departure is still let through.
+    ///
+    /// # Locking (see locking.md)
+    /// * `Server::denylist` (read)
+    fn is_membership_denied_sdlr(&self, membership: &Membership) -> bool {
+        membership.health != Health::Departed
+        && self.denylist.read().denies_member(&membership.member)
+    }
+
+    /// Secure the ring's traffic with mutual TLS, rather than a ring key. Must be called before
+    /// the server is started, and every member of the ring must do the same.
+    pub fn use_tls(&mut self, tls: RingTls) { self.tls = Arc::new(Some(tls)); }
+
+    /// Whether the ring's traffic is secured with mutual TLS.
+    pub fn uses_tls(&self) -> bool { self.tls.is_some() }
+
+    /// Remove the service rumors of members which have been confirmed dead for `ttl`, and the
+    /// service file rumors of service groups no member has run for `ttl`. Must be called before
+    /// the server is started.
+    pub fn expire_rumors_after(&mut self, ttl: Duration) { self.rumor_ttl = Arc::new(Some(ttl)); }
+
+    /// How long rumors of members gone from the ring are kept, if they expire at all
+    pub fn rumor_ttl(&self) -> Option<Duration> { *self.rumor_ttl }
+
+    /// Only observe the ring: keep and share the rumors of other members, but never originate a
+    /// service rumor or an election. Must be called before the server is started.
+    pub fn observe_only(&mut self) { self.observer = true; }
+
+    /// Whether this server only observes the ring.
+    pub fn is_observer(&self) -> bool { self.observer }
+
+    /// The TLS the ring is secured with, if it uses TLS rather than a ring key
+    pub fn tls(&self) -> Option<&RingTls> { (*self.tls).as_ref() }
+
     /// Stop the outbound and inbound threads from processing work.
     pub fn pause(&mut self) {
         self.pause
@@ -570,6 +801,9 @@ impl Server {
     /// Whether this server is currently paused.
     pub fn paused(&self) -> bool { self.pause.load(Ordering::Relaxed) }
 
+    /// Return the swim address we are bound to
+    pub fn swim_addr(&self) -> &SocketAddr { &self.swim_addr }
+
     /// Return the port number of the swim socket we are bound to.
     fn swim_port(&self) -> u16 { self.swim_addr.port() }
 
@@ -585,6 +819,32 @@ impl Server {
     /// Return the name of this server.
     pub fn name(&self) -> &str { &self.name }
 
+    /// The zone this server runs in, if any
+    pub fn zone(&self) -> Option<&str> { self.zone.as_deref() }
+
+    /// Whether reaching `member` crosses the link between this server's zone and another
+    pub fn is_cross_zone(&self, member: &Member) -> bool { !member.in_zone(self.zone()) }
+
+    /// Note that `member_id` sent a SWIM message at `sent_at` by its clock, which has just been
+    /// received, to estimate how far its clock is from this server's.
+    pub fn record_clock_offset(&self, member_id: &str, sent_at: SystemTime) {
+        self.clock_offsets
+            .record(member_id, sent_at, SystemTime::now(), Instant::now());
+    }
+
+    /// The skew of the clocks of the ring, as estimated by this server
+    pub fn clock_skew(&self) -> ClockSkew { self.clock_offsets.skew(Instant::now()) }
+
+    /// Note that `member_id` acked a direct probe in `round_trip`
+    pub fn record_round_trip(&self, member_id: &str, round_trip: Duration) {
+        self.round_trips
+            .record(member_id, round_trip, Instant::now());
+    }
+
+    /// The mean time the other members of the ring take to ack this server's probes, if any have
+    /// acked one recently
+    pub fn mean_round_trip(&self) -> Option<Duration> { self.round_trips.mean(Instant::now()) }
+
     pub fn myself(&self) -> &Myself { self.myself.as_ref() }
 
     /// Insert a member to the `MemberList`, and update its `RumorKey` appropriately.
@@ -603,6 +863,7 @@ impl Server {
             // rumor.
             if health == Health::Departed {
                 self.rumor_heat.lock_rhw().purge(&member_id);
+                self.outbound_limiter.forget(&member_id);
             }
 
             self.rumor_heat.lock_rhw().start_hot_rumor(rk);
@@ -653,11 +914,15 @@ impl Server {
 
     /// Given a membership record and some health, insert it into the Member List.
     ///
+    /// When the record says this member isn't alive, the member refutes it, and sends its service
+    /// rumors again, as the members that thought it gone may have expired them with the rumor TTL.
+    ///
     /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
     /// * `MemberList::entries` (write)
     /// * `Server::member` (write)
     /// * `RumorHeat::inner` (write)
-    fn insert_member_from_rumor_mlw_smw_rhw(&self, member: Member, mut health: Health) {
+    fn insert_member_from_rumor_rsr_mlw_smw_rhw(&self, member: Member, mut health: Health) {
         let rk: RumorKey = RumorKey::from(&member);
 
         if member.id == self.member_id()
@@ -668,6 +933,7 @@ impl Server {
                 .lock_smw()
                 .refute_incarnation(member.incarnation);
             health = Health::Alive;
+            self.reheat_own_service_rumors_rsr_rhw();
         }
 
         let member_id = member.id.clone();
@@ -675,11 +941,31 @@ impl Server {
         if self.member_list.insert_mlw(member, health) {
             if member_id != self.member_id() && health == Health::Departed {
                 self.rumor_heat.lock_rhw().purge(&member_id);
+                self.outbound_limiter.forget(&member_id);
             }
             self.rumor_heat.lock_rhw().start_hot_rumor(rk);
         }
     }
 
+    /// Start this member's service rumors hot again.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    /// * `RumorHeat::inner` (write)
+    fn reheat_own_service_rumors_rsr_rhw(&self) {
+        let member_id = self.member_id();
+        let own: Vec<RumorKey> = self.service_store
+                                     .lock_rsr()
+                                     .rumors()
+                                     .filter(|service| service.member_id == member_id)
+                                     .map(RumorKey::from)
+                                     .collect();
+        let mut rumor_heat = self.rumor_heat.lock_rhw();
+        for rk in own {
+            rumor_heat.start_hot_rumor(rk);
+        }
+    }
+
     /// Insert a service rumor into the service store.
     /// If we're adding a new service group member, we want to avoid the
     /// situation where we could lose quorum due to Confirmed but not yet
@@ -697,6 +983,14 @@ impl Server {
     /// * `MemberList::entries` (write)
     /// * `RumorHeat::inner` (write)
     pub fn insert_service_rsw_mlw_rhw(&self, service: Service) {
+        if !self.accepts_service_group(&service.service_group) {
+            return;
+        }
+        if self.observer && service.member_id == *self.member_id {
+            debug!("Not gossiping {} as this member is an observer",
+                   service.service_group);
+            return;
+        }
         Self::insert_service_impl(service,
                                   &self.service_store,
                                   &self.member_list,
@@ -747,12 +1041,31 @@ impl Server {
         }
     }
 
+    /// Service rumors are only accepted for service groups in this server's organization (or with
+    /// no organization when the server has none). This lets several organizations share a ring
+    /// without seeing each other's services.
+    fn accepts_service_group(&self, service_group: &str) -> bool {
+        let organization =
+            ServiceGroup::from_str(service_group).ok()
+                                                 .and_then(|sg| sg.org().map(String::from));
+        if organization == *self.organization {
+            true
+        } else {
+            debug!("Rejecting rumor for {}; it is not in organization {:?}",
+                   service_group, self.organization);
+            false
+        }
+    }
+
     /// Insert a service config rumor into the service store.
     ///
     /// # Locking (see locking.md)
     /// * `RumorStore::list` (write)
     /// * `RumorHeat::inner` (write)
     pub fn insert_service_config_rsw_rhw(&self, service_config: ServiceConfig) {
+        if !self.accepts_service_group(&service_config.service_group) {
+            return;
+        }
         let rk = RumorKey::from(&service_config);
         if self.service_config_store.insert_rsw(service_config) {
             self.rumor_heat.lock_rhw().start_hot_rumor(rk);
@@ -765,12 +1078,53 @@ impl Server {
     /// * `RumorStore::list` (write)
     /// * `RumorHeat::inner` (write)
     pub fn insert_service_file_rsw_rhw(&self, service_file: ServiceFile) {
+        if !self.accepts_service_group(&service_file.service_group) {
+            return;
+        }
         let rk = RumorKey::from(&service_file);
         if self.service_file_store.insert_rsw(service_file) {
             self.rumor_heat.lock_rhw().start_hot_rumor(rk);
         }
     }
 
+    /// Insert a fleet manifest rumor into the fleet manifest store.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (write)
+    /// * `RumorHeat::inner` (write)
+    pub fn insert_fleet_manifest_rsw_rhw(&self, fleet_manifest: FleetManifest) {
+        let rk = RumorKey::from(&fleet_manifest);
+        if self.fleet_manifest_store.insert_rsw(fleet_manifest) {
+            self.rumor_heat.lock_rhw().start_hot_rumor(rk);
+        }
+    }
+
+    /// The fleet manifest with the highest incarnation this member has heard of, if any.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    pub fn fleet_manifest_rsr(&self) -> Option<FleetManifest> {
+        self.fleet_manifest_store
+            .lock_rsr()
+            .get(FleetManifest::const_id())
+            .and_then(|rumors| rumors.get(FleetManifest::const_id()))
+            .cloned()
+    }
+
+    /// How many rumors of each type this server holds, and their size encoded
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    pub fn rumor_store_sizes_rsr(&self) -> Vec<(RumorType, RumorStoreSize)> {
+        vec![(RumorType::Service, self.service_store.size_rsr()),
+             (RumorType::ServiceConfig, self.service_config_store.size_rsr()),
+             (RumorType::ServiceFile, self.service_file_store.size_rsr()),
+             (RumorType::Election, self.election_store.size_rsr()),
+             (RumorType::ElectionUpdate, self.update_store.size_rsr()),
+             (RumorType::Departure, self.departure_store.size_rsr()),
+             (RumorType::FleetManifest, self.fleet_manifest_store.size_rsr()),]
+    }
+
     /// Insert a departure rumor into the departure store.
     ///
     /// # Locking (see locking.md)
@@ -864,8 +1218,16 @@ impl Server {
         has_quorum
     }
 
-    /// Start an election for the given service group, declaring this members suitability and the
-    /// term for the election.
+    /// Whether every member of the ring advertises `capability`, so that this member may use it.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    pub fn ring_supports_mlr(&self, capability: Capability) -> bool {
+        self.member_list.all_advertise_mlr(capability)
+    }
+
+    /// Start an election for the given service group, declaring this members suitability, leader
+    /// affinity and the term for the election.
     ///
     /// # Locking (see locking.md)
     /// * `RumorStore::list` (write)
@@ -873,13 +1235,23 @@ impl Server {
     /// * `RumorHeat::inner` (write)
     /// * `ManagerServices::inner` (read)
     pub fn start_election_rsw_mlr_rhw_msr(&self, service_group: &str, term: u64) {
+        if self.observer {
+            debug!("Not starting an election for {} as this member is an observer",
+                   service_group);
+            return;
+        }
         let suitability = self.suitability_lookup.suitability_for_msr(service_group);
         let has_quorum = self.check_quorum_mlr(service_group);
-        let e = Election::new(self.member_id(),
-                              service_group,
-                              term,
-                              suitability,
-                              has_quorum);
+        let mut e = Election::new(self.member_id(),
+                                  service_group,
+                                  term,
+                                  suitability,
+                                  has_quorum);
+        // Members that don't know the leader affinity would rank the candidates differently
+        if self.ring_supports_mlr(Capability::LeaderAffinity) {
+            e.affinity = self.suitability_lookup
+                             .leader_affinity_for_msr(service_group, self.mean_round_trip());
+        }
         if !has_quorum {
             warn!("start_election check_quorum failed: {:?}", e);
         }
@@ -898,6 +1270,11 @@ impl Server {
                                              service_group: &str,
                                              suitability: u64,
                                              term: u64) {
+        if self.observer {
+            debug!("Not starting an update election for {} as this member is an observer",
+                   service_group);
+            return;
+        }
         let has_quorum = self.check_quorum_mlr(service_group);
         let e = ElectionUpdate::new(self.member_id(),
                                     service_group,
@@ -919,6 +1296,7 @@ impl Server {
     /// * `MemberList::entries` (read)
     fn elections_to_restart_rsr_mlr<T>(&self,
                                        elections: &RumorStore<T>,
+                                       dampener: &ElectionDampener,
                                        feature_flags: FeatureFlag)
                                        -> Vec<(String, u64)>
         where T: Rumor + ElectionRumor + Debug
@@ -928,15 +1306,18 @@ impl Server {
                                         self.member_id(),
                                         |k| self.check_quorum_mlr(k),
                                         &self.member_list,
+                                        dampener,
                                         feature_flags,
                                         &self.data_path)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn elections_to_restart_impl<T>(elections: &RumorStore<T>,
                                     service_store: &RumorStore<Service>,
                                     myself_member_id: &str,
                                     check_quorum: impl Fn(&str) -> bool,
                                     member_list: &MemberList,
+                                    dampener: &ElectionDampener,
                                     feature_flags: FeatureFlag,
                                     data_path: &Option<PathBuf>)
                                     -> Vec<(String, u64)>
@@ -963,15 +1344,24 @@ impl Server {
                     // need to start a new one the old-fashioned way.
 
                     // If we are finished, and the leader is dead, we should restart the election
+                    // once the dampener lets us
+                    let now = Instant::now();
+                    let mut needs_restart = false;
+                    if election.is_finished() {
+                        dampener.finished(service_group, election.term(), now);
+                    }
                     if election.is_finished() && election.member_id() == myself_member_id {
                         // If we are the leader, and we have lost quorum, we should restart
                         // the election
                         if !check_quorum(election.key()) {
-                            warn!("Restarting election with a new term as the leader has lost \
-                                   quorum: {:?}",
-                                  election);
-                            elections_to_restart.push((String::from(&service_group[..]),
-                                                       election.term()));
+                            needs_restart = true;
+                            if dampener.restart_due(service_group, election.term(), now) {
+                                warn!("Restarting election with a new term as the leader has \
+                                       lost quorum: {:?}",
+                                      election);
+                                elections_to_restart.push((String::from(&service_group[..]),
+                                                           election.term()));
+                            }
                         }
                     } else if election.is_finished() {
                         let leader_health = member_list.health_of_by_id_mlr(election.member_id())
@@ -982,13 +1372,19 @@ impl Server {
                                                            Health::Departed
                                                        });
                         if leader_health >= Health::Confirmed {
-                            warn!("Restarting election with a new term as the leader is dead {}: \
-                                   {:?}",
-                                  myself_member_id, election);
-                            elections_to_restart.push((String::from(&service_group[..]),
-                                                       election.term()));
+                            needs_restart = true;
+                            if dampener.restart_due(service_group, election.term(), now) {
+                                warn!("Restarting election with a new term as the leader is dead \
+                                       {}: {:?}",
+                                      myself_member_id, election);
+                                elections_to_restart.push((String::from(&service_group[..]),
+                                                           election.term()));
+                            }
                         }
                     }
+                    if !needs_restart {
+                        dampener.cancel(service_group);
+                    }
                 }
             }
         }
@@ -1007,15 +1403,17 @@ impl Server {
     /// * `RumorHeat::inner` (write)
     /// * `ManagerServices::inner` (read)
     pub fn restart_elections_rsw_mlr_rhw_msr(&self, feature_flags: FeatureFlag) {
-        let elections_to_restart =
-            self.elections_to_restart_rsr_mlr(&self.election_store, feature_flags);
+        let elections_to_restart = self.elections_to_restart_rsr_mlr(&self.election_store,
+                                                                     &self.election_dampener,
+                                                                     feature_flags);
 
         // TODO (CM): not currently triggering update elections!
         // There's only one kind of sentinel file at the moment, and
         // that's for non-update elections. If that file existed,
         // it'll be gone by the time we get here.
-        let update_elections_to_restart =
-            self.elections_to_restart_rsr_mlr(&self.update_store, feature_flags);
+        let update_elections_to_restart = self.elections_to_restart_rsr_mlr(&self.update_store,
+                                                                            &self.update_dampener,
+                                                                            feature_flags);
 
         for (service_group, old_term) in elections_to_restart {
             let term = old_term + 1;
@@ -1045,6 +1443,9 @@ impl Server {
     /// * `ManagerServices::inner` (read)
     pub fn insert_election_rsw_mlr_rhw_msr(&self, mut election: Election) {
         debug!("insert_election: {:?}", election);
+        if !self.accepts_service_group(&election.service_group) {
+            return;
+        }
         let rk = RumorKey::from(&election);
 
         // If this is an election for a service group we care about
@@ -1141,6 +1542,9 @@ impl Server {
     /// * `RumorHeat::inner` (write)
     pub fn insert_update_election_rsw_mlr_rhw(&self, mut election: ElectionUpdate) {
         debug!("insert_update_election: {:?}", election);
+        if !self.accepts_service_group(&election.service_group) {
+            return;
+        }
         let rk = RumorKey::from(&election);
 
         // If this is an election for a service group we care about
@@ -1211,12 +1615,41 @@ impl Server {
         }
     }
 
-    fn generate_wire(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
-        message::generate_wire(payload, (*self.ring_key).as_ref())
+    /// Wrap `payload` for the wire, compressed with `compression` if it is large enough to be
+    /// worth it, and encrypted if the ring is
+    fn generate_wire(&self,
+                     payload: Vec<u8>,
+                     compression: Option<message::Compression>)
+                     -> Result<Vec<u8>> {
+        let ring_key = (*self.keyring).as_ref()
+                                      .map(|keyring| keyring.sending_key(Instant::now()));
+        message::generate_wire(payload, ring_key.as_ref(), compression)
     }
 
     fn unwrap_wire(&self, payload: &[u8]) -> Result<Vec<u8>> {
-        message::unwrap_wire(payload, (*self.ring_key).as_ref())
+        message::unwrap_wire(payload, (*self.keyring).as_ref())
+    }
+
+    /// Load the revisions of the ring key the server starts with into the keyring. They are sent
+    /// with right away, as the other members are expected to have them already. Returns whether
+    /// the revisions changed; they never do for a ring without wire encryption.
+    pub fn load_ring_keys(&self, keys: Vec<RingKey>) -> bool {
+        (*self.keyring).as_ref()
+                       .map_or(false, |keyring| keyring.load(keys))
+    }
+
+    /// Replace the revisions of the ring key in the keyring with `keys`, e.g. after revisions were
+    /// added to or removed from the key cache. Returns whether the revisions changed; they never do
+    /// for a ring without wire encryption.
+    pub fn update_ring_keys(&self, keys: Vec<RingKey>) -> bool {
+        (*self.keyring).as_ref()
+                       .map_or(false, |keyring| keyring.update(keys, Instant::now()))
+    }
+
+    /// The named revisions of the ring key in the keyring, oldest first
+    pub fn ring_key_revisions(&self) -> Vec<String> {
+        (*self.keyring).as_ref()
+                       .map_or_else(Vec::new, Keyring::revisions)
     }
 
     /// # Locking (see locking.md)
@@ -1243,6 +1676,64 @@ impl Server {
 
     #[allow(dead_code)]
     pub fn is_departed(&self) -> bool { self.departed.load(Ordering::Relaxed) }
+
+    /// The service groups whose finished election or update election this server won
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    pub fn led_service_groups_rsr(&self) -> HashSet<String> {
+        let mut led = HashSet::new();
+        for (service_group, rumors) in self.election_store.lock_rsr().iter() {
+            if rumors.get(Election::const_id())
+                     .map_or(false, |e| e.is_finished() && e.member_id == *self.member_id)
+            {
+                led.insert(service_group.clone());
+            }
+        }
+        for (service_group, rumors) in self.update_store.lock_rsr().iter() {
+            if rumors.get(ElectionUpdate::const_id())
+                     .map_or(false, |e| e.is_finished() && e.member_id == *self.member_id)
+            {
+                led.insert(service_group.clone());
+            }
+        }
+        led
+    }
+
+    /// The IDs of the members at `host`, an IP address or a hostname, sorted. A member matches
+    /// when the address it gossips from is `host` or one of `addresses`, which `host` resolves
+    /// to, or when any of its services report `host` as their hostname or IP address, which still
+    /// finds members whose hostname no longer resolves. Resolving `host` is left to the caller, so
+    /// that no lookup is made with the locks held.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    /// * `MemberList::entries` (read)
+    pub fn member_ids_at_rsr_mlr(&self, host: &str, addresses: &[IpAddr]) -> Vec<String> {
+        let mut member_ids = HashSet::new();
+        self.member_list
+            .with_memberships_mlr(|Membership { member, .. }| {
+                let at_host = member.address == host
+                              || member.address
+                                       .parse()
+                                       .map_or(false, |ip: IpAddr| addresses.contains(&ip));
+                if at_host {
+                    member_ids.insert(member.id);
+                }
+                Ok(())
+            })
+            .ok();
+        for rumors in self.service_store.lock_rsr().values() {
+            for service in rumors.values() {
+                if service.sys.hostname.eq_ignore_ascii_case(host) || service.sys.ip == host {
+                    member_ids.insert(service.member_id.clone());
+                }
+            }
+        }
+        let mut member_ids = member_ids.into_iter().collect::<Vec<_>>();
+        member_ids.sort();
+        member_ids
+    }
 }
 
 impl fmt::Display for Server {
@@ -1285,6 +1776,20 @@ fn persist_loop(server: &Server) -> ! {
     }
 }
 
+/// Bind the SWIM socket. A socket bound to the unspecified IPv6 address (`[::]`) is made
+/// dual-stack, accepting IPv4 traffic as well, whatever the platform's default is.
+fn bind_swim_socket(addr: SocketAddr) -> io::Result<UdpSocket> {
+    match addr {
+        SocketAddr::V6(v6) if v6.ip().is_unspecified() => {
+            let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+            socket.set_only_v6(false)?;
+            socket.bind(&addr.into())?;
+            Ok(socket.into())
+        }
+        _ => UdpSocket::bind(addr),
+    }
+}
+
 /// There is a bug which surfaced in rust 1.38 where cloned sockets on windows
 /// get inherited by child processes and remain open even after the process that
 /// created the socket terminates as long as the child processes remain alive.
@@ -1335,6 +1840,7 @@ impl<'a> Serialize for ServerProxy<'a> {
         let eusp = RumorStoreProxy::new(&self.0.update_store);
         let scsp = RumorStoreProxy::new(&self.0.service_config_store);
         let sfsp = RumorStoreProxy::new(&self.0.service_file_store);
+        let fmsp = RumorStoreProxy::new(&self.0.fleet_manifest_store);
         let mlp = MemberListProxy::new(&self.0.member_list);
 
         let mut strukt = serializer.serialize_struct("butterfly_server", 7)?;
@@ -1352,6 +1858,59 @@ impl<'a> Serialize for ServerProxy<'a> {
         strukt.serialize_field("latest_election_update", &eusp)?;
         strukt.serialize_field("departure", &self.0.departure_store)?;
         strukt.serialize_field("departed_members", &dsp)?;
+        strukt.serialize_field("latest_fleet_manifest", &fmsp)?;
+        strukt.serialize_field("clock_skew", &self.0.clock_skew())?;
+        strukt.end()
+    }
+}
+
+/// This proxy presents everything the Server knows about the ring for the Supervisor's
+/// /butterfly/debug HTTP API endpoint: each member with its health and incarnation, and the full
+/// contents of each rumor store, keyed by rumor type. Unlike `ServerProxy`, it makes no promises
+/// about the shape of its output, which follows the internal representation of the stores.
+pub struct ServerDebugProxy<'a>(&'a Server);
+
+impl<'a> ServerDebugProxy<'a> {
+    pub fn new(s: &'a Server) -> Self { ServerDebugProxy(s) }
+}
+
+impl<'a> Serialize for ServerDebugProxy<'a> {
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    /// * `RumorStore::list` (read)
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let mlp = MemberListDebugProxy::new(&self.0.member_list);
+
+        let mut strukt = serializer.serialize_struct("butterfly_debug", 7)?;
+        strukt.serialize_field("member_id", self.0.member_id())?;
+        strukt.serialize_field("swim_addr", self.0.swim_addr())?;
+        strukt.serialize_field("gossip_addr", self.0.gossip_addr())?;
+        strukt.serialize_field("departed", &self.0.is_departed())?;
+        strukt.serialize_field("member_list", &mlp)?;
+        strukt.serialize_field("rumors", &RumorStoresDebugProxy(self.0))?;
+        strukt.serialize_field("clock_skew", &self.0.clock_skew())?;
+        strukt.end()
+    }
+}
+
+struct RumorStoresDebugProxy<'a>(&'a Server);
+
+impl<'a> Serialize for RumorStoresDebugProxy<'a> {
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let mut strukt = serializer.serialize_struct("rumors", 7)?;
+        strukt.serialize_field("departure", &self.0.departure_store)?;
+        strukt.serialize_field("election", &self.0.election_store)?;
+        strukt.serialize_field("election_update", &self.0.update_store)?;
+        strukt.serialize_field("fleet_manifest", &self.0.fleet_manifest_store)?;
+        strukt.serialize_field("service", &self.0.service_store)?;
+        strukt.serialize_field("service_config", &self.0.service_config_store)?;
+        strukt.serialize_field("service_file", &self.0.service_file_store)?;
         strukt.end()
     }
 }
@@ -1428,6 +1987,10 @@ mod tests {
 
     fn check_quorum_returns(val: bool) -> impl Fn(&str) -> bool { move |_: &str| val }
 
+    fn undampened() -> ElectionDampener {
+        ElectionDampener::new(Duration::from_secs(0), Duration::from_millis(0))
+    }
+
     fn mock_service(member: &Member) -> Service {
         Service { member_id:     member.id.clone(),
                   service_group: ServiceGroup::from_str("group.default").unwrap(),
@@ -1435,7 +1998,9 @@ mod tests {
                   initialized:   Default::default(),
                   pkg:           Default::default(),
                   cfg:           Default::default(),
-                  sys:           Default::default(), }
+                  sys:           Default::default(),
+                  maintenance:   Default::default(),
+                  unload:        Default::default(), }
     }
 
     #[test]
@@ -1465,6 +2030,7 @@ mod tests {
                                                            &myself.id,
                                                            check_quorum_returns(true),
                                                            &member_list,
+                                                           &undampened(),
                                                            FeatureFlag::empty(),
                                                            &None);
 
@@ -1500,12 +2066,46 @@ mod tests {
                                                            &myself.id,
                                                            check_quorum_returns(true),
                                                            &member_list,
+                                                           &undampened(),
                                                            FeatureFlag::empty(),
                                                            &None);
 
         assert_eq!(to_restart, vec![(service.service_group.to_string(), term)]);
     }
 
+    #[test]
+    fn elections_are_not_restarted_during_the_leaders_minimum_term() {
+        env_logger::try_init().ok();
+        let elections = RumorStore::<Election>::default();
+        let service_store = RumorStore::<Service>::default();
+        let myself = Member::default();
+        let departed_leader = Member::default();
+        let member_list = MemberList::new();
+        let service = mock_service(&myself);
+        let dampener = ElectionDampener::new(Duration::from_secs(60), Duration::from_millis(0));
+
+        let mut election = Election::new(departed_leader.id.clone(),
+                                         &service.service_group,
+                                         Term::default(),
+                                         1,
+                                         true /* has_quorum */);
+        election.finish();
+        elections.insert_rsw(election);
+        service_store.insert_rsw(service);
+        member_list.insert_mlw(departed_leader, Health::Departed);
+
+        let to_restart = Server::elections_to_restart_impl(&elections,
+                                                           &service_store,
+                                                           &myself.id,
+                                                           check_quorum_returns(true),
+                                                           &member_list,
+                                                           &dampener,
+                                                           FeatureFlag::empty(),
+                                                           &None);
+
+        assert!(to_restart.is_empty());
+    }
+
     #[test]
     fn insert_service_adds_service_to_service_store() {
         let service = mock_service(&Member::default());
@@ -1679,6 +2279,7 @@ mod tests {
     mod server {
         use super::*;
         use crate::{member::Member,
+                    rumor::heat,
                     server::{timing::Timing,
                              Server,
                              Suitability}};
@@ -1701,7 +2302,9 @@ mod tests {
             fn suitability_for_msr(&self, _service_group: &str) -> u64 { 0 }
         }
 
-        fn start_server() -> Server {
+        fn start_server() -> Server { start_server_in_organization(None) }
+
+        fn start_server_in_organization(organization: Option<&str>) -> Server {
             let swim_port;
             {
                 let mut swim_port_guard = SWIM_PORT.lock().expect("SWIM_PORT poisoned");
@@ -1723,6 +2326,7 @@ mod tests {
                         gossip_listen,
                         member,
                         None,
+                        organization.map(String::from),
                         None,
                         None,
                         Arc::new(ZeroSuitability)).unwrap()
@@ -1755,6 +2359,7 @@ mod tests {
                         member,
                         None,
                         None,
+                        None,
                         Some(tmpdir.path()),
                         Arc::new(ZeroSuitability)).unwrap()
         }
@@ -1771,6 +2376,82 @@ mod tests {
                   .expect("Server failed to start");
         }
 
+        #[test]
+        fn service_rumors_are_scoped_to_the_organization() {
+            let server = start_server_in_organization(Some("acme"));
+            let member = server.myself().lock_smr().to_member();
+
+            let mut service = mock_service(&member);
+            service.service_group = ServiceGroup::from_str("group.default@other").unwrap();
+            server.insert_service_rsw_mlw_rhw(service);
+            assert_eq!(server.service_store.lock_rsr().rumors().count(), 0);
+
+            let mut service = mock_service(&member);
+            service.service_group = ServiceGroup::from_str("group.default").unwrap();
+            server.insert_service_rsw_mlw_rhw(service);
+            assert_eq!(server.service_store.lock_rsr().rumors().count(), 0);
+
+            let mut service = mock_service(&member);
+            service.service_group = ServiceGroup::from_str("group.default@acme").unwrap();
+            server.insert_service_rsw_mlw_rhw(service);
+            assert_eq!(server.service_store.lock_rsr().rumors().count(), 1);
+        }
+
+        #[test]
+        fn observers_only_keep_the_rumors_of_other_members() {
+            let mut server = start_server();
+            server.observe_only();
+            let member = server.myself().lock_smr().to_member();
+
+            server.insert_service_rsw_mlw_rhw(mock_service(&member));
+            assert_eq!(server.service_store.lock_rsr().rumors().count(), 0);
+
+            let other = Member { id: "other".to_string(),
+                                 ..Member::default() };
+            server.insert_service_rsw_mlw_rhw(mock_service(&other));
+            assert_eq!(server.service_store.lock_rsr().rumors().count(), 1);
+
+            server.start_election_rsw_mlr_rhw_msr("group.default", 0);
+            assert_eq!(server.election_store.lock_rsr().rumors().count(), 0);
+        }
+
+        #[test]
+        fn members_that_refute_their_death_send_their_service_rumors_again() {
+            let server = start_server();
+            let member = server.myself().lock_smr().to_member();
+            let service = mock_service(&member);
+            server.insert_service_rsw_mlw_rhw(service.clone());
+            heat::sync::tests::cool_rumor_completely_rhw(&server.rumor_heat, "other", &service);
+            heat::sync::tests::assert_rumor_is_cold_rhr(&server.rumor_heat, "other", &service);
+
+            server.insert_member_from_rumor_rsr_mlw_smw_rhw(member, Health::Confirmed);
+            heat::sync::tests::assert_rumor_is_hot_rhr(&server.rumor_heat, "other", &service);
+        }
+
+        #[test]
+        fn members_are_found_by_address_or_hostname() {
+            let server = start_server();
+            let by_address = Member { id: "by-address".to_string(),
+                                      address: "10.0.4.7".to_string(),
+                                      ..Member::default() };
+            server.insert_member_mlw_rhw(by_address, Health::Confirmed);
+            let by_hostname = Member { id: "by-hostname".to_string(),
+                                       address: "10.0.4.8".to_string(),
+                                       ..Member::default() };
+            let mut service = mock_service(&by_hostname);
+            service.sys.hostname = "db-1.example.com".to_string();
+            server.insert_member_mlw_rhw(by_hostname, Health::Confirmed);
+            server.insert_service_rsw_mlw_rhw(service);
+
+            assert_eq!(server.member_ids_at_rsr_mlr("10.0.4.7", &[]),
+                       vec!["by-address"]);
+            assert_eq!(server.member_ids_at_rsr_mlr("db-1", &["10.0.4.7".parse().unwrap()]),
+                       vec!["by-address"]);
+            assert_eq!(server.member_ids_at_rsr_mlr("DB-1.example.com", &[]),
+                       vec!["by-hostname"]);
+            assert!(server.member_ids_at_rsr_mlr("10.0.4.9", &[]).is_empty());
+        }
+
         #[test]
         fn start_listener() {
             let mut server = start_server();
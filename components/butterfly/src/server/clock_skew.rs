@@ -0,0 +1,144 @@
+//! Estimates how far the clocks of the other members of the ring are from this member's, from the
+//! time each SWIM message says it was sent at, so that skew across the ring can be reported before
+//! it shows up as confusing symptoms in the logic that relies on clocks agreeing, such as the TTLs
+//! of rumors.
+//!
+//! An estimate is only as accurate as the time a message takes to arrive, which is usually a few
+//! milliseconds. The estimates of members that have not been heard from for `ESTIMATE_TTL` are
+//! dropped.
+
+use std::{collections::{BTreeMap,
+                        HashMap},
+          sync::Mutex,
+          time::{Duration,
+                 Instant,
+                 SystemTime}};
+
+habitat_core::env_config_duration!(
+    /// How far apart the clocks of the ring can be before they are reported as skewed
+    ClockSkewThreshold,
+    HAB_CLOCK_SKEW_THRESHOLD_SECS => from_secs,
+    Duration::from_secs(30));
+
+const ESTIMATE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// The skew of the clocks of the ring, as estimated by this member
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ClockSkew {
+    /// How far apart the fastest and the slowest clock of the ring are, in milliseconds
+    pub spread_ms:         u64,
+    pub threshold_ms:      u64,
+    pub exceeds_threshold: bool,
+    /// How far ahead of this member's clock each other member's clock is, in milliseconds. It is
+    /// negative for the clocks that are behind.
+    pub offsets_ms:        BTreeMap<String, i64>,
+}
+
+impl ClockSkew {
+    /// The member whose clock is furthest from this member's, and its offset
+    pub fn furthest(&self) -> Option<(&str, i64)> {
+        self.offsets_ms
+            .iter()
+            .max_by_key(|(_, offset)| offset.abs())
+            .map(|(member_id, offset)| (member_id.as_str(), *offset))
+    }
+}
+
+#[derive(Debug)]
+pub struct ClockOffsets {
+    threshold: Duration,
+    /// The latest offset estimate of each member, and when it was made
+    offsets:   Mutex<HashMap<String, (i64, Instant)>>,
+}
+
+impl Default for ClockOffsets {
+    fn default() -> Self { Self::new(ClockSkewThreshold::configured_value().into()) }
+}
+
+impl ClockOffsets {
+    pub fn new(threshold: Duration) -> Self {
+        ClockOffsets { threshold,
+                       offsets: Mutex::default() }
+    }
+
+    /// Estimate the offset of `member_id`'s clock from a message it sent at `sent_at` by its
+    /// clock, which was received at `received_at` by this member's
+    pub fn record(&self,
+                  member_id: &str,
+                  sent_at: SystemTime,
+                  received_at: SystemTime,
+                  now: Instant) {
+        let offset = match sent_at.duration_since(received_at) {
+            Ok(ahead) => ahead.as_millis() as i64,
+            Err(behind) => -(behind.duration().as_millis() as i64),
+        };
+        self.offsets
+            .lock()
+            .expect("Clock offsets lock poisoned")
+            .insert(member_id.to_string(), (offset, now));
+    }
+
+    pub fn skew(&self, now: Instant) -> ClockSkew {
+        let mut offsets = self.offsets.lock().expect("Clock offsets lock poisoned");
+        offsets.retain(|_, (_, estimated_at)| now.duration_since(*estimated_at) < ESTIMATE_TTL);
+        let offsets_ms: BTreeMap<String, i64> =
+            offsets.iter()
+                   .map(|(member_id, (offset, _))| (member_id.clone(), *offset))
+                   .collect();
+        // This member's own clock is at an offset of zero
+        let fastest = offsets_ms.values().copied().max().unwrap_or(0).max(0);
+        let slowest = offsets_ms.values().copied().min().unwrap_or(0).min(0);
+        let spread_ms = (fastest - slowest) as u64;
+        let threshold_ms = self.threshold.as_millis() as u64;
+        ClockSkew { spread_ms,
+                    threshold_ms,
+                    exceeds_threshold: spread_ms > threshold_ms,
+                    offsets_ms }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skew_is_the_spread_of_the_ring_including_this_member() {
+        let offsets = ClockOffsets::new(Duration::from_secs(30));
+        let now = Instant::now();
+        let received_at = SystemTime::now();
+        offsets.record("ahead",
+                       received_at + Duration::from_secs(20),
+                       received_at,
+                       now);
+        offsets.record("behind",
+                       received_at - Duration::from_secs(15),
+                       received_at,
+                       now);
+
+        let skew = offsets.skew(now);
+        assert_eq!(skew.spread_ms, 35_000);
+        assert!(skew.exceeds_threshold);
+        assert_eq!(skew.offsets_ms["behind"], -15_000);
+        assert_eq!(skew.furthest(), Some(("ahead", 20_000)));
+
+        offsets.record("behind", received_at, received_at, now);
+        let skew = offsets.skew(now);
+        assert_eq!(skew.spread_ms, 20_000);
+        assert!(!skew.exceeds_threshold);
+    }
+
+    #[test]
+    fn stale_estimates_are_dropped() {
+        let offsets = ClockOffsets::new(Duration::from_secs(30));
+        let now = Instant::now();
+        let received_at = SystemTime::now();
+        offsets.record("ahead",
+                       received_at + Duration::from_secs(60),
+                       received_at,
+                       now);
+        assert!(offsets.skew(now).exceeds_threshold);
+        assert_eq!(offsets.skew(now + ESTIMATE_TTL),
+                   ClockSkew { threshold_ms: 30_000,
+                               ..ClockSkew::default() });
+    }
+}
@@ -0,0 +1,194 @@
+//! Periodically compact the rumor stores, so that a long-lived ring does not keep rumors that
+//! nothing refers to anymore, in memory or in its rumor file. Compaction removes:
+//!
+//! * The service rumors of departed members, which will never run their services again
+//! * The service rumors of members which have been confirmed dead for the server's rumor TTL, if it
+//!   has one. A member that comes back refutes its death and sends its service rumors again.
+//! * The service file, election and update rumors of service groups that no member has run for
+//!   `RumorCompactionRetention`, or for the rumor TTL if that is shorter
+//!
+//! The service config rumor of a service group is never removed: it is the group's latest applied
+//! configuration, which members that run the group again need.
+//!
+//! The size of each rumor store is published as a metric after every compaction.
+
+use crate::{rumor::{Rumor,
+                    RumorType},
+            server::Server};
+use habitat_common::liveliness_checker;
+use habitat_core::util::ToI64;
+use prometheus::{IntCounterVec,
+                 IntGaugeVec};
+use std::{collections::{HashMap,
+                        HashSet},
+          thread,
+          time::{Duration,
+                 Instant}};
+
+const LOOP_DELAY: Duration = Duration::from_secs(10);
+
+habitat_core::env_config_duration!(
+    /// How often the rumor stores are compacted
+    RumorCompactionInterval,
+    HAB_RUMOR_COMPACTION_INTERVAL_SECS => from_secs,
+    Duration::from_secs(5 * 60));
+
+habitat_core::env_config_duration!(
+    /// How long no member has to run a service group before its service file, election and update
+    /// rumors are removed
+    RumorCompactionRetention,
+    HAB_RUMOR_COMPACTION_RETENTION_SECS => from_secs,
+    Duration::from_secs(60 * 60 * 24 * 7));
+
+lazy_static! {
+    static ref RUMOR_STORE_RUMORS: IntGaugeVec =
+        register_int_gauge_vec!("hab_butterfly_rumor_store_rumors",
+                                "Number of rumors held in the rumor store",
+                                &["rumor"]).unwrap();
+    static ref RUMOR_STORE_BYTES: IntGaugeVec =
+        register_int_gauge_vec!("hab_butterfly_rumor_store_bytes",
+                                "Encoded size of the rumors held in the rumor store in bytes",
+                                &["rumor"]).unwrap();
+    static ref COMPACTED_RUMORS: IntCounterVec =
+        register_int_counter_vec!("hab_butterfly_compacted_rumors_total",
+                                  "Total number of rumors removed by compaction",
+                                  &["rumor"]).unwrap();
+}
+
+pub fn spawn_thread(name: String, server: Server) -> std::io::Result<()> {
+    thread::Builder::new().name(name)
+                          .spawn(move || -> ! { run_loop(&server) })
+                          .map(|_| ())
+}
+
+fn run_loop(server: &Server) -> ! {
+    let mut orphans = Orphans::default();
+    let mut last_compaction: Option<Instant> = None;
+    loop {
+        liveliness_checker::mark_thread_alive().and_divergent();
+
+        let interval = RumorCompactionInterval::configured_value().into();
+        if last_compaction.map_or(true, |last| last.elapsed() >= interval) {
+            let retention: Duration = RumorCompactionRetention::configured_value().into();
+            let retention = server.rumor_ttl()
+                                  .map_or(retention, |ttl| ttl.min(retention));
+            compact_rsw_mlr(server, &mut orphans, retention);
+            last_compaction = Some(Instant::now());
+        }
+
+        thread::sleep(LOOP_DELAY);
+    }
+}
+
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (write)
+/// * `MemberList::entries` (read)
+fn compact_rsw_mlr(server: &Server, orphans: &mut Orphans, retention: Duration) {
+    let gone = server.member_list.members_gone_mlr(server.rumor_ttl());
+    if !gone.is_empty() {
+        let removed = server.service_store
+                            .retain_rsw(|service| !gone.contains(service.id()));
+        record_compaction(RumorType::Service, removed);
+    }
+
+    let running: HashSet<String> = server.service_store.lock_rsr().keys().cloned().collect();
+    let mut groups = HashSet::new();
+    groups.extend(server.service_file_store.lock_rsr().keys().cloned());
+    groups.extend(server.election_store.lock_rsr().keys().cloned());
+    groups.extend(server.update_store.lock_rsr().keys().cloned());
+    let expired = orphans.expired(groups, &running, Instant::now(), retention);
+    if !expired.is_empty() {
+        record_compaction(RumorType::ServiceFile,
+                          server.service_file_store
+                                .retain_rsw(|r| !expired.contains(r.key())));
+        record_compaction(RumorType::Election,
+                          server.election_store
+                                .retain_rsw(|r| !expired.contains(r.key())));
+        record_compaction(RumorType::ElectionUpdate,
+                          server.update_store
+                                .retain_rsw(|r| !expired.contains(r.key())));
+    }
+
+    for (kind, size) in server.rumor_store_sizes_rsr() {
+        let label = kind.to_string();
+        RUMOR_STORE_RUMORS.with_label_values(&[&label])
+                          .set(size.rumors.to_i64());
+        RUMOR_STORE_BYTES.with_label_values(&[&label])
+                         .set(size.bytes.to_i64());
+    }
+}
+
+fn record_compaction(kind: RumorType, removed: usize) {
+    if removed > 0 {
+        debug!("Compaction removed {} {} rumors", removed, kind);
+        COMPACTED_RUMORS.with_label_values(&[&kind.to_string()])
+                        .inc_by(removed as u64);
+    }
+}
+
+/// When each service group that has rumors but that no member runs was first found that way
+#[derive(Debug, Default)]
+struct Orphans(HashMap<String, Instant>);
+
+impl Orphans {
+    /// Of `groups`, the service groups with rumors, return those that no member has run for at
+    /// least `retention`, given the groups in `running`.
+    fn expired(&mut self,
+               groups: HashSet<String>,
+               running: &HashSet<String>,
+               now: Instant,
+               retention: Duration)
+               -> HashSet<String> {
+        self.0
+            .retain(|group, _| groups.contains(group) && !running.contains(group));
+        groups.into_iter()
+              .filter(|group| !running.contains(group))
+              .filter(|group| {
+                  let orphaned_at = *self.0.entry(group.clone()).or_insert(now);
+                  now.duration_since(orphaned_at) >= retention
+              })
+              .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups(names: &[&str]) -> HashSet<String> { names.iter().map(|s| s.to_string()).collect() }
+
+    #[test]
+    fn service_groups_expire_once_unrun_for_the_retention() {
+        let mut orphans = Orphans::default();
+        let retention = Duration::from_secs(60);
+        let start = Instant::now();
+        let all = groups(&["redis.default", "nginx.default"]);
+        let running = groups(&["redis.default"]);
+
+        assert!(orphans.expired(all.clone(), &running, start, retention)
+                       .is_empty());
+        assert!(orphans.expired(all.clone(),
+                                &running,
+                                start + Duration::from_secs(30),
+                                retention)
+                       .is_empty());
+        assert_eq!(orphans.expired(all, &running, start + retention, retention),
+                   groups(&["nginx.default"]));
+    }
+
+    #[test]
+    fn service_groups_that_run_again_start_over() {
+        let mut orphans = Orphans::default();
+        let retention = Duration::from_secs(60);
+        let start = Instant::now();
+        let all = groups(&["nginx.default"]);
+
+        orphans.expired(all.clone(), &HashSet::new(), start, retention);
+        orphans.expired(all.clone(),
+                        &all,
+                        start + Duration::from_secs(30),
+                        retention);
+        assert!(orphans.expired(all, &HashSet::new(), start + retention, retention)
+                       .is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+//! The members a Supervisor refuses all SWIM and gossip traffic from, by member ID or by the
+//! network their address is in.
+//!
+//! A departed member can rejoin the ring under a new member ID, and a member that was merely
+//! blocked is still gossiped about by the rest of the ring. A denied member is departed when it is
+//! denied, and is refused under any member ID while its address is in a denied network.
+
+use crate::member::Member;
+pub use habitat_common::types::DenylistEntry;
+use std::{collections::BTreeSet,
+          net::IpAddr};
+
+#[derive(Debug, Default)]
+pub struct Denylist {
+    entries: BTreeSet<DenylistEntry>,
+}
+
+impl Denylist {
+    /// Returns whether the entry was not already on the denylist.
+    pub fn insert(&mut self, entry: DenylistEntry) -> bool { self.entries.insert(entry) }
+
+    /// Returns whether the entry was on the denylist.
+    pub fn remove(&mut self, entry: &DenylistEntry) -> bool { self.entries.remove(entry) }
+
+    pub fn entries(&self) -> Vec<DenylistEntry> { self.entries.iter().cloned().collect() }
+
+    /// Whether traffic from `member_id`, received from or advertised as `address`, is refused.
+    pub fn denies(&self, member_id: &str, address: Option<IpAddr>) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.matches(member_id, address))
+    }
+
+    /// Whether `member` is refused, by its ID or by the address it advertises.
+    pub fn denies_member(&self, member: &Member) -> bool {
+        self.denies(&member.id, member.address.parse().ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(id: &str, address: &str) -> Member {
+        Member { id: id.to_string(),
+                 address: address.to_string(),
+                 ..Member::default() }
+    }
+
+    #[test]
+    fn members_are_denied_by_id_or_network() {
+        let mut denylist = Denylist::default();
+        assert!(denylist.insert("bad".parse().unwrap()));
+        assert!(denylist.insert("10.0.4.0/24".parse().unwrap()));
+        assert!(!denylist.insert("bad".parse().unwrap()));
+
+        assert!(denylist.denies_member(&member("bad", "10.0.5.1")));
+        assert!(denylist.denies_member(&member("good", "10.0.4.1")));
+        assert!(!denylist.denies_member(&member("good", "10.0.5.1")));
+        assert!(!denylist.denies_member(&member("good", "")));
+    }
+
+    #[test]
+    fn removed_entries_no_longer_deny() {
+        let mut denylist = Denylist::default();
+        let entry: DenylistEntry = "10.0.4.0/24".parse().unwrap();
+        denylist.insert(entry.clone());
+        assert!(denylist.remove(&entry));
+        assert!(!denylist.remove(&entry));
+        assert!(!denylist.denies("anyone", Some("10.0.4.1".parse().unwrap())));
+        assert!(denylist.entries().is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+//! Dampens the elections this member starts when the leader of a service group dies or loses
+//! quorum, to stop election storms on networks where members flap between alive and dead.
+//!
+//! A leader keeps its term for at least `HAB_ELECTION_MIN_TERM_SECS` before this member starts a
+//! new election over it, and a new election is started only after a random delay of up to
+//! `HAB_ELECTION_JITTER_MS`, so that the members of a group do not all start one at once. Elections
+//! triggered manually are not dampened.
+
+use rand::Rng;
+use std::{collections::HashMap,
+          sync::Mutex,
+          time::{Duration,
+                 Instant}};
+
+habitat_core::env_config_duration!(
+    /// The minimum time that a leader keeps its term before a new election can be started
+    /// because it died or lost quorum
+    ElectionMinTerm,
+    HAB_ELECTION_MIN_TERM_SECS => from_secs,
+    Duration::from_secs(0));
+
+habitat_core::env_config_duration!(
+    /// The longest that a member waits before starting a new election
+    ElectionJitter,
+    HAB_ELECTION_JITTER_MS => from_millis,
+    Duration::from_millis(0));
+
+/// What this member knows of the term of a service group's election
+#[derive(Debug)]
+struct TermState {
+    term:        u64,
+    /// When this member first saw the election of the term finished
+    finished_at: Option<Instant>,
+    /// When this member is due to start a new election, if it wants to
+    restart_at:  Option<Instant>,
+}
+
+#[derive(Debug)]
+pub struct ElectionDampener {
+    min_term: Duration,
+    jitter:   Duration,
+    terms:    Mutex<HashMap<String, TermState>>,
+}
+
+impl Default for ElectionDampener {
+    fn default() -> Self {
+        Self::new(ElectionMinTerm::configured_value().into(),
+                  ElectionJitter::configured_value().into())
+    }
+}
+
+impl ElectionDampener {
+    pub fn new(min_term: Duration, jitter: Duration) -> Self {
+        ElectionDampener { min_term,
+                           jitter,
+                           terms: Mutex::default() }
+    }
+
+    /// Note that the election of `service_group` in `term` is finished, which starts the
+    /// leader's term as far as this member is concerned
+    pub fn finished(&self, service_group: &str, term: u64, now: Instant) {
+        let mut terms = self.terms.lock().expect("Election dampener lock poisoned");
+        let state = Self::state(&mut terms, service_group, term);
+        state.finished_at.get_or_insert(now);
+    }
+
+    /// Whether a new election of `service_group`, whose election in `term` needs one, is to be
+    /// started now. The first time it is asked, a start is scheduled after the leader's minimum
+    /// term and a random delay, and the answer is yes once that time comes.
+    pub fn restart_due(&self, service_group: &str, term: u64, now: Instant) -> bool {
+        let mut terms = self.terms.lock().expect("Election dampener lock poisoned");
+        let state = Self::state(&mut terms, service_group, term);
+        let earliest = state.finished_at
+                            .map_or(now, |at| (at + self.min_term).max(now));
+        let jitter = self.jitter;
+        let restart_at = *state.restart_at
+                               .get_or_insert_with(|| earliest + random_delay(jitter));
+        now >= restart_at
+    }
+
+    /// Cancel the new election scheduled for `service_group`, whose election no longer needs one
+    pub fn cancel(&self, service_group: &str) {
+        if let Some(state) = self.terms
+                                 .lock()
+                                 .expect("Election dampener lock poisoned")
+                                 .get_mut(service_group)
+        {
+            state.restart_at = None;
+        }
+    }
+
+    fn state<'a>(terms: &'a mut HashMap<String, TermState>,
+                 service_group: &str,
+                 term: u64)
+                 -> &'a mut TermState {
+        let state = terms.entry(service_group.to_string())
+                         .or_insert(TermState { term,
+                                                finished_at: None,
+                                                restart_at: None });
+        if state.term != term {
+            *state = TermState { term,
+                                 finished_at: None,
+                                 restart_at: None };
+        }
+        state
+    }
+}
+
+fn random_delay(jitter: Duration) -> Duration {
+    if jitter == Duration::from_millis(0) {
+        jitter
+    } else {
+        Duration::from_millis(rand::thread_rng().gen_range(0..=jitter.as_millis() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undampened_elections_restart_at_once() {
+        let dampener = ElectionDampener::new(Duration::from_secs(0), Duration::from_millis(0));
+        let now = Instant::now();
+        dampener.finished("group.default", 1, now);
+        assert!(dampener.restart_due("group.default", 1, now));
+    }
+
+    #[test]
+    fn elections_restart_after_the_minimum_term() {
+        let dampener = ElectionDampener::new(Duration::from_secs(60), Duration::from_millis(0));
+        let start = Instant::now();
+        dampener.finished("group.default", 1, start);
+        assert!(!dampener.restart_due("group.default", 1, start + Duration::from_secs(10)));
+        assert!(!dampener.restart_due("group.default", 1, start + Duration::from_secs(59)));
+        assert!(dampener.restart_due("group.default", 1, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn elections_restart_within_the_jitter() {
+        let dampener = ElectionDampener::new(Duration::from_secs(0), Duration::from_secs(5));
+        let start = Instant::now();
+        dampener.finished("group.default", 1, start);
+        dampener.restart_due("group.default", 1, start);
+        assert!(dampener.restart_due("group.default", 1, start + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn a_new_term_starts_a_new_minimum_term() {
+        let dampener = ElectionDampener::new(Duration::from_secs(60), Duration::from_millis(0));
+        let start = Instant::now();
+        dampener.finished("group.default", 1, start);
+        let later = start + Duration::from_secs(100);
+        dampener.finished("group.default", 2, later);
+        assert!(!dampener.restart_due("group.default", 2, later));
+        assert!(dampener.restart_due("group.default", 2, later + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn random_delays_are_within_the_jitter() {
+        for _ in 0..100 {
+            assert!(random_delay(Duration::from_millis(50)) <= Duration::from_millis(50));
+        }
+        assert_eq!(random_delay(Duration::from_millis(0)),
+                   Duration::from_millis(0));
+    }
+}
@@ -3,19 +3,24 @@
 //! This module handles all the inbound SWIM messages.
 
 use super::AckSender;
-use crate::{member::Health,
+use crate::{member::{self,
+                     Health},
             server::{outbound,
-                     Server},
+                     Server,
+                     BANDWIDTH},
             swim::{Ack,
                    Ping,
                    PingReq,
                    Swim,
-                   SwimKind}};
+                   SwimKind},
+            tls::{self,
+                  ServerStream}};
 use habitat_common::liveliness_checker;
 use habitat_core::util::ToI64;
 use prometheus::{IntCounterVec,
                  IntGaugeVec};
-use std::{net::{SocketAddr,
+use std::{io,
+          net::{SocketAddr,
                 UdpSocket},
           thread,
           time::Duration};
@@ -56,70 +61,15 @@ pub fn run_loop(server: &Server, socket: &UdpSocket, tx_outbound: &AckSender) ->
 
         match socket.recv_from(&mut recv_buffer[..]) {
             Ok((length, addr)) => {
-                let swim_payload = match server.unwrap_wire(&recv_buffer[0..length]) {
-                    Ok(swim_payload) => swim_payload,
-                    Err(e) => {
-                        // NOTE: In the future, we might want to block people who send us
-                        // garbage all the time.
-                        error!("Error unwrapping protocol message, {}", e);
-                        let label_values = &["unwrap_wire", "failure"];
-                        SWIM_BYTES_RECEIVED.with_label_values(label_values)
-                                           .set(length.to_i64());
-                        SWIM_MESSAGES_RECEIVED.with_label_values(label_values).inc();
-                        continue;
-                    }
-                };
-
-                let bytes_received = swim_payload.len();
-                let msg = match Swim::decode(&swim_payload) {
-                    Ok(msg) => msg,
-                    Err(e) => {
-                        // NOTE: In the future, we might want to block people who send us
-                        // garbage all the time.
-                        error!("Error decoding protocol message, {}", e);
-                        let label_values = &["undecodable", "failure"];
-                        SWIM_BYTES_RECEIVED.with_label_values(label_values)
-                                           .set(bytes_received.to_i64());
-                        SWIM_MESSAGES_RECEIVED.with_label_values(label_values).inc();
-                        continue;
-                    }
-                };
-
-                // Setting a label_values variable here throws errors about moving borrowed
-                // content that I couldn't solve w/o clones. Leaving this for now. I'm sure
-                // there's a better way.
-                SWIM_BYTES_RECEIVED.with_label_values(&[msg.kind.as_str(), "success"])
-                                   .set(bytes_received.to_i64());
-                SWIM_MESSAGES_RECEIVED.with_label_values(&[msg.kind.as_str(), "success"])
-                                      .inc();
-
-                trace!("SWIM Message: {:?}", msg);
-                match msg.kind {
-                    SwimKind::Ping(ping) => {
-                        if server.is_member_blocked_sblr(&ping.from.id) {
-                            debug!("Not processing message from {} - it is blocked",
-                                   ping.from.id);
-                            continue;
-                        }
-                        process_ping_mlw_smw_rhw(server, socket, addr, ping);
-                    }
-                    SwimKind::Ack(ack) => {
-                        if server.is_member_blocked_sblr(&ack.from.id) && ack.forward_to.is_none() {
-                            debug!("Not processing message from {} - it is blocked",
-                                   ack.from.id);
-                            continue;
-                        }
-                        process_ack_mlw_smw_rhw(server, socket, tx_outbound, addr, ack);
-                    }
-                    SwimKind::PingReq(pingreq) => {
-                        if server.is_member_blocked_sblr(&pingreq.from.id) {
-                            debug!("Not processing message from {} - it is blocked",
-                                   pingreq.from.id);
-                            continue;
-                        }
-                        process_pingreq_mlr_smr_rhw(server, socket, addr, pingreq);
-                    }
-                }
+                BANDWIDTH.with_label_values(&["swim", "received"])
+                         .inc_by(length as u64);
+                let addr = member::unmap_ipv4(addr);
+                process_message_rsr_mlw_smw_sblr_sdlr_rhw(server,
+                                                          socket,
+                                                          tx_outbound,
+                                                          &recv_buffer[0..length],
+                                                          addr,
+                                                          None);
             }
             Err(e) => {
                 // TODO: We can't use magic numbers here because the Supervisor runs on more
@@ -142,6 +92,175 @@ pub fn run_loop(server: &Server, socket: &UdpSocket, tx_outbound: &AckSender) ->
     }
 }
 
+/// Process a SWIM message received from `addr`, over `tls_peer` in a ring using TLS.
+///
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
+/// * `MemberList::entries` (write)
+/// * `Server::member` (write)
+/// * `Server::block_list` (read)
+/// * `Server::denylist` (read)
+/// * `RumorHeat::inner` (write)
+fn process_message_rsr_mlw_smw_sblr_sdlr_rhw(server: &Server,
+                                             socket: &UdpSocket,
+                                             tx_outbound: &AckSender,
+                                             bytes: &[u8],
+                                             addr: SocketAddr,
+                                             tls_peer: Option<&ServerStream>) {
+    let swim_payload = match server.unwrap_wire(bytes) {
+        Ok(swim_payload) => swim_payload,
+        Err(e) => {
+            // NOTE: In the future, we might want to block people who send us
+            // garbage all the time.
+            error!("Error unwrapping protocol message, {}", e);
+            let label_values = &["unwrap_wire", "failure"];
+            SWIM_BYTES_RECEIVED.with_label_values(label_values)
+                               .set(bytes.len().to_i64());
+            SWIM_MESSAGES_RECEIVED.with_label_values(label_values).inc();
+            return;
+        }
+    };
+
+    let bytes_received = swim_payload.len();
+    let msg = match Swim::decode(&swim_payload) {
+        Ok(msg) => msg,
+        Err(e) => {
+            // NOTE: In the future, we might want to block people who send us
+            // garbage all the time.
+            error!("Error decoding protocol message, {}", e);
+            let label_values = &["undecodable", "failure"];
+            SWIM_BYTES_RECEIVED.with_label_values(label_values)
+                               .set(bytes_received.to_i64());
+            SWIM_MESSAGES_RECEIVED.with_label_values(label_values).inc();
+            return;
+        }
+    };
+
+    // Setting a label_values variable here throws errors about moving borrowed
+    // content that I couldn't solve w/o clones. Leaving this for now. I'm sure
+    // there's a better way.
+    SWIM_BYTES_RECEIVED.with_label_values(&[msg.kind.as_str(), "success"])
+                       .set(bytes_received.to_i64());
+    SWIM_MESSAGES_RECEIVED.with_label_values(&[msg.kind.as_str(), "success"])
+                          .inc();
+
+    trace!("SWIM Message: {:?}", msg);
+    if let (Some(stream), Some(sender)) = (tls_peer, direct_sender(&msg.kind)) {
+        if !server.tls()
+                  .map_or(false, |tls| tls.certifies_member(stream, sender))
+        {
+            warn!("Not processing message from {} - its certificate was issued to another member",
+                  sender);
+            return;
+        }
+    }
+    let sent_at = msg.sent_at;
+    match msg.kind {
+        SwimKind::Ping(mut ping) => {
+            if server.is_member_blocked_sblr(&ping.from.id) {
+                debug!("Not processing message from {} - it is blocked",
+                       ping.from.id);
+                return;
+            }
+            if server.is_denied_sdlr(&ping.from.id, addr.ip()) {
+                debug!("Not processing message from {} - it is denied",
+                       ping.from.id);
+                return;
+            }
+            ping.membership
+                .retain(|membership| !server.is_membership_denied_sdlr(membership));
+            if let Some(sent_at) = sent_at {
+                server.record_clock_offset(&ping.from.id, sent_at);
+            }
+            process_ping_rsr_mlw_smw_rhw(server, socket, addr, ping);
+        }
+        SwimKind::Ack(mut ack) => {
+            if server.is_member_blocked_sblr(&ack.from.id) && ack.forward_to.is_none() {
+                debug!("Not processing message from {} - it is blocked",
+                       ack.from.id);
+                return;
+            }
+            // Unlike blocking, denial also refuses acks forwarded by or for the member
+            if server.is_denied_sdlr(&ack.from.id, addr.ip()) {
+                debug!("Not processing message from {} - it is denied", ack.from.id);
+                return;
+            }
+            ack.membership
+               .retain(|membership| !server.is_membership_denied_sdlr(membership));
+            // A forwarded ack is stamped by the member that forwarded it, rather
+            // than the one it is from
+            if let (Some(sent_at), None) = (sent_at, &ack.forward_to) {
+                server.record_clock_offset(&ack.from.id, sent_at);
+            }
+            process_ack_rsr_mlw_smw_rhw(server, socket, tx_outbound, addr, ack);
+        }
+        SwimKind::PingReq(pingreq) => {
+            if server.is_member_blocked_sblr(&pingreq.from.id) {
+                debug!("Not processing message from {} - it is blocked",
+                       pingreq.from.id);
+                return;
+            }
+            if server.is_denied_sdlr(&pingreq.from.id, addr.ip()) {
+                debug!("Not processing message from {} - it is denied",
+                       pingreq.from.id);
+                return;
+            }
+            if let Some(sent_at) = sent_at {
+                server.record_clock_offset(&pingreq.from.id, sent_at);
+            }
+            process_pingreq_mlr_smr_rhw(server, socket, addr, pingreq);
+        }
+    }
+}
+
+/// Process the SWIM messages received on a member's TLS connection until it is closed. The
+/// member first sends the port it receives SWIM messages on, where replies to it are sent.
+pub fn read_tls_connection(server: &Server,
+                           socket: &UdpSocket,
+                           tx_outbound: &AckSender,
+                           mut stream: ServerStream,
+                           peer_addr: SocketAddr) {
+    let addr = match tls::read_swim_port(&mut stream) {
+        Ok(swim_port) => SocketAddr::new(peer_addr.ip(), swim_port),
+        Err(e) => {
+            warn!("Dropping SWIM connection from {}: {}", peer_addr, e);
+            return;
+        }
+    };
+    loop {
+        let bytes = match tls::read_message(&mut stream, tls::MAX_SWIM_MESSAGE_BYTES) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return,
+            Err(e) => {
+                debug!("Closing SWIM connection from {}: {}", addr, e);
+                return;
+            }
+        };
+        BANDWIDTH.with_label_values(&["swim", "received"])
+                 .inc_by(bytes.len() as u64);
+        while server.paused() {
+            thread::sleep(Duration::from_millis(100));
+        }
+        process_message_rsr_mlw_smw_sblr_sdlr_rhw(server,
+                                                  socket,
+                                                  tx_outbound,
+                                                  &bytes,
+                                                  addr,
+                                                  Some(&stream));
+    }
+}
+
+/// The member that sent a SWIM message itself, which its certificate must have been issued to.
+/// A forwarded ack is sent by the member which forwarded it, rather than the one it is from.
+fn direct_sender(kind: &SwimKind) -> Option<&str> {
+    match kind {
+        SwimKind::Ping(ping) => Some(&ping.from.id),
+        SwimKind::Ack(ack) if ack.forward_to.is_none() => Some(&ack.from.id),
+        SwimKind::Ack(_) => None,
+        SwimKind::PingReq(pingreq) => Some(&pingreq.from.id),
+    }
+}
+
 /// Process pingreq messages.
 ///
 /// # Locking (see locking.md)
@@ -153,7 +272,7 @@ fn process_pingreq_mlr_smr_rhw(server: &Server,
                                addr: SocketAddr,
                                mut msg: PingReq) {
     if let Some(target) = server.member_list.get_cloned_mlr(&msg.target.id) {
-        msg.from.address = addr.ip().to_string();
+        msg.from.set_observed_address(addr);
         let ping_msg = Ping { membership: vec![],
                               from:       server.myself.lock_smr().to_member(),
                               forward_to: Some(msg.from.clone()), };
@@ -173,35 +292,36 @@ fn process_pingreq_mlr_smr_rhw(server: &Server,
 /// Process ack messages; forwards to the outbound thread.
 ///
 /// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
 /// * `MemberList::entries` (write)
 /// * `Server::member` (write)
 /// * `RumorHeat::inner` (write)
-fn process_ack_mlw_smw_rhw(server: &Server,
-                           socket: &UdpSocket,
-                           tx_outbound: &AckSender,
-                           addr: SocketAddr,
-                           mut msg: Ack) {
+fn process_ack_rsr_mlw_smw_rhw(server: &Server,
+                               socket: &UdpSocket,
+                               tx_outbound: &AckSender,
+                               addr: SocketAddr,
+                               mut msg: Ack) {
     trace!("Ack from {}@{}", msg.from.id, addr);
     if msg.forward_to.is_some() && *server.member_id != msg.forward_to.as_ref().unwrap().id {
-        let (forward_to_addr, from_addr) = {
+        let forward_to_addr = {
             let forward_to = msg.forward_to.as_ref().unwrap();
-            let forward_addr_str = format!("{}:{}", forward_to.address, forward_to.swim_port);
-            let forward_to_addr = match forward_addr_str.parse() {
-                Ok(addr) => addr,
-                Err(e) => {
-                    error!("Abandoning Ack forward: cannot parse member address: {}:{}, {}",
-                           forward_to.address, forward_to.swim_port, e);
-                    return;
-                }
-            };
+            let forward_to_addr =
+                match member::socket_address(&forward_to.address, forward_to.swim_port) {
+                    Ok(addr) => addr,
+                    Err(e) => {
+                        error!("Abandoning Ack forward: cannot parse member address: {}:{}, {}",
+                               forward_to.address, forward_to.swim_port, e);
+                        return;
+                    }
+                };
             trace!("Forwarding Ack from {}@{} to {}@{}",
                    msg.from.id,
                    addr,
                    forward_to.id,
                    forward_to.address,);
-            (forward_to_addr, addr.ip().to_string())
+            forward_to_addr
         };
-        msg.from.address = from_addr;
+        msg.from.set_observed_address(addr);
         outbound::forward_ack(server, socket, forward_to_addr, msg);
         return;
     }
@@ -209,7 +329,8 @@ fn process_ack_mlw_smw_rhw(server: &Server,
     match tx_outbound.send((addr, msg)) {
         Ok(()) => {
             for membership in memberships {
-                server.insert_member_from_rumor_mlw_smw_rhw(membership.member, membership.health);
+                server.insert_member_from_rumor_rsr_mlw_smw_rhw(membership.member,
+                                                                membership.health);
             }
         }
         Err(e) => panic!("Outbound thread has died - this shouldn't happen: #{:?}", e),
@@ -217,13 +338,17 @@ fn process_ack_mlw_smw_rhw(server: &Server,
 }
 
 /// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
 /// * `MemberList::entries` (write)
 /// * `Server::member` (write)
 /// * `RumorHeat::inner` (write)
-fn process_ping_mlw_smw_rhw(server: &Server, socket: &UdpSocket, addr: SocketAddr, mut msg: Ping) {
+fn process_ping_rsr_mlw_smw_rhw(server: &Server,
+                                socket: &UdpSocket,
+                                addr: SocketAddr,
+                                mut msg: Ping) {
     outbound::ack_mlr_smr_rhw(server, socket, &msg.from, addr, msg.forward_to);
     // Populate the member for this sender with its remote address
-    msg.from.address = addr.ip().to_string();
+    msg.from.set_observed_address(addr);
     trace!("Ping from {}@{}", msg.from.id, addr);
     if msg.from.departed {
         server.insert_member_mlw_rhw(msg.from, Health::Departed);
@@ -231,6 +356,6 @@ fn process_ping_mlw_smw_rhw(server: &Server, socket: &UdpSocket, addr: SocketAdd
         server.insert_member_mlw_rhw(msg.from, Health::Alive);
     }
     for membership in msg.membership {
-        server.insert_member_from_rumor_mlw_smw_rhw(membership.member, membership.health);
+        server.insert_member_from_rumor_rsr_mlw_smw_rhw(membership.member, membership.health);
     }
 }
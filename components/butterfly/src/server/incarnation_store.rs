@@ -1,16 +1,21 @@
 //! Provide the means to persist a Supervisor's own incarnation
 //! number across restarts.
+//!
+//! The number is stored with a checksum kept in a file beside it.
+//! If the file is found to be corrupt, the copy kept from before
+//! the most recent write is restored in its place.
 
 use crate::{error::{Error,
                     Result},
             member::Incarnation};
-use habitat_core::fs::atomic_write;
-use std::{fs::File,
-          io::{self,
-               Read},
+use habitat_core::fs::{checksummed_read,
+                       checksummed_recover,
+                       checksummed_write};
+use std::{io,
           num,
           path::{Path,
-                 PathBuf}};
+                 PathBuf},
+          str};
 
 /// Provide storage of an incarnation number that can persist across
 /// Supervisor restarts.
@@ -42,26 +47,49 @@ impl IncarnationStore {
         Ok(())
     }
 
-    /// Returns the incarnation value found within the file.
+    /// Returns the incarnation value found within the file. If the
+    /// file is corrupt, the last good copy of it is restored and its
+    /// value returned instead. Any higher incarnation lost with the
+    /// corrupt file is re-learned by refuting the ring's suspicion.
     ///
-    /// Returns an error if the file cannot be read or parsed for any
-    /// reason.
+    /// Returns an error if neither the file nor its last good copy
+    /// can be read or parsed.
     pub fn load(&self) -> Result<Incarnation> {
-        let into_err = |e: io::Error| Error::IncarnationIO(self.path.clone(), e);
+        match checksummed_read(&self.path).map_err(|e| Error::IncarnationIO(self.path.clone(), e))
+                                          .and_then(|data| self.parse(&data))
+        {
+            Ok(incarnation) => Ok(incarnation),
+            Err(Error::IncarnationIO(path, e)) if e.kind() != io::ErrorKind::InvalidData => {
+                Err(Error::IncarnationIO(path, e))
+            }
+            Err(err) => {
+                match checksummed_recover(&self.path) {
+                    Ok(data) => {
+                        warn!("Incarnation file {} was corrupt ({}); restored the last good copy",
+                              self.path.display(),
+                              err);
+                        self.parse(&data)
+                    }
+                    Err(_) => Err(err),
+                }
+            }
+        }
+    }
 
-        let mut file = File::open(&self.path).map_err(into_err)?;
-        let mut incarnation = String::new();
-        file.read_to_string(&mut incarnation).map_err(into_err)?;
-        incarnation.trim()
-                   .parse()
-                   .map_err(|e: num::ParseIntError| Error::IncarnationParse(self.path.clone(), e))
+    fn parse(&self, data: &[u8]) -> Result<Incarnation> {
+        str::from_utf8(data).unwrap_or_default()
+                            .trim()
+                            .parse()
+                            .map_err(|e: num::ParseIntError| {
+                                Error::IncarnationParse(self.path.clone(), e)
+                            })
     }
 
     /// Store the given `new_incarnation` to disk.
     pub fn store(&mut self, new_incarnation: Incarnation) -> Result<()> {
         let into_err = |e: io::Error| Error::IncarnationIO(self.path.clone(), e);
 
-        atomic_write(&self.path, new_incarnation.to_string()).map_err(into_err)?;
+        checksummed_write(&self.path, new_incarnation.to_string()).map_err(into_err)?;
         // TODO (CM): set appropriate file permissions here
         Ok(())
     }
@@ -70,9 +98,12 @@ impl IncarnationStore {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use habitat_core::fs::checksum_path;
     use io::Write;
     use mktemp::Temp;
-    use std::path::Path;
+    use std::{fs::{self,
+                   File},
+              path::Path};
 
     #[test]
     fn happy_path() {
@@ -150,6 +181,20 @@ mod tests {
         assert_eq!(initial_value, Incarnation::default());
     }
 
+    #[test]
+    fn a_corrupt_incarnation_file_is_restored_from_the_last_good_copy() {
+        let dir = Temp::new_dir().expect("Could not create temp dir");
+        let path = dir.as_ref().join("my_incarnation_store");
+
+        let mut i = IncarnationStore::new(&path);
+        i.store(Incarnation::from(7)).unwrap();
+        i.store(Incarnation::from(8)).unwrap();
+        fs::write(checksum_path(&path), "not the checksum of 8").unwrap();
+
+        assert_eq!(i.load().unwrap(), Incarnation::from(7));
+        assert_eq!(i.load().unwrap(), Incarnation::from(7));
+    }
+
     #[test]
     fn initialize_returns_an_error_if_file_exists_but_is_unparseable() {
         let tempfile = Temp::new_file().expect("Could not create temp file");
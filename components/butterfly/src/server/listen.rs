@@ -0,0 +1,133 @@
+//! The listen thread.
+//!
+//! In a ring using TLS, this module accepts the TLS connections of members, and hands each one to
+//! the inbound or pull module, depending on the channel it carries. Each connection is read by a
+//! thread of its own, up to `HAB_RING_TLS_MAX_CONNECTIONS` at once; connections beyond that are
+//! closed as soon as they are accepted.
+
+use super::AckSender;
+use crate::{member,
+            server::{inbound,
+                     pull,
+                     Server},
+            tls::{self,
+                  Channel}};
+use std::{net::{TcpListener,
+                TcpStream,
+                UdpSocket},
+          sync::{atomic::{AtomicUsize,
+                          Ordering},
+                 Arc},
+          thread};
+
+habitat_core::env_config_int!(/// The most TLS connections from members read at once
+                              MaxConnections,
+                              usize,
+                              HAB_RING_TLS_MAX_CONNECTIONS,
+                              1024);
+
+pub fn spawn_thread(name: String,
+                    server: Server,
+                    socket: Arc<UdpSocket>,
+                    tx_outbound: AckSender,
+                    listener: TcpListener)
+                    -> std::io::Result<()> {
+    thread::Builder::new().name(name)
+                          .spawn(move || -> ! {
+                              run_loop(&server, &socket, &tx_outbound, &listener)
+                          })
+                          .map(|_| ())
+}
+
+/// Counts a connection being read for as long as it lives
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    /// Take a slot, unless `max` connections are being read already
+    fn take(open: &Arc<AtomicUsize>, max: usize) -> Option<Self> {
+        open.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                if open < max {
+                    Some(open + 1)
+                } else {
+                    None
+                }
+            })
+            .ok()
+            .map(|_| ConnectionSlot(open.clone()))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) { self.0.fetch_sub(1, Ordering::SeqCst); }
+}
+
+/// Accept connections, each read by a thread of its own until the member closes it.
+fn run_loop(server: &Server,
+            socket: &Arc<UdpSocket>,
+            tx_outbound: &AckSender,
+            listener: &TcpListener)
+            -> ! {
+    let max_connections = MaxConnections::configured_value().0;
+    let open = Arc::new(AtomicUsize::new(0));
+    loop {
+        let (stream, peer_addr) = match listener.accept() {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                error!("Cannot accept TLS connection: {}", e);
+                continue;
+            }
+        };
+        let slot = match ConnectionSlot::take(&open, max_connections) {
+            Some(slot) => slot,
+            None => {
+                warn!("Dropping TLS connection from {}: {} connections are open already",
+                      member::unmap_ipv4(peer_addr),
+                      max_connections);
+                continue;
+            }
+        };
+        let (server, socket, tx_outbound) = (server.clone(), socket.clone(), tx_outbound.clone());
+        if let Err(e) =
+            thread::Builder::new().name(String::from("listen-connection"))
+                                  .spawn(move || {
+                                      let _slot = slot;
+                                      read_connection(&server, &socket, &tx_outbound, stream)
+                                  })
+        {
+            error!("Could not spawn thread: {}", e);
+        }
+    }
+}
+
+fn read_connection(server: &Server,
+                   socket: &UdpSocket,
+                   tx_outbound: &AckSender,
+                   stream: TcpStream) {
+    let peer_addr = match stream.peer_addr() {
+        Ok(peer_addr) => member::unmap_ipv4(peer_addr),
+        Err(e) => {
+            debug!("Dropping TLS connection from an unknown address: {}", e);
+            return;
+        }
+    };
+    let mut stream = match server.tls().map(|tls| tls.accept(stream)) {
+        Some(Ok(stream)) => stream,
+        Some(Err(e)) => {
+            error!("Cannot set up TLS connection from {}: {}", peer_addr, e);
+            return;
+        }
+        None => return,
+    };
+    // The handshake, which checks the member's certificate, happens on this first read
+    let channel = tls::read_channel(&mut stream).and_then(|channel| {
+                      stream.sock.set_read_timeout(Some(tls::READ_TIMEOUT))?;
+                      Ok(channel)
+                  });
+    match channel {
+        Ok(Channel::Swim) => {
+            inbound::read_tls_connection(server, socket, tx_outbound, stream, peer_addr)
+        }
+        Ok(Channel::Gossip) => pull::read_tls_connection(server, stream, peer_addr),
+        Err(e) => warn!("Dropping TLS connection from {}: {}", peer_addr, e),
+    }
+}
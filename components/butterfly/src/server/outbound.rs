@@ -5,22 +5,29 @@
 use super::AckReceiver;
 use crate::{member::{Health,
                      Member},
+            message,
             rumor::{RumorKey,
                     RumorType},
             server::{timing::Timing,
-                     Server},
+                     Server,
+                     BANDWIDTH},
             swim::{Ack,
                    Ping,
                    PingReq,
                    Swim}};
 use habitat_common::liveliness_checker;
 use habitat_core::util::ToI64;
-use prometheus::{HistogramTimer,
+use prometheus::{Histogram,
+                 HistogramTimer,
                  HistogramVec,
                  IntCounterVec,
                  IntGaugeVec};
-use std::{fmt,
-          net::{SocketAddr,
+use std::{collections::{HashMap,
+                        HashSet},
+          fmt,
+          io,
+          net::{IpAddr,
+                SocketAddr,
                 UdpSocket},
           sync::mpsc,
           thread,
@@ -30,6 +37,25 @@ use std::{fmt,
 /// How long to sleep between calls to `recv`.
 const PING_RECV_QUEUE_EMPTY_SLEEP_MS: u64 = 10;
 
+habitat_core::env_config_int!(/// The number of consecutive probes of a member that have to fail
+                              /// before it is marked as Suspect. Raising this keeps a member
+                              /// whose network is briefly unreliable from being gossiped as
+                              /// Suspect (and back to Alive) across the ring.
+                              #[derive(Debug)]
+                              SuspicionConfirmationCount,
+                              u32,
+                              HAB_SWIM_SUSPICION_CONFIRMATION_COUNT,
+                              3);
+
+habitat_core::env_config_int!(/// How many times longer than a member in the same zone a member in
+                              /// another zone has to ack a probe, since the link between zones
+                              /// usually has a much higher round trip time.
+                              #[derive(Debug)]
+                              CrossZoneTimeoutFactor,
+                              u32,
+                              HAB_SWIM_CROSS_ZONE_TIMEOUT_FACTOR,
+                              3);
+
 lazy_static! {
     static ref SWIM_MESSAGES_SENT: IntCounterVec =
         register_int_counter_vec!("hab_butterfly_swim_messages_sent_total",
@@ -47,6 +73,13 @@ lazy_static! {
         register_histogram_vec!("hab_butterfly_swim_probe_duration_seconds",
                                 "SWIM probe round trip time",
                                 &["type"]).unwrap();
+    static ref SWIM_PROBE_ROUND_DURATION: Histogram =
+        register_histogram!("hab_butterfly_swim_probe_round_duration_seconds",
+                            "Time taken to probe every member of the ring once").unwrap();
+    static ref SWIM_ACK_TIMEOUTS: IntCounterVec =
+        register_int_counter_vec!("hab_butterfly_swim_ack_timeouts_total",
+                                  "Total number of SWIM probes that timed out waiting for an ack",
+                                  &["type"]).unwrap();
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -55,6 +88,15 @@ enum AckFrom {
     PingReq,
 }
 
+impl AckFrom {
+    fn label(self) -> &'static str {
+        match self {
+            AckFrom::Ping => "ping",
+            AckFrom::PingReq => "pingreq",
+        }
+    }
+}
+
 impl fmt::Display for AckFrom {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -83,6 +125,8 @@ pub fn spawn_thread(name: String,
 /// before starting the next probe.
 fn run_loop(server: &Server, socket: &UdpSocket, rx_inbound: &AckReceiver, timing: &Timing) -> ! {
     let mut have_members = false;
+    // The number of consecutive failed probes of each member, by member ID
+    let mut failed_probes = HashMap::new();
     loop {
         liveliness_checker::mark_thread_alive().and_divergent();
 
@@ -105,6 +149,16 @@ fn run_loop(server: &Server, socket: &UdpSocket, rx_inbound: &AckReceiver, timin
                                       });
                 }
             }
+        } else {
+            // Permanent initial members, such as the permanent peers of a peer file, keep being
+            // pinged while no member is alive at their address, so that they are found again
+            // however long they were gone
+            server.member_list.with_initial_members_imlr(|member| {
+                                  let addr = member.swim_socket_address();
+                                  if member.persistent && !server.member_list.alive_at_mlr(addr) {
+                                      ping_mlr_smr_rhw(server, socket, member, addr, None);
+                                  }
+                              });
         }
 
         if server.paused() {
@@ -116,16 +170,29 @@ fn run_loop(server: &Server, socket: &UdpSocket, rx_inbound: &AckReceiver, timin
 
         let check_list = server.member_list.check_list_mlr(&server.member_id);
 
+        // Forget the failed probes of members that departed or are otherwise no longer probed
+        let probed: HashSet<_> = check_list.iter()
+                                           .filter(|member| server.member_list.pingable_mlr(member))
+                                           .map(|member| member.id.clone())
+                                           .collect();
+        failed_probes.retain(|member_id, _| probed.contains(member_id));
+
         let probe_iteration_start = Instant::now();
         for member in check_list {
             if server.member_list.pingable_mlr(&member) {
                 // If we complete the probe faster than our protocol
                 // period, we'll want to wait after we finish.
                 let probe_start = Instant::now();
-                probe_mlw_smr_rhw(server, socket, rx_inbound, timing, member);
+                probe_mlw_smr_rhw(server,
+                                  socket,
+                                  rx_inbound,
+                                  timing,
+                                  &mut failed_probes,
+                                  member);
                 timing.sleep_for_remaining_swim_protocol_interval(probe_start);
             }
         }
+        SWIM_PROBE_ROUND_DURATION.observe(probe_iteration_start.elapsed().as_secs_f64());
 
         // This will only come into play if:
         //
@@ -158,7 +225,8 @@ fn run_loop(server: &Server, socket: &UdpSocket, rx_inbound: &AckReceiver, timin
 /// If the timer has not been exceeded, we park this thread for
 /// PING_RECV_QUEUE_EMPTY_SLEEP_MS, and try again.
 ///
-/// If we don't receive anything at all in the Ping/PingReq loop, we mark the member as Suspect.
+/// If we don't receive anything at all in the Ping/PingReq loop for
+/// `HAB_SWIM_SUSPICION_CONFIRMATION_COUNT` probes in a row, we mark the member as Suspect.
 ///
 /// # Locking (see locking.md)
 /// * `MemberList::entries` (write)
@@ -168,10 +236,12 @@ fn probe_mlw_smr_rhw(server: &Server,
                      socket: &UdpSocket,
                      rx_inbound: &AckReceiver,
                      timing: &Timing,
+                     failed_probes: &mut HashMap<String, u32>,
                      member: Member) {
     let pa_timer = SWIM_PROBE_DURATION.with_label_values(&["ping/ack"])
                                       .start_timer();
     let mut pr_timer: Option<HistogramTimer> = None;
+    let probe_start = Instant::now();
     let addr = member.swim_socket_address();
 
     // Ping the member, and wait for the ack.
@@ -181,6 +251,8 @@ fn probe_mlw_smr_rhw(server: &Server,
     if recv_ack_mlw_rhw(server, rx_inbound, timing, &member, addr, AckFrom::Ping) {
         SWIM_PROBES_SENT.with_label_values(&["ack"]).inc();
         pa_timer.observe_duration();
+        server.record_round_trip(&member.id, probe_start.elapsed());
+        failed_probes.remove(&member.id);
         return;
     }
 
@@ -189,8 +261,9 @@ fn probe_mlw_smr_rhw(server: &Server,
                                     target:     member.clone(), };
     let swim = populate_membership_rumors_mlr_rhw(server, &member, pingreq_message);
 
+    let fanout = timing.pingreq_fanout();
     server.member_list
-          .with_pingreq_targets_mlr(server.member_id(), &member.id, |pingreq_target| {
+          .with_pingreq_targets_mlr(server.member_id(), &member.id, fanout, |pingreq_target| {
               SWIM_PROBES_SENT.with_label_values(&["pingreq"]).inc();
               pr_timer = Some(SWIM_PROBE_DURATION.with_label_values(&["pingreq/ack"])
                                                  .start_timer());
@@ -199,14 +272,24 @@ fn probe_mlw_smr_rhw(server: &Server,
 
     if recv_ack_mlw_rhw(server, rx_inbound, timing, &member, addr, AckFrom::PingReq) {
         SWIM_PROBES_SENT.with_label_values(&["ack"]).inc();
+        failed_probes.remove(&member.id);
     } else {
-        // We mark as suspect when we fail to get a response from the PingReq. That moves us
-        // into the suspicion phase, where anyone marked as suspect has a certain number of
-        // protocol periods to recover.
-        warn!("Marking {} as Suspect", &member.id);
-        server.insert_member_mlw_rhw(member, Health::Suspect);
         SWIM_PROBES_SENT.with_label_values(&["pingreq/failure"])
                         .inc();
+        let failures = failed_probes.entry(member.id.clone()).or_insert(0);
+        *failures += 1;
+        if *failures >= SuspicionConfirmationCount::configured_value().0 {
+            // We mark as suspect when we fail to get a response from the PingReq. That moves
+            // us into the suspicion phase, where anyone marked as suspect has a certain number
+            // of protocol periods to recover.
+            warn!("Marking {} as Suspect", &member.id);
+            server.insert_member_mlw_rhw(member, Health::Suspect);
+        } else {
+            debug!("Probe of {} failed ({} of {} before it is Suspect)",
+                   &member.id,
+                   failures,
+                   SuspicionConfirmationCount::configured_value().0);
+        }
     }
 
     if let Some(prt) = pr_timer {
@@ -226,10 +309,13 @@ fn recv_ack_mlw_rhw(server: &Server,
                     addr: SocketAddr,
                     ack_from: AckFrom)
                     -> bool {
-    let timeout = match ack_from {
+    let mut timeout = match ack_from {
         AckFrom::Ping => timing.ping(),
         AckFrom::PingReq => timing.pingreq(),
     };
+    if server.is_cross_zone(member) {
+        timeout *= CrossZoneTimeoutFactor::configured_value().0.max(1);
+    }
     let start_time = Instant::now();
     loop {
         match rx_inbound.try_recv() {
@@ -237,7 +323,7 @@ fn recv_ack_mlw_rhw(server: &Server,
                 // If this was forwarded to us, we want to retain the address of the member who
                 // sent the ack, not the one we received on the socket.
                 if ack.forward_to.is_none() {
-                    ack.from.address = real_addr.ip().to_string();
+                    ack.from.set_observed_address(real_addr);
                 }
                 #[allow(clippy::branches_sharing_code)]
                 if member.id != ack.from.id {
@@ -261,6 +347,8 @@ fn recv_ack_mlw_rhw(server: &Server,
             Err(mpsc::TryRecvError::Empty) => {
                 if start_time.elapsed() > timeout {
                     warn!("Timed out waiting for Ack from {}@{}", &member.id, addr);
+                    SWIM_ACK_TIMEOUTS.with_label_values(&[ack_from.label()])
+                                     .inc();
                     return false;
                 }
                 thread::sleep(Duration::from_millis(PING_RECV_QUEUE_EMPTY_SLEEP_MS));
@@ -336,14 +424,14 @@ fn pingreq(server: &Server, // TODO: eliminate this arg
             return;
         }
     };
-    let payload = match server.generate_wire(bytes) {
+    let payload = match server.generate_wire(bytes, message::compression_for(pingreq_target)) {
         Ok(payload) => payload,
         Err(e) => {
             error!("Generating protocol message failed: {}", e);
             return;
         }
     };
-    match socket.send_to(&payload, addr) {
+    match send_to(server, socket, &payload, addr) {
         Ok(_s) => {
             let label_values = &["pingreq"];
             SWIM_MESSAGES_SENT.with_label_values(label_values).inc();
@@ -389,14 +477,14 @@ pub fn ping_mlr_smr_rhw(server: &Server,
             return;
         }
     };
-    let payload = match server.generate_wire(bytes) {
+    let payload = match server.generate_wire(bytes, message::compression_for(target)) {
         Ok(payload) => payload,
         Err(e) => {
             error!("Generating protocol message failed: {}", e);
             return;
         }
     };
-    match socket.send_to(&payload, addr) {
+    match send_to(server, socket, &payload, addr) {
         Ok(_s) => {
             let label_values = &["ping"];
             SWIM_MESSAGES_SENT.with_label_values(label_values).inc();
@@ -424,14 +512,14 @@ pub fn ping(server: &Server,
             return;
         }
     };
-    let payload = match server.generate_wire(bytes) {
+    let payload = match server.generate_wire(bytes, None) {
         Ok(payload) => payload,
         Err(e) => {
             error!("Generating protocol message failed: {}", e);
             return;
         }
     };
-    match socket.send_to(&payload, addr) {
+    match send_to(server, socket, &payload, addr) {
         Ok(_s) => {
             let label_values = &["ping"];
             SWIM_MESSAGES_SENT.with_label_values(label_values).inc();
@@ -458,14 +546,14 @@ pub fn forward_ack(server: &Server, socket: &UdpSocket, addr: SocketAddr, msg: A
             return;
         }
     };
-    let payload = match server.generate_wire(bytes) {
+    let payload = match server.generate_wire(bytes, None) {
         Ok(payload) => payload,
         Err(e) => {
             error!("Generating protocol message failed: {}", e);
             return;
         }
     };
-    match socket.send_to(&payload, addr) {
+    match send_to(server, socket, &payload, addr) {
         Ok(_s) => trace!("Forwarded ack to {}@{}", member_id, addr),
         Err(e) => error!("Failed ack to {}@{}: {}", member_id, addr, e),
     }
@@ -494,14 +582,14 @@ pub fn ack_mlr_smr_rhw(server: &Server,
             return;
         }
     };
-    let payload = match server.generate_wire(bytes) {
+    let payload = match server.generate_wire(bytes, message::compression_for(target)) {
         Ok(payload) => payload,
         Err(e) => {
             error!("Generating protocol message failed: {}", e);
             return;
         }
     };
-    match socket.send_to(&payload, addr) {
+    match send_to(server, socket, &payload, addr) {
         Ok(_s) => {
             let label_values = &["ack"];
             SWIM_MESSAGES_SENT.with_label_values(label_values).inc();
@@ -512,3 +600,29 @@ pub fn ack_mlr_smr_rhw(server: &Server,
         Err(e) => error!("Failed ack to {}@{}: {}", member_id, addr, e),
     }
 }
+
+/// Send `payload` to `addr`, addressing IPv4 members by their IPv4-mapped IPv6 address when the
+/// socket is bound to an IPv6 (dual-stack) address. A ring using TLS sends it over a TLS
+/// connection instead.
+fn send_to(server: &Server,
+           socket: &UdpSocket,
+           payload: &[u8],
+           addr: SocketAddr)
+           -> io::Result<usize> {
+    if let Some(tls) = server.tls() {
+        tls.send_swim(server.swim_port(), payload, addr)?;
+        BANDWIDTH.with_label_values(&["swim", "sent"])
+                 .inc_by(payload.len() as u64);
+        return Ok(payload.len());
+    }
+    let addr = match (socket.local_addr()?, addr) {
+        (SocketAddr::V6(_), SocketAddr::V4(v4)) => {
+            SocketAddr::new(IpAddr::V6(v4.ip().to_ipv6_mapped()), v4.port())
+        }
+        _ => addr,
+    };
+    let sent = socket.send_to(payload, addr)?;
+    BANDWIDTH.with_label_values(&["swim", "sent"])
+             .inc_by(sent as u64);
+    Ok(sent)
+}
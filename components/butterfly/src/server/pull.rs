@@ -1,16 +1,22 @@
 //! The pull thread.
 //!
-//! This module handles pulling all the pushed rumors from every member off a ZMQ socket.
+//! This module handles pulling all the pushed rumors from every member off a ZMQ socket, or off
+//! TLS connections in a ring using TLS.
 
 use crate::{rumor::{RumorEnvelope,
                     RumorKind},
-            server::Server,
+            server::{Server,
+                     BANDWIDTH},
+            tls::{self,
+                  ServerStream},
             ZMQ_CONTEXT};
 use habitat_common::liveliness_checker;
 use habitat_core::util::ToI64;
 use prometheus::{IntCounterVec,
                  IntGaugeVec};
-use std::{thread,
+use std::{io,
+          net::SocketAddr,
+          thread,
           time::Duration};
 
 lazy_static! {
@@ -42,6 +48,9 @@ fn run_loop(server: &Server) -> ! {
           .expect("Failure to set the ZMQ Pull socket to not use keepalive");
     socket.set_rcvtimeo(RecvTimeoutMillis::configured_value().into())
           .expect("Failure to set the ZMQ Pull socket receive timeout");
+    // Accept IPv6 connections, and IPv4 ones too when bound to `[::]`
+    socket.set_ipv6(true)
+          .expect("Failure to enable IPv6 on the ZMQ Pull socket");
     socket.bind(&format!("tcp://{}", server.gossip_addr()))
           .expect("Failure to bind the ZMQ Pull socket to the port");
     'recv: loop {
@@ -57,7 +66,11 @@ fn run_loop(server: &Server) -> ! {
         }
 
         let msg = match socket.recv_msg(0) {
-            Ok(msg) => msg,
+            Ok(msg) => {
+                BANDWIDTH.with_label_values(&["gossip", "received"])
+                         .inc_by(msg.len() as u64);
+                msg
+            }
             Err(e) => {
                 // We intentionally set a timeout above so that `mark_thread_alive` can be
                 // used to show this thread is alive even when there's no data to receive.
@@ -68,69 +81,127 @@ fn run_loop(server: &Server) -> ! {
             }
         };
 
-        let payload = match server.unwrap_wire(&msg) {
-            Ok(payload) => payload,
-            Err(e) => {
-                // NOTE: In the future, we might want to block people who send us
-                // garbage all the time.
-                error!("Error parsing protocol message: {:?}", e);
-                let label_values = &["unwrap_wire", "failure", "unknown"];
-                GOSSIP_BYTES_RECEIVED.with_label_values(label_values)
-                                     .set(msg.len().to_i64());
-                GOSSIP_MESSAGES_RECEIVED.with_label_values(label_values)
-                                        .inc();
-                continue;
-            }
-        };
+        process_rumor_msr_rsw_mlw_smw_sblr_sdlr_rhw(server, &msg, None);
+    }
+}
 
-        let proto = match RumorEnvelope::decode(&payload) {
-            Ok(proto) => proto,
-            Err(e) => {
-                error!("Error parsing protocol message: {:?}", e);
-                let label_values = &["undecodable", "failure", "unknown"];
-                GOSSIP_BYTES_RECEIVED.with_label_values(label_values)
-                                     .set(payload.len().to_i64());
-                GOSSIP_MESSAGES_RECEIVED.with_label_values(label_values)
-                                        .inc();
-                continue 'recv;
-            }
-        };
+/// Process a rumor pushed to us, over `tls_peer` in a ring using TLS.
+///
+/// # Locking (see locking.md)
+/// * `ManagerServices::inner` (read)
+/// * `RumorStore::list` (write)
+/// * `MemberList::entries` (write)
+/// * `Server::member` (write)
+/// * `Server::block_list` (read)
+/// * `Server::denylist` (read)
+/// * `RumorHeat::inner` (write)
+fn process_rumor_msr_rsw_mlw_smw_sblr_sdlr_rhw(server: &Server,
+                                               msg: &[u8],
+                                               tls_peer: Option<&ServerStream>) {
+    let payload = match server.unwrap_wire(msg) {
+        Ok(payload) => payload,
+        Err(e) => {
+            // NOTE: In the future, we might want to block people who send us
+            // garbage all the time.
+            error!("Error parsing protocol message: {:?}", e);
+            let label_values = &["unwrap_wire", "failure", "unknown"];
+            GOSSIP_BYTES_RECEIVED.with_label_values(label_values)
+                                 .set(msg.len().to_i64());
+            GOSSIP_MESSAGES_RECEIVED.with_label_values(label_values)
+                                    .inc();
+            return;
+        }
+    };
+
+    let proto = match RumorEnvelope::decode(&payload) {
+        Ok(proto) => proto,
+        Err(e) => {
+            error!("Error parsing protocol message: {:?}", e);
+            let label_values = &["undecodable", "failure", "unknown"];
+            GOSSIP_BYTES_RECEIVED.with_label_values(label_values)
+                                 .set(payload.len().to_i64());
+            GOSSIP_MESSAGES_RECEIVED.with_label_values(label_values)
+                                    .inc();
+            return;
+        }
+    };
 
-        let blocked = server.is_member_blocked_sblr(&proto.from_id);
-        let blocked_label = if blocked { "true" } else { "false" };
-        let label_values = &[&proto.r#type.to_string(), "success", blocked_label];
+    // Denied members are counted as blocked
+    let blocked = server.is_member_blocked_sblr(&proto.from_id)
+                  || server.is_sender_denied_mlr_sdlr(&proto.from_id);
+    let blocked_label = if blocked { "true" } else { "false" };
+    let label_values = &[&proto.r#type.to_string(), "success", blocked_label];
 
-        GOSSIP_MESSAGES_RECEIVED.with_label_values(label_values)
-                                .inc();
-        GOSSIP_BYTES_RECEIVED.with_label_values(label_values)
-                             .set(payload.len().to_i64());
+    GOSSIP_MESSAGES_RECEIVED.with_label_values(label_values)
+                            .inc();
+    GOSSIP_BYTES_RECEIVED.with_label_values(label_values)
+                         .set(payload.len().to_i64());
 
-        if blocked {
-            warn!("Not processing message from {} - it is blocked",
+    if blocked {
+        warn!("Not processing message from {} - it is blocked",
+              proto.from_id);
+        return;
+    }
+
+    // Membership rumors are sent by the member pushing them, while the others keep the member
+    // that originated them
+    if let (Some(stream), RumorKind::Membership(_)) = (tls_peer, &proto.kind) {
+        if !server.tls()
+                  .map_or(false, |tls| tls.certifies_member(stream, &proto.from_id))
+        {
+            warn!("Not processing message from {} - its certificate was issued to another member",
                   proto.from_id);
-            continue 'recv;
+            return;
         }
+    }
 
-        match proto.kind {
-            RumorKind::Membership(membership) => {
-                server.insert_member_from_rumor_mlw_smw_rhw(membership.member, membership.health);
-            }
-            RumorKind::Service(service) => server.insert_service_rsw_mlw_rhw(*service),
-            RumorKind::ServiceConfig(service_config) => {
-                server.insert_service_config_rsw_rhw(service_config);
+    match proto.kind {
+        RumorKind::Membership(membership) => {
+            if server.is_membership_denied_sdlr(&membership) {
+                debug!("Not inserting member {} - it is denied",
+                       membership.member.id);
+                return;
             }
-            RumorKind::ServiceFile(service_file) => {
-                server.insert_service_file_rsw_rhw(service_file);
-            }
-            RumorKind::Election(election) => {
-                server.insert_election_rsw_mlr_rhw_msr(election);
-            }
-            RumorKind::ElectionUpdate(election) => {
-                server.insert_update_election_rsw_mlr_rhw(election);
-            }
-            RumorKind::Departure(departure) => {
-                server.insert_departure_rsw_mlw_rhw(departure);
+            server.insert_member_from_rumor_rsr_mlw_smw_rhw(membership.member, membership.health);
+        }
+        RumorKind::Service(service) => server.insert_service_rsw_mlw_rhw(*service),
+        RumorKind::ServiceConfig(service_config) => {
+            server.insert_service_config_rsw_rhw(service_config);
+        }
+        RumorKind::ServiceFile(service_file) => {
+            server.insert_service_file_rsw_rhw(service_file);
+        }
+        RumorKind::Election(election) => {
+            server.insert_election_rsw_mlr_rhw_msr(election);
+        }
+        RumorKind::ElectionUpdate(election) => {
+            server.insert_update_election_rsw_mlr_rhw(election);
+        }
+        RumorKind::Departure(departure) => {
+            server.insert_departure_rsw_mlw_rhw(departure);
+        }
+        RumorKind::FleetManifest(fleet_manifest) => {
+            server.insert_fleet_manifest_rsw_rhw(fleet_manifest);
+        }
+    }
+}
+
+/// Process the rumors pushed over a member's TLS connection until it is closed.
+pub fn read_tls_connection(server: &Server, mut stream: ServerStream, peer_addr: SocketAddr) {
+    loop {
+        let msg = match tls::read_message(&mut stream, tls::MAX_GOSSIP_MESSAGE_BYTES) {
+            Ok(msg) => msg,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return,
+            Err(e) => {
+                debug!("Closing gossip connection from {}: {}", peer_addr, e);
+                return;
             }
+        };
+        BANDWIDTH.with_label_values(&["gossip", "received"])
+                 .inc_by(msg.len() as u64);
+        while server.paused() {
+            thread::sleep(Duration::from_millis(100));
         }
+        process_rumor_msr_rsw_mlw_smw_sblr_sdlr_rhw(server, &msg, Some(&stream));
     }
 }
@@ -1,27 +1,47 @@
 //! The push thread.
 //!
 //! This is the thread for distributing rumors to members. It distributes to `FANOUT` members, no
-//! more often than `Timing::GOSSIP_PERIOD_DEFAULT_MS`.
+//! more often than `Timing::GOSSIP_PERIOD_DEFAULT_MS`, within the limits of the server's
+//! `OutboundLimiter`.
 
-use crate::{member::{Member,
+use crate::{error::{Error,
+                    Result},
+            member::{self,
+                     Member,
                      Membership},
+            message,
             rumor::{RumorEnvelope,
                     RumorKey,
                     RumorKind,
                     RumorType},
             server::{timing::Timing,
-                     Server},
+                     Server,
+                     BANDWIDTH},
+            tls::{self,
+                  Channel,
+                  ClientStream},
             ZMQ_CONTEXT};
 use habitat_common::liveliness_checker;
 use habitat_core::util::ToI64;
 use prometheus::{IntCounterVec,
                  IntGaugeVec};
-use std::{thread,
+use std::{collections::HashMap,
+          net::SocketAddr,
+          thread,
           time::{Duration,
                  Instant}};
 
 const FANOUT: usize = 5;
 
+habitat_core::env_config_int!(/// The most members in other zones that rumors are sent to each
+                              /// gossip round. They send the rumors on to the members of their
+                              /// zone, so little gossip has to cross the link between zones.
+                              #[derive(Debug)]
+                              CrossZoneFanout,
+                              usize,
+                              HAB_GOSSIP_CROSS_ZONE_FANOUT,
+                              1);
+
 lazy_static! {
     static ref GOSSIP_MESSAGES_SENT: IntCounterVec =
         register_int_counter_vec!("hab_butterfly_gossip_messages_sent_total",
@@ -31,6 +51,16 @@ lazy_static! {
         register_int_gauge_vec!("hab_butterfly_gossip_sent_bytes",
                                 "Gossip message size sent in bytes",
                                 &["type", "mode"]).unwrap();
+    static ref GOSSIP_QUEUE_DEPTH: IntGaugeVec =
+        register_int_gauge_vec!("hab_butterfly_gossip_queue_depth",
+                                "Number of rumors still to be sent to members in the last gossip \
+                                 round",
+                                &["type"]).unwrap();
+    static ref GOSSIP_RUMORS_DEFERRED: IntCounterVec =
+        register_int_counter_vec!("hab_butterfly_gossip_rumors_deferred_total",
+                                  "Total number of rumors left for a later gossip round as a \
+                                   rate limit was reached or too many were waiting",
+                                  &["type"]).unwrap();
 }
 
 pub fn spawn_thread(name: String, server: Server, timing: Timing) -> std::io::Result<()> {
@@ -54,8 +84,12 @@ fn run_loop(server: &Server, timing: &Timing) -> ! {
 
         server.update_gossip_round();
 
-        let mut check_list = server.member_list.check_list_mlr(server.member_id());
+        let check_list = server.member_list.check_list_mlr(server.member_id());
+        let mut check_list = member::limit_cross_zone(check_list,
+                                                      server.zone(),
+                                                      CrossZoneFanout::configured_value().0);
         let fanout_loop_start_time = Instant::now();
+        let mut queue_depths: HashMap<RumorType, i64> = HashMap::new();
 
         'fanout: loop {
             let mut thread_list = Vec::with_capacity(FANOUT);
@@ -79,6 +113,20 @@ fn run_loop(server: &Server, timing: &Timing) -> ! {
                     let rumors = server.rumor_heat
                                        .lock_rhr()
                                        .currently_hot_rumors(&member.id);
+                    let (rumors, deferred) = server.outbound_limiter.queue(rumors);
+                    if !deferred.is_empty() {
+                        debug!("Deferring {} rumors for {} - too many are waiting",
+                               deferred.len(),
+                               member.id);
+                        for rumor in &deferred {
+                            GOSSIP_RUMORS_DEFERRED.with_label_values(&[&rumor.kind.to_string()])
+                                                  .inc();
+                            *queue_depths.entry(rumor.kind).or_insert(0) += 1;
+                        }
+                    }
+                    for rumor in &rumors {
+                        *queue_depths.entry(rumor.kind).or_insert(0) += 1;
+                    }
                     if !rumors.is_empty() {
                         let sc = server.clone();
                         let guard = match thread::Builder::new().name(String::from("push-worker"))
@@ -106,6 +154,7 @@ fn run_loop(server: &Server, timing: &Timing) -> ! {
             // for that long.
             timing.sleep_for_remaining_gossip_interval(gossip_start_time);
         }
+        record_queue_depths(queue_depths);
 
         // If we've still got any time left in the gossip interval, sleep
         // for that long.
@@ -131,6 +180,16 @@ fn run_loop(server: &Server, timing: &Timing) -> ! {
     }
 }
 
+/// Publish how many rumors each member still had to be sent in the gossip round, by rumor type.
+/// The types with nothing left to send are dropped rather than set to zero.
+fn record_queue_depths(queue_depths: HashMap<RumorType, i64>) {
+    GOSSIP_QUEUE_DEPTH.reset();
+    for (kind, depth) in queue_depths {
+        GOSSIP_QUEUE_DEPTH.with_label_values(&[&kind.to_string()])
+                          .set(depth);
+    }
+}
+
 /// Send the list of rumors to a given member. This method creates an outbound socket and then
 /// closes the connection as soon as we are done sending rumors. ZeroMQ may choose to keep the
 /// connection and socket open for 1 second longer - so it is possible, but unlikely, that this
@@ -146,31 +205,31 @@ fn run_loop(server: &Server, timing: &Timing) -> ! {
 // expected reward.
 #[allow(clippy::cognitive_complexity)]
 fn send_rumors_rsr_mlr_rhw(server: &Server, member: &Member, rumors: &[RumorKey]) {
-    let socket = (**ZMQ_CONTEXT).as_mut()
-                                .socket(zmq::PUSH)
-                                .expect("Failure to create the ZMQ push socket");
-    socket.set_linger(1000)
-          .expect("Failure to set the ZMQ push socket to not linger");
-    socket.set_tcp_keepalive(0)
-          .expect("Failure to set the ZMQ push socket to not use keepalive");
-    socket.set_immediate(true)
-          .expect("Failure to set the ZMQ push socket to immediate");
-    socket.set_sndhwm(1000)
-          .expect("Failure to set the ZMQ push socket hwm");
-    socket.set_sndtimeo(500)
-          .expect("Failure to set the ZMQ send timeout");
-    let to_addr = format!("{}:{}", member.address, member.gossip_port);
-    match socket.connect(&format!("tcp://{}", to_addr)) {
-        Ok(()) => debug!("Connected push socket to {:?}", member),
+    let to_addr = match member::socket_address(&member.address, member.gossip_port) {
+        Ok(addr) => addr,
+        Err(e) => {
+            error!("Cannot parse address of {:?}: {}", member, e);
+            return;
+        }
+    };
+    let mut connection = match PushConnection::connect(server, to_addr) {
+        Ok(connection) => {
+            debug!("Connected push socket to {:?}", member);
+            connection
+        }
         Err(e) => {
-            error!("Cannot connect push socket to {:?}: {:?}", member, e);
+            error!("Cannot connect push socket to {:?}: {}", member, e);
             let label_values = &["socket_connect", "failure"];
             GOSSIP_MESSAGES_SENT.with_label_values(label_values).inc();
             GOSSIP_BYTES_SENT.with_label_values(label_values).set(0);
             return;
         }
-    }
-    'rumorlist: for rumor_key in rumors.iter() {
+    };
+    // The codec is negotiated once for the connection, from the codecs the member accepts
+    let compression = message::compression_for(member);
+    // The rumors from this index on stay hot for a later round if a rate limit is reached
+    let mut deferred_from = rumors.len();
+    'rumorlist: for (index, rumor_key) in rumors.iter().enumerate() {
         let rumor_as_bytes = match rumor_key.kind {
             RumorType::Member => {
                 let send_rumor = match create_member_rumor_mlr(server, rumor_key) {
@@ -283,13 +342,30 @@ fn send_rumors_rsr_mlr_rhw(server: &Server, member: &Member, rumors: &[RumorKey]
                     }
                 }
             }
+            RumorType::FleetManifest => {
+                match server.fleet_manifest_store
+                            .lock_rsr()
+                            .encode_rumor_for(rumor_key)
+                {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        error!("Could not write our own rumor to bytes; abandoning sending \
+                                rumor: {:?}",
+                               e);
+                        let label_values = &["fleet_manifest_rumor_encode", "failure"];
+                        GOSSIP_MESSAGES_SENT.with_label_values(label_values).inc();
+                        GOSSIP_BYTES_SENT.with_label_values(label_values).set(0);
+                        continue 'rumorlist;
+                    }
+                }
+            }
             RumorType::Fake | RumorType::Fake2 => {
                 debug!("You have fake rumors; how odd!");
                 continue 'rumorlist;
             }
         };
         let rumor_len = rumor_as_bytes.len().to_i64();
-        let payload = match server.generate_wire(rumor_as_bytes) {
+        let payload = match server.generate_wire(rumor_as_bytes, compression) {
             Ok(payload) => payload,
             Err(e) => {
                 error!("Generating protobuf failed: {}", e);
@@ -300,8 +376,23 @@ fn send_rumors_rsr_mlr_rhw(server: &Server, member: &Member, rumors: &[RumorKey]
                 continue 'rumorlist;
             }
         };
-        match socket.send(&payload, 0) {
+        if !server.outbound_limiter
+                  .try_send(&member.id, payload.len(), Instant::now())
+        {
+            debug!("Deferring {} rumors for {} - the gossip rate limit is reached",
+                   rumors.len() - index,
+                   member.id);
+            for rumor in &rumors[index..] {
+                GOSSIP_RUMORS_DEFERRED.with_label_values(&[&rumor.kind.to_string()])
+                                      .inc();
+            }
+            deferred_from = index;
+            break 'rumorlist;
+        }
+        match connection.send(&payload) {
             Ok(()) => {
+                BANDWIDTH.with_label_values(&["gossip", "sent"])
+                         .inc_by(payload.len() as u64);
                 GOSSIP_MESSAGES_SENT.with_label_values(&[&rumor_key.kind.to_string(), "success"])
                                     .inc();
                 GOSSIP_BYTES_SENT.with_label_values(&[&rumor_key.kind.to_string(), "success"])
@@ -309,13 +400,55 @@ fn send_rumors_rsr_mlr_rhw(server: &Server, member: &Member, rumors: &[RumorKey]
                 debug!("Sent rumor {:?} to {:?}", rumor_key, member);
             }
             Err(e) => {
-                warn!("Could not send rumor to {:?} @ {:?}; ZMQ said: {:?}",
+                warn!("Could not send rumor to {:?} @ {:?}; {}",
                       member.id, to_addr, e)
             }
         }
     }
 
-    server.rumor_heat.lock_rhw().cool_rumors(&member.id, rumors);
+    server.rumor_heat
+          .lock_rhw()
+          .cool_rumors(&member.id, &rumors[..deferred_from]);
+}
+
+/// Where the rumors of a push are sent: a ZMQ socket, or a TLS connection in a ring using TLS
+enum PushConnection {
+    Zmq(zmq::Socket),
+    Tls(Box<ClientStream>),
+}
+
+impl PushConnection {
+    fn connect(server: &Server, to_addr: SocketAddr) -> Result<Self> {
+        if let Some(tls) = server.tls() {
+            let stream = tls.connect(to_addr, Channel::Gossip)?;
+            return Ok(PushConnection::Tls(Box::new(stream)));
+        }
+        let socket = (**ZMQ_CONTEXT).as_mut()
+                                    .socket(zmq::PUSH)
+                                    .expect("Failure to create the ZMQ push socket");
+        socket.set_linger(1000)
+              .expect("Failure to set the ZMQ push socket to not linger");
+        socket.set_tcp_keepalive(0)
+              .expect("Failure to set the ZMQ push socket to not use keepalive");
+        socket.set_immediate(true)
+              .expect("Failure to set the ZMQ push socket to immediate");
+        socket.set_sndhwm(1000)
+              .expect("Failure to set the ZMQ push socket hwm");
+        socket.set_sndtimeo(500)
+              .expect("Failure to set the ZMQ send timeout");
+        socket.set_ipv6(true)
+              .expect("Failure to enable IPv6 on the ZMQ push socket");
+        socket.connect(&format!("tcp://{}", to_addr))
+              .map_err(Error::ZmqConnectError)?;
+        Ok(PushConnection::Zmq(socket))
+    }
+
+    fn send(&mut self, payload: &[u8]) -> Result<()> {
+        match self {
+            PushConnection::Zmq(socket) => socket.send(payload, 0).map_err(Error::ZmqSendError),
+            PushConnection::Tls(stream) => Ok(tls::write_message(stream, payload)?),
+        }
+    }
 }
 
 /// Given a rumorkey, creates a protobuf rumor for sharing.
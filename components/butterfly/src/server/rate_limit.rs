@@ -0,0 +1,243 @@
+//! Limits the rumors this member sends, so that a burst of them, as from many config applies at
+//! once, can neither saturate a small link nor overwhelm a slow member.
+//!
+//! Each member is sent no more than `HAB_GOSSIP_PEER_MAX_BYTES_PER_SEC` of rumors, and all members
+//! together no more than `HAB_GOSSIP_MAX_BYTES_PER_SEC`. A push stops once either limit is reached,
+//! and the rumors it did not send stay hot, to be sent in a later gossip round. Since rumors are
+//! tracked by key, a newer version of a rumor that is still waiting replaces the older one rather
+//! than queueing behind it.
+//!
+//! No more than `HAB_GOSSIP_MAX_QUEUED_RUMORS` rumors are queued to be sent to a member each gossip
+//! round. Past that, the least important ones stay hot and wait for a later round, once those
+//! ahead of them have been sent often enough to cool off. SWIM messages are never limited, so that
+//! a member busy gossiping is not suspected.
+
+use crate::rumor::{RumorKey,
+                   RumorType};
+use std::{collections::HashMap,
+          sync::Mutex,
+          time::Instant};
+
+habitat_core::env_config_int!(/// The most bytes of rumors sent to all members each second, or 0
+                              /// for no limit
+                              #[derive(Debug)]
+                              GossipMaxBytesPerSec,
+                              u64,
+                              HAB_GOSSIP_MAX_BYTES_PER_SEC,
+                              0);
+
+habitat_core::env_config_int!(/// The most bytes of rumors sent to any one member each second, or 0
+                              /// for no limit
+                              #[derive(Debug)]
+                              GossipPeerMaxBytesPerSec,
+                              u64,
+                              HAB_GOSSIP_PEER_MAX_BYTES_PER_SEC,
+                              0);
+
+habitat_core::env_config_int!(/// The most rumors queued to be sent to any one member each gossip
+                              /// round, or 0 for no limit
+                              #[derive(Debug)]
+                              GossipMaxQueuedRumors,
+                              usize,
+                              HAB_GOSSIP_MAX_QUEUED_RUMORS,
+                              0);
+
+/// The bytes a limit lets through, refilled at its rate up to a second's worth
+#[derive(Debug)]
+struct Bucket {
+    bytes:      f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn full(rate: u64, now: Instant) -> Self {
+        Bucket { bytes:      rate as f64,
+                 updated_at: now, }
+    }
+
+    fn refill(&mut self, rate: u64, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at);
+        self.bytes = (self.bytes + elapsed.as_secs_f64() * rate as f64).min(rate as f64);
+        self.updated_at = now;
+    }
+}
+
+#[derive(Debug)]
+pub struct OutboundLimiter {
+    max_bytes_per_sec:      Option<u64>,
+    peer_max_bytes_per_sec: Option<u64>,
+    max_queued:             Option<usize>,
+    global:                 Mutex<Option<Bucket>>,
+    peers:                  Mutex<HashMap<String, Bucket>>,
+}
+
+impl Default for OutboundLimiter {
+    fn default() -> Self {
+        Self::new(GossipMaxBytesPerSec::configured_value().0,
+                  GossipPeerMaxBytesPerSec::configured_value().0,
+                  GossipMaxQueuedRumors::configured_value().0)
+    }
+}
+
+impl OutboundLimiter {
+    /// A limiter to `max_bytes_per_sec` in all, `peer_max_bytes_per_sec` per member and
+    /// `max_queued` rumors queued per member each round. A 0 is no limit.
+    pub fn new(max_bytes_per_sec: u64, peer_max_bytes_per_sec: u64, max_queued: usize) -> Self {
+        OutboundLimiter { max_bytes_per_sec:      Some(max_bytes_per_sec).filter(|r| *r > 0),
+                          peer_max_bytes_per_sec: Some(peer_max_bytes_per_sec).filter(|r| *r > 0),
+                          max_queued:             Some(max_queued).filter(|m| *m > 0),
+                          global:                 Mutex::default(),
+                          peers:                  Mutex::default(), }
+    }
+
+    fn limited(&self) -> bool {
+        self.max_bytes_per_sec.is_some() || self.peer_max_bytes_per_sec.is_some()
+    }
+
+    /// Split the hot rumors of a member into those to send it this round, most important first,
+    /// and those to leave hot for a later round as there are too many waiting
+    pub fn queue(&self, mut rumors: Vec<RumorKey>) -> (Vec<RumorKey>, Vec<RumorKey>) {
+        let max_queued = self.max_queued.unwrap_or(usize::MAX);
+        if !self.limited() && rumors.len() <= max_queued {
+            return (rumors, Vec::new());
+        }
+        // A stable sort, which keeps the coldest rumors of each kind first
+        rumors.sort_by_key(|rumor| priority(rumor.kind));
+        let deferred = rumors.split_off(rumors.len().min(max_queued));
+        (rumors, deferred)
+    }
+
+    /// Whether a payload of `len` bytes may be sent to `member_id` now, counting it against the
+    /// limits if so. A payload is sent as long as neither limit is used up, even if it uses up
+    /// more than is left; the limit then lets nothing through until it has made up for it.
+    pub fn try_send(&self, member_id: &str, len: usize, now: Instant) -> bool {
+        if !self.limited() {
+            return true;
+        }
+        let mut peers = self.peers.lock().expect("Outbound limiter lock poisoned");
+        let mut global = self.global.lock().expect("Outbound limiter lock poisoned");
+        let mut buckets = Vec::with_capacity(2);
+        if let Some(rate) = self.peer_max_bytes_per_sec {
+            let bucket = peers.entry(member_id.to_string())
+                              .or_insert_with(|| Bucket::full(rate, now));
+            bucket.refill(rate, now);
+            buckets.push(bucket);
+        }
+        if let Some(rate) = self.max_bytes_per_sec {
+            let bucket = global.get_or_insert_with(|| Bucket::full(rate, now));
+            bucket.refill(rate, now);
+            buckets.push(bucket);
+        }
+        if buckets.iter().any(|bucket| bucket.bytes <= 0.0) {
+            return false;
+        }
+        for bucket in buckets.iter_mut() {
+            bucket.bytes -= len as f64;
+        }
+        true
+    }
+
+    /// Forget the limit of a member that is gone
+    pub fn forget(&self, member_id: &str) {
+        self.peers
+            .lock()
+            .expect("Outbound limiter lock poisoned")
+            .remove(member_id);
+    }
+}
+
+/// The order in which rumors are sent when they are limited. Membership comes first, since the
+/// ring relies on it to detect failures, then elections, and the service rumors last.
+fn priority(kind: RumorType) -> u8 {
+    match kind {
+        RumorType::Member | RumorType::Departure => 0,
+        RumorType::Election | RumorType::ElectionUpdate => 1,
+        RumorType::Service | RumorType::FleetManifest => 2,
+        RumorType::ServiceConfig | RumorType::ServiceFile => 3,
+        RumorType::Fake | RumorType::Fake2 => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rumor::heat::sync::RumorHeat;
+    use std::{collections::HashSet,
+              time::Duration};
+
+    fn rumor(kind: RumorType, id: &str) -> RumorKey { RumorKey::new(kind, id, "") }
+
+    #[test]
+    fn nothing_is_limited_by_default() {
+        let limiter = OutboundLimiter::new(0, 0, 0);
+        let now = Instant::now();
+        assert!((0..1_000).all(|_| limiter.try_send("member", 1_000_000, now)));
+        let rumors = vec![rumor(RumorType::ServiceConfig, "a"),
+                          rumor(RumorType::Member, "b")];
+        assert_eq!(limiter.queue(rumors.clone()), (rumors, Vec::new()));
+    }
+
+    #[test]
+    fn each_member_is_limited_on_its_own() {
+        let limiter = OutboundLimiter::new(0, 1_000, 0);
+        let now = Instant::now();
+        assert!(limiter.try_send("slow", 600, now));
+        assert!(limiter.try_send("slow", 600, now));
+        assert!(!limiter.try_send("slow", 1, now));
+        assert!(limiter.try_send("other", 600, now));
+
+        // Going over the limit is made up for before anything else is sent
+        assert!(!limiter.try_send("slow", 1, now + Duration::from_millis(100)));
+        assert!(limiter.try_send("slow", 1, now + Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn all_members_together_are_limited() {
+        let limiter = OutboundLimiter::new(1_000, 0, 0);
+        let now = Instant::now();
+        assert!(limiter.try_send("a", 800, now));
+        assert!(limiter.try_send("b", 800, now));
+        assert!(!limiter.try_send("c", 1, now));
+        assert!(limiter.try_send("c", 1, now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn the_least_important_rumors_are_deferred() {
+        let limiter = OutboundLimiter::new(0, 0, 2);
+        let rumors = vec![rumor(RumorType::ServiceConfig, "a"),
+                          rumor(RumorType::Service, "b"),
+                          rumor(RumorType::Member, "c")];
+        let (queued, deferred) = limiter.queue(rumors);
+        assert_eq!(queued,
+                   vec![rumor(RumorType::Member, "c"),
+                        rumor(RumorType::Service, "b")]);
+        assert_eq!(deferred, vec![rumor(RumorType::ServiceConfig, "a")]);
+    }
+
+    #[test]
+    fn a_member_with_too_many_hot_rumors_is_eventually_sent_every_one() {
+        let limiter = OutboundLimiter::new(0, 0, 2);
+        let heat = RumorHeat::default();
+        let member_id = "member";
+        let rumors: HashSet<RumorKey> =
+            (0..5).map(|i| rumor(RumorType::ServiceConfig, &i.to_string()))
+                  .chain((0..3).map(|i| rumor(RumorType::Member, &i.to_string())))
+                  .collect();
+        for rumor in &rumors {
+            heat.lock_rhw().start_hot_rumor(rumor.clone());
+        }
+
+        let mut sent = HashSet::new();
+        for _ in 0..100 {
+            let hot = heat.lock_rhr().currently_hot_rumors(member_id);
+            if hot.is_empty() {
+                break;
+            }
+            let (queued, _) = limiter.queue(hot);
+            assert!(queued.len() <= 2);
+            heat.lock_rhw().cool_rumors(member_id, &queued);
+            sent.extend(queued);
+        }
+        assert_eq!(sent, rumors);
+    }
+}
@@ -0,0 +1,59 @@
+//! Tracks how long the other members of the ring take to ack this member's direct probes, so that
+//! the member can tell how far it is from the rest of the ring. Leader elections use it to prefer
+//! the members with the lowest latency.
+//!
+//! The round trips of members that have not acked a probe for `ESTIMATE_TTL` are dropped.
+
+use std::{collections::HashMap,
+          convert::TryFrom,
+          sync::Mutex,
+          time::{Duration,
+                 Instant}};
+
+const ESTIMATE_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Default)]
+pub struct RoundTrips {
+    /// The latest round trip of each member, and when it was made
+    times: Mutex<HashMap<String, (Duration, Instant)>>,
+}
+
+impl RoundTrips {
+    /// Note that `member_id` acked a direct probe in `round_trip`
+    pub fn record(&self, member_id: &str, round_trip: Duration, now: Instant) {
+        self.times
+            .lock()
+            .expect("Round trips lock poisoned")
+            .insert(member_id.to_string(), (round_trip, now));
+    }
+
+    /// The mean round trip of the members that acked a probe recently, if any did
+    pub fn mean(&self, now: Instant) -> Option<Duration> {
+        let mut times = self.times.lock().expect("Round trips lock poisoned");
+        times.retain(|_, (_, made_at)| now.saturating_duration_since(*made_at) < ESTIMATE_TTL);
+        let total: Duration = times.values().map(|(round_trip, _)| *round_trip).sum();
+        u32::try_from(times.len()).ok()
+                                  .filter(|count| *count > 0)
+                                  .map(|count| total / count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_mean_only_counts_recent_round_trips() {
+        let round_trips = RoundTrips::default();
+        let now = Instant::now();
+        assert_eq!(round_trips.mean(now), None);
+
+        round_trips.record("near", Duration::from_millis(2), now);
+        round_trips.record("far", Duration::from_millis(40), now);
+        assert_eq!(round_trips.mean(now), Some(Duration::from_millis(21)));
+
+        round_trips.record("near", Duration::from_millis(4), now + ESTIMATE_TTL);
+        assert_eq!(round_trips.mean(now + ESTIMATE_TTL),
+                   Some(Duration::from_millis(4)));
+    }
+}
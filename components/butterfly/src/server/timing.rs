@@ -1,4 +1,7 @@
-use std::{thread,
+use crate::error::{Error,
+                   Result};
+use std::{ops::RangeInclusive,
+          thread,
           time::{Duration,
                  Instant}};
 
@@ -8,20 +11,34 @@ const PING_TIMING_DEFAULT_MS: u64 = 1000;
 const PINGREQ_TIMING_DEFAULT_MS: u64 = 2100;
 /// How many protocol periods before a suspect member is marked as confirmed.
 const SUSPICION_TIMEOUT_DEFAULT_PROTOCOL_PERIODS: u64 = 3;
+/// How many members we ask to ping a member on our behalf when it does not answer our ping.
+const PINGREQ_FANOUT_DEFAULT: usize = 5;
 /// How long to wait between each time we send rumors out.
 const GOSSIP_INTERVAL_DEFAULT_MS: u64 = 1000;
 /// How long before we set a confirmed member to a departed member, removing them from quorums
 ///   just for your own sanity - this is 3 days.
 const DEPARTURE_TIMEOUT_DEFAULT_MS: u64 = 259_200_000;
 
+/// The ping timeouts we accept. Below this range a busy member on a small LAN is suspected for
+/// answering a little late; above it a dead member goes unnoticed for too long.
+const PING_TIMEOUT_RANGE_MS: RangeInclusive<u64> = 50..=10_000;
+/// The longest protocol period we accept.
+const PROTOCOL_PERIOD_MAX_MS: u64 = 60_000;
+/// The protocol period must leave at least this many ping timeouts for the PingReq, since an
+/// indirect probe takes two round trips.
+const PROTOCOL_PERIOD_MIN_PING_TIMEOUTS: u32 = 3;
+const PINGREQ_FANOUT_RANGE: RangeInclusive<usize> = 1..=16;
+const SUSPICION_TIMEOUT_RANGE_PROTOCOL_PERIODS: RangeInclusive<u64> = 1..=100;
+
 /// Collects important timing durations and timekeeping activities for
 /// the underlying gossip protocols.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Timing {
-    ping:      Duration,
-    pingreq:   Duration,
-    confirm:   Duration,
-    departure: Duration,
+    ping:           Duration,
+    pingreq:        Duration,
+    pingreq_fanout: usize,
+    confirm:        Duration,
+    departure:      Duration,
 
     gossip_interval:     Duration,
     swim_probe_interval: Duration,
@@ -34,6 +51,7 @@ impl Default for Timing {
 
         Timing { ping:                Duration::from_millis(PING_TIMING_DEFAULT_MS),
                  pingreq:             Duration::from_millis(PINGREQ_TIMING_DEFAULT_MS),
+                 pingreq_fanout:      PINGREQ_FANOUT_DEFAULT,
                  confirm:             Duration::from_millis(confirm_ms),
                  departure:           Duration::from_millis(DEPARTURE_TIMEOUT_DEFAULT_MS),
                  gossip_interval:     Duration::from_millis(GOSSIP_INTERVAL_DEFAULT_MS),
@@ -42,12 +60,69 @@ impl Default for Timing {
 }
 
 impl Timing {
+    /// Timing for a ring whose SWIM protocol period is `protocol_period`. Each period we ping one
+    /// member and wait `ping_timeout` for its Ack, then ask `pingreq_fanout` other members to ping
+    /// it for us and wait out the rest of the period. A suspect member is confirmed after
+    /// `suspicion_periods` protocol periods.
+    ///
+    /// Returns an error if any of these are outside of the ranges the protocol works with.
+    pub fn new(protocol_period: Duration,
+               ping_timeout: Duration,
+               pingreq_fanout: usize,
+               suspicion_periods: u64)
+               -> Result<Timing> {
+        let ping_ms = ping_timeout.as_millis() as u64;
+        if !PING_TIMEOUT_RANGE_MS.contains(&ping_ms) {
+            return Err(Error::InvalidSwimTiming(format!("the ping timeout must be between \
+                                                         {}ms and {}ms, not {}ms",
+                                                        PING_TIMEOUT_RANGE_MS.start(),
+                                                        PING_TIMEOUT_RANGE_MS.end(),
+                                                        ping_ms)));
+        }
+        let min_period = ping_timeout * PROTOCOL_PERIOD_MIN_PING_TIMEOUTS;
+        let max_period = Duration::from_millis(PROTOCOL_PERIOD_MAX_MS);
+        if protocol_period < min_period || protocol_period > max_period {
+            return Err(Error::InvalidSwimTiming(format!("the protocol period must be between \
+                                                         {}ms and {}ms for a {}ms ping \
+                                                         timeout, not {}ms",
+                                                        min_period.as_millis(),
+                                                        max_period.as_millis(),
+                                                        ping_ms,
+                                                        protocol_period.as_millis())));
+        }
+        if !PINGREQ_FANOUT_RANGE.contains(&pingreq_fanout) {
+            return Err(Error::InvalidSwimTiming(format!("the ping-req fanout must be between \
+                                                         {} and {} members, not {}",
+                                                        PINGREQ_FANOUT_RANGE.start(),
+                                                        PINGREQ_FANOUT_RANGE.end(),
+                                                        pingreq_fanout)));
+        }
+        if !SUSPICION_TIMEOUT_RANGE_PROTOCOL_PERIODS.contains(&suspicion_periods) {
+            return Err(Error::InvalidSwimTiming(format!(
+                "the suspicion timeout must be between {} and {} protocol periods, not {}",
+                SUSPICION_TIMEOUT_RANGE_PROTOCOL_PERIODS.start(),
+                SUSPICION_TIMEOUT_RANGE_PROTOCOL_PERIODS.end(),
+                suspicion_periods
+            )));
+        }
+
+        Ok(Timing { ping: ping_timeout,
+                    pingreq: protocol_period - ping_timeout,
+                    pingreq_fanout,
+                    confirm: protocol_period * suspicion_periods as u32,
+                    swim_probe_interval: protocol_period,
+                    ..Timing::default() })
+    }
+
     /// How long a ping has to timeout.
     pub fn ping(&self) -> Duration { self.ping }
 
     /// How long a pingreq has to timeout.
     pub fn pingreq(&self) -> Duration { self.pingreq }
 
+    /// How many members we ask to ping a member that did not answer our ping.
+    pub fn pingreq_fanout(&self) -> usize { self.pingreq_fanout }
+
     /// How long after not hearing from a suspect member before we
     /// consider it confirmed.
     pub fn confirm(&self) -> Duration { self.confirm }
@@ -77,3 +152,41 @@ fn maybe_sleep(start: Instant, timeout: Duration) {
         thread::sleep(amount)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(period_ms: u64, ping_ms: u64, fanout: usize, suspicion: u64) -> Result<Timing> {
+        Timing::new(Duration::from_millis(period_ms),
+                    Duration::from_millis(ping_ms),
+                    fanout,
+                    suspicion)
+    }
+
+    #[test]
+    fn defaults_can_be_given_explicitly() {
+        assert_eq!(timing(3100, 1000, 5, 3).unwrap(), Timing::default());
+    }
+
+    #[test]
+    fn timeouts_are_derived_from_the_protocol_period() {
+        let timing = timing(600, 150, 2, 4).unwrap();
+        assert_eq!(timing.ping(), Duration::from_millis(150));
+        assert_eq!(timing.pingreq(), Duration::from_millis(450));
+        assert_eq!(timing.pingreq_fanout(), 2);
+        assert_eq!(timing.confirm(), Duration::from_millis(2400));
+    }
+
+    #[test]
+    fn timing_outside_of_the_valid_ranges_is_refused() {
+        assert!(timing(3100, 10, 5, 3).is_err());
+        assert!(timing(60_000, 20_000, 5, 3).is_err());
+        assert!(timing(2000, 1000, 5, 3).is_err());
+        assert!(timing(90_000, 1000, 5, 3).is_err());
+        assert!(timing(3100, 1000, 0, 3).is_err());
+        assert!(timing(3100, 1000, 17, 3).is_err());
+        assert!(timing(3100, 1000, 5, 0).is_err());
+        assert!(timing(3100, 1000, 5, 101).is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! Discover the public address a NAT device maps the SWIM listener to.
+//!
+//! A Supervisor behind NAT can ask a STUN server (RFC 5389) which address its Binding request
+//! appeared to come from, and then advertise that address to the ring in place of the one it
+//! listens on. Only the simplest part of the protocol is implemented: a single Binding request,
+//! retried a few times, with the mapped address read from the response.
+
+use crate::error::{Error,
+                   Result};
+use habitat_common::util;
+use std::{io,
+          net::{IpAddr,
+                Ipv4Addr,
+                Ipv6Addr,
+                SocketAddr,
+                UdpSocket},
+          time::Duration};
+
+/// The port STUN servers listen on when none is given
+pub const DEFAULT_PORT: u16 = 3478;
+
+const MAGIC_COOKIE: u32 = 0x2112_A442;
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS_RESPONSE: u16 = 0x0101;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+const FAMILY_IPV4: u8 = 0x01;
+const FAMILY_IPV6: u8 = 0x02;
+const HEADER_LEN: usize = 20;
+
+const ATTEMPTS: usize = 3;
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+type TransactionId = [u8; 12];
+
+/// Ask the STUN server at `server` (HOST[:PORT]) for the address that packets sent from `local`
+/// appear to come from.
+///
+/// This binds `local` itself, so it must be called before the SWIM listener is started. Most
+/// NAT devices keep the mapping of a local address for some time after it was last used, which
+/// makes the returned address the one the rest of the ring will see.
+pub fn mapped_address(local: SocketAddr, server: &str) -> Result<SocketAddr> {
+    let (_, server_addr) = util::resolve_socket_addr_with_default_port(server, DEFAULT_PORT)
+        .map_err(|e| Error::Stun(e.to_string()))?;
+    let socket = UdpSocket::bind(local).map_err(Error::CannotBind)?;
+    socket.set_read_timeout(Some(RESPONSE_TIMEOUT))
+          .map_err(Error::SocketSetReadTimeout)?;
+    let transaction_id: TransactionId = rand::random();
+    let request = binding_request(&transaction_id);
+    let mut buf = [0u8; 512];
+    for _ in 0..ATTEMPTS {
+        socket.send_to(&request, server_addr)
+              .map_err(|e| Error::Stun(format!("{}: {}", server, e)))?;
+        match socket.recv_from(&mut buf) {
+            Ok((len, from)) if from == server_addr => {
+                if let Some(addr) = parse_binding_response(&buf[..len], &transaction_id) {
+                    return Ok(addr);
+                }
+            }
+            Ok(_) => {}
+            // Timed out waiting for a response; try again
+            Err(ref e) if is_timeout(e) => {}
+            Err(e) => return Err(Error::Stun(format!("{}: {}", server, e))),
+        }
+    }
+    let msg = format!("No response from {} after {} attempts", server, ATTEMPTS);
+    Err(Error::Stun(msg))
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(),
+             io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+fn binding_request(transaction_id: &TransactionId) -> Vec<u8> {
+    let mut request = Vec::with_capacity(HEADER_LEN);
+    request.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    request.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(transaction_id);
+    request
+}
+
+/// The mapped address in a Binding success response to the request with `transaction_id`.
+/// XOR-MAPPED-ADDRESS is preferred over the older MAPPED-ADDRESS when a server sends both.
+fn parse_binding_response(buf: &[u8], transaction_id: &TransactionId) -> Option<SocketAddr> {
+    if buf.len() < HEADER_LEN
+       || read_u16(&buf[0..2]) != BINDING_SUCCESS_RESPONSE
+       || buf[4..8] != MAGIC_COOKIE.to_be_bytes()
+       || buf[8..20] != transaction_id[..]
+    {
+        return None;
+    }
+    let len = usize::from(read_u16(&buf[2..4]));
+    let attributes = buf.get(HEADER_LEN..HEADER_LEN + len)?;
+    let mut mapped_address = None;
+    let mut pos = 0;
+    while pos + 4 <= attributes.len() {
+        let attr_type = read_u16(&attributes[pos..pos + 2]);
+        let attr_len = usize::from(read_u16(&attributes[pos + 2..pos + 4]));
+        let value = attributes.get(pos + 4..pos + 4 + attr_len)?;
+        match attr_type {
+            ATTR_XOR_MAPPED_ADDRESS => return parse_address(value, Some(transaction_id)),
+            ATTR_MAPPED_ADDRESS => mapped_address = parse_address(value, None),
+            _ => {}
+        }
+        // Attributes are padded to a multiple of 4 bytes
+        pos += 4 + ((attr_len + 3) & !3);
+    }
+    mapped_address
+}
+
+/// Parse a (XOR-)MAPPED-ADDRESS value. The transaction ID is only given for XOR-MAPPED-ADDRESS,
+/// whose port and address are XORed with the magic cookie and transaction ID.
+fn parse_address(value: &[u8], xor_transaction_id: Option<&TransactionId>) -> Option<SocketAddr> {
+    let family = *value.get(1)?;
+    let mut port = read_u16(value.get(2..4)?);
+    let mut key = MAGIC_COOKIE.to_be_bytes().to_vec();
+    if let Some(transaction_id) = xor_transaction_id {
+        port ^= (MAGIC_COOKIE >> 16) as u16;
+        key.extend_from_slice(transaction_id);
+    }
+    let unxor = |octets: &mut [u8]| {
+        if xor_transaction_id.is_some() {
+            for (octet, k) in octets.iter_mut().zip(key.iter()) {
+                *octet ^= k;
+            }
+        }
+    };
+    let ip = match family {
+        FAMILY_IPV4 => {
+            let mut octets = [0u8; 4];
+            octets.copy_from_slice(value.get(4..8)?);
+            unxor(&mut octets);
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        FAMILY_IPV6 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(value.get(4..20)?);
+            unxor(&mut octets);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+fn read_u16(bytes: &[u8]) -> u16 { u16::from_be_bytes([bytes[0], bytes[1]]) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSACTION_ID: TransactionId = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+
+    fn response(attributes: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (attr_type, value) in attributes {
+            body.extend_from_slice(&attr_type.to_be_bytes());
+            body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            body.extend_from_slice(value);
+            body.resize((body.len() + 3) & !3, 0);
+        }
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&BINDING_SUCCESS_RESPONSE.to_be_bytes());
+        buf.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        buf.extend_from_slice(&TRANSACTION_ID);
+        buf.extend(body);
+        buf
+    }
+
+    fn address_value(family: u8, port: u16, octets: &[u8]) -> Vec<u8> {
+        let mut value = vec![0, family];
+        value.extend_from_slice(&port.to_be_bytes());
+        value.extend_from_slice(octets);
+        value
+    }
+
+    #[test]
+    fn binding_request_has_header_only() {
+        let request = binding_request(&TRANSACTION_ID);
+        assert_eq!(request.len(), HEADER_LEN);
+        assert_eq!(&request[0..4], &[0, 1, 0, 0]);
+        assert_eq!(&request[4..8], &MAGIC_COOKIE.to_be_bytes());
+        assert_eq!(&request[8..], &TRANSACTION_ID);
+    }
+
+    #[test]
+    fn parses_xor_mapped_ipv4_address() {
+        // 203.0.113.7:40000, XORed with the magic cookie
+        let port = 40000 ^ 0x2112;
+        let octets = [203 ^ 0x21, 0x12, 113 ^ 0xA4, 7 ^ 0x42];
+        let buf = response(&[(ATTR_XOR_MAPPED_ADDRESS, address_value(FAMILY_IPV4, port, &octets))]);
+        assert_eq!(parse_binding_response(&buf, &TRANSACTION_ID),
+                   Some("203.0.113.7:40000".parse().unwrap()));
+    }
+
+    #[test]
+    fn parses_xor_mapped_ipv6_address() {
+        let expected: SocketAddr = "[2001:db8::1]:9638".parse().unwrap();
+        let mut key = MAGIC_COOKIE.to_be_bytes().to_vec();
+        key.extend_from_slice(&TRANSACTION_ID);
+        let octets: Vec<u8> = match expected.ip() {
+            IpAddr::V6(ip) => {
+                ip.octets()
+                  .iter()
+                  .zip(key.iter())
+                  .map(|(o, k)| o ^ k)
+                  .collect()
+            }
+            IpAddr::V4(_) => unreachable!(),
+        };
+        let value = address_value(FAMILY_IPV6, 9638 ^ 0x2112, &octets);
+        let buf = response(&[(ATTR_XOR_MAPPED_ADDRESS, value)]);
+        assert_eq!(parse_binding_response(&buf, &TRANSACTION_ID),
+                   Some(expected));
+    }
+
+    #[test]
+    fn prefers_xor_mapped_address_over_mapped_address() {
+        let mapped = address_value(FAMILY_IPV4, 1234, &[192, 0, 2, 1]);
+        let xor_mapped = address_value(FAMILY_IPV4,
+                                       9638 ^ 0x2112,
+                                       &[198 ^ 0x21, 51 ^ 0x12, 100 ^ 0xA4, 0x42]);
+        let buf = response(&[(ATTR_MAPPED_ADDRESS, mapped.clone()),
+                             (ATTR_XOR_MAPPED_ADDRESS, xor_mapped)]);
+        assert_eq!(parse_binding_response(&buf, &TRANSACTION_ID),
+                   Some("198.51.100.0:9638".parse().unwrap()));
+
+        let buf = response(&[(ATTR_MAPPED_ADDRESS, mapped)]);
+        assert_eq!(parse_binding_response(&buf, &TRANSACTION_ID),
+                   Some("192.0.2.1:1234".parse().unwrap()));
+    }
+
+    #[test]
+    fn ignores_responses_to_other_requests() {
+        let buf = response(&[(ATTR_MAPPED_ADDRESS, address_value(FAMILY_IPV4, 1, &[1, 1, 1, 1]))]);
+        assert_eq!(parse_binding_response(&buf, &[0; 12]), None);
+        assert_eq!(parse_binding_response(&buf[..HEADER_LEN - 1], &TRANSACTION_ID),
+                   None);
+    }
+}
@@ -11,7 +11,10 @@ use crate::{error::{Error,
 use bytes::BytesMut;
 use prost::Message as ProstMessage;
 use std::{fmt,
-          str::FromStr};
+          str::FromStr,
+          time::{Duration,
+                 SystemTime,
+                 UNIX_EPOCH}};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Ack {
@@ -62,7 +65,8 @@ impl From<Ack> for proto::Swim {
                                        .into_iter()
                                        .map(proto::Membership::from)
                                        .collect(),
-                      payload:    Some(SwimPayload::Ack(value.into())), }
+                      payload:    Some(SwimPayload::Ack(value.into())),
+                      sent_at_ms: None, }
     }
 }
 
@@ -70,7 +74,8 @@ impl From<Ack> for Swim {
     fn from(value: Ack) -> Self {
         Swim { r#type:     SwimType::Ack,
                membership: value.membership.clone(),
-               kind:       SwimKind::Ack(value), }
+               kind:       SwimKind::Ack(value),
+               sent_at:    None, }
     }
 }
 
@@ -149,7 +154,8 @@ impl From<Ping> for proto::Swim {
                                        .into_iter()
                                        .map(proto::Membership::from)
                                        .collect(),
-                      payload:    Some(SwimPayload::Ping(value.into())), }
+                      payload:    Some(SwimPayload::Ping(value.into())),
+                      sent_at_ms: None, }
     }
 }
 
@@ -157,7 +163,8 @@ impl From<Ping> for Swim {
     fn from(value: Ping) -> Self {
         Swim { r#type:     SwimType::Ping,
                membership: value.membership.clone(),
-               kind:       SwimKind::Ping(value), }
+               kind:       SwimKind::Ping(value),
+               sent_at:    None, }
     }
 }
 
@@ -207,7 +214,8 @@ impl From<PingReq> for proto::Swim {
                                        .into_iter()
                                        .map(proto::Membership::from)
                                        .collect(),
-                      payload:    Some(SwimPayload::Pingreq(value.into())), }
+                      payload:    Some(SwimPayload::Pingreq(value.into())),
+                      sent_at_ms: None, }
     }
 }
 
@@ -215,7 +223,8 @@ impl From<PingReq> for Swim {
     fn from(value: PingReq) -> Self {
         Swim { r#type:     SwimType::Pingreq,
                membership: value.membership.clone(),
-               kind:       SwimKind::PingReq(value), }
+               kind:       SwimKind::PingReq(value),
+               sent_at:    None, }
     }
 }
 
@@ -258,12 +267,17 @@ pub struct Swim {
     pub r#type:     SwimType,
     pub membership: Vec<Membership>,
     pub kind:       SwimKind,
+    /// When the message was sent by its sender's clock, if it was received from a sender that
+    /// says. Messages are stamped with the time as they are encoded.
+    pub sent_at:    Option<SystemTime>,
 }
 
 impl Swim {
     pub fn decode(bytes: &[u8]) -> Result<Self> {
         let proto = proto::Swim::decode(bytes)?;
         let r#type = SwimType::from_i32(proto.r#type).ok_or(Error::ProtocolMismatch("type"))?;
+        let sent_at = proto.sent_at_ms
+                           .map(|ms| UNIX_EPOCH + Duration::from_millis(ms));
         let mut memberships = Vec::with_capacity(proto.membership.len());
         for membership in proto.membership.clone() {
             memberships.push(Membership::from_proto(membership)?);
@@ -275,10 +289,12 @@ impl Swim {
         };
         Ok(Swim { r#type,
                   membership: memberships,
-                  kind })
+                  kind,
+                  sent_at })
     }
 
-    pub fn encode(self) -> Result<Vec<u8>> {
+    pub fn encode(mut self) -> Result<Vec<u8>> {
+        self.sent_at = Some(SystemTime::now());
         let proto: proto::Swim = self.into();
         let mut buf = BytesMut::with_capacity(proto.encoded_len());
         proto.encode(&mut buf)?;
@@ -293,7 +309,14 @@ impl From<Swim> for proto::Swim {
                                        .into_iter()
                                        .map(proto::Membership::from)
                                        .collect(),
-                      payload:    Some(value.kind.into()), }
+                      payload:    Some(value.kind.into()),
+                      sent_at_ms: value.sent_at.and_then(unix_millis), }
     }
 }
+
+fn unix_millis(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|since_epoch| since_epoch.as_millis() as u64)
+}
 //
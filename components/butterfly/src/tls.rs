@@ -0,0 +1,366 @@
+//! Mutual TLS for a ring whose members prove their identity with certificates, rather than
+//! encrypting messages with a shared ring key.
+//!
+//! Every member presents a certificate issued by the ring's certificate authority and refuses
+//! connections from peers which do not. Since members are reached by address rather than by a name
+//! of their own, their certificates must also be valid for the ring's server name. They must be
+//! valid for `<member ID>.<server name>` too, binding each certificate to the member it was issued
+//! to: the SWIM messages and membership rumors a member sends are only accepted from it.
+//!
+//! SWIM messages are sent over TLS connections to the SWIM port over TCP, which are kept open
+//! between protocol periods by a thread for each member messages are sent to, so that a slow or
+//! unreachable member never holds up the SWIM threads. Rumors are sent over a TLS connection to
+//! the gossip port for each push. These replace UDP and ZMQ. Since the two ports usually share a
+//! number, a connection starts with the `Channel` it carries. Each message is preceded by its
+//! length, as a 4 byte big-endian integer.
+
+use crate::error::{Error,
+                   Result};
+use habitat_core::tls::rustls_wrapper;
+use rustls::{AllowAnyAuthenticatedClient,
+             ClientConfig,
+             ClientSession,
+             ServerConfig,
+             ServerSession,
+             Session,
+             StreamOwned};
+use socket2::{Domain,
+              Protocol,
+              Socket,
+              Type};
+use std::{collections::HashMap,
+          fmt,
+          io::{self,
+               Read,
+               Write},
+          net::{SocketAddr,
+                TcpListener,
+                TcpStream},
+          path::PathBuf,
+          sync::{atomic::{AtomicU64,
+                          Ordering},
+                 mpsc::{self,
+                        Receiver,
+                        SyncSender,
+                        TrySendError},
+                 Arc,
+                 Mutex},
+          thread,
+          time::Duration};
+use webpki::{DNSName,
+             DNSNameRef,
+             EndEntityCert};
+
+/// The name the certificates of members are checked against unless the ring sets another
+pub const DEFAULT_SERVER_NAME: &str = "habitat-ring";
+/// The largest SWIM message accepted, the most a UDP datagram could have held
+pub const MAX_SWIM_MESSAGE_BYTES: usize = 65_507;
+/// The largest rumor accepted, well over the size of the largest rumor a member creates
+pub const MAX_GOSSIP_MESSAGE_BYTES: usize = 1024 * 1024;
+/// Connections which receive nothing for this long are closed
+pub const READ_TIMEOUT: Duration = Duration::from_secs(60);
+/// Connections which have not finished their handshake and sent their channel within this long
+/// of each read are closed
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// SWIM connections unused for this long are closed along with the thread sending on them, so that
+/// they are never written to after the member at the other end closed them for reaching
+/// `READ_TIMEOUT`.
+const SWIM_CONNECTION_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+/// How many SWIM messages wait to be sent to a member before more are dropped
+const SWIM_QUEUE_LEN: usize = 16;
+
+pub type ClientStream = StreamOwned<ClientSession, TcpStream>;
+pub type ServerStream = StreamOwned<ServerSession, TcpStream>;
+
+/// What a connection carries, sent as its first byte
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Swim   = 1,
+    Gossip = 2,
+}
+
+/// Where to find the certificates of a ring using TLS, and the name they are valid for
+#[derive(Clone, Debug, PartialEq)]
+pub struct RingTlsConfig {
+    /// The PEM certificate chain of this member, starting with its own certificate
+    pub cert_path:    PathBuf,
+    /// The PEM PKCS8 private key of this member's certificate
+    pub key_path:     PathBuf,
+    /// The PEM certificate of the authority which issues the certificates of members
+    pub ca_cert_path: PathBuf,
+    /// The name the certificates of members must be valid for
+    pub server_name:  String,
+}
+
+pub struct RingTls {
+    server_config:  Arc<ServerConfig>,
+    connector:      Connector,
+    swim_senders:   Arc<Mutex<HashMap<SocketAddr, SwimSender>>>,
+    next_sender_id: AtomicU64,
+}
+
+impl fmt::Debug for RingTls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RingTls")
+         .field("server_name", &self.connector.server_name)
+         .finish()
+    }
+}
+
+/// Connects to members
+#[derive(Clone)]
+struct Connector {
+    client_config: Arc<ClientConfig>,
+    server_name:   DNSName,
+}
+
+impl Connector {
+    fn connect(&self, addr: SocketAddr, channel: Channel) -> io::Result<ClientStream> {
+        let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(WRITE_TIMEOUT))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let session = ClientSession::new(&self.client_config, self.server_name.as_ref());
+        let mut stream = StreamOwned::new(session, stream);
+        // The handshake, which checks the member's certificate, happens on this first write
+        stream.write_all(&[channel as u8])?;
+        Ok(stream)
+    }
+}
+
+/// The queue of the thread sending SWIM messages to a member, as the port replies go to and the
+/// message
+struct SwimSender {
+    id: u64,
+    tx: SyncSender<(u16, Vec<u8>)>,
+}
+
+impl RingTls {
+    pub fn new(config: &RingTlsConfig) -> Result<Self> {
+        let certs =
+            rustls_wrapper::certificates_from_file(&config.cert_path).map_err(config_error)?;
+        let key = rustls_wrapper::private_key_from_file(&config.key_path).map_err(config_error)?;
+        let roots = rustls_wrapper::root_certificate_store_from_file(&config.ca_cert_path)
+            .map_err(config_error)?;
+        let server_name = DNSNameRef::try_from_ascii_str(&config.server_name).map_err(|_| {
+                              Error::RingTls(format!("'{}' is not a valid server name",
+                                                     config.server_name))
+                          })?
+                          .to_owned();
+
+        let mut server_config = ServerConfig::new(AllowAnyAuthenticatedClient::new(roots.clone()));
+        server_config.set_single_cert(certs.clone(), key.clone())
+                     .map_err(config_error)?;
+        let mut client_config = ClientConfig::new();
+        client_config.root_store = roots;
+        client_config.set_single_client_cert(certs, key)
+                     .map_err(config_error)?;
+
+        Ok(RingTls { server_config:  Arc::new(server_config),
+                     connector:      Connector { client_config: Arc::new(client_config),
+                                                 server_name },
+                     swim_senders:   Arc::default(),
+                     next_sender_id: AtomicU64::new(0), })
+    }
+
+    /// Wrap a connection accepted from a member. The handshake, which checks the member's
+    /// certificate, happens on the first read, which must come within `HANDSHAKE_TIMEOUT`. Once
+    /// the channel has been read, `READ_TIMEOUT` applies instead.
+    pub fn accept(&self, stream: TcpStream) -> io::Result<ServerStream> {
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        Ok(StreamOwned::new(ServerSession::new(&self.server_config), stream))
+    }
+
+    /// Connect to the member listening on `addr`, to send it messages for `channel`.
+    pub fn connect(&self, addr: SocketAddr, channel: Channel) -> io::Result<ClientStream> {
+        self.connector.connect(addr, channel)
+    }
+
+    /// Whether the certificate the member at the other end of `stream` presented was issued to
+    /// `member_id`, being valid for `<member_id>.<server name>`
+    pub fn certifies_member(&self, stream: &ServerStream, member_id: &str) -> bool {
+        let server_name: &str = self.connector.server_name.as_ref().into();
+        let name = format!("{}.{}", member_id, server_name);
+        let name = match DNSNameRef::try_from_ascii_str(&name) {
+            Ok(name) => name,
+            Err(_) => return false,
+        };
+        stream.sess
+              .get_peer_certificates()
+              .and_then(|certs| certs.into_iter().next())
+              .map_or(false, |cert| {
+                  EndEntityCert::from(&cert.0).and_then(|cert| {
+                                                  cert.verify_is_valid_for_dns_name(name)
+                                              })
+                                              .is_ok()
+              })
+    }
+
+    /// Queue a SWIM message for the member listening on `addr`, to be sent by the thread sending
+    /// to it over a connection kept open for the messages which follow. A new connection first
+    /// tells the member `swim_port`, the port its replies are sent to. Messages are dropped, as
+    /// UDP datagrams would be, while the member's queue is full.
+    pub fn send_swim(&self, swim_port: u16, payload: &[u8], addr: SocketAddr) -> io::Result<()> {
+        let mut senders = self.swim_senders
+                              .lock()
+                              .expect("SWIM senders lock poisoned");
+        let mut msg = (swim_port, payload.to_vec());
+        if let Some(sender) = senders.get(&addr) {
+            match sender.tx.try_send(msg) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(_)) => {
+                    return Err(io::Error::new(io::ErrorKind::WouldBlock,
+                                              format!("SWIM messages to {} are backed up",
+                                                      addr)));
+                }
+                // The thread stopped after the connection went idle
+                Err(TrySendError::Disconnected(returned)) => msg = returned,
+            }
+        }
+        let (tx, rx) = mpsc::sync_channel(SWIM_QUEUE_LEN);
+        tx.try_send(msg).expect("A new SWIM queue has room");
+        let id = self.next_sender_id.fetch_add(1, Ordering::Relaxed);
+        let (connector, swim_senders) = (self.connector.clone(), self.swim_senders.clone());
+        thread::Builder::new().name(String::from("swim-tls-sender"))
+                              .spawn(move || {
+                                  send_swim_messages(&connector, &swim_senders, id, addr, &rx)
+                              })?;
+        senders.insert(addr, SwimSender { id, tx });
+        Ok(())
+    }
+}
+
+/// Send the SWIM messages queued for `addr` until none have been queued for
+/// `SWIM_CONNECTION_IDLE_TIMEOUT`, reconnecting when a write fails.
+fn send_swim_messages(connector: &Connector,
+                      senders: &Mutex<HashMap<SocketAddr, SwimSender>>,
+                      id: u64,
+                      addr: SocketAddr,
+                      rx: &Receiver<(u16, Vec<u8>)>) {
+    let mut connection: Option<ClientStream> = None;
+    while let Ok((swim_port, payload)) = rx.recv_timeout(SWIM_CONNECTION_IDLE_TIMEOUT) {
+        if let Some(stream) = connection.as_mut() {
+            if write_message(stream, &payload).is_ok() {
+                continue;
+            }
+        }
+        connection = connector.connect(addr, Channel::Swim)
+                              .and_then(|mut stream| {
+                                  stream.write_all(&swim_port.to_be_bytes())?;
+                                  write_message(&mut stream, &payload)?;
+                                  Ok(stream)
+                              })
+                              .map_err(|e| debug!("Cannot send SWIM message to {}: {}", addr, e))
+                              .ok();
+    }
+    let mut senders = senders.lock().expect("SWIM senders lock poisoned");
+    if senders.get(&addr).map_or(false, |sender| sender.id == id) {
+        senders.remove(&addr);
+    }
+}
+
+fn config_error(err: impl fmt::Display) -> Error { Error::RingTls(err.to_string()) }
+
+/// Listen for connections on `addr`. Like the SWIM socket, a listener bound to the unspecified
+/// IPv6 address (`[::]`) accepts IPv4 connections too.
+pub fn bind(addr: SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() && addr.ip().is_unspecified() {
+        socket.set_only_v6(false)?;
+    }
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(128)?;
+    Ok(socket.into())
+}
+
+/// Write `payload`, preceded by its length.
+pub fn write_message(stream: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    stream.write_all(&(payload.len() as u32).to_be_bytes())?;
+    stream.write_all(payload)?;
+    stream.flush()
+}
+
+/// Read a message written by `write_message`, refusing ones longer than `max_len`.
+pub fn read_message(stream: &mut impl Read, max_len: usize) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > max_len {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                  format!("message of {} bytes is over the \
+                                           limit of {}",
+                                          len, max_len)));
+    }
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Read the channel a member sent at the start of its connection.
+pub fn read_channel(stream: &mut impl Read) -> io::Result<Channel> {
+    let mut channel = [0; 1];
+    stream.read_exact(&mut channel)?;
+    match channel[0] {
+        1 => Ok(Channel::Swim),
+        2 => Ok(Channel::Gossip),
+        other => {
+            Err(io::Error::new(io::ErrorKind::InvalidData,
+                               format!("unknown channel {}", other)))
+        }
+    }
+}
+
+/// Read the port a member receives SWIM messages on, sent at the start of its connection.
+pub fn read_swim_port(stream: &mut impl Read) -> io::Result<u16> {
+    let mut port = [0; 2];
+    stream.read_exact(&mut port)?;
+    Ok(u16::from_be_bytes(port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let mut buf = Vec::new();
+        write_message(&mut buf, b"ping").unwrap();
+        write_message(&mut buf, b"").unwrap();
+        let mut reader = &buf[..];
+        assert_eq!(read_message(&mut reader, 4).unwrap(), b"ping");
+        assert_eq!(read_message(&mut reader, 4).unwrap(), b"");
+        assert!(read_message(&mut reader, 4).is_err());
+    }
+
+    #[test]
+    fn messages_over_the_limit_are_refused() {
+        let mut buf = Vec::new();
+        write_message(&mut buf, b"rumor").unwrap();
+        let err = read_message(&mut &buf[..], 4).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn channels_round_trip() {
+        for channel in &[Channel::Swim, Channel::Gossip] {
+            let buf = [*channel as u8];
+            assert_eq!(read_channel(&mut &buf[..]).unwrap(), *channel);
+        }
+        assert!(read_channel(&mut &[0][..]).is_err());
+    }
+
+    #[test]
+    fn missing_certificates_are_errors() {
+        let config = RingTlsConfig { cert_path:    PathBuf::from("/nonexistent/member.crt"),
+                                     key_path:     PathBuf::from("/nonexistent/member.key"),
+                                     ca_cert_path: PathBuf::from("/nonexistent/ca.crt"),
+                                     server_name:  DEFAULT_SERVER_NAME.to_string(), };
+        assert!(RingTls::new(&config).is_err());
+    }
+}
@@ -11,17 +11,23 @@ use habitat_butterfly::{error::Error,
                                 Election},
                         server::{timing::Timing,
                                  Server,
-                                 Suitability}};
+                                 Suitability},
+                        tls::{RingTls,
+                              RingTlsConfig,
+                              DEFAULT_SERVER_NAME}};
 use habitat_core::{crypto::keys::RingKey,
                    package::{Identifiable,
                              PackageIdent},
                    service::ServiceGroup};
 use std::{net::{IpAddr,
                 Ipv4Addr,
-                SocketAddr},
+                Ipv6Addr,
+                SocketAddr,
+                UdpSocket},
           ops::{Deref,
                 DerefMut,
                 Range},
+          path::Path,
           str::FromStr,
           sync::{Arc,
                  Mutex},
@@ -42,17 +48,20 @@ impl Suitability for NSuitability {
 /// * `Server::member` (write)
 /// * `RumorHeat::inner` (write)
 pub fn start_server_smw_rhw(name: &str, ring_key: Option<RingKey>, suitability: u64) -> Server {
-    let swim_port;
-    let gossip_port;
-    {
-        let mut port_guard = SERVER_PORT.lock().expect("SERVER_PORT mutex poisoned");
-        swim_port = *port_guard;
-        *port_guard += 1;
-        gossip_port = *port_guard;
-        *port_guard += 1;
-    }
-    let listen_swim = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), swim_port);
-    let listen_gossip = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), gossip_port);
+    start_server_on_smw_rhw(name, IpAddr::V4(Ipv4Addr::LOCALHOST), ring_key, suitability)
+}
+
+/// # Locking (see locking.md)
+/// * `Server::member` (write)
+/// * `RumorHeat::inner` (write)
+pub fn start_server_on_smw_rhw(name: &str,
+                               ip: IpAddr,
+                               ring_key: Option<RingKey>,
+                               suitability: u64)
+                               -> Server {
+    let (swim_port, gossip_port) = next_ports();
+    let listen_swim = SocketAddr::new(ip, swim_port);
+    let listen_gossip = SocketAddr::new(ip, gossip_port);
     let member = Member { swim_port,
                           gossip_port,
                           ..Default::default() };
@@ -60,6 +69,7 @@ pub fn start_server_smw_rhw(name: &str, ring_key: Option<RingKey>, suitability:
                                  listen_gossip,
                                  member,
                                  ring_key,
+                                 None,
                                  Some(String::from(name)),
                                  None,
                                  Arc::new(NSuitability(suitability))).unwrap();
@@ -68,6 +78,52 @@ pub fn start_server_smw_rhw(name: &str, ring_key: Option<RingKey>, suitability:
     server
 }
 
+/// Start a server in a ring using TLS, as the member `member_id`, with the certificate named
+/// `cert` in the test fixtures
+///
+/// # Locking (see locking.md)
+/// * `Server::member` (write)
+/// * `RumorHeat::inner` (write)
+pub fn start_tls_server_smw_rhw(member_id: &str, cert: &str) -> Server {
+    let (swim_port, gossip_port) = next_ports();
+    let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let member = Member { id: String::from(member_id),
+                          swim_port,
+                          gossip_port,
+                          ..Default::default() };
+    let mut server = Server::new(SocketAddr::new(ip, swim_port),
+                                 SocketAddr::new(ip, gossip_port),
+                                 member,
+                                 None,
+                                 None,
+                                 Some(String::from(member_id)),
+                                 None,
+                                 Arc::new(NSuitability(0))).unwrap();
+    server.use_tls(RingTls::new(&tls_config(cert)).expect("Cannot load test certificates"));
+    server.start_rsw_mlw_smw_rhw_msr(&Timing::default())
+          .expect("Cannot start server");
+    server
+}
+
+/// The configuration of the certificate named `cert` in the test fixtures, issued by the test
+/// certificate authority for `<cert>.habitat-ring`
+pub fn tls_config(cert: &str) -> RingTlsConfig {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls");
+    RingTlsConfig { cert_path:    fixtures.join(format!("{}.crt", cert)),
+                    key_path:     fixtures.join(format!("{}.key", cert)),
+                    ca_cert_path: fixtures.join("ca.crt"),
+                    server_name:  DEFAULT_SERVER_NAME.to_string(), }
+}
+
+fn next_ports() -> (u16, u16) {
+    let mut port_guard = SERVER_PORT.lock().expect("SERVER_PORT mutex poisoned");
+    let swim_port = *port_guard;
+    *port_guard += 1;
+    let gossip_port = *port_guard;
+    *port_guard += 1;
+    (swim_port, gossip_port)
+}
+
 /// # Locking (see locking.md)
 /// * `Server::member` (read)
 pub fn member_from_server_smr(server: &Server) -> Member {
@@ -79,10 +135,22 @@ pub fn member_from_server_smr(server: &Server) -> Member {
     //
     // TODO (CM): Investigate this further; does this have adverse
     // effects on our tests? Are we missing something we'd otherwise catch?
-    member.address = String::from("127.0.0.1");
+    //
+    // Servers listening on all interfaces are reached over IPv4
+    // loopback, which exercises dual-stack sockets when they listen
+    // on `[::]`.
+    let ip = server.swim_addr().ip();
+    member.address = if ip.is_unspecified() {
+        String::from("127.0.0.1")
+    } else {
+        ip.to_string()
+    };
     member
 }
 
+/// Whether this host can use IPv6 loopback at all
+pub fn ipv6_available() -> bool { UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).is_ok() }
+
 #[derive(Debug)]
 pub struct SwimNet {
     pub members: Vec<Server>,
@@ -112,6 +180,18 @@ impl SwimNet {
                                         .collect(), }
     }
 
+    /// Start a network whose members listen on the given addresses
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorHeat::inner` (write)
+    pub fn new_on_rhw(ips: Vec<IpAddr>) -> SwimNet {
+        SwimNet { members:
+                      ips.into_iter()
+                         .enumerate()
+                         .map(|(x, ip)| start_server_on_smw_rhw(&format!("{}", x), ip, None, 0))
+                         .collect(), }
+    }
+
     /// # Locking (see locking.md)
     /// * `RumorHeat::inner` (write)
     pub fn new_rhw(count: usize) -> SwimNet {
@@ -130,6 +210,18 @@ impl SwimNet {
         SwimNet { members }
     }
 
+    /// Start a ring using TLS, with a member for each pair of member ID and the certificate it
+    /// presents
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorHeat::inner` (write)
+    pub fn new_tls_rhw(members: &[(&str, &str)]) -> SwimNet {
+        SwimNet { members:
+                      members.iter()
+                             .map(|(member_id, cert)| start_tls_server_smw_rhw(member_id, cert))
+                             .collect(), }
+    }
+
     /// # Locking (see locking.md)
     /// * `Server::member` (read)
     pub fn connect_smr(&mut self, from_entry: usize, to_entry: usize) {
@@ -2,10 +2,14 @@
 mod common;
 mod encryption;
 mod rumor;
+mod tls;
 
 use common as btest;
 use habitat_butterfly::{self,
                         member::Health};
+use std::net::{IpAddr,
+               Ipv4Addr,
+               Ipv6Addr};
 
 #[test]
 fn two_members_meshed_confirm_one_member() {
@@ -121,3 +125,39 @@ fn ten_members_meshed_confirm_one_member() {
     net[0].pause();
     assert_wait_for_health_of_mlr!(net, 0, Health::Confirmed);
 }
+
+#[test]
+fn two_ipv6_members_meshed_share_services() {
+    if !btest::ipv6_available() {
+        return;
+    }
+    let mut net = btest::SwimNet::new_on_rhw(vec![IpAddr::V6(Ipv6Addr::LOCALHOST),
+                                                  IpAddr::V6(Ipv6Addr::LOCALHOST)]);
+    net.mesh_mlw_smr();
+    assert_wait_for_health_of_mlr!(net, 0, 1, Health::Alive);
+    assert_wait_for_health_of_mlr!(net, 1, 0, Health::Alive);
+    net.add_service(0, "core/witcher/1.2.3/20161208121212");
+    net.wait_for_rounds(2);
+    assert!(net[1].service_store
+                  .lock_rsr()
+                  .service_group("witcher.prod")
+                  .contains_id(net[0].member_id()));
+}
+
+#[test]
+fn dual_stack_member_meshes_with_ipv4_member() {
+    if !btest::ipv6_available() {
+        return;
+    }
+    let mut net = btest::SwimNet::new_on_rhw(vec![IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+                                                  IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+    net.mesh_mlw_smr();
+    assert_wait_for_health_of_mlr!(net, 0, 1, Health::Alive);
+    assert_wait_for_health_of_mlr!(net, 1, 0, Health::Alive);
+    net.add_service(1, "core/witcher/1.2.3/20161208121212");
+    net.wait_for_rounds(2);
+    assert!(net[0].service_store
+                  .lock_rsr()
+                  .service_group("witcher.prod")
+                  .contains_id(net[1].member_id()));
+}
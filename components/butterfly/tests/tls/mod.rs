@@ -0,0 +1,22 @@
+use crate::btest;
+use habitat_butterfly::member::Health;
+
+#[test]
+fn members_using_tls_mesh_and_share_services() {
+    let mut net = btest::SwimNet::new_tls_rhw(&[("member0", "member0"), ("member1", "member1")]);
+    net.connect_smr(0, 1);
+    assert_wait_for_health_of_mlr!(net, [0..2, 0..2], Health::Alive);
+    net.add_service(0, "core/beast/1.2.3/20161208121212");
+    net.wait_for_gossip_rounds(2);
+    assert!(net[1].service_store
+                  .lock_rsr()
+                  .service_group("beast.prod")
+                  .contains_id(net[0].member_id()));
+}
+
+#[test]
+fn members_presenting_the_certificate_of_another_member_are_refused() {
+    let mut net = btest::SwimNet::new_tls_rhw(&[("member0", "member0"), ("impostor", "member1")]);
+    net.connect_smr(0, 1);
+    assert_wait_for_health_of_mlr!(net, 0, 1, Health::Confirmed);
+}
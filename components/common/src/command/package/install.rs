@@ -318,9 +318,19 @@ pub async fn start<U>(ui: &mut U,
     key_cache.setup()?;
     debug!("install key cache: {}", key_cache.as_ref().display());
 
-    let api_client = Client::new(url, product, version, Some(fs_root_path))?;
+    // Offline mode, whether asked for here or for the whole process, never contacts Builder
+    let install_mode = if habitat_core::env::is_offline() {
+        &InstallMode::Offline
+    } else {
+        install_mode
+    };
+    let api_client = match install_mode {
+        InstallMode::Online => Some(Client::new(url, product, version, Some(fs_root_path))?),
+        InstallMode::Offline => None,
+    };
     let task = InstallTask { install_mode,
                              local_package_usage,
+                             url,
                              api_client,
                              channel,
                              fs_root_path,
@@ -423,7 +433,9 @@ fn read_install_hook_status(path: PathBuf) -> Result<Option<i32>> {
 struct InstallTask<'a> {
     install_mode:        &'a InstallMode,
     local_package_usage: &'a LocalPackageUsage,
-    api_client:          BuilderAPIClient,
+    url:                 &'a str,
+    /// The Builder client, which is only created when installing online
+    api_client:          Option<BuilderAPIClient>,
     channel:             &'a ChannelIdent,
     fs_root_path:        &'a Path,
     /// The path to the local artifact cache (e.g., /hab/cache/artifacts)
@@ -831,7 +843,7 @@ impl<'a> InstallTask<'a> {
                                                    channel: &ChannelIdent,
                                                    token: Option<&str>)
                                                    -> Result<FullyQualifiedPackageIdent> {
-        let origin_package = self.api_client
+        let origin_package = self.api_client()?
                                  .show_package((ident, target), channel, token)
                                  .await?;
         Ok(FullyQualifiedPackageIdent::try_from(origin_package)?)
@@ -848,7 +860,7 @@ impl<'a> InstallTask<'a> {
     {
         retry_builder_api!(async {
             ui.status(Status::Downloading, format!("{} for {}", ident, target))?;
-            self.api_client
+            self.api_client()?
                 .fetch_package((ident.as_ref(), target),
                                token,
                                self.artifact_cache_path,
@@ -877,7 +889,7 @@ impl<'a> InstallTask<'a> {
         } else {
             ui.status(Status::Downloading,
                       format!("{} public origin key", named_revision))?;
-            self.api_client
+            self.api_client()?
                 .fetch_origin_key(named_revision.name(),
                                   named_revision.revision(),
                                   token,
@@ -975,6 +987,12 @@ impl<'a> InstallTask<'a> {
 
     fn is_offline(&self) -> bool { self.install_mode == &InstallMode::Offline }
 
+    fn api_client(&self) -> api_client::Result<&BuilderAPIClient> {
+        self.api_client
+            .as_ref()
+            .ok_or_else(|| api_client::Error::Offline(self.url.to_string()))
+    }
+
     /// We may not want to use currently-installed packages if one
     /// can't be found in Builder in the given channel.
     ///
@@ -1026,7 +1044,7 @@ impl<'a> InstallTask<'a> {
                                          -> Result<Vec<(String, String)>> {
         let mut res = Vec::new();
 
-        let channels = match self.api_client
+        let channels = match self.api_client()?
                                  .list_channels(&ident.hacky_get_origin(), false)
                                  .await
         {
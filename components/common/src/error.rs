@@ -64,6 +64,8 @@ pub enum Error {
     BadGlyphStyle(String),
     CantUploadGossipToml,
     ChannelNotFound,
+    /// Occurs when a package's configuration schema cannot be read or compiled
+    ConfigSchema(String),
     CryptoKeyError(String),
     EditorEnv(env::VarError),
     EditStatus,
@@ -77,7 +79,11 @@ pub enum Error {
         hook:          &'static str,
         error:         CommandExecutionError,
     },
+    /// Occurs when a configuration does not satisfy its package's schema
+    InvalidConfig(Vec<String>),
     InvalidEventStreamToken(String),
+    /// Occurs when the redaction configuration cannot be read or its patterns are invalid
+    InvalidRedactionConfig(PathBuf, String),
     /// Occurs when making lower level IO calls.
     IO(io::Error),
     /// Errors when joining paths :)
@@ -155,6 +161,7 @@ impl fmt::Display for Error {
                 "Can't upload gossip.toml, it's a reserved file name".to_string()
             }
             Error::ChannelNotFound => "Channel not found".to_string(),
+            Error::ConfigSchema(ref e) => format!("Invalid configuration schema: {}", e),
             Error::CryptoKeyError(ref s) => format!("Missing or invalid key: {}", s),
             Error::EditorEnv(ref e) => format!("Missing EDITOR environment variable: {}", e),
             Error::EditStatus => "Failed edit text command".to_string(),
@@ -173,9 +180,16 @@ impl fmt::Display for Error {
                                 ref error, } => {
                 format!("{} {} hook failed: {}", package_ident, hook, error)
             }
+            Error::InvalidConfig(ref errors) => {
+                format!("Configuration does not satisfy the package's schema: {}",
+                        errors.join(", "))
+            }
             Error::InvalidEventStreamToken(ref s) => {
                 format!("Invalid event stream token provided: '{}'", s)
             }
+            Error::InvalidRedactionConfig(ref path, ref e) => {
+                format!("Invalid redaction configuration {}: {}", path.display(), e)
+            }
             Error::IO(ref err) => format!("{}", err),
             Error::JoinPathsError(ref err) => format!("{}", err),
             Error::NamedPipeTimeoutOnStart(ref group, ref hook, ref err) => {
@@ -205,7 +219,7 @@ impl fmt::Display for Error {
                 format!("Unable to serialize rendering context, {}", e)
             }
             Error::ListenCtlResolutionError(ref sup_addr, ref err) => {
-                format!("Failed to resolve ctl address '{}': {}", sup_addr, err,)
+                format!("Failed to resolve address '{}': {}", sup_addr, err,)
             }
             Error::RootRequired => {
                 "Root or administrator permissions required to complete operation".to_string()
@@ -32,6 +32,7 @@ pub mod liveliness_checker;
 pub mod output;
 pub mod owning_refs;
 pub mod package_graph;
+pub mod redaction;
 pub mod templating;
 pub mod types;
 pub mod ui;
@@ -85,6 +86,7 @@ bitflags::bitflags! {
         const SERVICE_CONFIG_FILES       = 0b0000_0001_0000;
         const OFFLINE_INSTALL            = 0b0000_0100_0000;
         const IGNORE_LOCAL               = 0b0000_1000_0000;
+        const DEV_MODE                   = 0b0001_0000_0000;
         const TRIGGER_ELECTION           = 0b0010_0000_0000;
         const STRUCTOPT_CLI              = 0b0100_0000_0000;
         const NO_NAMED_PIPE_HEALTH_CHECK = 0b1000_0000_0000;
@@ -99,6 +101,7 @@ lazy_static! {
                            (FeatureFlag::REDACT_HTTP, "HAB_FEAT_REDACT_HTTP"),
                            (FeatureFlag::OFFLINE_INSTALL, "HAB_FEAT_OFFLINE_INSTALL"),
                            (FeatureFlag::IGNORE_LOCAL, "HAB_FEAT_IGNORE_LOCAL"),
+                           (FeatureFlag::DEV_MODE, "HAB_FEAT_DEV_MODE"),
                            (FeatureFlag::TRIGGER_ELECTION, "HAB_FEAT_TRIGGER_ELECTION"),
                            (FeatureFlag::STRUCTOPT_CLI, "HAB_FEAT_STRUCTOPT_CLI"),
                            (FeatureFlag::NO_NAMED_PIPE_HEALTH_CHECK,
@@ -17,8 +17,12 @@
 //! on the tin :) JSON-formatted output emits this information as a
 //! JSON object. It ignores the coloring option, and does _not_ ever log
 //! with ANSI color codes, but does honor the verbose flag.
+//!
+//! Sensitive values are redacted from the content of every line, see
+//! `crate::redaction`.
 
-use crate::PROGRAM_NAME;
+use crate::{redaction,
+            PROGRAM_NAME};
 use serde::{ser::SerializeMap,
             Serialize,
             Serializer};
@@ -184,7 +188,7 @@ impl<'a> StructuredOutput<'a> {
                 if let OutputFormat::Color(ref color_spec) = self.format {
                     writer.set_color(color_spec)?;
                 }
-                writer.write_all(self.content.as_bytes())?;
+                writer.write_all(redaction::redact_text(self.content).as_bytes())?;
                 writer.reset()?;
                 writer.flush()
             }
@@ -212,7 +216,7 @@ impl<'a> Serialize for StructuredOutput<'a> {
             map.serialize_entry("line", &line)?;
             map.serialize_entry("column", &column)?;
         }
-        map.serialize_entry("content", &self.content)?;
+        map.serialize_entry("content", &redaction::redact_text(self.content))?;
 
         map.end()
     }
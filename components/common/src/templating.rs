@@ -70,10 +70,21 @@ pub type RenderResult<T> = result::Result<T, RenderError>;
 pub struct TemplateRenderer(Handlebars);
 
 impl TemplateRenderer {
-    pub fn new() -> Self {
+    /// A renderer for templates outside of a service, such as those of `hab plan render`, whose
+    /// `stableRandom` helper renders a new value every time
+    pub fn new() -> Self { Self::with_stable_random(helpers::EPHEMERAL_RANDOM) }
+
+    /// A renderer for the configuration and hooks of a service, whose `stableRandom` helper keeps
+    /// its values in the data directory of the service
+    pub fn for_service() -> Self { Self::with_stable_random(helpers::STABLE_RANDOM) }
+
+    fn with_stable_random(stable_random: helpers::StableRandomHelper) -> Self {
         let mut handlebars = Handlebars::new();
         handlebars.register_helper("eachAlive", Box::new(helpers::EACH_ALIVE));
+        handlebars.register_helper("membersWith", Box::new(helpers::MEMBERS_WITH));
+        handlebars.register_helper("pkgAtLeast", Box::new(helpers::PKG_AT_LEAST));
         handlebars.register_helper("pkgPathFor", Box::new(helpers::PKG_PATH_FOR));
+        handlebars.register_helper("stableRandom", Box::new(stable_random));
         handlebars.register_helper("strConcat", Box::new(helpers::STR_CONCAT));
         handlebars.register_helper("strJoin", Box::new(helpers::STR_JOIN));
         handlebars.register_helper("strReplace", Box::new(helpers::STR_REPLACE));
@@ -82,6 +93,7 @@ impl TemplateRenderer {
         handlebars.register_helper("toJson", Box::new(helpers::TO_JSON));
         handlebars.register_helper("toToml", Box::new(helpers::TO_TOML));
         handlebars.register_helper("toYaml", Box::new(helpers::TO_YAML));
+        handlebars.register_helper("versionCompare", Box::new(helpers::VERSION_COMPARE));
 
         handlebars.register_escape_fn(never_escape);
         TemplateRenderer(handlebars)
@@ -19,6 +19,7 @@ use std::{self,
           path::{Path,
                  PathBuf},
           result};
+use valico::json_schema;
 
 static LOGKEY: &str = "CF";
 static ENV_VAR_PREFIX: &str = "HAB";
@@ -28,6 +29,9 @@ static ENV_VAR_PREFIX: &str = "HAB";
 /// is deeper than this value crosses into overly complex territory when describing configuration
 /// for a single service.
 static TOML_MAX_MERGE_DEPTH: u16 = 30;
+/// The JSON schema a package may ship next to its `default.toml`, which a reloaded `user.toml`
+/// must satisfy to be taken
+pub const CONFIG_SCHEMA_FILE: &str = "config_schema.json";
 #[cfg(unix)]
 pub const CONFIG_PERMISSIONS: u32 = 0o740;
 #[cfg(unix)]
@@ -97,6 +101,8 @@ pub struct Cfg {
     /// The path to an optional dev-time configuration directory that
     /// is being used.
     override_config_dir:    Option<PathBuf>,
+    /// The schema of the configuration loaded from a Package's `config_schema.json`
+    schema:                 Option<serde_json::Value>,
 }
 
 impl Cfg {
@@ -104,12 +110,12 @@ impl Cfg {
         where P: PackageConfigPaths
     {
         let override_config_dir = config_from.map(Clone::clone);
-        let default = {
+        let (default, schema) = {
             let pkg_root = match override_config_dir {
                 Some(ref path) => Cow::Borrowed(path),
                 None => Cow::Owned(package.default_config_dir()),
             };
-            Self::load_default(pkg_root.as_ref())?
+            (Self::load_default(pkg_root.as_ref())?, Self::load_schema(pkg_root.as_ref())?)
         };
         let user_config_path = Self::determine_user_config_path(package);
         let user = Self::load_user(user_config_path.get_path())?;
@@ -120,7 +126,8 @@ impl Cfg {
                   environment,
                   gossip_incarnation: 0,
                   user_config_path,
-                  override_config_dir })
+                  override_config_dir,
+                  schema })
     }
 
     /// Validates a service configuration against a configuration interface.
@@ -169,13 +176,14 @@ impl Cfg {
     pub fn update_defaults_from_package<P>(&mut self, package: &P) -> Result<bool>
         where P: PackageConfigPaths
     {
-        let incoming_defaults = {
+        let (incoming_defaults, incoming_schema) = {
             let pkg_root = match self.override_config_dir {
                 Some(ref path) => Cow::Borrowed(path),
                 None => Cow::Owned(package.default_config_dir()),
             };
-            Self::load_default(pkg_root.as_ref())?
+            (Self::load_default(pkg_root.as_ref())?, Self::load_schema(pkg_root.as_ref())?)
         };
+        self.schema = incoming_schema;
 
         if incoming_defaults != self.default {
             self.default = incoming_defaults;
@@ -259,6 +267,18 @@ impl Cfg {
         Self::load_toml_file(config_from, "default.toml")
     }
 
+    fn load_schema(config_from: &Path) -> Result<Option<serde_json::Value>> {
+        let path = config_from.join(CONFIG_SCHEMA_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let file = File::open(&path)?;
+        match serde_json::from_reader(file) {
+            Ok(schema) => Ok(Some(schema)),
+            Err(e) => Err(Error::ConfigSchema(format!("{}, {}", path.display(), e))),
+        }
+    }
+
     fn determine_user_config_path<P: PackageConfigPaths>(package: &P) -> UserConfigPath {
         let recommended_dir = package.recommended_user_config_dir();
         let recommended_path = recommended_dir.join(USER_CONFIG_FILE);
@@ -290,12 +310,43 @@ impl Cfg {
     }
 
     /// Reloads the user configuration file.
+    ///
+    /// If the package has a configuration schema, the configuration resulting from the new user
+    /// configuration must satisfy it. Otherwise, an error is returned and the previous user
+    /// configuration is kept, as it is when the new one cannot be parsed.
     pub fn reload_user(&mut self) -> Result<()> {
         let user = Self::load_user(self.user_config_path.get_path())?;
-        self.user = user;
+        let reloaded = Self { user,
+                              ..self.clone() };
+        reloaded.check_schema()?;
+        *self = reloaded;
         Ok(())
     }
 
+    /// Checks the whole configuration against the package's schema, if it has one.
+    fn check_schema(&self) -> Result<()> {
+        let schema = match self.schema {
+            Some(ref schema) => schema.clone(),
+            None => return Ok(()),
+        };
+        let mut scope = json_schema::scope::Scope::new();
+        let schema = scope.compile_and_return(schema, false)
+                          .map_err(|e| Error::ConfigSchema(format!("{:?}", e)))?;
+        let cfg = serde_json::to_value(self).map_err(|e| Error::ConfigSchema(e.to_string()))?;
+        let state = schema.validate(&cfg);
+        if state.is_valid() {
+            return Ok(());
+        }
+        let errors = state.errors
+                          .iter()
+                          .map(|e| {
+                              let detail = e.get_detail().unwrap_or_else(|| e.get_title());
+                              format!("{} {}", e.get_path(), detail)
+                          })
+                          .collect();
+        Err(Error::InvalidConfig(errors))
+    }
+
     fn load_environment(package_name: &str) -> Result<Option<toml::value::Table>> {
         let var_name = format!("{}_{}", ENV_VAR_PREFIX, package_name).to_ascii_uppercase()
                                                                      .replace("-", "_");
@@ -399,9 +450,9 @@ impl CfgRenderer {
         if templates_path.as_ref().is_dir() {
             load_templates(templates_path.as_ref(),
                            &PathBuf::new(),
-                           TemplateRenderer::new()).map(CfgRenderer)
+                           TemplateRenderer::for_service()).map(CfgRenderer)
         } else {
-            Ok(CfgRenderer(TemplateRenderer::new()))
+            Ok(CfgRenderer(TemplateRenderer::for_service()))
         }
     }
 
@@ -936,6 +987,42 @@ mod test {
         assert_eq!(cfg.user, Some(toml_from_str(toml)));
     }
 
+    const PORT_SCHEMA: &str = r#"{
+  "type": "object",
+  "properties": { "port": { "type": "integer" } }
+}"#;
+
+    #[test]
+    fn reload_user_toml_satisfying_the_schema() {
+        let cfg_data = CfgTestData::new();
+        write_toml(&cfg_data.pkg.default_config_dir().join(CONFIG_SCHEMA_FILE),
+                   PORT_SCHEMA);
+        write_toml(&cfg_data.rucp, "port = 80");
+        let mut cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+
+        write_toml(&cfg_data.rucp, "port = 8080");
+        cfg.reload_user().expect("reload user config");
+
+        assert_eq!(cfg.user, Some(toml_from_str("port = 8080")));
+    }
+
+    #[test]
+    fn keep_previous_user_toml_not_satisfying_the_schema() {
+        let cfg_data = CfgTestData::new();
+        write_toml(&cfg_data.pkg.default_config_dir().join(CONFIG_SCHEMA_FILE),
+                   PORT_SCHEMA);
+        write_toml(&cfg_data.rucp, "port = 80");
+        let mut cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+
+        write_toml(&cfg_data.rucp, "port = \"eighty\"");
+        match cfg.reload_user() {
+            Err(Error::InvalidConfig(errors)) => assert_eq!(errors.len(), 1),
+            other => panic!("Expected Error::InvalidConfig, got {:?}", other),
+        }
+
+        assert_eq!(cfg.user, Some(toml_from_str("port = 80")));
+    }
+
     #[test]
     fn serialize_config() {
         let concrete_path = TempDir::new().expect("create temp dir");
@@ -34,7 +34,8 @@
 //! anything else, and so, they _can't_ be used for anything else.
 
 use super::{config::Cfg,
-            package::{Env,
+            package::{Dependency,
+                      Env,
                       Pkg}};
 use crate::hcore::package::{FullyQualifiedPackageIdent,
                             Identifiable,
@@ -61,8 +62,9 @@ use std::{borrow::Cow,
 /// as required.
 #[derive(Clone, Debug, Serialize)]
 pub struct RenderContext<'a> {
-    pkg: Package<'a>,
-    cfg: Cow<'a, Cfg>,
+    pkg:  Package<'a>,
+    pkgs: Pkgs<'a>,
+    cfg:  Cow<'a, Cfg>,
 }
 
 impl<'a> RenderContext<'a> {
@@ -76,8 +78,9 @@ impl<'a> RenderContext<'a> {
     /// nature of `Cfg`s behavior, we should be safe relying on that
     /// implementation for the foreseeable future.
     pub fn new(pkg: &'a Pkg, cfg: &'a Cfg) -> RenderContext<'a> {
-        RenderContext { pkg: Package::from_pkg(pkg),
-                        cfg: Cow::Borrowed(cfg), }
+        RenderContext { pkg:  Package::from_pkg(pkg),
+                        pkgs: Pkgs::from_pkg(pkg),
+                        cfg:  Cow::Borrowed(cfg), }
     }
 }
 
@@ -92,6 +95,7 @@ impl<'a> RenderContext<'a> {
 struct Package<'a> {
     ident:                   Cow<'a, FullyQualifiedPackageIdent>,
     deps:                    Cow<'a, Vec<PackageIdent>>,
+    strict_deps:             Cow<'a, bool>,
     env:                     Cow<'a, Env>,
     // TODO (CM): Ideally, this would be Vec<u16>, since they're ports.
     exposes:                 Cow<'a, Vec<String>>,
@@ -115,6 +119,7 @@ impl<'a> Package<'a> {
     fn from_pkg(pkg: &'a Pkg) -> Self {
         Package { ident:                   Cow::Borrowed(&pkg.ident),
                   deps:                    Cow::Borrowed(&pkg.deps),
+                  strict_deps:             Cow::Borrowed(&pkg.strict_deps),
                   env:                     Cow::Borrowed(&pkg.env),
                   exposes:                 Cow::Borrowed(&pkg.exposes),
                   exports:                 Cow::Borrowed(&pkg.exports),
@@ -158,6 +163,7 @@ impl<'a> Serialize for Package<'a> {
         map.serialize_entry("release", &self.ident.release())?;
 
         map.serialize_entry("deps", &self.deps)?;
+        map.serialize_entry("strict_deps", &self.strict_deps)?;
         map.serialize_entry("env", &self.env)?;
 
         map.serialize_entry("exposes", &self.exposes)?;
@@ -182,6 +188,27 @@ impl<'a> Serialize for Package<'a> {
 
 ////////////////////////////////////////////////////////////////////////
 
+/// Templating proxy for the runtime dependencies of a `Pkg`.
+///
+/// Exposed to users under the `pkgs` key, keyed by the origin and
+/// name of each dependency (e.g., `{{pkgs.[core/openssl].path}}`).
+/// A package with strict dependencies only exposes the ones declared
+/// in its plan.
+#[derive(Clone, Debug, Serialize)]
+struct Pkgs<'a>(BTreeMap<String, Cow<'a, Dependency>>);
+
+impl<'a> Pkgs<'a> {
+    fn from_pkg(pkg: &'a Pkg) -> Self {
+        Pkgs(pkg.dependencies
+                .iter()
+                .filter(|dep| dep.declared || !pkg.strict_deps)
+                .map(|dep| (dep.key(), Cow::Borrowed(dep)))
+                .collect())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -274,8 +301,20 @@ two = 2
         export_hash.insert("blah".into(), "stuff.thing".into());
         export_hash.insert("port".into(), "test_port".into());
 
+        let pkgs = deps.iter()
+                       .map(|dep| {
+                           let path = PathBuf::from("/hab/pkgs").join(dep.to_string());
+                           let dep = Dependency { ident: dep.clone(),
+                                                  path,
+                                                  exports: BTreeMap::new(),
+                                                  declared: dep.name == "pkg1" };
+                           (dep.key(), Cow::Owned(dep))
+                       })
+                       .collect();
+
         let pkg = Package { ident:                   Cow::Owned(ident),
                             deps:                    Cow::Owned(deps),
+                            strict_deps:             Cow::Owned(false),
                             env:                     Cow::Owned(env_hash.into()),
                             exposes:                 Cow::Owned(vec!["1234".into(),
                                                                      "8000".into(),
@@ -300,6 +339,7 @@ two = 2
         let cfg = Cfg::new(&test_pkg, None).expect("create config");
 
         RenderContext { pkg,
+                        pkgs: Pkgs(pkgs),
                         cfg: Cow::Owned(cfg) }
     }
 
@@ -357,4 +397,14 @@ two = 2
 
         assert_eq!(output, "core");
     }
+
+    #[test]
+    fn renders_dependency_metadata() {
+        let ctx = default_render_context();
+
+        assert_eq!(render("{{pkgs.[test/pkg2].version}}", &ctx), "2.0.0");
+        assert_eq!(render("{{pkgs.[test/pkg1].declared}}", &ctx), "true");
+        assert_eq!(render("{{pkgs.[test/pkg3].path}}", &ctx),
+                   "/hab/pkgs/test/pkg3/3.0.0/20180321150416");
+    }
 }
@@ -1,5 +1,8 @@
 mod each_alive;
+mod members_with;
+mod pkg_at_least;
 mod pkg_path_for;
+mod stable_random;
 mod str_concat;
 mod str_join;
 mod str_replace;
@@ -8,9 +11,15 @@ mod to_lowercase;
 mod to_toml;
 mod to_uppercase;
 mod to_yaml;
+mod version_compare;
 
 pub use self::{each_alive::EACH_ALIVE,
+               members_with::MEMBERS_WITH,
+               pkg_at_least::PKG_AT_LEAST,
                pkg_path_for::PKG_PATH_FOR,
+               stable_random::{StableRandomHelper,
+                               EPHEMERAL_RANDOM,
+                               STABLE_RANDOM},
                str_concat::STR_CONCAT,
                str_join::STR_JOIN,
                str_replace::STR_REPLACE,
@@ -18,10 +27,22 @@ pub use self::{each_alive::EACH_ALIVE,
                to_lowercase::TO_LOWERCASE,
                to_toml::TO_TOML,
                to_uppercase::TO_UPPERCASE,
-               to_yaml::TO_YAML};
+               to_yaml::TO_YAML,
+               version_compare::VERSION_COMPARE};
+use super::RenderResult;
+use crate::hcore::package::{ident::version_sort,
+                            Identifiable,
+                            PackageIdent};
+use handlebars::{Handlebars,
+                 Helper,
+                 RenderContext,
+                 RenderError,
+                 Renderable};
 use serde::Serialize;
 use serde_json::{self,
                  Value as Json};
+use std::{cmp::Ordering,
+          str::FromStr};
 
 // Taken from `handlebars::context::JsonTruthy`. The trait is marked public but it's in a private
 // module. It's super useful so let's pull it into here.
@@ -48,3 +69,51 @@ fn to_json<T>(src: &T) -> Json
 {
     serde_json::to_value(src).unwrap_or(Json::Null)
 }
+
+/// Compare two version strings with the same ordering used for package idents
+fn compare_versions(a: &str, b: &str, helper: &str) -> RenderResult<Ordering> {
+    version_sort(a, b).map_err(|_| {
+                          RenderError::new(format!("Invalid version \"{}\" or \"{}\" for \"{}\"",
+                                                   a, b, helper))
+                      })
+}
+
+/// Fail the render when the package has strict dependencies and `ident` doesn't resolve to one of
+/// the dependencies declared in its plan, so that undeclared runtime dependencies are caught when
+/// the template renders rather than when the service uses them
+fn ensure_declared_dep(ident: &PackageIdent,
+                       rc: &RenderContext<'_>,
+                       helper: &str)
+                       -> RenderResult<()> {
+    let context = rc.context();
+    let data = context.data();
+    if !data["pkg"]["strict_deps"].as_bool().unwrap_or(false) {
+        return Ok(());
+    }
+    let declared = data["pkgs"].as_object()
+                               .into_iter()
+                               .flat_map(|pkgs| pkgs.values())
+                               .filter(|dep| dep["declared"].as_bool().unwrap_or(false))
+                               .filter_map(|dep| dep["ident"].as_str())
+                               .filter_map(|dep| PackageIdent::from_str(dep).ok())
+                               .any(|dep| dep.satisfies(ident));
+    if declared {
+        return Ok(());
+    }
+    let message = format!("\"{}\" refers to {}, which is not a dependency declared in the plan",
+                          helper, ident);
+    Err(RenderError::new(message))
+}
+
+/// Render the block of a block helper if `condition` holds, or its `{{else}}` block otherwise
+fn render_block_if(condition: bool,
+                   h: &Helper<'_>,
+                   r: &Handlebars,
+                   rc: &mut RenderContext<'_>)
+                   -> RenderResult<()> {
+    let template = if condition { h.template() } else { h.inverse() };
+    match template {
+        Some(template) => template.render(r, rc),
+        None => Ok(()),
+    }
+}
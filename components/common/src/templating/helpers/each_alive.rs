@@ -24,15 +24,9 @@ impl HelperDef for EachAliveHelper {
             let rendered = match (value.value().is_truthy(), value.value()) {
                 (true, &Json::Array(ref list)) => {
                     let alive_members: Vec<Json> = list.iter()
-                                                       .filter_map(|m| {
-                                                           m.as_object().and_then(|m| {
-                                if m.contains_key("alive") && m["alive"].as_bool().unwrap() {
-                                    Some(to_json(&m))
-                                } else {
-                                    None
-                                }
-                            })
-                                                       })
+                                                       .filter_map(Json::as_object)
+                                                       .filter(|m| is_serving(m))
+                                                       .map(|m| to_json(&m))
                                                        .collect();
                     let len = alive_members.len();
                     for (i, alive_member) in alive_members.iter().enumerate() {
@@ -57,7 +51,7 @@ impl HelperDef for EachAliveHelper {
                 }
                 (true, &Json::Object(ref obj)) => {
                     let mut first: bool = true;
-                    if !obj.contains_key("alive") || !obj["alive"].as_bool().unwrap() {
+                    if !is_serving(obj) {
                         return Ok(());
                     }
                     for k in obj.keys() {
@@ -117,4 +111,35 @@ impl HelperDef for EachAliveHelper {
     }
 }
 
+/// Whether a member is alive, and its service is not in maintenance
+fn is_serving(member: &serde_json::Map<String, Json>) -> bool {
+    member.get("alive").and_then(Json::as_bool) == Some(true)
+    && member.get("maintenance").and_then(Json::as_bool) != Some(true)
+}
+
 pub static EACH_ALIVE: EachAliveHelper = EachAliveHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_each_alive_helper_skips_members_in_maintenance() {
+        let json = json!({
+            "members": [
+                { "alive": true, "sys": { "ip": "10.0.0.1" } },
+                { "alive": true, "maintenance": true, "sys": { "ip": "10.0.0.2" } },
+                { "alive": false, "sys": { "ip": "10.0.0.3" } },
+                { "alive": true, "maintenance": false, "sys": { "ip": "10.0.0.4" } }
+            ]
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("eachAlive", Box::new(EACH_ALIVE));
+        let rendered = handlebars.template_render("{{#eachAlive members as \
+                                                   |m|}}{{m.sys.ip}}{{#unless \
+                                                   @last}},{{/unless}}{{/eachAlive}}",
+                                                  &json)
+                                 .unwrap();
+        assert_eq!(rendered, "10.0.0.1,10.0.0.4");
+    }
+}
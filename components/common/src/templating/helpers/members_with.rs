@@ -0,0 +1,107 @@
+use super::{super::RenderResult,
+            to_json};
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError,
+                 Renderable};
+use serde_json::Value as Json;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy)]
+pub struct MembersWithHelper;
+
+impl HelperDef for MembersWithHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let list = h.param(0)
+                    .map(|v| v.value())
+                    .ok_or_else(|| RenderError::new("Expected 3 parameters for \"membersWith\""))?;
+        let key = h.param(1)
+                   .and_then(|v| v.value().as_str())
+                   .ok_or_else(|| RenderError::new("Expected 3 parameters for \"membersWith\""))?;
+        let value = h.param(2)
+                     .and_then(|v| v.value().as_str())
+                     .ok_or_else(|| RenderError::new("Expected 3 parameters for \"membersWith\""))?;
+        let members: Vec<&Json> = match list {
+            Json::Array(ref list) => list.iter().filter(|m| has_label(m, key, value)).collect(),
+            Json::Null => Vec::new(),
+            _ => {
+                return Err(RenderError::new("Param type is not iterable for \"membersWith\""));
+            }
+        };
+        if members.is_empty() {
+            if let Some(else_template) = h.inverse() {
+                else_template.render(r, rc)?;
+            }
+            return Ok(());
+        }
+        if let Some(template) = h.template() {
+            rc.promote_local_vars();
+            let len = members.len();
+            for (i, member) in members.into_iter().enumerate() {
+                let mut local_rc = rc.derive();
+                local_rc.set_local_var("@first".to_string(), to_json(&(i == 0usize)));
+                local_rc.set_local_var("@last".to_string(), to_json(&(i == len - 1)));
+                local_rc.set_local_var("@index".to_string(), to_json(&i));
+
+                if let Some(block_param) = h.block_param() {
+                    let mut map = BTreeMap::new();
+                    map.insert(block_param.to_string(), member.clone());
+                    local_rc.push_block_context(&map)?;
+                }
+
+                template.render(r, &mut local_rc)?;
+
+                if h.block_param().is_some() {
+                    local_rc.pop_block_context();
+                }
+            }
+            rc.demote_local_vars();
+        }
+        Ok(())
+    }
+}
+
+fn has_label(member: &Json, key: &str, value: &str) -> bool {
+    member.get("sys")
+          .and_then(|sys| sys.get("labels"))
+          .and_then(|labels| labels.get(key))
+          .and_then(Json::as_str)
+    == Some(value)
+}
+
+pub static MEMBERS_WITH: MembersWithHelper = MembersWithHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let json = json!({
+            "members": [
+                { "sys": { "ip": "10.0.0.1", "labels": { "disk": "ssd" } } },
+                { "sys": { "ip": "10.0.0.2", "labels": { "disk": "hdd" } } },
+                { "sys": { "ip": "10.0.0.3" } },
+                { "sys": { "ip": "10.0.0.4", "labels": { "disk": "ssd", "role": "cache" } } }
+            ]
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("membersWith", Box::new(MEMBERS_WITH));
+        handlebars.template_render(template, &json).unwrap()
+    }
+
+    #[test]
+    fn test_members_with_helper() {
+        assert_eq!("10.0.0.1,10.0.0.4",
+                   render("{{#membersWith members \"disk\" \"ssd\" as \
+                           |m|}}{{m.sys.ip}}{{#unless @last}},{{/unless}}{{/membersWith}}"));
+    }
+
+    #[test]
+    fn test_members_with_helper_renders_else_without_matches() {
+        assert_eq!("none",
+                   render("{{#membersWith members \"disk\" \"nvme\" as \
+                           |m|}}{{m.sys.ip}}{{else}}none{{/membersWith}}"));
+    }
+}
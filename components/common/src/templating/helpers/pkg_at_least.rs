@@ -0,0 +1,120 @@
+use super::{super::RenderResult,
+            compare_versions,
+            ensure_declared_dep,
+            render_block_if};
+use crate::hcore::package::{Identifiable,
+                            PackageIdent};
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use std::{cmp::Ordering,
+          str::FromStr};
+
+/// Renders its block if the dependency best resolved from the given package identifier is at
+/// least the given version, and its `{{else}}` block otherwise, including when the package is
+/// not one of the dependencies.
+#[derive(Clone, Copy)]
+pub struct PkgAtLeastHelper;
+
+impl HelperDef for PkgAtLeastHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let ident =
+            h.param(0)
+             .and_then(|v| v.value().as_str())
+             .and_then(|v| PackageIdent::from_str(v).ok())
+             .ok_or_else(|| RenderError::new("Invalid package identifier for \"pkgAtLeast\""))?;
+        ensure_declared_dep(&ident, rc, "pkgAtLeast")?;
+        let min_version =
+            h.param(1)
+             .and_then(|v| v.value().as_str())
+             .ok_or_else(|| RenderError::new("Expected a version for \"pkgAtLeast\""))?
+             .to_string();
+        let deps =
+            serde_json::from_value::<Vec<PackageIdent>>(rc.context().data()["pkg"]["deps"].clone())
+                .unwrap_or_default();
+        let version = deps.iter()
+                          .find(|dep| dep.satisfies(&ident))
+                          .and_then(|dep| dep.version.clone());
+        let at_least = match version {
+            Some(version) => {
+                compare_versions(&version, &min_version, "pkgAtLeast")? != Ordering::Less
+            }
+            None => false,
+        };
+        render_block_if(at_least, h, r, rc)
+    }
+}
+
+pub static PKG_AT_LEAST: PkgAtLeastHelper = PkgAtLeastHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let json = json!({
+            "pkg": {
+                "deps": [
+                    { "origin": "core", "name": "openssl", "version": "3.0.2",
+                      "release": "20220315000000" },
+                    { "origin": "core", "name": "glibc", "version": "2.29",
+                      "release": "20200305172459" }
+                ]
+            }
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("pkgAtLeast", Box::new(PKG_AT_LEAST));
+        handlebars.template_render(template, &json).unwrap()
+    }
+
+    #[test]
+    fn test_pkg_at_least_helper() {
+        assert_eq!("new",
+                   render("{{#pkgAtLeast \"core/openssl\" \"3.0\"}}new{{else}}old{{/pkgAtLeast}}"));
+        assert_eq!("old",
+                   render("{{#pkgAtLeast \"core/glibc\" \"2.34\"}}new{{else}}old{{/pkgAtLeast}}"));
+    }
+
+    #[test]
+    fn test_pkg_at_least_helper_renders_else_for_missing_deps() {
+        assert_eq!("missing",
+                   render("{{#pkgAtLeast \"core/zlib\" \
+                           \"1.0\"}}present{{else}}missing{{/pkgAtLeast}}"));
+    }
+
+    fn render_strict(template: &str) -> Option<String> {
+        let json = json!({
+            "pkg": {
+                "strict_deps": true,
+                "deps": [
+                    { "origin": "core", "name": "openssl", "version": "3.0.2",
+                      "release": "20220315000000" },
+                    { "origin": "core", "name": "glibc", "version": "2.29",
+                      "release": "20200305172459" }
+                ]
+            },
+            "pkgs": {
+                "core/openssl": { "ident": "core/openssl/3.0.2/20220315000000",
+                                  "declared": true },
+                "core/glibc": { "ident": "core/glibc/2.29/20200305172459",
+                                "declared": false }
+            }
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("pkgAtLeast", Box::new(PKG_AT_LEAST));
+        handlebars.template_render(template, &json).ok()
+    }
+
+    #[test]
+    fn test_pkg_at_least_helper_with_strict_deps() {
+        assert_eq!(Some(String::from("new")),
+                   render_strict("{{#pkgAtLeast \"core/openssl\" \"3.0\"}}new{{/pkgAtLeast}}"));
+        // A transitive dependency is not declared in the plan
+        assert_eq!(None,
+                   render_strict("{{#pkgAtLeast \"core/glibc\" \"2.0\"}}new{{/pkgAtLeast}}"));
+        assert_eq!(None,
+                   render_strict("{{#pkgAtLeast \"core/zlib\" \"1.0\"}}new{{/pkgAtLeast}}"));
+    }
+}
@@ -1,4 +1,5 @@
-use super::super::RenderResult;
+use super::{super::RenderResult,
+            ensure_declared_dep};
 use crate::hcore::{fs,
                    package::{Identifiable,
                              PackageIdent}};
@@ -19,6 +20,7 @@ impl HelperDef for PkgPathForHelper {
              .and_then(|v| v.value().as_str())
              .and_then(|v| PackageIdent::from_str(v).ok())
              .ok_or_else(|| RenderError::new("Invalid package identifier for \"pkgPathFor\""))?;
+        ensure_declared_dep(&param, rc, "pkgPathFor")?;
         let deps =
             serde_json::from_value::<Vec<PackageIdent>>(rc.context().data()["pkg"]["deps"].clone())
                 .unwrap();
@@ -0,0 +1,178 @@
+use super::super::RenderResult;
+use crate::hcore::fs::{AtomicWriter,
+                       Permissions};
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use rand::{distributions::Alphanumeric,
+           rngs::OsRng,
+           Rng};
+use std::{fs,
+          io::{self,
+               Write},
+          path::Path};
+
+/// The directory of the service's data directory that the values are kept in, one file per name
+const STABLE_RANDOM_DIR: &str = "stable_random";
+const DEFAULT_LENGTH: u64 = 32;
+const MAX_LENGTH: u64 = 4096;
+
+// The values are secrets, so only the Supervisor may read them back
+#[cfg(windows)]
+const STABLE_RANDOM_PERMISSIONS: Permissions = Permissions::Standard;
+#[cfg(not(windows))]
+const STABLE_RANDOM_PERMISSIONS: Permissions = Permissions::Explicit(0o600);
+
+/// Renders a random alphanumeric value of the given length, 32 by default, that is generated the
+/// first time it is rendered for the given name and kept in the data directory of the service, so
+/// that it stays the same across renders and restarts. Asking for another length generates a new
+/// value. Outside of a service, as with `hab plan render`, a new value is rendered every time.
+#[derive(Clone, Copy)]
+pub struct StableRandomHelper {
+    /// Whether values are kept in the data directory of the service. Only the renderers of a
+    /// service's configuration and hooks keep them, so that rendering a template elsewhere with
+    /// a render context that names a data directory does not write to it.
+    keep: bool,
+}
+
+impl HelperDef for StableRandomHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let name = h.param(0)
+                    .and_then(|v| v.value().as_str())
+                    .filter(|name| valid_name(name))
+                    .ok_or_else(|| {
+                        RenderError::new("Expected a name of letters, digits, '-' and '_' for \
+                                          \"stableRandom\"")
+                    })?
+                    .to_string();
+        let length = match h.param(1) {
+            Some(v) => {
+                v.value()
+                 .as_u64()
+                 .filter(|length| (1..=MAX_LENGTH).contains(length))
+                 .ok_or_else(|| {
+                     RenderError::new(format!("Expected a length from 1 to {} for \
+                                               \"stableRandom\"",
+                                              MAX_LENGTH))
+                 })?
+            }
+            None => DEFAULT_LENGTH,
+        } as usize;
+        let data_path = rc.context().data()["pkg"]["svc_data_path"].as_str()
+                                                                   .filter(|_| self.keep)
+                                                                   .map(str::to_string);
+        let value = match data_path {
+            Some(data_path) => {
+                let dir = Path::new(&data_path).join(STABLE_RANDOM_DIR);
+                match stable_random(&dir, &name, length) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        return Err(RenderError::new(format!("Can't keep the value of \"{}\" \
+                                                             in {}: {}",
+                                                            name,
+                                                            dir.display(),
+                                                            e)));
+                    }
+                }
+            }
+            None => random(length),
+        };
+        rc.writer.write_all(value.into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static STABLE_RANDOM: StableRandomHelper = StableRandomHelper { keep: true };
+pub static EPHEMERAL_RANDOM: StableRandomHelper = StableRandomHelper { keep: false };
+
+/// Names become file names, so they are kept to characters that are safe in one
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+    && name.chars()
+           .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The value kept for `name` in `dir`, generated and kept there first if there is none of
+/// `length` yet
+fn stable_random(dir: &Path, name: &str, length: usize) -> io::Result<String> {
+    let path = dir.join(name);
+    match fs::read_to_string(&path) {
+        Ok(value) if value.len() == length => return Ok(value),
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let value = random(length);
+    fs::create_dir_all(dir)?;
+    let w = AtomicWriter::new_with_permissions(&path, STABLE_RANDOM_PERMISSIONS)?;
+    w.with_writer(|f| f.write_all(value.as_bytes()))?;
+    Ok(value)
+}
+
+fn random(length: usize) -> String {
+    OsRng.sample_iter(&Alphanumeric)
+         .take(length)
+         .map(char::from)
+         .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn render_with(helper: StableRandomHelper, template: &str, data_path: &Path) -> Option<String> {
+        let json = json!({ "pkg": { "svc_data_path": data_path } });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("stableRandom", Box::new(helper));
+        handlebars.template_render(template, &json).ok()
+    }
+
+    fn render(template: &str, data_path: &Path) -> Option<String> {
+        render_with(STABLE_RANDOM, template, data_path)
+    }
+
+    #[test]
+    fn values_are_kept_across_renders() {
+        let tmp = TempDir::new().unwrap();
+        let token = render("{{stableRandom \"token\" 48}}", tmp.path()).unwrap();
+        assert_eq!(token.len(), 48);
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(render("{{stableRandom \"token\" 48}}", tmp.path()).unwrap(),
+                   token);
+        assert_eq!(fs::read_to_string(tmp.path().join(STABLE_RANDOM_DIR).join("token")).unwrap(),
+                   token);
+
+        let other = render("{{stableRandom \"other\"}}", tmp.path()).unwrap();
+        assert_eq!(other.len(), 32);
+        assert_ne!(other, token);
+    }
+
+    #[test]
+    fn values_are_not_kept_outside_of_a_service() {
+        let tmp = TempDir::new().unwrap();
+        let token =
+            render_with(EPHEMERAL_RANDOM, "{{stableRandom \"token\"}}", tmp.path()).unwrap();
+        assert_eq!(token.len(), 32);
+        assert!(!tmp.path().join(STABLE_RANDOM_DIR).exists());
+    }
+
+    #[test]
+    fn another_length_generates_a_new_value() {
+        let tmp = TempDir::new().unwrap();
+        let token = render("{{stableRandom \"token\" 16}}", tmp.path()).unwrap();
+        let longer = render("{{stableRandom \"token\" 24}}", tmp.path()).unwrap();
+        assert_eq!(longer.len(), 24);
+        assert!(!longer.starts_with(&token));
+    }
+
+    #[test]
+    fn names_that_are_not_safe_file_names_are_rejected() {
+        let tmp = TempDir::new().unwrap();
+        assert!(render("{{stableRandom \"../token\"}}", tmp.path()).is_none());
+        assert!(render("{{stableRandom \"\"}}", tmp.path()).is_none());
+        assert!(render("{{stableRandom \"token\" 0}}", tmp.path()).is_none());
+    }
+}
@@ -0,0 +1,92 @@
+use super::{super::RenderResult,
+            compare_versions,
+            render_block_if};
+use handlebars::{Handlebars,
+                 Helper,
+                 HelperDef,
+                 RenderContext,
+                 RenderError};
+use std::cmp::Ordering;
+
+/// Compares two versions. Used inline with two versions, it renders `-1`, `0` or `1`. Used as a
+/// block with a comparison operator between the versions, it renders its block if the comparison
+/// holds and its `{{else}}` block otherwise.
+#[derive(Clone, Copy)]
+pub struct VersionCompareHelper;
+
+impl HelperDef for VersionCompareHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let params =
+            h.params()
+             .iter()
+             .map(|v| v.value().as_str())
+             .collect::<Option<Vec<_>>>()
+             .ok_or_else(|| {
+                 RenderError::new("Expected string parameters for \"versionCompare\"")
+             })?;
+        match (h.template(), params.as_slice()) {
+            (None, [a, b]) => {
+                let rendered = match compare_versions(a, b, "versionCompare")? {
+                    Ordering::Less => "-1",
+                    Ordering::Equal => "0",
+                    Ordering::Greater => "1",
+                };
+                rc.writer.write_all(rendered.as_bytes())?;
+                Ok(())
+            }
+            (Some(_), [a, op, b]) => {
+                let ordering = compare_versions(a, b, "versionCompare")?;
+                let holds = match *op {
+                    "<" => ordering == Ordering::Less,
+                    "<=" => ordering != Ordering::Greater,
+                    "==" => ordering == Ordering::Equal,
+                    "!=" => ordering != Ordering::Equal,
+                    ">=" => ordering != Ordering::Less,
+                    ">" => ordering == Ordering::Greater,
+                    _ => {
+                        return Err(RenderError::new(format!("Invalid operator \"{}\" for \
+                                                             \"versionCompare\"",
+                                                            op)));
+                    }
+                };
+                render_block_if(holds, h, r, rc)
+            }
+            _ => {
+                Err(RenderError::new("Expected 2 versions, or 2 versions and \
+                                      an operator for a block, for \
+                                      \"versionCompare\""))
+            }
+        }
+    }
+}
+
+pub static VERSION_COMPARE: VersionCompareHelper = VersionCompareHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let json = json!({ "pkg": { "version": "1.10.2" } });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("versionCompare", Box::new(VERSION_COMPARE));
+        handlebars.template_render(template, &json).unwrap()
+    }
+
+    #[test]
+    fn test_version_compare_helper() {
+        assert_eq!("1", render("{{versionCompare pkg.version \"1.9\"}}"));
+        assert_eq!("0", render("{{versionCompare \"1.10.2\" pkg.version}}"));
+        assert_eq!("-1", render("{{versionCompare pkg.version \"2.0.0-rc1\"}}"));
+    }
+
+    #[test]
+    fn test_version_compare_block_helper() {
+        assert_eq!("yes",
+                   render("{{#versionCompare pkg.version \">=\" \
+                           \"1.9\"}}yes{{else}}no{{/versionCompare}}"));
+        assert_eq!("no",
+                   render("{{#versionCompare pkg.version \"<\" \
+                           \"1.10\"}}yes{{else}}no{{/versionCompare}}"));
+    }
+}
@@ -15,6 +15,8 @@ use habitat_core::{crypto::Blake2bHash,
                    fs::svc_hooks_path,
                    package::PackageInstall,
                    util::BufReadLossy};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
 use serde::{Serialize,
             Serializer};
 #[cfg(unix)]
@@ -22,19 +24,74 @@ use std::os::unix::process::ExitStatusExt;
 #[cfg(not(windows))]
 use std::process::{Child,
                    ExitStatus};
-use std::{ffi::OsStr,
+use std::{collections::HashMap,
+          ffi::OsStr,
           fmt,
           fs::File,
           io::{prelude::*,
                BufReader},
           path::{Path,
                  PathBuf},
-          result};
+          result,
+          sync::atomic::{AtomicU64,
+                         Ordering}};
 
 #[cfg(not(windows))]
 pub const HOOK_PERMISSIONS: u32 = 0o755;
 static LOGKEY: &str = "HK";
 
+type HookOutputListener = Box<dyn Fn(&str) + Send>;
+
+lazy_static! {
+    /// The listeners to the hook output of each service group, by the ID they were added with
+    static ref HOOK_OUTPUT_LISTENERS: Mutex<HashMap<String, HashMap<u64, HookOutputListener>>> =
+        Mutex::default();
+}
+
+static NEXT_HOOK_OUTPUT_LISTENER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Keeps a listener to the hook output of a service group added until it is dropped
+#[must_use = "the listener is removed when this is dropped"]
+pub struct HookOutputListenerGuard {
+    service_group: String,
+    id:            u64,
+}
+
+impl Drop for HookOutputListenerGuard {
+    fn drop(&mut self) {
+        let mut listeners = HOOK_OUTPUT_LISTENERS.lock();
+        if let Some(group_listeners) = listeners.get_mut(&self.service_group) {
+            group_listeners.remove(&self.id);
+            if group_listeners.is_empty() {
+                listeners.remove(&self.service_group);
+            }
+        }
+    }
+}
+
+/// Hand every line the hooks of `service_group` print to `listener`, prefixed like in the
+/// Supervisor's output, besides printing it, until the returned guard is dropped
+pub fn listen_to_hook_output(service_group: &str,
+                             listener: impl Fn(&str) + Send + 'static)
+                             -> HookOutputListenerGuard {
+    let id = NEXT_HOOK_OUTPUT_LISTENER_ID.fetch_add(1, Ordering::Relaxed);
+    HOOK_OUTPUT_LISTENERS.lock()
+                         .entry(service_group.to_string())
+                         .or_default()
+                         .insert(id, Box::new(listener));
+    HookOutputListenerGuard { service_group: service_group.to_string(),
+                              id }
+}
+
+fn notify_hook_output_listeners(service_group: &str, preamble_str: &str, line: &str) {
+    if let Some(group_listeners) = HOOK_OUTPUT_LISTENERS.lock().get(service_group) {
+        let line = format!("{} {}", preamble_str, line);
+        for listener in group_listeners.values() {
+            listener(&line);
+        }
+    }
+}
+
 pub fn stdout_log_path<T>(package_name: &str) -> PathBuf
     where T: Hook
 {
@@ -209,11 +266,12 @@ pub trait Hook: fmt::Debug + Sized + Send {
                    -> Result<Self::ExitValue>
         where T: ToString
     {
-        let mut child = Self::exec(self.path(), pkg, svc_encrypted_password).map_err(|err| {
-                            outputln!(preamble service_group,
+        let mut child =
+            Self::exec(self.path(), service_group, pkg, svc_encrypted_password).map_err(|err| {
+                outputln!(preamble service_group,
                                       "Hook failed to run, {}, {}", Self::FILE_NAME, err);
-                            err
-                        })?;
+                err
+            })?;
         let mut hook_output = HookOutput::new(self.stdout_log_path(), self.stderr_log_path());
         hook_output.output_standard_streams::<Self>(service_group, &mut child);
         Ok(child.wait()
@@ -226,7 +284,11 @@ pub trait Hook: fmt::Debug + Sized + Send {
     }
 
     #[cfg(windows)]
-    fn exec<T, S>(path: S, pkg: &Pkg, svc_encrypted_password: Option<T>) -> Result<Child>
+    fn exec<T, S>(path: S,
+                  _service_group: &str,
+                  pkg: &Pkg,
+                  svc_encrypted_password: Option<T>)
+                  -> Result<Child>
         where T: ToString,
               S: AsRef<OsStr>
     {
@@ -240,7 +302,7 @@ pub trait Hook: fmt::Debug + Sized + Send {
     }
 
     #[cfg(unix)]
-    fn exec<T, S>(path: S, pkg: &Pkg, _: Option<T>) -> Result<Child>
+    fn exec<T, S>(path: S, service_group: &str, pkg: &Pkg, _: Option<T>) -> Result<Child>
         where T: ToString,
               S: AsRef<OsStr>
     {
@@ -268,6 +330,39 @@ pub trait Hook: fmt::Debug + Sized + Send {
             None
         };
 
+        // The hooks of a service that runs in its own network namespace join it while it
+        // exists, so that they see the network the service does. Hooks that run while the service
+        // is down, such as `init`, have no namespace to join and run in the host's.
+        #[cfg(target_os = "linux")]
+        {
+            use std::{fs::File,
+                      io,
+                      os::unix::io::AsRawFd};
+
+            if pkg.port_mappings.is_some() {
+                let netns_path = process::exec::unix::network_namespace_path(service_group);
+                match File::open(&netns_path) {
+                    Ok(netns) => {
+                        let mut cmd =
+                            process::exec::unix::hook_command_in_network_namespace(path,
+                                                                                   pkg.env
+                                                                                      .deref(),
+                                                                                   ids,
+                                                                                   netns.as_raw_fd());
+                        return Ok(cmd.spawn()?);
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        debug!("{} has no network namespace to run {:?} in",
+                               service_group,
+                               path.as_ref());
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = service_group;
+
         let mut cmd = process::exec::unix::hook_command(path, pkg.env.deref(), ids);
         Ok(cmd.spawn()?)
     }
@@ -515,7 +610,7 @@ impl RenderPair {
         where C: Into<PathBuf>,
               T: AsRef<Path>
     {
-        let mut renderer = TemplateRenderer::new();
+        let mut renderer = TemplateRenderer::for_service();
         renderer.register_template_file(name, template_path.as_ref())?;
         Ok(RenderPair { path: concrete_path.into(),
                         renderer })
@@ -580,15 +675,19 @@ impl<'a> HookOutput<'a> {
     fn output_standard_streams<H: Hook>(&mut self, service_group: &str, process: &mut Child) {
         let preamble_str = Self::stream_preamble::<H>(service_group);
         if let Some(stdout) = &mut process.stdout {
-            Self::tee_standard_stream(&preamble_str, stdout, self.stdout_log_file);
+            Self::tee_standard_stream(service_group, &preamble_str, stdout, self.stdout_log_file);
         }
         if let Some(stderr) = &mut process.stderr {
-            Self::tee_standard_stream(&preamble_str, stderr, self.stderr_log_file);
+            Self::tee_standard_stream(service_group, &preamble_str, stderr, self.stderr_log_file);
         }
     }
 
-    /// Try to write a stream to stdout and to `path`
-    fn tee_standard_stream(preamble_str: &str, reader: impl Read, path: &Path) {
+    /// Try to write a stream to stdout, to the hook output listeners of `service_group` and to
+    /// `path`
+    fn tee_standard_stream(service_group: &str,
+                           preamble_str: &str,
+                           reader: impl Read,
+                           path: &Path) {
         let mut file_result = File::create(path);
         if let Err(e) = &file_result {
             error!("Failed to create file {:?} to write hook output, {}",
@@ -598,6 +697,7 @@ impl<'a> HookOutput<'a> {
                                           .filter_map(result::Result::ok)
         {
             outputln!(preamble preamble_str, &line);
+            notify_hook_output_listeners(service_group, preamble_str, &line);
             if let Ok(file) = &mut file_result {
                 writeln!(file, "{}", &line).unwrap_or_else(|e| {
                                                error!("Failed to write hook output to {:?}, {}",
@@ -957,4 +1057,23 @@ echo "The message is Hello"
 
         stdfs::remove_dir_all(tmp_dir).expect("remove temp dir");
     }
+
+    #[test]
+    fn hook_output_listeners_receive_lines_until_dropped() {
+        let lines = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let lines_for_listener = std::sync::Arc::clone(&lines);
+        let guard = listen_to_hook_output("listened.default", move |line| {
+            lines_for_listener.lock().push(line.to_string())
+        });
+
+        notify_hook_output_listeners("listened.default", "listened.default hook[init]:", "one");
+        notify_hook_output_listeners("other.default", "other.default hook[init]:", "two");
+        drop(guard);
+        notify_hook_output_listeners("listened.default", "listened.default hook[init]:", "three");
+
+        assert_eq!(*lines.lock(),
+                   vec![String::from("listened.default hook[init]: one")]);
+        assert!(!HOOK_OUTPUT_LISTENERS.lock()
+                                      .contains_key("listened.default"));
+    }
 }
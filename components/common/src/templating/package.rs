@@ -4,7 +4,10 @@ use crate::{error::{Error,
                     os::{process::{ShutdownSignal,
                                    ShutdownTimeout},
                          users},
-                    package::{FullyQualifiedPackageIdent,
+                    package::{metadata::{HookRetry,
+                                         HookRole,
+                                         PortMapping},
+                              FullyQualifiedPackageIdent,
                               PackageIdent,
                               PackageInstall},
                     util},
@@ -54,6 +57,13 @@ impl Env {
 
     pub fn to_hash_map(&self) -> HashMap<String, String> { self.0.clone().into_iter().collect() }
 
+    /// This environment with `vars` added to it, replacing any variables of the same names
+    pub fn with_vars(&self, vars: &BTreeMap<String, String>) -> Self {
+        let mut env = self.0.clone();
+        env.extend(vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+        Env(env)
+    }
+
     async fn transform_path(path: Option<&String>) -> Result<String> {
         let mut paths: Vec<PathBuf> = match path {
             Some(path) => env::split_paths(&path).collect(),
@@ -73,9 +83,22 @@ pub struct Pkg {
     pub version:                 String,
     pub release:                 String,
     pub deps:                    Vec<PackageIdent>,
+    /// The runtime dependencies of the package, with what templates may need to know about each
+    #[serde(default)]
+    pub dependencies:            Vec<Dependency>,
+    /// Whether templates fail to render when they refer to a dependency that the plan doesn't
+    /// declare
+    #[serde(default)]
+    pub strict_deps:             bool,
     pub env:                     Env,
     pub exposes:                 Vec<String>,
     pub exports:                 BTreeMap<String, String>,
+    /// Hooks which only run on the leader or only on the followers of a leader topology group
+    #[serde(default)]
+    pub hook_roles:              BTreeMap<String, HookRole>,
+    /// How hooks which failed are run again, if the package gives them a retry policy
+    #[serde(default)]
+    pub hook_retries:            BTreeMap<String, HookRetry>,
     pub path:                    PathBuf,
     pub svc_path:                PathBuf,
     pub svc_config_path:         PathBuf,
@@ -90,12 +113,19 @@ pub struct Pkg {
     pub svc_group:               String,
     pub shutdown_signal:         ShutdownSignal,
     pub shutdown_timeout:        ShutdownTimeout,
+    /// Present if the service is to run in its own network namespace
+    pub port_mappings:           Option<Vec<PortMapping>>,
 }
 
 impl Pkg {
     pub async fn from_install(package: &PackageInstall) -> Result<Self> {
         let ident = FullyQualifiedPackageIdent::try_from(&package.ident)?;
         let (svc_user, svc_group) = get_user_and_group(package)?;
+        let deps = package.tdeps()?;
+        let declared = package.deps()?;
+        let dependencies = deps.iter()
+                               .map(|dep| Dependency::new(dep, declared.contains(dep)))
+                               .collect();
         let pkg = Pkg { svc_path: fs::svc_path(&package.ident.name),
                         svc_config_path: fs::svc_config_path(&package.ident.name),
                         svc_config_install_path: fs::svc_config_install_path(&package.ident
@@ -109,9 +139,13 @@ impl Pkg {
                         svc_user,
                         svc_group,
                         env: Env::new(package).await?,
-                        deps: package.tdeps()?,
+                        deps,
+                        dependencies,
+                        strict_deps: false,
                         exposes: package.exposes()?,
                         exports: package.exports()?,
+                        hook_roles: package.hook_roles()?,
+                        hook_retries: package.hook_retries()?,
                         path: package.installed_path.clone(),
                         origin: package.ident.origin.clone(),
                         name: package.ident.name.clone(),
@@ -119,11 +153,62 @@ impl Pkg {
                         release: String::from(ident.release()),
                         shutdown_signal: package.shutdown_signal()?.unwrap_or_default(),
                         shutdown_timeout: package.shutdown_timeout()?.unwrap_or_default(),
+                        port_mappings: package.port_mappings()?,
                         ident };
         Ok(pkg)
     }
 }
 
+/// A runtime dependency of a package, exposed to templates under the `pkgs` key
+#[derive(Clone, Debug, Deserialize)]
+pub struct Dependency {
+    #[serde(with = "util::serde::string")]
+    pub ident:    PackageIdent,
+    pub path:     PathBuf,
+    pub exports:  BTreeMap<String, String>,
+    /// Whether the plan declares the dependency in `pkg_deps`, rather than it coming in with
+    /// another dependency
+    pub declared: bool,
+}
+
+impl Dependency {
+    fn new(ident: &PackageIdent, declared: bool) -> Self {
+        let exports = match PackageInstall::load(ident, Some(fs::FS_ROOT_PATH.as_ref())) {
+            Ok(install) => install.exports().unwrap_or_default(),
+            Err(e) => {
+                debug!("Can't read the exports of dependency {}: {}", ident, e);
+                BTreeMap::new()
+            }
+        };
+        Dependency { ident: ident.clone(),
+                     path: fs::pkg_install_path(ident, Some(&*fs::FS_ROOT_PATH)),
+                     exports,
+                     declared }
+    }
+
+    /// The key of the dependency under `pkgs`
+    pub fn key(&self) -> String { format!("{}/{}", self.ident.origin, self.ident.name) }
+}
+
+impl Serialize for Dependency {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        // The components of the identifier are broken out for easy access in templates, as they
+        // are for `pkg`
+        let mut strukt = serializer.serialize_struct("dependency", 8)?;
+        strukt.serialize_field("ident", &self.ident.to_string())?;
+        strukt.serialize_field("origin", &self.ident.origin)?;
+        strukt.serialize_field("name", &self.ident.name)?;
+        strukt.serialize_field("version", &self.ident.version)?;
+        strukt.serialize_field("release", &self.ident.release)?;
+        strukt.serialize_field("path", &self.path)?;
+        strukt.serialize_field("exports", &self.exports)?;
+        strukt.serialize_field("declared", &self.declared)?;
+        strukt.end()
+    }
+}
+
 /// This is a proxy struct to represent the data about a Pkg that we actually want to be
 /// serialized, similar to ServiceProxy
 pub struct PkgProxy<'a> {
@@ -1,6 +1,7 @@
 use crate::{error::Error,
             util};
 use clap::ArgMatches;
+use ipnet::IpNet;
 use native_tls::Certificate;
 use std::{collections::HashMap,
           fmt,
@@ -8,6 +9,7 @@ use std::{collections::HashMap,
           io,
           net::{IpAddr,
                 Ipv4Addr,
+                Ipv6Addr,
                 SocketAddr,
                 SocketAddrV4,
                 ToSocketAddrs},
@@ -109,6 +111,300 @@ impl EventStreamMetadata {
     pub const ARG_NAME: &'static str = "EVENT_META";
 }
 
+/// A `key=value` label describing a Supervisor, used to select the Supervisors a service in a
+/// fleet manifest runs on.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "&str", into = "String")]
+pub struct SupervisorLabel(String, String);
+
+impl FromStr for SupervisorLabel {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.splitn(2, '=').collect::<Vec<_>>().as_slice() {
+            [key, value] if !key.is_empty() && !value.is_empty() => {
+                Ok(Self(String::from(*key), String::from(*value)))
+            }
+            _ => {
+                let e = format!("Invalid label given (must be a '='-delimited pair of non-empty \
+                                 strings): {}",
+                                s);
+                Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+            }
+        }
+    }
+}
+
+impl fmt::Display for SupervisorLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}={}", self.0, self.1) }
+}
+
+impl std::convert::TryFrom<&str> for SupervisorLabel {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> { Ok(SupervisorLabel::from_str(s)?) }
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<String> for SupervisorLabel {
+    fn into(self) -> String { self.to_string() }
+}
+
+impl From<SupervisorLabel> for (String, String) {
+    fn from(label: SupervisorLabel) -> Self { (label.0, label.1) }
+}
+
+/// A Kubernetes Service whose endpoints are the peers of a Supervisor, given as
+/// `[NAMESPACE/]NAME[:PORT_NAME]`. Without a namespace, the namespace of the Supervisor's pod is
+/// used.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "&str", into = "String")]
+pub struct KubernetesService {
+    pub namespace: Option<String>,
+    pub name:      String,
+    /// The name of the Service port that is the gossip port of the peers
+    pub port_name: Option<String>,
+}
+
+impl FromStr for KubernetesService {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || {
+            let e = format!("Invalid Kubernetes Service given (must be \
+                             [NAMESPACE/]NAME[:PORT_NAME]): {}",
+                            s);
+            io::Error::new(io::ErrorKind::InvalidInput, e)
+        };
+        let (service, port_name) = match s.splitn(2, ':').collect::<Vec<_>>().as_slice() {
+            [service] => (*service, None),
+            [service, port_name] if !port_name.is_empty() => {
+                (*service, Some(port_name.to_string()))
+            }
+            _ => return Err(invalid()),
+        };
+        let (namespace, name) = match service.splitn(2, '/').collect::<Vec<_>>().as_slice() {
+            [name] => (None, *name),
+            [namespace, name] if !namespace.is_empty() => (Some(namespace.to_string()), *name),
+            _ => return Err(invalid()),
+        };
+        if name.is_empty() {
+            return Err(invalid());
+        }
+        Ok(KubernetesService { namespace,
+                               name: name.to_string(),
+                               port_name })
+    }
+}
+
+impl fmt::Display for KubernetesService {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(namespace) = &self.namespace {
+            write!(f, "{}/", namespace)?;
+        }
+        write!(f, "{}", self.name)?;
+        if let Some(port_name) = &self.port_name {
+            write!(f, ":{}", port_name)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::convert::TryFrom<&str> for KubernetesService {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> { Ok(KubernetesService::from_str(s)?) }
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<String> for KubernetesService {
+    fn into(self) -> String { self.to_string() }
+}
+
+/// The EC2 instances that are the peers of a Supervisor, given as `tag:KEY=VALUE` for the
+/// instances with a tag or `asg:NAME` for the instances of an Auto Scaling group
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "&str", into = "String")]
+pub enum Ec2PeerFilter {
+    Tag(String, String),
+    AutoScalingGroup(String),
+}
+
+impl Ec2PeerFilter {
+    /// The tag Auto Scaling sets on the instances of a group
+    const AUTO_SCALING_GROUP_TAG: &'static str = "aws:autoscaling:groupName";
+
+    /// The tag key and value the peers have
+    pub fn tag(&self) -> (&str, &str) {
+        match self {
+            Ec2PeerFilter::Tag(key, value) => (key, value),
+            Ec2PeerFilter::AutoScalingGroup(name) => (Self::AUTO_SCALING_GROUP_TAG, name),
+        }
+    }
+}
+
+impl FromStr for Ec2PeerFilter {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if let Some(tag) = s.strip_prefix("tag:") {
+            if let [key, value] = tag.splitn(2, '=').collect::<Vec<_>>().as_slice() {
+                if !key.is_empty() && !value.is_empty() {
+                    return Ok(Ec2PeerFilter::Tag(key.to_string(), value.to_string()));
+                }
+            }
+        } else if let Some(name) = s.strip_prefix("asg:") {
+            if !name.is_empty() {
+                return Ok(Ec2PeerFilter::AutoScalingGroup(name.to_string()));
+            }
+        }
+        let e = format!("Invalid EC2 peer filter given (must be tag:KEY=VALUE or asg:NAME): {}",
+                        s);
+        Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+}
+
+impl fmt::Display for Ec2PeerFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ec2PeerFilter::Tag(key, value) => write!(f, "tag:{}={}", key, value),
+            Ec2PeerFilter::AutoScalingGroup(name) => write!(f, "asg:{}", name),
+        }
+    }
+}
+
+impl std::convert::TryFrom<&str> for Ec2PeerFilter {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> { Ok(Ec2PeerFilter::from_str(s)?) }
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<String> for Ec2PeerFilter {
+    fn into(self) -> String { self.to_string() }
+}
+
+/// The stable host identity a Supervisor's member ID is derived from, given as `machine-id` for
+/// the host's machine ID, `instance-id` for the ID of its EC2 instance, or `value:IDENTITY` for an
+/// identity of the operator's choosing
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "&str", into = "String")]
+pub enum MemberIdSource {
+    MachineId,
+    InstanceId,
+    Value(String),
+}
+
+impl FromStr for MemberIdSource {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "machine-id" => return Ok(MemberIdSource::MachineId),
+            "instance-id" => return Ok(MemberIdSource::InstanceId),
+            _ => {
+                if let Some(value) = s.strip_prefix("value:") {
+                    if !value.is_empty() {
+                        return Ok(MemberIdSource::Value(value.to_string()));
+                    }
+                }
+            }
+        }
+        let e = format!("Invalid member ID source given (must be machine-id, instance-id or \
+                         value:IDENTITY): {}",
+                        s);
+        Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+}
+
+impl fmt::Display for MemberIdSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MemberIdSource::MachineId => write!(f, "machine-id"),
+            MemberIdSource::InstanceId => write!(f, "instance-id"),
+            MemberIdSource::Value(value) => write!(f, "value:{}", value),
+        }
+    }
+}
+
+impl std::convert::TryFrom<&str> for MemberIdSource {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> { Ok(MemberIdSource::from_str(s)?) }
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<String> for MemberIdSource {
+    fn into(self) -> String { self.to_string() }
+}
+
+/// A member the Supervisor refuses SWIM and gossip traffic from, given either as a member ID or as
+/// an IP address or CIDR range (ex: 10.0.4.0/24) that the member's address is in
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Deserialize, Serialize)]
+#[serde(try_from = "&str", into = "String")]
+pub enum DenylistEntry {
+    MemberId(String),
+    Network(IpNet),
+}
+
+impl DenylistEntry {
+    /// Whether this entry matches the member with `member_id`, whose address is `address` if it is
+    /// known.
+    pub fn matches(&self, member_id: &str, address: Option<IpAddr>) -> bool {
+        match self {
+            DenylistEntry::MemberId(id) => id == member_id,
+            DenylistEntry::Network(network) => address.map_or(false, |a| network.contains(&a)),
+        }
+    }
+}
+
+impl FromStr for DenylistEntry {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            let e = "Invalid denylist entry given (must be a member ID, IP address or CIDR range)";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+        }
+        if s.contains('/') {
+            return s.parse::<IpNet>().map(DenylistEntry::Network).map_err(|_| {
+                       let e = format!("Invalid CIDR range given for denylist entry: {}", s);
+                       io::Error::new(io::ErrorKind::InvalidInput, e)
+                   });
+        }
+        match s.parse::<IpAddr>() {
+            Ok(address) => Ok(DenylistEntry::Network(IpNet::from(address))),
+            Err(_) => Ok(DenylistEntry::MemberId(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for DenylistEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DenylistEntry::MemberId(id) => write!(f, "{}", id),
+            // A single address reads better without its `/32` or `/128`
+            DenylistEntry::Network(network) if network.prefix_len() == network.max_prefix_len() => {
+                write!(f, "{}", network.addr())
+            }
+            DenylistEntry::Network(network) => write!(f, "{}", network),
+        }
+    }
+}
+
+impl std::convert::TryFrom<&str> for DenylistEntry {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> { Ok(DenylistEntry::from_str(s)?) }
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<String> for DenylistEntry {
+    fn into(self) -> String { self.to_string() }
+}
+
 /// This represents an environment variable that holds an authentication token which enables
 /// integration with Automate. Supervisors use this token to connect to the messaging server
 /// on the Automate side in order to send data about the services they're running via event
@@ -341,6 +637,16 @@ impl ResolvedListenCtlAddr {
     pub fn addr(&self) -> SocketAddr { self.addr.into() }
 
     pub fn domain(&self) -> &str { &self.domain }
+
+    /// Use the port of the named ring if this is the default address.
+    pub fn for_ring(mut self, ring_name: &str) -> Self {
+        if self.addr == ListenCtlAddr::default() {
+            self.addr
+                .0
+                .set_port(ring_port(ListenCtlAddr::DEFAULT_PORT, ring_name));
+        }
+        self
+    }
 }
 
 impl From<ResolvedListenCtlAddr> for ListenCtlAddr {
@@ -349,6 +655,24 @@ impl From<ResolvedListenCtlAddr> for ListenCtlAddr {
 
 habitat_core::impl_try_from_str_and_into_string!(ResolvedListenCtlAddr);
 
+/// The number of ports between the blocks of ports used by different named rings
+const RING_PORT_STRIDE: u16 = 10;
+/// The number of distinct blocks of ports available to named rings
+const RING_PORT_BLOCKS: u32 = 100;
+
+/// Derive the port a listener uses in the named ring `ring_name` from its default port.
+///
+/// Each ring name hashes to its own block of ports above the defaults so that Supervisors in
+/// different rings can share a host. Two ring names may hash to the same block, in which case
+/// listen addresses must be set explicitly.
+pub fn ring_port(default_port: u16, ring_name: &str) -> u16 {
+    let hash = ring_name.bytes().fold(0u32, |hash, b| {
+                                    hash.wrapping_mul(31).wrapping_add(u32::from(b))
+                                });
+    // `hash % RING_PORT_BLOCKS` is always less than 100 so the cast cannot truncate
+    default_port + RING_PORT_STRIDE * (1 + (hash % RING_PORT_BLOCKS) as u16)
+}
+
 habitat_core::env_config_socketaddr!(#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
                                      pub GossipListenAddr,
                                      HAB_LISTEN_GOSSIP,
@@ -371,15 +695,25 @@ impl GossipListenAddr {
     ///
     /// In particular, a server configured to listen on `0.0.0.0` vs
     /// `192.168.1.1` should be contacted via `127.0.0.1` in the
-    /// former case, but `192.168.1.1` in the latter.
+    /// former case, but `192.168.1.1` in the latter. Likewise, `[::]`
+    /// is contacted via `[::1]`.
     pub fn local_addr(&self) -> Self {
         let mut addr = *self;
-        if addr.0.ip().is_unspecified() {
-            // TODO (CM): Support IPV6, once we do that more broadly
-            addr.0.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        match addr.0.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => addr.0.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            IpAddr::V6(ip) if ip.is_unspecified() => addr.0.set_ip(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            _ => {}
         }
         addr
     }
+
+    /// Use the port of the named ring if this is the default address.
+    pub fn for_ring(mut self, ring_name: &str) -> Self {
+        if self == Self::default() {
+            self.0.set_port(ring_port(Self::DEFAULT_PORT, ring_name));
+        }
+        self
+    }
 }
 
 impl Deref for GossipListenAddr {
@@ -398,12 +732,68 @@ impl ToSocketAddrs for GossipListenAddr {
     fn to_socket_addrs(&self) -> io::Result<Self::Iter> { self.0.to_socket_addrs() }
 }
 
+/// The address a Supervisor advertises to the rest of the ring for gossip, when it cannot be
+/// reached at the address its messages appear to come from (e.g. behind NAT or in an overlay
+/// network). The port is optional and defaults to the gossip listen port.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "&str", into = "String")]
+pub struct GossipAdvertiseAddr {
+    domain: String,
+    ip:     IpAddr,
+    port:   Option<u16>,
+}
+
+impl FromStr for GossipAdvertiseAddr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (_, port) = util::split_host_port(s);
+        let (domain, addr) = util::resolve_socket_addr_with_default_port(s, 0)?;
+        Ok(Self { domain,
+                  ip: addr.ip(),
+                  port: port.map(|_| addr.port()) })
+    }
+}
+
+impl fmt::Display for GossipAdvertiseAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let domain = if self.domain.contains(':') {
+            format!("[{}]", self.domain)
+        } else {
+            self.domain.clone()
+        };
+        match self.port {
+            Some(port) => write!(f, "{}:{}", domain, port),
+            None => write!(f, "{}", domain),
+        }
+    }
+}
+
+impl GossipAdvertiseAddr {
+    /// The advertised socket address, using `listen_port` if no port was given
+    pub fn socket_addr(&self, listen_port: u16) -> SocketAddr {
+        SocketAddr::new(self.ip, self.port.unwrap_or(listen_port))
+    }
+}
+
+habitat_core::impl_try_from_str_and_into_string!(GossipAdvertiseAddr);
+
 habitat_core::env_config_socketaddr!(#[derive(PartialEq, Eq, Debug, Clone, Copy, Deserialize, Serialize)]
                                      pub HttpListenAddr,
                                      HAB_LISTEN_HTTP,
-                                     0, 0, 0, 0, 9631);
+                                     0, 0, 0, 0, Self::DEFAULT_PORT);
 impl HttpListenAddr {
+    pub const DEFAULT_PORT: u16 = 9631;
+
     pub fn new(ip: IpAddr, port: u16) -> Self { Self(SocketAddr::new(ip, port)) }
+
+    /// Use the port of the named ring if this is the default address.
+    pub fn for_ring(mut self, ring_name: &str) -> Self {
+        if self == Self::default() {
+            self.0.set_port(ring_port(Self::DEFAULT_PORT, ring_name));
+        }
+        self
+    }
 }
 
 impl Deref for HttpListenAddr {
@@ -454,6 +844,159 @@ mod tests {
         fn cannot_parse_from_empty_string() { assert!("".parse::<EventStreamToken>().is_err()) }
     }
 
+    mod supervisor_label {
+        use super::*;
+
+        #[test]
+        fn values_may_contain_equals_signs() {
+            let label: SupervisorLabel = "query=a=b".parse().unwrap();
+            assert_eq!(<(String, String)>::from(label),
+                       ("query".to_string(), "a=b".to_string()));
+        }
+
+        #[test]
+        fn keys_and_values_may_not_be_empty() {
+            assert!("disk".parse::<SupervisorLabel>().is_err());
+            assert!("=ssd".parse::<SupervisorLabel>().is_err());
+            assert!("disk=".parse::<SupervisorLabel>().is_err());
+        }
+    }
+
+    mod kubernetes_service {
+        use super::*;
+
+        #[test]
+        fn namespace_and_port_name_are_optional() {
+            let service: KubernetesService = "hab-ring".parse().unwrap();
+            assert_eq!(service,
+                       KubernetesService { namespace: None,
+                                           name:      String::from("hab-ring"),
+                                           port_name: None, });
+            let service: KubernetesService = "prod/hab-ring:gossip".parse().unwrap();
+            assert_eq!(service.namespace.as_deref(), Some("prod"));
+            assert_eq!(service.port_name.as_deref(), Some("gossip"));
+            assert_eq!(service.to_string(), "prod/hab-ring:gossip");
+        }
+
+        #[test]
+        fn names_may_not_be_empty() {
+            assert!("".parse::<KubernetesService>().is_err());
+            assert!("prod/".parse::<KubernetesService>().is_err());
+            assert!("/hab-ring".parse::<KubernetesService>().is_err());
+            assert!("hab-ring:".parse::<KubernetesService>().is_err());
+        }
+    }
+
+    mod ec2_peer_filter {
+        use super::*;
+
+        #[test]
+        fn filters_are_parsed() {
+            let filter: Ec2PeerFilter = "tag:hab-ring=prod".parse().unwrap();
+            assert_eq!(filter.tag(), ("hab-ring", "prod"));
+            let filter: Ec2PeerFilter = "asg:web-asg".parse().unwrap();
+            assert_eq!(filter.tag(), ("aws:autoscaling:groupName", "web-asg"));
+            assert_eq!(filter.to_string(), "asg:web-asg");
+        }
+
+        #[test]
+        fn invalid_filters_are_rejected() {
+            assert!("web-asg".parse::<Ec2PeerFilter>().is_err());
+            assert!("asg:".parse::<Ec2PeerFilter>().is_err());
+            assert!("tag:hab-ring".parse::<Ec2PeerFilter>().is_err());
+            assert!("tag:=prod".parse::<Ec2PeerFilter>().is_err());
+        }
+    }
+
+    mod member_id_source {
+        use super::*;
+
+        #[test]
+        fn sources_are_parsed() {
+            assert_eq!("machine-id".parse::<MemberIdSource>().unwrap(),
+                       MemberIdSource::MachineId);
+            assert_eq!("instance-id".parse::<MemberIdSource>().unwrap(),
+                       MemberIdSource::InstanceId);
+            let source: MemberIdSource = "value:web-01.example.com".parse().unwrap();
+            assert_eq!(source,
+                       MemberIdSource::Value(String::from("web-01.example.com")));
+            assert_eq!(source.to_string(), "value:web-01.example.com");
+        }
+
+        #[test]
+        fn invalid_sources_are_rejected() {
+            assert!("machine".parse::<MemberIdSource>().is_err());
+            assert!("value:".parse::<MemberIdSource>().is_err());
+        }
+    }
+
+    mod denylist_entry {
+        use super::*;
+
+        #[test]
+        fn entries_are_parsed() {
+            assert_eq!("10.0.4.0/24".parse::<DenylistEntry>().unwrap(),
+                       DenylistEntry::Network("10.0.4.0/24".parse().unwrap()));
+            assert_eq!("10.0.4.7".parse::<DenylistEntry>().unwrap(),
+                       DenylistEntry::Network("10.0.4.7/32".parse().unwrap()));
+            assert_eq!("fd00::/8".parse::<DenylistEntry>().unwrap(),
+                       DenylistEntry::Network("fd00::/8".parse().unwrap()));
+            assert_eq!("b4a2b6d5bd0b4f4c8e4c2bc9ff5a4ab7".parse::<DenylistEntry>()
+                                                         .unwrap(),
+                       DenylistEntry::MemberId(String::from("b4a2b6d5bd0b4f4c8e4c2bc9ff5a4ab7")));
+        }
+
+        #[test]
+        fn invalid_entries_are_rejected() {
+            assert!("".parse::<DenylistEntry>().is_err());
+            assert!("10.0.4.0/33".parse::<DenylistEntry>().is_err());
+            assert!("not-a-network/24".parse::<DenylistEntry>().is_err());
+        }
+
+        #[test]
+        fn single_addresses_are_displayed_without_a_prefix() {
+            assert_eq!("10.0.4.7".parse::<DenylistEntry>().unwrap().to_string(),
+                       "10.0.4.7");
+            assert_eq!("10.0.4.0/24".parse::<DenylistEntry>().unwrap().to_string(),
+                       "10.0.4.0/24");
+        }
+
+        #[test]
+        fn entries_match_member_ids_and_addresses() {
+            let network: DenylistEntry = "10.0.4.0/24".parse().unwrap();
+            assert!(network.matches("anyone", Some("10.0.4.7".parse().unwrap())));
+            assert!(!network.matches("anyone", Some("10.0.5.7".parse().unwrap())));
+            assert!(!network.matches("anyone", None));
+            let member_id = DenylistEntry::MemberId(String::from("bad"));
+            assert!(member_id.matches("bad", None));
+            assert!(!member_id.matches("good", Some("10.0.4.7".parse().unwrap())));
+        }
+    }
+
+    mod ring_port {
+        use super::*;
+
+        #[test]
+        fn ring_ports_do_not_overlap_the_defaults() {
+            for ring_name in &["blue", "green", "test", "prod"] {
+                let gossip = GossipListenAddr::default().for_ring(ring_name);
+                let http = HttpListenAddr::default().for_ring(ring_name);
+                let ctl = ResolvedListenCtlAddr::default().for_ring(ring_name);
+                assert!(gossip.port() > GossipListenAddr::DEFAULT_PORT);
+                assert_eq!(gossip.port() - GossipListenAddr::DEFAULT_PORT,
+                           http.port() - HttpListenAddr::DEFAULT_PORT);
+                assert_eq!(gossip.port() - GossipListenAddr::DEFAULT_PORT,
+                           ctl.addr().port() - ListenCtlAddr::DEFAULT_PORT);
+            }
+        }
+
+        #[test]
+        fn explicit_addresses_are_not_changed() {
+            let http: HttpListenAddr = "0.0.0.0:8000".parse().unwrap();
+            assert_eq!(http.for_ring("blue"), http);
+        }
+    }
+
     mod gossip_listen_addr {
         use super::*;
         #[test]
@@ -477,6 +1020,33 @@ mod tests {
         }
     }
 
+    mod gossip_advertise_addr {
+        use super::*;
+
+        #[test]
+        fn port_defaults_to_the_listen_port() {
+            let addr: GossipAdvertiseAddr = "203.0.113.7".parse().unwrap();
+            assert_eq!(addr.socket_addr(9638), "203.0.113.7:9638".parse().unwrap());
+            assert_eq!(addr.to_string(), "203.0.113.7");
+
+            let addr: GossipAdvertiseAddr = "203.0.113.7:19638".parse().unwrap();
+            assert_eq!(addr.socket_addr(9638), "203.0.113.7:19638".parse().unwrap());
+            assert_eq!(addr.to_string(), "203.0.113.7:19638");
+        }
+
+        #[test]
+        fn supports_ipv6_addresses() {
+            let addr: GossipAdvertiseAddr = "[2001:db8::1]:19638".parse().unwrap();
+            assert_eq!(addr.socket_addr(9638),
+                       "[2001:db8::1]:19638".parse().unwrap());
+            assert_eq!(addr.to_string(), "[2001:db8::1]:19638");
+
+            let addr: GossipAdvertiseAddr = "2001:db8::1".parse().unwrap();
+            assert_eq!(addr.socket_addr(9638),
+                       "[2001:db8::1]:9638".parse().unwrap());
+        }
+    }
+
     mod env_config {
         use habitat_core::{env::Config as EnvConfig,
                            locked_env_var};
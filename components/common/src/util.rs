@@ -1,36 +1,58 @@
 pub mod path;
 
 use crate::error::Error;
+use habitat_core::dns;
 use std::{io,
-          net::{SocketAddr,
-                ToSocketAddrs}};
+          net::SocketAddr};
 
+/// Resolve `addr` to a socket address, using `default_port` if `addr` does not include a port.
+///
+/// IPv6 addresses may be given with or without a port, e.g. `::1`, `[::1]` or `[::1]:9638`. When
+/// a name resolves to both IPv4 and IPv6 addresses, IPv4 is preferred. Names are resolved with
+/// the DNS servers and timeout configured for Habitat, see `habitat_core::dns`.
 pub fn resolve_socket_addr_with_default_port<S: AsRef<str>>(
     addr: S,
     default_port: u16)
     -> Result<(String, SocketAddr), Error> {
     let addr = addr.as_ref();
-    let domain;
-    let mut addrs = if let Some(index) = addr.find(':') {
-                        domain = &addr[..index];
-                        addr.to_socket_addrs()
-                    } else {
-                        domain = addr;
-                        (addr, default_port).to_socket_addrs()
-                    }.map_err(|e| Error::ListenCtlResolutionError(addr.to_string(), e))?;
-    addrs.find(std::net::SocketAddr::is_ipv4)
-         .ok_or_else(|| {
-             Error::ListenCtlResolutionError(addr.to_string(),
-                                             io::Error::new(io::ErrorKind::InvalidInput,
-                                                            "did not resolve to an ipv4 socket \
-                                                             address"))
-         })
-         .map(|addr| (domain.to_string(), addr))
+    let resolution_error = |msg: &'static str| {
+        Error::ListenCtlResolutionError(addr.to_string(),
+                                        io::Error::new(io::ErrorKind::InvalidInput, msg))
+    };
+    let (domain, port) = split_host_port(addr);
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| resolution_error("invalid port"))?,
+        None => default_port,
+    };
+    let addrs = dns::resolve(domain, port).map_err(|e| {
+                                              Error::ListenCtlResolutionError(addr.to_string(), e)
+                                          })?;
+    addrs.iter()
+         .find(|addr| addr.is_ipv4())
+         .or_else(|| addrs.first())
+         .map(|addr| (domain.to_string(), *addr))
+         .ok_or_else(|| resolution_error("did not resolve to a socket address"))
+}
+
+/// Split `addr` into its host and, if present, its port. IPv6 addresses must be enclosed in
+/// square brackets to be given a port.
+pub fn split_host_port(addr: &str) -> (&str, Option<&str>) {
+    if let Some(bracketed) = addr.strip_prefix('[') {
+        if let Some((host, rest)) = bracketed.split_once(']') {
+            return (host, rest.strip_prefix(':'));
+        }
+    } else if addr.matches(':').count() == 1 {
+        if let Some((host, port)) = addr.split_once(':') {
+            return (host, Some(port));
+        }
+    }
+    (addr, None)
 }
 
 #[cfg(test)]
 mod test {
-    use super::resolve_socket_addr_with_default_port;
+    use super::{resolve_socket_addr_with_default_port,
+                split_host_port};
 
     #[test]
     fn test_socket_addr_with_default_port() {
@@ -41,5 +63,28 @@ mod test {
         assert_eq!(resolve_socket_addr_with_default_port("1.2.3.4:1500", 89).unwrap(),
                    ("1.2.3.4".to_string(), "1.2.3.4:1500".parse().expect("")));
         assert!(resolve_socket_addr_with_default_port("an_invalid_address", 89).is_err());
+        assert!(resolve_socket_addr_with_default_port("1.2.3.4:http", 89).is_err());
+    }
+
+    #[test]
+    fn test_ipv6_socket_addr_with_default_port() {
+        assert_eq!(resolve_socket_addr_with_default_port("::1", 89).unwrap(),
+                   ("::1".to_string(), "[::1]:89".parse().expect("")));
+        assert_eq!(resolve_socket_addr_with_default_port("[::1]", 89).unwrap(),
+                   ("::1".to_string(), "[::1]:89".parse().expect("")));
+        assert_eq!(resolve_socket_addr_with_default_port("[::1]:1500", 89).unwrap(),
+                   ("::1".to_string(), "[::1]:1500".parse().expect("")));
+    }
+
+    #[test]
+    fn test_split_host_port() {
+        assert_eq!(split_host_port("localhost"), ("localhost", None));
+        assert_eq!(split_host_port("localhost:9638"),
+                   ("localhost", Some("9638")));
+        assert_eq!(split_host_port("1.2.3.4:9638"), ("1.2.3.4", Some("9638")));
+        assert_eq!(split_host_port("2001:db8::1"), ("2001:db8::1", None));
+        assert_eq!(split_host_port("[2001:db8::1]"), ("2001:db8::1", None));
+        assert_eq!(split_host_port("[2001:db8::1]:9638"),
+                   ("2001:db8::1", Some("9638")));
     }
 }
@@ -171,7 +171,7 @@ impl fmt::Display for NamedRevision {
 ////////////////////////////////////////////////////////////////////////
 
 /// A timestamp string used to identify Habitat keys.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct KeyRevision(String);
 
 impl KeyRevision {
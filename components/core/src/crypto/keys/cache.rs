@@ -5,6 +5,7 @@ use crate::{crypto::{hash::Blake2bHash,
                                          BUILDER_KEY_NAME},
                             generate_signing_key_pair,
                             BuilderSecretEncryptionKey,
+                            Key,
                             KeyFile,
                             NamedRevision,
                             OriginPublicEncryptionKey,
@@ -170,6 +171,17 @@ impl KeyCache {
         self.fetch_latest_revision::<RingKey>(name)
     }
 
+    /// Every revision of a ring key in the cache, oldest first.
+    ///
+    /// Note: name is just the name, not the name + revision
+    pub fn ring_key_revisions(&self, name: &str) -> Result<Vec<RingKey>> {
+        let mut keys = self.get_all_paths_for(name, RingKey::extension())?
+                           .map(RingKey::try_from)
+                           .collect::<Result<Vec<_>>>()?;
+        keys.sort_by_key(|key| key.named_revision().revision().clone());
+        Ok(keys)
+    }
+
     pub fn latest_secret_origin_signing_key(&self,
                                             origin: &Origin)
                                             -> Result<SecretOriginSigningKey> {
@@ -397,6 +409,22 @@ mod test {
         assert!(paths.contains(&k2.own_filename()));
     }
 
+    #[test]
+    fn ring_key_revisions_are_oldest_first() {
+        let (cache, _dir) = new_cache();
+        assert!(cache.ring_key_revisions("beyonce").unwrap().is_empty());
+
+        let k1 = RingKey::new("beyonce");
+        cache.write_key(&k1).unwrap();
+        wait_1_sec(); // ensure new revision
+                      // will be different.
+        let k2 = RingKey::new("beyonce");
+        cache.write_key(&k2).unwrap();
+        cache.write_key(&RingKey::new("jayz")).unwrap();
+
+        assert_eq!(cache.ring_key_revisions("beyonce").unwrap(), vec![k1, k2]);
+    }
+
     #[test]
     #[should_panic(expected = "No revisions found for")]
     fn latest_cached_revision_nonexistent() {
@@ -100,6 +100,16 @@ impl SecretOriginSigningKey {
         Ok(self.sign_inner(hex_encoded_hash.to_string().as_bytes()))
     }
 
+    /// Like `sign`, but for content that is already in memory rather
+    /// than in a file. The output can be verified against the same
+    /// content with `PublicOriginSigningKey::verify`.
+    pub fn sign_bytes<B>(&self, content: B) -> Vec<u8>
+        where B: AsRef<[u8]>
+    {
+        let hex_encoded_hash = Blake2bHash::from_bytes(content);
+        self.sign_inner(hex_encoded_hash.to_string().as_bytes())
+    }
+
     /// Does the actual heavy lifting of signing a string of bytes.
     ///
     /// Mainly separate to facilitate testing.
@@ -186,6 +196,20 @@ mod tests {
         assert_eq!(verified_hash, expected_hash);
     }
 
+    #[test]
+    fn sign_bytes_and_verify_roundtrip() {
+        let origin = "test-origin".parse().unwrap();
+        let (public, secret) = generate_signing_key_pair(&origin);
+
+        let content = b"[[service]]\nident = \"core/redis\"\n";
+        let signed_message = secret.sign_bytes(content);
+
+        assert_eq!(public.verify(&signed_message, &mut &content[..]).unwrap(),
+                   Blake2bHash::from_bytes(content));
+        assert!(public.verify(&signed_message, &mut &b"tampered"[..])
+                      .is_err());
+    }
+
     /// This is mainly to encapsulate knowledge about how Habitat's
     /// signing behaves. We historically have signed the lowercase
     /// hex-encoded Blake2b hash digest of a file, rather than
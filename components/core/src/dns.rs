@@ -0,0 +1,341 @@
+//! Resolution of hostnames: of peers, of the addresses the Supervisor listens on, and of Builder.
+//!
+//! Names are resolved by the operating system, following `/etc/resolv.conf` and the like, unless
+//! `HAB_DNS_SERVERS` names the DNS servers to query instead. It is a comma-separated list of
+//! servers, queried in order, each given as
+//!
+//! * `IP[:PORT]` or `udp://IP[:PORT]`, queried over UDP, and over TCP for answers too large for UDP
+//! * `tcp://IP[:PORT]`
+//! * `tls://IP[:PORT]#NAME`, DNS over TLS, where `NAME` is the name the certificate of the server
+//!   is verified against
+//! * `https://IP[:PORT]#NAME`, DNS over HTTPS
+//!
+//! `HAB_DNS_TIMEOUT_SECS` bounds how long resolving a name may take, whichever way it is
+//! resolved.
+//!
+//! When either is set, names are looked up on a runtime of the module's own, so that every lookup
+//! shares one resolver, whatever runtime the caller runs on, if any, and a lookup that times out
+//! is dropped rather than left running.
+
+use crate::error::{Error,
+                   Result};
+use std::{io,
+          net::{IpAddr,
+                SocketAddr,
+                ToSocketAddrs},
+          str::FromStr,
+          sync::mpsc,
+          time::Duration};
+use tokio::{net,
+            runtime::{self,
+                      Runtime},
+            time};
+use trust_dns_resolver::{config::{NameServerConfig,
+                                  Protocol,
+                                  ResolverConfig,
+                                  ResolverOpts},
+                         Resolver,
+                         TokioAsyncResolver};
+
+lazy_static::lazy_static! {
+    /// The runtime names are looked up on
+    static ref RUNTIME: io::Result<Runtime> = runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("dns-resolution")
+        .enable_all()
+        .build();
+
+    /// The resolver querying the DNS servers of `HAB_DNS_SERVERS`, shared by every lookup, if
+    /// there are any
+    static ref SHARED_RESOLVER: io::Result<Option<TokioAsyncResolver>> = {
+        let servers: Vec<DnsServer> = DnsServers::configured_value().into();
+        let timeout: Option<Duration> = DnsTimeout::configured_value().into();
+        if servers.is_empty() {
+            Ok(None)
+        } else {
+            let (config, opts) = resolver_config(&servers, timeout)?;
+            TokioAsyncResolver::tokio(config, opts).map(Some).map_err(io::Error::from)
+        }
+    };
+}
+
+crate::env_config!(
+    /// The DNS servers to query instead of the ones of the operating system, if any
+    #[derive(Clone, Debug)]
+    pub DnsServers,
+    Vec<DnsServer>,
+    HAB_DNS_SERVERS,
+    Vec::new(),
+    Error,
+    s,
+    s.split(',')
+     .map(str::trim)
+     .filter(|server| !server.is_empty())
+     .map(str::parse)
+     .collect::<Result<_>>()
+     .map(Self));
+
+crate::env_config!(
+    /// How long resolving a name may take. Zero, the default, leaves it to the resolver.
+    #[derive(Clone, Copy, Debug)]
+    pub DnsTimeout,
+    Option<Duration>,
+    HAB_DNS_TIMEOUT_SECS,
+    None,
+    std::num::ParseIntError,
+    s,
+    Ok(Self(Some(Duration::from_secs(s.parse()?)).filter(|timeout| !timeout.is_zero()))));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DnsProtocol {
+    Udp,
+    Tcp,
+    Tls,
+    Https,
+}
+
+impl DnsProtocol {
+    fn default_port(self) -> u16 {
+        match self {
+            DnsProtocol::Udp | DnsProtocol::Tcp => 53,
+            DnsProtocol::Tls => 853,
+            DnsProtocol::Https => 443,
+        }
+    }
+
+    /// The protocols the resolver speaks to a server of this protocol. Answers too large for UDP
+    /// are asked for again over TCP.
+    fn resolver_protocols(self) -> &'static [Protocol] {
+        match self {
+            DnsProtocol::Udp => &[Protocol::Udp, Protocol::Tcp],
+            DnsProtocol::Tcp => &[Protocol::Tcp],
+            DnsProtocol::Tls => &[Protocol::Tls],
+            DnsProtocol::Https => &[Protocol::Https],
+        }
+    }
+}
+
+/// A DNS server to query instead of the ones of the operating system
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DnsServer {
+    addr:     SocketAddr,
+    protocol: DnsProtocol,
+    /// The name the certificate of a DNS over TLS or HTTPS server is verified against
+    tls_name: Option<String>,
+}
+
+impl FromStr for DnsServer {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidDnsServer(s.to_string());
+        let (protocol, rest) = match s.split_once("://") {
+            Some(("udp", rest)) => (DnsProtocol::Udp, rest),
+            Some(("tcp", rest)) => (DnsProtocol::Tcp, rest),
+            Some(("tls", rest)) => (DnsProtocol::Tls, rest),
+            Some(("https", rest)) => (DnsProtocol::Https, rest),
+            Some(_) => return Err(invalid()),
+            None => (DnsProtocol::Udp, s),
+        };
+        let (addr, tls_name) = match rest.split_once('#') {
+            Some((addr, name)) if !name.is_empty() => (addr, Some(name.to_string())),
+            Some(_) => return Err(invalid()),
+            None => (rest, None),
+        };
+        let encrypted = matches!(protocol, DnsProtocol::Tls | DnsProtocol::Https);
+        if encrypted != tls_name.is_some() {
+            return Err(invalid());
+        }
+        let addr = match addr.parse::<SocketAddr>() {
+            Ok(addr) => addr,
+            Err(_) => {
+                let ip = addr.trim_start_matches('[')
+                             .trim_end_matches(']')
+                             .parse::<IpAddr>()
+                             .map_err(|_| invalid())?;
+                SocketAddr::new(ip, protocol.default_port())
+            }
+        };
+        Ok(DnsServer { addr,
+                       protocol,
+                       tls_name })
+    }
+}
+
+/// Whether names are resolved by the DNS servers of `HAB_DNS_SERVERS` rather than by the
+/// operating system
+pub fn servers_configured() -> bool {
+    let servers: Vec<DnsServer> = DnsServers::configured_value().into();
+    !servers.is_empty()
+}
+
+/// Whether names are looked up by this module, as `HAB_DNS_SERVERS` or `HAB_DNS_TIMEOUT_SECS` is
+/// set, rather than left to the operating system
+pub fn lookups_configured() -> bool {
+    let timeout: Option<Duration> = DnsTimeout::configured_value().into();
+    servers_configured() || timeout.is_some()
+}
+
+/// A resolver querying the DNS servers of `HAB_DNS_SERVERS`, or the ones of the operating system
+/// if there are none, giving up on a query after `HAB_DNS_TIMEOUT_SECS`.
+///
+/// The resolver runs a runtime of its own, so it must not be used from asynchronous code.
+pub fn resolver() -> io::Result<Resolver> {
+    let servers: Vec<DnsServer> = DnsServers::configured_value().into();
+    let timeout: Option<Duration> = DnsTimeout::configured_value().into();
+    let (config, opts) = resolver_config(&servers, timeout)?;
+    Resolver::new(config, opts)
+}
+
+fn resolver_config(servers: &[DnsServer],
+                   timeout: Option<Duration>)
+                   -> io::Result<(ResolverConfig, ResolverOpts)> {
+    let (config, mut opts) = if servers.is_empty() {
+        trust_dns_resolver::system_conf::read_system_conf()?
+    } else {
+        let mut config = ResolverConfig::new();
+        for server in servers {
+            for protocol in server.protocol.resolver_protocols() {
+                config.add_name_server(NameServerConfig { socket_addr:        server.addr,
+                                                          protocol:           *protocol,
+                                                          tls_dns_name:       server.tls_name
+                                                                                    .clone(),
+                                                          trust_nx_responses: false,
+                                                          tls_config:         None, });
+            }
+        }
+        (config, ResolverOpts::default())
+    };
+    if let Some(timeout) = timeout {
+        opts.timeout = timeout;
+    }
+    Ok((config, opts))
+}
+
+/// Resolve `host`, an IP address or a hostname, to the socket addresses of `port` at the host.
+///
+/// This blocks until the host is resolved, so asynchronous code should use `resolve_async`.
+pub fn resolve(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    if let Some(addr) = ip_address(host, port) {
+        return Ok(vec![addr]);
+    }
+    if !lookups_configured() {
+        return (host, port).to_socket_addrs().map(Iterator::collect);
+    }
+    let (tx, rx) = mpsc::channel();
+    let host = host.to_string();
+    runtime()?.spawn(async move {
+                  // The lookup gives up at the timeout, so the caller always gets an answer
+                  tx.send(lookup(&host, port).await).ok();
+              });
+    rx.recv().unwrap_or_else(|_| {
+                 Err(io::Error::new(io::ErrorKind::Other, "the DNS resolution runtime stopped"))
+             })
+}
+
+/// Resolve `host`, an IP address or a hostname, to the socket addresses of `port` at the host,
+/// from asynchronous code.
+pub async fn resolve_async(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    if let Some(addr) = ip_address(host, port) {
+        return Ok(vec![addr]);
+    }
+    if !lookups_configured() {
+        return net::lookup_host((host, port)).await.map(Iterator::collect);
+    }
+    let host = host.to_string();
+    runtime()?.spawn(async move { lookup(&host, port).await })
+              .await
+              .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+}
+
+fn ip_address(host: &str, port: u16) -> Option<SocketAddr> {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .ok()
+        .map(|ip| SocketAddr::new(ip, port))
+}
+
+fn runtime() -> io::Result<&'static Runtime> {
+    RUNTIME.as_ref()
+           .map_err(|e| io::Error::new(e.kind(), e.to_string()))
+}
+
+/// Look up `host` with the shared resolver, or with the operating system if there are no DNS
+/// servers to query, giving up after `HAB_DNS_TIMEOUT_SECS`. Runs on the runtime of the module.
+async fn lookup(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    let timeout: Option<Duration> = DnsTimeout::configured_value().into();
+    let lookup = async {
+        let resolver = SHARED_RESOLVER.as_ref()
+                                      .map_err(|e| io::Error::new(e.kind(), e.to_string()))?;
+        match resolver {
+            Some(resolver) => {
+                let ips = resolver.lookup_ip(host).await.map_err(io::Error::from)?;
+                Ok(ips.iter().map(|ip| SocketAddr::new(ip, port)).collect())
+            }
+            None => net::lookup_host((host, port)).await.map(Iterator::collect),
+        }
+    };
+    match timeout {
+        Some(timeout) => {
+            match time::timeout(timeout, lookup).await {
+                Ok(addrs) => addrs,
+                Err(_) => {
+                    let message = format!("resolving {} timed out", host);
+                    Err(io::Error::new(io::ErrorKind::TimedOut, message))
+                }
+            }
+        }
+        None => lookup.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dns_servers_are_parsed() {
+        assert_eq!("8.8.8.8".parse::<DnsServer>().unwrap(),
+                   DnsServer { addr:     "8.8.8.8:53".parse().unwrap(),
+                               protocol: DnsProtocol::Udp,
+                               tls_name: None, });
+        assert_eq!("tcp://[2001:4860:4860::8888]:5353".parse::<DnsServer>()
+                                                      .unwrap(),
+                   DnsServer { addr:     "[2001:4860:4860::8888]:5353".parse().unwrap(),
+                               protocol: DnsProtocol::Tcp,
+                               tls_name: None, });
+        assert_eq!("tls://1.1.1.1#cloudflare-dns.com".parse::<DnsServer>()
+                                                     .unwrap(),
+                   DnsServer { addr:     "1.1.1.1:853".parse().unwrap(),
+                               protocol: DnsProtocol::Tls,
+                               tls_name: Some("cloudflare-dns.com".to_string()), });
+        assert_eq!("https://[::1]#dns.example.com".parse::<DnsServer>()
+                                                  .unwrap(),
+                   DnsServer { addr:     "[::1]:443".parse().unwrap(),
+                               protocol: DnsProtocol::Https,
+                               tls_name: Some("dns.example.com".to_string()), });
+    }
+
+    #[test]
+    fn invalid_dns_servers_are_rejected() {
+        for server in &["dns.example.com",
+                        "quic://1.1.1.1",
+                        "tls://1.1.1.1",
+                        "https://1.1.1.1#",
+                        "8.8.8.8#dns.google"]
+        {
+            assert!(server.parse::<DnsServer>().is_err(),
+                    "{} should not be a valid DNS server",
+                    server);
+        }
+    }
+
+    #[test]
+    fn ip_addresses_are_not_looked_up() {
+        assert_eq!(resolve("10.0.0.1", 9638).unwrap(),
+                   vec!["10.0.0.1:9638".parse().unwrap()]);
+        assert_eq!(resolve("[::1]", 9638).unwrap(),
+                   vec!["[::1]:9638".parse().unwrap()]);
+    }
+}
@@ -2,7 +2,12 @@ use std::{self,
           env::VarError,
           ffi::{OsStr,
                 OsString},
-          str::FromStr};
+          str::FromStr,
+          sync::atomic::{AtomicBool,
+                         Ordering}};
+
+/// Set by `hab --offline`
+static OFFLINE: AtomicBool = AtomicBool::new(false);
 
 /// Fetches the environment variable `key` from the current process, but only it is not empty.
 ///
@@ -72,6 +77,23 @@ pub fn var_os<K: AsRef<OsStr>>(key: K) -> std::option::Option<OsString> {
     }
 }
 
+/// Disable network access for the rest of this process and the commands it becomes.
+pub fn set_offline() { OFFLINE.store(true, Ordering::Relaxed) }
+
+/// Whether network access is disabled, with `set_offline` or by setting `HAB_OFFLINE`
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed) || var_os(crate::OFFLINE_ENVVAR).is_some()
+}
+
+/// The environment which keeps the commands this process runs offline when it is.
+pub fn offline_env() -> Vec<(&'static str, String)> {
+    if is_offline() {
+        vec![(crate::OFFLINE_ENVVAR, String::from("1"))]
+    } else {
+        vec![]
+    }
+}
+
 /// Declare a struct that implements the `Config` trait with a minimum of boilerplate.
 /// This declares a simple newtype struct whose name comes from `$wrapping_type`,
 /// which wraps the provided `$wrapped_type` and can be overridden by setting the
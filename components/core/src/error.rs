@@ -82,6 +82,8 @@ pub enum Error {
     FullyQualifiedPackageIdentRequired(String),
     /// Occurs when a service binding cannot be successfully parsed.
     InvalidBinding(String),
+    /// Occurs when a DNS server of `HAB_DNS_SERVERS` cannot be successfully parsed.
+    InvalidDnsServer(String),
     /// Occurs when an origin is in an invalid format
     InvalidOrigin(String),
     /// Occurs when a package identifier string cannot be successfully parsed.
@@ -110,6 +112,12 @@ pub enum Error {
     /// Occurs when a BIND, BIND_OPTIONAL, or BIND_MAP MetaFile is
     /// read and contains a bad entry.
     MetaFileBadBind,
+    /// Occurs when a PORT_MAPPINGS MetaFile is read and contains a bad entry.
+    MetaFileBadPortMapping(String),
+    /// Occurs when a HOOK_ROLES MetaFile is read and contains a bad entry.
+    MetaFileBadHookRole(String),
+    /// Occurs when a HOOK_RETRIES MetaFile is read and contains a bad entry.
+    MetaFileBadHookRetry(String),
     /// Occurs when a package metadata file cannot be opened, read, or parsed.
     MetaFileMalformed(package::metadata::MetaFile),
     /// Occurs when a particular package metadata file is not found.
@@ -275,6 +283,12 @@ impl fmt::Display for Error {
                          <NAME> is a service name, and <SERVICE_GROUP> is a valid service group",
                         binding)
             }
+            Error::InvalidDnsServer(ref server) => {
+                format!("Invalid DNS server '{}', must be of the form IP[:PORT], \
+                         udp://IP[:PORT], tcp://IP[:PORT], tls://IP[:PORT]#NAME or \
+                         https://IP[:PORT]#NAME",
+                        server)
+            }
             Error::InvalidOrigin(ref origin) => {
                 format!("Invalid origin: {}. Origins must begin with a lowercase letter or \
                          number. Allowed characters include lowercase letters, numbers, -, and _. \
@@ -314,6 +328,21 @@ impl fmt::Display for Error {
             Error::MetaFileBadBind => {
                 "Bad value parsed from BIND, BIND_OPTIONAL, or BIND_MAP".to_string()
             }
+            Error::MetaFileBadPortMapping(ref e) => {
+                format!("Bad value parsed from PORT_MAPPINGS: '{}'. Port mappings take the form \
+                         host_port:service_port[/tcp|udp]",
+                        e)
+            }
+            Error::MetaFileBadHookRole(ref e) => {
+                format!("Bad value parsed from HOOK_ROLES: '{}'. Hooks can only be restricted to \
+                         the leader or follower role",
+                        e)
+            }
+            Error::MetaFileBadHookRetry(ref e) => {
+                format!("Bad value parsed from HOOK_RETRIES: '{}'. Hook retries take the form \
+                         attempts:backoff_seconds, with at least one attempt",
+                        e)
+            }
             Error::MetaFileMalformed(ref e) => {
                 format!("MetaFile: {:?}, didn't contain a valid UTF-8 string", e)
             }
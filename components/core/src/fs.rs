@@ -10,7 +10,8 @@ use std::{iter,
 #[cfg(windows)]
 use winapi::um::winbase::MoveFileExW;
 
-use crate::{env as henv,
+use crate::{crypto::Blake2bHash,
+            env as henv,
             error::{Error,
                     Result},
             os::{process,
@@ -60,6 +61,10 @@ pub const FS_ROOT_ENVVAR: &str = "FS_ROOT";
 pub const SYSTEMDRIVE_ENVVAR: &str = "SYSTEMDRIVE";
 /// The file where user-defined configuration for each service is found.
 pub const USER_CONFIG_FILE: &str = "user.toml";
+/// The file beside `user.toml` whose variables are added to the environment of each service.
+pub const ENVIRONMENT_FILE: &str = "environment.toml";
+/// The `KEY=VALUE` alternative to `environment.toml`.
+pub const DOTENV_FILE: &str = ".env";
 /// Permissions that service-owned service directories should
 /// have. The user and group will be `SVC_USER` / `SVC_GROUP`.
 #[cfg(not(windows))]
@@ -361,6 +366,17 @@ pub fn svc_pid_file<T: AsRef<Path>>(service_name: T) -> PathBuf {
     svc_path(service_name).join("PID")
 }
 
+/// Returns the path to the runtime manifest (PID, cgroup, ports, etc.) of a given service, for
+/// use by node agents.
+pub fn svc_run_manifest_file<T: AsRef<Path>>(service_name: T) -> PathBuf {
+    svc_path(service_name).join("run.json")
+}
+
+/// Returns the path to the history of the package updates of a given service.
+pub fn svc_update_history_file<T: AsRef<Path>>(service_name: T) -> PathBuf {
+    svc_path(service_name).join("update_history.json")
+}
+
 /// Returns the root path for a given service's user configuration,
 /// files, and data.
 pub fn user_path<T: AsRef<Path>>(service_name: T) -> PathBuf { USER_ROOT.join(service_name) }
@@ -779,10 +795,23 @@ impl AtomicWriter {
         Ok(r)
     }
 
+    /// Writes the content of the file with `op` without renaming it
+    /// into place, so that the content of several files can be
+    /// written before any of them is replaced. `finish` renames it
+    /// into place; dropping the writer instead leaves the file as it
+    /// was.
+    pub fn stage<F, E>(mut self, op: F) -> std::result::Result<Self, E>
+        where F: FnOnce(&mut std::fs::File) -> std::result::Result<(), E>,
+              E: From<std::io::Error>
+    {
+        op(self.tempfile.as_file_mut())?;
+        Ok(self)
+    }
+
     /// Completes the atomic write by calling sync on the temporary
     /// file to ensure all data is flushed to disk and then renaming
     /// the file into place.
-    fn finish(self) -> io::Result<()> {
+    pub fn finish(self) -> io::Result<()> {
         // Note that we only set permissions if given explicit ones to
         // override whatever permissions the file was created with.
         if let Permissions::Explicit(ref permissions) = self.permissions {
@@ -889,10 +918,189 @@ pub fn atomic_write(dest_path: &Path, data: impl AsRef<[u8]>) -> io::Result<()>
     w.with_writer(|f| f.write_all(data.as_ref()))
 }
 
+/// The suffix of the file holding the checksum of a file written by `checksummed_write`. The
+/// checksum is kept beside the file rather than in it, so that the file still holds exactly
+/// what Supervisors that don't know about checksums expect to read.
+const CHECKSUM_SUFFIX: &str = ".blake2b";
+
+/// The path of the file holding the checksum of a checksummed file.
+pub fn checksum_path(path: &Path) -> PathBuf { with_suffix(path, CHECKSUM_SUFFIX) }
+
+/// The path of the copy of a checksummed file that is kept from before its most recent write.
+pub fn last_good_path(path: &Path) -> PathBuf { with_suffix(path, ".last-good") }
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+/// A checksummed write whose data is written next to its destination, to be moved into place
+/// with `finish`. See `AtomicWriter::stage`.
+pub struct StagedChecksummedWrite {
+    writer:   AtomicWriter,
+    checksum: Blake2bHash,
+}
+
+impl StagedChecksummedWrite {
+    /// Stage `data` to be written to the destination of `writer`.
+    pub fn new(writer: AtomicWriter, data: &[u8]) -> io::Result<Self> {
+        let checksum = Blake2bHash::from_bytes(data);
+        let writer = writer.stage(|f| f.write_all(data))?;
+        Ok(Self { writer, checksum })
+    }
+
+    /// Move the data into place, followed by its checksum. If the destination already holds
+    /// verified data, it is first copied to `last_good_path` so it can be restored by
+    /// `checksummed_recover`.
+    pub fn finish(self) -> io::Result<()> {
+        let dest = self.writer.dest.clone();
+        if let Ok(previous) = checksummed_read(&dest) {
+            let last_good = last_good_path(&dest);
+            atomic_write(&last_good, &previous)?;
+            atomic_write(&checksum_path(&last_good),
+                         Blake2bHash::from_bytes(&previous).to_string())?;
+        }
+        self.writer.finish()?;
+        atomic_write(&checksum_path(&dest), self.checksum.to_string())
+    }
+}
+
+/// Atomically write `data` to `dest_path`, followed by its BLAKE2b checksum to `checksum_path`
+/// so that corruption can be detected by `checksummed_read`. If the file already holds verified
+/// data, it is first copied to `last_good_path` so it can be restored by `checksummed_recover`.
+pub fn checksummed_write(dest_path: &Path, data: impl AsRef<[u8]>) -> io::Result<()> {
+    StagedChecksummedWrite::new(AtomicWriter::new(dest_path)?, data.as_ref())?.finish()
+}
+
+/// Read data written by `checksummed_write`, verifying its checksum. A file without a checksum,
+/// or one changed since its checksum was written, was written by something that doesn't keep
+/// checksums, such as an older Supervisor or an operator's editor, and is returned as it is.
+///
+/// Returns an error of kind `io::ErrorKind::InvalidData` if the checksum does not match.
+pub fn checksummed_read(path: &Path) -> io::Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    if verify_checksum(path, &data)? {
+        Ok(data)
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "checksum mismatch"))
+    }
+}
+
+/// Replace the file at `path` with the copy kept from before its most recent write, returning
+/// the data it holds. The copy is verified before it is restored.
+pub fn checksummed_recover(path: &Path) -> io::Result<Vec<u8>> {
+    let last_good = last_good_path(path);
+    let data = fs::read(&last_good)?;
+    let checksum = fs::read_to_string(checksum_path(&last_good))?;
+    if checksum.trim().parse::<Blake2bHash>().ok() != Some(Blake2bHash::from_bytes(&data)) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "checksum mismatch"));
+    }
+    atomic_write(path, &data)?;
+    atomic_write(&checksum_path(path), checksum)?;
+    warn!("Recovered {} from {}", path.display(), last_good.display());
+    Ok(data)
+}
+
+/// Remove a checksummed file along with its checksum and the copy kept from before its most
+/// recent write, so that none of it can be recovered.
+pub fn checksummed_remove(path: &Path) -> io::Result<()> {
+    let last_good = last_good_path(path);
+    let files = [checksum_path(&last_good),
+                 last_good,
+                 checksum_path(path),
+                 path.to_path_buf()];
+    for path in &files {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Whether `data`, read from `path`, matches its checksum. The file is taken as it is if there is
+/// no checksum, or if the file was modified after the checksum was written.
+fn verify_checksum(path: &Path, data: &[u8]) -> io::Result<bool> {
+    let checksum_path = checksum_path(path);
+    let checksum = match fs::read_to_string(&checksum_path) {
+        Ok(checksum) => checksum,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e),
+    };
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    if let (Some(data_time), Some(checksum_time)) = (modified(path), modified(&checksum_path)) {
+        if data_time > checksum_time {
+            return Ok(true);
+        }
+    }
+    Ok(checksum.trim().parse::<Blake2bHash>().ok() == Some(Blake2bHash::from_bytes(data)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    mod checksummed {
+        use super::*;
+        use tempfile::tempdir;
+
+        #[test]
+        fn round_trips_data() {
+            let dir = tempdir().expect("couldn't create tempdir");
+            let path = dir.path().join("state");
+            checksummed_write(&path, "42").unwrap();
+            assert_eq!(checksummed_read(&path).unwrap(), b"42");
+            checksummed_write(&path, "multi\nline\n").unwrap();
+            assert_eq!(checksummed_read(&path).unwrap(), b"multi\nline\n");
+        }
+
+        #[test]
+        fn keeps_the_checksum_out_of_the_file() {
+            let dir = tempdir().expect("couldn't create tempdir");
+            let path = dir.path().join("state");
+            checksummed_write(&path, "42").unwrap();
+            assert_eq!(fs::read(&path).unwrap(), b"42");
+            assert!(checksum_path(&path).is_file());
+        }
+
+        #[test]
+        fn accepts_files_without_a_checksum() {
+            let dir = tempdir().expect("couldn't create tempdir");
+            let path = dir.path().join("state");
+            fs::write(&path, "42").unwrap();
+            assert_eq!(checksummed_read(&path).unwrap(), b"42");
+        }
+
+        #[test]
+        fn detects_corruption_and_recovers_the_last_good_copy() {
+            let dir = tempdir().expect("couldn't create tempdir");
+            let path = dir.path().join("state");
+            checksummed_write(&path, "1").unwrap();
+            checksummed_write(&path, "2").unwrap();
+
+            let wrong_checksum = Blake2bHash::from_bytes(b"7").to_string();
+            fs::write(checksum_path(&path), wrong_checksum).unwrap();
+            assert_eq!(checksummed_read(&path).unwrap_err().kind(),
+                       io::ErrorKind::InvalidData);
+
+            assert_eq!(checksummed_recover(&path).unwrap(), b"1");
+            assert_eq!(checksummed_read(&path).unwrap(), b"1");
+        }
+
+        #[test]
+        fn remove_leaves_nothing_to_recover() {
+            let dir = tempdir().expect("couldn't create tempdir");
+            let path = dir.path().join("state");
+            checksummed_write(&path, "1").unwrap();
+            checksummed_write(&path, "2").unwrap();
+
+            checksummed_remove(&path).unwrap();
+            assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+            assert!(checksummed_recover(&path).is_err());
+        }
+    }
+
     mod svc_dir {
         use super::*;
         use std::fs::{self,
@@ -9,6 +9,7 @@ pub use self::error::{Error,
 
 pub mod binlink;
 pub mod crypto;
+pub mod dns;
 pub mod env;
 pub mod error;
 pub mod flowcontrol;
@@ -30,6 +31,8 @@ use serde_derive::{Deserialize,
                    Serialize};
 
 pub const AUTH_TOKEN_ENVVAR: &str = "HAB_AUTH_TOKEN";
+/// When set, Habitat must not access the network and can only use what is in the local caches
+pub const OFFLINE_ENVVAR: &str = "HAB_OFFLINE";
 
 // A Builder channel
 env_config_string!(#[derive(Deserialize, Serialize, Clone, Debug, Eq, Hash, PartialEq)]
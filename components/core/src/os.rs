@@ -4,4 +4,5 @@ pub mod net;
 pub mod process;
 pub mod signals;
 pub mod system;
+pub mod systemd;
 pub mod users;
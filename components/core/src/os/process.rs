@@ -16,6 +16,7 @@ pub mod exec;
 
 // Common platform-independent interface
 pub use implementation::{become_command,
+                         become_command_with_env,
                          can_run_services_as_svc_user,
                          current_pid,
                          is_alive,
@@ -9,6 +9,34 @@ use std::{ffi::OsStr,
           process::{Command,
                     Stdio},
           result};
+#[cfg(target_os = "linux")]
+use std::{os::unix::io::RawFd,
+          path::{Path,
+                 PathBuf}};
+
+/// The variables systemd sets for the Launcher, which hooks and services must not act on
+const SYSTEMD_ENV_VARS: &[&str] = &["LISTEN_FDNAMES",
+                                    "LISTEN_FDS",
+                                    "LISTEN_PID",
+                                    "NOTIFY_SOCKET",
+                                    "WATCHDOG_PID",
+                                    "WATCHDOG_USEC"];
+
+/// Where `ip netns add` bind-mounts the network namespaces it creates
+#[cfg(target_os = "linux")]
+const NETNS_RUN_DIR: &str = "/var/run/netns";
+
+/// The name of the network namespace of the service identified by `service_id` (e.g.
+/// "redis.default")
+#[cfg(target_os = "linux")]
+pub fn network_namespace_name(service_id: &str) -> String { format!("hab-{}", service_id) }
+
+/// Where the network namespace of the service identified by `service_id` is bind-mounted while
+/// the service runs
+#[cfg(target_os = "linux")]
+pub fn network_namespace_path(service_id: &str) -> PathBuf {
+    Path::new(NETNS_RUN_DIR).join(network_namespace_name(service_id))
+}
 
 /// Prepare a `Command` to execute a lifecycle hook.
 // TODO (CM): Ideally, `ids` would not be an `Option`, but separate
@@ -19,6 +47,46 @@ pub fn hook_command<X, I, K, V>(executable: X, env: I, ids: Option<(Uid, Gid)>)
           I: IntoIterator<Item = (K, V)>,
           K: AsRef<OsStr>,
           V: AsRef<OsStr>
+{
+    let mut cmd = base_hook_command(executable, env);
+    if let Some((uid, gid)) = ids {
+        with_user_and_group_information(&mut cmd, uid, gid);
+    }
+
+    cmd
+}
+
+/// Prepare a `Command` to execute a lifecycle hook inside the network
+/// namespace referred to by the open file descriptor `netns`.
+///
+/// The descriptor must remain open until the command is spawned.
+#[cfg(target_os = "linux")]
+pub fn hook_command_in_network_namespace<X, I, K, V>(executable: X,
+                                                     env: I,
+                                                     ids: Option<(Uid, Gid)>,
+                                                     netns: RawFd)
+                                                     -> Command
+    where X: AsRef<OsStr>,
+          I: IntoIterator<Item = (K, V)>,
+          K: AsRef<OsStr>,
+          V: AsRef<OsStr>
+{
+    let mut cmd = base_hook_command(executable, env);
+    // Joining a network namespace requires CAP_SYS_ADMIN, so this
+    // must happen before we drop privileges.
+    with_network_namespace(&mut cmd, netns);
+    if let Some((uid, gid)) = ids {
+        with_user_and_group_information(&mut cmd, uid, gid);
+    }
+
+    cmd
+}
+
+fn base_hook_command<X, I, K, V>(executable: X, env: I) -> Command
+    where X: AsRef<OsStr>,
+          I: IntoIterator<Item = (K, V)>,
+          K: AsRef<OsStr>,
+          V: AsRef<OsStr>
 {
     let mut cmd = Command::new(executable);
 
@@ -29,12 +97,27 @@ pub fn hook_command<X, I, K, V>(executable: X, env: I, ids: Option<(Uid, Gid)>)
        .stdout(Stdio::piped())
        .stderr(Stdio::piped())
        .envs(env);
+    for var in SYSTEMD_ENV_VARS {
+        cmd.env_remove(var);
+    }
 
     with_own_process_group(&mut cmd);
-    if let Some((uid, gid)) = ids {
-        with_user_and_group_information(&mut cmd, uid, gid);
-    }
+    cmd
+}
 
+/// Ensures that the `Command` is executed within the network
+/// namespace referred to by `netns`.
+#[cfg(target_os = "linux")]
+fn with_network_namespace(cmd: &mut Command, netns: RawFd) -> &mut Command {
+    unsafe {
+        cmd.pre_exec(move || {
+               if libc::setns(netns, libc::CLONE_NEWNET) == 0 {
+                   Ok(())
+               } else {
+                   Err(io::Error::last_os_error())
+               }
+           });
+    }
     cmd
 }
 
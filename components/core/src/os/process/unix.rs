@@ -1,6 +1,7 @@
 use super::Signal;
-use crate::error::{Error,
-                   Result};
+use crate::{env::offline_env,
+            error::{Error,
+                    Result}};
 use libc::{self,
            pid_t};
 use std::{ffi::OsString,
@@ -13,7 +14,15 @@ pub type Pid = libc::pid_t;
 pub(crate) type SignalCode = libc::c_int;
 
 pub fn become_command(command: PathBuf, args: &[OsString]) -> Result<()> {
-    become_exec_command(command, args)
+    become_exec_command(command, args, &[])
+}
+
+/// Like `become_command`, with the given environment variables set for the command only.
+pub fn become_command_with_env(command: PathBuf,
+                               args: &[OsString],
+                               env: &[(&str, String)])
+                               -> Result<()> {
+    become_exec_command(command, args, env)
 }
 
 /// Get process identifier of calling process.
@@ -85,9 +94,12 @@ impl From<Signal> for SignalCode {
 /// # Failures
 ///
 /// * If the system call fails the error will be returned, otherwise this function does not return
-fn become_exec_command(command: PathBuf, args: &[OsString]) -> Result<()> {
+fn become_exec_command(command: PathBuf, args: &[OsString], env: &[(&str, String)]) -> Result<()> {
     debug!("Calling execvp(): ({:?}) {:?}", command.display(), &args);
-    let error_if_failed = Command::new(command).args(args).exec();
+    let error_if_failed = Command::new(command).args(args)
+                                               .envs(offline_env())
+                                               .envs(env.iter().cloned())
+                                               .exec();
     // The only possible return for the above function is an `Error` so return it, meaning that we
     // failed to exec to our target program
     Err(error_if_failed.into())
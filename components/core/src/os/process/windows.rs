@@ -1,5 +1,6 @@
-use crate::error::{Error,
-                   Result};
+use crate::{env::offline_env,
+            error::{Error,
+                    Result}};
 use std::{ffi::OsString,
           io,
           path::PathBuf,
@@ -20,7 +21,15 @@ const STILL_ACTIVE: u32 = 259;
 pub type Pid = DWORD;
 
 pub fn become_command(command: PathBuf, args: &[OsString]) -> Result<()> {
-    become_child_command(command, args)
+    become_child_command(command, args, &[])
+}
+
+/// Like `become_command`, with the given environment variables set for the command only.
+pub fn become_command_with_env(command: PathBuf,
+                               args: &[OsString],
+                               env: &[(&str, String)])
+                               -> Result<()> {
+    become_child_command(command, args, env)
 }
 
 /// Get process identifier of calling process.
@@ -85,11 +94,14 @@ pub fn terminate(pid: Pid) -> Result<()> {
 /// # Failures
 ///
 /// * If the child process cannot be created
-fn become_child_command(command: PathBuf, args: &[OsString]) -> Result<()> {
+fn become_child_command(command: PathBuf, args: &[OsString], env: &[(&str, String)]) -> Result<()> {
     debug!("Calling child process: ({:?}) {:?}",
            command.display(),
            &args);
-    let status = Command::new(command).args(args).status()?;
+    let status = Command::new(command).args(args)
+                                      .envs(offline_env())
+                                      .envs(env.iter().cloned())
+                                      .status()?;
     // Let's honor the exit codes from the child process we finished running
     process::exit(status.code().unwrap())
 }
@@ -0,0 +1,120 @@
+//! Notifying systemd of the state of a unit through `sd_notify`, and pinging its watchdog.
+//!
+//! Both do nothing outside of Linux, or when the process doesn't run in a unit that expects
+//! notifications.
+
+use std::time::{Duration,
+                Instant};
+#[cfg(target_os = "linux")]
+use std::{env,
+          ffi::OsStr,
+          io,
+          mem,
+          os::unix::ffi::OsStrExt};
+
+/// Tells systemd that the unit is up
+pub const READY: &str = "READY=1";
+/// Tells systemd that the unit is shutting down for good
+pub const STOPPING: &str = "STOPPING=1";
+const WATCHDOG: &str = "WATCHDOG=1";
+
+/// Send `state` to systemd, if the process runs in a unit that expects notifications.
+#[cfg(target_os = "linux")]
+pub fn notify(state: &str) {
+    if let Some(socket) = env::var_os("NOTIFY_SOCKET") {
+        if let Err(e) = send_notification(&socket, state) {
+            debug!("Unable to notify systemd of {}: {}", state, e);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn notify(_state: &str) {}
+
+/// Tell systemd what the unit is doing, or why it is about to fail
+pub fn notify_status(status: &str) { notify(&format!("STATUS={}", status)) }
+
+#[cfg(target_os = "linux")]
+fn send_notification(socket: &OsStr, state: &str) -> io::Result<()> {
+    let socket = socket.as_bytes();
+    let mut addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+    if socket.is_empty() || socket.len() >= addr.sun_path.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid NOTIFY_SOCKET"));
+    }
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    for (dst, src) in addr.sun_path.iter_mut().zip(socket) {
+        *dst = *src as libc::c_char;
+    }
+    // The name of a socket in the abstract namespace is given with a leading '@' that stands for
+    // a NUL byte
+    if socket[0] == b'@' {
+        addr.sun_path[0] = 0;
+    }
+    let addr_len = mem::size_of::<libc::sa_family_t>() + socket.len();
+    unsafe {
+        let fd = libc::socket(libc::AF_UNIX, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let sent = libc::sendto(fd,
+                                state.as_ptr() as *const libc::c_void,
+                                state.len(),
+                                0,
+                                &addr as *const libc::sockaddr_un as *const libc::sockaddr,
+                                addr_len as libc::socklen_t);
+        let result = if sent == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        };
+        libc::close(fd);
+        result
+    }
+}
+
+/// Pings the systemd watchdog of the unit, if it has one, often enough for systemd not to
+/// consider the unit hung
+#[derive(Debug)]
+pub struct Watchdog {
+    timeout:   Option<Duration>,
+    next_ping: Instant,
+}
+
+impl Watchdog {
+    /// The watchdog of the unit, if it is meant for one of `pids`: the watchdog is only meant
+    /// for the process named by `WATCHDOG_PID`, or for every process of the unit without it.
+    pub fn from_env(pids: &[u32]) -> Self {
+        #[cfg(target_os = "linux")]
+        let timeout = {
+            let for_us = match env::var("WATCHDOG_PID") {
+                Ok(pid) => pid.parse::<u32>().map_or(false, |pid| pids.contains(&pid)),
+                Err(_) => true,
+            };
+            env::var("WATCHDOG_USEC").ok()
+                                     .and_then(|usec| usec.parse::<u64>().ok())
+                                     .filter(|_| for_us)
+                                     .map(Duration::from_micros)
+        };
+        #[cfg(not(target_os = "linux"))]
+        let timeout = {
+            let _ = pids;
+            None
+        };
+        Watchdog { timeout,
+                   next_ping: Instant::now() }
+    }
+
+    /// How long systemd waits for a ping before it considers the unit hung
+    pub fn timeout(&self) -> Option<Duration> { self.timeout }
+
+    /// Ping the watchdog if it is time to, at half of the timeout. Called on every pass through
+    /// the main loop.
+    pub fn ping_if_due(&mut self, now: Instant) {
+        if let Some(timeout) = self.timeout {
+            if now >= self.next_ping {
+                notify(WATCHDOG);
+                self.next_ping = now + timeout / 2;
+            }
+        }
+    }
+}
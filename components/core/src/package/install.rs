@@ -5,8 +5,11 @@ use super::{list::package_list_for_ident,
                        read_metafile,
                        Bind,
                        BindMapping,
+                       HookRetry,
+                       HookRole,
                        MetaFile,
-                       PackageType},
+                       PackageType,
+                       PortMapping},
             Identifiable,
             PackageIdent};
 use crate::{error::{Error,
@@ -263,6 +266,15 @@ impl PackageInstall {
         Ok(env)
     }
 
+    /// Returns the names of the runtime environment variables that hold paths, as recorded in the
+    /// RUNTIME_ENVIRONMENT_PATHS metadata. Packages built before release 0.85.0 have none.
+    pub fn runtime_environment_path_keys(&self) -> Result<Vec<String>> {
+        Ok(self.runtime_environment_file_map(MetaFile::RuntimeEnvironmentPaths)?
+               .into_iter()
+               .map(|(key, _)| key)
+               .collect())
+    }
+
     /// Returns all the package's binds, required and then optional
     pub fn all_binds(&self) -> Result<Vec<Bind>> {
         let mut all_binds = self.binds()?;
@@ -379,6 +391,35 @@ impl PackageInstall {
         }
     }
 
+    /// The hooks which only run on either the leader or the followers of a leader topology
+    /// service group, keyed by hook name
+    pub fn hook_roles(&self) -> Result<BTreeMap<String, HookRole>> {
+        match self.read_metafile(MetaFile::HookRoles) {
+            Ok(body) => {
+                parse_key_value(&body).map_err(|_| Error::MetaFileMalformed(MetaFile::HookRoles))?
+                                      .into_iter()
+                                      .map(|(hook, role)| Ok((hook, role.parse()?)))
+                                      .collect()
+            }
+            Err(Error::MetaFileNotFound(MetaFile::HookRoles)) => Ok(BTreeMap::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// How the hooks which are run again after failing are retried, keyed by hook name
+    pub fn hook_retries(&self) -> Result<BTreeMap<String, HookRetry>> {
+        match self.read_metafile(MetaFile::HookRetries) {
+            Ok(body) => {
+                parse_key_value(&body).map_err(|_| Error::MetaFileMalformed(MetaFile::HookRetries))?
+                                      .into_iter()
+                                      .map(|(hook, retry)| Ok((hook, retry.parse()?)))
+                                      .collect()
+            }
+            Err(Error::MetaFileNotFound(MetaFile::HookRetries)) => Ok(BTreeMap::new()),
+            Err(e) => Err(e),
+        }
+    }
+
     /// A vector of ports we expose
     pub fn exposes(&self) -> Result<Vec<String>> {
         match self.read_metafile(MetaFile::Exposes) {
@@ -621,6 +662,21 @@ impl PackageInstall {
         }
     }
 
+    /// Returns the ports to forward to the service if the package is specified to run in its own
+    /// network namespace, or None if the package doesn't contain a PORT_MAPPINGS Metafile
+    pub fn port_mappings(&self) -> Result<Option<Vec<PortMapping>>> {
+        match self.read_metafile(MetaFile::PortMappings) {
+            Ok(body) => {
+                let mappings = body.split_whitespace()
+                                   .map(str::parse)
+                                   .collect::<Result<_>>()?;
+                Ok(Some(mappings))
+            }
+            Err(Error::MetaFileNotFound(MetaFile::PortMappings)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Read the contents of a given metafile.
     ///
     /// # Failures
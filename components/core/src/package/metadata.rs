@@ -1,7 +1,8 @@
 use crate::{error::{Error,
                     Result},
             package::PackageIdent};
-use serde_derive::Serialize;
+use serde_derive::{Deserialize,
+                   Serialize};
 use std::{self,
           collections::BTreeMap,
           env,
@@ -13,6 +14,7 @@ use std::{self,
                  PathBuf},
           str::FromStr,
           string::ToString,
+          time::Duration,
           vec::IntoIter};
 
 #[cfg(not(windows))]
@@ -85,6 +87,132 @@ impl FromStr for BindMapping {
     }
 }
 
+/// The transport protocol of a `PortMapping`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum PortProtocol {
+    Tcp,
+    Udp,
+}
+
+impl Default for PortProtocol {
+    fn default() -> Self { PortProtocol::Tcp }
+}
+
+impl fmt::Display for PortProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortProtocol::Tcp => write!(f, "tcp"),
+            PortProtocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+/// Describes a port on the host which is forwarded to a port of a service running in its own
+/// network namespace. Entries in a PORT_MAPPINGS MetaFile take the form
+/// `host_port:service_port[/protocol]`, e.g. `8080:80` or `5353:53/udp`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct PortMapping {
+    pub host_port:    u16,
+    pub service_port: u16,
+    pub protocol:     PortProtocol,
+}
+
+impl FromStr for PortMapping {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let bad_mapping = || Error::MetaFileBadPortMapping(value.to_string());
+        let (ports, protocol) = match value.split_once('/') {
+            Some((ports, "tcp")) => (ports, PortProtocol::Tcp),
+            Some((ports, "udp")) => (ports, PortProtocol::Udp),
+            Some(_) => return Err(bad_mapping()),
+            None => (value, PortProtocol::Tcp),
+        };
+        let (host_port, service_port) = ports.split_once(':').ok_or_else(bad_mapping)?;
+        Ok(PortMapping { host_port: host_port.parse().map_err(|_| bad_mapping())?,
+                         service_port: service_port.parse().map_err(|_| bad_mapping())?,
+                         protocol })
+    }
+}
+
+impl fmt::Display for PortMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f,
+               "{}:{}/{}",
+               self.host_port, self.service_port, self.protocol)
+    }
+}
+
+/// The members of a leader topology service group a hook is restricted to. Entries in a
+/// HOOK_ROLES MetaFile take the form `hook=role`, e.g. `post-run=leader`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum HookRole {
+    Leader,
+    Follower,
+}
+
+impl FromStr for HookRole {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "leader" => Ok(HookRole::Leader),
+            "follower" => Ok(HookRole::Follower),
+            _ => Err(Error::MetaFileBadHookRole(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for HookRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookRole::Leader => write!(f, "leader"),
+            HookRole::Follower => write!(f, "follower"),
+        }
+    }
+}
+
+/// How a hook is run again after failing. Entries in a HOOK_RETRIES MetaFile take the form
+/// `hook=attempts:backoff`, e.g. `init=5:2`, where the backoff is the number of seconds to wait
+/// after the first failed attempt, doubling after every further one, up to a day.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct HookRetry {
+    /// How often the hook is run at most, including the first attempt
+    pub attempts: u32,
+    /// How long to wait after the first failed attempt
+    pub backoff:  Duration,
+}
+
+impl HookRetry {
+    /// The longest time to wait between two attempts
+    pub const MAX_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// How long to wait before the attempt following the failed attempt `attempt`, counted from 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        1u32.checked_shl(attempt.saturating_sub(1))
+            .and_then(|factor| self.backoff.checked_mul(factor))
+            .map_or(Self::MAX_DELAY, |delay| delay.min(Self::MAX_DELAY))
+    }
+}
+
+impl FromStr for HookRetry {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let bad_retry = || Error::MetaFileBadHookRetry(value.to_string());
+        let (attempts, backoff) = value.split_once(':').ok_or_else(bad_retry)?;
+        let attempts = attempts.parse::<u32>().map_err(|_| bad_retry())?;
+        let backoff = backoff.parse::<u64>().map_err(|_| bad_retry())?;
+        if attempts == 0 {
+            return Err(bad_retry());
+        }
+        Ok(HookRetry { attempts,
+                       backoff: Duration::from_secs(backoff) })
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct EnvVar {
     pub key:       String,
@@ -146,11 +274,14 @@ pub enum MetaFile {
     EnvironmentSep,
     Exports,
     Exposes,
+    HookRetries,
+    HookRoles,
     Ident,
     LdFlags,
     LdRunPath,
     Manifest,
     Path,
+    PortMappings,
     ResolvedServices, // Composite-only
     RuntimeEnvironment,
     RuntimeEnvironmentPaths,
@@ -180,11 +311,14 @@ impl fmt::Display for MetaFile {
             MetaFile::EnvironmentSep => "ENVIRONMENT_SEP",
             MetaFile::Exports => "EXPORTS",
             MetaFile::Exposes => "EXPOSES",
+            MetaFile::HookRetries => "HOOK_RETRIES",
+            MetaFile::HookRoles => "HOOK_ROLES",
             MetaFile::Ident => "IDENT",
             MetaFile::LdFlags => "LDFLAGS",
             MetaFile::LdRunPath => "LD_RUN_PATH",
             MetaFile::Manifest => "MANIFEST",
             MetaFile::Path => "PATH",
+            MetaFile::PortMappings => "PORT_MAPPINGS",
             MetaFile::ResolvedServices => "RESOLVED_SERVICES",
             MetaFile::RuntimeEnvironment => "RUNTIME_ENVIRONMENT",
             MetaFile::RuntimeEnvironmentPaths => "RUNTIME_ENVIRONMENT_PATHS",
@@ -384,6 +518,55 @@ port=front-end.port
         assert!(output.is_err());
     }
 
+    #[test]
+    fn can_parse_port_mappings() {
+        assert_eq!("8080:80".parse::<PortMapping>().unwrap(),
+                   PortMapping { host_port:    8080,
+                                 service_port: 80,
+                                 protocol:     PortProtocol::Tcp, });
+        assert_eq!("5353:53/udp".parse::<PortMapping>().unwrap(),
+                   PortMapping { host_port:    5353,
+                                 service_port: 53,
+                                 protocol:     PortProtocol::Udp, });
+        assert_eq!("8080:80/udp".parse::<PortMapping>().unwrap().to_string(),
+                   "8080:80/udp");
+    }
+
+    #[test]
+    fn fails_to_parse_invalid_port_mappings() {
+        for input in &["8080", "8080:http", "70000:80", "8080:80/sctp", ":80"] {
+            assert!(input.parse::<PortMapping>().is_err(),
+                    "{} should not be a valid port mapping",
+                    input);
+        }
+    }
+
+    #[test]
+    fn can_parse_hook_roles() {
+        assert_eq!("leader".parse::<HookRole>().unwrap(), HookRole::Leader);
+        assert_eq!("follower".parse::<HookRole>().unwrap(), HookRole::Follower);
+        assert!("Leader".parse::<HookRole>().is_err());
+        assert!("standalone".parse::<HookRole>().is_err());
+    }
+
+    #[test]
+    fn can_parse_hook_retries() {
+        let retry = "5:2".parse::<HookRetry>().unwrap();
+        assert_eq!(retry,
+                   HookRetry { attempts: 5,
+                               backoff:  Duration::from_secs(2), });
+        assert_eq!(retry.delay(1), Duration::from_secs(2));
+        assert_eq!(retry.delay(3), Duration::from_secs(8));
+        assert_eq!(retry.delay(33), HookRetry::MAX_DELAY);
+        let longest = "2:18446744073709551615".parse::<HookRetry>().unwrap();
+        assert_eq!(longest.delay(2), HookRetry::MAX_DELAY);
+        for input in &["5", "0:2", "five:2", "5:-1", "5:2s"] {
+            assert!(input.parse::<HookRetry>().is_err(),
+                    "{} should not be a valid hook retry",
+                    input);
+        }
+    }
+
     #[test]
     fn can_read_metafile() {
         let pkg_root = Builder::new().prefix("pkg-root").tempdir().unwrap();
@@ -14,10 +14,11 @@ use rustls::{Certificate,
              RootCertStore};
 use serde::{Deserialize,
             Serialize};
-use std::{path::PathBuf,
+use std::{path::{Path,
+                 PathBuf},
           str::FromStr};
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(try_from = "&str", into = "String")]
 pub struct CertificateChainCli {
     path:         PathBuf,
@@ -26,13 +27,14 @@ pub struct CertificateChainCli {
 
 impl CertificateChainCli {
     pub fn into_inner(self) -> Vec<Certificate> { self.certificates }
-}
 
-impl FromStr for CertificateChainCli {
-    type Err = Error;
+    pub fn path(&self) -> &Path { &self.path }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let path = PathBuf::from(s);
+    /// Read the certificates again from the file, or latest file of the directory, that they were
+    /// read from
+    pub fn reload(&self) -> Result<Self, Error> { Self::from_path(self.path.clone()) }
+
+    fn from_path(path: PathBuf) -> Result<Self, Error> {
         let certificates = if path.is_dir() {
             ctl_gateway::latest_certificates(&path)?
         } else {
@@ -42,13 +44,19 @@ impl FromStr for CertificateChainCli {
     }
 }
 
+impl FromStr for CertificateChainCli {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Self::from_path(PathBuf::from(s)) }
+}
+
 impl std::fmt::Display for CertificateChainCli {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}", self.path.display())
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(try_from = "&str", into = "String")]
 pub struct PrivateKeyCli {
     path:        PathBuf,
@@ -57,13 +65,14 @@ pub struct PrivateKeyCli {
 
 impl PrivateKeyCli {
     pub fn into_inner(self) -> RustlsPrivateKey { self.private_key }
-}
 
-impl FromStr for PrivateKeyCli {
-    type Err = Error;
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Read the private key again from the file, or latest file of the directory, that it was read
+    /// from
+    pub fn reload(&self) -> Result<Self, Error> { Self::from_path(self.path.clone()) }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let path = PathBuf::from(s);
+    fn from_path(path: PathBuf) -> Result<Self, Error> {
         let private_key = if path.is_dir() {
             ctl_gateway::latest_private_key(&path)?
         } else {
@@ -73,6 +82,12 @@ impl FromStr for PrivateKeyCli {
     }
 }
 
+impl FromStr for PrivateKeyCli {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Self::from_path(PathBuf::from(s)) }
+}
+
 impl std::fmt::Display for PrivateKeyCli {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}", self.path.to_string_lossy())
@@ -88,13 +103,14 @@ pub struct RootCertificateStoreCli {
 
 impl RootCertificateStoreCli {
     pub fn into_inner(self) -> RootCertStore { self.root_certificate_store }
-}
 
-impl FromStr for RootCertificateStoreCli {
-    type Err = Error;
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Read the root certificates again from the file, or latest file of the directory, that they
+    /// were read from
+    pub fn reload(&self) -> Result<Self, Error> { Self::from_path(self.path.clone()) }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let path = PathBuf::from(s);
+    fn from_path(path: PathBuf) -> Result<Self, Error> {
         let root_certificate_store = if path.is_dir() {
             ctl_gateway::latest_root_certificate_store(&path)?
         } else {
@@ -105,6 +121,12 @@ impl FromStr for RootCertificateStoreCli {
     }
 }
 
+impl FromStr for RootCertificateStoreCli {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Self::from_path(PathBuf::from(s)) }
+}
+
 impl std::fmt::Display for RootCertificateStoreCli {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}", self.path.to_string_lossy())
@@ -5,11 +5,17 @@ pub use crate::os::system::{uname,
 use std::{io,
           net::{IpAddr,
                 Ipv4Addr,
+                Ipv6Addr,
                 SocketAddr,
                 ToSocketAddrs,
                 UdpSocket}};
 
 const UNSPECIFIED_SOCKET_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::UNSPECIFIED, 0);
+const UNSPECIFIED_V6_SOCKET_ADDR: (Ipv6Addr, u16) = (Ipv6Addr::UNSPECIFIED, 0);
+/// Google DNS over IPv6; used on IPv6-only hosts, which have no route to the default lookup
+/// address
+const OUTBOUND_IPV6_ADDR_LOOKUP_SOCKET_ADDR: (Ipv6Addr, u16) =
+    (Ipv6Addr::new(0x2001, 0x4860, 0x4860, 0, 0, 0, 0, 0x8888), 53);
 
 crate::env_config_socketaddr!(OutboundIpAddrLookupSocketAddr,
                               HAB_OUTBOUND_IP_ADDR_LOOKUP_SOCKET_ADDR,
@@ -33,11 +39,22 @@ crate::env_config_socketaddr!(OutboundIpAddrLookupSocketAddr,
 /// [1]: http://www.masterraghu.com/subjects/np/introduction/unix_network_programming_v1.3/ch08lev1sec14.html
 pub fn ip() -> Result<IpAddr> {
     let connect_addr = SocketAddr::from(OutboundIpAddrLookupSocketAddr::configured_value());
-    ip_impl(connect_addr).map_err(Error::NoOutboundIpAddr)
+    let is_default = connect_addr == SocketAddr::from(OutboundIpAddrLookupSocketAddr::default());
+    match ip_impl(connect_addr) {
+        Err(_) if is_default => ip_impl(OUTBOUND_IPV6_ADDR_LOOKUP_SOCKET_ADDR),
+        result => result,
+    }.map_err(Error::NoOutboundIpAddr)
 }
 
 fn ip_impl(connect_addr: impl ToSocketAddrs) -> io::Result<IpAddr> {
-    let socket = UdpSocket::bind(UNSPECIFIED_SOCKET_ADDR)?;
+    let connect_addr = connect_addr.to_socket_addrs()?
+                                   .next()
+                                   .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let socket = if connect_addr.is_ipv4() {
+        UdpSocket::bind(UNSPECIFIED_SOCKET_ADDR)?
+    } else {
+        UdpSocket::bind(UNSPECIFIED_V6_SOCKET_ADDR)?
+    };
     socket.connect(connect_addr)?;
     let addr = socket.local_addr()?;
     Ok(addr.ip())
@@ -59,4 +76,14 @@ mod test {
         // localhost should route back to localhost
         assert_eq!(ip().unwrap(), Ipv4Addr::LOCALHOST);
     }
+
+    #[test]
+    fn ipv6_ip_lookup() {
+        // Only meaningful where the loopback interface has an IPv6 address
+        if UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).is_err() {
+            return;
+        }
+        assert_eq!(ip_impl((Ipv6Addr::LOCALHOST, 80)).unwrap(),
+                   Ipv6Addr::LOCALHOST);
+    }
 }
@@ -4,6 +4,7 @@ pub mod hab;
 
 use crate::cli::hab::Hab;
 use clap::{App,
+           Arg,
            ArgMatches};
 use habitat_common::{cli::{file_into_idents,
                            is_toml_file},
@@ -29,6 +30,35 @@ pub const AFTER_HELP: &str =
 
 pub fn get(_feature_flags: FeatureFlag) -> App<'static, 'static> { Hab::clap() }
 
+/// The `--error-format` option. It is global, so that it may be given to any subcommand.
+pub fn error_format_arg() -> Arg<'static, 'static> {
+    Arg::with_name("ERROR_FORMAT").long("error-format")
+                                  .value_name("FORMAT")
+                                  .possible_values(&["text", "json"])
+                                  .global(true)
+                                  .help("How to report the error hab fails with, as text or as a \
+                                         line of JSON on standard error [env: HAB_ERROR_FORMAT] \
+                                         [default: text]")
+}
+
+/// The `--offline` option. It applies to the whole command, so it is only accepted before the
+/// subcommand.
+pub fn offline_arg() -> Arg<'static, 'static> {
+    Arg::with_name("OFFLINE").long("offline")
+                             .help("Never contact Builder, using only what is in the local \
+                                    caches. Commands run by this one are offline too [env: \
+                                    HAB_OFFLINE]")
+}
+
+/// The `--ring` option. It applies to the whole command, so it is only accepted before the
+/// subcommand.
+pub fn ring_arg() -> Arg<'static, 'static> {
+    Arg::with_name("RING").long("ring")
+                          .value_name("RING_NAME")
+                          .validator(valid_ring_name)
+                          .help("Talk to the Supervisor of the named ring [env: HAB_RING_NAME]")
+}
+
 ////////////////////////////////////////////////////////////////////////
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
@@ -125,9 +155,34 @@ fn valid_fully_qualified_ident(val: String) -> result::Result<(), String> {
     }
 }
 
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_binlink_pin(val: String) -> result::Result<(), String> {
+    crate::command::pkg::binlink::parse_pin(&val).map(|_| ())
+                                                 .map_err(|e| e.to_string())
+}
+
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+fn valid_target_label(val: String) -> result::Result<(), String> {
+    match val.split_once('=') {
+        Some((key, _)) if !key.is_empty() => Ok(()),
+        _ => Err(format!("Label '{}' is not of the form KEY=VALUE", val)),
+    }
+}
+
 #[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
 fn valid_origin(val: String) -> result::Result<(), String> { CoreOrigin::validate(val) }
 
+#[allow(clippy::needless_pass_by_value)] // Signature required by CLAP
+pub fn valid_ring_name(val: String) -> result::Result<(), String> {
+    if habitat_sup_protocol::is_valid_ring_name(&val) {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not valid. A valid ring name contains \
+                     a-z, 0-9, and _ or - after the first character",
+                    &val))
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
@@ -24,7 +24,9 @@ use self::{bldr::*,
                     ConfigOptServiceConfigShow,
                     ServiceConfigApply,
                     ServiceConfigShow},
-           file::{ConfigOptFileUpload,
+           file::{ConfigOptFileStatus,
+                  ConfigOptFileUpload,
+                  FileStatus,
                   FileUpload},
            license::{ConfigOptLicense,
                      License},
@@ -37,9 +39,13 @@ use self::{bldr::*,
            ring::{ConfigOptRingKeyExport,
                   ConfigOptRingKeyGenerate,
                   ConfigOptRingKeyImport,
+                  ConfigOptRingManifestApply,
+                  ConfigOptRingStatus,
                   RingKeyExport,
                   RingKeyGenerate,
-                  RingKeyImport},
+                  RingKeyImport,
+                  RingManifestApply,
+                  RingStatus},
            studio::{ConfigOptStudio,
                     Studio},
            sup::{ConfigOptHabSup,
@@ -56,7 +62,10 @@ use self::{bldr::*,
                   UserKeyGenerate},
            util::{CacheKeyPath,
                   ConfigOptCacheKeyPath}};
-use crate::{cli::AFTER_HELP,
+use crate::{cli::{error_format_arg,
+                  offline_arg,
+                  ring_arg,
+                  AFTER_HELP},
             VERSION};
 use configopt::ConfigOpt;
 use structopt::{clap::AppSettings,
@@ -68,6 +77,9 @@ use structopt::{clap::AppSettings,
             about = "Patents: https://chef.io/patents\n\"A Habitat is the natural environment for your services\" - Alan Turing",
             author = "\nThe Habitat Maintainers <humans@habitat.sh>\n",
             settings = &[AppSettings::GlobalVersion],
+            arg = error_format_arg(),
+            arg = offline_arg(),
+            arg = ring_arg(),
             after_help = AFTER_HELP
         )]
 #[allow(clippy::large_enum_variant)]
@@ -101,6 +113,8 @@ pub enum Hab {
     Svc(Svc),
     #[structopt(no_version)]
     User(User),
+    #[structopt(no_version)]
+    X(PkgX),
 
     /// Alias for 'config apply'
     #[structopt(no_version, settings = &[AppSettings::Hidden])]
@@ -131,12 +145,21 @@ pub enum Hab {
 #[structopt(no_version, aliases = &["b", "bl", "bld"], settings = &[AppSettings::ArgRequiredElseHelp, AppSettings::SubcommandRequiredElseHelp])]
 /// Commands relating to Habitat Builder
 pub enum Bldr {
+    #[structopt(no_version)]
+    Artifact(Artifact),
     #[structopt(no_version)]
     Channel(Channel),
     #[structopt(no_version)]
     Job(Job),
 }
 
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version, aliases = &["a", "ar", "art", "arti", "artif", "artifa", "artifac"], settings = &[AppSettings::ArgRequiredElseHelp, AppSettings::SubcommandRequiredElseHelp])]
+/// Commands relating to Habitat Builder artifacts
+pub enum Artifact {
+    PromoteOnGreen(ArtifactPromoteOnGreen),
+}
+
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(no_version, aliases = &["c", "ch", "cha", "chan", "chann", "channe"], settings = &[AppSettings::ArgRequiredElseHelp, AppSettings::SubcommandRequiredElseHelp])]
 /// Commands relating to Habitat Builder channels
@@ -196,6 +219,9 @@ pub enum File {
     #[structopt(no_version, aliases = &["u", "up", "upl", "uplo", "uploa"])]
     /// Uploads a file to be shared between members of a Service Group
     Upload(FileUpload),
+    #[structopt(no_version, aliases = &["s", "st", "sta", "stat", "statu"])]
+    /// Reports what a Supervisor's member of a Service Group did with the files uploaded to it
+    Status(FileStatus),
 }
 
 /// Commands relating to Habitat users
@@ -289,6 +315,8 @@ pub enum Pkg {
     Install(PkgInstall),
     #[structopt(no_version, aliases = &["p", "pa", "pat"])]
     Path(PkgPath),
+    #[structopt(no_version, aliases = &["pre", "pref"])]
+    Prefetch(PkgPrefetch),
     #[structopt(no_version, aliases = &["li"])]
     List(PkgList),
     Provides(PkgProvides),
@@ -334,6 +362,9 @@ pub enum Plan {
 /// Commands relating to Habitat rings
 pub enum Ring {
     Key(RingKey),
+    Manifest(RingManifest),
+    #[structopt(no_version, aliases = &["s", "st", "sta", "stat", "statu"])]
+    Status(RingStatus),
 }
 
 #[derive(ConfigOpt, StructOpt)]
@@ -347,3 +378,11 @@ pub enum RingKey {
     #[structopt(no_version, aliases = &["g", "ge", "gen", "gene", "gener", "genera", "generat"])]
     Generate(RingKeyGenerate),
 }
+
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "manifest", no_version, aliases = &["m", "ma", "man"], settings = &[AppSettings::ArgRequiredElseHelp, AppSettings::SubcommandRequiredElseHelp])]
+/// Commands relating to the fleet manifest of a Habitat ring
+pub enum RingManifest {
+    #[structopt(no_version, aliases = &["ap", "app", "appl"])]
+    Apply(RingManifestApply),
+}
@@ -1,13 +1,19 @@
-use super::util::{AuthToken,
-                  BldrUrl,
-                  ConfigOptAuthToken,
-                  ConfigOptBldrUrl,
-                  ConfigOptPkgIdent,
-                  PkgIdent};
+use super::{svc::{ConfigOptSharedLoad,
+                  SharedLoad},
+            util::{AuthToken,
+                   BldrUrl,
+                   ConfigOptAuthToken,
+                   ConfigOptBldrUrl,
+                   ConfigOptFullyQualifiedPkgIdent,
+                   ConfigOptPkgIdent,
+                   FullyQualifiedPkgIdent,
+                   PkgIdent}};
 use crate::cli::valid_origin;
 use configopt::ConfigOpt;
-use habitat_common::cli::PACKAGE_TARGET_ENVVAR;
-use habitat_core::package::PackageTarget;
+use habitat_common::{cli::PACKAGE_TARGET_ENVVAR,
+                     types::ResolvedListenCtlAddr};
+use habitat_core::{package::PackageTarget,
+                   ChannelIdent};
 use structopt::{clap::ArgGroup,
                 StructOpt};
 
@@ -15,12 +21,57 @@ use structopt::{clap::ArgGroup,
 #[structopt(no_version)]
 /// Commands relating to Habitat Builder
 pub enum Bldr {
+    #[structopt(no_version)]
+    Artifact(Artifact),
     #[structopt(no_version)]
     Channel(Channel),
     #[structopt(no_version)]
     Job(Job),
 }
 
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version)]
+/// Commands relating to Habitat Builder artifacts
+pub enum Artifact {
+    PromoteOnGreen(ArtifactPromoteOnGreen),
+}
+
+/// Load a release into a staging service group, and promote it to a channel once it is healthy
+///
+/// The release is loaded, or reloaded, on every Supervisor given with `--remote-sup`, which are
+/// then asked for its health once per health check interval. It is promoted once it was running
+/// and healthy on all of them that many times in a row, and is not promoted if that does not
+/// happen within the timeout.
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "promote-on-green", no_version, rename_all = "screamingsnake")]
+pub struct ArtifactPromoteOnGreen {
+    #[structopt(flatten)]
+    pub pkg_ident:   FullyQualifiedPkgIdent,
+    /// The channel to promote the release to once it is healthy
+    #[structopt(name = "TARGET_CHANNEL")]
+    pub channel:     ChannelIdent,
+    /// A package target (ex: x86_64-windows) (default: system appropriate target)
+    #[structopt(name = "PKG_TARGET", env = PACKAGE_TARGET_ENVVAR)]
+    pub pkg_target:  Option<PackageTarget>,
+    /// The addresses of the Control Gateways of the Supervisors to stage the release on
+    #[structopt(name = "REMOTE_SUP",
+                long = "remote-sup",
+                short = "r",
+                required = true,
+                number_of_values = 1)]
+    pub remote_sups: Vec<ResolvedListenCtlAddr>,
+    /// How many health checks in a row the release must pass on every Supervisor
+    #[structopt(long = "checks", default_value = "3")]
+    pub checks:      u32,
+    /// How long in seconds to wait for the release to become healthy before giving up
+    #[structopt(long = "timeout", default_value = "600")]
+    pub timeout:     u64,
+    #[structopt(flatten)]
+    pub shared_load: SharedLoad,
+    #[structopt(flatten)]
+    pub auth_token:  AuthToken,
+}
+
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(no_version)]
 /// Commands relating to Habitat Builder channels
@@ -2,8 +2,10 @@ use super::util::{CacheKeyPath,
                   ConfigOptCacheKeyPath,
                   ConfigOptPkgIdent,
                   ConfigOptRemoteSup,
+                  HumanDuration,
                   PkgIdent,
-                  RemoteSup};
+                  RemoteSup,
+                  Timestamp};
 use crate::cli::file_exists_or_stdin;
 use configopt::ConfigOpt;
 use habitat_core::service::ServiceGroup;
@@ -34,6 +36,14 @@ pub struct ServiceConfigApply {
     /// Name of a user key to use for encryption
     #[structopt(short = "u", long = "user")]
     user:           Option<String>,
+    /// Have the members apply the configuration at this time by their clocks instead of as soon
+    /// as they receive it (ex: 2024-06-01T02:00Z)
+    #[structopt(long = "at", conflicts_with = "AFTER")]
+    at:             Option<Timestamp>,
+    /// Have the members apply the configuration this long from now instead of as soon as they
+    /// receive it (ex: 30m, 2h)
+    #[structopt(long = "after")]
+    after:          Option<HumanDuration>,
     #[structopt(flatten)]
     remote_sup:     RemoteSup,
     #[structopt(flatten)]
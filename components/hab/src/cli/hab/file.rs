@@ -2,7 +2,8 @@ use super::util::{CacheKeyPath,
                   ConfigOptCacheKeyPath,
                   ConfigOptRemoteSup,
                   RemoteSup};
-use crate::cli::file_exists;
+use crate::cli::{file_exists,
+                 valid_target_label};
 use configopt::ConfigOpt;
 use habitat_core::service::ServiceGroup;
 use structopt::StructOpt;
@@ -12,6 +13,7 @@ use structopt::StructOpt;
 /// Commands relating to Habitat files
 pub enum File {
     Upload(FileUpload),
+    Status(FileStatus),
 }
 
 /// Uploads a file to be shared between members of a Service Group
@@ -31,8 +33,33 @@ pub struct FileUpload {
     /// Name of the user key
     #[structopt(name = "USER", short = "u", long = "user")]
     user:           Option<String>,
+    /// Only write the file on the member with this ID. May be given more than once
+    #[structopt(name = "TARGET_MEMBER", long = "target-member", number_of_values = 1)]
+    target_member:  Vec<String>,
+    /// Only write the file on members with this Supervisor label (ex: zone=us-east-1a). Members
+    /// must have every label given, unless they are targeted by ID
+    #[structopt(name = "TARGET_LABEL",
+                long = "target-label",
+                number_of_values = 1,
+                validator = valid_target_label)]
+    target_label:   Vec<String>,
+    /// Render the file with the service's templating data on each member before it is written
+    #[structopt(name = "TEMPLATE", long = "template")]
+    template:       bool,
     #[structopt(flatten)]
     remote_sup:     RemoteSup,
     #[structopt(flatten)]
     cache_key_path: CacheKeyPath,
 }
+
+/// Reports what a Supervisor's member of a Service Group did with the files uploaded to it
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "status", no_version)]
+pub struct FileStatus {
+    /// Target service group service.group[@organization] (ex: redis.default or
+    /// foo.default@bazcorp)
+    #[structopt(name = "SERVICE_GROUP")]
+    service_group: ServiceGroup,
+    #[structopt(flatten)]
+    remote_sup:    RemoteSup,
+}
@@ -15,6 +15,7 @@ use super::util::{self,
                   PkgIdent};
 use crate::cli::{dir_exists,
                  file_exists,
+                 valid_binlink_pin,
                  valid_ident_or_toml_file,
                  valid_origin};
 use configopt::ConfigOpt;
@@ -90,6 +91,7 @@ pub enum Pkg {
     Install(PkgInstall),
     List(PkgList),
     Path(PkgPath),
+    Prefetch(PkgPrefetch),
     Promote(PkgPromote),
     Provides(PkgProvides),
     Search(PkgSearch),
@@ -292,8 +294,10 @@ pub struct PkgVerify {
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(name = "binlink", no_version, rename_all = "screamingsnake")]
 pub struct PkgBinlink {
-    #[structopt(flatten)]
-    pkg_ident: PkgIdent,
+    /// A package identifier (ex: core/redis, core/busybox-static/1.42.2)
+    #[structopt(name = "PKG_IDENT",
+                required_unless_one = &["SYNC", "CLEAN", "PIN", "UNPIN"])]
+    pkg_ident: Option<PackageIdent>,
     /// The command to binlink (ex: bash)
     #[structopt(name = "BINARY")]
     binary:    Option<String>,
@@ -307,6 +311,29 @@ pub struct PkgBinlink {
     /// Overwrite existing binlinks
     #[structopt(name = "FORCE", short = "f", long = "force")]
     force:     bool,
+    /// What to do when a binary is already binlinked from another package: keep the existing
+    /// binlink, keep the binlink from the newest release or fail
+    #[structopt(name = "CONFLICT",
+                long = "conflict",
+                default_value = "skip",
+                possible_values = &["skip", "newest", "error"],
+                conflicts_with = "FORCE")]
+    conflict:  String,
+    /// Always binlink a binary from the given package, whatever the conflict policy (ex:
+    /// openssl=core/openssl). Pins are kept for later binlinks
+    #[structopt(name = "PIN", long = "pin", number_of_values = 1, validator = valid_binlink_pin)]
+    pin:       Vec<String>,
+    /// Remove the pin of a binary
+    #[structopt(name = "UNPIN", long = "unpin", number_of_values = 1)]
+    unpin:     Vec<String>,
+    /// Update every binlink created by `hab pkg binlink` to the newest installed release of its
+    /// package, and remove the binlinks of packages that are no longer installed
+    #[structopt(name = "SYNC", long = "sync", conflicts_with = "PKG_IDENT")]
+    sync:      bool,
+    /// Remove the binlinks in the destination directory that point into packages that are no
+    /// longer installed
+    #[structopt(name = "CLEAN", long = "clean", conflicts_with = "PKG_IDENT")]
+    clean:     bool,
 }
 
 /// Builds a Plan using a Studio
@@ -421,12 +448,49 @@ pub struct PkgDownload {
     ignore_missing_seed: bool,
 }
 
+/// Download the current releases of packages (including dependencies and keys) into the local
+/// artifact cache without installing them
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "prefetch", no_version, rename_all = "screamingsnake")]
+pub struct PkgPrefetch {
+    #[structopt(flatten)]
+    auth_token:          AuthToken,
+    #[structopt(flatten)]
+    bldr_url:            BldrUrl,
+    /// Prefetch from the specified release channel. Overridden if channel is specified in toml
+    /// file
+    #[structopt(name = "CHANNEL",
+                short = "c",
+                long = "channel",
+                default_value = "stable",
+                env = ChannelIdent::ENVVAR)]
+    channel:             String,
+    /// File with newline separated package identifiers, or TOML file (ending with .toml
+    /// extension)
+    #[structopt(name = "PKG_IDENT_FILE", long = "file", validator = valid_ident_or_toml_file)]
+    pkg_ident_file:      Vec<String>,
+    /// One or more Habitat package identifiers (ex: acme/redis)
+    #[structopt(name = "PKG_IDENT")]
+    pkg_ident:           Vec<PackageIdent>,
+    /// Target architecture to fetch. E.g. x86_64-linux. Overridden if architecture is
+    /// specified in toml file
+    #[structopt(name = "PKG_TARGET", short = "t", long = "target")]
+    pkg_target:          Option<PackageTarget>,
+    /// Ignore packages specified that are not present on the target Builder
+    #[structopt(name = "IGNORE_MISSING_SEEDS", long = "ignore-missing-seeds")]
+    ignore_missing_seed: bool,
+}
+
 /// Executes a command using the 'PATH' context of an installed package
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(name = "exec", aliases = &["exe"], no_version, rename_all = "screamingsnake")]
 pub struct PkgExec {
     #[structopt(flatten)]
     pub pkg_ident: PkgIdent,
+    /// Add the runtime environment of another package, after the one of PKG_IDENT (ex: --with
+    /// core/openssl --with core/curl)
+    #[structopt(name = "WITH", long = "with", number_of_values = 1)]
+    pub with:      Vec<PackageIdent>,
     /// The command to execute (ex: ls)
     #[structopt()]
     pub cmd:       PathBuf,
@@ -434,12 +498,47 @@ pub struct PkgExec {
     pub args:      ExternalCommandArgsWithHelpAndVersion,
 }
 
+/// Runs a command from a package, installing the package for the run if it is not installed
+///
+/// Packages installed for the run, dependencies included, are removed again once the command
+/// exits unless --keep is given. Packages that were already installed are never removed.
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "x", no_version, rename_all = "screamingsnake")]
+pub struct PkgX {
+    #[structopt(flatten)]
+    pub bldr_url:   BldrUrl,
+    /// Install from the specified release channel
+    #[structopt(short = "c",
+                long = "channel",
+                default_value = "stable",
+                env = ChannelIdent::ENVVAR)]
+    pub channel:    String,
+    #[structopt(flatten)]
+    pub auth_token: AuthToken,
+    /// Keep the packages installed for the run
+    #[structopt(name = "KEEP", long = "keep")]
+    pub keep:       bool,
+    #[structopt(flatten)]
+    pub pkg_ident:  PkgIdent,
+    /// The command to execute (ex: rg)
+    #[structopt()]
+    pub cmd:        PathBuf,
+    #[structopt(flatten)]
+    pub args:       ExternalCommandArgsWithHelpAndVersion,
+}
+
 /// Prints the runtime environment of a specific installed package
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(name = "env", no_version)]
 pub struct PkgEnv {
-    #[structopt(flatten)]
-    pkg_ident: PkgIdent,
+    /// One or more package identifiers (ex: core/redis, core/busybox-static/1.42.2). More than
+    /// one requires --merged
+    #[structopt(name = "PKG_IDENT", required = true)]
+    pkg_ident: Vec<PackageIdent>,
+    /// Print the runtime environment of all of the packages layered in the order given, and flag
+    /// the variables they set to different values and the commands shadowed on the PATH
+    #[structopt(name = "MERGED", long = "merged")]
+    merged:    bool,
 }
 
 /// Generates a blake2b hashsum from a target at any given filepath
@@ -488,8 +587,18 @@ pub struct PkgInstall {
     channel:               String,
     /// One or more Habitat package identifiers (ex: acme/redis) and/or filepaths to a Habitat
     /// Artifact (ex: /home/acme-redis-3.0.7-21120102031201-x86_64-linux.hart)
-    #[structopt(required = true)]
+    #[structopt(required_unless = "PKG_IDENT_FILE")]
     pkg_ident_or_artifact: Vec<String>,
+    /// A TOML file of packages to install, in the format used by `hab pkg download --file`.
+    /// Either all of the packages are installed or none are
+    #[structopt(name = "PKG_IDENT_FILE",
+                long = "file",
+                conflicts_with = "PKG_IDENT_OR_ARTIFACT",
+                validator = file_exists)]
+    pkg_ident_file:        Option<PathBuf>,
+    /// Write a JSON report of the result of installing each package of the file to this path
+    #[structopt(name = "REPORT", long = "report", requires = "PKG_IDENT_FILE")]
+    report:                Option<PathBuf>,
     /// Binlink all binaries from installed package(s) into BINLINK_DIR
     #[structopt(short = "b", long = "binlink")]
     binlink:               bool,
@@ -1,5 +1,9 @@
 use super::util::{CacheKeyPath,
-                  ConfigOptCacheKeyPath};
+                  ConfigOptCacheKeyPath,
+                  ConfigOptRemoteSup,
+                  RemoteSup};
+use crate::cli::{file_exists_or_stdin,
+                 valid_origin};
 use configopt::ConfigOpt;
 use structopt::StructOpt;
 
@@ -8,6 +12,53 @@ use structopt::StructOpt;
 /// Commands relating to Habitat rings
 pub enum Ring {
     Key(Key),
+    Manifest(Manifest),
+    Status(RingStatus),
+}
+
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version)]
+/// Commands relating to the fleet manifest of a Habitat ring
+pub enum Manifest {
+    Apply(RingManifestApply),
+}
+
+/// Signs a fleet manifest and gossips it to the ring
+///
+/// Supervisors started with `--fleet-manifest-origin` load and unload services to match the
+/// manifest, provided it is signed by a key of that origin.
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "apply", no_version, rename_all = "screamingsnake")]
+pub struct RingManifestApply {
+    /// A version number (positive integer) for this manifest, greater than that of the manifest
+    /// it replaces (ex: 42)
+    #[structopt()]
+    version_number: u64,
+    /// Path to local file on disk (ex: /tmp/fleet.toml, default: <stdin>)
+    #[structopt(validator = file_exists_or_stdin)]
+    file:           Option<String>,
+    /// The origin whose latest secret signing key signs the manifest. Default is from
+    /// 'HAB_ORIGIN' or cli.toml
+    #[structopt(name = "ORIGIN",
+                short = "o",
+                long = "origin",
+                validator = valid_origin)]
+    origin:         Option<String>,
+    #[structopt(flatten)]
+    remote_sup:     RemoteSup,
+    #[structopt(flatten)]
+    cache_key_path: CacheKeyPath,
+}
+
+/// Report the health of the whole ring, as seen by a Supervisor
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "status", no_version)]
+pub struct RingStatus {
+    /// Output will be rendered in json
+    #[structopt(name = "TO_JSON", short = "j", long = "json")]
+    to_json:    bool,
+    #[structopt(flatten)]
+    remote_sup: RemoteSup,
 }
 
 #[derive(ConfigOpt, StructOpt)]
@@ -10,7 +10,8 @@ use super::{svc::{ConfigOptSharedLoad,
                    DurationProxy,
                    RemoteSup,
                    SubjectAlternativeName}};
-use crate::{error::Error,
+use crate::{cli::valid_ring_name,
+            error::Error,
             VERSION};
 use configopt::{self,
                 configopt_fields,
@@ -18,29 +19,40 @@ use configopt::{self,
 use habitat_common::{cli::{RING_ENVVAR,
                            RING_KEY_ENVVAR},
                      command::package::install::InstallSource,
-                     types::{EventStreamConnectMethod,
+                     types::{DenylistEntry,
+                             Ec2PeerFilter,
+                             EventStreamConnectMethod,
                              EventStreamMetaPair,
                              EventStreamServerCertificate,
                              EventStreamToken,
+                             GossipAdvertiseAddr,
                              GossipListenAddr,
                              HttpListenAddr,
+                             KubernetesService,
                              ListenCtlAddr,
-                             ResolvedListenCtlAddr},
+                             MemberIdSource,
+                             ResolvedListenCtlAddr,
+                             SupervisorLabel},
                      FeatureFlag,
                      FEATURE_FLAGS};
 use habitat_core::{env::Config,
                    fs::HAB_CTL_KEYS_CACHE,
+                   origin::Origin,
                    package::PackageIdent,
                    util as core_util};
+use habitat_sup_protocol::RING_NAME_ENVVAR;
 use rants::{error::Error as RantsError,
             Address as NatsAddress};
-use std::{fmt,
+use std::{ffi::OsString,
+          fmt,
+          iter,
           net::{IpAddr,
                 SocketAddr},
           path::PathBuf,
           str::FromStr};
 use structopt::{clap::AppSettings,
                 StructOpt};
+use url::Url;
 
 // All commands relating to the Supervisor (ie commands handled by both the `hab` and `hab-sup`
 // binary)
@@ -50,11 +62,45 @@ use structopt::{clap::AppSettings,
 pub enum HabSup {
     /// Depart a Supervisor from the gossip ring; kicking and banning the target from joining again
     /// with the same member-id
+    ///
+    /// A Supervisor departed with --self refuses to start again until `hab sup rejoin` is run. It
+    /// refuses to depart while it leads a service group, unless its services are stopped too.
     #[structopt(no_version, aliases = &["d", "de", "dep", "depa", "depart"])]
     Depart {
         /// The member-id of the Supervisor to depart
-        #[structopt(name = "MEMBER_ID")]
-        member_id:  String,
+        #[structopt(name = "MEMBER_ID", required_unless_one = &["SELF", "ADDRESS"])]
+        member_id:     Option<String>,
+        /// Depart the Supervisor the command is sent to
+        #[structopt(name = "SELF", long = "self", conflicts_with_all = &["MEMBER_ID", "ADDRESS"])]
+        depart_self:   bool,
+        /// Depart the other member at an IP address or hostname, found by the address it gossips
+        /// from or the hostname and IP address its services report
+        #[structopt(name = "ADDRESS", long = "address", conflicts_with = "MEMBER_ID")]
+        address:       Option<String>,
+        /// Depart every member found at the address; without it, an address more than one member
+        /// is found at is refused
+        #[structopt(long = "all", requires = "ADDRESS")]
+        all:           bool,
+        /// Stop the Supervisor and all of its services once it has departed
+        #[structopt(long = "stop-services", requires = "SELF")]
+        stop_services: bool,
+        #[structopt(flatten)]
+        remote_sup:    RemoteSup,
+    },
+    /// Refuse all gossip from members of the ring, or allow it again
+    ///
+    /// Members are given by member ID, or by an IP address or CIDR range their address is in (ex:
+    /// 10.0.4.0/24). Denied members are departed and kept out of the ring across restarts of the
+    /// Supervisor, and a member denied by address is refused under any member-id. The
+    /// Supervisor's denylist is displayed once it is updated.
+    #[structopt(no_version)]
+    Deny {
+        /// The members to deny
+        #[structopt(name = "MEMBER")]
+        deny:       Vec<DenylistEntry>,
+        /// A member to allow again
+        #[structopt(long = "allow")]
+        allow:      Vec<DenylistEntry>,
         #[structopt(flatten)]
         remote_sup: RemoteSup,
     },
@@ -66,6 +112,9 @@ pub enum HabSup {
         /// A package identifier (ex: core/redis, core/busybox-static/1.42.2)
         #[structopt(name = "PKG_IDENT")]
         pkg_ident:  Option<PackageIdent>,
+        /// Also show the result and output of each service's most recent health check
+        #[structopt(long = "detail")]
+        detail:     bool,
         #[structopt(flatten)]
         remote_sup: RemoteSup,
     },
@@ -75,6 +124,11 @@ pub enum HabSup {
         #[structopt(flatten)]
         remote_sup: RemoteSup,
     },
+    #[structopt(no_version, aliases = &["c", "co", "con", "conf"])]
+    Config(SupConfig),
+    #[cfg(not(target_os = "macos"))]
+    #[structopt(no_version)]
+    Cluster(SupCluster),
     #[cfg(not(target_os = "macos"))]
     #[structopt(flatten)]
     Sup(Sup),
@@ -93,6 +147,20 @@ pub enum Sup {
     /// Start an interactive Bash-like shell
     #[structopt(no_version, aliases = &["b", "ba", "bas"])]
     Bash,
+    /// Write the services loaded on the Supervisor out as a PowerShell DSC configuration or as
+    /// Ansible tasks that load them again with the same options
+    #[structopt(no_version)]
+    Export(SupExport),
+    /// Check the Supervisor's state directory for corrupt files
+    #[structopt(no_version)]
+    Fsck(SupFsck),
+    /// Check the state directory of a Supervisor departed with `hab sup depart --self` and clear
+    /// its departed member-id, so that it joins the ring as a new member when it next starts
+    #[structopt(no_version)]
+    Rejoin,
+    /// Print the JSON schema of the data available to configuration and hook templates
+    #[structopt(no_version)]
+    RenderContextSchema,
     #[structopt(no_version, aliases = &["r", "ru"])]
     Run(SupRun),
     /// Start an interactive Bourne-like shell
@@ -107,6 +175,26 @@ pub enum Sup {
 #[structopt(name = "term", no_version)]
 pub struct SupTerm {}
 
+#[derive(StructOpt)]
+#[structopt(name = "fsck", no_version)]
+pub struct SupFsck {
+    /// Restore corrupt files from their last good copy, or move them aside so the Supervisor
+    /// recreates them
+    #[structopt(long = "repair")]
+    pub repair: bool,
+}
+
+#[derive(StructOpt)]
+#[structopt(name = "export", no_version)]
+pub struct SupExport {
+    /// The configuration management tooling to export to
+    #[structopt(long = "format", default_value = "dsc", possible_values = &["dsc", "ansible"])]
+    pub format: String,
+    /// The file to write the exported services to
+    #[structopt(name = "OUTPUT")]
+    pub output: PathBuf,
+}
+
 // TODO (DM): This is unnecessarily difficult due to this issue in serde
 // https://github.com/serde-rs/serde/issues/723. The easiest way to get around the issue is by
 // using a wrapper type since NatsAddress is not defined in this crate.
@@ -127,6 +215,46 @@ impl From<EventStreamAddress> for NatsAddress {
     fn from(address: EventStreamAddress) -> Self { address.0 }
 }
 
+/// The config file read by `hab sup run` when no `--config-files` are given. This is
+/// `/hab/sup/default/config/sup.toml` unless a named ring is given or selected.
+pub fn sup_default_config_file(ring_name: Option<&str>) -> PathBuf {
+    habitat_sup_protocol::ring_sup_root(ring_name).join("config")
+                                                  .join("sup.toml")
+}
+
+impl SupRun {
+    /// Parse `hab sup run` arguments, starting with `run`. The default config file belongs to the
+    /// ring the Supervisor runs in, which is only known once the arguments are parsed, so they are
+    /// parsed again with that file when no `--config-files` are given and it exists.
+    pub fn try_from_args_with_default_config(args: &[OsString]) -> Result<Self, configopt::Error> {
+        Self::try_from_args_with_config_file_of(args, sup_default_config_file)
+    }
+
+    /// Parse `hab sup run` arguments as `try_from_args_with_default_config` does, with the default
+    /// config file of a ring given by `default_config_file`.
+    pub fn try_from_args_with_config_file_of<F>(args: &[OsString],
+                                                default_config_file: F)
+                                                -> Result<Self, configopt::Error>
+        where F: Fn(Option<&str>) -> PathBuf
+    {
+        let sup_run = Self::try_from_iter_with_configopt(args)?;
+        let matches = Self::clap().get_matches_from_safe(args)
+                                  .map_err(configopt::Error::Clap)?;
+        let config_file = default_config_file(sup_run.ring_name.as_deref());
+        if matches.is_present("CONFIG_FILES") || !config_file.is_file() {
+            return Ok(sup_run);
+        }
+        let mut config_files_arg = OsString::from("--config-files=");
+        config_files_arg.push(config_file);
+        let args = args.iter()
+                       .take(1)
+                       .cloned()
+                       .chain(iter::once(config_files_arg))
+                       .chain(args.iter().skip(1).cloned());
+        Self::try_from_iter_with_configopt(args)
+    }
+}
+
 fn parse_peer(s: &str) -> Result<SocketAddr, Error> {
     Ok(habitat_common::util::resolve_socket_addr_with_default_port(s, GossipListenAddr::DEFAULT_PORT)?.1)
 }
@@ -134,7 +262,7 @@ fn parse_peer(s: &str) -> Result<SocketAddr, Error> {
 /// Run the Habitat Supervisor
 #[configopt_fields]
 #[derive(ConfigOpt, StructOpt, Deserialize)]
-#[configopt(attrs(serde), default_config_file("/hab/sup/default/config/sup.toml"))]
+#[configopt(attrs(serde))]
 #[serde(deny_unknown_fields)]
 #[structopt(name = "run",
             no_version,
@@ -153,8 +281,30 @@ pub struct SupRun {
     pub listen_gossip: GossipListenAddr,
     /// Start the supervisor in local mode
     #[structopt(long = "local-gossip-mode",
-                conflicts_with_all = &["LISTEN_GOSSIP", "PEER", "PEER_WATCH_FILE"])]
+                conflicts_with_all = &["LISTEN_GOSSIP",
+                                       "PEER",
+                                       "PEER_WATCH_FILE",
+                                       "PEER_KUBERNETES_SERVICE",
+                                       "PEER_EC2",
+                                       "PEER_MDNS",
+                                       "PEER_CONSUL_SERVICE"])]
     pub local_gossip_mode: bool,
+    /// The address to advertise to other Supervisors for gossip (IP[:PORT])
+    ///
+    /// Use this when other Supervisors cannot reach this one at the address its gossip appears to
+    /// come from, such as behind NAT or in an overlay network. The port defaults to the
+    /// LISTEN_GOSSIP port.
+    #[structopt(long = "gossip-advertise-addr",
+                conflicts_with_all = &["LOCAL_GOSSIP_MODE", "GOSSIP_STUN_SERVER"])]
+    pub gossip_advertise_addr: Option<GossipAdvertiseAddr>,
+    /// Detect the address to advertise to other Supervisors for gossip with a STUN server
+    /// (HOST[:PORT])
+    ///
+    /// Only the UDP port mapping is detected, so the TCP gossip port must be forwarded to the
+    /// Supervisor unchanged. If detection fails, the Supervisor starts without advertising an
+    /// address.
+    #[structopt(long = "gossip-stun-server", conflicts_with = "LOCAL_GOSSIP_MODE")]
+    pub gossip_stun_server: Option<String>,
     /// The listen address for the HTTP Gateway
     #[structopt(long = "listen-http",
                 env = HttpListenAddr::ENVVAR,
@@ -181,6 +331,13 @@ pub struct SupRun {
     #[structopt(long = "ctl-client-ca-certificate",
                 default_value = HAB_CTL_KEYS_CACHE)]
     pub ctl_client_ca_certificate: Option<RootCertificateStoreCli>,
+    /// Also serve the control gateway over gRPC on this address (IP:PORT)
+    ///
+    /// Calls are authenticated with the control gateway secret key, sent as `authorization:
+    /// Bearer <secret>` metadata. The gRPC endpoint uses the control gateway's TLS settings, and
+    /// must listen on a loopback address without them.
+    #[structopt(long = "listen-ctl-grpc")]
+    pub listen_ctl_grpc: Option<SocketAddr>,
     /// The organization the Supervisor and its services are part of
     #[structopt(long = "org")]
     pub organization: Option<String>,
@@ -193,9 +350,91 @@ pub struct SupRun {
     /// Make this Supervisor a permanent peer
     #[structopt(long = "permanent-peer", short = "I")]
     pub permanent_peer: bool,
+    /// Only observe the ring, for dashboards and monitoring hosts
+    ///
+    /// An observer joins the ring, receives its rumors and serves the census over the HTTP
+    /// Gateway, but never runs services or takes part in elections, and refuses requests to
+    /// gossip service config, files, departures or fleet manifests.
+    #[structopt(long = "observer",
+                conflicts_with_all = &["PKG_IDENT_OR_ARTIFACT", "FLEET_MANIFEST_ORIGIN"])]
+    pub observer: bool,
+    /// The failure domain this Supervisor runs in (ex: us-east-1a)
+    ///
+    /// The zone is gossiped to the rest of the ring, where it is used to enforce the
+    /// `--max-per-zone` placement constraint of loaded services, and to keep most gossip and
+    /// failure detection traffic within a zone.
+    #[structopt(long = "zone")]
+    pub zone: Option<String>,
+    /// A label describing this Supervisor (ex: role=cache)
+    ///
+    /// Labels select the Supervisors that services in a fleet manifest are loaded on.
+    #[structopt(long = "label")]
+    pub label: Vec<SupervisorLabel>,
+    /// Reconcile the services loaded on this Supervisor to the ring's fleet manifest
+    ///
+    /// Only manifests signed by a key of this origin, whose public key must be in the key cache,
+    /// are acted on. See `hab ring manifest apply --help` for details.
+    #[structopt(long = "fleet-manifest-origin")]
+    pub fleet_manifest_origin: Option<Origin>,
     /// Watch this file for connecting to the ring
+    ///
+    /// Each line is the address of a peer (IP[:PORT], or IP:SWIM_PORT:GOSSIP_PORT if its SWIM and
+    /// gossip ports differ) or, prefixed with `srv:`, the name of a DNS SRV record whose targets
+    /// are the peers. A file named *.toml or *.json holds an array `peer` of tables with an
+    /// `address`, optional `swim_port`, `gossip_port` and `permanent` keys, or an `srv` record
+    /// instead. If this is a directory, the peers of every file in it, other than hidden files,
+    /// are merged.
     #[structopt(long = "peer-watch-file", conflicts_with = "PEER")]
     pub peer_watch_file: Option<PathBuf>,
+    /// Join the ring through the pods of this Kubernetes Service
+    ///
+    /// Given as [NAMESPACE/]NAME[:PORT_NAME], usually naming the headless Service of a
+    /// StatefulSet. The endpoints of the Service are read from the API server with the pod's
+    /// service account, which must be allowed to get endpoints. The gossip port is the port
+    /// named PORT_NAME, or the only port of the Service.
+    #[structopt(long = "peer-kubernetes-service",
+                conflicts_with_all = &["PEER", "PEER_WATCH_FILE"])]
+    pub peer_kubernetes_service: Option<KubernetesService>,
+    /// Join the ring through the EC2 instances with a tag, or in an Auto Scaling group
+    ///
+    /// Given as tag:KEY=VALUE or asg:NAME. Only running instances in the Supervisor's own region
+    /// are found, through the credentials of its instance profile, which must allow
+    /// `ec2:DescribeInstances`. The peers gossip on this Supervisor's LISTEN_GOSSIP port.
+    #[structopt(long = "peer-ec2",
+                conflicts_with_all = &["PEER", "PEER_WATCH_FILE", "PEER_KUBERNETES_SERVICE"])]
+    pub peer_ec2: Option<Ec2PeerFilter>,
+    /// Advertise this Supervisor and discover its peers on the local network over mDNS
+    ///
+    /// Supervisors started with this option find each other on the local network without a peer
+    /// file or --peer options. Only Supervisors using the same ring key, or none, join each other.
+    /// Multicast traffic on UDP port 5353 must be allowed between them.
+    #[structopt(long = "peer-mdns",
+                conflicts_with_all = &["PEER",
+                                       "PEER_WATCH_FILE",
+                                       "PEER_KUBERNETES_SERVICE",
+                                       "PEER_EC2",
+                                       "PEER_CONSUL_SERVICE"])]
+    pub peer_mdns: bool,
+    /// Join the ring through the healthy instances of this service in the Consul catalog
+    ///
+    /// The instances whose health checks are all passing are read from the Consul agent at
+    /// PEER_CONSUL_ADDR. The address of an instance is its service address, or its node's address
+    /// if it has none, and its gossip port is the port of the service.
+    #[structopt(long = "peer-consul-service",
+                conflicts_with_all = &["PEER",
+                                       "PEER_WATCH_FILE",
+                                       "PEER_KUBERNETES_SERVICE",
+                                       "PEER_EC2"])]
+    pub peer_consul_service: Option<String>,
+    /// The HTTP API address of the Consul agent to read PEER_CONSUL_SERVICE from
+    #[structopt(long = "peer-consul-addr", default_value = "http://127.0.0.1:8500")]
+    pub peer_consul_addr: Url,
+    /// The Consul datacenter to read PEER_CONSUL_SERVICE from, instead of the agent's own
+    #[structopt(long = "peer-consul-datacenter", requires = "PEER_CONSUL_SERVICE")]
+    pub peer_consul_datacenter: Option<String>,
+    /// The Consul ACL token to read PEER_CONSUL_SERVICE with
+    #[structopt(long = "peer-consul-token", env = "CONSUL_HTTP_TOKEN")]
+    pub peer_consul_token: Option<String>,
     #[structopt(flatten)]
     #[serde(flatten)]
     pub cache_key_path: CacheKeyPath,
@@ -214,6 +453,40 @@ pub struct SupRun {
                 env = RING_KEY_ENVVAR,
                 hidden = true)]
     pub ring_key: Option<String>,
+    /// The certificate chain of this Supervisor when running with mutual TLS on the wire
+    ///
+    /// Read PEM-format certificates from RING_TLS_CERT_FILE. The first certificate should certify
+    /// RING_TLS_KEY_FILE and be valid for the ring's server name, both as a server and as a
+    /// client. Every member of the ring must use TLS, and none can use a ring key.
+    #[structopt(long = "ring-tls-cert",
+                requires_all = &["RING_TLS_KEY_FILE", "RING_TLS_CA_CERT_FILE"],
+                conflicts_with_all = &["RING", "RING_KEY"])]
+    pub ring_tls_cert_file: Option<PathBuf>,
+    /// The private key of this Supervisor's certificate when running with mutual TLS on the wire
+    ///
+    /// Read the private key from RING_TLS_KEY_FILE. This should be a PKCS8-encoded private key
+    /// in PEM format.
+    #[structopt(long = "ring-tls-key", requires = "RING_TLS_CERT_FILE")]
+    pub ring_tls_key_file: Option<PathBuf>,
+    /// The certificate of the authority issuing the certificates of the ring's members
+    ///
+    /// Read the PEM-format CA certificate from RING_TLS_CA_CERT_FILE. Members whose certificates
+    /// it does not validate are refused.
+    #[structopt(long = "ring-tls-ca-cert", requires = "RING_TLS_CERT_FILE")]
+    pub ring_tls_ca_cert_file: Option<PathBuf>,
+    /// The name the certificates of the ring's members must be valid for
+    #[structopt(long = "ring-tls-server-name", default_value = "habitat-ring")]
+    pub ring_tls_server_name: String,
+    /// Run the Supervisor in a named ring, isolating its state directory and deriving its listen
+    /// ports from the ring name
+    ///
+    /// The state directory is `/hab/sup/<RING_NAME>`. Listen addresses which are not set
+    /// explicitly use ports offset from their defaults by an amount derived from the ring name.
+    /// Select the ring from other `hab` commands with `hab --ring <RING_NAME>`.
+    #[structopt(long = "ring-name",
+                env = RING_NAME_ENVVAR,
+                validator = valid_ring_name)]
+    pub ring_name: Option<String>,
     /// Enable automatic updates for the Supervisor itself
     #[structopt(long = "auto-update", short = "A")]
     pub auto_update: bool,
@@ -327,6 +600,97 @@ pub struct SupRun {
     /// automatic package cleanup is performed.
     #[structopt(long = "keep-latest-packages", env = "HAB_KEEP_LATEST_PACKAGES")]
     pub keep_latest_packages: Option<usize>,
+    /// The maximum combined rate, in bytes per second, at which the Supervisor downloads
+    /// artifacts
+    ///
+    /// Use this to keep Supervisor and service updates from saturating slow network links. If
+    /// this argument is not specified, downloads are not rate limited.
+    #[structopt(long = "download-rate-limit", env = "HAB_DOWNLOAD_RATE_LIMIT")]
+    pub download_rate_limit: Option<u64>,
+    /// Periodically write a JSON snapshot of the Supervisor's services and census to this file
+    ///
+    /// The file is replaced atomically, so external agents can read the Supervisor's state from
+    /// it at any time without querying the HTTP gateway, and the last snapshot remains after a
+    /// crash.
+    #[structopt(long = "state-snapshot-path")]
+    pub state_snapshot_path: Option<PathBuf>,
+    /// The period of time in seconds between state snapshots
+    #[structopt(long = "state-snapshot-interval", default_value = "60")]
+    pub state_snapshot_interval: DurationProxy,
+    /// Keep the artifact cache warm with the current releases of the packages of this file
+    ///
+    /// The file is either a package set file, as used by `hab pkg download --file`, or a file
+    /// of newline separated package identifiers, which are fetched from the stable channel.
+    /// Artifacts are downloaded without being installed, so later updates don't wait on
+    /// downloads.
+    #[structopt(long = "prefetch-file")]
+    pub prefetch_file: Option<PathBuf>,
+    /// The period of time in seconds between prefetches of the packages of the prefetch file
+    #[structopt(long = "prefetch-interval", default_value = "3600")]
+    pub prefetch_interval: DurationProxy,
+    /// Obtain and renew TLS certificates for services from this ACME directory (ex:
+    /// https://acme-v02.api.letsencrypt.org/directory)
+    ///
+    /// A service requests a certificate with an `acme` table in its configuration, holding the
+    /// `domains` of the certificate and the `webroot` that is served on port 80 for them. The
+    /// certificate is written to `acme/cert.pem` and its key to `acme/key.pem` in the service's
+    /// config directory, and the service's reload and reconfigure hooks run when it is renewed.
+    #[structopt(long = "acme-directory", requires = "ACME_EMAIL")]
+    pub acme_directory: Option<Url>,
+    /// The contact email of the ACME account
+    #[structopt(long = "acme-email", requires = "ACME_DIRECTORY")]
+    pub acme_email: Option<String>,
+    /// Run this program beside the Supervisor as its extension
+    ///
+    /// The Supervisor writes its service lifecycle, census and health check events to the
+    /// extension's standard input as lines of JSON, and reads commands from its standard output
+    /// that annotate the status of services or veto the start of their updates. The extension is
+    /// started again if it exits.
+    #[structopt(long = "extension")]
+    pub extension: Option<PathBuf>,
+    /// Derive the member ID from a stable identity of the host instead of generating a random one
+    ///
+    /// One of `machine-id` for the host's machine ID, `instance-id` for the ID of its EC2
+    /// instance, or `value:IDENTITY` for an identity of your choosing. A host that is reimaged
+    /// then rejoins the ring as the same member, rather than as a new one that leaves the old one
+    /// behind as departed.
+    #[structopt(long = "member-id-from")]
+    pub member_id_source: Option<MemberIdSource>,
+    /// Refuse all gossip from a member, by member ID or by an IP address or CIDR range its address
+    /// is in (ex: 10.0.4.0/24)
+    ///
+    /// Denied members are departed and kept out of the ring until they are allowed again with
+    /// `hab sup deny --allow`.
+    #[structopt(long = "deny")]
+    pub deny: Vec<DenylistEntry>,
+    /// The length in milliseconds of a SWIM protocol period, in which one member is probed
+    ///
+    /// Must be at least three times `--swim-ping-timeout`, and at most 60000.
+    #[structopt(long = "swim-protocol-period", default_value = "3100")]
+    pub swim_protocol_period: u64,
+    /// How long in milliseconds to wait for a member to answer a ping before asking other members
+    /// to ping it (50 to 10000)
+    ///
+    /// Lower this on small, fast LANs to notice failed members sooner, and raise it for
+    /// high-latency rings so members are not suspected for answering slowly.
+    #[structopt(long = "swim-ping-timeout", default_value = "1000")]
+    pub swim_ping_timeout: u64,
+    /// How many members to ask to ping a member that did not answer a ping (1 to 16)
+    #[structopt(long = "swim-ping-req-fanout", default_value = "5")]
+    pub swim_ping_req_fanout: usize,
+    /// How many protocol periods a member stays suspect before it is confirmed dead (1 to 100)
+    #[structopt(long = "swim-suspicion-timeout", default_value = "3")]
+    pub swim_suspicion_timeout: u64,
+    /// How long in seconds a member must be confirmed dead before the rumors of its services are
+    /// removed
+    ///
+    /// The service file rumors of service groups no member has run for as long are removed too,
+    /// so the census and gossip stop carrying services that left the ring. The configuration
+    /// applied to a service group is kept. A member that comes back gossips its services again.
+    /// Every member of the ring should use the same value. If this argument is not specified,
+    /// the services of confirmed members are kept until they depart.
+    #[structopt(long = "rumor-ttl")]
+    pub rumor_ttl: Option<DurationProxy>,
     /// Paths to files or directories of service config files to load on startup
     ///
     /// See `hab svc bulkload --help` for details
@@ -339,6 +703,62 @@ pub struct SupRun {
     pub shared_load: SharedLoad,
 }
 
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(no_version)]
+/// Commands relating to the configuration of a Habitat Supervisor
+pub enum SupConfig {
+    Show(SupConfigShow),
+    /// Reload a running Supervisor's configuration, applying the settings that can be changed
+    /// without a restart
+    #[structopt(no_version)]
+    Reload {
+        #[structopt(flatten)]
+        remote_sup: RemoteSup,
+    },
+}
+
+/// Display the configuration `hab sup run` would start the Supervisor with
+///
+/// Without `--effective` only the contents of the Supervisor config files are displayed.
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "show", no_version, rename_all = "screamingsnake")]
+pub struct SupConfigShow {
+    /// Display the fully merged configuration and the source of each setting
+    ///
+    /// Settings are resolved in order of precedence: command line, environment variables,
+    /// config files and finally built-in defaults.
+    #[structopt(long = "effective")]
+    pub effective:    bool,
+    /// Only display settings whose effective value differs from the built-in default
+    #[structopt(long = "diff")]
+    pub diff:         bool,
+    /// The `hab sup run` arguments to evaluate (ex: -- --listen-gossip 0.0.0.0:9000)
+    #[structopt(name = "SUP_RUN_ARGS", last = true)]
+    pub sup_run_args: Vec<String>,
+}
+
+/// Run a ring of Supervisors on this machine for local development
+///
+/// Each member runs in the named ring `<NAME>-<N>`, which gives it a state directory and listen
+/// ports of its own, and peers with the first member. Select a member from other `hab` commands
+/// with `hab --ring <NAME>-<N>`. The members are terminated when this command is interrupted.
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "cluster", no_version, rename_all = "screamingsnake")]
+pub struct SupCluster {
+    /// The number of Supervisors to run
+    #[structopt(long = "members", short = "n", default_value = "3")]
+    pub members:      u16,
+    /// The prefix of the ring names of the members
+    #[structopt(long = "name", default_value = "cluster", validator = valid_ring_name)]
+    pub name:         String,
+    /// Delete the state directories of the members once they are terminated
+    #[structopt(long = "purge")]
+    pub purge:        bool,
+    /// Additional `hab sup run` arguments for every member (ex: -- core/redis --topology leader)
+    #[structopt(name = "SUP_RUN_ARGS", last = true)]
+    pub sup_run_args: Vec<String>,
+}
+
 #[derive(ConfigOpt, StructOpt)]
 #[structopt(no_version)]
 /// Commands relating to a Habitat Supervisor's Control Gateway secret
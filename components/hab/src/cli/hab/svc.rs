@@ -38,9 +38,11 @@ pub const DEFAULT_SVC_CONFIG_DIR: &str = "/hab/sup/default/config/svc";
 pub enum Svc {
     #[structopt(name = "bulkload")]
     BulkLoad(BulkLoad),
+    History(SvcHistory),
     Key(Key),
     #[structopt(no_version)]
     Load(Load),
+    Maintenance(SvcMaintenance),
     #[structopt(no_version)]
     Update(Update),
     Start(SvcStart),
@@ -66,6 +68,11 @@ pub struct BulkLoad {
     #[structopt(long = "svc-config-paths",
                 default_value = "/hab/sup/default/config/svc")]
     pub svc_config_paths: Vec<PathBuf>,
+    /// Load the services as one transaction, so that either all of them are loaded or none are
+    ///
+    /// All the service config files must name the same Supervisor.
+    #[structopt(long = "atomic")]
+    pub atomic:           bool,
 }
 
 /// Start a loaded, but stopped, Habitat service.
@@ -76,6 +83,42 @@ pub struct SvcStart {
     pkg_ident:  PkgIdent,
     #[structopt(flatten)]
     remote_sup: RemoteSup,
+    /// Print the output of the service's hooks until it is running, and fail if it is not
+    /// within `HAB_CTL_HOOK_OUTPUT_TIMEOUT_SECS` on the Supervisor [default: 60]
+    #[structopt(name = "FOLLOW", long = "follow")]
+    follow:     bool,
+}
+
+/// Show the package updates of a loaded Habitat service, oldest first
+///
+/// The Supervisor keeps the latest 100 updates of each service, with the update strategy they
+/// were made with, how long the service took to start again, and whether they went to an older
+/// release.
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "history", no_version, rename_all = "screamingsnake")]
+pub struct SvcHistory {
+    /// The service group of the service (ex: redis.default)
+    #[structopt(name = "SERVICE_GROUP")]
+    pub service_group: ServiceGroup,
+    #[structopt(flatten)]
+    pub remote_sup:    RemoteSup,
+}
+
+/// Put a loaded Habitat service in or out of maintenance
+///
+/// A service in maintenance keeps running its health checks, but this member is left out of
+/// `eachAlive` and of the members its consumers bind to, everywhere in the ring.
+#[derive(ConfigOpt, StructOpt)]
+#[structopt(name = "maintenance", no_version, rename_all = "screamingsnake")]
+pub struct SvcMaintenance {
+    /// Whether to put the service in maintenance or take it out of maintenance
+    #[structopt(name = "MODE", possible_values = &["on", "off"])]
+    pub mode:          String,
+    /// The service group of the service (ex: redis.default)
+    #[structopt(name = "SERVICE_GROUP")]
+    pub service_group: ServiceGroup,
+    #[structopt(flatten)]
+    pub remote_sup:    RemoteSup,
 }
 
 /// Query the status of Habitat services
@@ -85,6 +128,12 @@ pub struct SvcStatus {
     /// A package identifier (ex: core/redis, core/busybox-static/1.42.2)
     #[structopt(name = "PKG_IDENT")]
     pub pkg_ident:  Option<PackageIdent>,
+    /// Also show the result and output of each service's most recent health check
+    #[structopt(long = "detail")]
+    pub detail:     bool,
+    /// Also show the CPU, memory and file descriptors used by each service's processes
+    #[structopt(long = "resources")]
+    pub resources:  bool,
     #[structopt(flatten)]
     pub remote_sup: RemoteSup,
 }
@@ -103,6 +152,10 @@ pub struct SvcStop {
     /// The default value is set in the packages plan file.
     #[structopt(name = "SHUTDOWN_TIMEOUT", long = "shutdown-timeout")]
     shutdown_timeout: Option<ShutdownTimeout>,
+    /// Print the output of the service's hooks until it has stopped, and fail if it has not
+    /// within `HAB_CTL_HOOK_OUTPUT_TIMEOUT_SECS` on the Supervisor [default: 60]
+    #[structopt(name = "FOLLOW", long = "follow")]
+    follow:           bool,
 }
 
 #[derive(ConfigOpt, StructOpt)]
@@ -196,6 +249,43 @@ pub struct SharedLoad {
                 possible_values = &["strict", "relaxed"])]
     #[serde(default)]
     pub binding_mode:          habitat_sup_protocol::types::BindingMode,
+    /// One or more service groups this service must not run on the same Supervisor as (ex:
+    /// redis.default)
+    ///
+    /// The service waits to start for as long as a member of any of the groups is loaded on the
+    /// same Supervisor.
+    #[structopt(long = "anti-affinity")]
+    #[serde(default)]
+    pub anti_affinity:         Vec<ServiceGroup>,
+    /// The most members of the service group that may run in a single zone
+    ///
+    /// Zones are set with `hab sup run --zone`; Supervisors without one count as a single zone.
+    /// Members beyond the limit wait to start until a member ahead of them in the zone leaves
+    /// the ring.
+    #[structopt(long = "max-per-zone")]
+    pub max_per_zone:          Option<u32>,
+    /// Fail to render the service's templates when they refer to a dependency with `pkgPathFor`
+    /// or `pkgAtLeast` that the plan doesn't declare in `pkg_deps`
+    #[structopt(long = "strict-deps")]
+    #[serde(default)]
+    pub strict_deps:           bool,
+    /// One or more `KEY=VALUE` Supervisor labels, `zone=ZONE`, or `latency=lowest`, that make a
+    /// member preferred as the leader, most preferred first (ex: disk=ssd)
+    ///
+    /// Leader elections rank members by the preferences they match ahead of their suitability.
+    /// `latency=lowest` prefers the members with the lowest round trip time to the rest of the
+    /// ring.
+    #[structopt(name = "LEADER_AFFINITY", long = "leader-affinity")]
+    #[serde(default)]
+    pub leader_affinity:       Vec<String>,
+    /// Clear the leader affinity of an already loaded service
+    #[structopt(long = "no-leader-affinity", conflicts_with = "LEADER_AFFINITY")]
+    #[serde(default)]
+    pub no_leader_affinity:    bool,
+    /// Only use the leader affinity to break ties between equally suitable members
+    #[structopt(long = "leader-tie-break")]
+    #[serde(default)]
+    pub leader_tie_break:      bool,
     /// The interval in seconds on which to run health checks
     // We would prefer to use `HealthCheckInterval`. However, `HealthCheckInterval` uses a map based
     // serialization format. We want to allow the user to simply specify a `u64` to be consistent
@@ -257,6 +347,17 @@ pub struct Load {
     #[structopt(short = "f", long = "force")]
     #[serde(default)]
     pub force:       bool,
+    /// Reload an already loaded service only if its spec is still at this revision
+    ///
+    /// The revision is shown by `hab svc status --detail`. The reload is refused if another
+    /// change was made to the spec since.
+    #[structopt(long = "revision")]
+    pub revision:    Option<u64>,
+    /// Print the output of the service's hooks until it is running, and fail if it is not
+    /// within `HAB_CTL_HOOK_OUTPUT_TIMEOUT_SECS` on the Supervisor [default: 60]
+    #[structopt(long = "follow")]
+    #[serde(default)]
+    pub follow:      bool,
     #[structopt(flatten)]
     #[serde(flatten)]
     pub remote_sup:  RemoteSup,
@@ -271,15 +372,41 @@ pub struct Load {
 /// additionally be stopped.
 pub struct SvcUnload {
     #[structopt(flatten)]
-    pkg_ident:        PkgIdent,
+    pkg_ident:            PkgIdent,
     #[structopt(flatten)]
-    remote_sup:       RemoteSup,
+    remote_sup:           RemoteSup,
     /// The delay in seconds after sending the shutdown signal to wait before killing the
     /// service process
     ///
     /// The default value is set in the packages plan file.
     #[structopt(name = "SHUTDOWN_TIMEOUT", long = "shutdown-timeout")]
-    shutdown_timeout: Option<ShutdownTimeout>,
+    shutdown_timeout:     Option<ShutdownTimeout>,
+    /// Delete the config, data, and var directories of the service once it is unloaded
+    #[structopt(name = "PURGE", long = "purge", conflicts_with = "RETAIN_DATA")]
+    purge:                bool,
+    /// Archive the config, data, and var directories of the service once it is unloaded, and
+    /// delete the archive after this many days
+    #[structopt(name = "RETAIN_DATA", long = "retain-data")]
+    retain_data:          Option<u32>,
+    /// Unload the service from every member of its service group at once
+    ///
+    /// Every member runs the drain hook of the service first. The service is only unloaded once
+    /// every alive member has drained, and stays loaded everywhere if one has not by the
+    /// coordination timeout.
+    #[structopt(name = "GROUP_COORDINATED",
+                long = "group-coordinated",
+                conflicts_with_all = &["SHUTDOWN_TIMEOUT", "PURGE", "RETAIN_DATA"])]
+    group_coordinated:    bool,
+    /// How long in seconds to wait for every member of the service group to drain before
+    /// abandoning a coordinated unload [default: 300]
+    #[structopt(name = "COORDINATION_TIMEOUT",
+                long = "coordination-timeout",
+                requires = "GROUP_COORDINATED")]
+    coordination_timeout: Option<u32>,
+    /// Print the output of the service's hooks until it has been unloaded, and fail if it has
+    /// not within `HAB_CTL_HOOK_OUTPUT_TIMEOUT_SECS` on the Supervisor [default: 60]
+    #[structopt(name = "FOLLOW", long = "follow", conflicts_with = "GROUP_COORDINATED")]
+    follow:               bool,
 }
 
 pub fn svc_loads_from_paths<T: AsRef<Path>>(paths: &[T]) -> Result<Vec<Load>> {
@@ -325,7 +452,8 @@ pub fn shared_load_cli_to_ctl(ident: PackageIdent,
                          ui::UIWriter};
     #[cfg(target_os = "windows")]
     use habitat_core::crypto::dpapi;
-    use habitat_sup_protocol::{ctl::{ServiceBindList,
+    use habitat_sup_protocol::{ctl::{LeaderAffinityList,
+                                     ServiceBindList,
                                      SvcLoad},
                                types::{HealthCheckInterval,
                                        ServiceBind}};
@@ -346,6 +474,13 @@ pub fn shared_load_cli_to_ctl(ident: PackageIdent,
                                                  .collect(), })
     };
 
+    let leader_affinity =
+        if shared_load.no_leader_affinity || !shared_load.leader_affinity.is_empty() {
+            Some(LeaderAffinityList { preferences: shared_load.leader_affinity, })
+        } else {
+            None
+        };
+
     let config_from = if let Some(config_from) = shared_load.config_from {
         warn!("");
         warn!("WARNING: Setting '--config-from' should only be used in development, not \
@@ -379,16 +514,29 @@ pub fn shared_load_cli_to_ctl(ident: PackageIdent,
                  health_check_interval:
                      Some(HealthCheckInterval { seconds: shared_load.health_check_interval, }),
                  shutdown_timeout: shared_load.shutdown_timeout.map(u32::from),
-                 update_condition: Some(shared_load.update_condition as i32) })
+                 update_condition: Some(shared_load.update_condition as i32),
+                 anti_affinity: shared_load.anti_affinity
+                                           .iter()
+                                           .map(ToString::to_string)
+                                           .collect(),
+                 max_per_zone: shared_load.max_per_zone,
+                 strict_deps: Some(shared_load.strict_deps),
+                 leader_affinity,
+                 leader_tie_break: Some(shared_load.leader_tie_break),
+                 revision: None,
+                 follow: None })
 }
 
 impl TryFrom<Load> for habitat_sup_protocol::ctl::SvcLoad {
     type Error = crate::error::Error;
 
     fn try_from(svc_load: Load) -> Result<Self> {
-        shared_load_cli_to_ctl(svc_load.pkg_ident.pkg_ident(),
-                               svc_load.shared_load,
-                               svc_load.force)
+        let mut msg = shared_load_cli_to_ctl(svc_load.pkg_ident.pkg_ident(),
+                                             svc_load.shared_load,
+                                             svc_load.force)?;
+        msg.revision = svc_load.revision;
+        msg.follow = Some(svc_load.follow);
+        Ok(msg)
     }
 }
 
@@ -480,6 +628,18 @@ pub struct Update {
     #[cfg(target_os = "windows")]
     #[structopt(long = "password")]
     pub password: Option<String>,
+
+    /// The revision the service's spec must be at for the update to be made
+    ///
+    /// The revision is shown by `hab svc status --detail`. The update is refused if another
+    /// change was made to the spec since. Either this or `--force` must be given.
+    #[structopt(long = "revision")]
+    pub revision: Option<u64>,
+
+    /// Update the service's spec whatever its revision
+    #[structopt(short = "f", long = "force", conflicts_with = "REVISION")]
+    #[serde(default)]
+    pub force: bool,
 }
 
 impl TryFrom<Update> for ctl::SvcUpdate {
@@ -502,7 +662,9 @@ impl TryFrom<Update> for ctl::SvcUpdate {
                                    #[cfg(windows)]
                                    svc_encrypted_password: u.password,
                                    #[cfg(not(windows))]
-                                   svc_encrypted_password: None, };
+                                   svc_encrypted_password: None,
+                                   revision: u.revision,
+                                   force: Some(u.force), };
 
         // Compiler-assisted validation that the user has indeed
         // specified *something* to change. If they didn't, all the
@@ -518,7 +680,9 @@ impl TryFrom<Update> for ctl::SvcUpdate {
                                 update_strategy: None,
                                 health_check_interval: None,
                                 shutdown_timeout: None,
-                                update_condition: None, } = &msg
+                                update_condition: None,
+                                revision: _,
+                                force: _, } = &msg
         {
             Err(Error::ArgumentError("No fields specified for update".to_string()))
         } else {
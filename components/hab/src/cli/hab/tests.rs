@@ -1,4 +1,5 @@
-use super::{svc::{Load,
+use super::{sup::sup_default_config_file,
+            svc::{Load,
                   Svc,
                   Update},
             Hab};
@@ -465,3 +466,34 @@ fn test_hab_svc_update_empty_binds() {
     let update = extract_hab_svc_update(hab);
     assert_eq!(update.bind.unwrap().len(), 1);
 }
+
+#[test]
+fn test_hab_svc_update_revision() {
+    let hab = Hab::try_from_iter_with_configopt(&["hab",
+                                                  "svc",
+                                                  "update",
+                                                  "core/redis",
+                                                  "--revision",
+                                                  "3"]).unwrap();
+    let update = extract_hab_svc_update(hab);
+    assert_eq!(update.revision, Some(3));
+    assert!(!update.force);
+
+    let hab = Hab::try_from_iter_with_configopt(&["hab", "svc", "update", "core/redis", "--force"]).unwrap();
+    let update = extract_hab_svc_update(hab);
+    assert_eq!(update.revision, None);
+    assert!(update.force);
+
+    assert!(Hab::try_from_iter_with_configopt(&["hab",
+                                                "svc",
+                                                "update",
+                                                "core/redis",
+                                                "--force",
+                                                "--revision",
+                                                "3"]).is_err());
+}
+
+#[test]
+fn test_hab_sup_run_default_config_file_is_the_rings() {
+    assert!(sup_default_config_file(Some("blue")).ends_with("hab/sup/blue/config/sup.toml"));
+}
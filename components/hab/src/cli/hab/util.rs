@@ -2,6 +2,9 @@ pub mod tls;
 
 use crate::{cli::valid_fully_qualified_ident,
             error::Error};
+use chrono::{DateTime,
+             NaiveDateTime,
+             Utc};
 use configopt::{self,
                 ConfigOpt};
 use habitat_common::{cli_config::CliConfig,
@@ -22,7 +25,8 @@ use std::{ffi::OsString,
           path::PathBuf,
           result,
           str::FromStr,
-          time::Duration};
+          time::{Duration,
+                 SystemTime}};
 use structopt::{clap::AppSettings,
                 StructOpt};
 use url::{ParseError,
@@ -183,6 +187,10 @@ pub struct FullyQualifiedPkgIdent {
     pkg_ident: PackageIdent,
 }
 
+impl FullyQualifiedPkgIdent {
+    pub fn pkg_ident(self) -> PackageIdent { self.pkg_ident }
+}
+
 #[derive(Clone, ConfigOpt, StructOpt, Deserialize, Debug)]
 #[configopt(derive(Serialize, Clone, Debug))]
 #[structopt(no_version)]
@@ -229,6 +237,86 @@ impl fmt::Display for DurationProxy {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", u64::from(*self)) }
 }
 
+/// A duration given as a number of seconds, or of minutes, hours or days with an `m`, `h` or `d`
+/// suffix (ex: 90, 30m, 2h)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "&str", into = "String")]
+pub struct HumanDuration(Duration);
+
+impl FromStr for HumanDuration {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (amount, seconds_per_unit) = match s.char_indices().last() {
+            Some((i, 's')) => (&s[..i], 1),
+            Some((i, 'm')) => (&s[..i], 60),
+            Some((i, 'h')) => (&s[..i], 60 * 60),
+            Some((i, 'd')) => (&s[..i], 24 * 60 * 60),
+            _ => (s, 1),
+        };
+        amount.parse::<u64>()
+              .ok()
+              .and_then(|amount| amount.checked_mul(seconds_per_unit))
+              .map(|seconds| HumanDuration(Duration::from_secs(seconds)))
+              .ok_or_else(|| {
+                  Error::ArgumentError(format!("Invalid duration '{}', expected a number of \
+                                                seconds or a number followed by s, m, h or d",
+                                               s))
+              })
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}s", self.0.as_secs()) }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(d: HumanDuration) -> Self { d.0 }
+}
+
+habitat_core::impl_try_from_str_and_into_string!(HumanDuration);
+
+/// A time given as an RFC 3339 timestamp, whose seconds can be left out when it is in UTC (ex:
+/// 2024-06-01T02:00Z)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "&str", into = "String")]
+pub struct Timestamp(DateTime<Utc>);
+
+impl FromStr for Timestamp {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Timestamp(time.with_timezone(&Utc)));
+        }
+        s.strip_suffix('Z')
+         .and_then(|time| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M").ok())
+         .map(|time| Timestamp(DateTime::from_utc(time, Utc)))
+         .ok_or_else(|| {
+             Error::ArgumentError(format!("Invalid timestamp '{}', expected a time like \
+                                           2024-06-01T02:00Z",
+                                          s))
+         })
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.to_rfc3339())
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(t: Timestamp) -> Self { t.0.into() }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(t: SystemTime) -> Self { Timestamp(t.into()) }
+}
+
+habitat_core::impl_try_from_str_and_into_string!(Timestamp);
+
 // Collect trailing arguments to pass to an external command
 //
 // This disables help and version flags for the subcommand. Making it easy to check the help or
@@ -296,3 +384,29 @@ impl SubjectAlternativeName {
 }
 
 habitat_core::impl_try_from_str_and_into_string!(SubjectAlternativeName);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn human_durations_are_parsed_with_or_without_a_unit() {
+        assert_eq!(Duration::from("90".parse::<HumanDuration>().unwrap()),
+                   Duration::from_secs(90));
+        assert_eq!(Duration::from("30m".parse::<HumanDuration>().unwrap()),
+                   Duration::from_secs(30 * 60));
+        assert_eq!(Duration::from("2h".parse::<HumanDuration>().unwrap()),
+                   Duration::from_secs(2 * 60 * 60));
+        assert!("2w".parse::<HumanDuration>().is_err());
+        assert!("h".parse::<HumanDuration>().is_err());
+    }
+
+    #[test]
+    fn timestamps_can_leave_out_seconds_in_utc() {
+        let short = "2024-06-01T02:00Z".parse::<Timestamp>().unwrap();
+        let full = "2024-06-01T04:00:00+02:00".parse::<Timestamp>().unwrap();
+        assert_eq!(short, full);
+        assert_eq!(short.to_string(), "2024-06-01T02:00:00+00:00");
+        assert!("2024-06-01".parse::<Timestamp>().is_err());
+    }
+}
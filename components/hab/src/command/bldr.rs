@@ -1,2 +1,3 @@
+pub mod artifact;
 pub mod channel;
 pub mod job;
@@ -0,0 +1 @@
+pub mod promote_on_green;
@@ -0,0 +1,234 @@
+//! Load a release into a staging service group, wait for it to pass its health checks on every
+//! Supervisor of the group, and promote it to a channel.
+//!
+//! # Examples
+//!
+//! ```bash
+//! $ hab bldr artifact promote-on-green acme/redis/2.0.7/2112010203120101 stable \
+//!     --group staging --channel unstable -r 10.0.0.5:9632 -r 10.0.0.6:9632
+//! ```
+//!
+//! This loads the release from the unstable channel into `redis.staging` on both Supervisors,
+//! and promotes it to the stable channel once it has been running and healthy on both of them
+//! for three health checks in a row.
+
+use crate::{cli::{gateway_util,
+                  hab::{bldr::ArtifactPromoteOnGreen,
+                        svc}},
+            command,
+            common::ui::{Glyph,
+                         Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result},
+            hcore::package::{PackageIdent,
+                             PackageTarget}};
+use futures::stream::StreamExt;
+use habitat_common::types::ResolvedListenCtlAddr;
+use habitat_sup_client::{SrvClient,
+                         SrvClientError};
+use habitat_sup_protocol::{self as sup_proto,
+                           types::{HealthCheckResult,
+                                   ProcessState,
+                                   ServiceStatus}};
+use std::{io,
+          time::{Duration,
+                 Instant}};
+use tokio::time;
+
+/// What a Supervisor reports about the release in the staging group
+#[derive(Debug, PartialEq)]
+enum Report {
+    Healthy,
+    /// Not healthy, and why
+    Waiting(String),
+}
+
+pub async fn start(ui: &mut UI,
+                   bldr_url: &str,
+                   token: &str,
+                   promote: ArtifactPromoteOnGreen)
+                   -> Result<()> {
+    let ArtifactPromoteOnGreen { pkg_ident,
+                                 channel,
+                                 pkg_target,
+                                 remote_sups,
+                                 checks,
+                                 timeout,
+                                 shared_load,
+                                 .. } = promote;
+    let ident = pkg_ident.pkg_ident();
+    let target = pkg_target.unwrap_or_else(PackageTarget::active_target);
+    let group = shared_load.group.clone();
+    let interval = Duration::from_secs(shared_load.health_check_interval);
+    let mut load = svc::shared_load_cli_to_ctl(ident.clone(), shared_load, true)?;
+    load.bldr_url = Some(bldr_url.to_string());
+
+    ui.begin(format!("Loading {} into {}.{} on {} Supervisors",
+                     ident,
+                     ident.name,
+                     group,
+                     remote_sups.len()))?;
+    for remote_sup in &remote_sups {
+        gateway_util::send(Some(remote_sup), load.clone()).await?;
+        ui.status(Status::Custom(Glyph::CheckMark, String::from("Loaded")),
+                  remote_sup)?;
+    }
+
+    ui.begin(format!("Waiting for {} healthy checks in a row on every Supervisor",
+                     checks))?;
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    let mut healthy_checks = 0;
+    while healthy_checks < checks {
+        if Instant::now() >= deadline {
+            return Err(Error::ReleaseNotHealthy(ident.to_string(), group, timeout));
+        }
+        time::sleep(interval).await;
+        let mut waiting = Vec::new();
+        for remote_sup in &remote_sups {
+            let outcome = match service_statuses(remote_sup).await {
+                Ok(statuses) => report(&statuses, &ident, &group),
+                Err(e) => Report::Waiting(e.to_string()),
+            };
+            if let Report::Waiting(reason) = outcome {
+                waiting.push(format!("{}: {}", remote_sup, reason));
+            }
+        }
+        if waiting.is_empty() {
+            healthy_checks += 1;
+            ui.status(Status::Custom(Glyph::CheckMark, String::from("Healthy")),
+                      format!("{} of {} checks", healthy_checks, checks))?;
+        } else {
+            healthy_checks = 0;
+            for reason in waiting {
+                ui.status(Status::Custom(Glyph::Elipses, String::from("Waiting")),
+                          reason)?;
+            }
+        }
+    }
+    ui.end(format!("{} is healthy in {}.{}", ident, ident.name, group))?;
+
+    command::pkg::promote::start(ui, bldr_url, (&ident, target), &channel, token).await
+}
+
+/// The statuses of the services loaded on `remote_sup`
+async fn service_statuses(remote_sup: &ResolvedListenCtlAddr) -> Result<Vec<ServiceStatus>> {
+    let msg = sup_proto::ctl::SvcStatus { ident:     None,
+                                          detail:    Some(false),
+                                          resources: Some(false), };
+    let mut statuses = Vec::new();
+    let mut response = SrvClient::request(Some(remote_sup), msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "ServiceStatus" => {
+                statuses.push(reply.parse::<ServiceStatus>()
+                                   .map_err(SrvClientError::Decode)?);
+            }
+            "NetErr" => {
+                let err = reply.parse::<sup_proto::net::NetErr>()
+                               .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(err).into());
+            }
+            _ => (),
+        }
+    }
+    if statuses.is_empty() {
+        return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into());
+    }
+    Ok(statuses)
+}
+
+/// Whether `statuses` show `ident` running and healthy in `group`
+fn report(statuses: &[ServiceStatus], ident: &PackageIdent, group: &str) -> Report {
+    let status = statuses.iter().find(|status| {
+                                    status.service_group.service == ident.name
+                                    && status.service_group.group == group
+                                });
+    let status = match status {
+        Some(status) => status,
+        None => return Report::Waiting(format!("{}.{} is not loaded", ident.name, group)),
+    };
+    let running: PackageIdent = status.ident.clone().into();
+    if running != *ident {
+        return Report::Waiting(format!("{} is running", running));
+    }
+    if status.process.as_ref().map(|process| process.state) != Some(ProcessState::Up as i32) {
+        return Report::Waiting(String::from("the service is down"));
+    }
+    let result = status.health_check
+                       .as_ref()
+                       .and_then(|health_check| HealthCheckResult::from_i32(health_check.result))
+                       .unwrap_or(HealthCheckResult::Unknown);
+    if result == HealthCheckResult::Ok {
+        Report::Healthy
+    } else {
+        Report::Waiting(format!("health check {}", result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sup_proto::types::{HealthCheckStatus,
+                           ProcessStatus,
+                           ServiceGroup};
+
+    fn status(ident: &str,
+              group: &str,
+              state: ProcessState,
+              result: HealthCheckResult)
+              -> ServiceStatus {
+        let ident: PackageIdent = ident.parse().unwrap();
+        ServiceStatus { ident: ident.clone().into(),
+                        process: Some(ProcessStatus { state: state as i32,
+                                                      ..Default::default() }),
+                        service_group: ServiceGroup { service: ident.name,
+                                                      group: group.to_string(),
+                                                      ..Default::default() },
+                        health_check: Some(HealthCheckStatus { result: result as i32,
+                                                               ..Default::default() }),
+                        ..Default::default() }
+    }
+
+    #[test]
+    fn the_release_is_healthy_when_it_runs_and_passes_its_health_check() {
+        let ident = "acme/redis/2.0.7/20211201020312".parse().unwrap();
+        let statuses = vec![status("acme/redis/2.0.7/20211201020312",
+                                   "default",
+                                   ProcessState::Up,
+                                   HealthCheckResult::Critical),
+                            status("acme/redis/2.0.7/20211201020312",
+                                   "staging",
+                                   ProcessState::Up,
+                                   HealthCheckResult::Ok),];
+        assert_eq!(report(&statuses, &ident, "staging"), Report::Healthy);
+    }
+
+    #[test]
+    fn the_release_is_not_healthy_until_it_runs_in_the_group() {
+        let ident: PackageIdent = "acme/redis/2.0.7/20211201020312".parse().unwrap();
+        assert!(matches!(report(&[], &ident, "staging"), Report::Waiting(_)));
+
+        let older = vec![status("acme/redis/2.0.6/20211101020312",
+                                "staging",
+                                ProcessState::Up,
+                                HealthCheckResult::Ok)];
+        assert_eq!(report(&older, &ident, "staging"),
+                   Report::Waiting(String::from("acme/redis/2.0.6/20211101020312 is running")));
+
+        let down = vec![status("acme/redis/2.0.7/20211201020312",
+                               "staging",
+                               ProcessState::Down,
+                               HealthCheckResult::Ok)];
+        assert_eq!(report(&down, &ident, "staging"),
+                   Report::Waiting(String::from("the service is down")));
+
+        let warning = vec![status("acme/redis/2.0.7/20211201020312",
+                                  "staging",
+                                  ProcessState::Up,
+                                  HealthCheckResult::Warning)];
+        assert!(matches!(report(&warning, &ident, "staging"), Report::Waiting(_)));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{cli::hab::sup::SupRun,
-            command::sup::{SUP_CMD,
+            command::sup::{ring_env,
+                           SUP_CMD,
                            SUP_CMD_ENVVAR,
                            SUP_PKG_IDENT},
             common::ui::UI,
@@ -43,7 +44,7 @@ pub async fn start(ui: &mut UI, sup_run: SupRun, args: &[OsString]) -> Result<()
         }
     };
     if let Some(cmd) = find_command(&command) {
-        process::become_command(cmd, args)?;
+        process::become_command_with_env(cmd, args, &ring_env())?;
         Ok(())
     } else {
         Err(Error::ExecCommandNotFound(command))
@@ -12,8 +12,10 @@ pub mod export;
 pub mod hash;
 pub mod header;
 pub mod info;
+pub mod install;
 pub mod list;
 pub mod path;
+pub mod prefetch;
 pub mod promote;
 pub mod provides;
 pub mod search;
@@ -21,6 +23,7 @@ pub mod sign;
 pub mod uninstall;
 pub mod upload;
 pub mod verify;
+pub mod x;
 
 /// Used in commands like uninstall which provide a --dry-run option
 #[derive(Clone, Copy)]
@@ -1,16 +1,22 @@
+mod registry;
+
+use self::registry::Registry;
 use crate::{common::ui::{Status,
                          UIWriter,
                          UI},
             error::{Error,
                     Result},
             hcore::{fs as hfs,
-                    package::{PackageIdent,
+                    package::{Identifiable,
+                              PackageIdent,
                               PackageInstall}}};
 use std::{collections::BTreeMap,
           env,
+          fmt,
           fs,
           path::{Path,
-                 PathBuf}};
+                 PathBuf},
+          str::FromStr};
 #[cfg(windows)]
 use std::{fs::File,
           io::{BufRead,
@@ -19,6 +25,60 @@ use std::{fs::File,
 #[cfg(windows)]
 const COMMENT_MARKER: &str = "REM";
 
+/// What to do when a binary is already binlinked from another package. A binary pinned to a
+/// package is always binlinked from that package, unless the policy is `Force`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep the existing binlink
+    Skip,
+    /// Replace the existing binlink
+    Force,
+    /// Keep the binlink from the package with the newest release
+    Newest,
+    /// Fail
+    Error,
+}
+
+impl Default for ConflictPolicy {
+    fn default() -> Self { ConflictPolicy::Skip }
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "force" => Ok(ConflictPolicy::Force),
+            "newest" => Ok(ConflictPolicy::Newest),
+            "error" => Ok(ConflictPolicy::Error),
+            _ => {
+                Err(Error::ArgumentError(format!("Invalid binlink conflict \
+                                                  policy: {}",
+                                                 value)))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            ConflictPolicy::Skip => "skip",
+            ConflictPolicy::Force => "force",
+            ConflictPolicy::Newest => "newest",
+            ConflictPolicy::Error => "error",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+enum Resolution {
+    Replace,
+    /// Keep the existing binlink, for the given reason
+    Keep(String),
+}
+
 struct Binlink {
     link:   PathBuf,
     target: PathBuf,
@@ -121,7 +181,7 @@ pub fn start(ui: &mut UI,
              binary: &str,
              dest_path: &Path,
              fs_root_path: &Path,
-             force: bool)
+             policy: ConflictPolicy)
              -> Result<()> {
     let dst_path = fs_root_path.join(dest_path.strip_prefix("/")?);
     ui.begin(format!("Binlinking {} from {} into {}",
@@ -129,7 +189,7 @@ pub fn start(ui: &mut UI,
                      ident,
                      dst_path.display()))?;
     let pkg_install = PackageInstall::load(ident, Some(fs_root_path))?;
-    let mut src = match hfs::find_command_in_pkg(binary, &pkg_install, fs_root_path)? {
+    let src = match command_in_pkg(binary, &pkg_install, fs_root_path)? {
         Some(c) => c,
         None => {
             return Err(Error::CommandNotFoundInPkg((pkg_install.ident()
@@ -137,9 +197,6 @@ pub fn start(ui: &mut UI,
                                                     binary.to_string())));
         }
     };
-    if cfg!(target_os = "windows") {
-        src = fs_root_path.join(src.strip_prefix("/")?);
-    }
     if !dst_path.is_dir() {
         ui.status(Status::Creating,
                   format!("parent directory {}", dst_path.display()))?;
@@ -151,25 +208,32 @@ pub fn start(ui: &mut UI,
                                binary,
                                pkg_install.ident(),
                                binlink.link.display(),);
-    match Binlink::from_file(&binlink.link) {
+    let mut registry = Registry::load(fs_root_path)?;
+    let linked = match Binlink::from_file(&binlink.link) {
+        Ok(link) if link.target == src => true,
         Ok(link) => {
-            if force && link.target != src {
-                fs::remove_file(link.link)?;
-                binlink.link(pkg_install.environment_for_command()?)?;
-                ui.end(ui_binlinked)?;
-            } else if link.target != src {
-                ui.warn(format!("Skipping binlink because {} already exists at {}. Use --force \
-                                 to overwrite",
-                                binary,
-                                link.link.display(),))?;
-            } else {
-                ui.end(ui_binlinked)?;
+            let pin = registry.pin_for(binary);
+            match resolve_conflict(binary, &link, pkg_install.ident(), pin.as_ref(), policy)? {
+                Resolution::Replace => {
+                    fs::remove_file(link.link)?;
+                    binlink.link(pkg_install.environment_for_command()?)?;
+                    true
+                }
+                Resolution::Keep(reason) => {
+                    ui.warn(reason)?;
+                    false
+                }
             }
         }
         Err(_) => {
             binlink.link(pkg_install.environment_for_command()?)?;
-            ui.end(ui_binlinked)?;
+            true
         }
+    };
+    if linked {
+        registry.record(&binlink.link, pkg_install.ident());
+        registry.save(fs_root_path)?;
+        ui.end(ui_binlinked)?;
     }
 
     if cfg!(target_os = "windows") && !is_dest_on_path(&dst_path) {
@@ -185,7 +249,7 @@ pub fn binlink_all_in_pkg(ui: &mut UI,
                           pkg_ident: &PackageIdent,
                           dest_path: &Path,
                           fs_root_path: &Path,
-                          force: bool)
+                          policy: ConflictPolicy)
                           -> Result<()> {
     let pkg_path = PackageInstall::load(pkg_ident, Some(fs_root_path))?;
     for bin_path in pkg_path.paths()? {
@@ -225,12 +289,243 @@ pub fn binlink_all_in_pkg(ui: &mut UI,
                     continue;
                 }
             };
-            self::start(ui, pkg_ident, &bin_name, dest_path, fs_root_path, force)?;
+            self::start(ui, pkg_ident, &bin_name, dest_path, fs_root_path, policy)?;
+        }
+    }
+    Ok(())
+}
+
+/// Update each binlink recorded by `start` to the newest installed release of the package it was
+/// created from, or of the package its binary is pinned to, and remove the binlinks of packages
+/// that are no longer installed.
+pub fn sync(ui: &mut UI, fs_root_path: &Path) -> Result<()> {
+    ui.begin("Synchronizing binlinks with the installed packages")?;
+    let mut registry = Registry::load(fs_root_path)?;
+    let recorded = registry.links()
+                           .map(|(link, owner)| (link.to_path_buf(), owner.to_string()))
+                           .collect::<Vec<_>>();
+    for (link_path, owner) in recorded {
+        let owner: PackageIdent = owner.parse()?;
+        let existing = match Binlink::from_file(&link_path) {
+            Ok(existing) => existing,
+            Err(_) => {
+                registry.forget(&link_path);
+                continue;
+            }
+        };
+        // A binlink that was replaced by something else than `hab pkg binlink` is no longer ours
+        if !owner_of(&existing.target).map_or(false, |o| is_same_package(&o, &owner)) {
+            registry.forget(&link_path);
+            continue;
+        }
+        let binary = match existing.target.file_name().and_then(|name| name.to_str()) {
+            Some(binary) => binary.to_string(),
+            None => return Err(Error::CannotParseBinlinkTarget(existing.target)),
+        };
+
+        let latest = PackageIdent::new(owner.origin.clone(), owner.name.clone(), None, None);
+        let mut replacement = None;
+        for ident in registry.pin_for(&binary).into_iter().chain(Some(latest)) {
+            if let Ok(pkg_install) = PackageInstall::load(&ident, Some(fs_root_path)) {
+                if let Some(src) = command_in_pkg(&binary, &pkg_install, fs_root_path)? {
+                    replacement = Some((pkg_install, src));
+                    break;
+                }
+            }
+        }
+        match replacement {
+            Some((_, src)) if src == existing.target => {}
+            Some((pkg_install, src)) => {
+                ui.status(Status::Updating,
+                          format!("{} to {}", link_path.display(), pkg_install.ident()))?;
+                fs::remove_file(&link_path)?;
+                Binlink { link:   link_path.clone(),
+                          target: src, }.link(pkg_install.environment_for_command()?)?;
+                registry.record(&link_path, pkg_install.ident());
+            }
+            None => {
+                ui.status(Status::Deleting, link_path.display())?;
+                fs::remove_file(&link_path)?;
+                registry.forget(&link_path);
+            }
+        }
+    }
+    registry.save(fs_root_path)?;
+    ui.end("Binlinks are synchronized")?;
+    Ok(())
+}
+
+/// Remove the binlinks in `dest_path` that point into packages that are no longer installed,
+/// whether or not they were recorded by `start`.
+pub fn clean(ui: &mut UI, dest_path: &Path, fs_root_path: &Path) -> Result<()> {
+    let dst_path = fs_root_path.join(dest_path.strip_prefix("/")?);
+    ui.begin(format!("Removing dangling binlinks from {}", dst_path.display()))?;
+    let mut registry = Registry::load(fs_root_path)?;
+    if dst_path.is_dir() {
+        for entry in fs::read_dir(&dst_path)? {
+            let path = entry?.path();
+            let link = match Binlink::from_file(&path) {
+                Ok(link) => link,
+                Err(_) => continue,
+            };
+            if owner_of(&link.target).is_some() && !on_disk(&link.target, fs_root_path).exists() {
+                ui.status(Status::Deleting, path.display())?;
+                fs::remove_file(&path)?;
+                registry.forget(&path);
+            }
         }
     }
+    let removed = registry.links()
+                          .filter(|(link, _)| Binlink::from_file(link).is_err())
+                          .map(|(link, _)| link.to_path_buf())
+                          .collect::<Vec<_>>();
+    for link in removed {
+        registry.forget(&link);
+    }
+    registry.save(fs_root_path)?;
+    ui.end("Dangling binlinks are removed")?;
     Ok(())
 }
 
+/// Pin binaries to the packages they are always binlinked from, and remove the pins of
+/// `unpinned` binaries
+pub fn update_pins(ui: &mut UI,
+                   pins: &[(String, PackageIdent)],
+                   unpinned: &[&str],
+                   fs_root_path: &Path)
+                   -> Result<()> {
+    let mut registry = Registry::load(fs_root_path)?;
+    for (binary, ident) in pins {
+        ui.status(Status::Adding, format!("pin of {} to {}", binary, ident))?;
+        registry.pin(binary, ident);
+    }
+    for binary in unpinned {
+        ui.status(Status::Deleting, format!("pin of {}", binary))?;
+        registry.unpin(binary);
+    }
+    registry.save(fs_root_path)
+}
+
+/// Parse a `BINARY=PKG_IDENT` pin
+pub fn parse_pin(value: &str) -> Result<(String, PackageIdent)> {
+    let mut parts = value.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(binary), Some(ident)) if !binary.is_empty() => {
+            Ok((binary.to_string(), ident.parse()?))
+        }
+        _ => {
+            Err(Error::ArgumentError(format!("Invalid binlink pin '{}'. \
+                                              Pins have the form \
+                                              BINARY=PKG_IDENT (ex: \
+                                              openssl=core/openssl)",
+                                             value)))
+        }
+    }
+}
+
+/// Decide whether `candidate` takes over the binlink of `binary` from the package `existing`
+/// points into
+fn resolve_conflict(binary: &str,
+                    existing: &Binlink,
+                    candidate: &PackageIdent,
+                    pin: Option<&PackageIdent>,
+                    policy: ConflictPolicy)
+                    -> Result<Resolution> {
+    let owner = owner_of(&existing.target);
+    if policy == ConflictPolicy::Force
+       || owner.as_ref()
+               .map_or(false, |o| is_same_package(o, candidate))
+    {
+        return Ok(Resolution::Replace);
+    }
+    if let Some(pin) = pin {
+        if candidate.satisfies(pin) {
+            return Ok(Resolution::Replace);
+        }
+        if owner.as_ref().map_or(false, |o| o.satisfies(pin)) {
+            return Ok(Resolution::Keep(format!("Skipping binlink because {} is \
+                                                pinned to {}",
+                                               binary, pin)));
+        }
+    }
+    let owner_name = owner.as_ref()
+                          .map_or_else(|| existing.target.display().to_string(),
+                                       ToString::to_string);
+    match policy {
+        ConflictPolicy::Force => Ok(Resolution::Replace),
+        ConflictPolicy::Skip => {
+            Ok(Resolution::Keep(format!("Skipping binlink because {} \
+                                         already exists at {}. Use \
+                                         --force to overwrite",
+                                        binary,
+                                        existing.link.display())))
+        }
+        ConflictPolicy::Newest => {
+            match owner {
+                Some(ref owner) if candidate.release > owner.release => Ok(Resolution::Replace),
+                _ => {
+                    Ok(Resolution::Keep(format!("Skipping binlink because {} at \
+                                                 {} is binlinked from {}, which \
+                                                 is not older than {}",
+                                                binary,
+                                                existing.link.display(),
+                                                owner_name,
+                                                candidate)))
+                }
+            }
+        }
+        ConflictPolicy::Error => {
+            Err(Error::BinlinkConflict(binary.to_string(),
+                                       existing.link.clone(),
+                                       owner_name))
+        }
+    }
+}
+
+/// The path of `binary` in `pkg_install`, as a binlink targets it
+fn command_in_pkg(binary: &str,
+                  pkg_install: &PackageInstall,
+                  fs_root_path: &Path)
+                  -> Result<Option<PathBuf>> {
+    match hfs::find_command_in_pkg(binary, pkg_install, fs_root_path)? {
+        Some(src) if cfg!(target_os = "windows") => {
+            Ok(Some(fs_root_path.join(src.strip_prefix("/")?)))
+        }
+        src => Ok(src),
+    }
+}
+
+/// The package a binlink target is in, if it is in an installed package's directory
+fn owner_of(target: &Path) -> Option<PackageIdent> {
+    let parts = target.components()
+                      .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                      .collect::<Vec<_>>();
+    let pkgs = parts.windows(2)
+                    .position(|pair| pair[0] == "hab" && pair[1] == "pkgs")?;
+    match &parts[pkgs + 2..] {
+        [origin, name, version, release, _, ..] => {
+            Some(PackageIdent::new(origin.clone(),
+                                   name.clone(),
+                                   Some(version.clone()),
+                                   Some(release.clone())))
+        }
+        _ => None,
+    }
+}
+
+fn is_same_package(a: &PackageIdent, b: &PackageIdent) -> bool {
+    a.origin == b.origin && a.name == b.name
+}
+
+/// Where a binlink target is under `fs_root_path`. Windows binstubs already target a path
+/// under the filesystem root.
+fn on_disk(target: &Path, fs_root_path: &Path) -> PathBuf {
+    match target.strip_prefix("/") {
+        Ok(relative) if cfg!(not(target_os = "windows")) => fs_root_path.join(relative),
+        _ => target.to_path_buf(),
+    }
+}
+
 fn is_dest_on_path(dest_dir: &Path) -> bool {
     if let Some(val) = env::var_os("PATH") {
         env::split_paths(&val).any(|p| p == dest_dir)
@@ -243,8 +538,14 @@ fn is_dest_on_path(dest_dir: &Path) -> bool {
 #[cfg(not(target_os = "macos"))]
 mod test {
     use super::{binlink_all_in_pkg,
+                clean,
+                owner_of,
+                parse_pin,
                 start,
-                Binlink};
+                sync,
+                update_pins,
+                Binlink,
+                ConflictPolicy};
     use crate::{common::ui::UI,
                 hcore::{self,
                         package::{PackageIdent,
@@ -273,7 +574,7 @@ mod test {
                                    .join(rootfs_src_dir.strip_prefix("/").unwrap());
         }
         let rootfs_bin_dir = rootfs.path().join("opt/bin");
-        let force = true;
+        let policy = ConflictPolicy::Force;
 
         let mut ui = UI::with_sinks();
 
@@ -291,7 +592,7 @@ mod test {
               "magicate.exe",
               dst_path,
               rootfs.path(),
-              force).unwrap();
+              policy).unwrap();
         #[cfg(windows)]
         assert!(
                 fs::read_to_string(rootfs_bin_dir.join(magicate_link)).unwrap()
@@ -312,7 +613,7 @@ mod test {
               "hypnoanalyze.exe",
               dst_path,
               rootfs.path(),
-              force).unwrap();
+              policy).unwrap();
         #[cfg(windows)]
         assert!(
                 fs::read_to_string(rootfs_bin_dir.join(hypnoanalyze_link)).unwrap()
@@ -344,7 +645,7 @@ mod test {
                                    .join(rootfs_src_dir.strip_prefix("/").unwrap());
         }
         let rootfs_bin_dir = rootfs.path().join("opt/bin");
-        let force = true;
+        let policy = ConflictPolicy::Force;
 
         #[cfg(target_os = "linux")]
         let magicate_link = "magicate.exe";
@@ -360,7 +661,7 @@ mod test {
         let securitize_link = "securitize.bat";
 
         let mut ui = UI::with_sinks();
-        binlink_all_in_pkg(&mut ui, &ident, dst_path, rootfs.path(), force).unwrap();
+        binlink_all_in_pkg(&mut ui, &ident, dst_path, rootfs.path(), policy).unwrap();
 
         assert_eq!(rootfs_src_dir.join("bin/magicate.exe"),
                    Binlink::from_file(&rootfs_bin_dir.join(magicate_link)).unwrap()
@@ -387,10 +688,10 @@ mod test {
                   .join(hcore::fs::pkg_install_path(&ident, None::<&Path>).strip_prefix("/")
                                                                           .unwrap());
         let rootfs_bin_dir = rootfs.path().join("opt/bin");
-        let force = true;
+        let policy = ConflictPolicy::Force;
 
         let mut ui = UI::with_sinks();
-        binlink_all_in_pkg(&mut ui, &ident, dst_path, rootfs.path(), force).unwrap();
+        binlink_all_in_pkg(&mut ui, &ident, dst_path, rootfs.path(), policy).unwrap();
 
         assert_eq!(rootfs_src_dir.join("bin/magicate.exe"),
                    Binlink::from_file(&rootfs_bin_dir.join("magicate.bat")).unwrap()
@@ -413,7 +714,7 @@ mod test {
                                    .join(rootfs_src_dir.strip_prefix("/").unwrap());
         }
         let rootfs_bin_dir = rootfs.path().join("opt/bin");
-        let force = true;
+        let policy = ConflictPolicy::Force;
 
         // Create an empty subdirectory that is not strictly a directory containing package
         // binaries
@@ -432,7 +733,7 @@ mod test {
         let bonus_round_link = "bonus-round.bat";
 
         let mut ui = UI::with_sinks();
-        binlink_all_in_pkg(&mut ui, &ident, dst_path, rootfs.path(), force).unwrap();
+        binlink_all_in_pkg(&mut ui, &ident, dst_path, rootfs.path(), policy).unwrap();
 
         assert_eq!(rootfs_src_dir.join("bin/magicate.exe"),
                    Binlink::from_file(&rootfs_bin_dir.join(magicate_link)).unwrap()
@@ -442,6 +743,156 @@ mod test {
                                                                              .target);
     }
 
+    #[test]
+    fn start_resolves_conflicts_with_the_policy() {
+        let rootfs = TempDir::new().unwrap();
+        let mut tools = HashMap::new();
+        tools.insert("bin", vec!["magicate.exe"]);
+        let old = fake_bin_pkg_install("acme/cooltools/1.0.0/20200101000000",
+                                       tools.clone(),
+                                       rootfs.path());
+        let new = fake_bin_pkg_install("acme/hottools/1.0.0/20210101000000", tools, rootfs.path());
+        let dst_path = Path::new("/opt/bin");
+        let link = rootfs.path().join("opt/bin").join(link_name("magicate"));
+        let target_in = |ident: &PackageIdent| {
+            owner_of(&Binlink::from_file(&link).unwrap().target).as_ref() == Some(ident)
+        };
+
+        let mut ui = UI::with_sinks();
+        start(&mut ui,
+              &new,
+              "magicate.exe",
+              dst_path,
+              rootfs.path(),
+              ConflictPolicy::Skip).unwrap();
+        start(&mut ui,
+              &old,
+              "magicate.exe",
+              dst_path,
+              rootfs.path(),
+              ConflictPolicy::Skip).unwrap();
+        assert!(target_in(&new));
+        start(&mut ui,
+              &old,
+              "magicate.exe",
+              dst_path,
+              rootfs.path(),
+              ConflictPolicy::Newest).unwrap();
+        assert!(target_in(&new));
+        assert!(start(&mut ui,
+                      &old,
+                      "magicate.exe",
+                      dst_path,
+                      rootfs.path(),
+                      ConflictPolicy::Error).is_err());
+        assert!(target_in(&new));
+
+        update_pins(&mut ui,
+                    &[(String::from("magicate.exe"), "acme/cooltools".parse().unwrap())],
+                    &[],
+                    rootfs.path()).unwrap();
+        start(&mut ui,
+              &old,
+              "magicate.exe",
+              dst_path,
+              rootfs.path(),
+              ConflictPolicy::Skip).unwrap();
+        assert!(target_in(&old));
+        start(&mut ui,
+              &new,
+              "magicate.exe",
+              dst_path,
+              rootfs.path(),
+              ConflictPolicy::Newest).unwrap();
+        assert!(target_in(&old));
+        start(&mut ui,
+              &new,
+              "magicate.exe",
+              dst_path,
+              rootfs.path(),
+              ConflictPolicy::Force).unwrap();
+        assert!(target_in(&new));
+    }
+
+    #[test]
+    fn sync_follows_the_newest_release_and_removes_uninstalled_packages() {
+        let rootfs = TempDir::new().unwrap();
+        let mut tools = HashMap::new();
+        tools.insert("bin", vec!["magicate.exe"]);
+        let old = fake_bin_pkg_install("acme/cooltools/1.0.0/20200101000000",
+                                       tools.clone(),
+                                       rootfs.path());
+        let dst_path = Path::new("/opt/bin");
+        let link = rootfs.path().join("opt/bin").join(link_name("magicate"));
+
+        let mut ui = UI::with_sinks();
+        start(&mut ui,
+              &old,
+              "magicate.exe",
+              dst_path,
+              rootfs.path(),
+              ConflictPolicy::Skip).unwrap();
+        let new = fake_bin_pkg_install("acme/cooltools/1.1.0/20210101000000", tools, rootfs.path());
+        sync(&mut ui, rootfs.path()).unwrap();
+        assert_eq!(owner_of(&Binlink::from_file(&link).unwrap().target),
+                   Some(new.clone()));
+
+        for ident in &[old, new] {
+            fs::remove_dir_all(hcore::fs::pkg_install_path(ident, Some(rootfs.path()))).unwrap();
+        }
+        sync(&mut ui, rootfs.path()).unwrap();
+        assert!(Binlink::from_file(&link).is_err());
+    }
+
+    #[test]
+    fn clean_removes_dangling_binlinks() {
+        let rootfs = TempDir::new().unwrap();
+        let mut tools = HashMap::new();
+        tools.insert("bin", vec!["magicate.exe", "hypnoanalyze.exe"]);
+        let ident = fake_bin_pkg_install("acme/cooltools", tools, rootfs.path());
+        let dst_path = Path::new("/opt/bin");
+        let rootfs_bin_dir = rootfs.path().join("opt/bin");
+
+        let mut ui = UI::with_sinks();
+        binlink_all_in_pkg(&mut ui,
+                           &ident,
+                           dst_path,
+                           rootfs.path(),
+                           ConflictPolicy::Skip).unwrap();
+        fs::remove_file(hcore::fs::pkg_install_path(&ident, Some(rootfs.path()))
+                            .join("bin/magicate.exe")).unwrap();
+        clean(&mut ui, dst_path, rootfs.path()).unwrap();
+
+        assert!(Binlink::from_file(&rootfs_bin_dir.join(link_name("magicate"))).is_err());
+        assert!(Binlink::from_file(&rootfs_bin_dir.join(link_name("hypnoanalyze"))).is_ok());
+    }
+
+    #[test]
+    fn parse_pin_requires_a_binary_and_an_ident() {
+        let (binary, ident) = parse_pin("openssl=core/openssl/1.0.2").unwrap();
+        assert_eq!(binary, "openssl");
+        assert_eq!(ident, PackageIdent::from_str("core/openssl/1.0.2").unwrap());
+        assert!(parse_pin("openssl").is_err());
+        assert!(parse_pin("=core/openssl").is_err());
+    }
+
+    #[test]
+    fn owner_of_finds_the_package_of_a_target() {
+        let target = Path::new("/hab/pkgs/core/openssl/1.0.2/20200101000000/bin/openssl");
+        assert_eq!(owner_of(target),
+                   Some(PackageIdent::from_str("core/openssl/1.0.2/20200101000000").unwrap()));
+        assert_eq!(owner_of(Path::new("/usr/bin/openssl")), None);
+    }
+
+    /// The file name of the binlink of a `.exe` binary
+    fn link_name(binary: &str) -> String {
+        if cfg!(target_os = "windows") {
+            format!("{}.bat", binary)
+        } else {
+            format!("{}.exe", binary)
+        }
+    }
+
     fn fake_bin_pkg_install<P>(ident: &str,
                                binaries: HashMap<&str, Vec<&str>>,
                                rootfs: P)
@@ -0,0 +1,102 @@
+//! The record of the binlinks created by `hab pkg binlink`, kept in the cache directory so that
+//! `hab pkg binlink --sync` knows which links it manages and which package owns each of them.
+
+use crate::{error::Result,
+            hcore::{fs::{cache_root_path,
+                         AtomicWriter},
+                    package::PackageIdent}};
+use serde::{Deserialize,
+            Serialize};
+use std::{collections::BTreeMap,
+          fs,
+          io::Write,
+          path::{Path,
+                 PathBuf}};
+
+const REGISTRY_FILE: &str = "binlinks.toml";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Registry {
+    /// The package each binlink was created from, by the path of the binlink
+    #[serde(default)]
+    links: BTreeMap<PathBuf, String>,
+    /// The package that always provides a binary, by the name of the binary
+    #[serde(default)]
+    pins:  BTreeMap<String, String>,
+}
+
+impl Registry {
+    /// Load the registry of `fs_root_path`, which is empty if no binlink was recorded yet
+    pub fn load(fs_root_path: &Path) -> Result<Self> {
+        match fs::read_to_string(Self::path(fs_root_path)) {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, fs_root_path: &Path) -> Result<()> {
+        let path = Self::path(fs_root_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = toml::to_string(self)?;
+        AtomicWriter::new(&path)?.with_writer(|f| f.write_all(contents.as_bytes()))?;
+        Ok(())
+    }
+
+    pub fn links(&self) -> impl Iterator<Item = (&Path, &str)> {
+        self.links
+            .iter()
+            .map(|(link, owner)| (link.as_path(), owner.as_str()))
+    }
+
+    pub fn record(&mut self, link: &Path, owner: &PackageIdent) {
+        self.links.insert(link.to_path_buf(), owner.to_string());
+    }
+
+    pub fn forget(&mut self, link: &Path) { self.links.remove(link); }
+
+    pub fn pin(&mut self, binary: &str, ident: &PackageIdent) {
+        self.pins.insert(binary.to_string(), ident.to_string());
+    }
+
+    pub fn unpin(&mut self, binary: &str) { self.pins.remove(binary); }
+
+    /// The package `binary` is pinned to, if any. Pins that no longer parse are ignored.
+    pub fn pin_for(&self, binary: &str) -> Option<PackageIdent> {
+        self.pins.get(binary).and_then(|ident| ident.parse().ok())
+    }
+
+    fn path(fs_root_path: &Path) -> PathBuf {
+        cache_root_path(Some(fs_root_path)).join(REGISTRY_FILE)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn registry_round_trips() {
+        let rootfs = TempDir::new().unwrap();
+        let owner: PackageIdent = "core/openssl/1.0.2/20200101000000".parse().unwrap();
+        let pinned: PackageIdent = "core/openssl11".parse().unwrap();
+
+        let mut registry = Registry::load(rootfs.path()).unwrap();
+        assert_eq!(registry.links().count(), 0);
+        registry.record(Path::new("/bin/openssl"), &owner);
+        registry.pin("openssl", &pinned);
+        registry.save(rootfs.path()).unwrap();
+
+        let mut registry = Registry::load(rootfs.path()).unwrap();
+        assert_eq!(registry.links().collect::<Vec<_>>(),
+                   vec![(Path::new("/bin/openssl"), "core/openssl/1.0.2/20200101000000")]);
+        assert_eq!(registry.pin_for("openssl"), Some(pinned));
+        registry.forget(Path::new("/bin/openssl"));
+        registry.unpin("openssl");
+        assert_eq!(registry.links().count(), 0);
+        assert_eq!(registry.pin_for("openssl"), None);
+    }
+}
@@ -76,6 +76,41 @@ pub struct PackageSetFile {
     pub targets: HashMap<PackageTarget, Vec<PackageSetValue>>,
 }
 
+impl PackageSetFile {
+    /// Read a package set file, of which we currently only accept version 1
+    pub fn load(path: &Path) -> Result<Self> {
+        let file_data = std::fs::read_to_string(path)?;
+        let toml_data: PackageSetFile =
+            toml::from_str(&file_data).map_err(habitat_common::Error::TomlParser)?;
+
+        if toml_data.format_version.unwrap_or(1) != 1 {
+            return Err(Error::PackageSetParseError(format!(
+                "format_version invalid, only version 1 allowed ({} provided",
+                toml_data.format_version.unwrap()
+            )));
+        }
+        Ok(toml_data)
+    }
+
+    pub fn package_sets(&self) -> Result<Vec<PackageSet>> {
+        let mut sources = Vec::new();
+        for (target, target_array) in &self.targets {
+            for package_set_value in target_array {
+                let idents = package_set_value.packages
+                                              .iter()
+                                              .map(|s| s.parse().map_err(Error::from))
+                                              .collect::<Result<Vec<PackageIdent>>>()?;
+                let package_set = PackageSet { target: *target,
+                                               channel: package_set_value.channel.clone(),
+                                               idents };
+                debug!("Package Set {:?}", package_set);
+                sources.push(package_set)
+            }
+        }
+        Ok(sources)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PackageSetValue {
     pub channel:  ChannelIdent,
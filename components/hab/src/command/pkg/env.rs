@@ -1,8 +1,43 @@
 use crate::{error::Result,
-            hcore::package::{PackageIdent,
-                             PackageInstall}};
-use std::{collections::BTreeMap,
-          path::Path};
+            hcore::{fs::windows_system_paths,
+                    package::{PackageIdent,
+                              PackageInstall}}};
+use std::{collections::{BTreeMap,
+                        BTreeSet,
+                        HashSet},
+          env,
+          fs,
+          path::{Path,
+                 PathBuf}};
+
+const PATH_KEY: &str = "PATH";
+
+/// The runtime environment of several packages layered in order, the first package taking
+/// precedence
+#[derive(Debug, Default)]
+pub struct MergedEnvironment {
+    pub env:       BTreeMap<String, String>,
+    pub conflicts: Vec<Conflict>,
+    pub shadowed:  Vec<ShadowedCommand>,
+}
+
+/// A variable that packages set to different values. The value of the first package is used.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub key:    String,
+    /// Each package that sets the variable and its value, in order of precedence
+    pub values: Vec<(PackageIdent, String)>,
+}
+
+/// A command found in more than one directory of the merged `PATH`
+#[derive(Debug, PartialEq, Eq)]
+pub struct ShadowedCommand {
+    pub command:  String,
+    /// The directory the command is run from
+    pub used:     PathBuf,
+    /// The directories further down the `PATH` that also contain the command
+    pub shadowed: Vec<PathBuf>,
+}
 
 pub fn start(ident: &PackageIdent, fs_root_path: &Path) -> Result<()> {
     let pkg_install = PackageInstall::load(ident, Some(fs_root_path))?;
@@ -11,6 +46,130 @@ pub fn start(ident: &PackageIdent, fs_root_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Print the merged runtime environment of `idents`, followed by comments flagging the variables
+/// the packages disagree on and the commands shadowed on the `PATH`
+pub fn start_merged(idents: &[PackageIdent], fs_root_path: &Path) -> Result<()> {
+    let merged = merge(&load_all(idents, fs_root_path)?)?;
+    render_environment(merged.env);
+    for conflict in merged.conflicts {
+        let (winner, value) = &conflict.values[0];
+        println!("# {} is set to \"{}\" by {}, overriding:",
+                 conflict.key, value, winner);
+        for (ident, value) in &conflict.values[1..] {
+            println!("#   \"{}\" from {}", value, ident);
+        }
+    }
+    for command in merged.shadowed {
+        println!("# {} is run from {}, shadowing:",
+                 command.command,
+                 command.used.display());
+        for dir in command.shadowed {
+            println!("#   {}", dir.display());
+        }
+    }
+    Ok(())
+}
+
+pub fn load_all(idents: &[PackageIdent], fs_root_path: &Path) -> Result<Vec<PackageInstall>> {
+    idents.iter()
+          .map(|ident| PackageInstall::load(ident, Some(fs_root_path)).map_err(Into::into))
+          .collect()
+}
+
+/// Merge the runtime environments of `pkg_installs`, in order of precedence.
+///
+/// Variables that hold paths, which are `PATH`, the variables recorded as paths by the packages
+/// and any other variable whose name ends in `PATH`, are concatenated with duplicate entries
+/// removed. Any other variable gets the value of the first package that sets it.
+pub fn merge(pkg_installs: &[PackageInstall]) -> Result<MergedEnvironment> {
+    let mut path_keys = BTreeSet::new();
+    let mut envs = Vec::with_capacity(pkg_installs.len());
+    for pkg_install in pkg_installs {
+        path_keys.extend(pkg_install.runtime_environment_path_keys()?);
+        envs.push((pkg_install.ident().clone(), pkg_install.environment_for_command()?));
+    }
+    let mut merged = merge_environments(envs, &path_keys)?;
+    if let Some(path) = merged.env.get(PATH_KEY) {
+        merged.shadowed = shadowed_commands(path);
+    }
+    Ok(merged)
+}
+
+fn merge_environments(envs: Vec<(PackageIdent, BTreeMap<String, String>)>,
+                      path_keys: &BTreeSet<String>)
+                      -> Result<MergedEnvironment> {
+    let mut values_by_key = BTreeMap::<String, Vec<(PackageIdent, String)>>::new();
+    for (ident, env) in envs {
+        for (key, value) in env {
+            values_by_key.entry(key)
+                         .or_default()
+                         .push((ident.clone(), value));
+        }
+    }
+
+    let mut merged = MergedEnvironment::default();
+    for (key, values) in values_by_key {
+        if is_path_key(&key, path_keys) {
+            let mut seen = HashSet::new();
+            let mut paths = values.iter()
+                                  .flat_map(|(_, value)| env::split_paths(value))
+                                  .filter(|path| seen.insert(path.clone()))
+                                  .collect::<Vec<_>>();
+            if key == PATH_KEY {
+                // Every package appends the system paths on Windows, they belong at the end
+                let system_paths = windows_system_paths();
+                paths.retain(|path| !system_paths.contains(path));
+                paths.extend(system_paths);
+            }
+            let joined = env::join_paths(paths)?;
+            merged.env
+                  .insert(key, joined.to_string_lossy().into_owned());
+        } else {
+            let value = values[0].1.clone();
+            if values.iter().any(|(_, v)| *v != value) {
+                merged.conflicts.push(Conflict { key: key.clone(),
+                                                 values });
+            }
+            merged.env.insert(key, value);
+        }
+    }
+    Ok(merged)
+}
+
+fn is_path_key(key: &str, path_keys: &BTreeSet<String>) -> bool {
+    path_keys.contains(key) || key.to_uppercase().ends_with(PATH_KEY)
+}
+
+/// Find the commands that are in more than one directory of `path`
+fn shadowed_commands(path: &str) -> Vec<ShadowedCommand> {
+    let mut found = BTreeMap::<String, Vec<PathBuf>>::new();
+    for dir in env::split_paths(path) {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if entry.path().is_dir() {
+                continue;
+            }
+            let dirs = found.entry(entry.file_name().to_string_lossy().into_owned())
+                            .or_default();
+            if !dirs.contains(&dir) {
+                dirs.push(dir.clone());
+            }
+        }
+    }
+    found.into_iter()
+         .filter(|(_, dirs)| dirs.len() > 1)
+         .map(|(command, mut dirs)| {
+             let used = dirs.remove(0);
+             ShadowedCommand { command,
+                               used,
+                               shadowed: dirs }
+         })
+         .collect()
+}
+
 #[cfg(unix)]
 fn render_environment(env: BTreeMap<String, String>) {
     for (key, value) in env.into_iter() {
@@ -24,3 +183,73 @@ fn render_environment(env: BTreeMap<String, String>) {
         println!("$env:{}=\"{}\"", key, value);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn env_of(vars: &[(&str, &str)]) -> BTreeMap<String, String> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn join(paths: &[&str]) -> String { env::join_paths(paths).unwrap().into_string().unwrap() }
+
+    #[test]
+    fn merge_environments_layers_paths_in_order() {
+        let app: PackageIdent = "acme/app/1.0.0/20200101000000".parse().unwrap();
+        let tools: PackageIdent = "acme/tools/1.0.0/20200101000000".parse().unwrap();
+        let envs = vec![(app,
+                         env_of(&[("PATH", &join(&["/app/bin", "/openssl/bin"])),
+                                  ("LD_LIBRARY_PATH", "/app/lib"),
+                                  ("PYTHONHOME", "/app/python")])),
+                        (tools,
+                         env_of(&[("PATH", &join(&["/tools/bin", "/openssl/bin"])),
+                                  ("LD_LIBRARY_PATH", "/tools/lib"),
+                                  ("CLASSES", &join(&["/tools/classes"]))])),];
+        let path_keys = vec![String::from("CLASSES")].into_iter().collect();
+
+        let merged = merge_environments(envs, &path_keys).unwrap();
+        assert_eq!(merged.env["PATH"],
+                   join(&["/app/bin", "/openssl/bin", "/tools/bin"]));
+        assert_eq!(merged.env["LD_LIBRARY_PATH"],
+                   join(&["/app/lib", "/tools/lib"]));
+        assert_eq!(merged.env["CLASSES"], "/tools/classes");
+        assert_eq!(merged.env["PYTHONHOME"], "/app/python");
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_environments_flags_conflicting_values() {
+        let jdk8: PackageIdent = "core/jdk8/8.0/20200101000000".parse().unwrap();
+        let jdk11: PackageIdent = "core/jdk11/11.0/20200101000000".parse().unwrap();
+        let envs = vec![(jdk8.clone(), env_of(&[("JAVA_HOME", "/jdk8")])),
+                        (jdk11.clone(), env_of(&[("JAVA_HOME", "/jdk11")])),];
+
+        let merged = merge_environments(envs, &BTreeSet::new()).unwrap();
+        assert_eq!(merged.env["JAVA_HOME"], "/jdk8");
+        assert_eq!(merged.conflicts,
+                   vec![Conflict { key:    String::from("JAVA_HOME"),
+                                   values: vec![(jdk8, String::from("/jdk8")),
+                                                (jdk11, String::from("/jdk11")),], }]);
+    }
+
+    #[test]
+    fn shadowed_commands_are_found_in_path_order() {
+        let root = TempDir::new().unwrap();
+        let first = root.path().join("first");
+        let second = root.path().join("second");
+        for (dir, command) in &[(&first, "openssl"), (&first, "curl"), (&second, "openssl")] {
+            fs::create_dir_all(dir).unwrap();
+            fs::write(dir.join(command), "").unwrap();
+        }
+        let path = env::join_paths(&[&first, &second]).unwrap();
+
+        assert_eq!(shadowed_commands(&path.to_string_lossy()),
+                   vec![ShadowedCommand { command:  String::from("openssl"),
+                                          used:     first,
+                                          shadowed: vec![second], }]);
+    }
+}
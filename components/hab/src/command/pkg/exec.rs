@@ -1,4 +1,5 @@
-use crate::{error::{Error,
+use crate::{command::pkg::env as pkg_env,
+            error::{Error,
                     Result},
             hcore::{fs::{find_command,
                          FS_ROOT_PATH},
@@ -8,16 +9,53 @@ use crate::{error::{Error,
 use std::{env,
           ffi::OsString,
           io,
-          path::PathBuf};
+          path::PathBuf,
+          process::{Command,
+                    ExitStatus}};
 
 const PATH_KEY: &str = "PATH";
 
-pub fn start<T>(ident: &PackageIdent, command: T, args: &[OsString]) -> Result<()>
+/// Run `command` in the runtime environment of `ident`. The environments of the packages in
+/// `with` are layered under it, in order.
+pub fn start<T>(ident: &PackageIdent,
+                with: &[PackageIdent],
+                command: T,
+                args: &[OsString])
+                -> Result<()>
+    where T: Into<PathBuf>
+{
+    let command = set_up(ident, with, command, args)?;
+    process::become_command(command, args)?;
+    Ok(())
+}
+
+/// Run `command` in the runtime environment of `ident` as a child process, returning its exit
+/// status once it exits.
+pub fn run<T>(ident: &PackageIdent, command: T, args: &[OsString]) -> Result<ExitStatus>
+    where T: Into<PathBuf>
+{
+    let command = set_up(ident, &[], command, args)?;
+    Ok(Command::new(command).args(args).status()?)
+}
+
+/// Switch this process to the runtime environment of `ident` layered over the packages of
+/// `with`, returning the path of `command` in it.
+fn set_up<T>(ident: &PackageIdent,
+             with: &[PackageIdent],
+             command: T,
+             args: &[OsString])
+             -> Result<PathBuf>
     where T: Into<PathBuf>
 {
     let command = command.into();
     let pkg_install = PackageInstall::load(ident, Some(&*FS_ROOT_PATH))?;
-    let mut cmd_env = pkg_install.environment_for_command()?;
+    let mut cmd_env = if with.is_empty() {
+        pkg_install.environment_for_command()?
+    } else {
+        let mut pkg_installs = vec![pkg_install];
+        pkg_installs.extend(pkg_env::load_all(with, &*FS_ROOT_PATH)?);
+        pkg_env::merge(&pkg_installs)?.env
+    };
 
     if let Some(path) = cmd_env.get(PATH_KEY) {
         if let Some(val) = env::var_os(PATH_KEY) {
@@ -48,6 +86,5 @@ pub fn start<T>(ident: &PackageIdent, command: T, args: &[OsString]) -> Result<(
         display_args.push_str(arg.to_string_lossy().as_ref());
     }
     debug!("Running: {}", display_args);
-    process::become_command(command, args)?;
-    Ok(())
+    Ok(command)
 }
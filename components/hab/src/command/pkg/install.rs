@@ -0,0 +1,249 @@
+//! Installs the packages listed in a manifest as a single transaction.
+//!
+//! # Examples
+//!
+//! ```bash
+//! $ hab pkg install --file packages.toml --report install-report.json
+//! ```
+//!
+//! The manifest uses the package set format of `hab pkg download --file`. Packages are installed
+//! in the order they are listed. If any of them fails to install, every package installed by this
+//! run, dependencies included, is uninstalled again so that no partial set of packages is left
+//! behind. Packages that were already installed, or that another package installed in the
+//! meantime depends on, are never removed.
+
+use crate::{command::pkg::{download::PackageSet,
+                           uninstall::{self,
+                                       UninstallHookMode,
+                                       UninstallSafety},
+                           ExecutionStrategy,
+                           Scope},
+            common::{self,
+                     command::package::install::{InstallHookMode,
+                                                 InstallMode,
+                                                 InstallSource,
+                                                 LocalPackageUsage},
+                     ui::{Status,
+                          UIWriter}},
+            error::{Error,
+                    Result},
+            hcore::{fs::{pkg_install_path,
+                         pkg_root_path},
+                    package::{list,
+                              PackageIdent,
+                              PackageInstall,
+                              PackageTarget},
+                    ChannelIdent},
+            PRODUCT,
+            VERSION};
+use serde::Serialize;
+use std::{collections::HashSet,
+          fs,
+          path::Path};
+
+/// Where to install packages from and how
+pub struct InstallOptions<'a> {
+    pub url:                 &'a str,
+    pub token:               Option<&'a str>,
+    pub fs_root_path:        &'a Path,
+    pub artifact_cache_path: &'a Path,
+    pub install_mode:        &'a InstallMode,
+    pub local_package_usage: &'a LocalPackageUsage,
+    pub install_hook_mode:   InstallHookMode,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageStatus {
+    Installed,
+    Failed,
+    /// Installed, then removed again because another package failed to install
+    RolledBack,
+    /// Not attempted because another package failed to install first
+    NotAttempted,
+    /// Listed for a target other than the one of this system
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackageReport {
+    pub ident:     String,
+    pub target:    PackageTarget,
+    pub channel:   ChannelIdent,
+    pub status:    PackageStatus,
+    /// The fully qualified identifier of the package that was installed
+    pub installed: Option<String>,
+    pub error:     Option<String>,
+}
+
+impl PackageReport {
+    fn new(ident: &PackageIdent, set: &PackageSet) -> Self {
+        PackageReport { ident:     ident.to_string(),
+                        target:    set.target,
+                        channel:   set.channel.clone(),
+                        status:    PackageStatus::NotAttempted,
+                        installed: None,
+                        error:     None, }
+    }
+}
+
+/// The machine-readable result of installing a manifest
+#[derive(Debug, Serialize)]
+pub struct InstallReport {
+    pub success:     bool,
+    pub packages:    Vec<PackageReport>,
+    /// Every package removed while rolling back, dependencies included
+    pub rolled_back: Vec<String>,
+    /// The packages that were installed, if all of them were
+    #[serde(skip)]
+    pub installed:   Vec<PackageIdent>,
+}
+
+/// Install all of the packages of `package_sets` for `active_target`, or none of them. A failure
+/// to install a package is recorded in the returned report rather than returned as an error.
+pub async fn start<U>(ui: &mut U,
+                      package_sets: &[PackageSet],
+                      active_target: PackageTarget,
+                      options: &InstallOptions<'_>)
+                      -> Result<InstallReport>
+    where U: UIWriter
+{
+    let idents = package_sets.iter()
+                             .flat_map(|set| set.idents.iter().map(move |ident| (ident, set)))
+                             .collect::<Vec<_>>();
+    let mut report = InstallReport { success:     true,
+                                     packages:    Vec::with_capacity(idents.len()),
+                                     rolled_back: Vec::new(),
+                                     installed:   Vec::new(), };
+    let pkg_root = pkg_root_path(Some(options.fs_root_path));
+    let mut already_installed = installed_packages(&pkg_root)?;
+    // The packages installed by this run, dependents before their dependencies
+    let mut installed_by_run = Vec::new();
+
+    let total = idents.len();
+    for (i, (ident, set)) in idents.into_iter().enumerate() {
+        let mut package = PackageReport::new(ident, set);
+        if set.target != active_target {
+            ui.status(Status::Skipping,
+                      format!("{} for {} ({} of {})", ident, set.target, i + 1, total))?;
+            package.status = PackageStatus::Skipped;
+        } else if report.success {
+            ui.begin(format!("Installing {} ({} of {})", ident, i + 1, total))?;
+            let install_source = InstallSource::Ident(ident.clone(), set.target);
+            match common::command::package::install::start(ui,
+                                                           options.url,
+                                                           &set.channel,
+                                                           &install_source,
+                                                           PRODUCT,
+                                                           VERSION,
+                                                           options.fs_root_path,
+                                                           options.artifact_cache_path,
+                                                           options.token,
+                                                           options.install_mode,
+                                                           options.local_package_usage,
+                                                           options.install_hook_mode).await
+            {
+                Ok(pkg_install) => {
+                    record_installed(&pkg_install, &mut already_installed, &mut installed_by_run)?;
+                    package.status = PackageStatus::Installed;
+                    package.installed = Some(pkg_install.ident().to_string());
+                    report.installed.push(pkg_install.ident().clone());
+                }
+                Err(err) => {
+                    // The package itself is unpacked before its install hook runs, which may fail
+                    let partial = PackageInstall::load(ident, Some(options.fs_root_path));
+                    if let Ok(pkg_install) = partial {
+                        record_installed(&pkg_install,
+                                         &mut already_installed,
+                                         &mut installed_by_run)?;
+                    }
+                    ui.fatal(format!("Failed to install {}: {}", ident, err))?;
+                    package.status = PackageStatus::Failed;
+                    package.error = Some(err.to_string());
+                    report.success = false;
+                }
+            }
+        }
+        report.packages.push(package);
+    }
+
+    if !report.success {
+        report.installed.clear();
+        installed_by_run.reverse();
+        report.rolled_back = roll_back(ui, &installed_by_run, options.fs_root_path).await?;
+        for package in report.packages
+                             .iter_mut()
+                             .filter(|p| p.status == PackageStatus::Installed)
+        {
+            package.status = PackageStatus::RolledBack;
+        }
+    }
+    Ok(report)
+}
+
+/// Write `report` to `path` as JSON
+pub fn write_report(report: &InstallReport, path: &Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(report)?;
+    fs::write(path, json + "\n").map_err(Error::from)
+}
+
+pub(crate) fn installed_packages(pkg_root: &Path) -> Result<HashSet<PackageIdent>> {
+    if !pkg_root.exists() {
+        return Ok(HashSet::new());
+    }
+    Ok(list::all_packages(pkg_root)?.into_iter().collect())
+}
+
+/// The packages installed along with `pkg_install`: the package and its transitive dependencies
+/// that are not in `already_installed`, dependents before their dependencies
+pub(crate) fn newly_installed(pkg_install: &PackageInstall,
+                              already_installed: &HashSet<PackageIdent>)
+                              -> Result<Vec<PackageIdent>> {
+    let mut idents = vec![pkg_install.ident().clone()];
+    idents.extend(pkg_install.tdeps()?);
+    Ok(idents.into_iter()
+             .filter(|ident| !already_installed.contains(ident))
+             .collect())
+}
+
+/// Add the packages installed along with `pkg_install` to `installed_by_run`, which lists the
+/// packages installed so far with each package after its dependencies
+fn record_installed(pkg_install: &PackageInstall,
+                    already_installed: &mut HashSet<PackageIdent>,
+                    installed_by_run: &mut Vec<PackageIdent>)
+                    -> Result<()> {
+    let mut installed = newly_installed(pkg_install, already_installed)?;
+    already_installed.extend(installed.iter().cloned());
+    installed.reverse();
+    installed_by_run.append(&mut installed);
+    Ok(())
+}
+
+/// Uninstall the packages of `installed`, which lists dependents before their dependencies,
+/// running their uninstall hooks. A package that another one has come to depend on, or that is
+/// loaded by the Supervisor, is left installed. Returns the idents of the packages removed.
+pub(crate) async fn roll_back<U>(ui: &mut U,
+                                 installed: &[PackageIdent],
+                                 fs_root_path: &Path)
+                                 -> Result<Vec<String>>
+    where U: UIWriter
+{
+    let mut rolled_back = Vec::new();
+    for ident in installed {
+        if let Err(err) = uninstall::uninstall(ui,
+                                               ident,
+                                               fs_root_path,
+                                               ExecutionStrategy::Run,
+                                               Scope::Package,
+                                               &[],
+                                               UninstallHookMode::Run,
+                                               UninstallSafety::Safe).await
+        {
+            ui.fatal(format!("Unable to remove {} while rolling back: {}", ident, err))?;
+        } else if !pkg_install_path(ident, Some(fs_root_path)).exists() {
+            rolled_back.push(ident.to_string());
+        }
+    }
+    rolled_back.sort();
+    Ok(rolled_back)
+}
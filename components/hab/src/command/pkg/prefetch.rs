@@ -0,0 +1,72 @@
+//! Downloads the current releases of packages, and all of their dependencies, into the local
+//! artifact cache without installing them.
+//!
+//! # Examples
+//!
+//! ```bash
+//! $ hab pkg prefetch --channel stable core/redis core/nginx
+//! ```
+//!
+//! Installing these releases later, whether with `hab pkg install` or through a Supervisor
+//! update, then uses the cached artifacts, so scheduled maintenance windows aren't spent waiting
+//! on downloads. Artifacts that are already in the cache are not downloaded again.
+
+use crate::{command::pkg::download::{self,
+                                     PackageSet,
+                                     PackageSetFile},
+            common::{cli::{file_into_idents,
+                           is_toml_file},
+                     error::Error as CommonError,
+                     ui::UIWriter},
+            error::Result,
+            hcore::{fs::cache_root_path,
+                    package::PackageTarget,
+                    ChannelIdent}};
+use std::path::Path;
+
+/// Prefetch the packages of `package_sets` into the artifact cache under `fs_root_path`.
+/// Artifacts are always verified, as they will be installed from the cache.
+pub async fn start<U>(ui: &mut U,
+                      url: &str,
+                      product: &str,
+                      version: &str,
+                      package_sets: &[PackageSet],
+                      fs_root_path: &Path,
+                      token: Option<&str>,
+                      ignore_missing_seeds: bool)
+                      -> Result<()>
+    where U: UIWriter
+{
+    if package_sets.iter().all(|set| set.idents.is_empty()) {
+        return Err(CommonError::MissingCLIInputError(String::from("No package identifiers \
+                                                                   found")).into());
+    }
+    // The artifact and key caches are the `artifacts` and `keys` directories of the cache root,
+    // which is where a download puts artifacts and their signing keys.
+    let cache_path = cache_root_path(Some(fs_root_path));
+    download::start(ui,
+                    url,
+                    product,
+                    version,
+                    package_sets,
+                    Some(&cache_path),
+                    token,
+                    true,
+                    ignore_missing_seeds).await
+}
+
+/// Read the package sets of a file of newline separated package identifiers, which are fetched
+/// from `channel` for `target`, or of a TOML package set file
+pub fn package_sets_from_file(path: &Path,
+                              channel: &ChannelIdent,
+                              target: PackageTarget)
+                              -> Result<Vec<PackageSet>> {
+    let filename = path.to_string_lossy();
+    if is_toml_file(&filename) {
+        PackageSetFile::load(path)?.package_sets()
+    } else {
+        Ok(vec![PackageSet { target,
+                             channel: channel.clone(),
+                             idents: file_into_idents(&filename)? }])
+    }
+}
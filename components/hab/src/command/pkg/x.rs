@@ -0,0 +1,71 @@
+//! Runs a command from a package without leaving the package installed.
+//!
+//! # Examples
+//!
+//! ```bash
+//! $ hab x core/ripgrep rg --version
+//! ```
+//!
+//! The package is installed if needed, with its dependencies, and the command is run in its
+//! runtime environment like `hab pkg exec` does. Once the command exits, every package installed
+//! for the run is uninstalled again, unless `--keep` is given. Packages that were already
+//! installed, or that were installed in the meantime by something else, are never removed.
+
+use crate::{command::pkg::{exec,
+                           install::{self,
+                                     InstallOptions}},
+            common::{self,
+                     command::package::install::InstallSource,
+                     ui::UI},
+            error::{Error,
+                    Result},
+            hcore::{fs::pkg_root_path,
+                    package::{PackageIdent,
+                              PackageTarget},
+                    ChannelIdent},
+            PRODUCT,
+            VERSION};
+use std::{ffi::OsString,
+          path::PathBuf};
+
+pub async fn start(ui: &mut UI,
+                   ident: &PackageIdent,
+                   channel: &ChannelIdent,
+                   options: &InstallOptions<'_>,
+                   keep: bool,
+                   command: PathBuf,
+                   args: &[OsString])
+                   -> Result<()> {
+    let pkg_root = pkg_root_path(Some(options.fs_root_path));
+    let already_installed = install::installed_packages(&pkg_root)?;
+
+    let install_source = InstallSource::Ident(ident.clone(), PackageTarget::active_target());
+    let pkg_install = common::command::package::install::start(ui,
+                                                               options.url,
+                                                               channel,
+                                                               &install_source,
+                                                               PRODUCT,
+                                                               VERSION,
+                                                               options.fs_root_path,
+                                                               options.artifact_cache_path,
+                                                               options.token,
+                                                               options.install_mode,
+                                                               options.local_package_usage,
+                                                               options.install_hook_mode).await?;
+
+    // An interrupt of the command must not end this process before the packages are removed
+    ctrlc::set_handler(move || {})?;
+    let installed = install::newly_installed(&pkg_install, &already_installed)?;
+    let status = exec::run(pkg_install.ident(), &command, args);
+
+    if !keep {
+        install::roll_back(ui, &installed, options.fs_root_path).await?;
+    }
+
+    let status = status?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::ExecCommandFailed(command, status.code()))
+    }
+}
@@ -1,4 +1,5 @@
 pub mod docker;
 pub mod enter;
+pub mod options;
 
 pub fn native_studio_support() -> bool { cfg!(target_os = "linux") || cfg!(target_os = "windows") }
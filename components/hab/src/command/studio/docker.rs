@@ -1,6 +1,7 @@
-use crate::{command::studio::enter::{ARTIFACT_PATH_ENVVAR,
-                                     CERT_PATH_ENVVAR,
-                                     SSL_CERT_FILE_ENVVAR},
+use crate::{command::studio::{enter::{ARTIFACT_PATH_ENVVAR,
+                                      CERT_PATH_ENVVAR,
+                                      SSL_CERT_FILE_ENVVAR},
+                              options::StudioOptions},
             common::ui::UI,
             error::{Error,
                     Result},
@@ -30,7 +31,7 @@ const DOCKER_OPTS_ENVVAR: &str = "HAB_DOCKER_OPTS";
 const DOCKER_SOCKET: &str = "/var/run/docker.sock";
 const HAB_STUDIO_SECRET: &str = "HAB_STUDIO_SECRET_";
 
-pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
+pub fn start_docker_studio(_ui: &mut UI, args: &[OsString], opts: &StudioOptions) -> Result<()> {
     let mut args = args.to_vec();
     if args.get(0) == Some(&OsString::from("rm")) {
         return Err(Error::CannotRemoveDockerStudio);
@@ -83,6 +84,9 @@ pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
     {
         volumes.push(format!("{}:{}", DOCKER_SOCKET, DOCKER_SOCKET));
     }
+    for mount in &opts.mounts {
+        volumes.push(format!("{}:{}{}", mount.src.display(), mnt_prefix, mount.dst));
+    }
 
     let mut env_vars = vec![String::from("DEBUG"),
                             String::from("DO_CHECK"),
@@ -107,6 +111,12 @@ pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
             env_vars.push(key);
         }
     }
+    env_vars.extend(opts.prepare_env_vars());
+
+    let ports = opts.ports
+                    .iter()
+                    .map(|p| format!("{}:{}", p.host, p.studio))
+                    .collect::<Vec<_>>();
 
     // We need to strip out the -D if it exists to avoid
     // it getting passed to the sup on entering the studio
@@ -128,6 +138,7 @@ pub fn start_docker_studio(_ui: &mut UI, args: &[OsString]) -> Result<()> {
                   &args,
                   volumes.iter(),
                   env_vars.iter(),
+                  ports.iter(),
                   image,
                   using_windows_containers)
 }
@@ -230,15 +241,17 @@ fn check_mounts<I, S>(docker_cmd: &Path, volumes: I, image: &str) -> Result<()>
     Ok(())
 }
 
-fn run_container<I, J, S, T>(docker_cmd: PathBuf,
-                             args: &[OsString],
-                             volumes: I,
-                             env_vars: J,
-                             image: String,
-                             using_windows_containers: bool)
-                             -> Result<()>
+fn run_container<I, J, K, S, T>(docker_cmd: PathBuf,
+                                args: &[OsString],
+                                volumes: I,
+                                env_vars: J,
+                                ports: K,
+                                image: String,
+                                using_windows_containers: bool)
+                                -> Result<()>
     where I: IntoIterator<Item = S>,
           J: IntoIterator<Item = T>,
+          K: IntoIterator<Item = S>,
           S: AsRef<OsStr>,
           T: AsRef<str>
 {
@@ -286,6 +299,11 @@ fn run_container<I, J, S, T>(docker_cmd: PathBuf,
         cmd_args.push(vol.as_ref().into());
     }
 
+    for port in ports {
+        cmd_args.push("--publish".into());
+        cmd_args.push(port.as_ref().into());
+    }
+
     cmd_args.push(image.into());
     cmd_args.extend_from_slice(args);
 
@@ -1,4 +1,5 @@
-use crate::{common::ui::{UIWriter,
+use crate::{command::studio::options::StudioOptions,
+            common::ui::{UIWriter,
                          UI},
             error::{Error,
                     Result},
@@ -133,12 +134,14 @@ pub async fn start(ui: &mut UI, args: &[OsString]) -> Result<()> {
         }
     }
 
-    inner::start(ui, args).await
+    let (opts, args) = StudioOptions::extract(args, &env::current_dir()?)?;
+    inner::start(ui, &args, &opts).await
 }
 
 #[cfg(target_os = "linux")]
 mod inner {
-    use crate::{command::studio::docker,
+    use crate::{command::studio::{docker,
+                                  options::StudioOptions},
                 common::ui::{UIWriter,
                              UI},
                 error::{Error,
@@ -162,11 +165,12 @@ mod inner {
     const STUDIO_CMD: &str = "hab-studio";
     const STUDIO_CMD_ENVVAR: &str = "HAB_STUDIO_BINARY";
 
-    pub async fn start(ui: &mut UI, args: &[OsString]) -> Result<()> {
+    pub async fn start(ui: &mut UI, args: &[OsString], opts: &StudioOptions) -> Result<()> {
         rerun_with_sudo_if_needed(ui, args)?;
         if is_docker_studio(args) {
-            docker::start_docker_studio(ui, args)
+            docker::start_docker_studio(ui, args, opts)
         } else {
+            opts.export_to_native_studio();
             let command = match henv::var(STUDIO_CMD_ENVVAR) {
                 Ok(command) => PathBuf::from(command),
                 Err(_) => {
@@ -256,8 +260,10 @@ mod inner {
 
 #[cfg(not(target_os = "linux"))]
 mod inner {
-    use crate::{command::studio::docker,
-                common::ui::UI,
+    use crate::{command::studio::{docker,
+                                  options::StudioOptions},
+                common::ui::{UIWriter,
+                             UI},
                 error::{Error,
                         Result},
                 exec,
@@ -269,11 +275,16 @@ mod inner {
     use std::{ffi::OsString,
               str::FromStr};
 
-    pub async fn start(_ui: &mut UI, args: &[OsString]) -> Result<()> {
+    pub async fn start(ui: &mut UI, args: &[OsString], opts: &StudioOptions) -> Result<()> {
         if is_windows_studio(args) {
-            start_windows_studio(_ui, args).await
+            if !opts.mounts.is_empty() {
+                ui.warn("Extra mounts are only supported by Docker Studios on Windows, ignoring \
+                         them.")?;
+            }
+            opts.export_to_native_studio();
+            start_windows_studio(ui, args).await
         } else {
-            docker::start_docker_studio(_ui, args)
+            docker::start_docker_studio(ui, args, opts)
         }
     }
 
@@ -0,0 +1,342 @@
+//! Extra bind-mounts, environment passthrough and port forwards for a Studio session.
+//!
+//! These are given ahead of the Studio subcommand with `--mount <SRC>:<DEST>`, `--env
+//! <VAR>[=<VALUE>]` and `--port <HOST>[:<STUDIO>]`, or declared in a `.studiorc.toml` in the
+//! directory `hab studio` is run from:
+//!
+//! ```toml
+//! mount = ["../shared-lib:/src/shared-lib"]
+//! env = ["AWS_PROFILE", "LOG_LEVEL=debug"]
+//! port = ["8080:80", "9631"]
+//! ```
+//!
+//! Options from the command line are added to the ones declared in the file.
+
+use crate::error::{Error,
+                   Result};
+use serde::Deserialize;
+use std::{env,
+          ffi::OsString,
+          fs,
+          path::{Path,
+                 PathBuf}};
+
+/// The file in the source directory that declares options for every session
+pub const STUDIORC_TOML: &str = ".studiorc.toml";
+/// The bind-mounts a chroot Studio makes, one `<SRC>:<DEST>` pair per line
+pub const STUDIO_MOUNTS_ENVVAR: &str = "HAB_STUDIO_MOUNTS";
+
+const STUDIO_SECRET_PREFIX: &str = "HAB_STUDIO_SECRET_";
+const MOUNT_FLAG: &str = "--mount";
+const ENV_FLAG: &str = "--env";
+const PORT_FLAG: &str = "--port";
+/// The `hab-studio` short options which take a value, so their value is not mistaken for the
+/// subcommand
+const OPTS_WITH_VALUE: &str = "ackrst";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Studiorc {
+    mount: Vec<String>,
+    env:   Vec<String>,
+    port:  Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Mount {
+    /// The absolute path on the host
+    pub src: PathBuf,
+    /// The absolute path in the Studio
+    pub dst: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EnvPassthrough {
+    pub name:  String,
+    /// The value to set, or `None` to pass the host's value through
+    pub value: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PortForward {
+    pub host:   u16,
+    pub studio: u16,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct StudioOptions {
+    pub mounts: Vec<Mount>,
+    pub env:    Vec<EnvPassthrough>,
+    pub ports:  Vec<PortForward>,
+}
+
+impl StudioOptions {
+    /// Collect the options declared in the `.studiorc.toml` of `src_dir` and given in `args`,
+    /// returning them along with the arguments `hab-studio` itself understands.
+    pub fn extract(args: &[OsString], src_dir: &Path) -> Result<(Self, Vec<OsString>)> {
+        let mut opts = Self::load(src_dir)?;
+        let args = opts.extract_flags(args, src_dir)?;
+        Ok((opts, args))
+    }
+
+    fn load(src_dir: &Path) -> Result<Self> {
+        let path = src_dir.join(STUDIORC_TOML);
+        let studiorc: Studiorc = match fs::read_to_string(&path) {
+            Ok(contents) => {
+                toml::from_str(&contents).map_err(|e| {
+                                             Error::ArgumentError(format!("Invalid {}: {}",
+                                                                          path.display(),
+                                                                          e))
+                                         })?
+            }
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        debug!("Loaded Studio options from {}", path.display());
+
+        let mut opts = Self::default();
+        for mount in &studiorc.mount {
+            opts.add_mount(mount, src_dir)?;
+        }
+        for var in &studiorc.env {
+            opts.add_env(var)?;
+        }
+        for port in &studiorc.port {
+            opts.add_port(port)?;
+        }
+        Ok(opts)
+    }
+
+    /// Take our flags out of the options preceding the subcommand, leaving the subcommand and
+    /// its arguments untouched.
+    fn extract_flags(&mut self, args: &[OsString], src_dir: &Path) -> Result<Vec<OsString>> {
+        let mut rest = Vec::with_capacity(args.len());
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let arg_str = arg.to_string_lossy();
+            let (flag, inline_value) = match arg_str.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+                _ => (arg_str.as_ref(), None),
+            };
+            match flag {
+                MOUNT_FLAG | ENV_FLAG | PORT_FLAG => {
+                    let value = match inline_value {
+                        Some(value) => value.to_string(),
+                        None => {
+                            iter.next()
+                                .map(|v| v.to_string_lossy().into_owned())
+                                .ok_or_else(|| {
+                                    Error::ArgumentError(format!("{} requires a value", flag))
+                                })?
+                        }
+                    };
+                    match flag {
+                        MOUNT_FLAG => self.add_mount(&value, src_dir)?,
+                        ENV_FLAG => self.add_env(&value)?,
+                        _ => self.add_port(&value)?,
+                    }
+                }
+                "--" => {
+                    rest.push(arg.clone());
+                    rest.extend(iter.cloned());
+                    break;
+                }
+                _ if takes_value(flag) => {
+                    rest.push(arg.clone());
+                    rest.extend(iter.next().cloned());
+                }
+                _ if flag.starts_with('-') => rest.push(arg.clone()),
+                _ => {
+                    rest.push(arg.clone());
+                    rest.extend(iter.cloned());
+                    break;
+                }
+            }
+        }
+        Ok(rest)
+    }
+
+    fn add_mount(&mut self, mount: &str, src_dir: &Path) -> Result<()> {
+        // Split on the last colon so a Windows drive letter stays with the source
+        let (src, dst) = mount.rsplit_once(':')
+                              .filter(|(src, dst)| !src.is_empty() && dst.starts_with('/'))
+                              .ok_or_else(|| {
+                                  Error::ArgumentError(format!("Invalid mount '{}': expected \
+                                                                <SRC>:<DEST> with an absolute \
+                                                                DEST",
+                                                               mount))
+                              })?;
+        let src = src_dir.join(src);
+        if !src.exists() {
+            let message = format!("Invalid mount '{}': {} does not exist",
+                                  mount,
+                                  src.display());
+            return Err(Error::ArgumentError(message));
+        }
+        self.mounts.push(Mount { src,
+                                 dst: dst.to_string() });
+        Ok(())
+    }
+
+    fn add_env(&mut self, var: &str) -> Result<()> {
+        let (name, value) = match var.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (var, None),
+        };
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            let message = format!("Invalid environment variable '{}'", var);
+            return Err(Error::ArgumentError(message));
+        }
+        self.env.push(EnvPassthrough { name: name.to_string(),
+                                       value });
+        Ok(())
+    }
+
+    fn add_port(&mut self, port: &str) -> Result<()> {
+        let invalid =
+            || Error::ArgumentError(format!("Invalid port '{}': expected <HOST>[:<STUDIO>]", port));
+        let (host, studio) = port.split_once(':').unwrap_or((port, port));
+        self.ports
+            .push(PortForward { host:   host.parse().map_err(|_| invalid())?,
+                                studio: studio.parse().map_err(|_| invalid())?, });
+        Ok(())
+    }
+
+    /// The names of the variables to pass into the Studio, after setting any given values in our
+    /// own environment.
+    pub fn prepare_env_vars(&self) -> Vec<String> {
+        for var in &self.env {
+            if let Some(ref value) = var.value {
+                env::set_var(&var.name, value);
+            }
+        }
+        self.env.iter().map(|var| var.name.clone()).collect()
+    }
+
+    /// Hand the options to a native Studio through its environment. A native Studio shares the
+    /// host's network, so ports need no forwarding.
+    pub fn export_to_native_studio(&self) {
+        for name in self.prepare_env_vars() {
+            if let Ok(value) = env::var(&name) {
+                env::set_var(format!("{}{}", STUDIO_SECRET_PREFIX, name), value);
+            } else {
+                warn!("Not passing {} into the Studio as it is not set", name);
+            }
+        }
+        if !self.mounts.is_empty() {
+            let mounts = self.mounts
+                             .iter()
+                             .map(|m| format!("{}:{}", m.src.display(), m.dst))
+                             .collect::<Vec<_>>()
+                             .join("\n");
+            debug!("Setting {}={:?}", STUDIO_MOUNTS_ENVVAR, mounts);
+            env::set_var(STUDIO_MOUNTS_ENVVAR, mounts);
+        }
+    }
+}
+
+/// Whether `arg` is a cluster of short options ending in one that takes the next argument as its
+/// value, such as `-s` or `-vr`.
+fn takes_value(arg: &str) -> bool {
+    if arg.starts_with("--") || !arg.starts_with('-') {
+        return false;
+    }
+    let opts = &arg[1..];
+    match opts.char_indices()
+              .find(|(_, c)| OPTS_WITH_VALUE.contains(*c))
+    {
+        Some((i, _)) => i == opts.len() - 1,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn os_args(args: &[&str]) -> Vec<OsString> { args.iter().map(OsString::from).collect() }
+
+    #[test]
+    fn extract_takes_flags_before_the_subcommand() {
+        let src_dir = TempDir::new().unwrap();
+        fs::create_dir(src_dir.path().join("lib")).unwrap();
+
+        let (opts, rest) = StudioOptions::extract(&os_args(&["-v",
+                                                             "--mount",
+                                                             "lib:/src/lib",
+                                                             "-s",
+                                                             "enter",
+                                                             "--env=FOO=bar",
+                                                             "--port",
+                                                             "8080:80",
+                                                             "run",
+                                                             "echo",
+                                                             "--port",
+                                                             "9631"]),
+                                                  src_dir.path()).unwrap();
+
+        assert_eq!(rest,
+                   os_args(&["-v", "-s", "enter", "run", "echo", "--port", "9631"]));
+        assert_eq!(opts.mounts,
+                   vec![Mount { src: src_dir.path().join("lib"),
+                                dst: "/src/lib".to_string(), }]);
+        assert_eq!(opts.env,
+                   vec![EnvPassthrough { name:  "FOO".to_string(),
+                                         value: Some("bar".to_string()), }]);
+        assert_eq!(opts.ports,
+                   vec![PortForward { host:   8080,
+                                      studio: 80, }]);
+    }
+
+    #[test]
+    fn extract_adds_flags_to_the_studiorc() {
+        let src_dir = TempDir::new().unwrap();
+        fs::write(src_dir.path().join(STUDIORC_TOML),
+                  "env = [\"AWS_PROFILE\"]\nport = [\"9631\"]\n").unwrap();
+
+        let (opts, rest) =
+            StudioOptions::extract(&os_args(&["--env", "HOME", "enter"]), src_dir.path()).unwrap();
+
+        assert_eq!(rest, os_args(&["enter"]));
+        assert_eq!(opts.env.iter().map(|v| v.name.as_str()).collect::<Vec<_>>(),
+                   vec!["AWS_PROFILE", "HOME"]);
+        assert_eq!(opts.ports,
+                   vec![PortForward { host:   9631,
+                                      studio: 9631, }]);
+    }
+
+    #[test]
+    fn extract_rejects_invalid_options() {
+        let src_dir = TempDir::new().unwrap();
+        for args in &[&["--mount", "/tmp"][..],
+                      &["--mount", "/tmp:relative"],
+                      &["--mount", "does-not-exist:/src/x"],
+                      &["--port", "http"],
+                      &["--env", "=value"],
+                      &["--env"]]
+        {
+            assert!(StudioOptions::extract(&os_args(args), src_dir.path()).is_err(),
+                    "{:?} should be rejected",
+                    args);
+        }
+    }
+
+    #[test]
+    fn extract_rejects_unknown_studiorc_keys() {
+        let src_dir = TempDir::new().unwrap();
+        fs::write(src_dir.path().join(STUDIORC_TOML), "volumes = []\n").unwrap();
+
+        assert!(StudioOptions::extract(&[], src_dir.path()).is_err());
+    }
+
+    #[test]
+    fn short_options_with_values() {
+        assert!(takes_value("-s"));
+        assert!(takes_value("-vr"));
+        assert!(!takes_value("-s/src"));
+        assert!(!takes_value("-v"));
+        assert!(!takes_value("--mount"));
+        assert!(!takes_value("enter"));
+    }
+}
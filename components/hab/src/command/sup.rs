@@ -1,3 +1,7 @@
+#[cfg(not(target_os = "macos"))]
+pub mod cluster;
+pub mod config;
+
 use crate::{common::ui::UI,
             error::{Error,
                     Result},
@@ -8,6 +12,7 @@ use crate::{common::ui::UI,
                     os::process,
                     package::PackageIdent},
             VERSION};
+use habitat_sup_protocol::RING_NAME_ENVVAR;
 use std::{ffi::OsString,
           path::PathBuf,
           str::FromStr};
@@ -30,9 +35,17 @@ pub async fn start(ui: &mut UI, args: &[OsString]) -> Result<()> {
         }
     };
     if let Some(cmd) = find_command(&command) {
-        process::become_command(cmd, args)?;
+        process::become_command_with_env(cmd, args, &ring_env())?;
         Ok(())
     } else {
         Err(Error::ExecCommandNotFound(command))
     }
 }
+
+/// The environment which passes a ring selected with `hab --ring` on to the `hab-sup` or
+/// `hab-launch` binary.
+pub fn ring_env() -> Vec<(&'static str, String)> {
+    habitat_sup_protocol::ring_name().map(|ring_name| (RING_NAME_ENVVAR, ring_name))
+                                     .into_iter()
+                                     .collect()
+}
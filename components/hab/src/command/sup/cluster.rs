@@ -0,0 +1,242 @@
+//! Runs a ring of Supervisors on one machine for local development.
+//!
+//! Every member is a `hab sup run` child process in a named ring of its own, so the members keep
+//! their state in separate directories and listen on separate ports. All members but the first
+//! are given the first member as a peer. When this command is interrupted, or a member exits,
+//! every member is terminated with `hab sup term`.
+
+use crate::{cli::hab::sup::SupCluster,
+            common::ui::{Glyph,
+                         Status,
+                         UIWriter,
+                         UI},
+            error::{Error,
+                    Result}};
+use habitat_common::types::{GossipListenAddr,
+                            HttpListenAddr,
+                            ResolvedListenCtlAddr};
+use habitat_sup_protocol::{RING_NAME_ENVVAR,
+                           STATE_PATH_PREFIX};
+use std::{collections::HashMap,
+          env,
+          ffi::OsString,
+          fs,
+          net::{Ipv4Addr,
+                SocketAddr},
+          path::{Path,
+                 PathBuf},
+          process::Stdio,
+          time::Duration};
+use tokio::{io::{AsyncBufReadExt,
+                 AsyncRead,
+                 BufReader},
+            process::{Child,
+                      Command},
+            signal,
+            time};
+
+/// How often the members are checked for having exited on their own
+const EXIT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A Supervisor of the cluster
+#[derive(Debug, PartialEq, Eq)]
+struct Member {
+    ring_name: String,
+    gossip:    u16,
+    http:      u16,
+    ctl:       u16,
+}
+
+impl Member {
+    fn new(ring_name: String) -> Self {
+        Member { gossip: GossipListenAddr::default().for_ring(&ring_name).port(),
+                 http: HttpListenAddr::default().for_ring(&ring_name).port(),
+                 ctl: ResolvedListenCtlAddr::default().for_ring(&ring_name)
+                                                      .addr()
+                                                      .port(),
+                 ring_name }
+    }
+
+    /// The address other members gossip with this member on
+    fn peer(&self) -> SocketAddr { SocketAddr::from((Ipv4Addr::LOCALHOST, self.gossip)) }
+
+    fn state_path(&self) -> PathBuf { STATE_PATH_PREFIX.join(&self.ring_name) }
+}
+
+pub async fn start(ui: &mut UI, cluster: SupCluster) -> Result<()> {
+    let members = members(&cluster.name, cluster.members)?;
+    let hab = env::current_exe()?;
+
+    ui.begin(format!("Starting a ring of {} Supervisors", members.len()))?;
+    let mut children = Vec::with_capacity(members.len());
+    for member in &members {
+        let mut command = Command::new(&hab);
+        command.args(run_args(member, &members[0], &cluster.sup_run_args))
+               .stdout(Stdio::piped())
+               .stderr(Stdio::piped());
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                terminate(ui, &hab, &members[..children.len()], children).await?;
+                return Err(e.into());
+            }
+        };
+        if let Some(stdout) = child.stdout.take() {
+            forward_output(member.ring_name.clone(), stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_output(member.ring_name.clone(), stderr);
+        }
+        ui.status(Status::Custom(Glyph::CheckMark, String::from("Started")),
+                  format!("{} (gossip {}, http {}, ctl {})",
+                          member.ring_name, member.gossip, member.http, member.ctl))?;
+        children.push(child);
+    }
+    ui.end(format!("Started the ring. Select a member from other commands with `hab --ring \
+                    {}-<N>`, and press Ctrl-C to terminate the ring.",
+                   cluster.name))?;
+
+    wait_for_interrupt_or_exit(ui, &members, &mut children).await?;
+    terminate(ui, &hab, &members, children).await?;
+
+    if cluster.purge {
+        for member in &members {
+            let path = member.state_path();
+            if path.is_dir() {
+                ui.status(Status::Deleting, path.display())?;
+                fs::remove_dir_all(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The members of the cluster `name`, which must all listen on different ports
+fn members(name: &str, count: u16) -> Result<Vec<Member>> {
+    if count == 0 {
+        return Err(Error::ArgumentError(String::from("A cluster needs at least one member")));
+    }
+    let mut ring_names_by_port = HashMap::new();
+    let mut members = Vec::with_capacity(count.into());
+    for n in 0..count {
+        let member = Member::new(format!("{}-{}", name, n));
+        if let Some(other) = ring_names_by_port.insert(member.gossip, member.ring_name.clone()) {
+            return Err(Error::ArgumentError(format!("The members {} and {} \
+                                                     would listen on the same \
+                                                     ports. Choose another \
+                                                     --name or fewer --members.",
+                                                    other, member.ring_name)));
+        }
+        members.push(member);
+    }
+    Ok(members)
+}
+
+/// The `hab` arguments that start `member`, peering it with `first` unless it is the first
+fn run_args(member: &Member, first: &Member, sup_run_args: &[String]) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec!["sup".into(),
+                                       "run".into(),
+                                       "--ring-name".into(),
+                                       member.ring_name.clone().into()];
+    if member != first {
+        args.push("--peer".into());
+        args.push(first.peer().to_string().into());
+    }
+    args.extend(sup_run_args.iter().map(OsString::from));
+    args
+}
+
+/// Print the output of a member, each line prefixed with its ring name
+fn forward_output<R>(ring_name: String, output: R)
+    where R: AsyncRead + Unpin + Send + 'static
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(output).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            println!("[{}] {}", ring_name, line);
+        }
+    });
+}
+
+async fn wait_for_interrupt_or_exit(ui: &mut UI,
+                                    members: &[Member],
+                                    children: &mut [Child])
+                                    -> Result<()> {
+    let ctrl_c = signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        tokio::select! {
+            result = &mut ctrl_c => return result.map_err(Error::from),
+            _ = time::sleep(EXIT_POLL_INTERVAL) => {}
+        }
+        for (member, child) in members.iter().zip(children.iter_mut()) {
+            if let Some(status) = child.try_wait()? {
+                ui.warn(format!("{} exited with {}, terminating the ring",
+                                member.ring_name, status))?;
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Terminate the members with `hab sup term` and wait for them to exit
+async fn terminate(ui: &mut UI,
+                   hab: &Path,
+                   members: &[Member],
+                   children: Vec<Child>)
+                   -> Result<()> {
+    for (member, mut child) in members.iter().zip(children) {
+        if child.try_wait()?.is_none() {
+            ui.status(Status::Custom(Glyph::BoxedX, String::from("Terminating")),
+                      &member.ring_name)?;
+            // A member that is shutting down already, because it was interrupted as well, has
+            // nothing left to terminate, so the outcome of the command does not matter.
+            let _ = Command::new(hab).args(&["sup", "term"])
+                                     .env(RING_NAME_ENVVAR, &member.ring_name)
+                                     .stdout(Stdio::null())
+                                     .stderr(Stdio::null())
+                                     .status()
+                                     .await;
+        }
+        child.wait().await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn members_have_their_own_ring_and_ports() {
+        let members = members("dev", 3).unwrap();
+        assert_eq!(members.iter()
+                          .map(|m| m.ring_name.as_str())
+                          .collect::<Vec<_>>(),
+                   vec!["dev-0", "dev-1", "dev-2"]);
+        assert_ne!(members[0].gossip, members[1].gossip);
+        assert_ne!(members[0].gossip, GossipListenAddr::DEFAULT_PORT);
+        assert!(members("dev", 0).is_err());
+    }
+
+    #[test]
+    fn members_peer_with_the_first_member() {
+        let members = members("dev", 2).unwrap();
+        let extra = vec![String::from("core/redis")];
+        let args = |member| {
+            run_args(member, &members[0], &extra).into_iter()
+                                                 .map(|arg| arg.into_string().unwrap())
+                                                 .collect::<Vec<_>>()
+        };
+        assert_eq!(args(&members[0]),
+                   vec!["sup", "run", "--ring-name", "dev-0", "core/redis"]);
+        assert_eq!(args(&members[1]),
+                   vec![String::from("sup"),
+                        String::from("run"),
+                        String::from("--ring-name"),
+                        String::from("dev-1"),
+                        String::from("--peer"),
+                        format!("127.0.0.1:{}", members[0].gossip),
+                        String::from("core/redis")]);
+    }
+}
@@ -0,0 +1,297 @@
+use crate::{cli::hab::sup::{sup_default_config_file,
+                            SupConfigShow,
+                            SupRun},
+            error::{Error,
+                    Result}};
+use std::{collections::HashMap,
+          env,
+          fmt,
+          fs,
+          io::{self,
+               Write},
+          iter,
+          path::PathBuf};
+use structopt::{clap::ArgMatches,
+                StructOpt};
+use tabwriter::TabWriter;
+
+/// Arguments added by configopt which are not part of the Supervisor's configuration
+const IGNORED_ARGS: &[&str] = &["CONFIG_FILES", "GENERATE_CONFIG", "help"];
+
+/// Settings whose values are secrets, which are only shown to be set
+const SECRET_SETTINGS: &[&str] = &["ring_key", "event_stream_token", "peer_consul_token"];
+
+const REDACTED: &str = "<redacted>";
+
+/// Where the effective value of a Supervisor setting came from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    CommandLine,
+    Environment(String),
+    ConfigFile(PathBuf),
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::CommandLine => write!(f, "command line"),
+            Source::Environment(var) => write!(f, "environment ({})", var),
+            Source::ConfigFile(path) => write!(f, "config file ({})", path.display()),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// A single Supervisor setting along with its provenance
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Setting {
+    pub name:    String,
+    pub value:   Option<String>,
+    pub default: Option<String>,
+    pub source:  Option<Source>,
+}
+
+impl Setting {
+    pub fn differs_from_default(&self) -> bool { self.value != self.default }
+}
+
+pub fn start(show: SupConfigShow) -> Result<()> {
+    if !show.effective {
+        for path in config_files(&sup_run_matches(&show.sup_run_args)?) {
+            if path.is_file() {
+                println!("# {}", path.display());
+                println!("{}", fs::read_to_string(&path)?);
+            }
+        }
+        return Ok(());
+    }
+
+    let settings = effective_settings(&show.sup_run_args)?;
+    let mut tw = TabWriter::new(io::stdout());
+    if show.diff {
+        writeln!(&mut tw, "setting\tdefault\teffective\tsource")?;
+        for setting in settings.iter().filter(|s| s.differs_from_default()) {
+            writeln!(&mut tw,
+                     "{}\t{}\t{}\t{}",
+                     setting.name,
+                     display_value(&setting.default),
+                     display_value(&setting.value),
+                     display_source(&setting.source))?;
+        }
+    } else {
+        writeln!(&mut tw, "setting\tvalue\tsource")?;
+        for setting in &settings {
+            writeln!(&mut tw,
+                     "{}\t{}\t{}",
+                     setting.name,
+                     display_value(&setting.value),
+                     display_source(&setting.source))?;
+        }
+    }
+    tw.flush()?;
+    Ok(())
+}
+
+/// Merge the built-in defaults, config files, environment variables and `hab sup run` arguments
+/// into the set of settings the Supervisor would use, recording where each value came from.
+pub fn effective_settings(sup_run_args: &[String]) -> Result<Vec<Setting>> {
+    let matches = sup_run_matches(sup_run_args)?;
+    let file_values = config_file_values(&config_files(&matches))?;
+
+    let app = SupRun::clap();
+    let mut settings = Vec::new();
+    for flag in &app.p.flags {
+        let name = flag.b.name;
+        if IGNORED_ARGS.contains(&name) || name == "version" {
+            continue;
+        }
+        let (value, source) = if matches.occurrences_of(name) > 0 {
+            (Some(String::from("true")), Some(Source::CommandLine))
+        } else if let Some((path, value)) = file_values.get(&setting_key(name)) {
+            (Some(toml_value_to_string(value)), Some(Source::ConfigFile(path.clone())))
+        } else {
+            (Some(String::from("false")), Some(Source::Default))
+        };
+        settings.push(Setting { name: setting_key(name),
+                                value,
+                                default: Some(String::from("false")),
+                                source });
+    }
+    let valued = app.p
+                    .opts
+                    .iter()
+                    .map(|o| (o.b.name, &o.v))
+                    .chain(app.p.positionals.values().map(|p| (p.b.name, &p.v)));
+    for (name, v) in valued {
+        if IGNORED_ARGS.contains(&name) {
+            continue;
+        }
+        let default = v.default_val
+                       .map(|default| default.to_string_lossy().to_string());
+        let env_var = v.env
+                       .as_ref()
+                       .map(|(var, _)| var.to_string_lossy().to_string())
+                       .filter(|var| env::var_os(var).is_some());
+        let (value, source) = if matches.occurrences_of(name) > 0 {
+            (matches_value(&matches, name), Some(Source::CommandLine))
+        } else if let Some(var) = env_var {
+            (matches_value(&matches, name), Some(Source::Environment(var)))
+        } else if let Some((path, value)) = file_values.get(&setting_key(name)) {
+            (Some(toml_value_to_string(value)), Some(Source::ConfigFile(path.clone())))
+        } else if default.is_some() {
+            (default.clone(), Some(Source::Default))
+        } else {
+            (None, None)
+        };
+        let value = if SECRET_SETTINGS.contains(&setting_key(name).as_str()) {
+            value.map(|_| String::from(REDACTED))
+        } else {
+            value
+        };
+        settings.push(Setting { name: setting_key(name),
+                                value,
+                                default,
+                                source });
+    }
+    settings.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(settings)
+}
+
+fn sup_run_matches(sup_run_args: &[String]) -> Result<ArgMatches<'static>> {
+    let args = iter::once(String::from("run")).chain(sup_run_args.iter().cloned());
+    SupRun::clap().get_matches_from_safe(args)
+                  .map_err(|e| Error::ArgumentError(e.message))
+}
+
+/// The config files read by `hab sup run`. Later files take precedence over earlier ones.
+fn config_files(matches: &ArgMatches) -> Vec<PathBuf> {
+    match matches.values_of("CONFIG_FILES") {
+        Some(paths) => paths.map(PathBuf::from).collect(),
+        None => vec![sup_default_config_file(matches.value_of("RING_NAME"))],
+    }
+}
+
+fn config_file_values(paths: &[PathBuf]) -> Result<HashMap<String, (PathBuf, toml::Value)>> {
+    let mut values = HashMap::new();
+    for path in paths.iter().filter(|p| p.is_file()) {
+        let table: toml::value::Table = toml::from_str(&fs::read_to_string(path)?)?;
+        for (key, value) in table {
+            values.insert(key, (path.clone(), value));
+        }
+    }
+    Ok(values)
+}
+
+/// Config file keys are the snake case form of the argument names.
+fn setting_key(arg_name: &str) -> String { arg_name.to_lowercase() }
+
+fn matches_value(matches: &ArgMatches, name: &str) -> Option<String> {
+    matches.values_of(name)
+           .map(|values| values.collect::<Vec<_>>().join(", "))
+}
+
+fn toml_value_to_string(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Array(values) => {
+            values.iter()
+                  .map(toml_value_to_string)
+                  .collect::<Vec<_>>()
+                  .join(", ")
+        }
+        value => value.to_string(),
+    }
+}
+
+fn display_value(value: &Option<String>) -> &str { value.as_deref().unwrap_or("-") }
+
+fn display_source(source: &Option<Source>) -> String {
+    source.as_ref()
+          .map(ToString::to_string)
+          .unwrap_or_else(|| String::from("unset"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    fn setting<'a>(settings: &'a [Setting], name: &str) -> &'a Setting {
+        settings.iter()
+                .find(|s| s.name == name)
+                .unwrap_or_else(|| panic!("no setting named {}", name))
+    }
+
+    #[test]
+    fn command_line_arguments_take_precedence() {
+        let tmp = TempDir::new().unwrap();
+        let config_file = tmp.path().join("sup.toml");
+        let mut file = File::create(&config_file).unwrap();
+        writeln!(file, "listen_gossip = \"1.1.1.1:1111\"").unwrap();
+        writeln!(file, "organization = \"acme\"").unwrap();
+
+        let args = vec![String::from("--config-files"),
+                        config_file.to_string_lossy().to_string(),
+                        String::from("--listen-gossip"),
+                        String::from("2.2.2.2:2222"),
+                        String::from("--permanent-peer")];
+        let settings = effective_settings(&args).unwrap();
+
+        let listen_gossip = setting(&settings, "listen_gossip");
+        assert_eq!(listen_gossip.value.as_deref(), Some("2.2.2.2:2222"));
+        assert_eq!(listen_gossip.source, Some(Source::CommandLine));
+        assert!(listen_gossip.differs_from_default());
+
+        let organization = setting(&settings, "organization");
+        assert_eq!(organization.value.as_deref(), Some("acme"));
+        assert_eq!(organization.source, Some(Source::ConfigFile(config_file)));
+
+        let permanent_peer = setting(&settings, "permanent_peer");
+        assert_eq!(permanent_peer.value.as_deref(), Some("true"));
+        assert_eq!(permanent_peer.source, Some(Source::CommandLine));
+
+        let http_disable = setting(&settings, "http_disable");
+        assert_eq!(http_disable.source, Some(Source::Default));
+        assert!(!http_disable.differs_from_default());
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        let tmp = TempDir::new().unwrap();
+        let config_file = tmp.path().join("sup.toml");
+        let mut file = File::create(&config_file).unwrap();
+        writeln!(file, "peer_consul_token = \"consul-secret\"").unwrap();
+
+        let args = vec![String::from("--config-files"),
+                        config_file.to_string_lossy().to_string(),
+                        String::from("--ring-key"),
+                        String::from("SYM-SEC-1\nfoo-20181113185935\n\nsecret"),
+                        String::from("--event-stream-token"),
+                        String::from("stream-secret")];
+        let settings = effective_settings(&args).unwrap();
+
+        for name in SECRET_SETTINGS {
+            let secret = setting(&settings, name);
+            assert_eq!(secret.value.as_deref(), Some(REDACTED));
+            assert!(secret.differs_from_default());
+        }
+        assert_eq!(setting(&settings, "peer_consul_token").source,
+                   Some(Source::ConfigFile(config_file)));
+    }
+
+    #[test]
+    fn unset_settings_have_no_source() {
+        let tmp = TempDir::new().unwrap();
+        let config_file = tmp.path().join("missing.toml");
+        let args = vec![String::from("--config-files"),
+                        config_file.to_string_lossy().to_string()];
+        let settings = effective_settings(&args).unwrap();
+
+        let ring = setting(&settings, "ring");
+        assert_eq!(ring.value, None);
+        assert_eq!(ring.source, None);
+        assert!(!ring.differs_from_default());
+    }
+}
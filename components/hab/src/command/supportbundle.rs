@@ -1,6 +1,8 @@
-use crate::{common::ui::{Status,
-                         UIWriter,
-                         UI},
+use crate::{common::{redaction::{self,
+                                 Redactor},
+                     ui::{Status,
+                          UIWriter,
+                          UI}},
             error::{Error,
                     Result},
             hcore::{fs::FS_ROOT_PATH,
@@ -8,9 +10,13 @@ use crate::{common::ui::{Status,
 use chrono::Local;
 use flate2::{write::GzEncoder,
              Compression};
+use serde_json::Value as Json;
 use std::{env,
+          ffi::OsStr,
           fs::{self,
                File},
+          io::{self,
+               Write},
           path::{Path,
                  MAIN_SEPARATOR},
           process};
@@ -49,7 +55,9 @@ pub fn start(ui: &mut UI) -> Result<()> {
     if sup_root.exists() {
         ui.status(Status::Adding,
                   format!("files from {}", &sup_root.display()))?;
-        if let Err(why) = tar.append_dir_all(format!("hab{}sup", MAIN_SEPARATOR), &sup_root) {
+        let name = Path::new("hab").join("sup");
+        let redactor = Redactor::default();
+        if let Err(why) = append_redacted_dir(&mut tar, &name, &sup_root, &redactor, ui) {
             ui.fatal(format!("Failed to add all files into the tarball: {}", why))?;
             fs::remove_file(&tarball_name)?;
             process::exit(1);
@@ -65,3 +73,77 @@ pub fn start(ui: &mut UI) -> Result<()> {
 
     Ok(())
 }
+
+/// Add the files of `dir` and of its subdirectories to the bundle under `name`, with sensitive
+/// values redacted from them. Files that are not text, such as rumor stores, are left out, as
+/// nothing can be redacted from them. The state directory of a Supervisor is redacted following
+/// its own redaction configuration, if it has one.
+fn append_redacted_dir<W: Write>(tar: &mut tar::Builder<W>,
+                                 name: &Path,
+                                 dir: &Path,
+                                 redactor: &Redactor,
+                                 ui: &mut UI)
+                                 -> Result<()> {
+    let config_file = redaction::config_file(dir);
+    let own_redactor;
+    let redactor = if config_file.is_file() {
+        own_redactor = Redactor::from_file(&config_file)?;
+        &own_redactor
+    } else {
+        redactor
+    };
+    tar.append_dir(name, dir)?;
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(fs::DirEntry::file_name);
+    for entry in entries {
+        let path = entry.path();
+        let entry_name = name.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            append_redacted_dir(tar, &entry_name, &path, redactor, ui)?;
+        } else if file_type.is_file() {
+            match redacted_contents(&path, redactor)? {
+                Some(contents) => {
+                    let mut header = tar::Header::new_gnu();
+                    header.set_metadata(&entry.metadata()?);
+                    header.set_size(contents.len() as u64);
+                    tar.append_data(&mut header, &entry_name, contents.as_bytes())?;
+                }
+                None => ui.warn(format!("Leaving out {}, which is not text", path.display()))?,
+            }
+        } else {
+            // Symlinks are added as they are, without following them
+            tar.append_path_with_name(&path, &entry_name)?;
+        }
+    }
+    Ok(())
+}
+
+/// The contents of the file at `path` with sensitive values redacted, or `None` if it is not text
+fn redacted_contents(path: &Path, redactor: &Redactor) -> Result<Option<String>> {
+    let contents = match String::from_utf8(fs::read(path)?) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(None),
+    };
+    let structured = match path.extension().and_then(OsStr::to_str) {
+        Some("json") => {
+            serde_json::from_str::<Json>(&contents).ok()
+                                                   .and_then(|mut json| {
+                                                       redactor.redact_json(&mut json);
+                                                       serde_json::to_string(&json).ok()
+                                                   })
+        }
+        Some("toml") | Some("spec") => {
+            toml::from_str::<toml::Value>(&contents).ok()
+                                                    .and_then(|mut toml| {
+                                                        redactor.redact_toml(&mut toml);
+                                                        toml::to_string(&toml).ok()
+                                                    })
+        }
+        _ => None,
+    };
+    // Files that don't parse are redacted as text
+    Ok(Some(structured.unwrap_or_else(|| {
+                          redactor.redact_text(&contents).into_owned()
+                      })))
+}
@@ -1,11 +1,13 @@
 use crate::{api_client,
             common,
             hcore,
-            protocol::net,
+            protocol::net::{self,
+                            ErrCode},
             sup_client::SrvClientError};
 use habitat_common::{cli_config,
                      error::DEFAULT_ERROR_EXIT_CODE};
 use habitat_core::package::PackageIdent;
+use reqwest::StatusCode;
 use std::{collections::HashMap,
           env,
           error,
@@ -24,6 +26,7 @@ pub type Result<T> = result::Result<T, Error>;
 pub enum Error {
     APIClient(api_client::Error),
     ArgumentError(String),
+    BinlinkConflict(String, PathBuf, String),
     ButterflyError(String),
     CacheSslCertError(String),
     CannotParseBinlinkBinaryName(PathBuf),
@@ -43,6 +46,7 @@ pub enum Error {
     DockerNetworkDown(String),
     EnvJoinPathsError(env::JoinPathsError),
     ErrorPerIdent(HashMap<PackageIdent, Error>),
+    ExecCommandFailed(PathBuf, Option<i32>),
     ExecCommandNotFound(PathBuf),
     FFINulError(ffi::NulError),
     FileNotFound(String),
@@ -61,11 +65,13 @@ pub enum Error {
     NameLookup,
     NetErr(net::NetErr),
     PackageArchiveMalformed(String),
+    PackageSetInstall(String),
     PackageSetParseError(String),
     ParseIntError(num::ParseIntError),
     ParseUrlError(url::ParseError),
     PathPrefixError(path::StripPrefixError),
     ProvidesError(String),
+    ReleaseNotHealthy(String, String, u64),
     RootRequired,
     ScheduleStatus(api_client::Error),
     SubcommandNotSupported(String),
@@ -82,6 +88,13 @@ impl fmt::Display for Error {
         let msg = match *self {
             Error::APIClient(ref e) => e.to_string(),
             Error::ArgumentError(ref e) => e.to_string(),
+            Error::BinlinkConflict(ref b, ref l, ref o) => {
+                format!("Cannot binlink {} because {} is already binlinked from {}. Use --force \
+                         to overwrite it or --pin to choose a package.",
+                        b,
+                        l.display(),
+                        o)
+            }
             Error::ButterflyError(ref e) => e.to_string(),
             Error::CacheSslCertError(ref e) => format!("Cannot cache SSL_CERT_FILE: {}", e),
             Error::CannotParseBinlinkBinaryName(ref p) => {
@@ -144,6 +157,12 @@ impl fmt::Display for Error {
                  .collect::<Vec<_>>()
                  .join("\n")
             }
+            Error::ExecCommandFailed(ref c, Some(code)) => {
+                format!("`{}' exited with code {}", c.display(), code)
+            }
+            Error::ExecCommandFailed(ref c, None) => {
+                format!("`{}' was terminated by a signal", c.display())
+            }
             Error::ExecCommandNotFound(ref c) => {
                 format!("`{}' was not found on the filesystem or in PATH",
                         c.display())
@@ -176,6 +195,11 @@ impl fmt::Display for Error {
                 format!("Package archive was unreadable or contained unexpected contents: {:?}",
                         e)
             }
+            Error::PackageSetInstall(ref file) => {
+                format!("Unable to install every package from {}; the packages installed along \
+                         the way were removed again",
+                        file)
+            }
             Error::PackageSetParseError(ref e) => {
                 format!("Package set file could not be parsed: {:?}", e)
             }
@@ -183,6 +207,11 @@ impl fmt::Display for Error {
             Error::ParseUrlError(ref err) => format!("{}", err),
             Error::PathPrefixError(ref err) => format!("{}", err),
             Error::ProvidesError(ref err) => format!("Can't find {}", err),
+            Error::ReleaseNotHealthy(ref ident, ref group, timeout) => {
+                format!("{} did not become healthy in the {} group on every Supervisor within {} \
+                         seconds, so it was not promoted",
+                        ident, group, timeout)
+            }
             Error::RootRequired => {
                 "Root or administrator permissions required to complete operation".to_string()
             }
@@ -203,11 +232,133 @@ impl fmt::Display for Error {
 
 impl error::Error for Error {}
 
+/// The classes of failures that `hab` tells apart by its exit code, so that scripts can branch on
+/// them. The codes are part of the interface of `hab` and must not change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Any failure that is not in one of the classes below
+    General,
+    /// The arguments or input of the command are invalid
+    Validation,
+    /// Authentication failed, or the user is not allowed to do what was asked
+    Auth,
+    /// A package, file, service or other resource does not exist
+    NotFound,
+    /// The Supervisor could not be reached, or the connection to it was lost
+    SupUnreachable,
+    /// The command succeeded for some of the things it acted on, and failed for others
+    PartialSuccess,
+}
+
+impl ErrorClass {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorClass::General => DEFAULT_ERROR_EXIT_CODE,
+            ErrorClass::Validation => 2,
+            ErrorClass::Auth => 3,
+            ErrorClass::NotFound => 4,
+            ErrorClass::SupUnreachable => 5,
+            ErrorClass::PartialSuccess => 6,
+        }
+    }
+
+    /// The name of the class in structured errors
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorClass::General => "general",
+            ErrorClass::Validation => "validation",
+            ErrorClass::Auth => "auth",
+            ErrorClass::NotFound => "not_found",
+            ErrorClass::SupUnreachable => "sup_unreachable",
+            ErrorClass::PartialSuccess => "partial_success",
+        }
+    }
+
+    fn of_api_client(err: &api_client::Error) -> Self {
+        match err {
+            api_client::Error::APIError(StatusCode::UNAUTHORIZED, _)
+            | api_client::Error::APIError(StatusCode::FORBIDDEN, _) => ErrorClass::Auth,
+            api_client::Error::APIError(StatusCode::NOT_FOUND, _) => ErrorClass::NotFound,
+            api_client::Error::APIError(StatusCode::BAD_REQUEST, _)
+            | api_client::Error::APIError(StatusCode::UNPROCESSABLE_ENTITY, _)
+            | api_client::Error::IdentNotFullyQualified => ErrorClass::Validation,
+            _ => ErrorClass::General,
+        }
+    }
+
+    fn of_net(err: &net::NetErr) -> Self {
+        match ErrCode::from_i32(err.code) {
+            Some(ErrCode::Unauthorized) => ErrorClass::Auth,
+            Some(ErrCode::NotFound) => ErrorClass::NotFound,
+            Some(ErrCode::BadPayload) | Some(ErrCode::InvalidPayload) => ErrorClass::Validation,
+            _ => ErrorClass::General,
+        }
+    }
+
+    fn of_core(err: &hcore::Error) -> Self {
+        match err {
+            hcore::Error::FileNotFound(_) | hcore::Error::PackageNotFound(_) => {
+                ErrorClass::NotFound
+            }
+            _ => ErrorClass::General,
+        }
+    }
+
+    fn of_common(err: &common::Error) -> Self {
+        match err {
+            common::Error::APIClient(e) => Self::of_api_client(e),
+            common::Error::HabitatCore(e) => Self::of_core(e),
+            common::Error::ChannelNotFound
+            | common::Error::FileNotFound(_)
+            | common::Error::OfflineArtifactNotFound(_)
+            | common::Error::OfflineOriginKeyNotFound(_)
+            | common::Error::OfflinePackageNotFound(_)
+            | common::Error::PackageNotFound(_) => ErrorClass::NotFound,
+            _ => ErrorClass::General,
+        }
+    }
+}
+
 impl Error {
     pub fn exit_code(&self) -> i32 {
         match self {
-            Self::HabitatCommon(e) => e.exit_code(),
-            _ => DEFAULT_ERROR_EXIT_CODE,
+            // Failed hooks and commands pass their own exit code on
+            Self::HabitatCommon(e @ common::Error::HookFailed { .. }) => e.exit_code(),
+            Self::ExecCommandFailed(_, Some(code)) => *code,
+            _ => self.class().exit_code(),
+        }
+    }
+
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Error::APIClient(e)
+            | Error::JobGroupCancel(e)
+            | Error::JobGroupPromoteOrDemote(e, _)
+            | Error::ScheduleStatus(e) => ErrorClass::of_api_client(e),
+            Error::ArgumentError(_)
+            | Error::ConfigOpt(_)
+            | Error::InvalidDnsName(_)
+            | Error::JobGroupPromoteOrDemoteUnprocessable(_)
+            | Error::KeyTypeParseError(_)
+            | Error::PackageSetParseError(_)
+            | Error::ParseIntError(_)
+            | Error::ParseUrlError(_)
+            | Error::SubcommandNotSupported(_)
+            | Error::UnsupportedExportFormat(_) => ErrorClass::Validation,
+            Error::RootRequired => ErrorClass::Auth,
+            Error::CommandNotFoundInPkg(_)
+            | Error::DockerImageNotFound(_)
+            | Error::ExecCommandNotFound(_)
+            | Error::FileNotFound(_)
+            | Error::ProvidesError(_) => ErrorClass::NotFound,
+            Error::CtlClient(SrvClientError::ConnectionRefused)
+            | Error::CtlClient(SrvClientError::ConnectionClosed)
+            | Error::CtlClient(SrvClientError::Io(_)) => ErrorClass::SupUnreachable,
+            Error::CtlClient(SrvClientError::NetErr(e)) | Error::NetErr(e) => ErrorClass::of_net(e),
+            Error::ErrorPerIdent(_) => ErrorClass::PartialSuccess,
+            Error::HabitatCommon(e) => ErrorClass::of_common(e),
+            Error::HabitatCore(e) => ErrorClass::of_core(e),
+            _ => ErrorClass::General,
         }
     }
 }
@@ -15,28 +15,39 @@ use configopt::{ConfigOpt,
 use futures::stream::StreamExt;
 use hab::{cli::{self,
                 gateway_util,
-                hab::{license::License,
+                hab::{bldr::ArtifactPromoteOnGreen,
+                      license::License,
                       origin::{Rbac,
                                RbacSet,
                                RbacShow},
                       pkg::{ExportCommand as PkgExportCommand,
-                            PkgExec},
+                            PkgExec,
+                            PkgX},
                       sup::{HabSup,
                             Secret,
-                            Sup},
+                            Sup,
+                            SupConfig,
+                            SupRun},
                       svc::{self,
                             BulkLoad as SvcBulkLoad,
                             Load as SvcLoad,
-                            Svc},
+                            Svc,
+                            SvcHistory,
+                            SvcMaintenance},
                       util::{bldr_auth_token_from_args_env_or_load,
-                             bldr_url_from_args_env_load_or_default},
+                             bldr_url_from_args_env_load_or_default,
+                             HumanDuration,
+                             Timestamp},
+                      Artifact,
+                      Bldr,
                       Hab,
                       Origin,
                       Pkg},
                 parse_optional_arg,
                 KeyType},
           command::{self,
-                    pkg::{download::{PackageSet,
+                    pkg::{binlink::ConflictPolicy,
+                          download::{PackageSet,
                                      PackageSetFile},
                           list::ListingType,
                           uninstall::UninstallHookMode}},
@@ -57,7 +68,8 @@ use habitat_common::{self as common,
                                                  InstallMode,
                                                  InstallSource,
                                                  LocalPackageUsage},
-                     types::ResolvedListenCtlAddr,
+                     types::{DenylistEntry,
+                             ResolvedListenCtlAddr},
                      ui::{self,
                           Status,
                           UIWriter,
@@ -65,7 +77,8 @@ use habitat_common::{self as common,
                      FeatureFlag};
 use habitat_core::{crypto::{init,
                             keys::{Key,
-                                   KeyCache}},
+                                   KeyCache},
+                            Blake2bHash},
                    env::{self as henv,
                          Config as _},
                    fs::{cache_artifact_path,
@@ -98,7 +111,10 @@ use std::{collections::HashMap,
           result,
           str::FromStr,
           string::ToString,
-          thread};
+          thread,
+          time::{Duration,
+                 SystemTime,
+                 UNIX_EPOCH}};
 use tabwriter::TabWriter;
 use webpki::DnsNameRef;
 
@@ -106,8 +122,28 @@ use webpki::DnsNameRef;
 const HABITAT_ORG_ENVVAR: &str = "HAB_ORG";
 /// Makes the --user CLI param optional when this env var is set
 const HABITAT_USER_ENVVAR: &str = "HAB_USER";
+/// Selects the format of errors when the --error-format option is not given
+const ERROR_FORMAT_ENVVAR: &str = "HAB_ERROR_FORMAT";
 
 lazy_static! {
+    static ref RING_STATUS_HEADER: Vec<&'static str> = {
+        vec!["service group",
+             "members",
+             "election",
+             "leader",
+             "update election",
+             "packages"]
+    };
+    static ref FILE_STATUS_HEADER: Vec<&'static str> =
+        { vec!["member", "file", "incarnation", "outcome", "error"] };
+    static ref SVC_HISTORY_HEADER: Vec<&'static str> = {
+        vec!["started",
+             "from",
+             "to",
+             "strategy",
+             "duration (s)",
+             "rollback"]
+    };
     static ref STATUS_HEADER: Vec<&'static str> = {
         vec!["package",
              "type",
@@ -124,22 +160,45 @@ async fn main() {
     env_logger::init();
     let mut ui = UI::default_with_env();
     let flags = FeatureFlag::from_env(&mut ui);
-    if let Err(e) = start(&mut ui, flags).await {
+    let hab_args: Vec<OsString> = env::args_os().collect();
+    let (error_format, result) = match ErrorFormat::from_args_or_env(&hab_args) {
+        Ok(error_format) => (error_format, start(&mut ui, flags, hab_args).await),
+        Err(e) => (ErrorFormat::Text, Err(e)),
+    };
+    if let Err(e) = result {
         let exit_code = e.exit_code();
-        ui.fatal(e).unwrap();
+        match error_format {
+            ErrorFormat::Text => ui.fatal(e).unwrap(),
+            ErrorFormat::Json => eprintln!("{}", serde_json::json!({ "error": error_json(&e) })),
+        }
         std::process::exit(exit_code)
     }
 }
 
 #[allow(clippy::cognitive_complexity)]
-async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
+async fn start(ui: &mut UI, feature_flags: FeatureFlag, hab_args: Vec<OsString>) -> Result<()> {
+    // The error format was resolved by `main` already, and may be given before the global options
+    // below
+    let hab_args = without_error_format(&hab_args);
+    let GlobalOptions { offline,
+                        ring_name,
+                        args: hab_args, } = GlobalOptions::from_args(hab_args);
+    if offline {
+        henv::set_offline();
+    }
+    if let Some(ring_name) = ring_name {
+        sup_proto::select_ring(&ring_name);
+    }
+
     // We parse arguments with configopt in a separate thread to eliminate
     // possible stack overflow crashes at runtime. OSX or a debug Windows build,
     // for instance, will crash with our large tree. This is a known issue:
     // https://github.com/kbknapp/clap-rs/issues/86
-    let child = thread::Builder::new().stack_size(8 * 1024 * 1024)
-                                      .spawn(Hab::try_from_args_with_configopt)
-                                      .unwrap();
+    let configopt_args = hab_args.clone();
+    let child =
+        thread::Builder::new().stack_size(8 * 1024 * 1024)
+                              .spawn(move || Hab::try_from_iter_with_configopt(configopt_args))
+                              .unwrap();
     let hab = child.join().unwrap();
 
     if let Ok(Hab::License(License::Accept)) = hab {
@@ -168,13 +227,13 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
     // `hab` binary and the `hab-sup` binary. Potential fixes:
     // 1. Handle all `hab sup` subcommands with the `hab-sup` binary
     // 2. Have a dedicated subcommand for commands handled by the `hab-sup` binary
-    let mut args = env::args();
-    if matches!((args.next().unwrap_or_default().as_str(),
-                 args.next().unwrap_or_default().as_str(),
-                 args.next().unwrap_or_default().as_str()),
+    let mut args = hab_args.iter().map(|arg| arg.to_string_lossy());
+    if matches!((args.next().unwrap_or_default().as_ref(),
+                 args.next().unwrap_or_default().as_ref(),
+                 args.next().unwrap_or_default().as_ref()),
                 (_, "sup", "--version") | (_, "sup", "-V"))
     {
-        return command::sup::start(ui, &args_after_first(2)).await;
+        return command::sup::start(ui, &args_after_first(&hab_args, 2)).await;
     }
 
     license::check_for_license_acceptance_and_prompt(ui)?;
@@ -195,10 +254,12 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                     }
                 }
                 #[cfg(not(target_os = "macos"))]
-                Hab::Run(sup_run) => {
+                Hab::Run(_) => {
                     ui.warn("'hab run' as an alias for 'hab sup run' is deprecated. Please \
                              update your automation and processes accordingly.")?;
-                    return command::launcher::start(ui, sup_run, &args_after_first(1)).await;
+                    let args = args_after_first(&hab_args, 1);
+                    let sup_run = SupRun::try_from_args_with_default_config(&args)?;
+                    return command::launcher::start(ui, sup_run, &args).await;
                 }
                 Hab::Studio(studio) => {
                     return command::studio::enter::start(ui, studio.args()).await;
@@ -211,20 +272,44 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                             // We need to pass the subcommand that was issued to the underlying
                             // binary. It is a bit hacky, but to do that we strip off the `hab sup`
                             // command prefix and pass the rest of the args to underlying binary.
-                            let args = args_after_first(2);
+                            let args = args_after_first(&hab_args, 2);
                             match sup {
-                                Sup::Bash | Sup::Sh | Sup::Term => {
+                                Sup::Bash
+                                | Sup::Export(_)
+                                | Sup::Fsck(_)
+                                | Sup::Rejoin
+                                | Sup::RenderContextSchema
+                                | Sup::Sh
+                                | Sup::Term => {
                                     return command::sup::start(ui, &args).await;
                                 }
-                                Sup::Run(sup_run) => {
+                                Sup::Run(_) => {
+                                    let sup_run = SupRun::try_from_args_with_default_config(&args)?;
                                     return command::launcher::start(ui, sup_run, &args).await;
                                 }
                             }
                         }
                         HabSup::Depart { member_id,
+                                         depart_self,
+                                         address,
+                                         all,
+                                         stop_services,
                                          remote_sup, } => {
+                            if depart_self {
+                                return sub_sup_depart_self(stop_services, remote_sup.inner()).await;
+                            }
+                            if let Some(address) = address {
+                                return sub_sup_depart_address(address, all, remote_sup.inner())
+                                    .await;
+                            }
+                            let member_id = member_id.expect("MEMBER_ID is required without --self");
                             return sub_sup_depart(member_id, remote_sup.inner()).await;
                         }
+                        HabSup::Deny { deny,
+                                       allow,
+                                       remote_sup, } => {
+                            return sub_sup_deny(deny, allow, remote_sup.inner()).await;
+                        }
                         HabSup::Secret(secret) => {
                             match secret {
                                 Secret::Generate => return sub_sup_secret_generate(),
@@ -236,15 +321,26 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                             }
                         }
                         HabSup::Status { pkg_ident,
+                                         detail,
                                          remote_sup, } => {
                             ui.warn("'hab sup status' as an alias for 'hab svc status' is \
                                      deprecated. Please update your automation and processes \
                                      accordingly.")?;
-                            return sub_svc_status(pkg_ident, remote_sup.inner()).await;
+                            return sub_svc_status(pkg_ident, detail, false, remote_sup.inner()).await;
                         }
                         HabSup::Restart { remote_sup } => {
                             return sub_sup_restart(remote_sup.inner()).await;
                         }
+                        HabSup::Config(SupConfig::Show(show)) => {
+                            return command::sup::config::start(show);
+                        }
+                        HabSup::Config(SupConfig::Reload { remote_sup }) => {
+                            return sub_sup_config_reload(remote_sup.inner()).await;
+                        }
+                        #[cfg(not(target_os = "macos"))]
+                        HabSup::Cluster(cluster) => {
+                            return command::sup::cluster::start(ui, cluster).await;
+                        }
                     }
                 }
                 Hab::Svc(svc) => {
@@ -260,8 +356,14 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                             return sub_svc_load(svc_load).await;
                         }
                         Svc::Update(svc_update) => return sub_svc_update(svc_update).await,
+                        Svc::Maintenance(svc_maintenance) => {
+                            return sub_svc_maintenance(svc_maintenance).await;
+                        }
+                        Svc::History(svc_history) => return sub_svc_history(svc_history).await,
                         Svc::Status(svc_status) => {
                             return sub_svc_status(svc_status.pkg_ident,
+                                                  svc_status.detail,
+                                                  svc_status.resources,
                                                   svc_status.remote_sup.inner()).await;
                         }
                         _ => {
@@ -273,8 +375,12 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                 Hab::Term => {
                     ui.warn("'hab term' as an alias for 'hab sup term' is deprecated. Please \
                              update your automation and processes accordingly.")?;
-                    return command::sup::start(ui, &args_after_first(1)).await;
+                    return command::sup::start(ui, &args_after_first(&hab_args, 1)).await;
+                }
+                Hab::Bldr(Bldr::Artifact(Artifact::PromoteOnGreen(promote))) => {
+                    return sub_bldr_artifact_promote_on_green(ui, promote).await;
                 }
+                Hab::X(x) => return sub_x(ui, x).await,
                 Hab::Pkg(pkg) => {
                     match pkg {
                         Pkg::Export(export) => {
@@ -305,9 +411,11 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                             }
                         }
                         Pkg::Exec(PkgExec { pkg_ident,
+                                            with,
                                             cmd,
                                             args, }) => {
                             return command::pkg::exec::start(&pkg_ident.pkg_ident(),
+                                                             &with,
                                                              cmd,
                                                              &args.args);
                         }
@@ -321,9 +429,8 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                 }
             }
         }
-        Err(e @ ConfigOptError::ConfigGenerated(_) | e @ ConfigOptError::ConfigFile(..)) => {
-            e.exit()
-        }
+        Err(e @ ConfigOptError::ConfigGenerated(_)) => e.exit(),
+        Err(e @ ConfigOptError::ConfigFile(..)) => return Err(e.into()),
         Err(_) => {
             // Completely ignore all other errors. They will be caught by the CLI parsing logic
             // below.
@@ -333,15 +440,23 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
     // Similar to the configopt parsing above We build the command tree in a
     // separate thread to eliminate possible stack overflow crashes at runtime.
     // See known issue:https://github.com/kbknapp/clap-rs/issues/86
-    let cli_child = thread::Builder::new().stack_size(8 * 1024 * 1024)
-                                          .spawn(move || {
-                                              cli::get(feature_flags).get_matches_safe()
-                                                                     .unwrap_or_else(|e| {
-                                                                         e.exit();
-                                                                     })
-                                          })
-                                          .unwrap();
-    let app_matches = cli_child.join().unwrap();
+    let cli_child =
+        thread::Builder::new().stack_size(8 * 1024 * 1024)
+                              .spawn(move || {
+                                  cli::get(feature_flags).get_matches_from_safe(hab_args)
+                              })
+                              .unwrap();
+    let app_matches = match cli_child.join().unwrap() {
+        Ok(app_matches) => app_matches,
+        Err(e)
+            if e.kind == ClapErrorKind::HelpDisplayed
+               || e.kind == ClapErrorKind::VersionDisplayed =>
+        {
+            e.exit()
+        }
+        // Usage errors are reported like any other, in the requested error format
+        Err(e) => return Err(ConfigOptError::Clap(e).into()),
+    };
 
     match app_matches.subcommand() {
         ("apply", Some(m)) => {
@@ -366,6 +481,7 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
         ("file", Some(m)) => {
             match m.subcommand() {
                 ("upload", Some(m)) => sub_file_put(m).await?,
+                ("status", Some(m)) => sub_file_status(m).await?,
                 _ => unreachable!(),
             }
         }
@@ -452,6 +568,7 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                 ("install", Some(m)) => sub_pkg_install(ui, m, feature_flags).await?,
                 ("list", Some(m)) => sub_pkg_list(m)?,
                 ("path", Some(m)) => sub_pkg_path(m)?,
+                ("prefetch", Some(m)) => sub_pkg_prefetch(ui, m).await?,
                 ("provides", Some(m)) => sub_pkg_provides(m)?,
                 ("search", Some(m)) => sub_pkg_search(m).await?,
                 ("sign", Some(m)) => sub_pkg_sign(ui, m)?,
@@ -484,6 +601,13 @@ async fn start(ui: &mut UI, feature_flags: FeatureFlag) -> Result<()> {
                         _ => unreachable!(),
                     }
                 }
+                ("manifest", Some(m)) => {
+                    match m.subcommand() {
+                        ("apply", Some(sc)) => sub_ring_manifest_apply(sc).await?,
+                        _ => unreachable!(),
+                    }
+                }
+                ("status", Some(m)) => sub_ring_status(m).await?,
                 _ => unreachable!(),
             }
         }
@@ -762,19 +886,46 @@ async fn sub_origin_member_role_set(ui: &mut UI, r: RbacSet) -> Result<()> {
 }
 
 fn sub_pkg_binlink(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
-    let ident = required_pkg_ident_from_input(m)?;
     let dest_dir = Path::new(required_value_of(m, "DEST_DIR"));
-    let force = m.is_present("FORCE");
+    let policy = binlink_conflict_policy_from_matches(m)?;
+    let pins = m.values_of("PIN")
+                .unwrap_or_default()
+                .map(command::pkg::binlink::parse_pin)
+                .collect::<Result<Vec<_>>>()?;
+    let unpinned = m.values_of("UNPIN").unwrap_or_default().collect::<Vec<_>>();
+    if !pins.is_empty() || !unpinned.is_empty() {
+        command::pkg::binlink::update_pins(ui, &pins, &unpinned, &FS_ROOT_PATH)?;
+    }
+    if m.is_present("SYNC") {
+        command::pkg::binlink::sync(ui, &FS_ROOT_PATH)?;
+    }
+    if m.is_present("CLEAN") {
+        command::pkg::binlink::clean(ui, dest_dir, &FS_ROOT_PATH)?;
+    }
+
+    let ident = match m.value_of("PKG_IDENT") {
+        Some(ident) => ident.parse()?,
+        None => return Ok(()),
+    };
     match m.value_of("BINARY") {
         Some(binary) => {
-            command::pkg::binlink::start(ui, &ident, binary, dest_dir, &FS_ROOT_PATH, force)
+            command::pkg::binlink::start(ui, &ident, binary, dest_dir, &FS_ROOT_PATH, policy)
         }
         None => {
-            command::pkg::binlink::binlink_all_in_pkg(ui, &ident, dest_dir, &FS_ROOT_PATH, force)
+            command::pkg::binlink::binlink_all_in_pkg(ui, &ident, dest_dir, &FS_ROOT_PATH, policy)
         }
     }
 }
 
+/// How to resolve conflicting binlinks, from `--force` and `--conflict`
+fn binlink_conflict_policy_from_matches(m: &ArgMatches<'_>) -> Result<ConflictPolicy> {
+    if m.is_present("FORCE") {
+        return Ok(ConflictPolicy::Force);
+    }
+    m.value_of("CONFLICT")
+     .map_or_else(|| Ok(ConflictPolicy::default()), str::parse)
+}
+
 /// Generate a (possibly empty) list of `Origin`s from the value of
 /// the `HAB_ORIGIN_KEYS` environment variable / `--keys` argument.
 fn hab_key_origins(m: &ArgMatches<'_>) -> Result<Vec<habitat_core::origin::Origin>> {
@@ -873,9 +1024,46 @@ async fn sub_pkg_download(ui: &mut UI,
     Ok(())
 }
 
+async fn sub_pkg_prefetch(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let token = maybe_auth_token(m);
+    let url = bldr_url_from_matches(m)?;
+    let channel = channel_from_matches_or_default(m);
+    let target = target_from_matches(m)?;
+
+    let mut package_sets = vec![PackageSet { target,
+                                             channel: channel.clone(),
+                                             idents: idents_from_matches(m)? }];
+    let mut install_sources_from_file = idents_from_file_matches(ui, m, &channel, target)?;
+    package_sets.append(&mut install_sources_from_file);
+    package_sets.retain(|set| !set.idents.is_empty());
+
+    init()?;
+
+    command::pkg::prefetch::start(ui,
+                                  &url,
+                                  PRODUCT,
+                                  VERSION,
+                                  &package_sets,
+                                  &FS_ROOT_PATH,
+                                  token.as_deref(),
+                                  ignore_missing_seeds_from_matches(m)).await
+}
+
 fn sub_pkg_env(m: &ArgMatches<'_>) -> Result<()> {
-    let ident = required_pkg_ident_from_input(m)?;
-    command::pkg::env::start(&ident, &*FS_ROOT_PATH)
+    let idents = m.values_of("PKG_IDENT")
+                  .unwrap_or_default()
+                  .map(str::parse)
+                  .collect::<std::result::Result<Vec<PackageIdent>, _>>()?;
+    if m.is_present("MERGED") {
+        return command::pkg::env::start_merged(&idents, &*FS_ROOT_PATH);
+    }
+    match idents.as_slice() {
+        [ident] => command::pkg::env::start(ident, &*FS_ROOT_PATH),
+        _ => {
+            let msg = "Use --merged to print the runtime environment of more than one package";
+            Err(Error::ArgumentError(msg.to_string()))
+        }
+    }
 }
 
 fn sub_pkg_hash(m: &ArgMatches<'_>) -> Result<()> {
@@ -927,6 +1115,17 @@ async fn sub_pkg_uninstall(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
                                    uninstall_hook_mode).await
 }
 
+async fn sub_bldr_artifact_promote_on_green(ui: &mut UI,
+                                            promote: ArtifactPromoteOnGreen)
+                                            -> Result<()> {
+    let bldr_url = bldr_url_from_args_env_load_or_default(promote.shared_load.bldr_url.clone())?;
+    let auth_token = bldr_auth_token_from_args_env_or_load(promote.auth_token.value.clone())?;
+    command::bldr::artifact::promote_on_green::start(ui,
+                                                     bldr_url.as_str(),
+                                                     &auth_token,
+                                                     promote).await
+}
+
 async fn sub_bldr_channel_create(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let url = bldr_url_from_matches(m)?;
     let origin = origin_param_or_env(m)?;
@@ -1081,7 +1280,6 @@ async fn sub_pkg_install(ui: &mut UI,
                          -> Result<()> {
     let url = bldr_url_from_matches(m)?;
     let channel = channel_from_matches_or_default(m);
-    let install_sources = install_sources_from_matches(m)?;
     let token = maybe_auth_token(m);
     let install_mode =
         if feature_flags.contains(FeatureFlag::OFFLINE_INSTALL) && m.is_present("OFFLINE") {
@@ -1105,6 +1303,42 @@ async fn sub_pkg_install(ui: &mut UI,
 
     init()?;
 
+    if let Some(file) = m.value_of("PKG_IDENT_FILE") {
+        let package_sets =
+            idents_from_file_matches(ui, m, &channel, PackageTarget::active_target())?;
+        let artifact_cache_path = cache_artifact_path(Some(&*FS_ROOT_PATH));
+        let options =
+            command::pkg::install::InstallOptions { url: &url,
+                                                    token: token.as_deref(),
+                                                    fs_root_path: &*FS_ROOT_PATH,
+                                                    artifact_cache_path: &artifact_cache_path,
+                                                    install_mode: &install_mode,
+                                                    local_package_usage: &local_package_usage,
+                                                    install_hook_mode };
+        let report = command::pkg::install::start(ui,
+                                                  &package_sets,
+                                                  PackageTarget::active_target(),
+                                                  &options).await?;
+        if let Some(report_path) = m.value_of("REPORT") {
+            command::pkg::install::write_report(&report, Path::new(report_path))?;
+        }
+        if !report.success {
+            return Err(Error::PackageSetInstall(file.to_string()));
+        }
+        if let Some(dest_dir) = binlink_dest_dir_from_matches(m) {
+            let policy = binlink_conflict_policy_from_matches(m)?;
+            for ident in report.installed.iter() {
+                command::pkg::binlink::binlink_all_in_pkg(ui,
+                                                          ident,
+                                                          &dest_dir,
+                                                          &FS_ROOT_PATH,
+                                                          policy)?;
+            }
+        }
+        return Ok(());
+    }
+
+    let install_sources = install_sources_from_matches(m)?;
     for install_source in install_sources.iter() {
         let pkg_install =
             common::command::package::install::start(ui,
@@ -1121,17 +1355,42 @@ async fn sub_pkg_install(ui: &mut UI,
                                                      install_hook_mode).await?;
 
         if let Some(dest_dir) = binlink_dest_dir_from_matches(m) {
-            let force = m.is_present("FORCE");
+            let policy = binlink_conflict_policy_from_matches(m)?;
             command::pkg::binlink::binlink_all_in_pkg(ui,
                                                       pkg_install.ident(),
                                                       &dest_dir,
                                                       &FS_ROOT_PATH,
-                                                      force)?;
+                                                      policy)?;
         }
     }
     Ok(())
 }
 
+async fn sub_x(ui: &mut UI, x: PkgX) -> Result<()> {
+    let url = bldr_url_from_args_env_load_or_default(x.bldr_url.value)?;
+    let token = bldr_auth_token_from_args_env_or_load(x.auth_token.value).ok();
+    let artifact_cache_path = cache_artifact_path(Some(&*FS_ROOT_PATH));
+    let install_mode = InstallMode::default();
+    let local_package_usage = LocalPackageUsage::default();
+    let install_hook_mode = InstallHookMode::default();
+    let options =
+        command::pkg::install::InstallOptions { url: url.as_str(),
+                                                token: token.as_deref(),
+                                                fs_root_path: &*FS_ROOT_PATH,
+                                                artifact_cache_path: &artifact_cache_path,
+                                                install_mode: &install_mode,
+                                                local_package_usage: &local_package_usage,
+                                                install_hook_mode };
+    init()?;
+    command::pkg::x::start(ui,
+                           &x.pkg_ident.pkg_ident(),
+                           &ChannelIdent::from(x.channel),
+                           &options,
+                           x.keep,
+                           x.cmd,
+                           &x.args.args).await
+}
+
 fn sub_pkg_path(m: &ArgMatches<'_>) -> Result<()> {
     let ident = required_pkg_ident_from_input(m)?;
     command::pkg::path::start(&ident, &*FS_ROOT_PATH)
@@ -1339,6 +1598,22 @@ async fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
     }
     set.service_group = Some(service_group.into());
     set.version = Some(value_t!(m, "VERSION_NUMBER", u64).unwrap());
+    let activate_at = if m.is_present("AT") {
+        Some(SystemTime::from(value_t!(m, "AT", Timestamp).unwrap()))
+    } else if m.is_present("AFTER") {
+        Some(SystemTime::now() + Duration::from(value_t!(m, "AFTER", HumanDuration).unwrap()))
+    } else {
+        None
+    };
+    if let Some(activate_at) = activate_at {
+        if activate_at <= SystemTime::now() {
+            return Err(Error::ArgumentError("The configuration cannot be scheduled for a time \
+                                             in the past"
+                                                         .to_string()));
+        }
+        let since_epoch = activate_at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        set.activate_at_ms = Some(since_epoch.as_millis() as u64);
+    }
     ui.begin(format!("Setting new configuration version {} for {}",
                      set.version
                         .as_ref()
@@ -1367,7 +1642,15 @@ async fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
             _ => return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into()),
         }
     }
-    ui.status(Status::Applying, format!("via peer {}", remote_sup_addr))?;
+    match activate_at {
+        Some(activate_at) => {
+            ui.status(Status::Applying,
+                      format!("via peer {}, scheduled for {}",
+                              remote_sup_addr,
+                              Timestamp::from(activate_at)))?
+        }
+        None => ui.status(Status::Applying, format!("via peer {}", remote_sup_addr))?,
+    }
     let mut response = SrvClient::request(Some(&remote_sup_addr), set).await?;
     while let Some(message_result) = response.next().await {
         let reply = message_result?;
@@ -1415,6 +1698,9 @@ async fn sub_svc_load(svc_load: SvcLoad) -> Result<()> {
 }
 
 async fn sub_svc_bulk_load(svc_bulk_load: SvcBulkLoad) -> Result<()> {
+    if svc_bulk_load.atomic {
+        return sub_svc_bundle_load(svc::svc_loads_from_paths(&svc_bulk_load.svc_config_paths)?).await;
+    }
     let mut errors = HashMap::new();
     for svc_load in svc::svc_loads_from_paths(&svc_bulk_load.svc_config_paths)? {
         let ident = svc_load.pkg_ident.clone().pkg_ident();
@@ -1429,12 +1715,39 @@ async fn sub_svc_bulk_load(svc_bulk_load: SvcBulkLoad) -> Result<()> {
     }
 }
 
+async fn sub_svc_bundle_load(svc_loads: Vec<SvcLoad>) -> Result<()> {
+    let remote_sup_addr = match svc_loads.first() {
+        Some(svc_load) => svc_load.remote_sup.clone(),
+        None => return Err(Error::ArgumentError("No service config files found".to_string())),
+    };
+    let remote_sup = remote_sup_addr.inner().map(ToString::to_string);
+    let mut loads = Vec::with_capacity(svc_loads.len());
+    for svc_load in svc_loads {
+        if svc_load.remote_sup.inner().map(ToString::to_string) != remote_sup {
+            let msg = "The services of an atomic load must all be loaded on the same Supervisor";
+            return Err(Error::ArgumentError(msg.to_string()));
+        }
+        loads.push(habitat_sup_protocol::ctl::SvcLoad::try_from(svc_load)?);
+    }
+    let msg = sup_proto::ctl::SvcLoadBundle { loads };
+    gateway_util::send(remote_sup_addr.inner(), msg).await
+}
+
 async fn sub_svc_unload(m: &ArgMatches<'_>) -> Result<()> {
     let ident = required_pkg_ident_from_input(m)?;
     let timeout_in_seconds =
         parse_optional_arg::<ShutdownTimeout>("SHUTDOWN_TIMEOUT", m).map(u32::from);
+    let purge = Some(m.is_present("PURGE"));
+    let retain_data_days = parse_optional_arg::<u32>("RETAIN_DATA", m);
+    let group_coordinated = Some(m.is_present("GROUP_COORDINATED"));
+    let coordination_timeout_in_seconds = parse_optional_arg::<u32>("COORDINATION_TIMEOUT", m);
     let msg = sup_proto::ctl::SvcUnload { ident: Some(ident.into()),
-                                          timeout_in_seconds };
+                                          timeout_in_seconds,
+                                          purge,
+                                          retain_data_days,
+                                          group_coordinated,
+                                          coordination_timeout_in_seconds,
+                                          follow: Some(m.is_present("FOLLOW")) };
     let remote_sup_addr = remote_sup_from_input(m)?;
     gateway_util::send(remote_sup_addr.as_ref(), msg).await
 }
@@ -1445,32 +1758,134 @@ async fn sub_svc_update(u: hab::cli::hab::svc::Update) -> Result<()> {
     gateway_util::send(ctl_addr.inner(), msg).await
 }
 
+async fn sub_svc_maintenance(svc_maintenance: SvcMaintenance) -> Result<()> {
+    let SvcMaintenance { mode,
+                         service_group,
+                         remote_sup, } = svc_maintenance;
+    let msg = sup_proto::ctl::SvcMaintenance { service_group: Some(service_group.into()),
+                                               enabled:       Some(mode == "on"), };
+    gateway_util::send(remote_sup.inner(), msg).await
+}
+
+async fn sub_svc_history(svc_history: SvcHistory) -> Result<()> {
+    let SvcHistory { service_group,
+                     remote_sup, } = svc_history;
+    let remote_sup_addr = SrvClient::ctl_addr(remote_sup.inner())?;
+    let msg = sup_proto::ctl::SvcUpdateHistory { service_group: Some(service_group.into()), };
+    let mut records = Vec::new();
+    let mut response = SrvClient::request(Some(&remote_sup_addr), msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "ServiceUpdateRecord" => {
+                records.push(reply.parse::<sup_proto::ctl::ServiceUpdateRecord>()
+                                  .map_err(SrvClientError::Decode)?);
+            }
+            "NetOk" => (),
+            "NetErr" => {
+                let m = reply.parse::<sup_proto::net::NetErr>()
+                             .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(m).into());
+            }
+            _ => return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into()),
+        }
+    }
+    if records.is_empty() {
+        println!("This service has not been updated.");
+        return Ok(());
+    }
+    print_svc_history(&mut io::stdout(), &records)?;
+    Ok(())
+}
+
+fn print_svc_history<T>(out: &mut T,
+                        records: &[sup_proto::ctl::ServiceUpdateRecord])
+                        -> io::Result<()>
+    where T: io::Write
+{
+    let mut out = TabWriter::new(out);
+    writeln!(out, "{}", SVC_HISTORY_HEADER.join("\t"))?;
+    for record in records {
+        let started = SystemTime::UNIX_EPOCH + Duration::from_secs(record.started_at());
+        let duration = record.duration_secs
+                             .map_or_else(|| String::from("in progress"), |secs| secs.to_string());
+        writeln!(out,
+                 "{}\t{}\t{}\t{}\t{}\t{}",
+                 Timestamp::from(started),
+                 record.from_ident(),
+                 record.to_ident(),
+                 record.strategy(),
+                 duration,
+                 if record.rollback() { "yes" } else { "no" })?;
+    }
+    out.flush()
+}
+
 async fn sub_svc_start(m: &ArgMatches<'_>) -> Result<()> {
     let ident = required_pkg_ident_from_input(m)?;
-    let msg = sup_proto::ctl::SvcStart { ident: Some(ident.into()), };
+    let msg = sup_proto::ctl::SvcStart { ident:  Some(ident.into()),
+                                         follow: Some(m.is_present("FOLLOW")), };
     let remote_sup_addr = remote_sup_from_input(m)?;
     gateway_util::send(remote_sup_addr.as_ref(), msg).await
 }
 
 async fn sub_svc_status(pkg_ident: Option<PackageIdent>,
+                        detail: bool,
+                        resources: bool,
                         remote_sup: Option<&ResolvedListenCtlAddr>)
                         -> Result<()> {
-    let msg = sup_proto::ctl::SvcStatus { ident: pkg_ident.map(Into::into), };
+    let msg = sup_proto::ctl::SvcStatus { ident:     pkg_ident.map(Into::into),
+                                          detail:    Some(detail),
+                                          resources: Some(resources), };
 
     let mut out = TabWriter::new(io::stdout());
+    let mut statuses = Vec::new();
     let mut response = SrvClient::request(remote_sup, msg).await?;
     // Ensure there is at least one result from the server otherwise produce an error
     if let Some(message_result) = response.next().await {
         let reply = message_result?;
-        print_svc_status(&mut out, &reply, true)?;
+        statuses.extend(print_svc_status(&mut out, &reply, true)?);
     } else {
         return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into());
     }
     while let Some(message_result) = response.next().await {
         let reply = message_result?;
-        print_svc_status(&mut out, &reply, false)?;
+        statuses.extend(print_svc_status(&mut out, &reply, false)?);
     }
     out.flush()?;
+    // A blocked service is otherwise only shown as down, so this is not left to `--detail`
+    let mut out = io::stdout();
+    for status in &statuses {
+        if let Some(blocked_by) = &status.blocked_by {
+            writeln!(out,
+                     "{}: blocked by constraint: {}",
+                     status.service_group, blocked_by)?;
+        }
+    }
+    if detail {
+        for status in &statuses {
+            if let Some(spec_revision) = status.spec_revision {
+                writeln!(out,
+                         "{}: spec revision: {}",
+                         status.service_group, spec_revision)?;
+            }
+            if status.maintenance == Some(true) {
+                writeln!(out, "{}: in maintenance", status.service_group)?;
+            }
+            print_svc_health_check(&mut out, status)?;
+            for bind in &status.degraded_binds {
+                writeln!(out,
+                         "{}: bind degraded: {} has no healthy members",
+                         status.service_group, bind)?;
+            }
+        }
+    }
+    if resources {
+        writeln!(out)?;
+        for status in &statuses {
+            print_svc_resources(&mut out, status)?;
+        }
+    }
     Ok(())
 }
 
@@ -1479,7 +1894,8 @@ async fn sub_svc_stop(m: &ArgMatches<'_>) -> Result<()> {
     let timeout_in_seconds =
         parse_optional_arg::<ShutdownTimeout>("SHUTDOWN_TIMEOUT", m).map(u32::from);
     let msg = sup_proto::ctl::SvcStop { ident: Some(ident.into()),
-                                        timeout_in_seconds };
+                                        timeout_in_seconds,
+                                        follow: Some(m.is_present("FOLLOW")) };
     let remote_sup_addr = remote_sup_from_input(m)?;
     gateway_util::send(remote_sup_addr.as_ref(), msg).await
 }
@@ -1491,14 +1907,23 @@ async fn sub_file_put(m: &ArgMatches<'_>) -> Result<()> {
     let mut ui = ui::ui();
     let mut msg = sup_proto::ctl::SvcFilePut::default();
     let file = Path::new(required_value_of(m, "FILE"));
-    if file.metadata()?.len() > sup_proto::butterfly::MAX_FILE_PUT_SIZE_BYTES as u64 {
+    if file.metadata()?.len() > sup_proto::butterfly::MAX_CHUNKED_FILE_PUT_SIZE_BYTES as u64 {
         ui.fatal(format!("File too large. Maximum size allowed is {} bytes.",
-                         sup_proto::butterfly::MAX_FILE_PUT_SIZE_BYTES))?;
+                         sup_proto::butterfly::MAX_CHUNKED_FILE_PUT_SIZE_BYTES))?;
         process::exit(1);
     };
     msg.service_group = Some(service_group.clone().into());
     msg.version = Some(value_t!(m, "VERSION_NUMBER", u64).unwrap());
     msg.filename = Some(file.file_name().unwrap().to_string_lossy().into_owned());
+    msg.target_member_ids = m.values_of("TARGET_MEMBER")
+                             .unwrap_or_default()
+                             .map(String::from)
+                             .collect();
+    msg.target_labels = m.values_of("TARGET_LABEL")
+                         .unwrap_or_default()
+                         .map(String::from)
+                         .collect();
+    msg.template = Some(m.is_present("TEMPLATE"));
     let mut buf = Vec::with_capacity(sup_proto::butterfly::MAX_FILE_PUT_SIZE_BYTES);
     let key_cache = key_cache_from_matches(m)?;
 
@@ -1533,7 +1958,43 @@ async fn sub_file_put(m: &ArgMatches<'_>) -> Result<()> {
     }
     ui.status(Status::Applying, format!("via peer {}", remote_sup_addr))
       .unwrap();
-    let mut response = SrvClient::request(Some(&remote_sup_addr), msg).await?;
+    let content = msg.content.take().unwrap_or_default();
+    if content.len() <= sup_proto::butterfly::MAX_FILE_PUT_SIZE_BYTES {
+        msg.content = Some(content);
+        send_file_put(&mut ui, &remote_sup_addr, msg).await?;
+    } else {
+        // Too large for a single rumor, so the file is gossiped in chunks that the Supervisors
+        // verify and reassemble
+        let chunks = content.chunks(sup_proto::butterfly::MAX_FILE_PUT_SIZE_BYTES)
+                            .collect::<Vec<_>>();
+        let file_checksum = Blake2bHash::from_bytes(&content).to_string();
+        let mut uploaded = 0;
+        for (index, chunk) in chunks.iter().enumerate() {
+            let mut chunk_msg = msg.clone();
+            chunk_msg.content = Some(chunk.to_vec());
+            chunk_msg.chunk_index = Some(index as u32);
+            chunk_msg.chunk_count = Some(chunks.len() as u32);
+            chunk_msg.chunk_checksum = Some(Blake2bHash::from_bytes(chunk).to_string());
+            chunk_msg.file_checksum = Some(file_checksum.clone());
+            send_file_put(&mut ui, &remote_sup_addr, chunk_msg).await?;
+            uploaded += chunk.len();
+            ui.status(Status::Uploading,
+                      format!("chunk {} of {} ({} of {} bytes)",
+                              index + 1,
+                              chunks.len(),
+                              uploaded,
+                              content.len()))?;
+        }
+    }
+    ui.end("Uploaded file")?;
+    Ok(())
+}
+
+async fn send_file_put(ui: &mut UI,
+                       remote_sup_addr: &ResolvedListenCtlAddr,
+                       msg: sup_proto::ctl::SvcFilePut)
+                       -> Result<()> {
+    let mut response = SrvClient::request(Some(remote_sup_addr), msg).await?;
     while let Some(message_result) = response.next().await {
         let reply = message_result?;
         match reply.message_id() {
@@ -1551,16 +2012,73 @@ async fn sub_file_put(m: &ArgMatches<'_>) -> Result<()> {
             _ => return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into()),
         }
     }
-    ui.end("Uploaded file")?;
     Ok(())
 }
 
+async fn sub_file_status(m: &ArgMatches<'_>) -> Result<()> {
+    let service_group = required_value_of(m, "SERVICE_GROUP").parse::<ServiceGroup>()?;
+    let remote_sup_addr = remote_sup_from_input(m)?;
+    let remote_sup_addr = SrvClient::ctl_addr(remote_sup_addr.as_ref())?;
+    let msg = sup_proto::ctl::SvcFileStatus { service_group: Some(service_group.into()), };
+    let mut receipts = Vec::new();
+    let mut response = SrvClient::request(Some(&remote_sup_addr), msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "ServiceFileReceipt" => {
+                receipts.push(reply.parse::<sup_proto::ctl::ServiceFileReceipt>()
+                                   .map_err(SrvClientError::Decode)?);
+            }
+            "NetOk" => (),
+            "NetErr" => {
+                let m = reply.parse::<sup_proto::net::NetErr>()
+                             .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(m).into());
+            }
+            _ => return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into()),
+        }
+    }
+    if receipts.is_empty() {
+        println!("No files have been uploaded to this service group.");
+        return Ok(());
+    }
+    print_file_receipts(&mut io::stdout(), &receipts)?;
+    Ok(())
+}
+
+fn print_file_receipts<T>(out: &mut T,
+                          receipts: &[sup_proto::ctl::ServiceFileReceipt])
+                          -> io::Result<()>
+    where T: io::Write
+{
+    let mut out = TabWriter::new(out);
+    writeln!(out, "{}", FILE_STATUS_HEADER.join("\t"))?;
+    for receipt in receipts {
+        let outcome = match receipt.outcome() {
+            sup_proto::ctl::service_file_receipt::Outcome::Written => "written",
+            sup_proto::ctl::service_file_receipt::Outcome::Rendered => "rendered",
+            sup_proto::ctl::service_file_receipt::Outcome::NotTargeted => "not targeted",
+            sup_proto::ctl::service_file_receipt::Outcome::Failed => "failed",
+        };
+        writeln!(out,
+                 "{}\t{}\t{}\t{}\t{}",
+                 receipt.member_id(),
+                 receipt.filename(),
+                 receipt.incarnation(),
+                 outcome,
+                 receipt.error())?;
+    }
+    out.flush()
+}
+
 async fn sub_sup_depart(member_id: String,
                         remote_sup: Option<&ResolvedListenCtlAddr>)
                         -> Result<()> {
     let remote_sup = SrvClient::ctl_addr(remote_sup)?;
     let mut ui = ui::ui();
-    let msg = sup_proto::ctl::SupDepart { member_id: Some(member_id), };
+    let msg = sup_proto::ctl::SupDepart { member_id: Some(member_id),
+                                          address:   None,
+                                          all:       None, };
 
     ui.begin(format!("Permanently marking {} as departed",
                      msg.member_id.as_deref().unwrap_or("UNKNOWN")))
@@ -1584,6 +2102,97 @@ async fn sub_sup_depart(member_id: String,
     Ok(())
 }
 
+async fn sub_sup_depart_address(address: String,
+                                all: bool,
+                                remote_sup: Option<&ResolvedListenCtlAddr>)
+                                -> Result<()> {
+    let remote_sup = SrvClient::ctl_addr(remote_sup)?;
+    let mut ui = ui::ui();
+    ui.begin(format!("Permanently marking the members at {} as departed", address))?;
+    ui.status(Status::Applying, format!("via peer {}", remote_sup))?;
+    let msg = sup_proto::ctl::SupDepart { member_id: None,
+                                          address:   Some(address),
+                                          all:       Some(all), };
+    gateway_util::send(Some(&remote_sup), msg).await?;
+    ui.end("Departures recorded.")?;
+    Ok(())
+}
+
+async fn sub_sup_depart_self(stop_services: bool,
+                             remote_sup: Option<&ResolvedListenCtlAddr>)
+                             -> Result<()> {
+    let remote_sup = SrvClient::ctl_addr(remote_sup)?;
+    let mut ui = ui::ui();
+    let msg = sup_proto::ctl::SupDepartSelf { stop_services: Some(stop_services), };
+
+    ui.begin(format!("Permanently departing supervisor {} from the ring",
+                     remote_sup))?;
+    if stop_services {
+        ui.status(Status::Applying, "stopping its services")?;
+    }
+    let mut response = SrvClient::request(Some(&remote_sup), msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "NetOk" => (),
+            "NetErr" => {
+                let m = reply.parse::<sup_proto::net::NetErr>()
+                             .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(m).into());
+            }
+            _ => return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into()),
+        }
+    }
+    ui.end("Departure recorded. Run `hab sup rejoin` before starting the Supervisor again.")?;
+    Ok(())
+}
+
+async fn sub_sup_deny(deny: Vec<DenylistEntry>,
+                      allow: Vec<DenylistEntry>,
+                      remote_sup: Option<&ResolvedListenCtlAddr>)
+                      -> Result<()> {
+    let remote_sup = SrvClient::ctl_addr(remote_sup)?;
+    let mut ui = ui::ui();
+    let msg = sup_proto::ctl::SupDenylistUpdate { deny:  deny.iter()
+                                                             .map(ToString::to_string)
+                                                             .collect(),
+                                                  allow: allow.iter()
+                                                              .map(ToString::to_string)
+                                                              .collect(), };
+
+    ui.begin(format!("Updating the denylist of supervisor {}", remote_sup))?;
+    for entry in &deny {
+        ui.status(Status::Applying, format!("deny {}", entry))?;
+    }
+    for entry in &allow {
+        ui.status(Status::Applying, format!("allow {}", entry))?;
+    }
+    let mut response = SrvClient::request(Some(&remote_sup), msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "SupDenylist" => {
+                let denylist = reply.parse::<sup_proto::ctl::SupDenylist>()
+                                    .map_err(SrvClientError::Decode)?;
+                if denylist.entries.is_empty() {
+                    ui.info("No members are denied")?;
+                }
+                for entry in denylist.entries {
+                    ui.info(format!("Denied: {}", entry))?;
+                }
+            }
+            "NetErr" => {
+                let m = reply.parse::<sup_proto::net::NetErr>()
+                             .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(m).into());
+            }
+            _ => return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into()),
+        }
+    }
+    ui.end("Denylist updated.")?;
+    Ok(())
+}
+
 async fn sub_sup_restart(remote_sup: Option<&ResolvedListenCtlAddr>) -> Result<()> {
     let remote_sup = SrvClient::ctl_addr(remote_sup)?;
     let mut ui = ui::ui();
@@ -1607,6 +2216,46 @@ async fn sub_sup_restart(remote_sup: Option<&ResolvedListenCtlAddr>) -> Result<(
     Ok(())
 }
 
+async fn sub_sup_config_reload(remote_sup: Option<&ResolvedListenCtlAddr>) -> Result<()> {
+    let remote_sup = SrvClient::ctl_addr(remote_sup)?;
+    let mut ui = ui::ui();
+    let msg = sup_proto::ctl::SupConfigReload::default();
+
+    ui.begin(format!("Reloading configuration of supervisor {}", remote_sup))?;
+    let mut response = SrvClient::request(Some(&remote_sup), msg).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "SupConfigReloadResult" => {
+                let result = reply.parse::<sup_proto::ctl::SupConfigReloadResult>()
+                                  .map_err(SrvClientError::Decode)?;
+                if result.applied.is_empty() {
+                    ui.info("No reloadable settings changed")?;
+                }
+                for setting in result.applied {
+                    ui.status(Status::Applying, setting)?;
+                }
+                for setting in result.applies_to_new_services {
+                    ui.info(format!("'{}' only applies to the services loaded from now on",
+                                    setting))?;
+                }
+                for setting in result.requires_restart {
+                    ui.warn(format!("'{}' changed but only takes effect after a restart",
+                                    setting))?;
+                }
+            }
+            "NetErr" => {
+                let m = reply.parse::<sup_proto::net::NetErr>()
+                             .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(m).into());
+            }
+            _ => return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into()),
+        }
+    }
+    ui.end("Reload complete.")?;
+    Ok(())
+}
+
 fn sub_sup_secret_generate() -> Result<()> {
     let mut ui = ui::ui();
     let mut buf = String::new();
@@ -1652,6 +2301,140 @@ fn sub_ring_key_import(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     command::ring::key::import::start(ui, content.trim(), &key_cache)
 }
 
+async fn sub_ring_manifest_apply(m: &ArgMatches<'_>) -> Result<()> {
+    let remote_sup_addr = remote_sup_from_input(m)?;
+    let remote_sup_addr = SrvClient::ctl_addr(remote_sup_addr.as_ref())?;
+    let origin = origin_param_or_env(m)?;
+    let version = value_t!(m, "VERSION_NUMBER", u64).unwrap();
+    let mut ui = ui::ui();
+    let mut buf = Vec::with_capacity(sup_proto::butterfly::MAX_FLEET_MANIFEST_SIZE);
+    let manifest_len = match m.value_of("FILE") {
+        Some("-") | None => io::stdin().read_to_end(&mut buf)?,
+        Some(f) => {
+            let mut file = File::open(f)?;
+            file.read_to_end(&mut buf)?
+        }
+    };
+    if manifest_len > sup_proto::butterfly::MAX_FLEET_MANIFEST_SIZE {
+        ui.fatal(format!("Fleet manifest too large. Maximum size allowed is {} bytes.",
+                         sup_proto::butterfly::MAX_FLEET_MANIFEST_SIZE))?;
+        process::exit(1);
+    }
+    let key_cache = key_cache_from_matches(m)?;
+    let key = key_cache.latest_secret_origin_signing_key(&origin)?;
+
+    ui.begin(format!("Applying fleet manifest version {}", version))?;
+    let signer = key.named_revision().to_string();
+    ui.status(Status::Signing, format!("fleet manifest with {}", signer))?;
+    let apply = sup_proto::ctl::SupFleetManifestApply { version:   Some(version),
+                                                        signer:    Some(signer),
+                                                        signature: Some(key.sign_bytes(&buf)),
+                                                        body:      Some(buf), };
+    ui.status(Status::Applying, format!("via peer {}", remote_sup_addr))?;
+    let mut response = SrvClient::request(Some(&remote_sup_addr), apply).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "NetOk" => (),
+            "NetErr" => {
+                let m = reply.parse::<sup_proto::net::NetErr>()
+                             .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(m).into());
+            }
+            _ => {
+                let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+                return Err(SrvClientError::from(eof).into());
+            }
+        }
+    }
+    ui.end("Applied fleet manifest")?;
+    Ok(())
+}
+
+async fn sub_ring_status(m: &ArgMatches<'_>) -> Result<()> {
+    let remote_sup_addr = remote_sup_from_input(m)?;
+    let remote_sup_addr = SrvClient::ctl_addr(remote_sup_addr.as_ref())?;
+    let to_json = m.is_present("TO_JSON");
+    let mut response = SrvClient::request(Some(&remote_sup_addr),
+                                          sup_proto::ctl::SupRingStatus::default()).await?;
+    while let Some(message_result) = response.next().await {
+        let reply = message_result?;
+        match reply.message_id() {
+            "RingStatus" => {
+                let status = reply.parse::<sup_proto::ctl::RingStatus>()
+                                  .map_err(SrvClientError::Decode)?;
+                if to_json {
+                    println!("{}", serde_json::to_string_pretty(&status)?);
+                } else {
+                    print_ring_status(&mut io::stdout(), &status)?;
+                }
+            }
+            "NetErr" => {
+                let m = reply.parse::<sup_proto::net::NetErr>()
+                             .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(m).into());
+            }
+            _ => return Err(SrvClientError::from(io::Error::from(io::ErrorKind::UnexpectedEof)).into()),
+        }
+    }
+    Ok(())
+}
+
+fn print_ring_status<T>(out: &mut T, status: &sup_proto::ctl::RingStatus) -> io::Result<()>
+    where T: io::Write
+{
+    writeln!(out, "Ring status as seen by member {}", status.member_id())?;
+    writeln!(out,
+             "Members: {} alive, {} suspect, {} confirmed, {} departed ({} flapping)",
+             status.alive(),
+             status.suspect(),
+             status.confirmed(),
+             status.departed(),
+             status.flapping())?;
+    if status.partition_suspected() {
+        writeln!(out,
+                 "WARNING: at least half of the other members are unreachable; the network may \
+                  be partitioned")?;
+    }
+    if status.clock_skew_exceeded() {
+        writeln!(out,
+                 "WARNING: the clocks of the members are {}ms apart",
+                 status.clock_skew_ms())?;
+    }
+    let versions: Vec<String> = status.supervisor_versions
+                                      .iter()
+                                      .map(|v| format!("{} ({})", v.version(), v.members()))
+                                      .collect();
+    if versions.len() > 1 {
+        writeln!(out,
+                 "WARNING: members are running different Supervisor versions")?;
+    }
+    writeln!(out, "Supervisor versions: {}", versions.join(", "))?;
+    let stores: Vec<String> =
+        status.rumor_stores
+              .iter()
+              .map(|s| format!("{} {} ({} bytes)", s.name(), s.rumors(), s.bytes()))
+              .collect();
+    writeln!(out, "Rumors: {}", stores.join(", "))?;
+    if status.service_groups.is_empty() {
+        return Ok(());
+    }
+    writeln!(out)?;
+    let mut out = TabWriter::new(out);
+    writeln!(out, "{}", RING_STATUS_HEADER.join("\t"))?;
+    for group in &status.service_groups {
+        writeln!(out,
+                 "{}\t{}\t{}\t{}\t{}\t{}",
+                 group.service_group(),
+                 group.active_members(),
+                 group.election_status(),
+                 group.leader_id.as_deref().unwrap_or("<none>"),
+                 group.update_election_status(),
+                 group.packages.join(", "))?;
+    }
+    out.flush()
+}
+
 fn sub_service_key_generate(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     let org = org_param_or_env(m)?;
     let service_group = required_value_of(m, "SERVICE_GROUP").parse()?;
@@ -1669,8 +2452,133 @@ fn sub_user_key_generate(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
     command::user::key::generate::start(ui, user, &key_cache)
 }
 
-fn args_after_first(args_to_skip: usize) -> Vec<OsString> {
-    env::args_os().skip(args_to_skip).collect()
+fn args_after_first(args: &[OsString], args_to_skip: usize) -> Vec<OsString> {
+    args.iter().skip(args_to_skip).cloned().collect()
+}
+
+/// The options which apply to the whole command. They are only accepted before the subcommand.
+#[derive(Debug, PartialEq)]
+struct GlobalOptions {
+    offline:   bool,
+    ring_name: Option<String>,
+    /// The arguments without the global options
+    args:      Vec<OsString>,
+}
+
+impl GlobalOptions {
+    /// Parse the global options, leaving the subcommand and its arguments unparsed. When this
+    /// fails, for example on `--help`, the arguments are left for the full parse to handle.
+    fn from_args(args: Vec<OsString>) -> Self {
+        let app = clap::App::new(PRODUCT).setting(clap::AppSettings::AllowExternalSubcommands)
+                                         .setting(clap::AppSettings::DisableHelpFlags)
+                                         .setting(clap::AppSettings::DisableVersion)
+                                         .arg(cli::offline_arg())
+                                         .arg(cli::ring_arg());
+        let matches = match app.get_matches_from_safe(&args) {
+            Ok(matches) => matches,
+            Err(_) => {
+                return GlobalOptions { offline: false,
+                                       ring_name: None,
+                                       args }
+            }
+        };
+        let mut rest: Vec<OsString> = args.into_iter().take(1).collect();
+        if let (subcommand, Some(subcommand_matches)) = matches.subcommand() {
+            rest.push(subcommand.into());
+            rest.extend(subcommand_matches.values_of_os("")
+                                          .into_iter()
+                                          .flatten()
+                                          .map(OsString::from));
+        }
+        GlobalOptions { offline:   matches.is_present("OFFLINE"),
+                        ring_name: matches.value_of("RING").map(String::from),
+                        args:      rest, }
+    }
+}
+
+/// The value of the `--error-format` option. Errors must be reported in the requested format even
+/// when clap fails to parse the rest of the arguments, so the option is looked up before parsing.
+fn error_format_arg(args: &[OsString]) -> Option<OsString> {
+    let mut args = args.iter().skip(1).take_while(|arg| *arg != "--");
+    while let Some(arg) = args.next() {
+        let arg = arg.to_string_lossy();
+        if arg == "--error-format" {
+            return args.next().cloned();
+        } else if let Some(format) = arg.strip_prefix("--error-format=") {
+            return Some(OsString::from(format));
+        }
+    }
+    None
+}
+
+/// The arguments without the `--error-format` option
+fn without_error_format(args: &[OsString]) -> Vec<OsString> {
+    let mut kept = Vec::with_capacity(args.len());
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            kept.push(arg.clone());
+            kept.extend(args.cloned());
+            break;
+        } else if arg == "--error-format" {
+            args.next();
+        } else if !arg.to_string_lossy().starts_with("--error-format=") {
+            kept.push(arg.clone());
+        }
+    }
+    kept
+}
+
+/// How the error that `hab` fails with is reported
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ErrorFormat {
+    /// A message for people to read
+    Text,
+    /// A JSON object on a single line of standard error, for scripts to parse
+    Json,
+}
+
+impl ErrorFormat {
+    /// The format given with `--error-format` in `args`, or else in `HAB_ERROR_FORMAT`
+    fn from_args_or_env(args: &[OsString]) -> Result<Self> {
+        match error_format_arg(args).or_else(|| env::var_os(ERROR_FORMAT_ENVVAR)) {
+            Some(format) => format.to_string_lossy().parse(),
+            None => Ok(ErrorFormat::Text),
+        }
+    }
+}
+
+impl FromStr for ErrorFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => {
+                Err(Error::ArgumentError(format!("Unknown error format '{}', \
+                                                  expected 'text' or 'json'",
+                                                 s)))
+            }
+        }
+    }
+}
+
+/// The structured form of an error. The errors of commands that act on several packages are
+/// included by package.
+fn error_json(err: &Error) -> serde_json::Value {
+    let mut value = serde_json::json!({
+        "class": err.class().as_str(),
+        "exit_code": err.exit_code(),
+        "message": err.to_string(),
+    });
+    if let Error::ErrorPerIdent(errors) = err {
+        value["packages"] = errors.iter()
+                                  .map(|(ident, e)| (ident.to_string(), error_json(e)))
+                                  .collect::<serde_json::Map<_, _>>()
+                                  .into();
+    }
+    value
 }
 
 /// Check to see if the user has passed in an AUTH_TOKEN param. If not, check the
@@ -1867,45 +2775,14 @@ fn idents_from_file_matches(ui: &mut UI,
 }
 
 fn idents_from_toml_file(ui: &mut UI, filename: &str) -> Result<Vec<PackageSet>> {
-    let mut sources: Vec<PackageSet> = Vec::new();
-
-    let file_data = std::fs::read_to_string(filename)?;
-    let toml_data: PackageSetFile =
-        toml::from_str(&file_data).map_err(habitat_common::Error::TomlParser)?;
-
-    // We currently only accept version 1
-    if toml_data.format_version.unwrap_or(1) != 1 {
-        return Err(Error::PackageSetParseError(format!(
-            "format_version invalid, only version 1 allowed ({} provided",
-            toml_data.format_version.unwrap()
-        )));
-    }
+    let toml_data = PackageSetFile::load(Path::new(filename))?;
 
     ui.status(Status::Using,
               format!("File {}, '{}'",
                       filename,
-                      toml_data.file_descriptor.unwrap_or_else(|| "".to_string())))?;
+                      toml_data.file_descriptor.as_deref().unwrap_or("")))?;
 
-    for (target, target_array) in toml_data.targets {
-        for package_set_value in target_array {
-            let channel = package_set_value.channel;
-            let idents: Vec<PackageIdent> = strings_to_idents(&package_set_value.packages)?;
-            let package_set = PackageSet { target,
-                                           channel,
-                                           idents };
-            debug!("Package Set {:?}", package_set);
-            sources.push(package_set)
-        }
-    }
-    Ok(sources)
-}
-
-fn strings_to_idents(strings: &[String]) -> Result<Vec<PackageIdent>> {
-    let ident_or_results: Result<Vec<PackageIdent>> =
-        strings.iter()
-               .map(|s| PackageIdent::from_str(s).map_err(Error::from))
-               .collect();
-    ident_or_results
+    toml_data.package_sets()
 }
 
 fn verify_from_matches(matches: &ArgMatches<'_>) -> bool { matches.is_present("VERIFY") }
@@ -1925,10 +2802,11 @@ fn excludes_from_matches(matches: &ArgMatches<'_>) -> Vec<PackageIdent> {
         .collect()
 }
 
+/// Print a row of the status table, returning the status it was printed from
 fn print_svc_status<T>(out: &mut T,
                        reply: &SrvMessage,
                        print_header: bool)
-                       -> result::Result<(), SrvClientError>
+                       -> result::Result<Option<sup_proto::types::ServiceStatus>, SrvClientError>
     where T: io::Write
 {
     let status = match reply.message_id() {
@@ -1938,7 +2816,7 @@ fn print_svc_status<T>(out: &mut T,
         }
         "NetOk" => {
             println!("No services loaded.");
-            return Ok(());
+            return Ok(None);
         }
         "NetErr" => {
             let err = reply.parse::<sup_proto::net::NetErr>()
@@ -1947,7 +2825,7 @@ fn print_svc_status<T>(out: &mut T,
         }
         _ => {
             warn!("Unexpected status message, {:?}", reply);
-            return Ok(());
+            return Ok(None);
         }
     };
     let svc_desired_state = status.desired_state
@@ -1984,6 +2862,52 @@ fn print_svc_status<T>(out: &mut T,
              svc_elapsed,
              svc_pid,
              status.service_group,)?;
+    Ok(Some(status))
+}
+
+/// Print the result of a service's most recent health check, followed by whatever the
+/// health-check hook printed
+fn print_svc_resources<T>(out: &mut T, status: &sup_proto::types::ServiceStatus) -> io::Result<()>
+    where T: io::Write
+{
+    match &status.resources {
+        Some(usage) => {
+            writeln!(out,
+                     "{}: cpu {:.1}% ({:.1}s), memory {:.1} MiB, {} open files, {} processes",
+                     status.service_group,
+                     usage.cpu_percent.unwrap_or_default(),
+                     usage.cpu_seconds.unwrap_or_default(),
+                     usage.memory_bytes.unwrap_or_default() as f64 / (1024.0 * 1024.0),
+                     usage.open_fds.unwrap_or_default(),
+                     usage.processes.unwrap_or_default())
+        }
+        None => writeln!(out, "{}: no resource usage", status.service_group),
+    }
+}
+
+fn print_svc_health_check<T>(out: &mut T,
+                             status: &sup_proto::types::ServiceStatus)
+                             -> io::Result<()>
+    where T: io::Write
+{
+    writeln!(out)?;
+    let health_check = match &status.health_check {
+        Some(health_check) => health_check,
+        None => return writeln!(out, "{}: no health check result", status.service_group),
+    };
+    let result = sup_proto::types::HealthCheckResult::from_i32(health_check.result)
+        .unwrap_or(sup_proto::types::HealthCheckResult::Unknown);
+    writeln!(out, "{}: health check {}", status.service_group, result)?;
+    for (stream, output) in &[("stdout", &health_check.stdout),
+                              ("stderr", &health_check.stderr)]
+    {
+        if let Some(output) = output.as_deref().filter(|o| !o.trim().is_empty()) {
+            writeln!(out, "  {}:", stream)?;
+            for line in output.lines() {
+                writeln!(out, "    {}", line)?;
+            }
+        }
+    }
     Ok(())
 }
 
@@ -2030,6 +2954,113 @@ fn required_value_of<'a>(matches: &'a ArgMatches<'a>, name: &str) -> &'a str {
 mod test {
     use super::*;
 
+    mod global_options {
+        use super::*;
+
+        fn os_args(args: &[&str]) -> Vec<OsString> { args.iter().map(OsString::from).collect() }
+
+        #[test]
+        fn leading_options_are_parsed_in_either_order() {
+            let args = os_args(&["hab", "--ring", "blue", "--offline", "svc", "status", "-x"]);
+            assert_eq!(GlobalOptions::from_args(args),
+                       GlobalOptions { offline:   true,
+                                       ring_name: Some(String::from("blue")),
+                                       args:      os_args(&["hab", "svc", "status", "-x"]), });
+            let args = os_args(&["hab", "--offline", "--ring=blue", "svc", "status"]);
+            assert_eq!(GlobalOptions::from_args(args),
+                       GlobalOptions { offline:   true,
+                                       ring_name: Some(String::from("blue")),
+                                       args:      os_args(&["hab", "svc", "status"]), });
+        }
+
+        #[test]
+        fn options_after_a_subcommand_are_left_alone() {
+            let args = os_args(&["hab", "sup", "run", "--ring", "my-key", "--offline"]);
+            assert_eq!(GlobalOptions::from_args(args.clone()),
+                       GlobalOptions { offline: false,
+                                       ring_name: None,
+                                       args });
+        }
+
+        #[test]
+        fn unparsable_options_are_left_for_the_full_parse() {
+            let args = os_args(&["hab", "--ring", "Blue", "svc", "status"]);
+            assert_eq!(GlobalOptions::from_args(args.clone()),
+                       GlobalOptions { offline: false,
+                                       ring_name: None,
+                                       args });
+        }
+    }
+
+    mod error_format_arg {
+        use super::*;
+
+        fn os_args(args: &[&str]) -> Vec<OsString> { args.iter().map(OsString::from).collect() }
+
+        #[test]
+        fn error_format_option_is_found_anywhere() {
+            let args = os_args(&["hab", "--error-format", "json", "svc", "status"]);
+            assert_eq!(error_format_arg(&args), Some(OsString::from("json")));
+            let args = os_args(&["hab", "svc", "status", "--error-format=json"]);
+            assert_eq!(error_format_arg(&args), Some(OsString::from("json")));
+            let args = os_args(&["hab", "pkg", "exec", "core/redis", "--", "--error-format=x"]);
+            assert_eq!(error_format_arg(&args), None);
+        }
+
+        #[test]
+        fn error_format_option_is_removed() {
+            let args = os_args(&["hab", "--error-format", "json", "--offline", "--", "-x"]);
+            assert_eq!(without_error_format(&args),
+                       os_args(&["hab", "--offline", "--", "-x"]));
+            let args = os_args(&["hab", "svc", "status", "--error-format=json"]);
+            assert_eq!(without_error_format(&args),
+                       os_args(&["hab", "svc", "status"]));
+        }
+
+        #[test]
+        fn usage_errors_are_validation_errors() {
+            let args = os_args(&["hab", "svc", "status", "--no-such-option"]);
+            let err = cli::get(FeatureFlag::empty()).get_matches_from_safe(&args)
+                                                    .unwrap_err();
+            assert_eq!(err.kind, ClapErrorKind::UnknownArgument);
+            assert_eq!(Error::from(ConfigOptError::Clap(err)).exit_code(), 2);
+        }
+
+        #[test]
+        fn unknown_error_formats_are_rejected() {
+            assert_eq!("json".parse::<ErrorFormat>().unwrap(), ErrorFormat::Json);
+            assert_eq!("text".parse::<ErrorFormat>().unwrap(), ErrorFormat::Text);
+            assert!("xml".parse::<ErrorFormat>().is_err());
+        }
+    }
+
+    mod error_json {
+        use super::*;
+
+        #[test]
+        fn errors_have_a_class_and_exit_code() {
+            let err = Error::ArgumentError(String::from("bad input"));
+            assert_eq!(error_json(&err),
+                       serde_json::json!({
+                           "class": "validation",
+                           "exit_code": 2,
+                           "message": "bad input",
+                       }));
+        }
+
+        #[test]
+        fn partial_failures_include_the_error_of_each_package() {
+            let ident = PackageIdent::from_str("core/redis").unwrap();
+            let mut errors = HashMap::new();
+            errors.insert(ident, Error::CtlClient(SrvClientError::ConnectionRefused));
+            let json = error_json(&Error::ErrorPerIdent(errors));
+            assert_eq!(json["class"], "partial_success");
+            assert_eq!(json["exit_code"], 6);
+            assert_eq!(json["packages"]["core/redis"]["class"], "sup_unreachable");
+            assert_eq!(json["packages"]["core/redis"]["exit_code"], 5);
+        }
+    }
+
     mod binlink_dest_dir_from_matches {
         use super::*;
 
@@ -2162,4 +3193,33 @@ mod test {
             binlink_dest_dir_from_matches(pkg_install_matches)
         }
     }
+
+    mod pkg_install_file {
+        use super::*;
+
+        fn pkg_install(args: &[&str]) -> bool {
+            let pre_pkg_install_args = &["hab", "pkg", "install"];
+            cli::get(FeatureFlag::empty()).get_matches_from_safe(pre_pkg_install_args.iter()
+                                                                                     .chain(args))
+                                          .is_ok()
+        }
+
+        const MANIFEST: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+
+        #[test]
+        fn file_replaces_pkg_idents() {
+            assert!(pkg_install(&["--file", MANIFEST, "--report", "report.json"]));
+            assert!(!pkg_install(&[]));
+        }
+
+        #[test]
+        fn file_conflicts_with_pkg_idents() {
+            assert!(!pkg_install(&["--file", MANIFEST, "core/redis"]));
+        }
+
+        #[test]
+        fn report_requires_file() {
+            assert!(!pkg_install(&["--report", "report.json", "core/redis"]));
+        }
+    }
 }
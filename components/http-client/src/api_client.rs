@@ -1,9 +1,14 @@
 use crate::error::{Error,
                    Result};
-use habitat_core::{env,
+use habitat_core::{dns,
+                   env,
                    package::PackageTarget,
                    util::sys};
-use reqwest::{header::{HeaderValue,
+use reqwest::{dns::{Addrs,
+                    Name,
+                    Resolve,
+                    Resolving},
+              header::{HeaderValue,
                        CONNECTION,
                        USER_AGENT},
               Certificate as ReqwestCertificate,
@@ -13,6 +18,7 @@ use reqwest::{header::{HeaderValue,
               RequestBuilder,
               Url};
 use std::{path::Path,
+          sync::Arc,
           time::Duration};
 
 // Read and write TCP socket timeout for Hyper/HTTP client calls.
@@ -87,6 +93,10 @@ impl ApiClient {
                                                  .timeout(Duration::from_secs(timeout_in_secs))
                                                  .danger_accept_invalid_certs(skip_cert_verify);
 
+        if dns::lookups_configured() {
+            client = client.dns_resolver(Arc::new(ConfiguredResolver));
+        }
+
         client =
             habitat_core::tls::native_tls_wrapper::certificates_as_der(fs_root_path)?
                                        .into_iter()
@@ -196,6 +206,20 @@ impl ApiClient {
     }
 }
 
+/// Resolves every host the client connects to, endpoint, redirect or proxy alike, with the DNS
+/// servers of `HAB_DNS_SERVERS` and within `HAB_DNS_TIMEOUT_SECS`, when either is set.
+struct ConfiguredResolver;
+
+impl Resolve for ConfiguredResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            // The client connects to the port of the URL, whatever the port resolved here
+            let addrs = dns::resolve_async(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
 fn proxy_for(url: &Url) -> reqwest::Result<Proxy> {
     trace!("Checking proxy for url: {:?}", url);
 
@@ -38,7 +38,9 @@ pub struct LauncherCli {
     pipe: String,
 
     /// Maximum wait time for interactions that can timeout.
-    timeout: Duration,
+    timeout:          Duration,
+    /// The protocol version agreed on with the Launcher
+    protocol_version: u32,
 }
 
 #[cfg(not(windows))]
@@ -67,12 +69,72 @@ impl LauncherCli {
         Self::read::<protocol::NetOk>(&raw)?;
 
         let timeout = LauncherInteractionTimeout::configured_value().into();
+        let protocol_version = Self::negotiate(&tx, &rx, timeout)?;
 
         Ok(LauncherCli { tx,
                          rx,
                          #[cfg(not(windows))]
                          pipe: pipe_to_sup,
-                         timeout })
+                         timeout,
+                         protocol_version })
+    }
+
+    /// Agree on a protocol version with the Launcher. Launchers that predate negotiation either
+    /// reject the message as unknown or never reply; they speak the legacy protocol, and the
+    /// Supervisor runs with core features only.
+    fn negotiate(tx: &IpcSender<Vec<u8>>,
+                 rx: &IpcReceiver<Vec<u8>>,
+                 timeout: Duration)
+                 -> Result<u32> {
+        Self::send(tx, &protocol::NegotiateProtocol::default())?;
+        match Self::recv_timeout::<protocol::ProtocolVersions>(rx, timeout) {
+            Ok(reply) => {
+                protocol::negotiate_protocol_version(reply.min_protocol_version,
+                                                     reply.protocol_version)
+                    .ok_or(Error::IncompatibleLauncher(Some((reply.min_protocol_version,
+                                                            reply.protocol_version))))
+            }
+            Err(Error::Protocol(ProtocolError::NetErr(ref err)))
+                if err.code == protocol::ErrCode::IncompatibleProtocol =>
+            {
+                Err(Error::IncompatibleLauncher(None))
+            }
+            Err(Error::Protocol(ProtocolError::NetErr(ref err)))
+                if err.code == protocol::ErrCode::UnknownMessage =>
+            {
+                warn!("Launcher does not support protocol negotiation; running with core \
+                       features only");
+                Ok(protocol::LEGACY_PROTOCOL_VERSION)
+            }
+            Err(Error::Timeout) => {
+                warn!("Launcher did not reply to protocol negotiation; running with core \
+                       features only");
+                Ok(protocol::LEGACY_PROTOCOL_VERSION)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The protocol version agreed on with the Launcher
+    pub fn protocol_version(&self) -> u32 { self.protocol_version }
+
+    /// Whether the Launcher is too old to negotiate a protocol version, in which case only core
+    /// features are available.
+    pub fn core_features_only(&self) -> bool {
+        self.protocol_version <= protocol::LEGACY_PROTOCOL_VERSION
+    }
+
+    /// Whether the agreed protocol version is recent enough for `feature`
+    pub fn supports(&self, feature: protocol::LauncherFeature) -> bool {
+        self.protocol_version >= feature.protocol_version()
+    }
+
+    fn require(&self, feature: protocol::LauncherFeature) -> Result<()> {
+        if self.supports(feature) {
+            Ok(())
+        } else {
+            Err(Error::ProtocolFeature(feature, self.protocol_version))
+        }
     }
 
     /// Read a launcher protocol message from a byte array
@@ -191,8 +253,15 @@ impl LauncherCli {
                             groupname,
                             gid, }: UserInfo,
                  password: Option<&str>,
-                 env: Env)
+                 env: Env,
+                 network_namespace: Option<protocol::NetworkNamespace>)
                  -> Result<Pid> {
+        // Older Launchers would silently ignore the network namespace and run the service in
+        // the host's namespace instead.
+        if network_namespace.is_some() {
+            self.require(protocol::LauncherFeature::NetworkNamespace)?;
+        }
+
         // On Windows, we only expect user to be Some.
         //
         // On Linux, we expect uid and gid to be Some, while
@@ -206,7 +275,8 @@ impl LauncherCli {
                                     svc_group_id: gid,
                                     svc_password: password.map(str::to_string),
                                     env,
-                                    id: id.to_string() };
+                                    id: id.to_string(),
+                                    network_namespace };
 
         Self::send(&self.tx, &msg)?;
         let reply = Self::recv::<protocol::SpawnOk>(&self.rx)?;
@@ -249,6 +319,20 @@ impl LauncherCli {
         Ok(reply.version)
     }
 
+    /// Tell the Launcher that the Supervisor is alive. The Launcher does not reply.
+    pub fn heartbeat(&self) -> Result<()> {
+        self.require(protocol::LauncherFeature::Heartbeat)?;
+        Self::send(&self.tx, &protocol::Heartbeat::default())
+    }
+
+    /// Tell the Launcher to redact the output of services following the redaction configuration
+    /// in `config_file`. The Launcher does not reply.
+    pub fn set_redaction(&self, config_file: &Path) -> Result<()> {
+        self.require(protocol::LauncherFeature::Redaction)?;
+        let config_file = config_file.to_string_lossy().into_owned();
+        Self::send(&self.tx, &protocol::SetRedaction { config_file })
+    }
+
     pub fn terminate(&self, pid: Pid) -> Result<i32> {
         let msg = protocol::Terminate { pid: pid.into() };
         Self::send(&self.tx, &msg)?;
@@ -10,9 +10,14 @@ pub enum Error {
     AcceptConn,
     BadPipe(io::Error),
     Connect(io::Error),
+    /// The Launcher only speaks protocol versions outside of this Supervisor's compatibility
+    /// window. The Launcher's range is unknown if it refused the negotiation itself.
+    IncompatibleLauncher(Option<(u32, u32)>),
     IPCBincode(String),
     IPCIO(io::ErrorKind),
     Protocol(protocol::Error),
+    /// The feature requires a newer protocol version than the one agreed with the Launcher
+    ProtocolFeature(protocol::LauncherFeature, u32),
     Send(ipc_channel::Error),
     Timeout,
 }
@@ -25,11 +30,29 @@ impl fmt::Display for Error {
             Error::AcceptConn => "Unable to accept connection from Launcher".to_string(),
             Error::BadPipe(ref e) => format!("Unable to open pipe to Launcher, {}", e),
             Error::Connect(ref e) => format!("Unable to connect to Launcher's pipe, {}", e),
+            Error::IncompatibleLauncher(versions) => {
+                let launcher = match versions {
+                    Some((min, max)) => format!("protocol versions {} to {}", min, max),
+                    None => "an incompatible protocol version".to_string(),
+                };
+                format!("The Launcher speaks {}, but this Supervisor speaks versions {} to {}. \
+                         Install a compatible core/hab-launcher and restart the Supervisor.",
+                        launcher,
+                        protocol::MIN_PROTOCOL_VERSION,
+                        protocol::PROTOCOL_VERSION)
+            }
             Error::IPCBincode(ref e) => {
                 format!("Unable to read message frame from Launcher, {}", e)
             }
             Error::IPCIO(ref e) => format!("Unable to receive message from Launcher, {:?}", e),
             Error::Protocol(ref e) => format!("{}", e),
+            Error::ProtocolFeature(feature, version) => {
+                format!("The Launcher does not support {}, which requires protocol version {} \
+                         (agreed on version {}). Install a newer core/hab-launcher to use it.",
+                        feature,
+                        feature.protocol_version(),
+                        version)
+            }
             Error::Send(ref e) => format!("Unable to send to Launcher's pipe, {}", e),
             Error::Timeout => "Launcher interaction timed out".to_string(),
         };
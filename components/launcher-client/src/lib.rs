@@ -4,7 +4,8 @@ extern crate log;
 mod client;
 pub mod error;
 
-pub use habitat_launcher_protocol::{ERR_NO_RETRY_EXCODE,
+pub use habitat_launcher_protocol::{LauncherFeature,
+                                    ERR_NO_RETRY_EXCODE,
                                     LAUNCHER_PID_ENV,
                                     OK_NO_RETRY_EXCODE};
 
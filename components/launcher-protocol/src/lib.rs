@@ -10,6 +10,7 @@ mod types;
 use crate::error::Result;
 pub use crate::{error::Error,
                 types::*};
+use std::fmt;
 
 pub const LAUNCHER_PIPE_ENV: &str = "HAB_LAUNCHER_PIPE";
 pub const LAUNCHER_PID_ENV: &str = "HAB_LAUNCHER_PID";
@@ -21,6 +22,62 @@ pub const OK_NO_RETRY_EXCODE: i32 = 84;
 /// exit code. The Launcher should exit immediately with a non-zero exit code.
 pub const ERR_NO_RETRY_EXCODE: i32 = 86;
 
+/// The newest version of the Supervisor/Launcher protocol this build speaks. Bump it whenever a
+/// message or field is added that the other side has to know about, and gate its use on a
+/// `LauncherFeature`.
+pub const PROTOCOL_VERSION: u32 = 4;
+/// The oldest version of the protocol this build still speaks. Together with `PROTOCOL_VERSION`
+/// this is the compatibility window between Supervisors and Launchers.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// The version spoken by Launchers that predate protocol negotiation. A Supervisor connected to
+/// one of these runs with core features only.
+pub const LEGACY_PROTOCOL_VERSION: u32 = 1;
+
+/// Features of the protocol beyond spawning, restarting and terminating services, which are only
+/// available once both sides have agreed on a recent enough protocol version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LauncherFeature {
+    /// Running a service in its own network namespace
+    NetworkNamespace,
+    /// Reporting the liveness of the Supervisor to the Launcher with `Heartbeat`s
+    Heartbeat,
+    /// Redacting the output of services following the Supervisor's redaction configuration
+    Redaction,
+}
+
+impl LauncherFeature {
+    /// The protocol version that introduced the feature
+    pub fn protocol_version(self) -> u32 {
+        match self {
+            LauncherFeature::NetworkNamespace => 2,
+            LauncherFeature::Heartbeat => 3,
+            LauncherFeature::Redaction => 4,
+        }
+    }
+}
+
+impl fmt::Display for LauncherFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let printable = match *self {
+            LauncherFeature::NetworkNamespace => "network namespaces",
+            LauncherFeature::Heartbeat => "liveness reporting",
+            LauncherFeature::Redaction => "redaction configuration",
+        };
+        write!(f, "{}", printable)
+    }
+}
+
+/// Pick the protocol version to use given the range spoken by the other side, which is the
+/// highest version both sides speak. Returns `None` if the ranges don't overlap.
+pub fn negotiate_protocol_version(min_version: u32, version: u32) -> Option<u32> {
+    let agreed = version.min(PROTOCOL_VERSION);
+    if agreed >= min_version.max(MIN_PROTOCOL_VERSION) {
+        Some(agreed)
+    } else {
+        None
+    }
+}
+
 #[derive(Debug)]
 pub struct NetTxn(Envelope);
 
@@ -55,3 +112,26 @@ pub fn error<T>(err: T) -> NetErr
     NetErr { msg:  err.to_string(),
              code: err.into(), }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiation_picks_the_highest_shared_version() {
+        assert_eq!(negotiate_protocol_version(MIN_PROTOCOL_VERSION, PROTOCOL_VERSION),
+                   Some(PROTOCOL_VERSION));
+        assert_eq!(negotiate_protocol_version(1, PROTOCOL_VERSION + 5),
+                   Some(PROTOCOL_VERSION));
+        assert_eq!(negotiate_protocol_version(LEGACY_PROTOCOL_VERSION, LEGACY_PROTOCOL_VERSION),
+                   Some(LEGACY_PROTOCOL_VERSION));
+    }
+
+    #[test]
+    fn negotiation_fails_outside_the_compatibility_window() {
+        assert_eq!(negotiate_protocol_version(PROTOCOL_VERSION + 1, PROTOCOL_VERSION + 2),
+                   None);
+        assert_eq!(negotiate_protocol_version(0, MIN_PROTOCOL_VERSION - 1),
+                   None);
+    }
+}
@@ -3,6 +3,7 @@ use crate::{error::{Error,
             generated};
 use prost::Message;
 use std::{collections::BTreeMap,
+          convert::TryFrom,
           fmt};
 
 pub trait LauncherMessage
@@ -27,6 +28,7 @@ pub trait LauncherMessage
 }
 
 pub use generated::{ErrCode,
+                    PortProtocol,
                     ShutdownMethod};
 
 // Now we're going to define our own set of structs to use internally, as well as conversion
@@ -124,14 +126,15 @@ impl From<Restart> for generated::Restart {
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Spawn {
-    pub id:           String,
-    pub binary:       String,
-    pub svc_user:     Option<String>,
-    pub svc_group:    Option<String>,
-    pub svc_password: Option<String>,
-    pub env:          BTreeMap<String, String>,
-    pub svc_user_id:  Option<u32>,
-    pub svc_group_id: Option<u32>,
+    pub id:                String,
+    pub binary:            String,
+    pub svc_user:          Option<String>,
+    pub svc_group:         Option<String>,
+    pub svc_password:      Option<String>,
+    pub env:               BTreeMap<String, String>,
+    pub svc_user_id:       Option<u32>,
+    pub svc_group_id:      Option<u32>,
+    pub network_namespace: Option<NetworkNamespace>,
 }
 
 impl LauncherMessage for Spawn {
@@ -140,27 +143,83 @@ impl LauncherMessage for Spawn {
     const MESSAGE_ID: &'static str = "Spawn";
 
     fn from_proto(proto: generated::Spawn) -> Result<Self> {
-        Ok(Spawn { id:           proto.id.ok_or(Error::ProtocolMismatch("id"))?,
-                   binary:       proto.binary.ok_or(Error::ProtocolMismatch("binary"))?,
-                   svc_user:     proto.svc_user,
-                   svc_group:    proto.svc_group,
-                   svc_password: proto.svc_password,
-                   env:          proto.env.into_iter().collect(),
-                   svc_user_id:  proto.svc_user_id,
-                   svc_group_id: proto.svc_group_id, })
+        Ok(Spawn { id:                proto.id.ok_or(Error::ProtocolMismatch("id"))?,
+                   binary:            proto.binary.ok_or(Error::ProtocolMismatch("binary"))?,
+                   svc_user:          proto.svc_user,
+                   svc_group:         proto.svc_group,
+                   svc_password:      proto.svc_password,
+                   env:               proto.env.into_iter().collect(),
+                   svc_user_id:       proto.svc_user_id,
+                   svc_group_id:      proto.svc_group_id,
+                   network_namespace: proto.network_namespace
+                                           .map(NetworkNamespace::from_proto)
+                                           .transpose()?, })
     }
 }
 
 impl From<Spawn> for generated::Spawn {
     fn from(value: Spawn) -> Self {
-        generated::Spawn { id:           Some(value.id),
-                           binary:       Some(value.binary),
-                           svc_user:     value.svc_user,
-                           svc_group:    value.svc_group,
-                           svc_password: value.svc_password,
-                           env:          value.env.into_iter().collect(),
-                           svc_user_id:  value.svc_user_id,
-                           svc_group_id: value.svc_group_id, }
+        generated::Spawn { id:                Some(value.id),
+                           binary:            Some(value.binary),
+                           svc_user:          value.svc_user,
+                           svc_group:         value.svc_group,
+                           svc_password:      value.svc_password,
+                           env:               value.env.into_iter().collect(),
+                           svc_user_id:       value.svc_user_id,
+                           svc_group_id:      value.svc_group_id,
+                           network_namespace: value.network_namespace.map(Into::into), }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetworkNamespace {
+    pub port_mappings: Vec<PortMapping>,
+}
+
+impl NetworkNamespace {
+    fn from_proto(proto: generated::NetworkNamespace) -> Result<Self> {
+        let port_mappings = proto.port_mappings
+                                 .into_iter()
+                                 .map(PortMapping::from_proto)
+                                 .collect::<Result<_>>()?;
+        Ok(NetworkNamespace { port_mappings })
+    }
+}
+
+impl From<NetworkNamespace> for generated::NetworkNamespace {
+    fn from(value: NetworkNamespace) -> Self {
+        generated::NetworkNamespace { port_mappings: value.port_mappings
+                                                          .into_iter()
+                                                          .map(Into::into)
+                                                          .collect(), }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PortMapping {
+    pub host_port:    u16,
+    pub service_port: u16,
+    pub protocol:     PortProtocol,
+}
+
+impl PortMapping {
+    fn from_proto(proto: generated::PortMapping) -> Result<Self> {
+        let port = |value: Option<u32>, field| {
+            value.and_then(|p| u16::try_from(p).ok())
+                 .ok_or(Error::ProtocolMismatch(field))
+        };
+        Ok(PortMapping { host_port:    port(proto.host_port, "host_port")?,
+                         service_port: port(proto.service_port, "service_port")?,
+                         protocol:     PortProtocol::from_i32(proto.protocol.unwrap_or_default())
+                                           .ok_or(Error::ProtocolMismatch("protocol"))?, })
+    }
+}
+
+impl From<PortMapping> for generated::PortMapping {
+    fn from(value: PortMapping) -> Self {
+        generated::PortMapping { host_port:    Some(value.host_port.into()),
+                                 service_port: Some(value.service_port.into()),
+                                 protocol:     Some(value.protocol as i32), }
     }
 }
 
@@ -354,3 +413,110 @@ impl LauncherMessage for Version {
 impl From<Version> for generated::Version {
     fn from(_value: Version) -> Self { generated::Version {} }
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NegotiateProtocol {
+    pub protocol_version:     u32,
+    pub min_protocol_version: u32,
+}
+
+impl Default for NegotiateProtocol {
+    fn default() -> Self {
+        NegotiateProtocol { protocol_version:     crate::PROTOCOL_VERSION,
+                            min_protocol_version: crate::MIN_PROTOCOL_VERSION, }
+    }
+}
+
+impl LauncherMessage for NegotiateProtocol {
+    type Generated = generated::NegotiateProtocol;
+
+    const MESSAGE_ID: &'static str = "NegotiateProtocol";
+
+    fn from_proto(proto: generated::NegotiateProtocol) -> Result<Self> {
+        Ok(NegotiateProtocol { protocol_version:
+                                   proto.protocol_version
+                                        .ok_or(Error::ProtocolMismatch("protocol_version"))?,
+                               min_protocol_version:
+                                   proto.min_protocol_version
+                                        .ok_or(Error::ProtocolMismatch("min_protocol_version"))?, })
+    }
+}
+
+impl From<NegotiateProtocol> for generated::NegotiateProtocol {
+    fn from(value: NegotiateProtocol) -> Self {
+        generated::NegotiateProtocol { protocol_version:     Some(value.protocol_version),
+                                       min_protocol_version: Some(value.min_protocol_version), }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProtocolVersions {
+    pub protocol_version:     u32,
+    pub min_protocol_version: u32,
+}
+
+impl Default for ProtocolVersions {
+    fn default() -> Self {
+        ProtocolVersions { protocol_version:     crate::PROTOCOL_VERSION,
+                           min_protocol_version: crate::MIN_PROTOCOL_VERSION, }
+    }
+}
+
+impl LauncherMessage for ProtocolVersions {
+    type Generated = generated::ProtocolVersions;
+
+    const MESSAGE_ID: &'static str = "ProtocolVersions";
+
+    fn from_proto(proto: generated::ProtocolVersions) -> Result<Self> {
+        Ok(ProtocolVersions { protocol_version:
+                                  proto.protocol_version
+                                       .ok_or(Error::ProtocolMismatch("protocol_version"))?,
+                              min_protocol_version:
+                                  proto.min_protocol_version
+                                       .ok_or(Error::ProtocolMismatch("min_protocol_version"))?, })
+    }
+}
+
+impl From<ProtocolVersions> for generated::ProtocolVersions {
+    fn from(value: ProtocolVersions) -> Self {
+        generated::ProtocolVersions { protocol_version:     Some(value.protocol_version),
+                                      min_protocol_version: Some(value.min_protocol_version), }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Heartbeat {}
+
+impl LauncherMessage for Heartbeat {
+    type Generated = generated::Heartbeat;
+
+    const MESSAGE_ID: &'static str = "Heartbeat";
+
+    fn from_proto(_proto: generated::Heartbeat) -> Result<Self> { Ok(Heartbeat {}) }
+}
+
+impl From<Heartbeat> for generated::Heartbeat {
+    fn from(_value: Heartbeat) -> Self { generated::Heartbeat {} }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SetRedaction {
+    pub config_file: String,
+}
+
+impl LauncherMessage for SetRedaction {
+    type Generated = generated::SetRedaction;
+
+    const MESSAGE_ID: &'static str = "SetRedaction";
+
+    fn from_proto(proto: generated::SetRedaction) -> Result<Self> {
+        Ok(SetRedaction { config_file: proto.config_file
+                                            .ok_or(Error::ProtocolMismatch("config_file"))?, })
+    }
+}
+
+impl From<SetRedaction> for generated::SetRedaction {
+    fn from(value: SetRedaction) -> Self {
+        generated::SetRedaction { config_file: Some(value.config_file), }
+    }
+}
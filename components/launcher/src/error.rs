@@ -4,7 +4,8 @@ use crate::{protocol,
 use std::{error,
           fmt,
           io,
-          result};
+          result,
+          time::Duration};
 
 #[derive(Debug)]
 pub enum Error {
@@ -13,6 +14,7 @@ pub enum Error {
     ExecWait(io::Error),
     GroupNotFound(String),
     HabitatCore(habitat_core::Error),
+    NetworkNamespace(String),
     OpenPipe(io::Error),
     Protocol(protocol::Error),
     Send(ipc_channel::Error),
@@ -22,6 +24,7 @@ pub enum Error {
     SupPackageNotFound,
     SupShutdown,
     SupSpawn(io::Error),
+    SupUnresponsive(Duration),
     UserNotFound(String),
 }
 
@@ -37,6 +40,7 @@ impl fmt::Display for Error {
             Error::ExecWait(ref e) => format!("Error waiting on PID, {}", e),
             Error::GroupNotFound(ref e) => format!("No GID for group '{}' could be found", e),
             Error::HabitatCore(ref err) => err.to_string(),
+            Error::NetworkNamespace(ref e) => format!("Unable to set up network namespace, {}", e),
             Error::OpenPipe(ref e) => format!("Unable to open Launcher's comm channel, {}", e),
             Error::Protocol(ref e) => format!("{}", e),
             Error::Send(ref e) => format!("Unable to send to Launcher's comm channel, {}", e),
@@ -50,6 +54,9 @@ impl fmt::Display for Error {
             }
             Error::SupShutdown => "Error waiting for Supervisor to shutdown".to_string(),
             Error::SupSpawn(ref e) => format!("Unable to spawn Supervisor, {}", e),
+            Error::SupUnresponsive(timeout) => {
+                format!("Supervisor sent no heartbeat for {}s", timeout.as_secs())
+            }
             Error::UserNotFound(ref e) => format!("No UID for user '{}' could be found", e),
         };
         write!(f, "{}", msg)
@@ -63,6 +70,7 @@ impl From<Error> for protocol::ErrCode {
         match err {
             Error::ExecWait(_) => protocol::ErrCode::ExecWait,
             Error::GroupNotFound(_) => protocol::ErrCode::GroupNotFound,
+            Error::NetworkNamespace(_) => protocol::ErrCode::NetworkNamespace,
             Error::UserNotFound(_) => protocol::ErrCode::UserNotFound,
             _ => protocol::ErrCode::Unknown,
         }
@@ -1,6 +1,8 @@
 extern crate habitat_core as core;
 use habitat_launcher_protocol as protocol;
 #[macro_use]
+extern crate lazy_static;
+#[macro_use]
 extern crate log;
 #[cfg(windows)]
 extern crate winapi;
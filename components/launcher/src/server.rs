@@ -1,10 +1,13 @@
 mod handlers;
+mod liveness;
 
 use crate::{core::{self,
                    fs::{launcher_root_path,
                         FS_ROOT_PATH},
                    os::{process,
-                        signals},
+                        signals,
+                        systemd::{self,
+                                  Watchdog}},
                    package::{PackageIdent,
                              PackageInstall}},
             error::{Error,
@@ -12,13 +15,17 @@ use crate::{core::{self,
             protocol::{self,
                        ERR_NO_RETRY_EXCODE,
                        OK_NO_RETRY_EXCODE},
-            server::handlers::Handler,
+            server::{handlers::Handler,
+                     liveness::{Failure,
+                                Heartbeats,
+                                RestartBudget}},
             service::Service,
             SUP_CMD,
             SUP_PACKAGE_IDENT};
 use habitat_common::{liveliness_checker::{self,
                                           ThreadUnregistered},
-                     outputln};
+                     outputln,
+                     redaction};
 #[cfg(unix)]
 use habitat_core::os::process::{Pid,
                                 Signal};
@@ -34,7 +41,8 @@ use std::{cmp::Ordering,
 use std::{collections::HashMap,
           fs,
           io::Write,
-          path::PathBuf,
+          path::{Path,
+                 PathBuf},
           process::{Child,
                     Command,
                     Stdio},
@@ -43,7 +51,8 @@ use std::{collections::HashMap,
                  Condvar,
                  Mutex},
           thread,
-          time::Duration};
+          time::{Duration,
+                 Instant}};
 
 const IPC_CONNECT_TIMEOUT_SECS: &str = "HAB_LAUNCH_SUP_CONNECT_TIMEOUT_SECS";
 const DEFAULT_IPC_CONNECT_TIMEOUT_SECS: u64 = 5;
@@ -72,6 +81,9 @@ pub struct Server {
     pipe:          String,
     supervisor:    Child,
     args:          Vec<String>,
+    restarts:      RestartBudget,
+    heartbeats:    Heartbeats,
+    watchdog:      Watchdog,
 }
 
 impl Drop for Server {
@@ -97,7 +109,10 @@ impl Server {
                     rx,
                     pipe,
                     supervisor,
-                    args })
+                    args,
+                    restarts: RestartBudget::default(),
+                    heartbeats: Heartbeats::default(),
+                    watchdog: Watchdog::from_env(&[std::process::id()]) })
     }
 
     /// Spawn a Supervisor and setup a bi-directional IPC connection to it.
@@ -129,6 +144,7 @@ impl Server {
         self.tx = tx;
         self.rx = rx;
         self.supervisor = supervisor;
+        self.heartbeats.reset();
         // We're connecting to a new supervisor instance, so we need to remove
         // the socket files for the old pipe to avoid https://github.com/habitat-sh/habitat/issues/4673
         #[cfg(not(windows))]
@@ -137,6 +153,68 @@ impl Server {
         Ok(())
     }
 
+    /// Restart the Supervisor, retrying until it starts, unless it has been restarted too often.
+    /// The Launcher keeps pinging the systemd watchdog on its own behalf meanwhile.
+    fn restart(&mut self) -> std::result::Result<(), Failure> {
+        loop {
+            self.watchdog.ping_if_due(Instant::now());
+            self.restarts.spend(Instant::now())?;
+            match self.reload() {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    error!("Unable to restart Supervisor, {}", err);
+                    thread::sleep(Duration::from_millis(1_000));
+                }
+            }
+        }
+    }
+
+    /// Stop the Supervisor and its services for good, telling the service manager the Launcher
+    /// runs under why. Returns the exit code of the Launcher, which gives the reason.
+    #[allow(unused_must_use)]
+    fn give_up(&mut self, failure: Failure) -> i32 {
+        outputln!("{}; shutting everything down now", failure);
+        if let Ok(None) = self.supervisor.try_wait() {
+            self.supervisor.kill();
+            self.supervisor.wait();
+        }
+        self.services.kill_all();
+        systemd::notify_status(&failure.to_string());
+        failure.exit_code()
+    }
+
+    /// Record a heartbeat of the Supervisor. The first one tells systemd that it is up.
+    fn heartbeat(&mut self) {
+        if self.heartbeats.beat(Instant::now()) {
+            debug!("Supervisor {} is sending heartbeats", self.supervisor.id());
+            systemd::notify(systemd::READY);
+        }
+    }
+
+    /// Record whether the Supervisor agreed on a protocol version that has it send heartbeats,
+    /// before the negotiation is handled as usual
+    fn negotiate(&mut self, msg: &protocol::NetTxn) {
+        if let Ok(negotiate) = msg.decode::<protocol::NegotiateProtocol>() {
+            let agreed = protocol::negotiate_protocol_version(negotiate.min_protocol_version,
+                                                              negotiate.protocol_version);
+            let heartbeat = protocol::LauncherFeature::Heartbeat.protocol_version();
+            self.heartbeats
+                .expect(agreed.map_or(false, |agreed| agreed >= heartbeat));
+        }
+    }
+
+    /// Ping the systemd watchdog on behalf of the Launcher and the Supervisor, as long as the
+    /// Supervisor sends heartbeats and has not gone without one for as long as systemd waits for
+    /// a ping. A Supervisor too old to send heartbeats pings the watchdog itself.
+    fn ping_watchdog(&mut self) {
+        let now = Instant::now();
+        if let Some(timeout) = self.watchdog.timeout() {
+            if self.heartbeats.vouch_for(timeout, now) {
+                self.watchdog.ping_if_due(now);
+            }
+        }
+    }
+
     // Signals aren't a thing on Windows
     #[cfg(unix)]
     fn forward_signal(&self, signal: Signal) {
@@ -150,7 +228,19 @@ impl Server {
     fn handle_message(&mut self) -> Result<TickState> {
         match self.rx.try_recv() {
             Ok(bytes) => {
-                dispatch(&self.tx, &bytes, &mut self.services);
+                match protocol::NetTxn::from_bytes(&bytes) {
+                    // Heartbeats are about the Supervisor itself rather than its services, and
+                    // are not replied to
+                    Ok(msg) if msg.message_id() == "Heartbeat" => self.heartbeat(),
+                    Ok(msg) if msg.message_id() == "SetRedaction" => set_redaction(&msg),
+                    Ok(msg) => {
+                        if msg.message_id() == "NegotiateProtocol" {
+                            self.negotiate(&msg);
+                        }
+                        dispatch(&self.tx, msg, &mut self.services)
+                    }
+                    Err(err) => error!("Unable to decode NetTxn from Supervisor, {}", err),
+                }
                 Ok(TickState::Continue)
             }
             Err(_) => {
@@ -176,10 +266,7 @@ impl Server {
     fn handle_supervisor_exit(&mut self, code: Option<i32>) -> Result<TickState> {
         debug!("launcher::server::handle_supervisor_exit(code: {:?})", code);
         match code {
-            Some(ERR_NO_RETRY_EXCODE) => {
-                self.services.kill_all();
-                Ok(TickState::Exit(ERR_NO_RETRY_EXCODE))
-            }
+            Some(ERR_NO_RETRY_EXCODE) => Ok(TickState::Exit(self.give_up(Failure::Refused))),
             Some(OK_NO_RETRY_EXCODE) => {
                 self.services.kill_all();
                 Ok(TickState::Exit(0))
@@ -196,6 +283,7 @@ impl Server {
 
     fn shutdown(&mut self) {
         debug!("Shutting down...");
+        systemd::notify(systemd::STOPPING);
         if send(&self.tx, &protocol::Shutdown::default()).is_err() {
             warn!("Forcefully stopping Supervisor: {}", self.supervisor.id());
             if let Err(err) = self.supervisor.kill() {
@@ -279,6 +367,12 @@ impl Server {
                 self.forward_signal(Signal::HUP);
             }
         }
+
+        if self.heartbeats.timed_out(Instant::now()) {
+            let err = Error::SupUnresponsive(self.heartbeats.timeout().unwrap_or_default());
+            outputln!("{}; restarting it", err);
+            return Err(err);
+        }
         self.handle_message()
     }
 
@@ -454,19 +548,23 @@ impl ServiceTable {
 //
 
 pub fn run(args: Vec<String>) -> Result<i32> {
+    #[cfg(target_os = "linux")]
+    crate::sys::systemd::keep_listen_fds_from_services();
     let mut server = Server::new(args)?;
     liveliness_checker::spawn_thread_alive_checker();
     let loop_value: ThreadUnregistered<_, _> = loop {
         let checked_thread = liveliness_checker::mark_thread_alive();
+        server.ping_watchdog();
 
         match server.tick() {
             Ok(TickState::Continue) => thread::sleep(Duration::from_millis(100)),
             Ok(TickState::Exit(code)) => {
                 break checked_thread.unregister(Ok(code));
             }
-            Err(_) => {
-                while server.reload().is_err() {
-                    thread::sleep(Duration::from_millis(1_000));
+            Err(err) => {
+                debug!("Restarting Supervisor, {}", err);
+                if let Err(failure) = server.restart() {
+                    break checked_thread.unregister(Ok(server.give_up(failure)));
                 }
             }
         }
@@ -487,20 +585,14 @@ pub fn send<T>(tx: &Sender, msg: &T) -> Result<()>
 // Private Func
 //
 
-fn dispatch(tx: &Sender, bytes: &[u8], services: &mut ServiceTable) {
-    let msg = match protocol::NetTxn::from_bytes(bytes) {
-        Ok(msg) => msg,
-        Err(err) => {
-            error!("Unable to decode NetTxn from Supervisor, {}", err);
-            return;
-        }
-    };
+fn dispatch(tx: &Sender, msg: protocol::NetTxn, services: &mut ServiceTable) {
     let func = match msg.message_id() {
         "Restart" => handlers::RestartHandler::run,
         "Spawn" => handlers::SpawnHandler::run,
         "Terminate" => handlers::TerminateHandler::run,
         "PidOf" => handlers::PidHandler::run,
         "Version" => handlers::VersionHandler::run,
+        "NegotiateProtocol" => handlers::NegotiateHandler::run,
         unknown => {
             // This sucks a bit because it replicates some code from the
             // Handler trait, but manipulating an unknown message
@@ -518,6 +610,19 @@ fn dispatch(tx: &Sender, bytes: &[u8], services: &mut ServiceTable) {
     func(tx, msg, services);
 }
 
+/// Redact the output of services following the redaction configuration the Supervisor passed
+/// down, which is not replied to
+fn set_redaction(msg: &protocol::NetTxn) {
+    match msg.decode::<protocol::SetRedaction>() {
+        Ok(set_redaction) => {
+            if let Err(e) = redaction::init(Path::new(&set_redaction.config_file)) {
+                error!("{}; keeping the redaction configuration in use", e);
+            }
+        }
+        Err(err) => error!("Unable to decode SetRedaction from Supervisor, {}", err),
+    }
+}
+
 #[allow(clippy::mutex_atomic)] // A Mutex is required for Condvar::wait_timeout
 fn setup_connection(server: IpcOneShotServer<Vec<u8>>) -> Result<(Receiver, Sender)> {
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
@@ -628,6 +733,8 @@ fn spawn_supervisor(pipe: &str, args: &[String]) -> Result<Child> {
     }
 
     let mut command = Command::new(&binary);
+    #[cfg(target_os = "linux")]
+    crate::sys::systemd::pass_listen_fds(&mut command);
 
     debug!("Starting Supervisor {:?} with args {:?}, {}={}...",
            binary,
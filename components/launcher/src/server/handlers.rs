@@ -1,10 +1,12 @@
+mod negotiate;
 mod pid;
 mod restart;
 mod spawn;
 mod terminate;
 mod version;
 
-pub use self::{pid::*,
+pub use self::{negotiate::*,
+               pid::*,
                restart::*,
                spawn::*,
                terminate::*,
@@ -0,0 +1,32 @@
+use super::{HandleResult,
+            Handler};
+use crate::{protocol,
+            server::ServiceTable};
+
+pub struct NegotiateHandler;
+
+impl Handler for NegotiateHandler {
+    type Message = protocol::NegotiateProtocol;
+    type Reply = protocol::ProtocolVersions;
+
+    fn handle(msg: Self::Message, _: &mut ServiceTable) -> HandleResult<Self::Reply> {
+        match protocol::negotiate_protocol_version(msg.min_protocol_version, msg.protocol_version) {
+            Some(version) => {
+                debug!("Agreed on protocol version {} with Supervisor", version);
+                Ok(protocol::ProtocolVersions::default())
+            }
+            None => {
+                let err_msg = format!("Supervisor speaks protocol versions {} to {}, but this \
+                                       Launcher speaks versions {} to {}",
+                                      msg.min_protocol_version,
+                                      msg.protocol_version,
+                                      protocol::MIN_PROTOCOL_VERSION,
+                                      protocol::PROTOCOL_VERSION);
+                warn!("{}", err_msg);
+                let reply = protocol::NetErr { code: protocol::ErrCode::IncompatibleProtocol,
+                                               msg:  err_msg, };
+                Err(reply)
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Keeping the Supervisor alive, and giving up on it when it can't be.
+//!
+//! The Launcher restarts a Supervisor that exits, and, with
+//! `HAB_LAUNCH_SUP_HEARTBEAT_TIMEOUT_SECS`, one that has stopped sending heartbeats for that long.
+//! With `HAB_LAUNCH_SUP_MAX_RESTARTS`, it gives up once it has restarted the Supervisor that many
+//! times within `HAB_LAUNCH_SUP_RESTART_WINDOW_SECS`, and exits with an exit code giving the
+//! reason, so that the service manager it runs under marks it failed.
+
+use crate::protocol::ERR_NO_RETRY_EXCODE;
+use std::{collections::VecDeque,
+          fmt,
+          time::{Duration,
+                 Instant}};
+
+habitat_core::env_config_int!(/// The most times the Supervisor is restarted within the restart
+                              /// window before the Launcher gives up on it, or 0 to never give up
+                              #[derive(Debug)]
+                              SupMaxRestarts,
+                              u32,
+                              HAB_LAUNCH_SUP_MAX_RESTARTS,
+                              0);
+
+habitat_core::env_config_duration!(/// The window `HAB_LAUNCH_SUP_MAX_RESTARTS` counts restarts in
+                                   SupRestartWindow,
+                                   HAB_LAUNCH_SUP_RESTART_WINDOW_SECS => from_secs,
+                                   Duration::from_secs(300));
+
+habitat_core::env_config_duration!(/// How long a Supervisor that sends heartbeats may go without
+                                   /// one before it is restarted, or 0 to never restart it
+                                   SupHeartbeatTimeout,
+                                   HAB_LAUNCH_SUP_HEARTBEAT_TIMEOUT_SECS => from_secs,
+                                   Duration::from_secs(0));
+
+/// The exit code of a Launcher that gave up on restarting a failing Supervisor
+pub const SUP_RESTART_LIMIT_EXCODE: i32 = 87;
+
+/// Why the Launcher stopped keeping the Supervisor alive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// The Supervisor exited with `ERR_NO_RETRY_EXCODE`, asking not to be restarted
+    Refused,
+    /// The Supervisor was restarted `HAB_LAUNCH_SUP_MAX_RESTARTS` times within the restart window
+    RestartLimit { restarts: u32, window: Duration },
+}
+
+impl Failure {
+    /// The exit code the Launcher reports the failure with
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Failure::Refused => ERR_NO_RETRY_EXCODE,
+            Failure::RestartLimit { .. } => SUP_RESTART_LIMIT_EXCODE,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Failure::Refused => write!(f, "Supervisor exited and asked not to be restarted"),
+            Failure::RestartLimit { restarts, window } => {
+                write!(f,
+                       "Supervisor failed after being restarted {} times within {}s",
+                       restarts,
+                       window.as_secs())
+            }
+        }
+    }
+}
+
+/// The restarts of the Supervisor within the restart window
+#[derive(Debug)]
+pub struct RestartBudget {
+    max:      Option<u32>,
+    window:   Duration,
+    restarts: VecDeque<Instant>,
+}
+
+impl Default for RestartBudget {
+    fn default() -> Self {
+        Self::new(SupMaxRestarts::configured_value().0,
+                  SupRestartWindow::configured_value().into())
+    }
+}
+
+impl RestartBudget {
+    /// A budget of `max` restarts within `window`. A `max` of 0 is no limit.
+    pub fn new(max: u32, window: Duration) -> Self {
+        RestartBudget { max: Some(max).filter(|max| *max > 0),
+                        window,
+                        restarts: VecDeque::new() }
+    }
+
+    /// Count a restart at `now`, unless there have been too many already
+    pub fn spend(&mut self, now: Instant) -> Result<(), Failure> {
+        let max = match self.max {
+            Some(max) => max,
+            None => return Ok(()),
+        };
+        while let Some(restart) = self.restarts.front() {
+            if now.saturating_duration_since(*restart) < self.window {
+                break;
+            }
+            self.restarts.pop_front();
+        }
+        if self.restarts.len() >= max as usize {
+            return Err(Failure::RestartLimit { restarts: max,
+                                               window:   self.window, });
+        }
+        self.restarts.push_back(now);
+        Ok(())
+    }
+}
+
+/// The heartbeats of the running Supervisor
+#[derive(Debug)]
+pub struct Heartbeats {
+    timeout:  Option<Duration>,
+    /// Whether the Supervisor agreed on a protocol version that has it send heartbeats
+    expected: bool,
+    last:     Option<Instant>,
+}
+
+impl Default for Heartbeats {
+    fn default() -> Self { Self::new(SupHeartbeatTimeout::configured_value().into()) }
+}
+
+impl Heartbeats {
+    /// Heartbeats that time out after `timeout`. A `timeout` of 0 never times out.
+    pub fn new(timeout: Duration) -> Self {
+        Heartbeats { timeout:  Some(timeout).filter(|timeout| *timeout > Duration::from_secs(0)),
+                     expected: false,
+                     last:     None, }
+    }
+
+    /// Record whether the Supervisor agreed on a protocol version that has it send heartbeats
+    pub fn expect(&mut self, expected: bool) { self.expected = expected; }
+
+    /// Record a heartbeat at `now`. Returns whether it is the first from the Supervisor.
+    pub fn beat(&mut self, now: Instant) -> bool { self.last.replace(now).is_none() }
+
+    /// Forget the heartbeats of a Supervisor that was restarted
+    pub fn reset(&mut self) {
+        self.expected = false;
+        self.last = None;
+    }
+
+    /// Whether the Launcher may vouch for the Supervisor, as it sends heartbeats and has sent one
+    /// within `within` of `now`. A Supervisor that sends them counts as alive until its first,
+    /// while it starts. One that is too old to send them never does, and vouches for itself.
+    pub fn vouch_for(&self, within: Duration, now: Instant) -> bool {
+        self.expected
+        && self.last
+               .map_or(true, |last| now.saturating_duration_since(last) <= within)
+    }
+
+    /// Whether the Supervisor has gone without a heartbeat for longer than the timeout since
+    /// its last one
+    pub fn timed_out(&self, now: Instant) -> bool {
+        match (self.timeout, self.last) {
+            (Some(timeout), Some(last)) => now.saturating_duration_since(last) > timeout,
+            _ => false,
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> { self.timeout }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restarts_are_limited_within_the_window() {
+        let mut budget = RestartBudget::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(budget.spend(now).is_ok());
+        assert!(budget.spend(now + Duration::from_secs(10)).is_ok());
+        assert_eq!(budget.spend(now + Duration::from_secs(20)),
+                   Err(Failure::RestartLimit { restarts: 2,
+                                               window:   Duration::from_secs(60), }));
+        assert!(budget.spend(now + Duration::from_secs(60)).is_ok());
+
+        let mut unlimited = RestartBudget::new(0, Duration::from_secs(60));
+        assert!((0..1_000).all(|_| unlimited.spend(now).is_ok()));
+    }
+
+    #[test]
+    fn supervisors_time_out_once_they_stop_sending_heartbeats() {
+        let mut heartbeats = Heartbeats::new(Duration::from_secs(30));
+        let now = Instant::now();
+        assert!(!heartbeats.timed_out(now + Duration::from_secs(600)));
+        assert!(heartbeats.beat(now));
+        assert!(!heartbeats.beat(now + Duration::from_secs(1)));
+        assert!(!heartbeats.timed_out(now + Duration::from_secs(31)));
+        assert!(heartbeats.timed_out(now + Duration::from_secs(32)));
+
+        heartbeats.reset();
+        assert!(!heartbeats.timed_out(now + Duration::from_secs(600)));
+        assert!(!Heartbeats::new(Duration::from_secs(0)).timed_out(now));
+    }
+
+    #[test]
+    fn only_supervisors_that_send_heartbeats_are_vouched_for() {
+        let within = Duration::from_secs(30);
+        let now = Instant::now();
+        let mut heartbeats = Heartbeats::new(Duration::from_secs(0));
+        assert!(!heartbeats.vouch_for(within, now));
+
+        heartbeats.expect(true);
+        assert!(heartbeats.vouch_for(within, now));
+        heartbeats.beat(now);
+        assert!(heartbeats.vouch_for(within, now + within));
+        assert!(!heartbeats.vouch_for(within, now + within + Duration::from_secs(1)));
+
+        heartbeats.reset();
+        assert!(!heartbeats.vouch_for(within, now));
+    }
+}
@@ -2,6 +2,14 @@
 #[path = "sys/unix/service.rs"]
 pub mod service;
 
+#[cfg(target_os = "linux")]
+#[path = "sys/unix/netns.rs"]
+pub mod netns;
+
+#[cfg(target_os = "linux")]
+#[path = "sys/unix/systemd.rs"]
+pub mod systemd;
+
 #[cfg(windows)]
 #[path = "sys/windows/service.rs"]
 pub mod service;
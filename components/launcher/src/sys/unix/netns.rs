@@ -0,0 +1,282 @@
+//! Per-service network namespaces.
+//!
+//! A service spawned with a `NetworkNamespace` gets a network stack of its own. The Launcher
+//! creates a named namespace (`hab-<service group>`), connects it to the host with a veth pair on
+//! a private /30 subnet, and forwards the port mappings declared in the service's plan to it with
+//! iptables DNAT rules. Everything is torn down again when the `NetworkNamespace` is dropped, which
+//! happens once the service's process has exited.
+//!
+//! IPv4 forwarding is turned on while any namespace exists, and set back to what it was once the
+//! last of them is torn down.
+//!
+//! All of the plumbing is done by shelling out to `ip` and `iptables`, which must be on the
+//! Launcher's `PATH`.
+
+use crate::{core::os::process::exec::unix::{network_namespace_name,
+                                            network_namespace_path},
+            error::{Error,
+                    Result},
+            protocol};
+use std::{collections::BTreeSet,
+          fs::{self,
+               File},
+          net::Ipv4Addr,
+          os::unix::io::{AsRawFd,
+                         RawFd},
+          path::{Path,
+                 PathBuf},
+          process::Command,
+          sync::Mutex};
+
+/// Every namespace gets a /30 out of 10.254.0.0/16, giving room for this many at once
+const MAX_NAMESPACES: u16 = 16_384;
+
+const IP_FORWARD: &str = "/proc/sys/net/ipv4/ip_forward";
+
+lazy_static! {
+    static ref HOST: Mutex<HostState> = Mutex::new(HostState::default());
+}
+
+/// What the Launcher has changed on the host for its namespaces
+#[derive(Default)]
+struct HostState {
+    /// The indexes of the namespaces that exist or are being set up
+    indexes:    BTreeSet<u16>,
+    /// The value of `ip_forward` before the first of them was set up
+    ip_forward: Option<String>,
+}
+
+pub struct NetworkNamespace {
+    name:      String,
+    path:      PathBuf,
+    index:     u16,
+    host_veth: String,
+    /// iptables rules to delete on teardown, as (table, chain, rule specification)
+    rules:     Vec<(&'static str, &'static str, Vec<String>)>,
+    file:      Option<File>,
+}
+
+impl NetworkNamespace {
+    /// Create the network namespace for the service identified by `service_id` (e.g.
+    /// "redis.default"), along with its veth pair and port mappings.
+    pub fn create(service_id: &str, spec: &protocol::NetworkNamespace) -> Result<Self> {
+        let index = claim_index()?;
+        let svc_veth = format!("hab{}s", index);
+        let (host_addr, svc_addr) = subnet_addrs(index);
+        // From here on, dropping `netns` undoes whatever has been set up so far
+        let mut netns = NetworkNamespace { name: network_namespace_name(service_id),
+                                           path: network_namespace_path(service_id),
+                                           index,
+                                           host_veth: host_veth_name(index),
+                                           rules: Vec::new(),
+                                           file: None };
+
+        // A namespace left behind by a previous Launcher would make `ip netns add` fail
+        if netns.path.exists() {
+            debug!("Removing stale network namespace {}", netns.name);
+            ip(&["netns", "del", &netns.name]).ok();
+        }
+        ip(&["netns", "add", &netns.name])?;
+        netns.file = Some(File::open(&netns.path).map_err(|e| {
+                              Error::NetworkNamespace(format!("{}: {}", netns.path.display(), e))
+                          })?);
+
+        let name = netns.name.as_str();
+        let host_cidr = format!("{}/30", host_addr);
+        let svc_cidr = format!("{}/30", svc_addr);
+        let host_addr = host_addr.to_string();
+        ip(&["link",
+             "add",
+             &netns.host_veth,
+             "type",
+             "veth",
+             "peer",
+             "name",
+             &svc_veth])?;
+        ip(&["link", "set", &svc_veth, "netns", name])?;
+        ip(&["addr", "add", &host_cidr, "dev", &netns.host_veth])?;
+        ip(&["link", "set", &netns.host_veth, "up"])?;
+        // Connections to a mapped port on 127.0.0.1 are DNATed out of the host veth, which the
+        // kernel only routes with `route_localnet`
+        let route_localnet = format!("/proc/sys/net/ipv4/conf/{}/route_localnet", netns.host_veth);
+        fs::write(&route_localnet, "1").map_err(|e| {
+                                           Error::NetworkNamespace(format!("Unable to enable \
+                                                                            route_localnet: {}",
+                                                                           e))
+                                       })?;
+        ip(&["-n", name, "link", "set", "lo", "up"])?;
+        ip(&["-n", name, "addr", "add", &svc_cidr, "dev", &svc_veth])?;
+        ip(&["-n", name, "link", "set", &svc_veth, "up"])?;
+        ip(&["-n", name, "route", "add", "default", "via", &host_addr])?;
+
+        let svc_host = format!("{}/32", svc_addr);
+        netns.add_rule("nat", "POSTROUTING", &["-s", &svc_host, "-j", "MASQUERADE"])?;
+        // Replies to connections from 127.0.0.1 must come back to the host, not the namespace's
+        // own loopback
+        netns.add_rule("nat",
+                       "POSTROUTING",
+                       &["-s",
+                         "127.0.0.0/8",
+                         "-o",
+                         &host_veth_name(index),
+                         "-j",
+                         "MASQUERADE"])?;
+        netns.add_rule("filter", "FORWARD", &["-s", &svc_host, "-j", "ACCEPT"])?;
+        netns.add_rule("filter", "FORWARD", &["-d", &svc_host, "-j", "ACCEPT"])?;
+        for mapping in &spec.port_mappings {
+            let protocol = match mapping.protocol {
+                protocol::PortProtocol::Tcp => "tcp",
+                protocol::PortProtocol::Udp => "udp",
+            };
+            let host_port = mapping.host_port.to_string();
+            let destination = format!("{}:{}", svc_addr, mapping.service_port);
+            // PREROUTING covers traffic from other hosts, OUTPUT traffic from this one
+            for &chain in &["PREROUTING", "OUTPUT"] {
+                netns.add_rule("nat",
+                               chain,
+                               &["-p",
+                                 protocol,
+                                 "-m",
+                                 "addrtype",
+                                 "--dst-type",
+                                 "LOCAL",
+                                 "--dport",
+                                 &host_port,
+                                 "-j",
+                                 "DNAT",
+                                 "--to-destination",
+                                 &destination])?;
+            }
+        }
+        debug!("Created network namespace {} ({} <-> {})",
+               netns.name, netns.host_veth, svc_veth);
+        Ok(netns)
+    }
+
+    /// A file descriptor referring to the namespace, suitable for `setns(2)`
+    pub fn fd(&self) -> RawFd {
+        self.file
+            .as_ref()
+            .expect("network namespace file is opened on creation")
+            .as_raw_fd()
+    }
+
+    fn add_rule(&mut self, table: &'static str, chain: &'static str, rule: &[&str]) -> Result<()> {
+        let rule: Vec<String> = rule.iter().map(ToString::to_string).collect();
+        iptables(table, "-A", chain, &rule)?;
+        self.rules.push((table, chain, rule));
+        Ok(())
+    }
+}
+
+impl Drop for NetworkNamespace {
+    fn drop(&mut self) {
+        for (table, chain, rule) in self.rules.drain(..).rev() {
+            if let Err(e) = iptables(table, "-D", chain, &rule) {
+                warn!("Unable to remove iptables rule for {}: {}", self.name, e);
+            }
+        }
+        // Deleting either end of a veth pair deletes the other. It may already be gone with
+        // the namespace, or may never have been created, so failures are not interesting.
+        ip(&["link", "del", &self.host_veth]).ok();
+        self.file = None;
+        if self.path.exists() {
+            if let Err(e) = ip(&["netns", "del", &self.name]) {
+                warn!("Unable to remove network namespace {}: {}", self.name, e);
+            }
+        }
+        release_index(self.index);
+        debug!("Removed network namespace {}", self.name);
+    }
+}
+
+/// Claim the lowest index that is neither used by another of our namespaces nor by a host veth
+/// left behind on the host, turning on IPv4 forwarding for the first namespace. Claiming under the
+/// lock keeps concurrent spawns from picking the same index.
+fn claim_index() -> Result<u16> {
+    let mut host = HOST.lock().expect("Network namespace lock poisoned");
+    let index = (0..MAX_NAMESPACES).find(|i| {
+                                       !host.indexes.contains(i)
+                                       && !Path::new("/sys/class/net").join(host_veth_name(*i))
+                                                                      .exists()
+                                   })
+                                   .ok_or_else(|| {
+                                       Error::NetworkNamespace(String::from("No free subnets are \
+                                                                             left"))
+                                   })?;
+    if host.indexes.is_empty() {
+        let previous = fs::read_to_string(IP_FORWARD).map_err(|e| {
+                           Error::NetworkNamespace(format!("Unable to read IPv4 forwarding: {}", e))
+                       })?;
+        fs::write(IP_FORWARD, "1").map_err(|e| {
+                                      Error::NetworkNamespace(format!("Unable to enable IPv4 \
+                                                                       forwarding: {}",
+                                                                      e))
+                                  })?;
+        host.ip_forward = Some(previous.trim().to_string());
+    }
+    host.indexes.insert(index);
+    Ok(index)
+}
+
+/// Give back `index`, setting IPv4 forwarding back to what it was once no namespaces are left
+fn release_index(index: u16) {
+    let mut host = HOST.lock().expect("Network namespace lock poisoned");
+    host.indexes.remove(&index);
+    if host.indexes.is_empty() {
+        if let Some(previous) = host.ip_forward.take() {
+            if let Err(e) = fs::write(IP_FORWARD, &previous) {
+                warn!("Unable to restore IPv4 forwarding to {}: {}", previous, e);
+            }
+        }
+    }
+}
+
+fn host_veth_name(index: u16) -> String { format!("hab{}h", index) }
+
+/// The host and service addresses of the /30 subnet for `index`
+fn subnet_addrs(index: u16) -> (Ipv4Addr, Ipv4Addr) {
+    let base = u32::from(Ipv4Addr::new(10, 254, 0, 0)) + u32::from(index) * 4;
+    (Ipv4Addr::from(base + 1), Ipv4Addr::from(base + 2))
+}
+
+fn ip(args: &[&str]) -> Result<()> { run("ip", args) }
+
+fn iptables(table: &str, action: &str, chain: &str, rule: &[String]) -> Result<()> {
+    let mut args = vec!["-w", "-t", table, action, chain];
+    args.extend(rule.iter().map(String::as_str));
+    run("iptables", &args)
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    debug!("Running {} {}", program, args.join(" "));
+    let output =
+        Command::new(program).args(args)
+                             .output()
+                             .map_err(|e| {
+                                 Error::NetworkNamespace(format!("Unable to run {}: {}", program, e))
+                             })?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::NetworkNamespace(format!("'{} {}' failed: {}",
+                                            program,
+                                            args.join(" "),
+                                            String::from_utf8_lossy(&output.stderr).trim())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subnets_do_not_overlap() {
+        assert_eq!(subnet_addrs(0),
+                   (Ipv4Addr::new(10, 254, 0, 1), Ipv4Addr::new(10, 254, 0, 2)));
+        assert_eq!(subnet_addrs(1),
+                   (Ipv4Addr::new(10, 254, 0, 5), Ipv4Addr::new(10, 254, 0, 6)));
+        assert_eq!(subnet_addrs(MAX_NAMESPACES - 1),
+                   (Ipv4Addr::new(10, 254, 255, 253), Ipv4Addr::new(10, 254, 255, 254)));
+    }
+}
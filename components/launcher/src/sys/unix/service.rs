@@ -1,3 +1,5 @@
+#[cfg(target_os = "linux")]
+use crate::sys::netns::NetworkNamespace;
 use crate::{error::{Error,
                     Result},
             protocol::{self,
@@ -16,15 +18,20 @@ use std::{io,
           time::{Duration,
                  Instant}};
 
-pub struct Process(Child);
+pub struct Process {
+    child:             Child,
+    /// Torn down when the process is dropped, i.e. once it has exited
+    #[cfg(target_os = "linux")]
+    network_namespace: Option<NetworkNamespace>,
+}
 
 impl Process {
-    pub fn id(&self) -> u32 { self.0.id() }
+    pub fn id(&self) -> u32 { self.child.id() }
 
     /// Attempt to gracefully terminate a process and then forcefully kill it after
     /// 8 seconds if it has not terminated.
     pub fn kill(&mut self) -> ShutdownMethod {
-        let mut pid_to_kill = self.0.id() as i32;
+        let mut pid_to_kill = self.child.id() as i32;
         // check the group of the process being killed
         // if it is the root process of the process group
         // we send our signals to the entire process group
@@ -63,9 +70,9 @@ impl Process {
         }
     }
 
-    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> { self.0.try_wait() }
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> { self.child.try_wait() }
 
-    pub fn wait(&mut self) -> io::Result<ExitStatus> { self.0.wait() }
+    pub fn wait(&mut self) -> io::Result<ExitStatus> { self.child.wait() }
 }
 
 pub fn run(msg: protocol::Spawn) -> Result<Service> {
@@ -90,12 +97,35 @@ pub fn run(msg: protocol::Spawn) -> Result<Service> {
     };
     let gid = Gid::from_raw(group_id);
 
-    let mut cmd = exec::unix::hook_command(&msg.binary, &msg.env, Some((uid, gid)));
+    #[cfg(target_os = "linux")]
+    let (mut child, network_namespace) = match &msg.network_namespace {
+        Some(spec) => {
+            let network_namespace = NetworkNamespace::create(&msg.id, spec)?;
+            let mut cmd = exec::unix::hook_command_in_network_namespace(&msg.binary,
+                                                                        &msg.env,
+                                                                        Some((uid, gid)),
+                                                                        network_namespace.fd());
+            (cmd.spawn().map_err(Error::Spawn)?, Some(network_namespace))
+        }
+        None => {
+            let mut cmd = exec::unix::hook_command(&msg.binary, &msg.env, Some((uid, gid)));
+            (cmd.spawn().map_err(Error::Spawn)?, None)
+        }
+    };
+    #[cfg(not(target_os = "linux"))]
+    let mut child = {
+        if msg.network_namespace.is_some() {
+            return Err(Error::NetworkNamespace(String::from("only supported on Linux")));
+        }
+        let mut cmd = exec::unix::hook_command(&msg.binary, &msg.env, Some((uid, gid)));
+        cmd.spawn().map_err(Error::Spawn)?
+    };
 
-    let mut child = cmd.spawn().map_err(Error::Spawn)?;
     let stdout = child.stdout.take();
     let stderr = child.stderr.take();
-    let process = Process(child);
+    let process = Process { child,
+                            #[cfg(target_os = "linux")]
+                            network_namespace };
     debug!(target: "pidfile_tracing", "Launcher spawned {} with PID = {}", msg.binary, process.id());
     Ok(Service::new(msg, process, stdout, stderr))
 }
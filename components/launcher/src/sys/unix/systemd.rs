@@ -0,0 +1,61 @@
+//! Handing the sockets of systemd socket activation on to the Supervisor.
+//!
+//! systemd passes the sockets of a socket-activated unit to its main process, the Launcher, as the
+//! descriptors from 3 on, naming the process they are meant for in `LISTEN_PID`. The Launcher
+//! keeps them from being inherited by the processes of services, and passes them to each
+//! Supervisor it starts, which takes its gateway listeners from them.
+
+use std::{env,
+          io,
+          os::unix::{io::RawFd,
+                     process::CommandExt},
+          process::{self,
+                    Command}};
+
+/// The first descriptor systemd passes sockets as
+const LISTEN_FDS_START: RawFd = 3;
+
+/// The descriptors of the sockets systemd passed to the Launcher, if any
+fn listen_fds() -> Vec<RawFd> {
+    let for_us = env::var("LISTEN_PID").ok()
+                                       .and_then(|pid| pid.parse::<u32>().ok())
+                 == Some(process::id());
+    let count = env::var("LISTEN_FDS").ok()
+                                      .and_then(|count| count.parse::<RawFd>().ok())
+                                      .filter(|_| for_us)
+                                      .unwrap_or(0);
+    (LISTEN_FDS_START..LISTEN_FDS_START + count).collect()
+}
+
+/// Close the sockets systemd passed on exec, so that the processes of services don't inherit
+/// them. Called once, when the Launcher starts.
+pub fn keep_listen_fds_from_services() {
+    let fds = listen_fds();
+    for fd in &fds {
+        unsafe {
+            libc::fcntl(*fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+    }
+    if !fds.is_empty() {
+        debug!("Passing {} socket(s) from systemd on to the Supervisor",
+               fds.len());
+    }
+}
+
+/// Let the Supervisor started by `command` inherit the sockets systemd passed.
+pub fn pass_listen_fds(command: &mut Command) {
+    let fds = listen_fds();
+    if fds.is_empty() {
+        return;
+    }
+    unsafe {
+        command.pre_exec(move || {
+                   for fd in &fds {
+                       if libc::fcntl(*fd, libc::F_SETFD, 0) == -1 {
+                           return Err(io::Error::last_os_error());
+                       }
+                   }
+                   Ok(())
+               });
+    }
+}
@@ -116,6 +116,9 @@ impl Process {
 
 pub fn run(msg: protocol::Spawn) -> Result<Service> {
     debug!("launcher is spawning {}", msg.binary);
+    if msg.network_namespace.is_some() {
+        return Err(Error::NetworkNamespace(String::from("only supported on Linux")));
+    }
     let ps_cmd = format!("iex $(gc {} | out-string)", &msg.binary);
     let password = msg.svc_password.clone();
 
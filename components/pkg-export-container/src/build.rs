@@ -13,6 +13,8 @@ use clap::{self,
            ArgMatches};
 #[cfg(unix)]
 use failure::SyncFailure;
+#[cfg(unix)]
+use hab::command::pkg::binlink::ConflictPolicy;
 use hab::license;
 use habitat_common::{command::package::install::{InstallHookMode,
                                                  InstallMode,
@@ -280,7 +282,11 @@ impl BuildSpec {
                       -> Result<()> {
         let dst = util::bin_path();
         for pkg in user_pkgs.iter() {
-            hab::command::pkg::binlink::binlink_all_in_pkg(ui, pkg.as_ref(), dst, rootfs, true)
+            hab::command::pkg::binlink::binlink_all_in_pkg(ui,
+                                                           pkg.as_ref(),
+                                                           dst,
+                                                           rootfs,
+                                                           ConflictPolicy::Force)
                 .map_err(SyncFailure::new)?;
         }
         Ok(())
@@ -297,9 +303,14 @@ impl BuildSpec {
                                                                 .as_ref(),
                                                        dst,
                                                        rootfs,
-                                                       true).map_err(SyncFailure::new)?;
-        hab::command::pkg::binlink::start(ui, base_pkgs.hab.as_ref(), "hab", dst, rootfs, true)
+                                                       ConflictPolicy::Force)
             .map_err(SyncFailure::new)?;
+        hab::command::pkg::binlink::start(ui,
+                                          base_pkgs.hab.as_ref(),
+                                          "hab",
+                                          dst,
+                                          rootfs,
+                                          ConflictPolicy::Force).map_err(SyncFailure::new)?;
         Ok(())
     }
 
@@ -121,6 +121,11 @@ $script:pkg_svc_run = ''
 $script:pkg_expose = @()
 # An associative array representing configuration data which should be gossiped to peers.
 $script:pkg_exports = @{}
+# An associative array of hooks which should only run on the leader or only on the followers of a
+# service group using the leader topology.
+$script:pkg_hook_roles = @{}
+# An associative array of hooks which should be run again when they fail, and how.
+$script:pkg_hook_retries = @{}
 # The user to run the service as
 $script:pkg_svc_user = ""
 # svc_group is not actually used on Windows but it needs to exist.
@@ -144,6 +149,10 @@ $script:pkg_svc_config_path="$pkg_svc_path\config"
 $script:pkg_svc_config_install_path="$pkg_svc_path\config_install"
 $script:pkg_svc_static_path="$pkg_svc_path\static"
 
+# The durations of the build phases, for the build report
+$script:_build_report_phases = @()
+# The files fetched with `_download_file`, for the build report
+$script:_build_report_sources = @()
 
 # ## Private/Internal helper functions
 #
@@ -857,6 +866,7 @@ function _download_file($url, $dst, $sha) {
             Write-BuildLine "Found previous file '$dst', attempting to re-use"
             if (_verify_file $dst $sha) {
                 Write-BuildLine "Using cached and verified '$dst'"
+                $script:_build_report_sources += $dst
                 return
             } else {
                 Write-BuildLine "Clearing previous '$dst' file and re-attempting download"
@@ -867,6 +877,7 @@ function _download_file($url, $dst, $sha) {
         Write-BuildLine "Downloading '$url' to '$dst'"
         Invoke-WebRequest $url -OutFile $dst -UserAgent "Habitat"
         Write-BuildLine "Downloaded '$dst'"
+        $script:_build_report_sources += $dst
     } finally {
         Pop-Location
     }
@@ -1260,6 +1271,10 @@ function Invoke-DefaultBuildConfig {
         Write-BuildLine "Writing default.toml"
         Copy-Item "$PLAN_CONTEXT/default.toml" $pkg_prefix
     }
+    if (Test-Path "$PLAN_CONTEXT/config_schema.json") {
+        Write-BuildLine "Writing config_schema.json"
+        Copy-Item "$PLAN_CONTEXT/config_schema.json" $pkg_prefix
+    }
 }
 
 # Write out the `$pkg_prefix\run` file. If a file named `hooks\run`
@@ -1402,6 +1417,30 @@ function Write-Metadata {
         }
     }
 
+    if ($pkg_hook_roles) {
+        foreach ($hookRole in $pkg_hook_roles.GetEnumerator()) {
+            if ($hookRole.Key -notin @("backup", "file-updated", "post-run", "reconfigure", "reload")) {
+                throw "Bad key in pkg_hook_roles; Only backup, file-updated, post-run, reconfigure and reload hooks can be restricted to a role: $($hookRole.Key)"
+            }
+            if ($hookRole.Value -notin @("leader", "follower")) {
+                throw "Bad value in pkg_hook_roles; Role must be leader or follower: $($hookRole.Key)=$($hookRole.Value)"
+            }
+            "$($hookRole.Key)=$($hookRole.Value)" | Out-File "$pkg_prefix\HOOK_ROLES" -Encoding ascii -Append
+        }
+    }
+
+    if ($pkg_hook_retries) {
+        foreach ($hookRetry in $pkg_hook_retries.GetEnumerator()) {
+            if ($hookRetry.Key -notin @("init", "post-run")) {
+                throw "Bad key in pkg_hook_retries; Only init and post-run hooks can be retried: $($hookRetry.Key)"
+            }
+            if ($hookRetry.Value -notmatch "^[1-9][0-9]*:[0-9]+$") {
+                throw "Bad value in pkg_hook_retries; Retries take the form attempts:backoff_seconds: $($hookRetry.Key)=$($hookRetry.Value)"
+            }
+            "$($hookRetry.Key)=$($hookRetry.Value)" | Out-File "$pkg_prefix\HOOK_RETRIES" -Encoding ascii -Append
+        }
+    }
+
     if ($pkg_binds) {
         foreach ($bind in $pkg_binds.GetEnumerator()) {
             "$($bind.Key)=$($bind.Value)" | Out-File "$pkg_prefix\BINDS" -Encoding ascii -Append
@@ -1509,6 +1548,55 @@ function Copy-BuildOutput {
 "@ | Out-File $lastBuild -Encoding ascii
 }
 
+# **Internal** Record how long a build phase took for the build report.
+function Add-BuildPhaseDuration($phase, $timer) {
+    $script:_build_report_phases += [ordered]@{
+        name        = $phase
+        duration_ms = $timer.ElapsedMilliseconds
+    }
+}
+
+# **Internal** Run a build phase, recording how long it took for the build
+# report.
+function Invoke-TimedPhase($phase, [ScriptBlock]$block) {
+    $timer = [System.Diagnostics.Stopwatch]::StartNew()
+    & $block
+    Add-BuildPhaseDuration $phase $timer
+}
+
+# **Internal** Write `last_build.json`, a report of how long each phase of the
+# build took and how large its sources, artifact and runtime dependencies are,
+# so CI can track build time and size regressions.
+function Write-BuildReport {
+    Write-BuildLine "Writing build report"
+    $files = @(Get-ChildItem $pkg_prefix -Recurse -File -Force)
+    $depFiles = @($pkg_tdeps_resolved | ForEach-Object { Get-ChildItem $_ -Recurse -File -Force })
+    $sources = @($_build_report_sources | Where-Object { Test-Path "$HAB_CACHE_SRC_PATH\$_" } | ForEach-Object {
+            [ordered]@{
+                filename   = $_
+                size_bytes = (Get-Item "$HAB_CACHE_SRC_PATH\$_").Length
+            }
+        })
+
+    [ordered]@{
+        pkg_ident            = "$pkg_origin/$pkg_name/$pkg_version/$pkg_release"
+        pkg_target           = $pkg_target
+        phases               = @($_build_report_phases)
+        total_duration_ms    = [int64]($_build_report_phases | ForEach-Object { $_.duration_ms } | Measure-Object -Sum).Sum
+        sources              = $sources
+        artifact             = [ordered]@{
+            filename             = Split-Path $pkg_artifact -Leaf
+            size_bytes           = (Get-Item $pkg_artifact).Length
+            installed_size_bytes = [int64]($files | Measure-Object -Property Length -Sum).Sum
+            file_count           = $files.Count
+        }
+        runtime_dependencies = [ordered]@{
+            count      = @($pkg_tdeps_resolved).Count
+            size_bytes = [int64]($depFiles | Measure-Object -Property Length -Sum).Sum
+        }
+    } | ConvertTo-Json -Depth 4 | Out-File "$pkg_output_path\last_build.json" -Encoding ascii
+}
+
 # A function for cleaning up after yourself. Delegates most of the
 # implementation to the `Invoke-DefaultEnd` function.
 function Invoke-End {
@@ -1749,6 +1837,7 @@ try {
     Set-HabBin
 
     # Download and resolve the depdencies
+    $dependenciesTimer = [System.Diagnostics.Stopwatch]::StartNew()
     # Create initial package arrays
     Initialize-DependencyList
 
@@ -1786,6 +1875,7 @@ try {
     _Set_DependencyArrays
 
     Assert-DepsAreValid
+    Add-BuildPhaseDuration "dependencies" $dependenciesTimer
 
     # Write out a prebuild file so workers can have some metadata about failed builds
     Write-PreBuildFile
@@ -1797,19 +1887,19 @@ try {
     New-Item "$HAB_CACHE_SRC_PATH" -ItemType Directory -Force | Out-Null
 
     # Run any code after the environment is set but before the build starts
-    Invoke-Before
+    Invoke-TimedPhase before { Invoke-Before }
 
     # Download the source
-    Invoke-Download
+    Invoke-TimedPhase download { Invoke-Download }
 
     # Verify the source
-    Invoke-Verify
+    Invoke-TimedPhase verify { Invoke-Verify }
 
     # Clean the cache
-    Invoke-Clean
+    Invoke-TimedPhase clean { Invoke-Clean }
 
     # Unpack the source
-    Invoke-Unpack
+    Invoke-TimedPhase unpack { Invoke-Unpack }
 
     # Set up the build environment
     Set-Environment
@@ -1817,39 +1907,42 @@ try {
     Assert-PkgVersion
 
     # Prepare the source
-    Invoke-PrepareWrapper
+    Invoke-TimedPhase prepare { Invoke-PrepareWrapper }
 
     # Build the source
-    Invoke-BuildWrapper
+    Invoke-TimedPhase build { Invoke-BuildWrapper }
 
     # Check the source
-    Invoke-CheckWrapper
+    Invoke-TimedPhase check { Invoke-CheckWrapper }
 
     # Install the source
-    Invoke-InstallWrapper
+    Invoke-TimedPhase install { Invoke-InstallWrapper }
 
     # Copy the configuration
-    Invoke-BuildConfig
+    Invoke-TimedPhase build_config { Invoke-BuildConfig }
 
     # Copy the service management scripts
-    Invoke-BuildService
+    Invoke-TimedPhase build_service { Invoke-BuildService }
 
     # Run any code after the package has finished building and installing, but
     # before the artifact metadata is generated and the artifact is signed.
-    Invoke-After
+    Invoke-TimedPhase after { Invoke-After }
 
     # Write the manifest
-    Write-Manifest
+    Invoke-TimedPhase manifest { Write-Manifest }
 
     # Render the linking and dependency files
-    Write-Metadata
+    Invoke-TimedPhase metadata { Write-Metadata }
 
     # Generate the artifact and write to artifact cache
-    Save-Artifact
+    Invoke-TimedPhase artifact { Save-Artifact }
 
     # Copy produced artifact to a local relative directory
     Copy-BuildOutput
 
+    # Report the phase timings and sizes of the build
+    Write-BuildReport
+
     # Cleanup
     Write-BuildLine "$program cleanup"
     Invoke-End
@@ -1869,6 +1962,7 @@ Write-BuildLine "Source Path: $SRC_PATH"
 Write-BuildLine "Installed Path: $pkg_prefix"
 Write-BuildLine "Artifact: $pkg_output_path\$(Split-Path $pkg_artifact -Leaf)"
 Write-BuildLine "Build Report: $pkg_output_path\last_build.ps1"
+Write-BuildLine "Build Timing Report: $pkg_output_path\last_build.json"
 Write-BuildLine "SHA256 Checksum: $_pkg_sha256sum"
 Write-BuildLine "Blake2b Checksum: $_pkg_blake2bsum"
 
@@ -193,6 +193,44 @@
 # pkg_exposes=(port)
 # ```
 #
+# ### pkg_network_namespace
+# Set to `true` to have the Launcher run the service in its own network namespace on Linux. The
+# service gets a private network stack reachable from the host through a veth pair, so services
+# listening on the same port no longer conflict.
+# ```
+# pkg_network_namespace=true
+# ```
+#
+# ### pkg_port_mappings
+# An array of host ports to forward into the service's network namespace, in the form
+# `host_port:service_port[/tcp|udp]`. Only used when `pkg_network_namespace` is `true`.
+# ```
+# pkg_port_mappings=(8080:80 5353:53/udp)
+# ```
+#
+# ### pkg_hook_roles
+# An associative array of hooks which should only run on the leader or only on the followers of a
+# service group using the leader topology. The keys are hook names (`backup`, `file-updated`,
+# `post-run`, `reconfigure` or `reload`) and the values are either `leader` or `follower`.
+# ```
+# pkg_hook_roles=(
+#   [post-run]=leader
+#   [reconfigure]=follower
+# )
+# ```
+#
+# ### pkg_hook_retries
+# An associative array of hooks which should be run again when they fail, before the Supervisor
+# gives up on them. The keys are hook names (`init` or `post-run`) and the values take the form
+# `attempts:backoff`, where `attempts` is how often the hook is run at most and `backoff` is the
+# number of seconds to wait after the first failure, doubling after every further one.
+# ```
+# pkg_hook_retries=(
+#   [init]=5:2
+#   [post-run]=10:1
+# )
+# ```
+#
 # ### pkg_binds
 # An associative array representing services which you depend on and the configuration keys that
 # you expect the service to export (by their `pkg_exports`). These binds *must* be set for the
@@ -391,7 +429,11 @@ pkg_pconfig_dirs=()
 # The command to run the service - must not fork or return
 pkg_svc_run=''
 pkg_exposes=()
+pkg_network_namespace=false
+pkg_port_mappings=()
 declare -A pkg_exports
+declare -A pkg_hook_roles
+declare -A pkg_hook_retries
 declare -A pkg_binds
 declare -A pkg_binds_optional
 # The user to run the service as
@@ -426,6 +468,10 @@ umask 0022
 unset TERMINFO
 
 _artifact_ext="hart"
+# The `<phase>:<milliseconds>` durations of the build phases, for the build report
+_build_report_phases=()
+# The files fetched with `download_file`, for the build report
+_build_report_sources=()
 
 # ## Private/Internal helper functions
 #
@@ -1993,6 +2039,9 @@ _build_metadata() {
   _render_metadata_RUNTIME_ENVIRONMENT_PROVENANCE
   _render_metadata_SHUTDOWN_SIGNAL
   _render_metadata_SHUTDOWN_TIMEOUT
+  _render_metadata_PORT_MAPPINGS
+  _render_metadata_HOOK_ROLES
+  _render_metadata_HOOK_RETRIES
 
   # Only generate `SVC_USER` & `SVC_GROUP` files if this package is a service.
   # We determine this by checking if there is a `hooks/run` script and/or
@@ -2039,6 +2088,9 @@ do_default_build_config() {
   if [[ -f "$PLAN_CONTEXT/default.toml" ]]; then
     cp "$PLAN_CONTEXT/default.toml" "$pkg_prefix"
   fi
+  if [[ -f "$PLAN_CONTEXT/config_schema.json" ]]; then
+    cp "$PLAN_CONTEXT/config_schema.json" "$pkg_prefix"
+  fi
   return 0
 }
 
@@ -2318,6 +2370,82 @@ EOF
   chown "$plan_owner" "$pkg_output_path/last_build.env" || true
 }
 
+# **Internal** Run a build phase, recording how long it took for the build
+# report.
+#
+# ```sh
+# _time_phase download do_download
+# ```
+_time_phase() {
+  local phase="$1"
+  local start
+  shift
+
+  start="$(date +%s%3N)"
+  "$@"
+  _build_report_phases+=("${phase}:$(( $(date +%s%3N) - start ))")
+}
+
+# **Internal** Write `last_build.json`, a report of how long each phase of the
+# build took and how large its sources, artifact and runtime dependencies are,
+# so CI can track build time and size regressions.
+_write_build_report() {
+  local plan_owner report total_ms phase name sep
+  local artifact_bytes installed_bytes file_count deps_bytes
+  plan_owner="$(stat -c '%u:%g' "$PLAN_CONTEXT/plan.sh")"
+  report="$pkg_output_path/last_build.json"
+
+  build_line "Writing build report"
+
+  artifact_bytes="$(stat -c %s "$pkg_artifact")"
+  installed_bytes="$(du -sb "$pkg_prefix" | cut -f 1)"
+  file_count="$(find "$pkg_prefix" -type f | wc -l)"
+  deps_bytes=0
+  if [[ "${#pkg_tdeps_resolved[@]}" -gt 0 ]]; then
+    deps_bytes="$(du -sbc "${pkg_tdeps_resolved[@]}" | tail -n 1 | cut -f 1)"
+  fi
+
+  {
+    echo "{"
+    echo "  \"pkg_ident\": \"${pkg_origin}/${pkg_name}/${pkg_version}/${pkg_release}\","
+    echo "  \"pkg_target\": \"${pkg_target}\","
+    echo "  \"phases\": ["
+    total_ms=0
+    sep=""
+    for phase in "${_build_report_phases[@]}"; do
+      printf '%s    {"name": "%s", "duration_ms": %d}' "$sep" "${phase%%:*}" "${phase##*:}"
+      total_ms=$(( total_ms + ${phase##*:} ))
+      sep=$',\n'
+    done
+    echo
+    echo "  ],"
+    echo "  \"total_duration_ms\": ${total_ms},"
+    echo "  \"sources\": ["
+    sep=""
+    for name in "${_build_report_sources[@]}"; do
+      if [[ -f "$HAB_CACHE_SRC_PATH/$name" ]]; then
+        printf '%s    {"filename": "%s", "size_bytes": %d}' \
+          "$sep" "${name//\"/\\\"}" "$(stat -c %s "$HAB_CACHE_SRC_PATH/$name")"
+        sep=$',\n'
+      fi
+    done
+    echo
+    echo "  ],"
+    echo "  \"artifact\": {"
+    echo "    \"filename\": \"$(basename "$pkg_artifact")\","
+    echo "    \"size_bytes\": ${artifact_bytes},"
+    echo "    \"installed_size_bytes\": ${installed_bytes},"
+    echo "    \"file_count\": ${file_count}"
+    echo "  },"
+    echo "  \"runtime_dependencies\": {"
+    echo "    \"count\": ${#pkg_tdeps_resolved[@]},"
+    echo "    \"size_bytes\": ${deps_bytes}"
+    echo "  }"
+    echo "}"
+  } > "$report"
+  chown "$plan_owner" "$report" || true
+}
+
 # A function for cleaning up after yourself. Delegates most of the
 # implementation to the `do_default_end()` function.
 do_end() {
@@ -2635,7 +2763,7 @@ _write_pre_build_file
 # Ensure that the origin key is available for package signing
 _ensure_origin_key_present
 
-_resolve_dependencies
+_time_phase dependencies _resolve_dependencies
 
 # Set up runtime and buildtime environments
 #
@@ -2661,19 +2789,19 @@ _set_build_path
 mkdir -pv "$HAB_CACHE_SRC_PATH"
 
 # Run any code after the environment is set but before the build starts
-do_before
+_time_phase before do_before
 
 # Download the source
-do_download
+_time_phase download do_download
 
 # Verify the source
-do_verify
+_time_phase verify do_verify
 
 # Clean the cache
-do_clean
+_time_phase clean do_clean
 
 # Unpack the source
-do_unpack
+_time_phase unpack do_unpack
 
 # Set up the build environment
 _build_environment
@@ -2688,46 +2816,49 @@ _verify_vars
 _verify_hook_line_endings
 
 # Prepare the source
-do_prepare_wrapper
+_time_phase prepare do_prepare_wrapper
 
 # Build the source
-do_build_wrapper
+_time_phase build do_build_wrapper
 
 # Check the source
-do_check_wrapper
+_time_phase check do_check_wrapper
 
 # Install the source
-do_install_wrapper
+_time_phase install do_install_wrapper
 
 # Copy the configuration
-do_build_config
+_time_phase build_config do_build_config
 
 # Copy the service management scripts
-do_build_service
+_time_phase build_service do_build_service
 
 # Strip the binaries
-do_strip
+_time_phase strip do_strip
 
 # Run any code after the package has finished building and installing, but
 # before the artifact metadata is generated and the artifact is signed.
-do_after
+_time_phase after do_after
 
 # Render the linking and dependency files
-_build_metadata
+_time_phase metadata _build_metadata
 
 # The FILES file must be the last metadata file generated, as it lists
 # all the other metadata files within it.
 _render_metadata_FILES
 
 # Write the manifest
-_build_manifest
+_time_phase manifest _build_manifest
 
 # Generate the artifact and write to artifact cache
-_generate_artifact
+_time_phase artifact _generate_artifact
 
 # Copy produced artifact to a local relative directory
 _prepare_build_outputs
 
+# Report the phase timings and sizes of the build
+_write_build_report
+
 # Cleanup
 build_line "$_program cleanup"
 do_end
@@ -2743,6 +2874,7 @@ build_line "Source Path: $SRC_PATH"
 build_line "Installed Path: $pkg_prefix"
 build_line "Artifact: $pkg_output_path/$(basename "$pkg_artifact")"
 build_line "Build Report: $pkg_output_path/last_build.env"
+build_line "Build Timing Report: $pkg_output_path/last_build.json"
 build_line "SHA256 Checksum: $_pkg_sha256sum"
 build_line "Blake2b Checksum: $_pkg_blake2bsum"
 
@@ -357,6 +357,7 @@ download_file() {
     build_line "Found previous file '$dst', attempting to re-use"
     if verify_file "$dst" "$sha"; then
       build_line "Using cached and verified '$dst'"
+      _build_report_sources+=("$dst")
       return 0
     else
       build_line "Clearing previous '$dst' file and re-attempting download"
@@ -368,6 +369,7 @@ download_file() {
   # shellcheck disable=2154
   $_wget_cmd "$url" -O "$dst"
   build_line "Downloaded '$dst'";
+  _build_report_sources+=("$dst")
   popd > /dev/null
 }
 
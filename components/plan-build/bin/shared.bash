@@ -80,6 +80,32 @@ _render_metadata_EXPOSES() {
   fi
 }
 
+_render_metadata_HOOK_ROLES() {
+  # shellcheck disable=2154
+  for hook in "${!pkg_hook_roles[@]}"; do
+    if [[ ! "$hook" =~ ^(backup|file-updated|post-run|reconfigure|reload)$ ]]; then
+      exit_with "Bad key in pkg_hook_roles; Only backup, file-updated, post-run, reconfigure and reload hooks can be restricted to a role: ${hook}"
+    fi
+    if [[ ! "${pkg_hook_roles[$hook]}" =~ ^(leader|follower)$ ]]; then
+      exit_with "Bad value in pkg_hook_roles; Role must be leader or follower: ${hook}=${pkg_hook_roles[$hook]}"
+    fi
+  done
+  _render_associative_array_file "${pkg_prefix}" HOOK_ROLES pkg_hook_roles
+}
+
+_render_metadata_HOOK_RETRIES() {
+  # shellcheck disable=2154
+  for hook in "${!pkg_hook_retries[@]}"; do
+    if [[ ! "$hook" =~ ^(init|post-run)$ ]]; then
+      exit_with "Bad key in pkg_hook_retries; Only init and post-run hooks can be retried: ${hook}"
+    fi
+    if [[ ! "${pkg_hook_retries[$hook]}" =~ ^[1-9][0-9]*:[0-9]+$ ]]; then
+      exit_with "Bad value in pkg_hook_retries; Retries take the form attempts:backoff_seconds: ${hook}=${pkg_hook_retries[$hook]}"
+    fi
+  done
+  _render_associative_array_file "${pkg_prefix}" HOOK_RETRIES pkg_hook_retries
+}
+
 # Generate the blake2b hashes of all the files in the package. This
 # is not in the resulting MANIFEST because MANIFEST is included!
 _render_metadata_FILES() {
@@ -260,6 +286,29 @@ _render_metadata_SHUTDOWN_TIMEOUT() {
   fi
 }
 
+_render_metadata_PORT_MAPPINGS() {
+  # shellcheck disable=2154
+  if [[ "${pkg_network_namespace}" != "true" ]]; then
+    if [[ ${#pkg_port_mappings[@]} -gt 0 ]]; then
+      warn "pkg_port_mappings is ignored unless pkg_network_namespace=true"
+    fi
+    return 0
+  fi
+
+  for mapping in "${pkg_port_mappings[@]}"; do
+    local ports="${mapping%/*}"
+    if [[ "$mapping" == */* && ! "${mapping##*/}" =~ ^(tcp|udp)$ ]]; then
+      exit_with "Bad value in pkg_port_mappings; Protocol must be tcp or udp: ${mapping}"
+    fi
+    if [[ "$ports" != *:* ]] || ! _port_is_valid "${ports%%:*}" || ! _port_is_valid "${ports#*:}"; then
+      exit_with "Bad value in pkg_port_mappings; Expected host_port:service_port[/tcp|udp]: ${mapping}"
+    fi
+  done
+
+  debug "Rendering PORT_MAPPINGS metadata file"
+  echo "${pkg_port_mappings[*]}" > "$pkg_prefix"/PORT_MAPPINGS
+}
+
 _render_metadata_TARGET() {
   debug "Rendering TARGET metadata file"
   # shellcheck disable=2154
@@ -76,6 +76,11 @@ COMMON OPTIONS:
     -t <STUDIO_TYPE>      Sets a Studio type when creating (default: default)
                           Valid types: [default baseimage busybox stage1]
 
+HAB STUDIO OPTIONS (also read from a \`.studiorc.toml' in the current directory):
+    --env <VAR>[=<VALUE>]     Passes an environment variable into the Studio
+    --mount <SRC>:<DEST>      Bind-mounts an extra path into the Studio
+    --port <HOST>[:<STUDIO>]  Publishes a port of a Docker Studio
+
 SUBCOMMANDS:
     build     Build using a Studio
     enter     Interactively enter a Studio
@@ -94,6 +99,7 @@ ENVIRONMENT VARIABLES:
     HAB_ORIGIN             Propagates this variable into any studios
     HAB_ORIGIN_KEYS        Installs secret keys (\`-k' option overrides)
     HAB_STUDIOS_HOME       Sets a home path for all Studios (default: /hab/studios)
+    HAB_STUDIO_MOUNTS      Extra \`<SRC>:<DEST>' bind-mounts into the Studio, one per line
     HAB_STUDIO_NOSTUDIORC  Disables sourcing a \`.studiorc' in \`studio enter'
     HAB_STUDIO_ROOT        Sets a Studio root (\`-r' option overrides)
     HAB_STUDIO_SUP         Sets args for a Supervisor in \`studio enter'
@@ -619,6 +625,30 @@ EOF
         $bb mount $v --bind "$SRC_PATH" "$HAB_STUDIO_ROOT/src"
       fi
     fi
+
+    # Bind-mount each extra `<SRC>:<DEST>` line of `$HAB_STUDIO_MOUNTS`, unless
+    # `$NO_MOUNT` is set. Each mount point is recorded so that it is unmounted
+    # even if a later invocation doesn't ask for it.
+    if [ -z "${NO_MOUNT}" ] && [ -n "${HAB_STUDIO_MOUNTS:-}" ]; then
+      echo "$HAB_STUDIO_MOUNTS" | while read -r extra_mount; do
+        [ -n "$extra_mount" ] || continue
+        extra_src="${extra_mount%:*}"
+        extra_dst="${HAB_STUDIO_ROOT}${extra_mount##*:}"
+        if ! $bb mount | $bb grep -q "on $extra_dst type"; then
+          if [ -d "$extra_src" ]; then
+            # shellcheck disable=2086
+            $bb mkdir -p $v "$extra_dst"
+          else
+            # shellcheck disable=2086
+            $bb mkdir -p $v "$($bb dirname "$extra_dst")"
+            $bb touch "$extra_dst"
+          fi
+          # shellcheck disable=2086
+          $bb mount $v --bind "$extra_src" "$extra_dst"
+          echo "$extra_dst" >> "$extra_mounts_file"
+        fi
+      done
+    fi
   fi
 }
 
@@ -1102,6 +1132,15 @@ is_fs_mounted() {
 # convergent on success and fast fail on failures, this can be safely run
 # multiple times across differnt program invocations.
 unmount_filesystems() {
+  # Extra mounts may live under `/src`, so they go first
+  if [ -f "$extra_mounts_file" ]; then
+    while read -r extra_dst; do
+      # shellcheck disable=2086
+      umount_fs $v -l "$extra_dst"
+    done < "$extra_mounts_file"
+    $bb rm -f "$extra_mounts_file"
+  fi
+
   # shellcheck disable=2086
   umount_fs $v -l "$HAB_STUDIO_ROOT/src"
 
@@ -1348,6 +1387,9 @@ dir_name="$(echo "$SRC_PATH" | $bb sed -e 's,^/$,root,' -e 's,^/,,' -e 's,/,--,g
 # extra environment variables, etc. Note that a valid Studio will have this
 # file at the root of its filesystem.
 studio_config="$HAB_STUDIO_ROOT/.studio"
+# The record of the extra mount points made from `$HAB_STUDIO_MOUNTS`, one per
+# line, which are unmounted along with the Studio's other filesystems.
+extra_mounts_file="$HAB_STUDIO_ROOT/.studio-mounts"
 # The type (flavor, variant, etc.) of Studio. Such types include `default`,
 # `stage1`, and `busybox` among others.
 : "${STUDIO_TYPE:=}"
@@ -206,6 +206,8 @@ impl SrvClient {
     /// 2. cli.toml
     /// 3. default value
     ///
+    /// When a named ring is selected, the default address uses the ring's port.
+    ///
     /// This is public because it allows parts of the code to lookup the address, log messages with
     /// that address, and then use that address to actually make the request.
     pub fn ctl_addr(maybe_addr: Option<&ResolvedListenCtlAddr>)
@@ -214,7 +216,11 @@ impl SrvClient {
             Ok(addr.clone())
         } else {
             let config = CliConfig::load()?;
-            Ok(config.listen_ctl.unwrap_or_default())
+            let addr = config.listen_ctl.unwrap_or_default();
+            match protocol::ring_name() {
+                Some(ring_name) => Ok(addr.for_ring(&ring_name)),
+                None => Ok(addr),
+            }
         }
     }
 
@@ -1,9 +1,11 @@
+use std::{env,
+          path::PathBuf};
+
 /// Automatically generate Rust code from our protobuf definitions at
 /// compile time.
 ///
 /// Generated code is deposited in `OUT_DIR` and automatically
 /// `include!`-ed in our Rust modules, per standard Prost practice.
-
 fn main() {
     let mut config = prost_build::Config::new();
     config.type_attribute(".",
@@ -14,4 +16,16 @@ fn main() {
                             "protocols/types.proto"],
                           &["protocols/"])
           .expect("Couldn't compile protobufs!");
+
+    // The gRPC service reuses the messages generated above. Its descriptor set, including the
+    // imported files, is served by the Supervisor for gRPC reflection.
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR to be set"));
+    tonic_build::configure().build_client(true)
+                            .build_server(true)
+                            .extern_path(".sup.ctl", "crate::ctl")
+                            .extern_path(".sup.net", "crate::net")
+                            .extern_path(".sup.types", "crate::types")
+                            .file_descriptor_set_path(out_dir.join("grpc_descriptor.bin"))
+                            .compile(&["protocols/grpc.proto"], &["protocols/"])
+                            .expect("Couldn't compile gRPC protobufs!");
 }
@@ -1,4 +1,13 @@
 /// Maximum allowed size for a file to be uploaded to a service (in bytes).
 pub const MAX_FILE_PUT_SIZE_BYTES: usize = 64 * 1024;
+/// Maximum allowed size for a file to be uploaded to a service in chunks of
+/// `MAX_FILE_PUT_SIZE_BYTES` (in bytes).
+pub const MAX_CHUNKED_FILE_PUT_SIZE_BYTES: usize = 4 * 1024 * 1024;
+/// Maximum number of chunks a file may be uploaded in. This leaves room for the growth of the
+/// content of a file of `MAX_CHUNKED_FILE_PUT_SIZE_BYTES` by encryption.
+pub const MAX_FILE_PUT_CHUNKS: u32 =
+    2 * (MAX_CHUNKED_FILE_PUT_SIZE_BYTES / MAX_FILE_PUT_SIZE_BYTES) as u32;
 /// Maximum allowed size for a configuration to be applied to a service (in bytes).
 pub const MAX_SVC_CFG_SIZE: usize = 64 * 1024;
+/// Maximum allowed size for a fleet manifest to be applied to a ring (in bytes).
+pub const MAX_FLEET_MANIFEST_SIZE: usize = 64 * 1024;
@@ -20,18 +20,70 @@ impl message::MessageStatic for ServiceBindList {
     const MESSAGE_ID: &'static str = "ServiceBindList";
 }
 
+impl message::MessageStatic for LeaderAffinityList {
+    const MESSAGE_ID: &'static str = "LeaderAffinityList";
+}
+
 impl message::MessageStatic for SupDepart {
     const MESSAGE_ID: &'static str = "SupDepart";
 }
 
+impl message::MessageStatic for SupDepartSelf {
+    const MESSAGE_ID: &'static str = "SupDepartSelf";
+}
+
+impl message::MessageStatic for SupDenylistUpdate {
+    const MESSAGE_ID: &'static str = "SupDenylistUpdate";
+}
+
+impl message::MessageStatic for SupDenylist {
+    const MESSAGE_ID: &'static str = "SupDenylist";
+}
+
+impl message::MessageStatic for SupFleetManifestApply {
+    const MESSAGE_ID: &'static str = "SupFleetManifestApply";
+}
+
 impl message::MessageStatic for SupRestart {
     const MESSAGE_ID: &'static str = "SupRestart";
 }
 
+impl message::MessageStatic for SupConfigReload {
+    const MESSAGE_ID: &'static str = "SupConfigReload";
+}
+
+impl message::MessageStatic for SupConfigReloadResult {
+    const MESSAGE_ID: &'static str = "SupConfigReloadResult";
+}
+
+impl message::MessageStatic for SupRingStatus {
+    const MESSAGE_ID: &'static str = "SupRingStatus";
+}
+
+impl message::MessageStatic for RingStatus {
+    const MESSAGE_ID: &'static str = "RingStatus";
+}
+
 impl message::MessageStatic for SvcFilePut {
     const MESSAGE_ID: &'static str = "SvcFilePut";
 }
 
+impl message::MessageStatic for SvcFileStatus {
+    const MESSAGE_ID: &'static str = "SvcFileStatus";
+}
+
+impl message::MessageStatic for ServiceFileReceipt {
+    const MESSAGE_ID: &'static str = "ServiceFileReceipt";
+}
+
+impl message::MessageStatic for SvcUpdateHistory {
+    const MESSAGE_ID: &'static str = "SvcUpdateHistory";
+}
+
+impl message::MessageStatic for ServiceUpdateRecord {
+    const MESSAGE_ID: &'static str = "ServiceUpdateRecord";
+}
+
 impl message::MessageStatic for SvcGetDefaultCfg {
     const MESSAGE_ID: &'static str = "SvcGetDefaultCfg";
 }
@@ -48,6 +100,10 @@ impl message::MessageStatic for SvcLoad {
     const MESSAGE_ID: &'static str = "SvcLoad";
 }
 
+impl message::MessageStatic for SvcLoadBundle {
+    const MESSAGE_ID: &'static str = "SvcLoadBundle";
+}
+
 impl message::MessageStatic for SvcUpdate {
     const MESSAGE_ID: &'static str = "SvcUpdate";
 }
@@ -68,6 +124,10 @@ impl message::MessageStatic for SvcStatus {
     const MESSAGE_ID: &'static str = "SvcStatus";
 }
 
+impl message::MessageStatic for SvcMaintenance {
+    const MESSAGE_ID: &'static str = "SvcMaintenance";
+}
+
 impl message::MessageStatic for ConsoleLine {
     const MESSAGE_ID: &'static str = "ConsoleLine";
 }
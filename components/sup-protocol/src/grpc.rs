@@ -0,0 +1,11 @@
+//! The Control Gateway's gRPC service. The service takes the same request messages as the framed
+//! SrvProtocol, so generated clients in any language can drive a Supervisor.
+//!
+//! Note: See `protocols/grpc.proto` for type level documentation for generated types.
+
+include!(concat!(env!("OUT_DIR"), "/sup.grpc.rs"));
+
+/// The encoded descriptors of `protocols/grpc.proto` and the files it imports, used to serve
+/// gRPC reflection.
+pub const FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/grpc_descriptor.bin"));
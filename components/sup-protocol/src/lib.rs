@@ -39,6 +39,7 @@ extern crate serde_derive;
 pub mod butterfly;
 pub mod codec;
 pub mod ctl;
+pub mod grpc;
 pub mod message;
 pub mod net;
 pub mod types;
@@ -51,12 +52,15 @@ use std::{fs::File,
           io::Read,
           net::SocketAddr,
           path::{Path,
-                 PathBuf}};
+                 PathBuf},
+          sync::Mutex};
 
 // Name of file containing the CtlGateway secret key.
 const CTL_SECRET_FILENAME: &str = "CTL_SECRET";
 /// Length of characters in CtlGateway secret key.
 const CTL_SECRET_LEN: usize = 64;
+/// Environment variable selecting the named ring whose Supervisor state and ports are used.
+pub const RING_NAME_ENVVAR: &str = "HAB_RING_NAME";
 
 lazy_static! {
     /// The root path containing all runtime service directories and files
@@ -67,6 +71,9 @@ lazy_static! {
     pub static ref DEFAULT_BLDR_URL: String = {
         core::url::default_bldr_url()
     };
+
+    /// The named ring selected for this process with `select_ring`
+    static ref SELECTED_RING: Mutex<Option<String>> = Mutex::new(None);
 }
 
 /// Generate a new secret key used for authenticating clients to the `CtlGateway`.
@@ -121,7 +128,46 @@ pub fn sup_root(custom_state_path: Option<&PathBuf>) -> PathBuf {
         // should really be /hab/sup now, but it would be an awkward change
         // since the assumption of /hab/sup/default is pervasive.
         // See https://github.com/habitat-sh/habitat/issues/5266
-        None => STATE_PATH_PREFIX.join("default"),
+        //
+        // Supervisors in a named ring keep their state in /hab/sup/<ring-name> so that several
+        // of them can share a host.
+        None => ring_sup_root(None),
+    }
+}
+
+/// The state directory of a Supervisor in the named ring, or in the selected ring when no name is
+/// given.
+pub fn ring_sup_root(ring_name: Option<&str>) -> PathBuf {
+    match ring_name {
+        Some(ring_name) => STATE_PATH_PREFIX.join(ring_name),
+        None => STATE_PATH_PREFIX.join(self::ring_name().as_deref().unwrap_or("default")),
+    }
+}
+
+/// Select the named ring for the rest of this process. It takes precedence over `HAB_RING_NAME`,
+/// but is not seen by commands this process runs, which must be given the ring explicitly.
+pub fn select_ring(ring_name: &str) {
+    *SELECTED_RING.lock().expect("SELECTED_RING lock poisoned") = Some(ring_name.to_string());
+}
+
+/// The named ring selected with `select_ring` or the `HAB_RING_NAME` environment variable. Invalid
+/// ring names are ignored.
+pub fn ring_name() -> Option<String> {
+    SELECTED_RING.lock()
+                 .expect("SELECTED_RING lock poisoned")
+                 .clone()
+                 .or_else(|| henv::var(RING_NAME_ENVVAR).ok())
+                 .filter(|name| is_valid_ring_name(name))
+}
+
+/// A valid ring name contains a-z, 0-9, and _ or - after the first character.
+pub fn is_valid_ring_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_lowercase() || first.is_ascii_digit() => {
+            chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+        }
+        _ => false,
     }
 }
 
@@ -194,3 +240,23 @@ mod ctl_secret {
         assert_eq!(empty, out);
     }
 }
+
+#[cfg(test)]
+mod ring_name {
+    use super::*;
+
+    #[test]
+    fn valid_ring_names() {
+        assert!(is_valid_ring_name("blue"));
+        assert!(is_valid_ring_name("prod-2"));
+        assert!(is_valid_ring_name("0_test"));
+    }
+
+    #[test]
+    fn invalid_ring_names() {
+        assert!(!is_valid_ring_name(""));
+        assert!(!is_valid_ring_name("-blue"));
+        assert!(!is_valid_ring_name("Blue"));
+        assert!(!is_valid_ring_name("../default"));
+    }
+}
@@ -35,6 +35,9 @@ impl message::MessageStatic for ServiceGroup {
 impl message::MessageStatic for ServiceStatus {
     const MESSAGE_ID: &'static str = "ServiceStatus";
 }
+impl message::MessageStatic for HealthCheckStatus {
+    const MESSAGE_ID: &'static str = "HealthCheckStatus";
+}
 impl message::MessageStatic for HealthCheckInterval {
     const MESSAGE_ID: &'static str = "HealthCheckInterval";
 }
@@ -89,6 +92,18 @@ impl fmt::Display for DesiredState {
     }
 }
 
+impl fmt::Display for HealthCheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = match *self {
+            HealthCheckResult::Ok => "OK",
+            HealthCheckResult::Warning => "WARNING",
+            HealthCheckResult::Critical => "CRITICAL",
+            HealthCheckResult::Unknown => "UNKNOWN",
+        };
+        write!(f, "{}", result)
+    }
+}
+
 impl FromStr for BindingMode {
     type Err = NetErr;
 
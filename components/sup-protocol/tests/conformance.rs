@@ -0,0 +1,188 @@
+//! Golden ctl protocol frames for validating client implementations against the actual wire
+//! format. See `test/fixtures/conformance/README.md`.
+//!
+//! Each fixture is built here from the Rust types, encoded with `SrvCodec` and compared with the
+//! committed frame, which is in turn decoded, checked against the message it was built from and
+//! re-encoded. Frames committed for a protocol version must keep decoding as long as that
+//! version is supported, so they are only regenerated when a fixture is added, by running this
+//! test with `HAB_UPDATE_CONFORMANCE_FIXTURES=1`.
+
+use bytes::BytesMut;
+use habitat_sup_protocol::{codec::{SrvCodec,
+                                   SrvMessage,
+                                   SrvTxn},
+                           ctl,
+                           message::MessageStatic,
+                           net,
+                           types};
+use prost::Message;
+use serde::Serialize;
+use serde_json::{json,
+                 Value as Json};
+use std::{env,
+          fs,
+          path::PathBuf};
+use tokio_util::codec::{Decoder,
+                        Encoder};
+
+const UPDATE_FIXTURES_ENVVAR: &str = "HAB_UPDATE_CONFORMANCE_FIXTURES";
+const MANIFEST_FILE: &str = "manifest.json";
+const MEMBER_ID: &str = "0123456789abcdef0123456789abcdef";
+
+struct Fixture {
+    name:    &'static str,
+    message: SrvMessage,
+    json:    Json,
+    /// Decode the body of a frame as the fixture's message type and convert it to JSON
+    decode:  fn(&SrvMessage) -> Json,
+}
+
+impl Fixture {
+    fn new<T>(name: &'static str, msg: T, txn: Option<SrvTxn>) -> Self
+        where T: Message + MessageStatic + Default + Serialize + Clone
+    {
+        let json = serde_json::to_value(&msg).unwrap();
+        let mut message = SrvMessage::from(msg);
+        if let Some(txn) = txn {
+            message.set_transaction(txn);
+        }
+        Fixture { name,
+                  message,
+                  json,
+                  decode: |msg| serde_json::to_value(msg.parse::<T>().unwrap()).unwrap() }
+    }
+
+    fn file(&self) -> String { format!("{}.bin", self.name) }
+
+    fn manifest_entry(&self) -> Json {
+        let transaction = match self.message.transaction() {
+            Some(txn) => {
+                json!({
+                    "id": txn.id(),
+                    "is_response": txn.is_response(),
+                    "is_complete": txn.is_complete(),
+                })
+            }
+            None => Json::Null,
+        };
+        json!({
+            "name": self.name,
+            "file": self.file(),
+            "message_id": self.message.message_id(),
+            "transaction": transaction,
+            "message": self.json,
+        })
+    }
+}
+
+fn request(id: u32) -> Option<SrvTxn> { Some(SrvTxn::from(id)) }
+
+fn reply(id: u32, complete: bool) -> Option<SrvTxn> {
+    let mut txn = SrvTxn::from(id);
+    txn.set_response();
+    if complete {
+        txn.set_complete();
+    }
+    Some(txn)
+}
+
+fn fixtures() -> Vec<Fixture> {
+    let ident = types::PackageIdent { origin:  String::from("core"),
+                                      name:    String::from("redis"),
+                                      version: None,
+                                      release: None, };
+    vec![Fixture::new("handshake",
+                      ctl::Handshake { secret_key: Some(String::from("c2VjcmV0LWtleQ==")), },
+                      request(1)),
+         Fixture::new("net_ok", net::ok(), reply(1, true)),
+         Fixture::new("svc_status",
+                      ctl::SvcStatus { ident:     Some(ident),
+                                       detail:    Some(true),
+                                       resources: None, },
+                      request(2)),
+         Fixture::new("console_line",
+                      ctl::ConsoleLine { line:  String::from("Loading core/redis\n"),
+                                         color: Some(String::from("green")),
+                                         bold:  true, },
+                      reply(2, false)),
+         Fixture::new("net_progress",
+                      ctl::NetProgress { total:    1024,
+                                         position: 512, },
+                      reply(2, false)),
+         Fixture::new("net_err",
+                      net::err(net::ErrCode::NotFound, "Service not loaded, core/redis"),
+                      reply(2, true)),
+         Fixture::new("sup_depart_without_transaction",
+                      ctl::SupDepart { member_id: Some(String::from(MEMBER_ID)),
+                                       address:   None,
+                                       all:       None, },
+                      None),]
+}
+
+fn fixture_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../test/fixtures/conformance/v1/ctl")
+}
+
+fn encode(message: SrvMessage) -> Vec<u8> {
+    let mut buf = BytesMut::new();
+    SrvCodec::new().encode(message, &mut buf).unwrap();
+    buf.to_vec()
+}
+
+fn write_fixtures() {
+    let dir = fixture_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let mut manifest = Vec::new();
+    for fixture in fixtures() {
+        fs::write(dir.join(fixture.file()), encode(fixture.message.clone())).unwrap();
+        manifest.push(fixture.manifest_entry());
+    }
+    let manifest = serde_json::to_string_pretty(&manifest).unwrap() + "\n";
+    fs::write(dir.join(MANIFEST_FILE), manifest).unwrap();
+}
+
+#[test]
+fn ctl_frames_match_the_committed_fixtures() {
+    if env::var_os(UPDATE_FIXTURES_ENVVAR).is_some() {
+        write_fixtures();
+    }
+    let dir = fixture_dir();
+
+    for fixture in fixtures() {
+        let committed = fs::read(dir.join(fixture.file())).unwrap();
+        assert_eq!(encode(fixture.message.clone()),
+                   committed,
+                   "{} is not encoded as the committed frame",
+                   fixture.name);
+
+        let decoded = SrvCodec::new().decode(&mut BytesMut::from(&committed[..]))
+                                     .unwrap()
+                                     .unwrap_or_else(|| panic!("{} is incomplete", fixture.name));
+        assert_eq!(decoded.message_id(), fixture.message.message_id());
+        assert_eq!(decoded.transaction(), fixture.message.transaction());
+        assert_eq!((fixture.decode)(&decoded), fixture.json);
+        assert_eq!(encode(decoded),
+                   committed,
+                   "{} does not round-trip",
+                   fixture.name);
+    }
+
+    let committed: Json =
+        serde_json::from_str(&fs::read_to_string(dir.join(MANIFEST_FILE)).unwrap()).unwrap();
+    let expected = Json::Array(fixtures().iter().map(Fixture::manifest_entry).collect());
+    assert_eq!(committed, expected);
+}
+
+#[test]
+fn truncated_ctl_frames_are_not_decoded() {
+    let dir = fixture_dir();
+    for fixture in fixtures() {
+        let committed = fs::read(dir.join(fixture.file())).unwrap();
+        let truncated = &committed[..committed.len() - 1];
+        let decoded = SrvCodec::new().decode(&mut BytesMut::from(truncated))
+                                     .unwrap();
+        assert!(decoded.is_none(),
+                "{} was decoded without its last byte",
+                fixture.name);
+    }
+}
@@ -6,10 +6,12 @@ use habitat_butterfly::{member::{Health,
                         rumor::{election::{Election as ElectionRumor,
                                            ElectionStatus as ElectionStatusRumor,
                                            ElectionUpdate as ElectionUpdateRumor},
-                                service::{Service as ServiceRumor,
+                                service::{CoordinatedUnload,
+                                          Service as ServiceRumor,
                                           SysInfo},
                                 service_config::ServiceConfig as ServiceConfigRumor,
-                                service_file::ServiceFile as ServiceFileRumor,
+                                service_file::{ServiceFile as ServiceFileRumor,
+                                               Target},
                                 ConstIdRumor as _,
                                 RumorStore}};
 use habitat_common::outputln;
@@ -27,10 +29,19 @@ use std::{borrow::Cow,
           fmt,
           iter::IntoIterator,
           result,
-          str::FromStr};
+          str::FromStr,
+          time::{Duration,
+                 SystemTime}};
 
 static LOGKEY: &str = "CE";
 
+habitat_core::env_config_duration!(
+    /// How long a member that was confirmed dead still counts toward the quorum of the ring that
+    /// the Supervisor may be partitioned from
+    PartitionMemberWindow,
+    HAB_PARTITION_MEMBER_WINDOW_SECS => from_secs,
+    Duration::from_secs(60 * 60));
+
 pub type MemberId = String;
 
 #[derive(Debug, Serialize)]
@@ -44,6 +55,14 @@ pub struct CensusRing {
     last_membership_counter: usize,
     last_service_config_counter: usize,
     last_service_file_counter: usize,
+    /// The earliest time a scheduled service configuration that has been received but not
+    /// applied yet is due
+    next_config_activation: Option<SystemTime>,
+    /// Whether a scheduled service configuration was applied by the last update
+    scheduled_config_activated: bool,
+    /// Whether the local Supervisor has lost contact with a quorum of the ring, as it does on
+    /// the minority side of a network partition
+    partitioned: bool,
 }
 
 impl CensusRing {
@@ -62,9 +81,19 @@ impl CensusRing {
                      last_election_update_counter: 0,
                      last_membership_counter: 0,
                      last_service_config_counter: 0,
-                     last_service_file_counter: 0, }
+                     last_service_file_counter: 0,
+                     next_config_activation: None,
+                     scheduled_config_activated: false,
+                     partitioned: false, }
     }
 
+    /// Indicates whether a service configuration scheduled for a later time was applied by the
+    /// last update.
+    pub fn scheduled_config_activated(&self) -> bool { self.scheduled_config_activated }
+
+    /// Indicates whether the local Supervisor has lost contact with a quorum of the ring.
+    pub fn partitioned(&self) -> bool { self.partitioned }
+
     /// # Locking (see locking.md)
     /// * `RumorStore::list` (write)
     /// * `MemberList::entries` (read)
@@ -77,21 +106,26 @@ impl CensusRing {
                                       member_list: &MemberList,
                                       service_config_rumors: &RumorStore<ServiceConfigRumor>,
                                       service_file_rumors: &RumorStore<ServiceFileRumor>) {
-        // If ANY new rumor, of any type, has been received,
-        // reconstruct the entire census state to ensure consistency
+        let now = SystemTime::now();
+        let window: Duration = PartitionMemberWindow::configured_value().into();
+        // If ANY new rumor, of any type, has been received, or a scheduled service configuration
+        // is due, reconstruct the entire census state to ensure consistency
         if (service_rumors.get_update_counter() > self.last_service_counter)
            || (member_list.get_update_counter() > self.last_membership_counter)
            || (election_rumors.get_update_counter() > self.last_election_counter)
            || (election_update_rumors.get_update_counter() > self.last_election_update_counter)
            || (service_config_rumors.get_update_counter() > self.last_service_config_counter)
            || (service_file_rumors.get_update_counter() > self.last_service_file_counter)
+           || self.next_config_activation
+                  .map_or(false, |activate_at| activate_at <= now)
         {
             self.changed = true;
 
             self.populate_census_rsr_mlr(service_rumors, member_list);
+            self.update_partition_mlr(member_list, window);
             self.update_from_election_store_rsr(election_rumors);
             self.update_from_election_update_store_rsr(election_update_rumors);
-            self.update_from_service_config_rsr(key_cache, service_config_rumors);
+            self.update_from_service_config_rsr(key_cache, service_config_rumors, now);
             self.update_from_service_files_rsr(key_cache, service_file_rumors);
 
             // Update our counters to reflect current state.
@@ -102,7 +136,9 @@ impl CensusRing {
             self.last_service_config_counter = service_config_rumors.get_update_counter();
             self.last_service_file_counter = service_file_rumors.get_update_counter();
         } else {
-            self.changed = false;
+            // Members confirmed dead drop out of the quorum over time, without any new rumors
+            self.changed = self.partitioned && self.update_partition_mlr(member_list, window);
+            self.scheduled_config_activated = false;
         }
     }
 
@@ -144,16 +180,56 @@ impl CensusRing {
             }
         }
 
+        let flapping = member_list.flapping_member_ids_mlr();
         member_list.with_memberships_mlr(|Membership { member, health }| {
                        for group in self.census_groups.values_mut() {
                            if let Some(census_member) = group.find_member_mut(&member.id) {
                                census_member.update_from_member(&member);
                                census_member.update_from_health(health);
+                               census_member.flapping = flapping.contains(&member.id);
+                           }
+                       }
+                       Ok(())
+                   })
+                   .ok();
+    }
+
+    /// Check whether the local Supervisor is still in contact with a quorum of the ring, returning
+    /// whether that changed. A quorum is a majority of the members that have not departed and
+    /// have not been confirmed dead for longer than `window`, so that a ring that shrinks without
+    /// departing its members doesn't stay partitioned. The Supervisor is partitioned when no more
+    /// than half of them, counting itself, are alive.
+    ///
+    /// # Locking (see locking.md)
+    /// * `MemberList::entries` (read)
+    fn update_partition_mlr(&mut self, member_list: &MemberList, window: Duration) -> bool {
+        let gone = member_list.members_gone_mlr(Some(window));
+        let mut alive = 1;
+        let mut total = 1;
+        member_list.with_memberships_mlr(|Membership { member, health }| {
+                       if member.id != self.local_member_id && !gone.contains(&member.id) {
+                           total += 1;
+                           if health == Health::Alive {
+                               alive += 1;
                            }
                        }
                        Ok(())
                    })
                    .ok();
+        #[allow(clippy::integer_division)]
+        let partitioned = alive <= total / 2;
+        if partitioned && !self.partitioned {
+            outputln!("Lost contact with a quorum of the ring, {} of {} members are alive",
+                      alive,
+                      total);
+        } else if !partitioned && self.partitioned {
+            outputln!("Regained contact with a quorum of the ring, {} of {} members are alive",
+                      alive,
+                      total);
+        }
+        let changed = partitioned != self.partitioned;
+        self.partitioned = partitioned;
+        changed
     }
 
     /// # Locking (see locking.md)
@@ -188,12 +264,28 @@ impl CensusRing {
     /// * `RumorStore::list` (read)
     fn update_from_service_config_rsr(&mut self,
                                       key_cache: &KeyCache,
-                                      service_config_rumors: &RumorStore<ServiceConfigRumor>) {
+                                      service_config_rumors: &RumorStore<ServiceConfigRumor>,
+                                      now: SystemTime) {
+        self.next_config_activation = None;
+        self.scheduled_config_activated = false;
         for (service_group, rumors) in service_config_rumors.lock_rsr().iter() {
             if let Ok(sg) = service_group_from_str(service_group) {
                 if let Some(service_config) = rumors.get(ServiceConfigRumor::const_id()) {
                     if let Some(census_group) = self.census_groups.get_mut(&sg) {
-                        census_group.update_from_service_config_rumor(key_cache, service_config);
+                        match census_group.update_from_service_config_rumor(key_cache,
+                                                                            service_config,
+                                                                            now)
+                        {
+                            ServiceConfigUpdate::Activated => {
+                                self.scheduled_config_activated = true;
+                            }
+                            ServiceConfigUpdate::Pending(activate_at) => {
+                                self.next_config_activation =
+                                    Some(self.next_config_activation
+                                             .map_or(activate_at, |next| next.min(activate_at)));
+                            }
+                            ServiceConfigUpdate::Applied | ServiceConfigUpdate::Unchanged => {}
+                        }
                     }
                 }
             }
@@ -231,7 +323,7 @@ impl<'a> Serialize for CensusRingProxy<'a> {
     fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
         where S: Serializer
     {
-        let mut strukt = serializer.serialize_struct("census_ring", 9)?;
+        let mut strukt = serializer.serialize_struct("census_ring", 10)?;
         strukt.serialize_field("changed", &self.0.changed)?;
         strukt.serialize_field("census_groups", &self.0.census_groups)?;
         strukt.serialize_field("local_member_id", &self.0.local_member_id)?;
@@ -244,6 +336,7 @@ impl<'a> Serialize for CensusRingProxy<'a> {
                                &self.0.last_service_config_counter)?;
         strukt.serialize_field("last_service_file_counter",
                                &self.0.last_service_file_counter)?;
+        strukt.serialize_field("partitioned", &self.0.partitioned)?;
         strukt.end()
     }
 }
@@ -301,6 +394,9 @@ pub struct ServiceFile {
     pub filename:    String,
     pub incarnation: u64,
     pub body:        Vec<u8>,
+    #[serde(skip_serializing_if = "Target::is_empty")]
+    pub target:      Target,
+    pub template:    bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -309,6 +405,18 @@ pub struct ServiceConfig {
     pub value:       toml::value::Table,
 }
 
+/// What became of the service configuration rumor of a group when the census was updated
+#[derive(Debug, PartialEq, Eq)]
+enum ServiceConfigUpdate {
+    /// The configuration replaced the one the group had as soon as it was received
+    Applied,
+    /// The configuration replaced the one the group had at the time it was scheduled for
+    Activated,
+    /// The configuration is newer than the one the group has, but not due until this time
+    Pending(SystemTime),
+    Unchanged,
+}
+
 #[derive(Debug)]
 pub struct CensusGroup {
     pub service_group:          ServiceGroup,
@@ -368,6 +476,19 @@ impl CensusGroup {
             .filter(|cm| cm.alive() || cm.suspect())
     }
 
+    /// Same as `active_members`, but leaves out the members whose
+    /// service is in maintenance. These are the members that the
+    /// consumers of the service should send their traffic to.
+    pub fn serving_members(&self) -> impl Iterator<Item = &CensusMember> {
+        self.active_members().filter(|cm| !cm.maintenance())
+    }
+
+    /// Whether the group has members, but none of them can serve its consumers, because they are
+    /// all confirmed dead, departed, or in maintenance.
+    pub fn is_degraded(&self) -> bool {
+        self.members().next().is_some() && self.serving_members().next().is_none()
+    }
+
     /// Return references to all a `CensusGroup`'s `ServiceFiles`.
     pub fn service_files(&self) -> impl IntoIterator<Item = &ServiceFile> {
         self.service_files.values()
@@ -464,20 +585,39 @@ impl CensusGroup {
         }
     }
 
+    /// A newer configuration that is scheduled for later is not applied until it is due. The
+    /// group keeps the configuration it has until then.
     fn update_from_service_config_rumor(&mut self,
                                         key_cache: &KeyCache,
-                                        service_config: &ServiceConfigRumor) {
+                                        service_config: &ServiceConfigRumor,
+                                        now: SystemTime)
+                                        -> ServiceConfigUpdate {
+        let current_incarnation = self.service_config.as_ref().map(|c| c.incarnation);
+        if current_incarnation.map_or(false, |current| service_config.incarnation <= current) {
+            return ServiceConfigUpdate::Unchanged;
+        }
+        if !service_config.is_active(now) {
+            if let Some(activate_at) = service_config.activate_at {
+                return ServiceConfigUpdate::Pending(activate_at);
+            }
+        }
         match service_config.config(key_cache) {
             Ok(config) => {
-                if self.service_config.is_none()
-                   || service_config.incarnation > self.service_config.as_ref().unwrap().incarnation
-                {
-                    self.service_config = Some(ServiceConfig { incarnation:
-                                                                   service_config.incarnation,
-                                                               value:       config, });
+                self.service_config = Some(ServiceConfig { incarnation: service_config.incarnation,
+                                                           value:       config, });
+                if service_config.activate_at.is_some() {
+                    outputln!(preamble self.service_group,
+                              "Applying configuration version {} scheduled for this time",
+                              service_config.incarnation);
+                    ServiceConfigUpdate::Activated
+                } else {
+                    ServiceConfigUpdate::Applied
                 }
             }
-            Err(err) => warn!("{}", err),
+            Err(err) => {
+                warn!("{}", err);
+                ServiceConfigUpdate::Unchanged
+            }
         }
     }
 
@@ -486,27 +626,70 @@ impl CensusGroup {
                                        service_file_rumors: &HashMap<String, ServiceFileRumor>)
     {
         self.changed_service_files.clear();
+        let mut chunks_by_incarnation = BTreeMap::<_, Vec<_>>::new();
         for (_m_id, service_file_rumor) in service_file_rumors.iter() {
-            let filename = service_file_rumor.filename.to_string();
-            let file = self.service_files
-                           .entry(filename.clone())
-                           .or_insert_with(ServiceFile::default);
-
-            if service_file_rumor.incarnation > file.incarnation {
-                match service_file_rumor.body(key_cache) {
-                    Ok(body) => {
-                        self.changed_service_files.insert(filename.clone());
-                        file.filename = filename.clone();
-                        file.incarnation = service_file_rumor.incarnation;
-                        file.body = body;
-                    }
-                    Err(e) => {
-                        warn!("Cannot decrypt service file for {} {} {}: {}",
-                              self.service_group,
-                              service_file_rumor.filename,
-                              service_file_rumor.incarnation,
-                              e)
-                    }
+            if service_file_rumor.chunk().is_some() {
+                chunks_by_incarnation.entry((service_file_rumor.filename.as_str(),
+                                             service_file_rumor.incarnation))
+                                     .or_default()
+                                     .push(service_file_rumor);
+            } else {
+                self.update_from_service_file_rumor(key_cache, service_file_rumor);
+            }
+        }
+
+        // Newer incarnations of a file come first, so only the newest incarnation that has all of
+        // its chunks is reassembled.
+        for ((filename, incarnation), chunks) in chunks_by_incarnation.into_iter().rev() {
+            if self.service_files
+                   .get(filename)
+                   .map_or(false, |file| file.incarnation >= incarnation)
+            {
+                continue;
+            }
+            let count = chunks[0].chunk().map_or(0, |chunk| chunk.count as usize);
+            if chunks.len() < count {
+                debug!("Waiting for {} of {} chunks of service file for {} {} {}",
+                       count - chunks.len(),
+                       count,
+                       self.service_group,
+                       filename,
+                       incarnation);
+                continue;
+            }
+            match ServiceFileRumor::assemble(chunks) {
+                Ok(service_file_rumor) => {
+                    self.update_from_service_file_rumor(key_cache, &service_file_rumor)
+                }
+                Err(e) => warn!("{} for {} {}", e, self.service_group, incarnation),
+            }
+        }
+    }
+
+    fn update_from_service_file_rumor(&mut self,
+                                      key_cache: &KeyCache,
+                                      service_file_rumor: &ServiceFileRumor) {
+        let filename = service_file_rumor.filename.to_string();
+        let file = self.service_files
+                       .entry(filename.clone())
+                       .or_insert_with(ServiceFile::default);
+
+        if service_file_rumor.incarnation > file.incarnation {
+            match service_file_rumor.body(key_cache) {
+                Ok(body) => {
+                    self.changed_service_files.insert(filename.clone());
+                    file.filename = filename;
+                    file.incarnation = service_file_rumor.incarnation;
+                    file.body = body;
+                    file.target = service_file_rumor.target.clone();
+                    file.template = service_file_rumor.template;
+                }
+                Err(e) => {
+                    warn!("Cannot decrypt service file for {} {} {}: {}",
+                          self.service_group,
+                          service_file_rumor.filename,
+                          service_file_rumor.incarnation,
+                          e)
                 }
             }
         }
@@ -586,6 +769,13 @@ pub struct CensusMember {
     pub suspect: bool,
     pub confirmed: bool,
     pub departed: bool,
+    /// Whether the health of this member has been changing back and forth recently
+    pub flapping: bool,
+    /// Whether the service has been put in maintenance on this member
+    pub maintenance: bool,
+    /// The part this member takes in an unload of the service coordinated across its group, if
+    /// any
+    pub unload: Option<CoordinatedUnload>,
     // Maps must be represented last in a serializable struct for the current version of the toml
     // crate. Additionally, this deserialization method is required to correct any ordering issues
     // with the table being serialized - https://docs.rs/toml/0.4.0/toml/ser/fn.tables_last.html
@@ -606,6 +796,8 @@ impl CensusMember {
             Err(err) => warn!("Received a bad package ident from gossip data, err={}", err),
         };
         self.sys = rumor.sys.clone();
+        self.maintenance = rumor.maintenance;
+        self.unload = rumor.unload.clone();
         self.cfg = toml::from_slice(&rumor.cfg).unwrap_or_default();
     }
 
@@ -614,13 +806,10 @@ impl CensusMember {
         self.election_is_no_quorum = election.status == ElectionStatusRumor::NoQuorum;
         self.election_is_finished = election.status == ElectionStatusRumor::Finished;
         if self.election_is_finished {
-            if self.member_id == election.member_id {
-                self.leader = true;
-                self.follower = false;
-            } else {
-                self.leader = false;
-                self.follower = true;
-            }
+            // A departed member keeps the elections it won until a new one is held, but it
+            // must not go on serving leader duties while it's out of the ring
+            self.leader = self.member_id == election.member_id && !self.departed;
+            self.follower = !self.leader;
         }
         self.leader
     }
@@ -630,13 +819,8 @@ impl CensusMember {
         self.update_election_is_no_quorum = election.status == ElectionStatusRumor::NoQuorum;
         self.update_election_is_finished = election.status == ElectionStatusRumor::Finished;
         if self.update_election_is_finished {
-            if self.member_id == election.member_id {
-                self.update_leader = true;
-                self.update_follower = false;
-            } else {
-                self.update_leader = false;
-                self.update_follower = true;
-            }
+            self.update_leader = self.member_id == election.member_id && !self.departed;
+            self.update_follower = !self.update_leader;
         }
         self.update_leader
     }
@@ -668,6 +852,10 @@ impl CensusMember {
     pub fn confirmed(&self) -> bool { self.confirmed }
 
     pub fn departed(&self) -> bool { self.departed }
+
+    pub fn flapping(&self) -> bool { self.flapping }
+
+    pub fn maintenance(&self) -> bool { self.maintenance }
 }
 
 /// This data structure just wraps the CensusMember and allows us to tweak the serialization logic.
@@ -694,7 +882,7 @@ impl<'a> Serialize for CensusMemberProxy<'a> {
     fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
         where S: Serializer
     {
-        let mut strukt = serializer.serialize_struct("census_member", 24)?;
+        let mut strukt = serializer.serialize_struct("census_member", 27)?;
         strukt.serialize_field("member_id", &self.member_id)?;
         strukt.serialize_field("pkg", &self.pkg)?;
 
@@ -721,6 +909,9 @@ impl<'a> Serialize for CensusMemberProxy<'a> {
         strukt.serialize_field("suspect", &self.suspect)?;
         strukt.serialize_field("confirmed", &self.confirmed)?;
         strukt.serialize_field("departed", &self.departed)?;
+        strukt.serialize_field("flapping", &self.flapping)?;
+        strukt.serialize_field("maintenance", &self.maintenance)?;
+        strukt.serialize_field("unload", &self.unload)?;
         strukt.serialize_field("cfg", &self.cfg)?;
         strukt.end()
     }
@@ -747,11 +938,15 @@ mod tests {
                                     service::{Service as ServiceRumor,
                                               SysInfo},
                                     service_config::ServiceConfig as ServiceConfigRumor,
-                                    service_file::ServiceFile as ServiceFileRumor,
+                                    service_file::{Chunk,
+                                                   ServiceFile as ServiceFileRumor},
+                                    Rumor,
                                     RumorStore}};
-    use habitat_core::{fs::CACHE_KEY_PATH,
+    use habitat_core::{crypto::Blake2bHash,
+                       fs::CACHE_KEY_PATH,
                        package::ident::PackageIdent,
                        service::ServiceGroup};
+    use std::time::Duration;
 
     #[test]
     fn update_from_rumors() {
@@ -850,6 +1045,46 @@ mod tests {
         (ring, sg_one, sg_two)
     }
 
+    #[test]
+    fn partitioned_without_a_majority_of_the_ring_alive() {
+        let mut ring = CensusRing::new("local".to_string());
+        let member_list = MemberList::new();
+        let member = |id: &str| {
+            Member { id: id.to_string(),
+                     ..Default::default() }
+        };
+        member_list.insert_mlw(member("local"), Health::Alive);
+        member_list.insert_mlw(member("alive"), Health::Alive);
+        member_list.insert_mlw(member("suspect"), Health::Suspect);
+        member_list.insert_mlw(member("confirmed"), Health::Confirmed);
+        let window = Duration::from_secs(60 * 60);
+        assert!(ring.update_partition_mlr(&member_list, window));
+        assert!(ring.partitioned());
+
+        member_list.insert_mlw(member("departed"), Health::Departed);
+        member_list.insert_mlw(member("another-alive"), Health::Alive);
+        assert!(ring.update_partition_mlr(&member_list, window));
+        assert!(!ring.partitioned());
+    }
+
+    #[test]
+    fn members_confirmed_dead_for_long_drop_out_of_the_quorum() {
+        let mut ring = CensusRing::new("local".to_string());
+        let member_list = MemberList::new();
+        let member = |id: &str| {
+            Member { id: id.to_string(),
+                     ..Default::default() }
+        };
+        member_list.insert_mlw(member("local"), Health::Alive);
+        member_list.insert_mlw(member("confirmed"), Health::Confirmed);
+        member_list.insert_mlw(member("another-confirmed"), Health::Confirmed);
+        assert!(ring.update_partition_mlr(&member_list, Duration::from_secs(60 * 60)));
+        assert!(ring.partitioned());
+
+        assert!(ring.update_partition_mlr(&member_list, Duration::from_secs(0)));
+        assert!(!ring.partitioned());
+    }
+
     /// Create a bare-minimum CensusMember with the given Health
     fn test_census_member(id: &str, health: Health) -> CensusMember {
         let pkg = "habitat-testing/test_service".parse()
@@ -875,6 +1110,9 @@ mod tests {
                        suspect: health == Health::Suspect,
                        confirmed: health == Health::Confirmed,
                        departed: health == Health::Departed,
+                       flapping: false,
+                       maintenance: false,
+                       unload: None,
                        cfg: toml::value::Table::new() }
     }
 
@@ -901,6 +1139,147 @@ mod tests {
         assert!(active_members.next().is_none());
     }
 
+    #[test]
+    fn serving_members_leaves_out_members_in_maintenance() {
+        let mut in_maintenance = test_census_member("maintenance-one", Health::Alive);
+        in_maintenance.maintenance = true;
+        let population = vec![test_census_member("live-one", Health::Alive),
+                              in_maintenance,
+                              test_census_member("departed-one", Health::Departed),];
+
+        let sg: ServiceGroup =
+            "test-service.default".parse()
+                                  .expect("This should be a valid service group");
+
+        let mut census_group = CensusGroup::new(sg, &"live-one".to_string());
+        for member in population {
+            census_group.population
+                        .insert(member.member_id.clone(), member);
+        }
+
+        assert_eq!(census_group.active_members().count(), 2);
+        let mut serving_members = census_group.serving_members();
+        assert_eq!(serving_members.next().unwrap().member_id, "live-one");
+        assert!(serving_members.next().is_none());
+    }
+
+    #[test]
+    fn groups_without_serving_members_are_degraded() {
+        let sg: ServiceGroup = "test-service.default".parse().unwrap();
+        let mut census_group = CensusGroup::new(sg, &"live-one".to_string());
+        assert!(!census_group.is_degraded());
+
+        let mut in_maintenance = test_census_member("maintenance-one", Health::Alive);
+        in_maintenance.maintenance = true;
+        for member in vec![in_maintenance,
+                           test_census_member("confirmed-one", Health::Confirmed),
+                           test_census_member("departed-one", Health::Departed)]
+        {
+            census_group.population
+                        .insert(member.member_id.clone(), member);
+        }
+        assert!(census_group.is_degraded());
+
+        let live = test_census_member("live-one", Health::Alive);
+        census_group.population.insert(live.member_id.clone(), live);
+        assert!(!census_group.is_degraded());
+    }
+
+    #[test]
+    fn departed_members_do_not_lead() {
+        let sg: ServiceGroup = "test-service.default".parse().unwrap();
+        let mut election =
+            ElectionRumor::new("departed-one", &sg, election::Term::default(), 10, true);
+        election.finish();
+        let mut election_update =
+            ElectionUpdateRumor::new("departed-one", &sg, election::Term::default(), 10, true);
+        election_update.finish();
+
+        let mut census_group = CensusGroup::new(sg, &"live-one".to_string());
+        for member in vec![test_census_member("live-one", Health::Alive),
+                           test_census_member("departed-one", Health::Departed)]
+        {
+            census_group.population
+                        .insert(member.member_id.clone(), member);
+        }
+        census_group.update_from_election_rumor(&election);
+        census_group.update_from_election_update_rumor(&election_update);
+
+        assert!(census_group.leader().is_none());
+        assert!(census_group.update_leader().is_none());
+        assert!(census_group.members().all(|member| member.follower));
+    }
+
+    #[test]
+    fn chunked_service_files_are_reassembled_once_complete() {
+        let sg: ServiceGroup = "test-service.default".parse().unwrap();
+        let body = b"-----BEGIN CERTIFICATE-----";
+        let file_checksum = Blake2bHash::from_bytes(&body[..]).to_string();
+        let chunks =
+            body.chunks(10)
+                .enumerate()
+                .map(|(index, chunk_body)| {
+                    let chunk = Chunk { index:         index as u32,
+                                        count:         3,
+                                        checksum:
+                                            Blake2bHash::from_bytes(chunk_body).to_string(),
+                                        file_checksum: file_checksum.clone(), };
+                    let mut rumor = ServiceFileRumor::new_chunk("member-a",
+                                                                sg.clone(),
+                                                                "cert.pem",
+                                                                chunk,
+                                                                chunk_body.to_vec());
+                    rumor.incarnation = 1;
+                    (rumor.id().to_string(), rumor)
+                })
+                .collect::<Vec<_>>();
+        let key_cache = KeyCache::new(&*CACHE_KEY_PATH);
+        let mut census_group = CensusGroup::new(sg, &"member-a".to_string());
+
+        let incomplete = chunks[..2].iter().cloned().collect();
+        census_group.update_from_service_file_rumors(&key_cache, &incomplete);
+        assert!(census_group.changed_service_files().is_empty());
+
+        let complete = chunks.into_iter().collect();
+        census_group.update_from_service_file_rumors(&key_cache, &complete);
+        let changed = census_group.changed_service_files();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].filename, "cert.pem");
+        assert_eq!(changed[0].incarnation, 1);
+        assert_eq!(changed[0].body, body.to_vec());
+    }
+
+    #[test]
+    fn scheduled_service_configs_are_applied_once_due() {
+        let sg: ServiceGroup = "test-service.default".parse().unwrap();
+        let key_cache = KeyCache::new(&*CACHE_KEY_PATH);
+        let mut census_group = CensusGroup::new(sg.clone(), &"member-a".to_string());
+        let now = SystemTime::now();
+
+        let mut current = ServiceConfigRumor::new("member-a", sg.clone(), b"port = 1".to_vec());
+        current.incarnation = 1;
+        assert_eq!(census_group.update_from_service_config_rumor(&key_cache, &current, now),
+                   ServiceConfigUpdate::Applied);
+
+        let activate_at = now + Duration::from_secs(60 * 60);
+        let mut scheduled = ServiceConfigRumor::new("member-a", sg, b"port = 2".to_vec());
+        scheduled.incarnation = 2;
+        scheduled.activate_at = Some(activate_at);
+        assert_eq!(census_group.update_from_service_config_rumor(&key_cache, &scheduled, now),
+                   ServiceConfigUpdate::Pending(activate_at));
+        assert_eq!(census_group.service_config.as_ref().unwrap().incarnation, 1);
+
+        assert_eq!(census_group.update_from_service_config_rumor(&key_cache,
+                                                                 &scheduled,
+                                                                 activate_at),
+                   ServiceConfigUpdate::Activated);
+        assert_eq!(census_group.service_config.as_ref().unwrap().incarnation, 2);
+        assert_eq!(census_group.update_from_service_config_rumor(&key_cache,
+                                                                 &scheduled,
+                                                                 activate_at),
+                   ServiceConfigUpdate::Unchanged);
+    }
+
     fn assert_eq_member_ids(cm: Option<&CensusMember>, id: Option<&str>) {
         assert_eq!(cm.map(|cm| cm.member_id.as_str()), id);
     }
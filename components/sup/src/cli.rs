@@ -80,6 +80,13 @@ mod test {
             assert!(cli().get_matches_from_safe(cmd_vec).is_err());
         }
 
+        #[test]
+        fn gossip_advertise_addr_and_gossip_stun_server_are_mutually_exclusive() {
+            let cmd = "hab-sup run --gossip-advertise-addr 1.1.1.1 --gossip-stun-server 2.2.2.2";
+            let cmd_vec: Vec<&str> = cmd.split_whitespace().collect();
+            assert!(cli().get_matches_from_safe(cmd_vec).is_err());
+        }
+
         #[test]
         fn peer_watch_file_and_peer_are_mutually_exclusive() {
             let cmd_vec: Vec<&str> =
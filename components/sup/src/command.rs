@@ -1,3 +1,7 @@
 //! The CLI commands.
 
+pub mod export;
+pub mod fsck;
+pub mod rejoin;
+pub mod render_context_schema;
 pub mod shell;
@@ -0,0 +1,327 @@
+//! Exporting the services loaded on a Supervisor to configuration management tooling.
+//!
+//! `hab sup export` reads the spec file of each service loaded on the Supervisor selected by
+//! `HAB_RING_NAME` and turns it into the `hab svc load` command that loads the service again with
+//! the same options, followed by the commands that stop it or put it in maintenance if it was. The
+//! commands are written out as a PowerShell DSC configuration or as Ansible tasks, so that a node's
+//! services can be captured and reproduced with the tooling a site already uses.
+
+use crate::{command::fsck::files_with_extension,
+            error::Result,
+            manager::service::spec::{DesiredState,
+                                     ServiceSpec}};
+use habitat_common::outputln;
+use habitat_core::{service::HealthCheckInterval,
+                   url::DEFAULT_BLDR_URL,
+                   ChannelIdent};
+use habitat_sup_protocol::types::BindingMode;
+use std::{fmt::Write,
+          fs,
+          path::Path};
+
+static LOGKEY: &str = "XP";
+const DEFAULT_GROUP: &str = "default";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// A PowerShell DSC configuration with a `Script` resource per service
+    Dsc,
+    /// A list of Ansible tasks, to be included in a role
+    Ansible,
+}
+
+/// The commands that reproduce a loaded service
+struct ServiceCommands {
+    /// The `hab` arguments of each command, the first of which loads the service
+    commands:  Vec<Vec<String>>,
+    /// The spec file whose presence shows the service is loaded
+    spec_file: String,
+    /// The name of the service, unique on a Supervisor
+    name:      String,
+    /// Settings that can't be exported and have to be supplied by hand
+    manual:    Vec<&'static str>,
+}
+
+impl ServiceCommands {
+    fn new(spec: &ServiceSpec, specs_path: &Path) -> Self {
+        let mut commands = vec![load_args(spec)];
+        if spec.desired_state == DesiredState::Down {
+            commands.push(args(&["svc", "stop", &spec.ident.to_string()]));
+        }
+        if spec.maintenance {
+            commands.push(args(&["svc",
+                                 "maintenance",
+                                 "on",
+                                 &format!("{}.{}", spec.ident.name, spec.group)]));
+        }
+        let mut manual = Vec::new();
+        if spec.svc_encrypted_password.is_some() {
+            manual.push("the service user's password (--password)");
+        }
+        if spec.include_dir.is_some() {
+            manual.push("the include directory of the spec file, whose settings are exported");
+        }
+        ServiceCommands { commands,
+                          spec_file: specs_path.join(spec.file()).display().to_string(),
+                          name: spec.ident.name.clone(),
+                          manual }
+    }
+}
+
+/// Export the services loaded on the Supervisor selected by `HAB_RING_NAME` to `output`
+pub fn export(format: Format, output: &Path) -> Result<()> {
+    let specs_path = habitat_sup_protocol::sup_root(None).join("specs");
+    let mut services = Vec::new();
+    for spec_file in files_with_extension(&specs_path, "spec") {
+        match ServiceSpec::from_file(&spec_file) {
+            Ok(spec) => services.push(ServiceCommands::new(&spec, &specs_path)),
+            Err(e) => outputln!("Skipping {}: {}", spec_file.display(), e),
+        }
+    }
+    for service in &services {
+        for setting in &service.manual {
+            outputln!("{} needs {} to be supplied by hand", service.name, setting);
+        }
+    }
+
+    let rendered = match format {
+        Format::Dsc => render_dsc(&services),
+        Format::Ansible => render_ansible(&services, cfg!(windows)),
+    };
+    fs::write(output, rendered)?;
+    outputln!("Exported {} service(s) to {}",
+              services.len(),
+              output.display());
+    Ok(())
+}
+
+fn args(args: &[&str]) -> Vec<String> { args.iter().map(ToString::to_string).collect() }
+
+/// The `hab svc load` arguments that load the service of `spec` with the same options, leaving
+/// out the ones that have their default value
+fn load_args(spec: &ServiceSpec) -> Vec<String> {
+    let mut load = args(&["svc", "load", &spec.ident.to_string()]);
+    let mut opt = |flag: &str, value: String| {
+        load.push(flag.to_string());
+        load.push(value);
+    };
+    if spec.group != DEFAULT_GROUP {
+        opt("--group", spec.group.clone());
+    }
+    if spec.bldr_url != DEFAULT_BLDR_URL {
+        opt("--url", spec.bldr_url.clone());
+    }
+    if spec.channel != ChannelIdent::stable() {
+        opt("--channel", spec.channel.to_string());
+    }
+    if spec.topology != Default::default() {
+        opt("--topology", spec.topology.to_string());
+    }
+    if spec.update_strategy != Default::default() {
+        opt("--strategy", spec.update_strategy.to_string());
+    }
+    if spec.update_condition != Default::default() {
+        opt("--update-condition", spec.update_condition.to_string());
+    }
+    for bind in &spec.binds {
+        opt("--bind", bind.to_string());
+    }
+    if spec.binding_mode == BindingMode::Relaxed {
+        opt("--binding-mode", spec.binding_mode.to_string());
+    }
+    for service_group in &spec.anti_affinity {
+        opt("--anti-affinity", service_group.to_string());
+    }
+    if let Some(max_per_zone) = spec.max_per_zone {
+        opt("--max-per-zone", max_per_zone.to_string());
+    }
+    for preference in &spec.leader_affinity {
+        opt("--leader-affinity", preference.clone());
+    }
+    if spec.health_check_interval != HealthCheckInterval::default() {
+        opt("--health-check-interval",
+            u64::from(spec.health_check_interval).to_string());
+    }
+    if let Some(shutdown_timeout) = spec.shutdown_timeout {
+        opt("--shutdown-timeout", shutdown_timeout.to_string());
+    }
+    if let Some(ref config_from) = spec.config_from {
+        opt("--config-from", config_from.display().to_string());
+    }
+    if spec.strict_deps {
+        load.push("--strict-deps".to_string());
+    }
+    if spec.leader_tie_break {
+        load.push("--leader-tie-break".to_string());
+    }
+    load
+}
+
+/// Quote `arg` as a PowerShell string unless it is a subcommand or a flag
+fn powershell_arg(arg: &str) -> String {
+    if arg.chars().all(|c| c.is_ascii_lowercase() || c == '-') {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "''"))
+    }
+}
+
+/// Quote `arg` for the command line of a Windows process if it needs to be
+fn windows_arg(arg: &str) -> String {
+    if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '"') {
+        format!("\"{}\"", arg.replace('"', "\\\""))
+    } else {
+        arg.to_string()
+    }
+}
+
+/// A YAML string, which JSON strings are
+fn yaml_str(value: &str) -> String { serde_json::Value::from(value).to_string() }
+
+fn render_dsc(services: &[ServiceCommands]) -> String {
+    let mut out = String::from("# Generated by `hab sup export`\nConfiguration \
+                                HabitatServices\n{\n    Import-DscResource -ModuleName \
+                                PSDesiredStateConfiguration\n\n    Node 'localhost'\n    {\n");
+    for service in services {
+        let spec_file = powershell_arg(&service.spec_file);
+        for setting in &service.manual {
+            writeln!(out, "        # TODO: supply {}", setting).ok();
+        }
+        writeln!(out, "        Script {}", powershell_arg(&service.name)).ok();
+        out.push_str("        {\n");
+        writeln!(out,
+                 "            GetScript  = {{ @{{ Result = [string](Test-Path {}) }} }}",
+                 spec_file).ok();
+        writeln!(out,
+                 "            TestScript = {{ Test-Path {} }}",
+                 spec_file).ok();
+        out.push_str("            SetScript  = {\n");
+        for command in &service.commands {
+            let quoted = command.iter()
+                                .map(|arg| powershell_arg(arg))
+                                .collect::<Vec<_>>()
+                                .join(" ");
+            writeln!(out, "                & hab {}", quoted).ok();
+            writeln!(out,
+                     "                if ($LASTEXITCODE -ne 0) {{ throw {} }}",
+                     powershell_arg(&format!("hab {} failed", command.join(" ")))).ok();
+        }
+        out.push_str("            }\n        }\n");
+    }
+    out.push_str("    }\n}\n");
+    out
+}
+
+fn render_ansible(services: &[ServiceCommands], windows: bool) -> String {
+    let mut out = String::from("# Generated by `hab sup export`\n");
+    if services.is_empty() {
+        out.push_str("[]\n");
+    }
+    for service in services {
+        let register = format!("hab_svc_{}",
+                               service.name
+                                      .replace(|c: char| !c.is_ascii_alphanumeric(), "_"));
+        for setting in &service.manual {
+            writeln!(out, "# TODO: supply {}", setting).ok();
+        }
+        for (i, command) in service.commands.iter().enumerate() {
+            writeln!(out,
+                     "- name: {}",
+                     yaml_str(&format!("hab {}", command.join(" ")))).ok();
+            if windows {
+                let cmd = command.iter()
+                                 .map(|arg| windows_arg(arg))
+                                 .collect::<Vec<_>>()
+                                 .join(" ");
+                out.push_str("  ansible.windows.win_command:\n");
+                writeln!(out, "    cmd: {}", yaml_str(&format!("hab {}", cmd))).ok();
+            } else {
+                let argv = std::iter::once("hab").chain(command.iter().map(String::as_str))
+                                                 .map(yaml_str)
+                                                 .collect::<Vec<_>>()
+                                                 .join(", ");
+                out.push_str("  ansible.builtin.command:\n");
+                writeln!(out, "    argv: [{}]", argv).ok();
+            }
+            // Only load the service if it isn't loaded already, and only stop it or put it in
+            // maintenance when it was just loaded
+            if i == 0 {
+                writeln!(out, "    creates: {}", yaml_str(&service.spec_file)).ok();
+                writeln!(out, "  register: {}", register).ok();
+            } else {
+                writeln!(out, "  when: {} is changed", register).ok();
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use habitat_core::{package::PackageIdent,
+                       service::ServiceBind};
+    use std::str::FromStr;
+
+    fn spec() -> ServiceSpec {
+        let mut spec = ServiceSpec::new(PackageIdent::from_str("core/redis").unwrap());
+        spec.group = "prod".to_string();
+        spec.binds = vec![ServiceBind::from_str("backend:postgres.prod").unwrap()];
+        spec.strict_deps = true;
+        spec.desired_state = DesiredState::Down;
+        spec
+    }
+
+    #[test]
+    fn load_args_leave_out_defaults() {
+        let spec = ServiceSpec::new(PackageIdent::from_str("core/redis").unwrap());
+        assert_eq!(load_args(&spec), vec!["svc", "load", "core/redis"]);
+
+        assert_eq!(load_args(&spec()),
+                   vec!["svc",
+                        "load",
+                        "core/redis",
+                        "--group",
+                        "prod",
+                        "--bind",
+                        "backend:postgres.prod",
+                        "--strict-deps"]);
+    }
+
+    #[test]
+    fn dsc_loads_and_stops_the_service() {
+        let services = vec![ServiceCommands::new(&spec(), Path::new("/hab/sup/default/specs"))];
+        let dsc = render_dsc(&services);
+
+        assert!(dsc.contains("        Script redis\n"));
+        assert!(dsc.contains("TestScript = { Test-Path '/hab/sup/default/specs/redis.spec' }"));
+        assert!(dsc.contains("& hab svc load 'core/redis' --group prod --bind \
+                              'backend:postgres.prod' --strict-deps\n"));
+        assert!(dsc.contains("& hab svc stop 'core/redis'\n"));
+    }
+
+    #[test]
+    fn ansible_only_stops_the_service_it_loaded() {
+        let services = vec![ServiceCommands::new(&spec(), Path::new("/hab/sup/default/specs"))];
+        let ansible = render_ansible(&services, false);
+
+        assert!(ansible.contains("    argv: [\"hab\", \"svc\", \"load\", \"core/redis\", \
+                                  \"--group\", \"prod\", \"--bind\", \"backend:postgres.prod\", \
+                                  \"--strict-deps\"]\n    creates: \
+                                  \"/hab/sup/default/specs/redis.spec\"\n  register: \
+                                  hab_svc_redis\n"));
+        assert!(ansible.contains("    argv: [\"hab\", \"svc\", \"stop\", \"core/redis\"]\n  \
+                                  when: hab_svc_redis is changed\n"));
+
+        let ansible = render_ansible(&services, true);
+        assert!(ansible.contains("    cmd: \"hab svc stop core/redis\"\n"));
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(powershell_arg("--group"), "--group");
+        assert_eq!(powershell_arg("it's"), "'it''s'");
+        assert_eq!(windows_arg("C:\\config from"), "\"C:\\config from\"");
+        assert_eq!(windows_arg("core/redis"), "core/redis");
+    }
+}
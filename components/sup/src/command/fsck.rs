@@ -0,0 +1,237 @@
+//! Checking and repairing a Supervisor's state directory.
+//!
+//! `hab sup fsck` validates the files a Supervisor keeps between runs: its member ID, its
+//! incarnation number, the rumors it last heard, the fleet manifest it last applied and its
+//! service spec files. With `--repair`, a corrupt checksummed file is replaced by the copy kept
+//! from before its last write. Any other unusable file is moved aside, keeping it for inspection,
+//! so that the Supervisor recreates it or, for a spec file, no longer loads it.
+
+use crate::{error::{Error,
+                    Result},
+            lock_file,
+            manager::{fleet_manifest::{self,
+                                       APPLIED_MANIFEST_FILE},
+                      service::spec::ServiceSpec,
+                      MEMBER_ID_FILE}};
+use habitat_butterfly::{member::Incarnation,
+                        rumor::{dat_file::DatFileReader,
+                                Departure,
+                                Election,
+                                ElectionUpdate,
+                                Service,
+                                ServiceConfig,
+                                ServiceFile}};
+use habitat_common::outputln;
+use habitat_core::fs::{checksum_path,
+                       checksummed_read,
+                       checksummed_recover,
+                       last_good_path};
+use std::{ffi::OsStr,
+          fs,
+          path::{Path,
+                 PathBuf},
+          str};
+
+static LOGKEY: &str = "FK";
+pub(crate) const INCARNATION_FILE: &str = "INCARNATION";
+const CORRUPT_SUFFIX: &str = ".corrupt";
+
+/// A problem found in the state directory
+struct Problem {
+    path:        PathBuf,
+    description: String,
+    repair:      Repair,
+}
+
+enum Repair {
+    /// Restore the copy of a checksummed file kept from before its last write
+    RestoreLastGood,
+    /// Move the file aside
+    MoveAside,
+}
+
+impl Problem {
+    fn new(path: &Path, description: impl ToString) -> Self {
+        let repair = if checksummed_read(&last_good_path(path)).is_ok() {
+            Repair::RestoreLastGood
+        } else {
+            Repair::MoveAside
+        };
+        Problem { path: path.to_path_buf(),
+                  description: description.to_string(),
+                  repair }
+    }
+
+    fn repair(&self) -> Result<String> {
+        match self.repair {
+            Repair::RestoreLastGood => {
+                let last_good = last_good_path(&self.path);
+                checksummed_recover(&self.path)?;
+                Ok(format!("restored {}", last_good.display()))
+            }
+            Repair::MoveAside => {
+                let aside = move_aside(&self.path)?;
+                // A checksum left behind would make whatever next takes the file's place
+                // look corrupt
+                let checksum = checksum_path(&self.path);
+                if checksum.exists() {
+                    move_aside(&checksum)?;
+                }
+                Ok(format!("moved to {}", aside.display()))
+            }
+        }
+    }
+}
+
+fn move_aside(path: &Path) -> Result<PathBuf> {
+    let mut aside = path.to_path_buf().into_os_string();
+    aside.push(CORRUPT_SUFFIX);
+    let aside = PathBuf::from(aside);
+    fs::rename(path, &aside)?;
+    Ok(aside)
+}
+
+/// Check the state directory of the Supervisor selected by `HAB_RING_NAME`, repairing the
+/// problems found if `repair` is set. Returns an error if any problem remains.
+pub fn fsck(repair: bool) -> Result<()> {
+    let sup_root = habitat_sup_protocol::sup_root(None);
+    if lock_file::supervisor_running()? {
+        return Err(Error::StateDirectoryInUse(sup_root));
+    }
+    outputln!("Checking Supervisor state in {}", sup_root.display());
+    let problems = check(&sup_root);
+    if problems.is_empty() {
+        outputln!("No problems found");
+        return Ok(());
+    }
+
+    let mut remaining = 0;
+    for problem in problems {
+        outputln!("{}: {}", problem.path.display(), problem.description);
+        if !repair {
+            remaining += 1;
+            continue;
+        }
+        match problem.repair() {
+            Ok(action) => outputln!("Repaired {}: {}", problem.path.display(), action),
+            Err(e) => {
+                outputln!("Unable to repair {}: {}", problem.path.display(), e);
+                remaining += 1;
+            }
+        }
+    }
+    if remaining == 0 {
+        Ok(())
+    } else {
+        Err(Error::StateDirectoryProblems(remaining))
+    }
+}
+
+/// How many problems there are in the state directory at `sup_root`
+pub(crate) fn count_problems(sup_root: &Path) -> usize { check(sup_root).len() }
+
+fn check(sup_root: &Path) -> Vec<Problem> {
+    let data_path = sup_root.join("data");
+    let mut problems = Vec::new();
+
+    let member_id_file = sup_root.join(MEMBER_ID_FILE);
+    match checksummed_read(&member_id_file) {
+        Ok(member_id) if !valid_member_id(&String::from_utf8_lossy(&member_id)) => {
+            problems.push(Problem::new(&member_id_file,
+                                       "not a valid member ID, the Supervisor will join the \
+                                        ring as a new member once it is moved aside"));
+        }
+        Err(e) if member_id_file.exists() => problems.push(Problem::new(&member_id_file, e)),
+        _ => {}
+    }
+
+    let incarnation_file = data_path.join(INCARNATION_FILE);
+    if incarnation_file.exists() {
+        if let Err(e) = check_incarnation_file(&incarnation_file) {
+            problems.push(Problem::new(&incarnation_file, e));
+        }
+    }
+
+    let applied_manifest_file = data_path.join(APPLIED_MANIFEST_FILE);
+    if applied_manifest_file.exists() {
+        if let Err(e) = fleet_manifest::verify_applied(&applied_manifest_file) {
+            problems.push(Problem::new(&applied_manifest_file, e));
+        }
+    }
+
+    for dat_file in files_with_extension(&data_path, "rst") {
+        if let Err(e) = check_dat_file(&dat_file) {
+            problems.push(Problem::new(&dat_file, e));
+        }
+    }
+
+    for spec_file in files_with_extension(&sup_root.join("specs"), "spec") {
+        if let Err(e) = check_spec_file(&spec_file) {
+            problems.push(Problem::new(&spec_file, e));
+        }
+    }
+
+    problems
+}
+
+fn valid_member_id(member_id: &str) -> bool {
+    member_id.len() == 32 && member_id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub(crate) fn files_with_extension(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> =
+        fs::read_dir(dir).into_iter()
+                         .flatten()
+                         .flatten()
+                         .map(|entry| entry.path())
+                         .filter(|path| {
+                             path.is_file() && path.extension() == Some(OsStr::new(extension))
+                         })
+                         .collect();
+    files.sort();
+    files
+}
+
+fn check_incarnation_file(path: &Path) -> std::result::Result<(), String> {
+    let data = checksummed_read(path).map_err(|e| e.to_string())?;
+    str::from_utf8(&data).map_err(|e| e.to_string())?
+                         .trim()
+                         .parse::<Incarnation>()
+                         .map_err(|e| format!("not a valid incarnation number, {}", e))?;
+    Ok(())
+}
+
+fn check_dat_file(path: &Path) -> habitat_butterfly::error::Result<()> {
+    let mut reader = DatFileReader::read(path.to_path_buf())?;
+    reader.read_members()?;
+    reader.read_rumors::<Service>()?;
+    reader.read_rumors::<ServiceConfig>()?;
+    reader.read_rumors::<ServiceFile>()?;
+    reader.read_rumors::<Election>()?;
+    reader.read_rumors::<ElectionUpdate>()?;
+    reader.read_rumors::<Departure>()?;
+    Ok(())
+}
+
+fn check_spec_file(path: &Path) -> std::result::Result<(), String> {
+    let spec = ServiceSpec::from_file(path).map_err(|e| e.to_string())?;
+    if path.file_stem() != Some(OsStr::new(&spec.ident.name)) {
+        return Err(format!("file name does not match ident = \"{}\", it \
+                            should be called '{}.spec'",
+                           spec.ident, spec.ident.name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn member_ids_are_uuids_in_simple_form() {
+        assert!(valid_member_id("0123456789abcdef0123456789abcdef"));
+        assert!(!valid_member_id("0123456789abcdef0123456789abcdef\n"));
+        assert!(!valid_member_id("0123456789abcdef"));
+        assert!(!valid_member_id("not a member id, not a member id"));
+    }
+}
@@ -0,0 +1,68 @@
+//! Rejoining the ring after departing it with `hab sup depart --self`.
+//!
+//! `hab sup rejoin` checks that the Supervisor's state directory is consistent with the departure
+//! it recorded, then clears the departed member ID along with the rumors and incarnation number it
+//! kept as that member. The Supervisor joins the ring as a new member the next time it starts.
+
+use super::fsck::{self,
+                  INCARNATION_FILE};
+use crate::{error::{Error,
+                    Result},
+            lock_file,
+            manager::{tombstone::Tombstone,
+                      MEMBER_ID_FILE}};
+use habitat_common::outputln;
+use habitat_core::fs::checksummed_remove;
+use std::{fs,
+          io,
+          path::Path};
+
+static LOGKEY: &str = "RJ";
+
+/// Clear the departure of the Supervisor selected by `HAB_RING_NAME`, so that it joins the ring
+/// as a new member.
+pub fn rejoin() -> Result<()> {
+    let sup_root = habitat_sup_protocol::sup_root(None);
+    if lock_file::supervisor_running()? {
+        return Err(Error::StateDirectoryInUse(sup_root));
+    }
+    let data_path = sup_root.join("data");
+    let tombstone_path = Tombstone::path(&data_path);
+    let tombstone = Tombstone::read(&tombstone_path)?.ok_or(Error::NotDeparted)?;
+
+    let member_id_file = sup_root.join(MEMBER_ID_FILE);
+    match fs::read_to_string(&member_id_file) {
+        Ok(member_id) if member_id != tombstone.member_id => {
+            return Err(Error::RejoinUnsafe(format!("the Supervisor departed as \
+                                                    member {}, but its state \
+                                                    directory belongs to member \
+                                                    {}",
+                                                   tombstone.member_id, member_id)));
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(Error::BadDataFile(member_id_file, e)),
+    }
+    let problems = fsck::count_problems(&sup_root);
+    if problems > 0 {
+        return Err(Error::RejoinUnsafe(format!("{} problem(s) were found in \
+                                                the state directory by `hab \
+                                                sup fsck`",
+                                               problems)));
+    }
+
+    outputln!("Clearing departed member {}", tombstone.member_id);
+    remove_if_exists(&member_id_file)?;
+    remove_if_exists(&data_path.join(format!("{}.rst", tombstone.member_id)))?;
+    remove_if_exists(&data_path.join(INCARNATION_FILE))?;
+    // The tombstone goes last, so that an interrupted rejoin can be run again
+    remove_if_exists(&tombstone_path)?;
+    outputln!("The Supervisor will join the ring as a new member when it starts");
+    Ok(())
+}
+
+/// Remove a file along with its checksum and last good copy, so that the Supervisor cannot
+/// restore what it held as the departed member
+fn remove_if_exists(path: &Path) -> Result<()> {
+    checksummed_remove(path).map_err(|e| Error::BadDataFile(path.to_path_buf(), e))
+}
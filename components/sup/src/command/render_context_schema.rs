@@ -0,0 +1,47 @@
+//! `hab sup render-context-schema` prints the JSON schema of the render context, the data that
+//! the configuration and hook templates of a service can refer to (`sys`, `pkg`, `pkgs`, `cfg`,
+//! `svc` and `bind`), as this Supervisor renders it. Editors and linters can use it to check the
+//! references of templates without running them.
+
+use crate::{error::Result,
+            VERSION};
+use serde_json::Value;
+
+/// The schema of the render context. It is also used to validate the render context in tests, so
+/// it stays in line with what the Supervisor renders.
+const RENDER_CONTEXT_SCHEMA: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"),
+                                                         "/doc/render_context_schema.json"));
+
+pub fn render_context_schema() -> Result<()> {
+    println!("{}", schema());
+    Ok(())
+}
+
+/// The schema, noting the version of the Supervisor it describes
+fn schema() -> String {
+    let mut schema: Value =
+        serde_json::from_str(RENDER_CONTEXT_SCHEMA).expect("the render context schema is JSON");
+    if let Value::Object(ref mut schema) = schema {
+        schema.insert(String::from("$comment"),
+                      Value::from(format!("Habitat Supervisor {}", VERSION.trim())));
+    }
+    serde_json::to_string_pretty(&schema).expect("the render context schema serializes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_describes_the_whole_render_context() {
+        let schema: Value = serde_json::from_str(&schema()).unwrap();
+        for key in &["sys", "pkg", "pkgs", "cfg", "svc", "bind"] {
+            assert!(schema["properties"].get(key).is_some(),
+                    "{} is not described",
+                    key);
+        }
+        assert!(schema["$comment"].as_str()
+                                  .unwrap()
+                                  .starts_with("Habitat Supervisor "));
+    }
+}
@@ -4,9 +4,11 @@
 //! CtlGateway.
 //!
 //! The [`ctl_gateway.client`] and [`ctl_gateway.server`] speak a streaming, multiplexed, binary
-//! protocol defined in [`protocol.codec`].
+//! protocol defined in [`protocol.codec`]. The same requests may optionally be made over gRPC to
+//! the [`ctl_gateway.grpc`] server.
 
 pub mod acceptor;
+pub mod grpc;
 pub mod handler;
 pub mod server;
 
@@ -24,7 +26,10 @@ use std::{fmt,
                File},
           io::{self,
                Write},
-          path::Path};
+          net::SocketAddr,
+          path::Path,
+          time::{Duration,
+                 Instant}};
 use termcolor::{Color,
                 ColorSpec,
                 StandardStream,
@@ -34,11 +39,26 @@ use termcolor::{Color,
 pub const REQ_TIMEOUT: u64 = 10_000;
 static LOGKEY: &str = "AG";
 
+habitat_core::env_config_duration!(CtlRequestDeadline,
+                                   HAB_CTL_REQUEST_DEADLINE_SECS => from_secs,
+                                   Duration::from_secs(600));
+
 /// The control gateway secret should only be readable by the
 /// Supervisor process
 #[cfg(not(windows))]
 pub const CTL_SECRET_PERMISSIONS: u32 = 0o600;
 
+/// The deadline of a request received now, unless deadlines are disabled by setting
+/// `HAB_CTL_REQUEST_DEADLINE_SECS` to 0
+fn request_deadline() -> Option<Instant> {
+    let deadline: Duration = CtlRequestDeadline::configured_value().into();
+    if deadline == Duration::ZERO {
+        None
+    } else {
+        Some(Instant::now() + deadline)
+    }
+}
+
 /// Used by modules outside of the CtlGateway for seamlessly replying to transactional messages.
 /// This type is used in functions which can be called by the CtlGateway such as
 /// [`Manager::service_load`] and [`Manager::service_unload`].
@@ -63,6 +83,11 @@ pub struct CtlRequest {
     tx:                 Option<server::CtlSender>,
     /// Transaction for the given request.
     transaction:        Option<habitat_sup_protocol::codec::SrvTxn>,
+    /// When the CtlGateway stops waiting for replies to the request and answers it with a
+    /// timeout itself. Requests without a deadline are waited for as long as they take.
+    deadline:           Option<Instant>,
+    /// The address of the client that sent the request, if it is known.
+    client:             Option<SocketAddr>,
     current_color_spec: ColorSpec,
     is_new_line:        bool,
 }
@@ -75,10 +100,30 @@ impl CtlRequest {
                -> Self {
         CtlRequest { tx: Some(tx),
                      transaction,
+                     deadline: request_deadline(),
+                     client: None,
                      current_color_spec: ColorSpec::new(),
                      is_new_line: true }
     }
 
+    /// Move the deadline of the request forward to `deadline`, if that comes before the one it
+    /// has, such as when the client sets a deadline of its own.
+    pub fn limit_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(self.deadline.map_or(deadline, |d| d.min(deadline)));
+    }
+
+    /// The time after which replies to the request are no longer sent to the client. Operations
+    /// that wait on hooks or the Launcher on behalf of the request should give up by then.
+    pub fn deadline(&self) -> Option<Instant> { self.deadline }
+
+    /// Returns true if replies to the request can no longer reach the client, because the client
+    /// went away or the deadline of the request passed. Bare requests are never cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.tx.as_ref().map_or(false, |tx| tx.is_closed())
+        || self.deadline
+               .map_or(false, |deadline| Instant::now() >= deadline)
+    }
+
     /// Reply to the transaction with the given message but indicate to the receiver that this is
     /// not the final message for the transaction.
     pub fn reply_partial<T>(&mut self, msg: T)
@@ -98,6 +143,16 @@ impl CtlRequest {
     /// Returns true if the request is transactional and false if not.
     pub fn transactional(&self) -> bool { self.transaction.is_some() && self.tx.is_some() }
 
+    /// Returns true if the request came from a client of the CtlGateway rather than being a "bare
+    /// request" made by the Supervisor itself.
+    pub fn has_client(&self) -> bool { self.tx.is_some() }
+
+    /// Record the address of the client that sent the request.
+    pub fn set_client(&mut self, client: SocketAddr) { self.client = Some(client); }
+
+    /// The address of the client that sent the request, for attributing the changes it makes.
+    pub fn client(&self) -> Option<SocketAddr> { self.client }
+
     fn send_msg<T>(&mut self, msg: T, complete: bool)
         where T: Into<habitat_sup_protocol::codec::SrvMessage> + fmt::Debug
     {
@@ -288,3 +343,35 @@ fn set_permissions<T: AsRef<Path>>(path: T) -> habitat_core::error::Result<()> {
 
     win_perm::harden_path(path.as_ref())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+
+    #[test]
+    fn requests_are_cancelled_when_their_client_goes_away() {
+        let (tx, rx) = mpsc::unbounded();
+        let req = CtlRequest::new(tx, None);
+        assert!(!req.is_cancelled());
+        drop(rx);
+        assert!(req.is_cancelled());
+    }
+
+    #[test]
+    fn requests_are_cancelled_when_their_deadline_passes() {
+        let (tx, _rx) = mpsc::unbounded();
+        let mut req = CtlRequest::new(tx, None);
+        let later = Instant::now() + Duration::from_secs(60);
+        req.limit_deadline(later);
+        assert!(req.deadline().unwrap() <= later);
+        assert!(!req.is_cancelled());
+        req.limit_deadline(Instant::now());
+        assert!(req.is_cancelled());
+    }
+
+    #[test]
+    fn bare_requests_are_never_cancelled() {
+        assert!(!CtlRequest::default().is_cancelled());
+    }
+}
@@ -0,0 +1,347 @@
+//! Listening server for clients calling the CtlGateway over gRPC.
+//!
+//! Requests are turned into the same [`server::CtlCommand`]s the SrvProtocol server dispatches to
+//! the main thread, and the replies sent back for them are streamed to the client. Clients
+//! authenticate each call with the CtlGateway secret key and may set a deadline for it, which
+//! shortens the deadline the Supervisor sets for every request. The server also answers gRPC
+//! reflection requests so that generic tools can discover the service. It is served over TLS with
+//! the control gateway's `ServerConfig` when the control gateway uses TLS.
+
+use super::server::{self,
+                    CtlReceiver,
+                    MgrSender,
+                    SrvHandler};
+use futures::{channel::mpsc,
+              stream::{self,
+                       Stream,
+                       StreamExt}};
+use habitat_core::crypto;
+use habitat_sup_protocol::{codec::{SrvMessage,
+                                   SrvTxn},
+                           ctl,
+                           grpc::{ctl_gateway_server::{CtlGateway,
+                                                       CtlGatewayServer},
+                                  reply,
+                                  Reply,
+                                  FILE_DESCRIPTOR_SET},
+                           message::MessageStatic,
+                           net::{ErrCode,
+                                 NetErr},
+                           types};
+use prometheus::HistogramTimer;
+use prost::Message;
+use rustls::ServerConfig as TlsServerConfig;
+use std::{net::SocketAddr,
+          pin::Pin,
+          time::Duration};
+use tokio::time::{self,
+                  Instant};
+use tonic::{metadata::MetadataMap,
+            transport::{Server,
+                        ServerTlsConfig},
+            Code,
+            Request,
+            Response,
+            Status};
+
+/// Metadata key of the gRPC deadline set by the client.
+const GRPC_TIMEOUT_KEY: &str = "grpc-timeout";
+
+type ReplyStream = Pin<Box<dyn Stream<Item = Result<Reply, Status>> + Send>>;
+
+pub(crate) struct CtlGatewayGrpcServer {
+    pub(crate) listen_addr: SocketAddr,
+    pub(crate) secret_key:  String,
+    pub(crate) mgr_sender:  MgrSender,
+    pub(crate) tls_config:  Option<TlsServerConfig>,
+}
+
+impl CtlGatewayGrpcServer {
+    /// Serve the CtlGateway over gRPC until the Supervisor exits.
+    ///
+    /// Calls without an `authorization: Bearer <secret key>` metadata entry matching
+    /// `secret_key` are rejected. Commands are sent to the main thread over `mgr_sender`.
+    pub async fn run(self) {
+        let Self { listen_addr,
+                   secret_key,
+                   mgr_sender,
+                   tls_config, } = self;
+
+        let reflection =
+            tonic_reflection::server::Builder::configure()
+                .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+                .build()
+                .expect("Could not build ctl gateway gRPC reflection service!");
+        let gateway = GrpcGateway { mgr_sender };
+        let gateway = CtlGatewayServer::with_interceptor(gateway, move |req: Request<()>| {
+            authenticate(req.metadata(), &secret_key)?;
+            Ok(req)
+        });
+        let mut builder = Server::builder();
+        if let Some(tls_config) = tls_config {
+            let mut server_tls_config = ServerTlsConfig::new();
+            server_tls_config.rustls_server_config(tls_config);
+            builder = match builder.tls_config(server_tls_config) {
+                Ok(builder) => builder,
+                Err(e) => {
+                    error!("ctl gateway gRPC server TLS setup failed, err: {}", e);
+                    return;
+                }
+            };
+        }
+        if let Err(e) = builder.add_service(reflection)
+                               .add_service(gateway)
+                               .serve(listen_addr)
+                               .await
+        {
+            error!("ctl gateway gRPC server failed, err: {}", e);
+        }
+    }
+}
+
+struct GrpcGateway {
+    mgr_sender: MgrSender,
+}
+
+impl GrpcGateway {
+    /// Dispatch a request to the main thread as the SrvProtocol server does and stream back its
+    /// replies.
+    ///
+    /// # Locking (see locking.md)
+    /// * `GatewayState::inner` (read)
+    /// * `ManagerServices::inner` (read)
+    async fn call_gsr_msr<T>(&self, request: Request<T>) -> Result<Response<ReplyStream>, Status>
+        where T: Message + MessageStatic
+    {
+        let client_deadline =
+            grpc_timeout(request.metadata()).map(|timeout| Instant::now() + timeout);
+        let client = request.remote_addr();
+        let timer = server::rpc_timer(T::MESSAGE_ID);
+        trace!("OnGrpcMessage, {}", T::MESSAGE_ID);
+
+        // The Manager only replies to transactional requests. Each call has its own reply
+        // channel, so every call can use the same transaction.
+        let mut msg = SrvMessage::from(request.into_inner());
+        let mut txn = SrvTxn::default();
+        txn.increment();
+        msg.set_transaction(txn);
+
+        let (ctl_sender, ctl_receiver) = mpsc::unbounded();
+        let mut cmd = SrvHandler::command_from_message_gsr_msr(&msg, ctl_sender)
+            .await
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        if let Some(client_deadline) = client_deadline {
+            cmd.req.limit_deadline(client_deadline.into_std());
+        }
+        if let Some(client) = client {
+            cmd.req.set_client(client);
+        }
+        let deadline = cmd.req.deadline().map(Instant::from_std);
+        self.mgr_sender
+            .unbounded_send(cmd)
+            .map_err(|_| Status::unavailable("Supervisor is shutting down"))?;
+        Ok(Response::new(reply_stream(ctl_receiver, deadline, timer)))
+    }
+}
+
+/// Implement each RPC of the service by dispatching its request with `call_gsr_msr`.
+macro_rules! ctl_gateway_rpcs {
+    ($($rpc:ident, $stream:ident => $request:ty;)*) => {
+        #[tonic::async_trait]
+        impl CtlGateway for GrpcGateway {
+            $(
+                type $stream = ReplyStream;
+
+                async fn $rpc(&self,
+                              request: Request<$request>)
+                              -> Result<Response<ReplyStream>, Status> {
+                    self.call_gsr_msr(request).await
+                }
+            )*
+        }
+    };
+}
+
+ctl_gateway_rpcs! {
+    svc_get_default_cfg, SvcGetDefaultCfgStream => ctl::SvcGetDefaultCfg;
+    svc_file_put, SvcFilePutStream => ctl::SvcFilePut;
+    svc_file_status, SvcFileStatusStream => ctl::SvcFileStatus;
+    svc_update_history, SvcUpdateHistoryStream => ctl::SvcUpdateHistory;
+    svc_set_cfg, SvcSetCfgStream => ctl::SvcSetCfg;
+    svc_validate_cfg, SvcValidateCfgStream => ctl::SvcValidateCfg;
+    svc_load, SvcLoadStream => ctl::SvcLoad;
+    svc_load_bundle, SvcLoadBundleStream => ctl::SvcLoadBundle;
+    svc_update, SvcUpdateStream => ctl::SvcUpdate;
+    svc_unload, SvcUnloadStream => ctl::SvcUnload;
+    svc_start, SvcStartStream => ctl::SvcStart;
+    svc_stop, SvcStopStream => ctl::SvcStop;
+    svc_status, SvcStatusStream => ctl::SvcStatus;
+    svc_maintenance, SvcMaintenanceStream => ctl::SvcMaintenance;
+    sup_depart, SupDepartStream => ctl::SupDepart;
+    sup_depart_self, SupDepartSelfStream => ctl::SupDepartSelf;
+    sup_restart, SupRestartStream => ctl::SupRestart;
+    sup_fleet_manifest_apply, SupFleetManifestApplyStream => ctl::SupFleetManifestApply;
+    sup_ring_status, SupRingStatusStream => ctl::SupRingStatus;
+    sup_config_reload, SupConfigReloadStream => ctl::SupConfigReload;
+    sup_denylist_update, SupDenylistUpdateStream => ctl::SupDenylistUpdate;
+}
+
+fn authenticate(metadata: &MetadataMap, secret_key: &str) -> Result<(), Status> {
+    let token = metadata.get("authorization")
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.strip_prefix("Bearer "))
+                        .ok_or_else(|| Status::unauthenticated("missing secret key"))?;
+    if crypto::secure_eq(token, secret_key) {
+        Ok(())
+    } else {
+        Err(Status::unauthenticated("secret key mismatch"))
+    }
+}
+
+/// Stream the replies to a request until the last one, ending the stream with
+/// `DEADLINE_EXCEEDED` if the deadline of the client or the Supervisor passes first. The request
+/// is then cancelled, but any part of it already underway keeps running in the Supervisor.
+fn reply_stream(replies: CtlReceiver,
+                deadline: Option<Instant>,
+                timer: HistogramTimer)
+                -> ReplyStream {
+    let replies = stream::unfold(Some((replies, timer)), move |state| {
+        async move {
+            let (mut replies, timer) = state?;
+            let next = match deadline {
+                Some(deadline) => {
+                    match time::timeout_at(deadline, replies.next()).await {
+                        Ok(next) => next,
+                        Err(_) => {
+                            let status = Status::deadline_exceeded("deadline exceeded");
+                            return Some((Err(status), None));
+                        }
+                    }
+                }
+                None => replies.next().await,
+            };
+            let msg = next?;
+            trace!("MgrSender -> GrpcGateway, {:?}", msg);
+            if msg.is_complete() {
+                timer.observe_duration();
+                Some((reply_from_message(&msg), None))
+            } else {
+                Some((reply_from_message(&msg), Some((replies, timer))))
+            }
+        }
+    });
+    Box::pin(replies)
+}
+
+fn reply_from_message(msg: &SrvMessage) -> Result<Reply, Status> {
+    let reply = match msg.message_id() {
+        "NetOk" => reply::Reply::Ok(parse(msg)?),
+        "NetErr" => return Err(status_from_net_err(&parse(msg)?)),
+        "ConsoleLine" => reply::Reply::ConsoleLine(parse(msg)?),
+        "NetProgress" => reply::Reply::Progress(parse(msg)?),
+        "ServiceStatus" => reply::Reply::ServiceStatus(parse::<types::ServiceStatus>(msg)?),
+        "ServiceCfg" => reply::Reply::ServiceCfg(parse::<types::ServiceCfg>(msg)?),
+        "SupConfigReloadResult" => reply::Reply::ConfigReloadResult(parse(msg)?),
+        "RingStatus" => reply::Reply::RingStatus(parse(msg)?),
+        "ServiceFileReceipt" => reply::Reply::ServiceFileReceipt(parse(msg)?),
+        "ServiceUpdateRecord" => reply::Reply::ServiceUpdateRecord(parse(msg)?),
+        "SupDenylist" => reply::Reply::Denylist(parse(msg)?),
+        message_id => {
+            return Err(Status::internal(format!("Unexpected reply, {}", message_id)));
+        }
+    };
+    Ok(Reply { reply: Some(reply) })
+}
+
+fn parse<T>(msg: &SrvMessage) -> Result<T, Status>
+    where T: Message + MessageStatic + Default
+{
+    msg.parse::<T>()
+       .map_err(|e| Status::internal(format!("Unable to decode {}, {}", T::MESSAGE_ID, e)))
+}
+
+fn status_from_net_err(err: &NetErr) -> Status {
+    let code = match ErrCode::from_i32(err.code) {
+        Some(ErrCode::Io) => Code::Unavailable,
+        Some(ErrCode::NotFound) => Code::NotFound,
+        Some(ErrCode::Conflict) => Code::AlreadyExists,
+        Some(ErrCode::Unauthorized) => Code::PermissionDenied,
+        Some(ErrCode::NotSupported) | Some(ErrCode::UpdateClient) => Code::Unimplemented,
+        Some(ErrCode::BadPayload) | Some(ErrCode::InvalidPayload) => Code::InvalidArgument,
+        Some(ErrCode::EntityTooLarge) => Code::ResourceExhausted,
+        Some(ErrCode::DeadlineExceeded) => Code::DeadlineExceeded,
+        Some(ErrCode::Internal) | Some(ErrCode::TlsHandshakeFailed) | None => Code::Internal,
+    };
+    Status::new(code, err.msg.clone())
+}
+
+/// Parse the deadline a client sets with the `grpc-timeout` metadata entry, an integer of at most
+/// eight digits followed by its unit.
+fn grpc_timeout(metadata: &MetadataMap) -> Option<Duration> {
+    let value = metadata.get(GRPC_TIMEOUT_KEY)?.to_str().ok()?;
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (amount, unit) = value.split_at(value.len() - 1);
+    let amount = amount.parse::<u64>().ok()?;
+    let timeout = match unit {
+        "H" => Duration::from_secs(amount * 60 * 60),
+        "M" => Duration::from_secs(amount * 60),
+        "S" => Duration::from_secs(amount),
+        "m" => Duration::from_millis(amount),
+        "u" => Duration::from_micros(amount),
+        "n" => Duration::from_nanos(amount),
+        _ => return None,
+    };
+    Some(timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use habitat_sup_protocol::net;
+
+    fn metadata(key: &'static str, value: &'static str) -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(key, value.parse().unwrap());
+        metadata
+    }
+
+    #[test]
+    fn grpc_timeout_is_parsed_from_metadata() {
+        assert_eq!(grpc_timeout(&metadata(GRPC_TIMEOUT_KEY, "2S")),
+                   Some(Duration::from_secs(2)));
+        assert_eq!(grpc_timeout(&metadata(GRPC_TIMEOUT_KEY, "1500m")),
+                   Some(Duration::from_millis(1500)));
+        assert_eq!(grpc_timeout(&metadata(GRPC_TIMEOUT_KEY, "1H")),
+                   Some(Duration::from_secs(3600)));
+        assert_eq!(grpc_timeout(&metadata(GRPC_TIMEOUT_KEY, "123456789S")),
+                   None);
+        assert_eq!(grpc_timeout(&metadata(GRPC_TIMEOUT_KEY, "10x")), None);
+        assert_eq!(grpc_timeout(&MetadataMap::new()), None);
+    }
+
+    #[test]
+    fn calls_are_authenticated_with_the_secret_key() {
+        let secret_key = "s3cr3t";
+        assert!(authenticate(&metadata("authorization", "Bearer s3cr3t"), secret_key).is_ok());
+
+        let mismatch = authenticate(&metadata("authorization", "Bearer wrong"), secret_key);
+        assert_eq!(mismatch.unwrap_err().code(), Code::Unauthenticated);
+
+        let missing = authenticate(&MetadataMap::new(), secret_key);
+        assert_eq!(missing.unwrap_err().code(), Code::Unauthenticated);
+    }
+
+    #[test]
+    fn net_errors_end_the_reply_stream_with_a_status() {
+        let msg = SrvMessage::from(net::err(ErrCode::NotFound, "Service not loaded, core/redis"));
+        let status = reply_from_message(&msg).unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), "Service not loaded, core/redis");
+
+        let msg = SrvMessage::from(net::ok());
+        assert!(matches!(reply_from_message(&msg).unwrap().reply,
+                         Some(reply::Reply::Ok(_))));
+    }
+}
@@ -43,6 +43,10 @@ impl Future for CtlHandler {
         // the reactor (long-running tasks should spawn their own
         // threads to do the main work).
         let inner = self.get_mut();
+        if inner.cmd.req.is_cancelled() {
+            debug!("CtlHandler skipped a request that was cancelled before it ran");
+            return Poll::Ready(());
+        }
         if let Err(err) = inner.cmd.run(&inner.state, inner.action_sender.clone()) {
             debug!("CtlHandler failed, {:?}", err);
             if inner.cmd.req.transactional() {
@@ -10,9 +10,10 @@
 
 use super::{CtlRequest,
             REQ_TIMEOUT};
-use crate::manager::{action::ActionSender,
-                     commands,
-                     ManagerState};
+use crate::{manager::{action::ActionSender,
+                      commands,
+                      ManagerState},
+            systemd};
 use futures::{channel::mpsc,
               executor,
               prelude::*,
@@ -34,21 +35,18 @@ use pin_project::pin_project;
 use prometheus::{HistogramTimer,
                  HistogramVec,
                  IntCounterVec};
-use rustls::{AllowAnyAuthenticatedClient,
-             Certificate,
-             NoClientAuth,
-             PrivateKey,
-             RootCertStore,
-             ServerConfig as TlsServerConfig};
+use rustls::ServerConfig as TlsServerConfig;
 use std::{error,
           fmt,
           io,
-          net::SocketAddr,
+          net::{IpAddr,
+                SocketAddr},
           pin::Pin,
           sync::{Arc,
                  Mutex},
           time::Duration};
-use tokio::{net::TcpListener,
+use tokio::{net::{self as tokio_net,
+                  TcpListener},
             task,
             time};
 use tokio_util::codec::Decoder;
@@ -149,6 +147,7 @@ impl CtlCommand {
 /// Server's client representation. Each new connection will allocate a new Client.
 struct Client {
     state: Arc<Mutex<SrvState>>,
+    addr:  SocketAddr,
 }
 
 impl Client {
@@ -165,7 +164,7 @@ impl Client {
                               .map_err(|_| {
                                   io::Error::new(io::ErrorKind::TimedOut, "client timed out")
                               })??;
-        SrvHandler::new(socket, mgr_sender).await
+        SrvHandler::new(socket, mgr_sender, self.addr).await
     }
 
     /// Initiate a handshake with the connected client before allowing future requests. A failed
@@ -271,7 +270,7 @@ mod util {
 /// A `Future` that will resolve into a stream of one or more `SrvMessage` replies.
 #[must_use = "futures do nothing unless polled"]
 #[pin_project]
-struct SrvHandler {
+pub(super) struct SrvHandler {
     #[pin]
     io:           SrvStream,
     state:        SrvHandlerState,
@@ -279,10 +278,16 @@ struct SrvHandler {
     ctl_receiver: CtlReceiver,
     ctl_sender:   CtlSender,
     timer:        Option<HistogramTimer>,
+    /// The transaction of the request being handled
+    txn:          Option<SrvTxn>,
+    /// Fires when the deadline of the request being handled passes
+    deadline:     Option<Pin<Box<time::Sleep>>>,
+    /// The address of the connected client
+    client:       SocketAddr,
 }
 
 impl SrvHandler {
-    fn new(io: SrvStream, mgr_sender: MgrSender) -> Self {
+    fn new(io: SrvStream, mgr_sender: MgrSender, client: SocketAddr) -> Self {
         let (ctl_sender, ctl_receiver) = mpsc::unbounded();
 
         SrvHandler { io,
@@ -290,19 +295,27 @@ impl SrvHandler {
                      mgr_sender,
                      ctl_receiver,
                      ctl_sender,
-                     timer: None }
+                     timer: None,
+                     txn: None,
+                     deadline: None,
+                     client }
     }
 
     /// # Locking (see locking.md)
     /// * `GatewayState::inner` (read)
     /// * `ManagerServices::inner` (read)
-    async fn command_from_message_gsr_msr(msg: &SrvMessage,
-                                          ctl_sender: CtlSender)
-                                          -> std::result::Result<CtlCommand, HandlerError> {
+    pub(super) async fn command_from_message_gsr_msr(
+        msg: &SrvMessage,
+        ctl_sender: CtlSender)
+        -> std::result::Result<CtlCommand, HandlerError> {
         match msg.message_id() {
             "SvcGetDefaultCfg" => util::to_command(msg, ctl_sender, commands::service_cfg_msr),
-            "SvcFilePut" => util::to_command(msg, ctl_sender, commands::service_file_put),
-            "SvcSetCfg" => util::to_command(msg, ctl_sender, commands::service_cfg_set),
+            "SvcFilePut" => util::to_command(msg, ctl_sender, commands::service_file_put_mlr),
+            "SvcFileStatus" => util::to_command(msg, ctl_sender, commands::service_file_status_msr),
+            "SvcUpdateHistory" => {
+                util::to_command(msg, ctl_sender, commands::service_update_history_msr)
+            }
+            "SvcSetCfg" => util::to_command(msg, ctl_sender, commands::service_cfg_set_mlr),
             "SvcValidateCfg" => util::to_command(msg, ctl_sender, commands::service_cfg_validate),
             "SvcLoad" => {
                 // This arm doesn't use a `util` module helper because
@@ -325,13 +338,66 @@ impl SrvHandler {
                                        })
                                    }))
             }
-            "SvcUpdate" => util::to_supervisor_command(msg, ctl_sender, commands::service_update),
-            "SvcUnload" => util::to_supervisor_command(msg, ctl_sender, commands::service_unload),
+            "SvcLoadBundle" => {
+                // Like `SvcLoad`, block on the future of the load.
+                let m = msg.parse::<protocol::ctl::SvcLoadBundle>()
+                           .map_err(HandlerError::from)?;
+                Ok(CtlCommand::new(ctl_sender,
+                                   msg.transaction(),
+                                   move |state, req, _action_sender| {
+                                       task::block_in_place(|| {
+                                           executor::block_on(commands::service_load_bundle(state,
+                                                                                            req,
+                                                                                            m.clone()))
+                                       })
+                                   }))
+            }
+            "SvcUpdate" => util::to_command(msg, ctl_sender, commands::service_update),
+            "SvcUnload" => {
+                util::to_supervisor_command(msg, ctl_sender, commands::service_unload_msr)
+            }
             "SvcStart" => util::to_command(msg, ctl_sender, commands::service_start),
             "SvcStop" => util::to_supervisor_command(msg, ctl_sender, commands::service_stop),
             "SvcStatus" => util::to_command(msg, ctl_sender, commands::service_status_gsr),
-            "SupDepart" => util::to_command(msg, ctl_sender, commands::supervisor_depart),
+            "SvcMaintenance" => {
+                util::to_command(msg, ctl_sender, commands::service_maintenance_msr)
+            }
+            "SupDepart" => {
+                // The address is resolved here, as a lookup would hold up the Supervisor if it
+                // were made by the command
+                let m = msg.parse::<protocol::ctl::SupDepart>()
+                           .map_err(HandlerError::from)?;
+                let addresses = match m.address.as_deref() {
+                    Some(address) => resolve(address).await,
+                    None => Vec::new(),
+                };
+                Ok(CtlCommand::new(ctl_sender,
+                                   msg.transaction(),
+                                   move |state, req, _action_sender| {
+                                       commands::supervisor_depart_rsr_mlr(state,
+                                                                           req,
+                                                                           m.clone(),
+                                                                           &addresses)
+                                   }))
+            }
+            "SupDepartSelf" => {
+                util::to_supervisor_command(msg, ctl_sender, commands::supervisor_depart_self_rsr)
+            }
             "SupRestart" => util::to_command(msg, ctl_sender, commands::supervisor_restart),
+            "SupFleetManifestApply" => {
+                util::to_command(msg, ctl_sender, commands::supervisor_fleet_manifest_apply)
+            }
+            "SupRingStatus" => {
+                util::to_command(msg, ctl_sender, commands::supervisor_ring_status_gsr)
+            }
+            "SupConfigReload" => {
+                util::to_supervisor_command(msg, ctl_sender, commands::supervisor_config_reload)
+            }
+            "SupDenylistUpdate" => {
+                util::to_command(msg,
+                                 ctl_sender,
+                                 commands::supervisor_denylist_update_mlw_sdlw_rhw)
+            }
             _ => {
                 warn!("Unhandled message, {}", msg.message_id());
                 Err(HandlerError::from(io::Error::from(io::ErrorKind::InvalidData)))
@@ -339,12 +405,51 @@ impl SrvHandler {
         }
     }
 
-    fn start_timer(&mut self, label: &str) {
-        let label_values = &[label];
-        RPC_CALLS.with_label_values(label_values).inc();
-        let timer = RPC_CALL_DURATION.with_label_values(label_values)
-                                     .start_timer();
-        self.timer = Some(timer);
+    fn start_timer(&mut self, label: &str) { self.timer = Some(rpc_timer(label)); }
+
+    /// Poll for the deadline of the request being handled to pass. Requests without a deadline
+    /// never time out.
+    fn poll_deadline(&mut self, cx: &mut Context) -> Poll<()> {
+        match self.deadline.as_mut() {
+            Some(deadline) => deadline.as_mut().poll(cx),
+            None => Poll::Pending,
+        }
+    }
+
+    /// Stop taking replies to the request being handled, and answer it with a timeout instead.
+    /// Commands still running for the request see it as cancelled.
+    fn deadline_exceeded(&mut self) -> Option<SrvMessage> {
+        self.ctl_receiver.close();
+        let txn = self.txn?;
+        let mut reply = SrvMessage::from(net::err(ErrCode::DeadlineExceeded,
+                                                  "The Supervisor did not finish handling the \
+                                                   request before its deadline"));
+        reply.reply_for(txn, true);
+        Some(reply)
+    }
+}
+
+/// Count an RPC call and start timing it. The duration is recorded when the timer is observed or
+/// dropped.
+pub(super) fn rpc_timer(label: &str) -> HistogramTimer {
+    let label_values = &[label];
+    RPC_CALLS.with_label_values(label_values).inc();
+    RPC_CALL_DURATION.with_label_values(label_values)
+                     .start_timer()
+}
+
+/// The IP addresses `host` resolves to, or none if it doesn't. Members are still found at a host
+/// that no longer resolves by the hostname their services report.
+async fn resolve(host: &str) -> Vec<IpAddr> {
+    if let Ok(ip) = host.parse() {
+        return vec![ip];
+    }
+    match tokio_net::lookup_host((host, 0)).await {
+        Ok(addrs) => addrs.map(|addr| addr.ip()).collect(),
+        Err(e) => {
+            debug!("Unable to resolve {}, {}", host, e);
+            Vec::new()
+        }
     }
 }
 
@@ -368,15 +473,21 @@ impl Future for SrvHandler {
                             let fut =
                                 Self::command_from_message_gsr_msr(&msg, self.ctl_sender.clone());
                             tokio::pin!(fut);
-                            let cmd = match futures::ready!(fut.poll_unpin(cx)) {
+                            let mut cmd = match futures::ready!(fut.poll_unpin(cx)) {
                                 Ok(cmd) => cmd,
                                 Err(_) => {
                                     break;
                                 }
                             };
+                            cmd.req.set_client(self.client);
                             if let Err(err) = futures::ready!(self.mgr_sender.poll_ready(cx)) {
                                 return Poll::Ready(Err(HandlerError::from(err)));
                             }
+                            self.txn = msg.transaction();
+                            self.deadline =
+                                cmd.req
+                                   .deadline()
+                                   .map(|deadline| Box::pin(time::sleep_until(deadline.into())));
                             match self.mgr_sender.start_send(cmd) {
                                 Ok(()) => {
                                     self.state = SrvHandlerState::Sending;
@@ -403,7 +514,21 @@ impl Future for SrvHandler {
                     }
                 }
                 SrvHandlerState::Sending => {
-                    match futures::ready!(self.ctl_receiver.poll_next_unpin(cx)) {
+                    let next = match self.ctl_receiver.poll_next_unpin(cx) {
+                        Poll::Ready(next) => next,
+                        Poll::Pending => {
+                            futures::ready!(self.poll_deadline(cx));
+                            warn!("Request deadline exceeded, {:?}", self.txn);
+                            match self.deadline_exceeded() {
+                                Some(reply) => Some(reply),
+                                None => {
+                                    self.state = SrvHandlerState::Sent;
+                                    continue;
+                                }
+                            }
+                        }
+                    };
+                    match next {
                         Some(msg) => {
                             trace!("MgrSender -> SrvHandler, {:?}", msg);
                             if msg.is_complete() {
@@ -461,12 +586,12 @@ struct SrvState {
 }
 
 pub(crate) struct CtlGatewayServer {
-    pub(crate) listen_addr:         SocketAddr,
-    pub(crate) secret_key:          String,
-    pub(crate) mgr_sender:          MgrSender,
-    pub(crate) server_certificates: Option<Vec<Certificate>>,
-    pub(crate) server_key:          Option<PrivateKey>,
-    pub(crate) client_certificates: Option<RootCertStore>,
+    pub(crate) listen_addr: SocketAddr,
+    pub(crate) secret_key:  String,
+    pub(crate) mgr_sender:  MgrSender,
+    /// The TLS configuration that connections are upgraded with, if TLS is enabled. Its
+    /// certificates are reloaded when their files change.
+    pub(crate) tls_config:  Option<Arc<TlsServerConfig>>,
 }
 
 impl CtlGatewayServer {
@@ -478,20 +603,19 @@ impl CtlGatewayServer {
         let Self { listen_addr,
                    secret_key,
                    mgr_sender,
-                   server_certificates,
-                   server_key,
-                   client_certificates, } = self;
+                   tls_config: maybe_tls_config, } = self;
 
         let state = SrvState { secret_key,
                                mgr_sender };
         let state = Arc::new(Mutex::new(state));
         let listener =
-            TcpListener::bind(&listen_addr).await
-                                           .expect("Could not bind ctl gateway listen address!");
+            systemd::gateway_listener(listen_addr).and_then(TcpListener::from_std)
+                                                  .expect("Could not bind ctl gateway listen \
+                                                           address!");
 
-        let maybe_tls_config = Self::maybe_tls_config(server_certificates,
-                                                      server_key,
-                                                      client_certificates).map(Arc::new);
+        if maybe_tls_config.is_some() {
+            debug!("Upgrading ctl-gateway to TLS");
+        }
         loop {
             let tcp_stream = listener.accept().await;
             match tcp_stream {
@@ -531,7 +655,8 @@ impl CtlGatewayServer {
                     };
 
                     let srv_codec = SrvCodec::new().framed(tcp_stream);
-                    let client = Client { state: Arc::clone(&state), };
+                    let client = Client { state: Arc::clone(&state),
+                                          addr };
                     tokio::spawn(async move {
                         let res = client.serve(srv_codec).await;
                         debug!("DISCONNECTED from {:?} with result {:?}", addr, res);
@@ -541,24 +666,4 @@ impl CtlGatewayServer {
             }
         }
     }
-
-    fn maybe_tls_config(server_certificates: Option<Vec<Certificate>>,
-                        server_key: Option<PrivateKey>,
-                        client_certificates: Option<RootCertStore>)
-                        -> Option<TlsServerConfig> {
-        if let Some(server_key) = server_key {
-            let mut tls_config = if let Some(client_certificates) = client_certificates {
-                debug!("Upgrading ctl-gateway to TLS with client authentication");
-                TlsServerConfig::new(AllowAnyAuthenticatedClient::new(client_certificates))
-            } else {
-                debug!("Upgrading ctl-gateway to TLS");
-                TlsServerConfig::new(NoClientAuth::new())
-            };
-            tls_config.set_single_cert(server_certificates.unwrap_or_default(), server_key)
-                      .expect("Could not set certificate for ctl gateway!");
-            Some(tls_config)
-        } else {
-            None
-        }
-    }
 }
@@ -24,7 +24,9 @@ pub type Result<T> = result::Result<T, Error>;
 /// All the kinds of errors we produce.
 #[derive(Debug)]
 pub enum Error {
+    Acme(String),
     Departed,
+    DepartedSelf(String),
     BadAddress(String),
     BadDataFile(PathBuf, io::Error),
     BadDataPath(PathBuf, io::Error),
@@ -36,30 +38,42 @@ pub enum Error {
     LockPoisoned,
     TestBootFail,
     ButterflyError(habitat_butterfly::error::Error),
+    ConsulPeers(String),
     CtlSecretIo(PathBuf, io::Error),
     APIClient(habitat_api_client::Error),
+    Ec2Peers(String),
+    MdnsPeers(String),
     EnvJoinPathsError(env::JoinPathsError),
     EnvVarError(env::VarError),
     ExecCommandNotFound(String),
     EventError(event::Error),
     FileNotFound(String),
     FileWatcherFileIsRoot,
+    FleetManifestInvalid(String),
+    FleetManifestParse(toml::de::Error),
+    FleetManifestUntrusted(String),
     GroupNotFound(String),
     Hab(hab::error::Error),
     HabitatCommon(habitat_common::Error),
     HabitatCore(habitat_core::Error),
+    InsecureCtlGrpcListen(net::SocketAddr),
     InvalidBinds(Vec<String>),
     InvalidCertFile(PathBuf),
+    InvalidEnvironmentFile(PathBuf, String),
     InvalidHealthCheckResult(i32),
     InvalidKeyFile(PathBuf),
     InvalidKeyParameter(String),
     InvalidPidFile,
+    InvalidTombstone(PathBuf, toml::de::Error),
     InvalidTopology(String),
     InvalidUpdateStrategy(String),
     Io(io::Error),
+    KeyCertificateMismatch(PathBuf),
+    KubernetesPeers(String),
     TaskJoin(JoinError),
     Launcher(habitat_launcher_client::Error),
     LockFileError(crate::lock_file::Error),
+    MemberIdSource(String, String),
     MissingRequiredBind(Vec<String>),
     MissingRequiredIdent,
     NameLookup(io::Error),
@@ -68,6 +82,7 @@ pub enum Error {
     NoActiveMembers(habitat_core::service::ServiceGroup),
     NoLauncher,
     NoSuchBind(String),
+    NotDeparted,
     NotifyCreateError(notify::Error),
     NotifyError(notify::Error),
     NulError(ffi::NulError),
@@ -77,18 +92,25 @@ pub enum Error {
     Permissions(String),
     RecvError(mpsc::RecvError),
     RecvTimeoutError(mpsc::RecvTimeoutError),
+    RejoinUnsafe(String),
     ServiceDeserializationError(serde_json::Error),
     ServiceNotLoaded(package::PackageIdent),
     ServiceSerializationError(serde_json::Error),
     ServiceSpecFileIO(PathBuf, io::Error),
+    ServiceSpecInvalid(String),
     ServiceSpecParse(toml::de::Error),
     ServiceSpecRender(toml::ser::Error),
+    ServiceSpecVersion(u32),
     SignalFailed,
     SpecWatcherNotCreated,
     SpecDirNotFound(String),
     SpecWatcherGlob(glob::PatternError),
+    SrvLookup(String, String),
+    StateDirectoryInUse(PathBuf),
+    StateDirectoryProblems(usize),
     StrFromUtf8Error(str::Utf8Error),
     StringFromUtf8Error(string::FromUtf8Error),
+    TemplateWatcherNotCreated,
     TLSError(rustls::TLSError),
     TomlEncode(toml::ser::Error),
     TryRecvError(mpsc::TryRecvError),
@@ -113,6 +135,7 @@ impl fmt::Display for Error {
         let content = match self {
             Error::APIClient(ref err) => err.to_string(),
             Error::BadAddress(ref err) => format!("Unable to bind to address {}.", err),
+            Error::Acme(ref e) => format!("ACME request failed, {}", e),
             Error::Departed => "This Supervisor has been manually departed.\n\nFor the safety of \
                                 the system, this Supervisor cannot be started (if we did, we \
                                 would risk the services on this machine behaving badly without \
@@ -123,6 +146,12 @@ impl fmt::Display for Error {
                                 If you are in doubt, it is better to consider the services \
                                 managed by this Supervisor as unsafe to run."
                                                                              .to_string(),
+            Error::DepartedSelf(ref member_id) => {
+                format!("This Supervisor departed the ring as member {} with `hab sup depart \
+                         --self`, and cannot be started as that member again. Run `hab sup \
+                         rejoin` to check its state and have it join the ring as a new member.",
+                        member_id)
+            }
             Error::BadDataFile(ref path, ref err) => {
                 format!("Unable to read or write to data file, {}, {}",
                         path.display(),
@@ -167,26 +196,58 @@ impl fmt::Display for Error {
             Error::Hab(ref err) => err.to_string(),
             Error::HabitatCommon(ref err) => err.to_string(),
             Error::HabitatCore(ref err) => err.to_string(),
+            Error::ConsulPeers(ref e) => format!("Unable to discover peers from Consul, {}", e),
+            Error::Ec2Peers(ref e) => format!("Unable to discover peers from EC2, {}", e),
+            Error::MdnsPeers(ref e) => format!("Unable to discover peers over mDNS, {}", e),
             Error::EnvJoinPathsError(ref err) => err.to_string(),
             Error::EnvVarError(ref err) => err.to_string(),
             Error::FileNotFound(ref e) => format!("File not found at: {}", e),
             Error::FileWatcherFileIsRoot => "Watched file is root".to_string(),
+            Error::FleetManifestInvalid(ref e) => format!("Invalid fleet manifest: {}", e),
+            Error::FleetManifestParse(ref e) => format!("Unable to parse fleet manifest, {}", e),
+            Error::FleetManifestUntrusted(ref signer) => {
+                format!("Fleet manifest is signed by {}, which is not a key of the trusted origin",
+                        signer)
+            }
             Error::GroupNotFound(ref e) => format!("No GID for group '{}' could be found", e),
             Error::InvalidBinds(ref e) => format!("Invalid bind(s), {}", e.join(", ")),
             Error::InvalidCertFile(ref path) => format!("Invalid cert file: {}", path.display()),
+            Error::InvalidEnvironmentFile(ref path, ref e) => {
+                format!("Invalid environment file {}, {}", path.display(), e)
+            }
             Error::InvalidHealthCheckResult(code) => {
                 format!("Invalid health check result: {}", code)
             }
+            Error::InsecureCtlGrpcListen(ref addr) => {
+                format!("Refusing to serve the control gateway over gRPC on {} without TLS. Only \
+                         loopback addresses may be used without --ctl-server-key.",
+                        addr)
+            }
             Error::InvalidKeyFile(ref path) => format!("Invalid key file: {}", path.display()),
             Error::InvalidKeyParameter(ref e) => {
                 format!("Invalid parameter for key generation: {:?}", e)
             }
             Error::InvalidPidFile => "Invalid child process PID file".to_string(),
+            Error::InvalidTombstone(ref path, ref e) => {
+                format!("Unable to read the record of the Supervisor's departure, {}, {}",
+                        path.display(),
+                        e)
+            }
             Error::InvalidTopology(ref t) => format!("Invalid topology: {}", t),
             Error::InvalidUpdateStrategy(ref s) => format!("Invalid update strategy: {}", s),
             Error::Io(ref err) => err.to_string(),
+            Error::KeyCertificateMismatch(ref path) => {
+                format!("The private key {} does not belong to the TLS certificate",
+                        path.display())
+            }
+            Error::KubernetesPeers(ref e) => {
+                format!("Unable to discover peers from Kubernetes, {}", e)
+            }
             Error::TaskJoin(ref err) => err.to_string(),
             Error::Launcher(ref err) => err.to_string(),
+            Error::MemberIdSource(ref source, ref e) => {
+                format!("Unable to derive the member ID from {}, {}", source, e)
+            }
             Error::MissingRequiredBind(ref e) => {
                 format!("Missing required bind(s), {}", e.join(", "))
             }
@@ -201,6 +262,11 @@ impl fmt::Display for Error {
             Error::NoActiveMembers(ref g) => format!("No active members in service group {}", g),
             Error::NoLauncher => "Supervisor must be run from `hab-launch`".to_string(),
             Error::NoSuchBind(ref b) => format!("No such bind: {}", b),
+            Error::NotDeparted => {
+                "This Supervisor has not departed the ring with `hab sup depart --self`, so there \
+                 is nothing to rejoin."
+                                       .to_string()
+            }
             Error::NotifyCreateError(ref e) => format!("Notify create error: {}", e),
             Error::NotifyError(ref e) => format!("Notify error: {}", e),
             Error::NulError(ref e) => e.to_string(),
@@ -215,6 +281,11 @@ impl fmt::Display for Error {
             Error::PackageNotRunnable(ref pkg) => format!("Package is not runnable: {}", pkg),
             Error::RecvError(ref err) => err.to_string(),
             Error::RecvTimeoutError(ref err) => err.to_string(),
+            Error::RejoinUnsafe(ref reason) => {
+                format!("Not rejoining the ring, {}. Resolve this before running `hab sup rejoin` \
+                         again.",
+                        reason)
+            }
             Error::ServiceDeserializationError(ref e) => {
                 format!("Can't deserialize service status: {}", e)
             }
@@ -227,12 +298,20 @@ impl fmt::Display for Error {
                         path.display(),
                         err)
             }
+            Error::ServiceSpecInvalid(ref err) => format!("Invalid service spec, {}", err),
             Error::ServiceSpecParse(ref err) => {
                 format!("Unable to parse contents of service spec file, {}", err)
             }
             Error::ServiceSpecRender(ref err) => {
                 format!("Service spec could not be rendered successfully: {}", err)
             }
+            Error::ServiceSpecVersion(version) => {
+                format!("Service spec version {} was written by a newer Supervisor. This \
+                         Supervisor supports versions 1 to {}; upgrade it, or load the service \
+                         again to rewrite the spec file",
+                        version,
+                        crate::manager::service::spec::SPEC_VERSION)
+            }
             Error::SignalFailed => "Failed to send a signal to the child process".to_string(),
             Error::SpecWatcherNotCreated => "Failed to create a SpecWatcher".to_string(),
             Error::SpecDirNotFound(ref path) => {
@@ -240,8 +319,23 @@ impl fmt::Display for Error {
                         path)
             }
             Error::SpecWatcherGlob(ref e) => e.to_string(),
+            Error::SrvLookup(ref name, ref e) => {
+                format!("Unable to resolve the peers of the SRV record {}, {}",
+                        name, e)
+            }
+            Error::StateDirectoryInUse(ref path) => {
+                format!("The Supervisor state directory {} is in use by a running Supervisor. \
+                         Stop it with `hab sup term` before checking or changing the directory.",
+                        path.display())
+            }
+            Error::StateDirectoryProblems(count) => {
+                format!("Found {} problem(s) in the Supervisor state directory. Run `hab sup fsck \
+                         --repair` to repair them.",
+                        count)
+            }
             Error::StrFromUtf8Error(ref e) => e.to_string(),
             Error::StringFromUtf8Error(ref e) => e.to_string(),
+            Error::TemplateWatcherNotCreated => "Failed to create a TemplateWatcher".to_string(),
             Error::TLSError(ref e) => e.to_string(),
             Error::TomlEncode(ref e) => format!("Failed to encode TOML: {}", e),
             Error::TryRecvError(ref err) => err.to_string(),
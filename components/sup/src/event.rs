@@ -11,33 +11,46 @@
 //!
 //! [1]:https://github.com/nats-io/nats-server
 
+#[cfg(test)]
+mod conformance;
 mod error;
 mod nats_message_stream;
 mod types;
 
+pub use self::types::PackageChangeInitiator;
 pub(crate) use self::types::ServiceMetadata;
-use self::types::{EventMessage,
+use self::types::{ClockSkewEvent,
+                  EventMessage,
                   EventMetadata,
                   HealthCheckEvent,
+                  PackageInstalledEvent,
+                  PackageSource,
+                  PackageUninstalledEvent,
                   ServiceStartedEvent,
                   ServiceStoppedEvent,
                   ServiceUpdateStartedEvent};
-use crate::manager::{service::{HealthCheckHookStatus,
-                               HealthCheckResult,
-                               ProcessOutput,
-                               Service,
-                               StandardStreams},
-                     sys::Sys};
+use crate::{extension,
+            manager::{service::{HealthCheckHookStatus,
+                                HealthCheckResult,
+                                ProcessOutput,
+                                Service,
+                                StandardStreams},
+                      sys::Sys}};
 pub use error::{Error,
                 Result};
-use habitat_common::types::{EventStreamConnectMethod,
-                            EventStreamMetadata,
-                            EventStreamServerCertificate,
-                            EventStreamToken};
+use habitat_butterfly::server::ClockSkew;
+use habitat_common::{command::package::install::InstallSource,
+                     redaction,
+                     types::{EventStreamConnectMethod,
+                             EventStreamMetadata,
+                             EventStreamServerCertificate,
+                             EventStreamToken}};
 use habitat_core::{package::ident::PackageIdent,
-                   service::HealthCheckInterval};
+                   service::HealthCheckInterval,
+                   ChannelIdent};
 use nats_message_stream::{NatsMessage,
                           NatsMessageStream};
+use parking_lot::RwLock;
 use prost_types::Duration as ProstDuration;
 use rants::{Address,
             Subject};
@@ -58,11 +71,17 @@ lazy_static! {
         "habitat.event.service_update_started".parse().expect("valid NATS subject");
     static ref HEALTHCHECK_SUBJECT: Subject =
         "habitat.event.healthcheck".parse().expect("valid NATS subject");
+    static ref PACKAGE_INSTALLED_SUBJECT: Subject =
+        "habitat.event.package_installed".parse().expect("valid NATS subject");
+    static ref PACKAGE_UNINSTALLED_SUBJECT: Subject =
+        "habitat.event.package_uninstalled".parse().expect("valid NATS subject");
+    static ref CLOCK_SKEW_SUBJECT: Subject =
+        "habitat.event.clock_skew".parse().expect("valid NATS subject");
 
     /// Reference to the event stream.
     static ref NATS_MESSAGE_STREAM: Storage<NatsMessageStream> = Storage::new();
     /// Core information that is shared between all events.
-    static ref EVENT_CORE: Storage<EventCore> = Storage::new();
+    static ref EVENT_CORE: Storage<RwLock<EventCore>> = Storage::new();
 }
 
 /// Starts a new task for sending events to a NATS Streaming
@@ -77,11 +96,33 @@ pub async fn init(sys: &Sys, fqdn: String, config: EventStreamConfig) -> Result<
         let event_core = EventCore::new(&supervisor_id, ip_address, &fqdn, &config);
         let stream = NatsMessageStream::new(&supervisor_id, config).await?;
         NATS_MESSAGE_STREAM.set(stream);
-        EVENT_CORE.set(event_core);
+        EVENT_CORE.set(RwLock::new(event_core));
     }
     Ok(())
 }
 
+/// Replace the application, environment, site, and metadata attached to all subsequent events.
+/// The application and environment are only replaced when they are given.
+///
+/// The connection to the event stream is left untouched. If the event stream has not been
+/// initialized, this function is a no-op.
+pub fn update_metadata(application: Option<String>,
+                       environment: Option<String>,
+                       site: Option<String>,
+                       meta: EventStreamMetadata) {
+    if let Some(event_core) = EVENT_CORE.try_get() {
+        let mut event_core = event_core.write();
+        if let Some(application) = application {
+            event_core.application = application;
+        }
+        if let Some(environment) = environment {
+            event_core.environment = environment;
+        }
+        event_core.site = site;
+        event_core.meta = meta;
+    }
+}
+
 /// Captures all event stream-related configuration options that would
 /// be passed in by a user
 // TODO (DM): The fields of this struct are only public for testing. We should refactor the crate
@@ -100,6 +141,9 @@ pub struct EventStreamConfig {
 
 /// Send an event for the start of a Service.
 pub fn service_started(service: &Service) {
+    let service_group = service.service_group.to_string();
+    let pkg = service.pkg.ident.to_string();
+    extension::send(extension::Event::ServiceStarted { service_group, pkg });
     if initialized() {
         publish(&SERVICE_STARTED_SUBJECT,
                 ServiceStartedEvent { service_metadata: Some(service.to_service_metadata()),
@@ -109,6 +153,9 @@ pub fn service_started(service: &Service) {
 
 /// Send an event for the stop of a Service.
 pub fn service_stopped(service: &Service) {
+    let service_group = service.service_group.to_string();
+    let pkg = service.pkg.ident.to_string();
+    extension::send(extension::Event::ServiceStopped { service_group, pkg });
     if initialized() {
         publish(&SERVICE_STOPPED_SUBJECT,
                 ServiceStoppedEvent { service_metadata: Some(service.to_service_metadata()),
@@ -118,6 +165,12 @@ pub fn service_stopped(service: &Service) {
 
 /// Send an event at the start of a Service update.
 pub fn service_update_started(service: &Service, update: &PackageIdent) {
+    let service_group = service.service_group.to_string();
+    let pkg = service.pkg.ident.to_string();
+    let update = update.to_string();
+    extension::send(extension::Event::ServiceUpdateStarted { service_group,
+                                                             pkg,
+                                                             update });
     if initialized() {
         publish(&SERVICE_UPDATE_STARTED_SUBJECT,
                 ServiceUpdateStartedEvent { event_metadata:       None,
@@ -134,6 +187,11 @@ pub fn health_check(metadata: ServiceMetadata,
                     health_check_result: HealthCheckResult,
                     health_check_hook_status: HealthCheckHookStatus,
                     health_check_interval: HealthCheckInterval) {
+    let service_group = metadata.service_group.clone();
+    let pkg = metadata.package_ident.clone();
+    extension::send(extension::Event::HealthCheck { service_group,
+                                                    pkg,
+                                                    result: health_check_result });
     if initialized() {
         let health_check_result: types::HealthCheckResult = health_check_result.into();
         let maybe_duration = health_check_hook_status.maybe_duration();
@@ -143,6 +201,8 @@ pub fn health_check(metadata: ServiceMetadata,
         let StandardStreams { stdout, stderr } =
             maybe_process_output.map(ProcessOutput::standard_streams)
                                 .unwrap_or_default();
+        let stdout = stdout.map(|stdout| redaction::redact_text(&stdout).into_owned());
+        let stderr = stderr.map(|stderr| redaction::redact_text(&stderr).into_owned());
 
         let prost_interval = ProstDuration::from(Duration::from(health_check_interval));
 
@@ -158,6 +218,67 @@ pub fn health_check(metadata: ServiceMetadata,
     }
 }
 
+/// Send an event for a package the Supervisor installed from `install_source`, along with its
+/// `dependencies`. `client` is the control gateway client it was installed for, if any.
+pub fn package_installed(ident: &PackageIdent,
+                         dependencies: &[PackageIdent],
+                         install_source: &InstallSource,
+                         bldr_url: &str,
+                         channel: &ChannelIdent,
+                         initiated_by: PackageChangeInitiator,
+                         client: Option<SocketAddr>) {
+    if initialized() {
+        let archive_path = match install_source {
+            InstallSource::Archive(_) => install_source.to_string(),
+            InstallSource::Ident(..) => String::new(),
+        };
+        let source = PackageSource { builder_url: bldr_url.to_string(),
+                                     channel:     channel.to_string(),
+                                     archive:     archive_path, };
+        publish(&PACKAGE_INSTALLED_SUBJECT,
+                PackageInstalledEvent { event_metadata: None,
+                                        package_ident:  ident.to_string(),
+                                        source:         Some(source),
+                                        initiated_by:   initiated_by.into(),
+                                        dependencies:   idents_to_strings(dependencies),
+                                        client:         client.map(|client| client.to_string())
+                                                              .unwrap_or_default(), });
+    }
+}
+
+/// Send an event for a package the Supervisor uninstalled, along with those of its
+/// `dependencies` no other package depended on.
+pub fn package_uninstalled(ident: &PackageIdent,
+                           dependencies: &[PackageIdent],
+                           initiated_by: PackageChangeInitiator) {
+    if initialized() {
+        publish(&PACKAGE_UNINSTALLED_SUBJECT,
+                PackageUninstalledEvent { event_metadata: None,
+                                          package_ident:  ident.to_string(),
+                                          initiated_by:   initiated_by.into(),
+                                          dependencies:   idents_to_strings(dependencies), });
+    }
+}
+
+fn idents_to_strings(idents: &[PackageIdent]) -> Vec<String> {
+    idents.iter().map(ToString::to_string).collect()
+}
+
+/// Send an event for the clocks of the ring drifting further apart than the threshold, or coming
+/// back within it.
+pub fn clock_skew(skew: &ClockSkew) {
+    if initialized() {
+        let (furthest_member_id, furthest_offset_ms) = skew.furthest().unwrap_or_default();
+        publish(&CLOCK_SKEW_SUBJECT,
+                ClockSkewEvent { event_metadata: None,
+                                 spread_ms: skew.spread_ms,
+                                 threshold_ms: skew.threshold_ms,
+                                 exceeds_threshold: skew.exceeds_threshold,
+                                 furthest_member_id: furthest_member_id.to_string(),
+                                 furthest_offset_ms });
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////
 
 /// A collection of data that will be present in all events. Rather
@@ -223,7 +344,7 @@ fn publish(subject: &'static Subject, mut event: impl EventMessage) {
         debug!("Publishing to event stream: event {:?} ", event);
         event.event_metadata(EventMetadata { occurred_at:
                                                  Some(std::time::SystemTime::now().into()),
-                                             ..EVENT_CORE.get().to_event_metadata() });
+                                             ..EVENT_CORE.get().read().to_event_metadata() });
 
         let packet = NatsMessage::new(subject, event.to_bytes());
         stream.send(packet);
@@ -249,13 +370,13 @@ mod tests {
     async fn health_check_event() {
         let (tx, rx) = futures_mpsc::unbounded();
         NATS_MESSAGE_STREAM.set(NatsMessageStream(tx));
-        EVENT_CORE.set(EventCore { supervisor_id: String::from("supervisor_id"),
-                                   ip_address:    "127.0.0.1:8080".parse().unwrap(),
-                                   fqdn:          String::from("fqdn"),
-                                   application:   String::from("application"),
-                                   environment:   String::from("environment"),
-                                   site:          None,
-                                   meta:          EventStreamMetadata::default(), });
+        EVENT_CORE.set(RwLock::new(EventCore { supervisor_id: String::from("supervisor_id"),
+                                               ip_address:    "127.0.0.1:8080".parse().unwrap(),
+                                               fqdn:          String::from("fqdn"),
+                                               application:   String::from("application"),
+                                               environment:   String::from("environment"),
+                                               site:          None,
+                                               meta:          EventStreamMetadata::default(), }));
         health_check(ServiceMetadata::default(),
                      HealthCheckResult::Ok,
                      HealthCheckHookStatus::NoHook,
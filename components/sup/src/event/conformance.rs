@@ -0,0 +1,175 @@
+//! Golden event stream payloads for validating consumers against the actual wire format. See
+//! `test/fixtures/conformance/README.md`.
+//!
+//! Each event is built here from the Rust types and compared with the committed payload, which
+//! is in turn decoded and re-encoded. Run these tests with `HAB_UPDATE_CONFORMANCE_FIXTURES=1`
+//! to regenerate the payloads after adding a fixture.
+
+use super::{types::{EventMessage,
+                    EventMetadata,
+                    HealthCheckEvent,
+                    HealthCheckResult,
+                    PackageChangeInitiator,
+                    PackageInstalledEvent,
+                    PackageSource,
+                    PackageUninstalledEvent,
+                    ServiceMetadata,
+                    ServiceStartedEvent,
+                    ServiceStoppedEvent,
+                    ServiceUpdateStartedEvent,
+                    UpdateConfig,
+                    UpdateStrategy},
+            HEALTHCHECK_SUBJECT,
+            PACKAGE_INSTALLED_SUBJECT,
+            PACKAGE_UNINSTALLED_SUBJECT,
+            SERVICE_STARTED_SUBJECT,
+            SERVICE_STOPPED_SUBJECT,
+            SERVICE_UPDATE_STARTED_SUBJECT};
+use prost::Message;
+use prost_types::{Duration as ProstDuration,
+                  Timestamp};
+use rants::Subject;
+use serde_json::{json,
+                 Value as Json};
+use std::{env,
+          fmt::Debug,
+          fs,
+          path::PathBuf};
+
+const UPDATE_FIXTURES_ENVVAR: &str = "HAB_UPDATE_CONFORMANCE_FIXTURES";
+const MANIFEST_FILE: &str = "manifest.json";
+const EVENT_PACKAGE: &str = "chef.habitat.supervisor.event";
+
+fn fixture_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../test/fixtures/conformance/v1/event")
+}
+
+fn event_metadata() -> EventMetadata {
+    let meta = vec![(String::from("team"), String::from("platform"))];
+    EventMetadata { supervisor_id: String::from("0123456789abcdef0123456789abcdef"),
+                    ip_address:    String::from("10.0.0.5:9638"),
+                    occurred_at:   Some(Timestamp { seconds: 1_600_000_000,
+                                                    nanos:   0, }),
+                    application:   String::from("shop"),
+                    environment:   String::from("production"),
+                    meta:          meta.into_iter().collect(),
+                    fqdn:          String::from("web-1.example.com"),
+                    site:          String::from("us-east"), }
+}
+
+fn service_metadata() -> ServiceMetadata {
+    ServiceMetadata { package_ident: String::from("core/redis/6.2.5/20211016180000"),
+                      spec_ident:    String::from("core/redis"),
+                      service_group: String::from("redis.default"),
+                      update_config: Some(UpdateConfig { strategy: UpdateStrategy::Rolling.into(),
+                                                         channel:  String::from("stable"), }), }
+}
+
+/// Check an event against its committed payload, writing the payload first if fixtures are
+/// being regenerated. Returns the event's manifest entry.
+fn check<T>(name: &str, subject: &Subject, message: &str, event: T) -> Json
+    where T: EventMessage + Default + PartialEq + Debug
+{
+    let path = fixture_dir().join(format!("{}.bin", name));
+    if env::var_os(UPDATE_FIXTURES_ENVVAR).is_some() {
+        fs::create_dir_all(fixture_dir()).unwrap();
+        fs::write(&path, event.to_bytes()).unwrap();
+    }
+
+    let committed = fs::read(&path).unwrap();
+    assert_eq!(event.to_bytes(),
+               committed,
+               "{} is not encoded as the committed payload",
+               name);
+    let decoded = T::decode(&committed[..]).unwrap();
+    assert_eq!(decoded, event);
+    assert_eq!(decoded.to_bytes(),
+               committed,
+               "{} does not round-trip",
+               name);
+
+    json!({
+        "name": name,
+        "file": format!("{}.bin", name),
+        "subject": subject.to_string(),
+        "message": format!("{}.{}", EVENT_PACKAGE, message),
+    })
+}
+
+#[test]
+fn event_payloads_match_the_committed_fixtures() {
+    let common = || (Some(event_metadata()), Some(service_metadata()));
+
+    let (event_metadata, service_metadata) = common();
+    let service_started = ServiceStartedEvent { event_metadata,
+                                                service_metadata };
+    let (event_metadata, service_metadata) = common();
+    let service_stopped = ServiceStoppedEvent { event_metadata,
+                                                service_metadata };
+    let (event_metadata, service_metadata) = common();
+    let update_package_ident = String::from("core/redis/6.2.6/20211101000000");
+    let service_update_started = ServiceUpdateStartedEvent { event_metadata,
+                                                             service_metadata,
+                                                             update_package_ident };
+    let (event_metadata, service_metadata) = common();
+    let health_check = HealthCheckEvent { event_metadata,
+                                          service_metadata,
+                                          result: HealthCheckResult::Critical.into(),
+                                          execution: Some(ProstDuration { seconds: 10,
+                                                                          nanos:   0, }),
+                                          exit_status: Some(2),
+                                          stdout: Some(String::from("stdout")),
+                                          stderr: Some(String::from("stderr")),
+                                          interval: Some(ProstDuration { seconds: 30,
+                                                                         nanos:   0, }) };
+    let source = PackageSource { builder_url: String::from("https://bldr.habitat.sh"),
+                                 channel:     String::from("stable"),
+                                 archive:     String::new(), };
+    let glibc = String::from("core/glibc/2.34/20211014000000");
+    let package_installed =
+        PackageInstalledEvent { event_metadata: Some(event_metadata()),
+                                package_ident:  String::from("core/redis/6.2.6/20211101000000"),
+                                source:         Some(source),
+                                initiated_by:   PackageChangeInitiator::ControlGateway.into(),
+                                dependencies:   vec![glibc.clone()],
+                                client:         String::from("10.0.0.7:51234"), };
+    let package_uninstalled =
+        PackageUninstalledEvent { event_metadata: Some(event_metadata()),
+                                  package_ident:  String::from("core/redis/6.2.5/20211016180000"),
+                                  initiated_by:   PackageChangeInitiator::GarbageCollection.into(),
+                                  dependencies:   vec![glibc], };
+
+    let manifest = vec![check("service_started",
+                              &SERVICE_STARTED_SUBJECT,
+                              "ServiceStartedEvent",
+                              service_started),
+                        check("service_stopped",
+                              &SERVICE_STOPPED_SUBJECT,
+                              "ServiceStoppedEvent",
+                              service_stopped),
+                        check("service_update_started",
+                              &SERVICE_UPDATE_STARTED_SUBJECT,
+                              "ServiceUpdateStartedEvent",
+                              service_update_started),
+                        check("health_check",
+                              &HEALTHCHECK_SUBJECT,
+                              "HealthCheckEvent",
+                              health_check),
+                        check("package_installed",
+                              &PACKAGE_INSTALLED_SUBJECT,
+                              "PackageInstalledEvent",
+                              package_installed),
+                        check("package_uninstalled",
+                              &PACKAGE_UNINSTALLED_SUBJECT,
+                              "PackageUninstalledEvent",
+                              package_uninstalled),];
+
+    let manifest_path = fixture_dir().join(MANIFEST_FILE);
+    if env::var_os(UPDATE_FIXTURES_ENVVAR).is_some() {
+        let manifest = serde_json::to_string_pretty(&manifest).unwrap() + "\n";
+        fs::write(&manifest_path, manifest).unwrap();
+    }
+    let committed: Json =
+        serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+    assert_eq!(committed, Json::Array(manifest));
+}
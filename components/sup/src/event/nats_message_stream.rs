@@ -4,12 +4,19 @@ use crate::event::{Error,
 use futures::{channel::{mpsc as futures_mpsc,
                         mpsc::UnboundedSender},
               stream::StreamExt};
+use prometheus::IntCounter;
 use rants::{error::Error as RantsError,
             native_tls::TlsConnector,
             Client,
             Subject};
 use tokio::time;
 
+lazy_static! {
+    static ref EVENT_STREAM_SENT_BYTES: IntCounter =
+        register_int_counter!("hab_sup_event_stream_sent_bytes_total",
+                              "Total number of bytes of event payloads published").unwrap();
+}
+
 /// The subject and payload of a NATS message.
 #[derive(Debug)]
 pub struct NatsMessage {
@@ -78,15 +85,17 @@ impl NatsMessageStream {
         // Spawn a task to handle publishing received messages
         tokio::spawn(async move {
             while let Some(packet) = rx.next().await {
-                if let Err(e) = client.publish(packet.subject, packet.payload()).await {
+                match client.publish(packet.subject, packet.payload()).await {
+                    Ok(()) => EVENT_STREAM_SENT_BYTES.inc_by(packet.payload().len() as u64),
                     // We do not retry any messages. If we are not connected when the message is
                     // processed or there is an error in publishing the message, the message will
                     // never be sent.
-                    if let RantsError::NotConnected = e {
+                    Err(RantsError::NotConnected) => {
                         trace!("Failed to publish message to subject '{}' because the client is \
                                 not connected",
                                packet.subject);
-                    } else {
+                    }
+                    Err(e) => {
                         error!("Failed to publish message to subject '{}', err: {}",
                                packet.subject, e);
                     }
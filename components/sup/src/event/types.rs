@@ -105,3 +105,6 @@ event_msg_impl!(ServiceStartedEvent);
 event_msg_impl!(ServiceStoppedEvent);
 event_msg_impl!(ServiceUpdateStartedEvent);
 event_msg_impl!(HealthCheckEvent);
+event_msg_impl!(PackageInstalledEvent);
+event_msg_impl!(PackageUninstalledEvent);
+event_msg_impl!(ClockSkewEvent);
@@ -0,0 +1,400 @@
+//! Streams the Supervisor's lifecycle, census and health events to an extension process, and takes
+//! a limited set of commands back from it, so that site-specific logic can run beside the
+//! Supervisor without forking it.
+//!
+//! The extension is started with `hab sup run --extension <path>`, and is started again if it
+//! exits. Each event is written to its standard input as a line of JSON:
+//!
+//! ```json
+//! {"event":"health_check","service_group":"redis.default","pkg":"core/redis/4.0.14/20200421191514","result":"Critical"}
+//! ```
+//!
+//! Each line the extension writes to its standard output is a command:
+//!
+//! ```json
+//! {"command":"annotate","service_group":"redis.default","annotation":"draining"}
+//! {"command":"veto_update","service_group":"redis.default","reason":"change freeze"}
+//! {"command":"allow_update","service_group":"redis.default"}
+//! ```
+//!
+//! An annotation is shown with its service at the HTTP gateway's `/services`, until it is replaced
+//! or cleared with a `null` one. An update of a service does not start while it is vetoed; it
+//! starts once the extension allows it again. Annotations and vetoes are cleared when the
+//! extension exits. Events sent while the extension is not running, or while it is too far behind
+//! in reading them, are dropped.
+
+use crate::{census::CensusRing,
+            manager::service::HealthCheckResult};
+use habitat_common::outputln;
+use habitat_core::service::ServiceGroup;
+use parking_lot::RwLock;
+use state::Storage;
+use std::{collections::HashMap,
+          path::{Path,
+                 PathBuf},
+          process::Stdio,
+          sync::atomic::{AtomicBool,
+                         Ordering},
+          time::Duration};
+use tokio::{io::{AsyncBufReadExt,
+                 AsyncWriteExt,
+                 BufReader},
+            process::{Child,
+                      Command as ProcessCommand},
+            sync::mpsc::{self,
+                         error::TrySendError,
+                         Receiver,
+                         Sender},
+            time};
+
+static LOGKEY: &str = "EX";
+
+/// How long to wait before starting the extension again after it exits
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+/// How many events may wait for the extension to read them before further ones are dropped
+const EVENT_CAPACITY: usize = 1024;
+
+lazy_static! {
+    static ref EXTENSION: Storage<Extension> = Storage::new();
+}
+
+/// An event sent to the extension
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    ServiceStarted {
+        service_group: String,
+        pkg:           String,
+    },
+    ServiceStopped {
+        service_group: String,
+        pkg:           String,
+    },
+    ServiceUpdateStarted {
+        service_group: String,
+        pkg:           String,
+        update:        String,
+    },
+    HealthCheck {
+        service_group: String,
+        pkg:           String,
+        result:        HealthCheckResult,
+    },
+    CensusChanged {
+        groups: Vec<GroupCensus>,
+    },
+}
+
+/// The members of a service group in the census, by health
+#[derive(Debug, Serialize)]
+pub struct GroupCensus {
+    service_group: String,
+    alive:         u32,
+    suspect:       u32,
+    confirmed:     u32,
+    leader:        Option<String>,
+}
+
+/// A command sent by the extension
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Command {
+    Annotate {
+        service_group: String,
+        annotation:    Option<String>,
+    },
+    VetoUpdate {
+        service_group: String,
+        reason:        String,
+    },
+    AllowUpdate {
+        service_group: String,
+    },
+}
+
+struct Extension {
+    events:      Sender<Event>,
+    /// Whether events are being dropped since the last one that was sent
+    dropping:    AtomicBool,
+    /// The annotation of each service group the extension annotated
+    annotations: RwLock<HashMap<String, String>>,
+    /// Why the extension vetoed the updates of each service group whose updates are vetoed
+    vetoes:      RwLock<HashMap<String, String>>,
+}
+
+impl Extension {
+    fn new(events: Sender<Event>) -> Self {
+        Extension { events,
+                    dropping: AtomicBool::new(false),
+                    annotations: RwLock::default(),
+                    vetoes: RwLock::default() }
+    }
+
+    fn send(&self, event: Event) {
+        match self.events.try_send(event) {
+            Ok(()) => self.dropping.store(false, Ordering::Relaxed),
+            Err(TrySendError::Full(_)) => {
+                if !self.dropping.swap(true, Ordering::Relaxed) {
+                    outputln!("The extension is not keeping up with events, dropping them");
+                }
+            }
+            // The receiver lives as long as the Supervisor
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+
+    /// Forget the annotations and vetoes of an extension that exited, so that a service is not
+    /// held back by an extension that no longer runs
+    fn clear(&self) {
+        self.annotations.write().clear();
+        for (service_group, _) in self.vetoes.write().drain() {
+            outputln!("The extension exited, allowing updates of {}",
+                      service_group);
+        }
+    }
+
+    fn apply(&self, command: Command) {
+        match command {
+            Command::Annotate { service_group,
+                                annotation: Some(annotation), } => {
+                self.annotations.write().insert(service_group, annotation);
+            }
+            Command::Annotate { service_group,
+                                annotation: None, } => {
+                self.annotations.write().remove(&service_group);
+            }
+            Command::VetoUpdate { service_group,
+                                  reason, } => {
+                outputln!("The extension vetoed updates of {}, {}",
+                          service_group,
+                          reason);
+                self.vetoes.write().insert(service_group, reason);
+            }
+            Command::AllowUpdate { service_group } => {
+                if self.vetoes.write().remove(&service_group).is_some() {
+                    outputln!("The extension allowed updates of {}", service_group);
+                }
+            }
+        }
+    }
+}
+
+/// Start the extension at `path`, and keep it running for as long as the Supervisor runs
+pub fn init(path: PathBuf) {
+    if EXTENSION.try_get().is_none() {
+        let (events, events_rx) = mpsc::channel(EVENT_CAPACITY);
+        EXTENSION.set(Extension::new(events));
+        tokio::spawn(run(path, events_rx));
+    }
+}
+
+/// Send an event to the extension, if there is one
+pub fn send(event: Event) {
+    if let Some(extension) = EXTENSION.try_get() {
+        extension.send(event);
+    }
+}
+
+/// Send the census of every service group to the extension, if there is one
+pub fn census_changed(census_ring: &CensusRing) {
+    if EXTENSION.try_get().is_none() {
+        return;
+    }
+    let groups = census_ring.groups()
+                            .into_iter()
+                            .map(|group| {
+                                let mut census =
+                                    GroupCensus { service_group: group.service_group.to_string(),
+                                                  alive:         0,
+                                                  suspect:       0,
+                                                  confirmed:     0,
+                                                  leader:        group.leader_id.clone(), };
+                                for member in group.members() {
+                                    if member.alive() {
+                                        census.alive += 1;
+                                    } else if member.suspect() {
+                                        census.suspect += 1;
+                                    } else if member.confirmed() {
+                                        census.confirmed += 1;
+                                    }
+                                }
+                                census
+                            })
+                            .collect();
+    send(Event::CensusChanged { groups });
+}
+
+/// The extension's annotation of `service_group`, if it has one
+pub fn annotation(service_group: &ServiceGroup) -> Option<String> {
+    EXTENSION.try_get()?
+             .annotations
+             .read()
+             .get(&service_group.to_string())
+             .cloned()
+}
+
+/// Whether the extension vetoed the updates of `service_group`
+pub fn update_vetoed(service_group: &ServiceGroup) -> bool {
+    EXTENSION.try_get().map_or(false, |extension| {
+                           extension.vetoes
+                                    .read()
+                                    .contains_key(&service_group.to_string())
+                       })
+}
+
+/// Run the extension at `path`, starting it again whenever it exits, forever
+async fn run(path: PathBuf, mut events: Receiver<Event>) {
+    loop {
+        match start(&path) {
+            Ok(child) => {
+                outputln!("Started extension {}", path.display());
+                serve(child, &mut events).await;
+                if let Some(extension) = EXTENSION.try_get() {
+                    extension.clear();
+                }
+                outputln!("Extension {} exited, starting it again in {:?}",
+                          path.display(),
+                          RESTART_DELAY);
+            }
+            Err(e) => {
+                outputln!("Unable to start extension {}, trying again in {:?}, {}",
+                          path.display(),
+                          RESTART_DELAY,
+                          e);
+            }
+        }
+        time::sleep(RESTART_DELAY).await;
+        while events.try_recv().is_ok() {}
+    }
+}
+
+fn start(path: &Path) -> std::io::Result<Child> {
+    ProcessCommand::new(path).stdin(Stdio::piped())
+                             .stdout(Stdio::piped())
+                             .kill_on_drop(true)
+                             .spawn()
+}
+
+/// Write events to the extension and apply its commands until it exits or stops reading them
+async fn serve(mut child: Child, events: &mut Receiver<Event>) {
+    let mut stdin = child.stdin.take().expect("extension stdin is piped");
+    let stdout = child.stdout.take().expect("extension stdout is piped");
+    let mut lines = BufReader::new(stdout).lines();
+    loop {
+        tokio::select! {
+            Some(event) = events.recv() => {
+                let mut line = serde_json::to_vec(&event).expect("extension events serialize");
+                line.push(b'\n');
+                if let Err(e) = stdin.write_all(&line).await {
+                    debug!("Unable to write to the extension, {}", e);
+                    break;
+                }
+            }
+            line = lines.next_line() => {
+                match line {
+                    Ok(Some(line)) => apply(&line),
+                    Ok(None) => break,
+                    Err(e) => {
+                        debug!("Unable to read from the extension, {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    let _ = child.kill().await;
+}
+
+fn apply(line: &str) {
+    match serde_json::from_str(line) {
+        Ok(command) => {
+            if let Some(extension) = EXTENSION.try_get() {
+                extension.apply(command);
+            }
+        }
+        Err(e) => outputln!("Ignoring invalid extension command {:?}, {}", line, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn command(value: Value) -> Command { serde_json::from_value(value).unwrap() }
+
+    #[test]
+    fn events_are_lines_of_json() {
+        let event = Event::HealthCheck { service_group: String::from("redis.default"),
+                                         pkg:           String::from("core/redis/4.0.14"),
+                                         result:        HealthCheckResult::Critical, };
+        assert_eq!(serde_json::to_value(&event).unwrap(),
+                   json!({"event": "health_check",
+                          "service_group": "redis.default",
+                          "pkg": "core/redis/4.0.14",
+                          "result": "Critical"}));
+    }
+
+    #[test]
+    fn commands_annotate_services_and_veto_their_updates() {
+        let (events, _events_rx) = mpsc::channel(EVENT_CAPACITY);
+        let extension = Extension::new(events);
+
+        extension.apply(command(json!({"command": "annotate",
+                                       "service_group": "redis.default",
+                                       "annotation": "draining"})));
+        assert_eq!(extension.annotations.read()["redis.default"], "draining");
+        extension.apply(command(json!({"command": "annotate",
+                                       "service_group": "redis.default",
+                                       "annotation": null})));
+        assert!(extension.annotations.read().is_empty());
+
+        extension.apply(command(json!({"command": "veto_update",
+                                       "service_group": "redis.default",
+                                       "reason": "change freeze"})));
+        assert!(extension.vetoes.read().contains_key("redis.default"));
+        extension.apply(command(json!({"command": "allow_update",
+                                       "service_group": "redis.default"})));
+        assert!(extension.vetoes.read().is_empty());
+    }
+
+    #[test]
+    fn annotations_and_vetoes_are_cleared_when_the_extension_exits() {
+        let (events, _events_rx) = mpsc::channel(EVENT_CAPACITY);
+        let extension = Extension::new(events);
+        extension.apply(command(json!({"command": "annotate",
+                                       "service_group": "redis.default",
+                                       "annotation": "draining"})));
+        extension.apply(command(json!({"command": "veto_update",
+                                       "service_group": "redis.default",
+                                       "reason": "change freeze"})));
+
+        extension.clear();
+        assert!(extension.annotations.read().is_empty());
+        assert!(extension.vetoes.read().is_empty());
+    }
+
+    #[test]
+    fn events_are_dropped_while_the_extension_is_behind() {
+        let (events, mut events_rx) = mpsc::channel(1);
+        let extension = Extension::new(events);
+        let stopped = || {
+            Event::ServiceStopped { service_group: String::from("redis.default"),
+                                    pkg:           String::from("core/redis/4.0.14"), }
+        };
+
+        extension.send(stopped());
+        extension.send(stopped());
+        assert!(extension.dropping.load(Ordering::Relaxed));
+        assert!(events_rx.try_recv().is_ok());
+        assert!(events_rx.try_recv().is_err());
+        extension.send(stopped());
+        assert!(!extension.dropping.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn unknown_commands_are_invalid() {
+        let restart = json!({"command": "restart", "service_group": "redis.default"});
+        assert!(serde_json::from_value::<Command>(restart).is_err());
+    }
+}
@@ -1,6 +1,7 @@
-use crate::manager::{self,
-                     service::{HealthCheckHook,
-                               HealthCheckResult}};
+use crate::{manager::{self,
+                      service::{HealthCheckResult,
+                                UpdateHistory}},
+            systemd};
 use actix_rt::System;
 use actix_web::{dev::{Body,
                       Service,
@@ -19,12 +20,15 @@ use actix_web::{dev::{Body,
 use futures::future::{ok,
                       Either,
                       Future};
+use habitat_butterfly::server::{self as butterfly,
+                                ServerDebugProxy};
 use habitat_common::{self,
-                     templating::hooks,
+                     redaction,
                      types::HttpListenAddr,
                      FeatureFlag};
 use habitat_core::{crypto,
                    env as henv,
+                   fs,
                    service::ServiceGroup};
 use manager::sync::GatewayState;
 
@@ -39,8 +43,6 @@ use serde_json::{self,
                  Value as Json};
 use std::{self,
           cell::Cell,
-          fs::File,
-          io::Read,
           sync::{Arc,
                  Condvar,
                  Mutex},
@@ -98,6 +100,9 @@ impl Into<StatusCode> for HealthCheckResult {
 
 struct AppState {
     gateway_state:        Arc<GatewayState>,
+    /// The gossip server itself, read directly by /butterfly/debug so that it reports the ring as
+    /// it is at the time of the request
+    butterfly:            butterfly::Server,
     authentication_token: Option<String>,
     timer:                Cell<Option<HistogramTimer>>,
     feature_flags:        FeatureFlag,
@@ -105,10 +110,12 @@ struct AppState {
 
 impl AppState {
     fn new(gs: Arc<GatewayState>,
+           butterfly: butterfly::Server,
            authentication_token: GatewayAuthenticationToken,
            feature_flags: FeatureFlag)
            -> Self {
         AppState { gateway_state: gs,
+                   butterfly,
                    // We'll unwrap to the inner type, since the
                    // GatewayAuthenticationToken type has done its job by this point.
                    authentication_token: authentication_token.0,
@@ -208,6 +215,25 @@ fn redact_http_middleware<S>(req: ServiceRequest,
     }
 }
 
+/// Hide endpoints that expose the internals of the Supervisor unless the gateway requires
+/// authentication. `authentication_middleware` has already checked the token of the request by the
+/// time this runs.
+fn authenticated_only_middleware<S>(req: ServiceRequest,
+                                    srv: &S)
+                                    -> impl Future<Output = Result<ServiceResponse<Body>, Error>>
+    where S: Service<ServiceRequest, Response = ServiceResponse<Body>, Error = Error>
+{
+    if req.app_data::<Data<AppState>>()
+          .expect("app data")
+          .authentication_token
+          .is_some()
+    {
+        Either::Left(srv.call(req))
+    } else {
+        Either::Right(ok(req.into_response(HttpResponse::NotFound().finish())))
+    }
+}
+
 // End middleware
 
 #[derive(Debug, PartialEq, Eq)]
@@ -223,6 +249,7 @@ impl Server {
     pub fn run(listen_addr: HttpListenAddr,
                tls_config: Option<ServerConfig>,
                gateway_state: Arc<GatewayState>,
+               butterfly: butterfly::Server,
                authentication_token: GatewayAuthenticationToken,
                feature_flags: FeatureFlag,
                control: Arc<(Mutex<ServerStartup>, Condvar)>) {
@@ -242,6 +269,7 @@ impl Server {
             let mut server = HttpServer::new(move || {
                                  let app_state =
                                      Data::new(AppState::new(gateway_state.clone(),
+                                                             butterfly.clone(),
                                                              authentication_token.clone(),
                                                              feature_flags));
                                  App::new().app_data(app_state)
@@ -253,10 +281,12 @@ impl Server {
             server = server.disable_signals();
             debug!("http_gateway server configured");
 
-            let bind = match tls_config {
-                Some(c) => server.bind_rustls(listen_addr.to_string(), c),
-                None => server.bind(listen_addr.to_string()),
-            };
+            let bind = systemd::gateway_listener(*listen_addr).and_then(|listener| {
+                           match tls_config {
+                               Some(c) => server.listen_rustls(listener, c),
+                               None => server.listen(listener),
+                           }
+                       });
             debug!("http_gateway server port bound");
 
             *lock.lock().expect("Control mutex is poisoned") = match bind {
@@ -282,16 +312,21 @@ impl Server {
 
 fn services_routes() -> Scope {
     web::scope("/services").route("", web::get().to(services_gsr))
+                           .route("/graph", web::get().to(graph_gsr))
                            .route("/{svc}/{group}", web::get().to(service_without_org_gsr))
                            .route("/{svc}/{group}/config",
                                   web::get().to(config_without_org_gsr))
                            .route("/{svc}/{group}/health",
                                   web::get().to(health_without_org_gsr))
+                           .route("/{svc}/{group}/history",
+                                  web::get().to(history_without_org_gsr))
                            .route("/{svc}/{group}/{org}", web::get().to(service_with_org_gsr))
                            .route("/{svc}/{group}/{org}/config",
                                   web::get().to(config_with_org_gsr))
                            .route("/{svc}/{group}/{org}/health",
                                   web::get().to(health_with_org_gsr))
+                           .route("/{svc}/{group}/{org}/history",
+                                  web::get().to(history_with_org_gsr))
 }
 
 fn routes() -> Scope {
@@ -299,6 +334,10 @@ fn routes() -> Scope {
                    .service(services_routes())
                    .service(web::resource("/butterfly").route(web::get().to(butterfly_gsr))
                                                        .wrap_fn(redact_http_middleware))
+                   .service(web::resource("/butterfly/debug")
+                                .route(web::get().to(butterfly_debug_rsr_mlr))
+                                .wrap_fn(redact_http_middleware)
+                                .wrap_fn(authenticated_only_middleware))
                    .service(web::resource("/census").route(web::get().to(census_gsr))
                                                     .wrap_fn(redact_http_middleware))
                    .route("/metrics", web::get().to(metrics))
@@ -319,6 +358,21 @@ fn butterfly_gsr(state: Data<AppState>) -> HttpResponse {
     json_response(data)
 }
 
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
+/// * `MemberList::entries` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn butterfly_debug_rsr_mlr(state: Data<AppState>) -> HttpResponse {
+    let proxy = ServerDebugProxy::new(&state.butterfly);
+    match redaction::to_redacted_json(&proxy) {
+        Ok(data) => json_response(data),
+        Err(e) => {
+            error!("Unable to serialize the butterfly debug output: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 #[allow(clippy::needless_pass_by_value)]
@@ -335,6 +389,14 @@ fn services_gsr(state: Data<AppState>) -> HttpResponse {
     json_response(data)
 }
 
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn graph_gsr(state: Data<AppState>) -> HttpResponse {
+    let data = state.gateway_state.lock_gsr().graph_data().to_string();
+    json_response(data)
+}
+
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 // Honestly, this doesn't feel great, but it's the pattern builder-api uses, and at the
@@ -398,18 +460,14 @@ fn health_gsr(svc: String, group: String, org: Option<&str>, state: &AppState) -
     };
 
     if let Some(health_check) = state.gateway_state.lock_gsr().health_of(&service_group) {
-        let mut body = HealthCheckBody::default();
-        let stdout_path = hooks::stdout_log_path::<HealthCheckHook>(service_group.service());
-        let stderr_path = hooks::stderr_log_path::<HealthCheckHook>(service_group.service());
-        let http_status: StatusCode = health_check.into();
-
-        body.status = health_check.to_string();
-        if let Ok(mut file) = File::open(&stdout_path) {
-            let _ = file.read_to_string(&mut body.stdout);
-        }
-        if let Ok(mut file) = File::open(&stderr_path) {
-            let _ = file.read_to_string(&mut body.stderr);
-        }
+        let http_status: StatusCode = health_check.result.into();
+        let output = health_check.output.clone();
+        let redacted = |stream: Option<String>| {
+            redaction::redact_text(&stream.unwrap_or_default()).into_owned()
+        };
+        let body = HealthCheckBody { status: health_check.result.to_string(),
+                                     stdout: redacted(output.stdout),
+                                     stderr: redacted(output.stderr), };
 
         HttpResponse::build(http_status).json(&body)
     } else {
@@ -419,6 +477,43 @@ fn health_gsr(svc: String, group: String, org: Option<&str>, state: &AppState) -
     }
 }
 
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn history_with_org_gsr(path: Path<(String, String, String)>,
+                        state: Data<AppState>)
+                        -> HttpResponse {
+    let (svc, group, org) = path.into_inner();
+    history_gsr(svc, group, Some(&org), &state)
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+#[allow(clippy::needless_pass_by_value)]
+fn history_without_org_gsr(path: Path<(String, String)>, state: Data<AppState>) -> HttpResponse {
+    let (svc, group) = path.into_inner();
+    history_gsr(svc, group, None, &state)
+}
+
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+fn history_gsr(svc: String, group: String, org: Option<&str>, state: &AppState) -> HttpResponse {
+    let service_group = match ServiceGroup::new(svc, group, org) {
+        Ok(sg) => sg,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+
+    match service_from_services(&service_group,
+                                state.gateway_state.lock_gsr().services_data())
+    {
+        Some(_) => {
+            let path = fs::svc_update_history_file(service_group.service());
+            HttpResponse::Ok().json(UpdateHistory::read(&path))
+        }
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 #[allow(clippy::needless_pass_by_value)]
@@ -492,6 +587,7 @@ mod tests {
     use crate::test_helpers::*;
     use habitat_butterfly::{member::Member,
                             server::{Server,
+                                     ServerDebugProxy,
                                      ServerProxy,
                                      Suitability}};
     use std::{fs::File,
@@ -545,7 +641,7 @@ mod tests {
     }
 
     #[test]
-    fn butterfly_server_proxy_is_valid() {
+    fn butterfly_server_proxies_are_valid() {
         lazy_static! {
             static ref SWIM_PORT: Mutex<u16> = Mutex::new(6666);
             static ref GOSSIP_PORT: Mutex<u16> = Mutex::new(7777);
@@ -581,6 +677,7 @@ mod tests {
                         None,
                         None,
                         None,
+                        None,
                         std::sync::Arc::new(ZeroSuitability)).unwrap()
         }
 
@@ -588,6 +685,18 @@ mod tests {
         let proxy = ServerProxy::new(&server);
         let json = serde_json::to_string(&proxy).unwrap();
         assert_valid(&json, "http_gateway_butterfly_schema.json");
+
+        let debug_proxy = ServerDebugProxy::new(&server);
+        let json = serde_json::to_string(&debug_proxy).unwrap();
+        assert_valid(&json, "http_gateway_butterfly_debug_schema.json");
+    }
+
+    #[test]
+    fn trivial_butterfly_debug_failure() {
+        let failure = validate_string(r#"{"member_id": "narf", "member_list": {"members": []}}"#,
+                                      "http_gateway_butterfly_debug_schema.json");
+        assert!(!failure.is_valid(),
+                "Expected schema validation to fail, but it succeeded");
     }
 
     #[test]
@@ -602,6 +711,20 @@ mod tests {
                                             "http_gateway_services_schema.json");
     }
 
+    #[test]
+    fn sample_graph_file_is_valid() {
+        validate_sample_file_against_schema("sample-graph-output.json",
+                                            "http_gateway_graph_schema.json");
+    }
+
+    #[test]
+    fn trivial_graph_failure() {
+        let failure = validate_string(r#"{"nodes": [], "edges": [{"consumer": true}]}"#,
+                                      "http_gateway_graph_schema.json");
+        assert!(!failure.is_valid(),
+                "Expected schema validation to fail, but it succeeded");
+    }
+
     #[test]
     fn trivial_services_failure() {
         let failure = validate_string(r#"[{"lulz": true}]"#, "http_gateway_services_schema.json");
@@ -60,11 +60,13 @@ pub mod command;
 pub mod ctl_gateway;
 pub mod error;
 pub mod event;
+pub mod extension;
 pub mod http_gateway;
 pub mod lock_file;
 pub mod logger; // must be pub if used in the `hab-sup` binary
 pub mod manager;
 mod sys;
+mod systemd;
 #[cfg(test)]
 pub mod test_helpers;
 pub mod util;
@@ -293,6 +293,21 @@ impl Drop for LockFile {
 /// - the process identified by the PID is the Launcher.
 pub fn read_lock_file() -> Result<Pid> { read_lock_file_impl(lock_file_path()) }
 
+/// Whether a running Supervisor holds the lock file. Unlike `read_lock_file`,
+/// this does not depend on the contents of the file.
+pub fn supervisor_running() -> Result<bool> {
+    let file = match File::open(lock_file_path()) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(Error::CannotOpen(e)),
+    };
+    if file.try_lock_exclusive().is_err() {
+        return Ok(true);
+    }
+    file.unlock().map_err(Error::IOError)?;
+    Ok(false)
+}
+
 /// Implementation of main lockfile reading logic, separate from the specific
 /// file being read. Done to facilitate testing.
 fn read_lock_file_impl<P>(path: P) -> Result<Pid>
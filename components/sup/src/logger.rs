@@ -1,10 +1,15 @@
-use log::LevelFilter;
+use habitat_common::redaction;
+use log::{LevelFilter,
+          Record};
 use log4rs::{append::console::ConsoleAppender,
              config::{Appender,
                       Config,
                       Deserializers,
                       Root},
-             encode::pattern::PatternEncoder};
+             encode::{self,
+                      pattern::PatternEncoder,
+                      writer::simple::SimpleWriter,
+                      Encode}};
 use std::path::PathBuf;
 
 mod env_logger_compatibility;
@@ -69,7 +74,7 @@ pub fn init() {
 /// (at that point, though, it may be better to define the default in
 /// a YAML file and `include!` it here for readability)
 fn default_config() -> Config {
-    let stdout = ConsoleAppender::builder().encoder(Box::new(PatternEncoder::new(DEFAULT_PATTERN)))
+    let stdout = ConsoleAppender::builder().encoder(Box::new(RedactingEncoder::default()))
                                            .build();
     // Calling `expect()` is OK because we have full control over this
     // configuration. It can only fail if we create an inconsistent
@@ -80,6 +85,25 @@ fn default_config() -> Config {
                      .expect("Default log4rs configuration should always be valid")
 }
 
+/// Encodes records with `DEFAULT_PATTERN`, redacting sensitive values from them (see
+/// `habitat_common::redaction`). Logging configured by a file is not redacted.
+#[derive(Debug)]
+struct RedactingEncoder(PatternEncoder);
+
+impl Default for RedactingEncoder {
+    fn default() -> Self { RedactingEncoder(PatternEncoder::new(DEFAULT_PATTERN)) }
+}
+
+impl Encode for RedactingEncoder {
+    fn encode(&self, w: &mut dyn encode::Write, record: &Record) -> anyhow::Result<()> {
+        let mut encoded = SimpleWriter(Vec::new());
+        self.0.encode(&mut encoded, record)?;
+        let encoded = String::from_utf8_lossy(&encoded.0);
+        w.write_all(redaction::redact_text(&encoded).as_bytes())?;
+        Ok(())
+    }
+}
+
 fn configuration_file() -> PathBuf {
     habitat_sup_protocol::sup_root(None).join("config")
                                         .join("log.yml")
@@ -49,8 +49,7 @@ use log4rs::{append::console::ConsoleAppender,
              config::{Appender,
                       Config,
                       Logger,
-                      Root},
-             encode::pattern::PatternEncoder};
+                      Root}};
 use std::{collections::HashMap,
           str::FromStr};
 
@@ -89,8 +88,9 @@ impl Into<Config> for EnvLogConfig {
     /// Actually create a `log4rs` configuration. This is
     /// infallible because we'll always create something valid.
     fn into(self) -> Config {
-        let stdout = ConsoleAppender::builder().encoder(Box::new(PatternEncoder::new(super::DEFAULT_PATTERN)))
-                                               .build();
+        let stdout =
+            ConsoleAppender::builder().encoder(Box::new(super::RedactingEncoder::default()))
+                                      .build();
         let loggers = self.module_filters
                           .into_iter()
                           .map(|(module, filter)| Logger::builder().build(module, filter));
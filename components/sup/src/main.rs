@@ -12,21 +12,28 @@ use crate::sup::{cli::cli,
                  command,
                  error::{Error,
                          Result},
-                 event::EventStreamConfig,
+                 event::{EventStreamConfig,
+                         PackageChangeInitiator},
                  logger,
-                 manager::{Manager,
+                 manager::{AcmeConfig,
+                           ConsulPeerConfig,
+                           Manager,
                            ManagerConfig,
+                           PrefetchConfig,
+                           StateSnapshotConfig,
                            TLSConfig},
                  util};
-use configopt::ConfigOpt;
 use hab::cli::hab::{sup::SupRun,
                     svc};
+use habitat_butterfly::{server::timing::Timing,
+                        tls::RingTlsConfig};
 use habitat_common::{command::package::install::InstallSource,
                      liveliness_checker,
                      output::{self,
                               OutputFormat,
                               OutputVerbosity},
                      outputln,
+                     redaction,
                      types::GossipListenAddr,
                      ui::{self,
                           UI},
@@ -35,22 +42,23 @@ use habitat_core::{self,
                    crypto::{self,
                             keys::{KeyCache,
                                    RingKey}},
-                   os::signals,
-                   tls::rustls_wrapper::{CertificateChainCli,
-                                         PrivateKeyCli,
-                                         RootCertificateStoreCli}};
+                   os::signals};
 use habitat_launcher_client::{LauncherCli,
+                              LauncherFeature,
                               ERR_NO_RETRY_EXCODE,
                               OK_NO_RETRY_EXCODE};
 use habitat_sup_protocol::{self as sup_proto};
 use std::{convert::TryInto,
           env,
+          ffi::OsString,
           io,
           io::Write,
           net::{IpAddr,
                 Ipv4Addr},
+          path::Path,
           process,
-          str::{self}};
+          str::{self},
+          time::Duration};
 use sup::manager::ServiceRestartConfig;
 use tokio::{self,
             runtime::Builder as RuntimeBuilder};
@@ -151,12 +159,20 @@ async fn start_rsr_imlw_mlw_gsw_smw_rhw_msw(feature_flags: FeatureFlag) -> Resul
     };
     match app_matches.subcommand() {
         ("bash", Some(_)) => sub_bash().await,
+        ("export", Some(m)) => {
+            sub_export(m.value_of("format").unwrap_or_default(),
+                       m.value_of("OUTPUT").unwrap_or_default())
+        }
+        ("fsck", Some(m)) => sub_fsck(m.is_present("repair")),
+        ("rejoin", Some(_)) => sub_rejoin(),
+        ("render-context-schema", Some(_)) => sub_render_context_schema(),
         ("run", Some(_)) => {
             // TODO (DM): This is a little hacky. Essentially, for `hab sup run` we switch to using
             // structopt/configopt instead of querying clap `ArgMatches` directly. We skip the first
             // arg ("sup") to construct a `SupRun`. Eventually, when we switch to exclusivly using
             // structopt/configopt this will go away and everything will be much cleaner.
-            let sup_run = match SupRun::try_from_iter_with_configopt(env::args().skip(1)) {
+            let run_args: Vec<OsString> = env::args_os().skip(1).collect();
+            let sup_run = match SupRun::try_from_args_with_default_config(&run_args) {
                 Ok(sup) => sup,
                 Err(err) => {
                     if launcher.is_some() {
@@ -172,7 +188,7 @@ async fn start_rsr_imlw_mlw_gsw_smw_rhw_msw(feature_flags: FeatureFlag) -> Resul
                 }
             };
             let launcher = launcher.ok_or(Error::NoLauncher)?;
-            sub_run_rsr_imlw_mlw_gsw_smw_rhw_msw(sup_run, launcher, feature_flags).await
+            sub_run_rsr_imlw_mlw_gsw_smw_rhw_msw(sup_run, run_args, launcher, feature_flags).await
         }
         ("sh", Some(_)) => sub_sh().await,
         ("term", Some(_)) => sub_term(),
@@ -182,6 +198,22 @@ async fn start_rsr_imlw_mlw_gsw_smw_rhw_msw(feature_flags: FeatureFlag) -> Resul
 
 async fn sub_bash() -> Result<()> { command::shell::bash().await }
 
+fn sub_export(format: &str, output: &str) -> Result<()> {
+    let format = match format {
+        "ansible" => command::export::Format::Ansible,
+        _ => command::export::Format::Dsc,
+    };
+    command::export::export(format, Path::new(output))
+}
+
+fn sub_fsck(repair: bool) -> Result<()> { command::fsck::fsck(repair) }
+
+fn sub_rejoin() -> Result<()> { command::rejoin::rejoin() }
+
+fn sub_render_context_schema() -> Result<()> {
+    command::render_context_schema::render_context_schema()
+}
+
 /// # Locking (see locking.md)
 /// * `RumorStore::list` (read)
 /// * `MemberList::initial_members` (write)
@@ -191,26 +223,43 @@ async fn sub_bash() -> Result<()> { command::shell::bash().await }
 /// * `RumorHeat::inner` (write)
 /// * `ManagerServices::inner` (write)
 async fn sub_run_rsr_imlw_mlw_gsw_smw_rhw_msw(sup_run: SupRun,
+                                              run_args: Vec<OsString>,
                                               launcher: LauncherCli,
                                               feature_flags: FeatureFlag)
                                               -> Result<()> {
     set_supervisor_logging_options(&sup_run);
+    // Select the ring for everything which locates the Supervisor's state directory
+    if let Some(ring_name) = &sup_run.ring_name {
+        sup_proto::select_ring(ring_name);
+    }
+    let redaction_config = redaction::config_file(&sup_proto::sup_root(None));
+    redaction::init(&redaction_config)?;
+    // The Launcher relays the output of services, which it redacts the same way once it knows
+    // where the configuration is
+    if launcher.supports(LauncherFeature::Redaction) {
+        if let Err(e) = launcher.set_redaction(&redaction_config) {
+            warn!("Unable to pass the redaction configuration to the Launcher, {}",
+                  e);
+        }
+    }
 
-    let mut svc_load_msgs = if feature_flags.contains(FeatureFlag::SERVICE_CONFIG_FILES) {
-        svc::svc_loads_from_paths(&sup_run.svc_config_paths)?.into_iter()
-                                                             .map(|svc_load| {
-                                                                 Ok(svc_load.try_into()?)
-                                                             })
-                                                             .collect::<Result<Vec<_>>>()?
-    } else {
-        vec![]
-    };
+    // An observer runs no services, so the service config files are not loaded on it
+    let mut svc_load_msgs =
+        if feature_flags.contains(FeatureFlag::SERVICE_CONFIG_FILES) && !sup_run.observer {
+            svc::svc_loads_from_paths(&sup_run.svc_config_paths)?.into_iter()
+                                                                 .map(|svc_load| {
+                                                                     Ok(svc_load.try_into()?)
+                                                                 })
+                                                                 .collect::<Result<Vec<_>>>()?
+        } else {
+            vec![]
+        };
 
     let (manager_cfg, maybe_svc_load_msg) = split_apart_sup_run(sup_run, feature_flags).await?;
     if let Some(svc_load_msg) = maybe_svc_load_msg {
         svc_load_msgs.push(svc_load_msg);
     }
-    let manager = Manager::load_imlw(manager_cfg, launcher).await?;
+    let manager = Manager::load_imlw(manager_cfg, run_args, launcher).await?;
     manager.run_rsw_imlw_mlw_gsw_smw_rhw_msw(svc_load_msgs)
            .await
 }
@@ -235,6 +284,14 @@ async fn split_apart_sup_run(sup_run: SupRun,
                              -> Result<(ManagerConfig, Option<sup_proto::ctl::SvcLoad>)> {
     let ring_key = get_ring_key(&sup_run)?;
 
+    // The gRPC endpoint uses the TLS settings of the control gateway, and may only go without
+    // them where nothing off the host can reach it
+    if let Some(addr) = sup_run.listen_ctl_grpc {
+        if sup_run.ctl_server_key.is_none() && !addr.ip().is_loopback() {
+            return Err(Error::InsecureCtlGrpcListen(addr));
+        }
+    }
+
     let shared_load = sup_run.shared_load;
 
     let event_stream_config = if sup_run.event_stream_url.is_some() {
@@ -275,6 +332,51 @@ async fn split_apart_sup_run(sup_run: SupRun,
     let key_cache = KeyCache::new(sup_run.cache_key_path.cache_key_path);
     key_cache.setup()?;
 
+    // Supervisors in a named ring listen on ports derived from the ring name unless their listen
+    // addresses were set explicitly.
+    let (listen_gossip, listen_http, listen_ctl) = match &sup_run.ring_name {
+        Some(ring_name) => {
+            (sup_run.listen_gossip.for_ring(ring_name),
+             sup_run.listen_http.for_ring(ring_name),
+             sup_run.listen_ctl.for_ring(ring_name))
+        }
+        None => (sup_run.listen_gossip, sup_run.listen_http, sup_run.listen_ctl),
+    };
+    let interval = sup_run.state_snapshot_interval.into();
+    let state_snapshot = sup_run.state_snapshot_path
+                                .map(|path| StateSnapshotConfig { path, interval });
+    let interval = sup_run.prefetch_interval.into();
+    let prefetch = sup_run.prefetch_file
+                          .map(|path| PrefetchConfig { path, interval });
+    let (address, datacenter, token) =
+        (sup_run.peer_consul_addr, sup_run.peer_consul_datacenter, sup_run.peer_consul_token);
+    let peer_consul = sup_run.peer_consul_service.map(|service| {
+                                                     ConsulPeerConfig { address,
+                                                                        service,
+                                                                        datacenter,
+                                                                        token }
+                                                 });
+    let acme = match (sup_run.acme_directory, sup_run.acme_email) {
+        (Some(directory), Some(email)) => Some(AcmeConfig { directory, email }),
+        _ => None,
+    };
+    let swim_timing = Timing::new(Duration::from_millis(sup_run.swim_protocol_period),
+                                  Duration::from_millis(sup_run.swim_ping_timeout),
+                                  sup_run.swim_ping_req_fanout,
+                                  sup_run.swim_suspicion_timeout)?;
+    let ring_tls = match (sup_run.ring_tls_cert_file,
+                          sup_run.ring_tls_key_file,
+                          sup_run.ring_tls_ca_cert_file)
+    {
+        (Some(cert_path), Some(key_path), Some(ca_cert_path)) => {
+            Some(RingTlsConfig { cert_path,
+                                 key_path,
+                                 ca_cert_path,
+                                 server_name: sup_run.ring_tls_server_name })
+        }
+        _ => None,
+    };
+
     let cfg =
         ManagerConfig { auto_update: sup_run.auto_update,
                         auto_update_period: sup_run.auto_update_period.into(),
@@ -291,27 +393,45 @@ async fn split_apart_sup_run(sup_run: SupRun,
                         http_disable: sup_run.http_disable,
                         organization: sup_run.organization,
                         gossip_permanent: sup_run.permanent_peer,
+                        gossip_advertise: sup_run.gossip_advertise_addr,
+                        gossip_stun_server: sup_run.gossip_stun_server,
+                        sys_zone: sup_run.zone,
+                        sys_labels: sup_run.label.into_iter().map(Into::into).collect(),
+                        fleet_manifest_origin: sup_run.fleet_manifest_origin,
                         ring_key,
+                        ring_tls,
                         gossip_peers: sup_run.peer,
                         watch_peer_file: sup_run.peer_watch_file
                                                 .map(|p| p.to_string_lossy().to_string()),
+                        peer_kubernetes_service: sup_run.peer_kubernetes_service,
+                        peer_ec2: sup_run.peer_ec2,
+                        peer_mdns: sup_run.peer_mdns,
+                        peer_consul,
                         gossip_listen: if sup_run.local_gossip_mode {
                             GossipListenAddr::local_only()
                         } else {
-                            sup_run.listen_gossip
+                            listen_gossip
                         },
-                        ctl_listen: sup_run.listen_ctl.into(),
-                        ctl_server_certificates: sup_run.ctl_server_certificate
-                                                        .map(CertificateChainCli::into_inner),
-                        ctl_server_key: sup_run.ctl_server_key.map(PrivateKeyCli::into_inner),
-                        ctl_client_ca_certificates:
-                            sup_run.ctl_client_ca_certificate
-                                   .map(RootCertificateStoreCli::into_inner),
-                        http_listen: sup_run.listen_http,
+                        ctl_listen: listen_ctl.into(),
+                        ctl_server_certificates: sup_run.ctl_server_certificate,
+                        ctl_server_key: sup_run.ctl_server_key,
+                        ctl_client_ca_certificates: sup_run.ctl_client_ca_certificate,
+                        ctl_grpc_listen: sup_run.listen_ctl_grpc,
+                        http_listen: listen_http,
                         tls_config,
                         feature_flags,
                         event_stream_config,
                         keep_latest_packages: sup_run.keep_latest_packages,
+                        download_rate_limit: sup_run.download_rate_limit,
+                        state_snapshot,
+                        prefetch,
+                        acme,
+                        extension: sup_run.extension,
+                        member_id_source: sup_run.member_id_source,
+                        denylist: sup_run.deny,
+                        swim_timing,
+                        rumor_ttl: sup_run.rumor_ttl.map(Into::into),
+                        observer: sup_run.observer,
                         sys_ip: sup_run.sys_ip_address
                                        .or_else(|| {
                                            let result_ip = habitat_core::util::sys::ip();
@@ -333,7 +453,9 @@ async fn split_apart_sup_run(sup_run: SupRun,
                 let install = util::pkg::install(&mut ui::ui(),
                                                  &bldr_url,
                                                  &source,
-                                                 &shared_load.channel).await?;
+                                                 &shared_load.channel,
+                                                 PackageChangeInitiator::SupervisorStartup,
+                                                 None).await?;
                 install.ident
             }
             InstallSource::Ident(ident, _) => ident,
@@ -399,7 +521,8 @@ mod test {
                                 ListenCtlAddr};
     use habitat_core::{fs::CACHE_KEY_PATH,
                        locked_env_var};
-    use habitat_sup_protocol::{ctl::ServiceBindList,
+    use habitat_sup_protocol::{ctl::{LeaderAffinityList,
+                                     ServiceBindList},
                                types::{BindingMode,
                                        ServiceBind,
                                        Topology,
@@ -582,6 +705,23 @@ mod test {
             assert_eq!(config.ctl_listen, expected_addr);
         }
 
+        #[test]
+        fn ring_name_offsets_default_listen_addresses() {
+            let config = config_from_cmd_str("hab-sup run --ring-name blue");
+            assert_eq!(config.gossip_listen,
+                       GossipListenAddr::default().for_ring("blue"));
+            assert_eq!(config.http_listen,
+                       HttpListenAddr::default().for_ring("blue"));
+            assert_ne!(config.ctl_listen, ListenCtlAddr::default());
+
+            let config =
+                config_from_cmd_str("hab-sup run --ring-name blue --listen-http 2.2.2.2:2222");
+            let expected_addr =
+                HttpListenAddr::from_str("2.2.2.2:2222").expect("Could not create http listen \
+                                                                 addr");
+            assert_eq!(config.http_listen, expected_addr);
+        }
+
         #[test]
         fn organization_should_be_set() {
             let config = config_from_cmd_str("hab-sup run --org foobar");
@@ -591,6 +731,160 @@ mod test {
             assert_eq!(config.organization, None);
         }
 
+        #[test]
+        fn gossip_advertise_addr_should_be_set() {
+            let config =
+                config_from_cmd_str("hab-sup run --gossip-advertise-addr 203.0.113.7:19638");
+            assert_eq!(config.gossip_advertise,
+                       Some("203.0.113.7:19638".parse().unwrap()));
+            assert_eq!(config.gossip_stun_server, None);
+
+            let config = config_from_cmd_str("hab-sup run --gossip-stun-server stun.example.com");
+            assert_eq!(config.gossip_advertise, None);
+            assert_eq!(config.gossip_stun_server,
+                       Some(String::from("stun.example.com")));
+        }
+
+        #[test]
+        fn sys_labels_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --label role=cache --label disk=ssd");
+            let mut labels = HashMap::new();
+            labels.insert(String::from("role"), String::from("cache"));
+            labels.insert(String::from("disk"), String::from("ssd"));
+            assert_eq!(config.sys_labels, labels);
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert!(config.sys_labels.is_empty());
+        }
+
+        #[test]
+        fn sys_zone_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --zone us-east-1a");
+            assert_eq!(config.sys_zone, Some(String::from("us-east-1a")));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.sys_zone, None);
+        }
+
+        #[test]
+        fn swim_timing_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --swim-protocol-period 600 \
+                                              --swim-ping-timeout 150 --swim-ping-req-fanout 2 \
+                                              --swim-suspicion-timeout 4");
+            assert_eq!(config.swim_timing,
+                       Timing::new(Duration::from_millis(600),
+                                   Duration::from_millis(150),
+                                   2,
+                                   4).unwrap());
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.swim_timing, Timing::default());
+        }
+
+        #[test]
+        fn rumor_ttl_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --rumor-ttl 86400");
+            assert_eq!(config.rumor_ttl, Some(Duration::from_secs(86400)));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.rumor_ttl, None);
+        }
+
+        #[test]
+        fn observer_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --observer");
+            assert!(config.observer);
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert!(!config.observer);
+        }
+
+        #[test]
+        fn invalid_swim_timing_should_be_refused() {
+            let sup_run = sup_run_from_cmd_str("hab-sup run --swim-protocol-period 1000 \
+                                                --swim-ping-timeout 1000");
+            assert!(executor::block_on(split_apart_sup_run(sup_run, no_feature_flags())).is_err());
+        }
+
+        #[test]
+        fn ring_tls_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --ring-tls-cert /tls/member.crt \
+                                              --ring-tls-key /tls/member.key --ring-tls-ca-cert \
+                                              /tls/ca.crt");
+            assert_eq!(config.ring_tls,
+                       Some(RingTlsConfig { cert_path:    PathBuf::from("/tls/member.crt"),
+                                            key_path:     PathBuf::from("/tls/member.key"),
+                                            ca_cert_path: PathBuf::from("/tls/ca.crt"),
+                                            server_name:  String::from("habitat-ring"), }));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.ring_tls, None);
+        }
+
+        #[test]
+        fn download_rate_limit_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --download-rate-limit 1048576");
+            assert_eq!(config.download_rate_limit, Some(1_048_576));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.download_rate_limit, None);
+        }
+
+        #[test]
+        fn ctl_grpc_listen_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --listen-ctl-grpc 127.0.0.1:9633");
+            assert_eq!(config.ctl_grpc_listen,
+                       Some("127.0.0.1:9633".parse().unwrap()));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.ctl_grpc_listen, None);
+        }
+
+        #[test]
+        fn ctl_grpc_listen_off_loopback_requires_tls() {
+            let sup_run = sup_run_from_cmd_str("hab-sup run --listen-ctl-grpc 0.0.0.0:9633");
+            let result = executor::block_on(split_apart_sup_run(sup_run, no_feature_flags()));
+            assert!(matches!(result, Err(Error::InsecureCtlGrpcListen(_))));
+        }
+
+        #[test]
+        fn state_snapshot_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --state-snapshot-path \
+                                              /tmp/sup-state.json --state-snapshot-interval 10");
+            assert_eq!(config.state_snapshot,
+                       Some(StateSnapshotConfig { path:     PathBuf::from("/tmp/sup-state.json"),
+                                                  interval: Duration::from_secs(10), }));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.state_snapshot, None);
+        }
+
+        #[test]
+        fn prefetch_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --prefetch-file /tmp/packages.toml \
+                                              --prefetch-interval 600");
+            assert_eq!(config.prefetch,
+                       Some(PrefetchConfig { path:     PathBuf::from("/tmp/packages.toml"),
+                                             interval: Duration::from_secs(600), }));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.prefetch, None);
+        }
+
+        #[test]
+        fn acme_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --acme-directory \
+                                              https://acme.example.com/directory --acme-email \
+                                              ops@example.com");
+            let directory = "https://acme.example.com/directory".parse().unwrap();
+            assert_eq!(config.acme,
+                       Some(AcmeConfig { directory,
+                                         email: String::from("ops@example.com") }));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.acme, None);
+        }
+
         #[test]
         fn gossip_permanent_should_be_set() {
             let config = config_from_cmd_str("hab-sup run --permanent-peer");
@@ -643,6 +937,50 @@ mod test {
             assert_eq!(config.watch_peer_file, None);
         }
 
+        #[test]
+        fn peer_kubernetes_service_should_be_set() {
+            let config =
+                config_from_cmd_str("hab-sup run --peer-kubernetes-service prod/hab-ring:gossip");
+            assert_eq!(config.peer_kubernetes_service,
+                       Some("prod/hab-ring:gossip".parse().unwrap()));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.peer_kubernetes_service, None);
+        }
+
+        #[test]
+        fn peer_ec2_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --peer-ec2 asg:hab-ring");
+            assert_eq!(config.peer_ec2, Some("asg:hab-ring".parse().unwrap()));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.peer_ec2, None);
+        }
+
+        #[test]
+        fn peer_consul_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --peer-consul-service hab-sup \
+                                              --peer-consul-datacenter dc2");
+            let address = "http://127.0.0.1:8500".parse().unwrap();
+            assert_eq!(config.peer_consul,
+                       Some(ConsulPeerConfig { address,
+                                               service: String::from("hab-sup"),
+                                               datacenter: Some(String::from("dc2")),
+                                               token: None }));
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.peer_consul, None);
+        }
+
+        #[test]
+        fn peer_mdns_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --peer-mdns");
+            assert!(config.peer_mdns);
+
+            let config = config_from_cmd_str("hab-sup run");
+            assert!(!config.peer_mdns);
+        }
+
         #[test]
         fn ring_key_is_set_properly_by_name() {
             let temp_dir = TempDir::new().expect("Could not create tempdir");
@@ -716,33 +1054,52 @@ gpoVMSncu2jMIDZX63IkQII=
             lock.unset();
 
             let config = config_from_cmd_str("hab-sup run");
-            assert_eq!(ManagerConfig { auto_update:                false,
-                                       auto_update_period:         Duration::from_secs(60),
-                                       service_update_period:      Duration::from_secs(60),
-                                       service_restart_config:     ServiceRestartConfig::default(),
-                                       custom_state_path:          None,
-                                       key_cache:                  KeyCache::new(&*CACHE_KEY_PATH),
-                                       update_url:
-                                           String::from("https://bldr.habitat.sh"),
-                                       update_channel:             ChannelIdent::default(),
-                                       gossip_listen:              GossipListenAddr::default(),
-                                       ctl_listen:                 ListenCtlAddr::default(),
-                                       ctl_server_certificates:    None,
-                                       ctl_server_key:             None,
+            assert_eq!(ManagerConfig { auto_update: false,
+                                       auto_update_period: Duration::from_secs(60),
+                                       service_update_period: Duration::from_secs(60),
+                                       service_restart_config: ServiceRestartConfig::default(),
+                                       custom_state_path: None,
+                                       key_cache: KeyCache::new(&*CACHE_KEY_PATH),
+                                       update_url: String::from("https://bldr.habitat.sh"),
+                                       update_channel: ChannelIdent::default(),
+                                       gossip_listen: GossipListenAddr::default(),
+                                       ctl_listen: ListenCtlAddr::default(),
+                                       ctl_server_certificates: None,
+                                       ctl_server_key: None,
                                        ctl_client_ca_certificates: None,
-                                       http_listen:                HttpListenAddr::default(),
-                                       http_disable:               false,
-                                       gossip_peers:               vec![],
-                                       gossip_permanent:           false,
-                                       ring_key:                   None,
-                                       organization:               None,
-                                       watch_peer_file:            None,
-                                       tls_config:                 None,
-                                       feature_flags:              FeatureFlag::empty(),
-                                       event_stream_config:        None,
-                                       keep_latest_packages:       None,
-                                       sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                       ctl_grpc_listen: None,
+                                       http_listen: HttpListenAddr::default(),
+                                       http_disable: false,
+                                       gossip_peers: vec![],
+                                       gossip_permanent: false,
+                                       gossip_advertise: None,
+                                       gossip_stun_server: None,
+                                       sys_zone: None,
+                                       sys_labels: HashMap::new(),
+                                       fleet_manifest_origin: None,
+                                       ring_key: None,
+                                       organization: None,
+                                       watch_peer_file: None,
+                                       peer_kubernetes_service: None,
+                                       peer_ec2: None,
+                                       peer_mdns: false,
+                                       peer_consul: None,
+                                       tls_config: None,
+                                       feature_flags: FeatureFlag::empty(),
+                                       event_stream_config: None,
+                                       keep_latest_packages: None,
+                                       download_rate_limit: None,
+                                       state_snapshot: None,
+                                       prefetch: None,
+                                       acme: None,
+                                       extension: None,
+                                       member_id_source: None,
+                                       denylist: Vec::new(),
+                                       swim_timing: Timing::default(),
+                                       rumor_ttl: None,
+                                       observer: false,
+                                       ring_tls: None,
+                                       sys_ip: habitat_core::util::sys::ip().unwrap(), },
                        config);
 
             let maybe_service_load = maybe_service_load_from_cmd_str("hab-sup run");
@@ -805,14 +1162,24 @@ gpoVMSncu2jMIDZX63IkQII=
                                        ctl_server_certificates: None,
                                        ctl_server_key: None,
                                        ctl_client_ca_certificates: None,
+                                       ctl_grpc_listen: None,
                                        http_listen:
                                            HttpListenAddr::from_str("5.5.5.5:11111").unwrap(),
                                        http_disable: true,
                                        gossip_peers,
                                        gossip_permanent: true,
+                                       gossip_advertise: None,
+                                       gossip_stun_server: None,
+                                       sys_zone: None,
+                                       sys_labels: HashMap::new(),
+                                       fleet_manifest_origin: None,
                                        ring_key: Some(ring_key),
                                        organization: Some(String::from("MY_ORG")),
                                        watch_peer_file: None,
+                                       peer_kubernetes_service: None,
+                                       peer_ec2: None,
+                                       peer_mdns: false,
+                                       peer_consul: None,
                                        tls_config: Some(TLSConfig { cert_path,
                                                                     key_path,
                                                                     ca_cert_path:
@@ -820,6 +1187,17 @@ gpoVMSncu2jMIDZX63IkQII=
                                        feature_flags: FeatureFlag::empty(),
                                        event_stream_config: None,
                                        keep_latest_packages: Some(5),
+                                       download_rate_limit: None,
+                                       state_snapshot: None,
+                                       prefetch: None,
+                                       acme: None,
+                                       extension: None,
+                                       member_id_source: None,
+                                       denylist: Vec::new(),
+                                       swim_timing: Timing::default(),
+                                       rumor_ttl: None,
+                                       observer: false,
+                                       ring_tls: None,
                                        sys_ip: "7.8.9.0".parse().unwrap() },
                        config);
         }
@@ -832,34 +1210,53 @@ gpoVMSncu2jMIDZX63IkQII=
             let args = "hab-sup run --local-gossip-mode";
 
             let config = config_from_cmd_str(args);
-            assert_eq!(ManagerConfig { auto_update:                false,
-                                       auto_update_period:         Duration::from_secs(60),
-                                       service_update_period:      Duration::from_secs(60),
-                                       service_restart_config:     ServiceRestartConfig::default(),
-                                       custom_state_path:          None,
-                                       key_cache:                  KeyCache::new("/cache/key/path"),
-                                       update_url:
-                                           String::from("https://bldr.habitat.sh"),
-                                       update_channel:             ChannelIdent::default(),
+            assert_eq!(ManagerConfig { auto_update: false,
+                                       auto_update_period: Duration::from_secs(60),
+                                       service_update_period: Duration::from_secs(60),
+                                       service_restart_config: ServiceRestartConfig::default(),
+                                       custom_state_path: None,
+                                       key_cache: KeyCache::new("/cache/key/path"),
+                                       update_url: String::from("https://bldr.habitat.sh"),
+                                       update_channel: ChannelIdent::default(),
                                        gossip_listen:
                                            GossipListenAddr::from_str("127.0.0.2:9638").unwrap(),
-                                       ctl_listen:                 ListenCtlAddr::default(),
-                                       ctl_server_certificates:    None,
-                                       ctl_server_key:             None,
+                                       ctl_listen: ListenCtlAddr::default(),
+                                       ctl_server_certificates: None,
+                                       ctl_server_key: None,
                                        ctl_client_ca_certificates: None,
-                                       http_listen:                HttpListenAddr::default(),
-                                       http_disable:               false,
-                                       gossip_peers:               vec![],
-                                       gossip_permanent:           false,
-                                       ring_key:                   None,
-                                       organization:               None,
-                                       watch_peer_file:            None,
-                                       tls_config:                 None,
-                                       feature_flags:              FeatureFlag::empty(),
-                                       event_stream_config:        None,
-                                       keep_latest_packages:       None,
-                                       sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                       ctl_grpc_listen: None,
+                                       http_listen: HttpListenAddr::default(),
+                                       http_disable: false,
+                                       gossip_peers: vec![],
+                                       gossip_permanent: false,
+                                       gossip_advertise: None,
+                                       gossip_stun_server: None,
+                                       sys_zone: None,
+                                       sys_labels: HashMap::new(),
+                                       fleet_manifest_origin: None,
+                                       ring_key: None,
+                                       organization: None,
+                                       watch_peer_file: None,
+                                       peer_kubernetes_service: None,
+                                       peer_ec2: None,
+                                       peer_mdns: false,
+                                       peer_consul: None,
+                                       tls_config: None,
+                                       feature_flags: FeatureFlag::empty(),
+                                       event_stream_config: None,
+                                       keep_latest_packages: None,
+                                       download_rate_limit: None,
+                                       state_snapshot: None,
+                                       prefetch: None,
+                                       acme: None,
+                                       extension: None,
+                                       member_id_source: None,
+                                       denylist: Vec::new(),
+                                       swim_timing: Timing::default(),
+                                       rumor_ttl: None,
+                                       observer: false,
+                                       ring_tls: None,
+                                       sys_ip: habitat_core::util::sys::ip().unwrap(), },
                        config);
         }
 
@@ -871,33 +1268,52 @@ gpoVMSncu2jMIDZX63IkQII=
             let args = "hab-sup run --peer-watch-file=/some/path";
 
             let config = config_from_cmd_str(args);
-            assert_eq!(ManagerConfig { auto_update:                false,
-                                       auto_update_period:         Duration::from_secs(60),
-                                       service_update_period:      Duration::from_secs(60),
-                                       service_restart_config:     ServiceRestartConfig::default(),
-                                       custom_state_path:          None,
-                                       key_cache:                  KeyCache::new(&*CACHE_KEY_PATH),
-                                       update_url:
-                                           String::from("https://bldr.habitat.sh"),
-                                       update_channel:             ChannelIdent::default(),
-                                       gossip_listen:              GossipListenAddr::default(),
-                                       ctl_listen:                 ListenCtlAddr::default(),
-                                       ctl_server_certificates:    None,
-                                       ctl_server_key:             None,
+            assert_eq!(ManagerConfig { auto_update: false,
+                                       auto_update_period: Duration::from_secs(60),
+                                       service_update_period: Duration::from_secs(60),
+                                       service_restart_config: ServiceRestartConfig::default(),
+                                       custom_state_path: None,
+                                       key_cache: KeyCache::new(&*CACHE_KEY_PATH),
+                                       update_url: String::from("https://bldr.habitat.sh"),
+                                       update_channel: ChannelIdent::default(),
+                                       gossip_listen: GossipListenAddr::default(),
+                                       ctl_listen: ListenCtlAddr::default(),
+                                       ctl_server_certificates: None,
+                                       ctl_server_key: None,
                                        ctl_client_ca_certificates: None,
-                                       http_listen:                HttpListenAddr::default(),
-                                       http_disable:               false,
-                                       gossip_peers:               vec![],
-                                       gossip_permanent:           false,
-                                       ring_key:                   None,
-                                       organization:               None,
-                                       watch_peer_file:            Some(String::from("/some/path")),
-                                       tls_config:                 None,
-                                       feature_flags:              FeatureFlag::empty(),
-                                       event_stream_config:        None,
-                                       keep_latest_packages:       None,
-                                       sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                       ctl_grpc_listen: None,
+                                       http_listen: HttpListenAddr::default(),
+                                       http_disable: false,
+                                       gossip_peers: vec![],
+                                       gossip_permanent: false,
+                                       gossip_advertise: None,
+                                       gossip_stun_server: None,
+                                       sys_zone: None,
+                                       sys_labels: HashMap::new(),
+                                       fleet_manifest_origin: None,
+                                       ring_key: None,
+                                       organization: None,
+                                       watch_peer_file: Some(String::from("/some/path")),
+                                       peer_kubernetes_service: None,
+                                       peer_ec2: None,
+                                       peer_mdns: false,
+                                       peer_consul: None,
+                                       tls_config: None,
+                                       feature_flags: FeatureFlag::empty(),
+                                       event_stream_config: None,
+                                       keep_latest_packages: None,
+                                       download_rate_limit: None,
+                                       state_snapshot: None,
+                                       prefetch: None,
+                                       acme: None,
+                                       extension: None,
+                                       member_id_source: None,
+                                       denylist: Vec::new(),
+                                       swim_timing: Timing::default(),
+                                       rumor_ttl: None,
+                                       observer: false,
+                                       ring_tls: None,
+                                       sys_ip: habitat_core::util::sys::ip().unwrap(), },
                        config);
         }
 
@@ -956,13 +1372,23 @@ gpoVMSncu2jMIDZX63IkQII=
                                        ctl_server_certificates: None,
                                        ctl_server_key:          None,
                                        ctl_client_ca_certificates: None,
+                                       ctl_grpc_listen: None,
                                        http_listen:          HttpListenAddr::default(),
                                        http_disable:         false,
                                        gossip_peers:         vec![],
                                        gossip_permanent:     false,
+                                       gossip_advertise: None,
+                                       gossip_stun_server: None,
+                                       sys_zone: None,
+                                       sys_labels: HashMap::new(),
+                                       fleet_manifest_origin: None,
                                        ring_key:             None,
                                        organization:         None,
                                        watch_peer_file:      None,
+                                       peer_kubernetes_service: None,
+                                       peer_ec2: None,
+                                       peer_mdns: false,
+                                       peer_consul: None,
                                        tls_config:           None,
                                        feature_flags:        FeatureFlag::empty(),
                                        event_stream_config:  Some(EventStreamConfig {
@@ -976,6 +1402,17 @@ gpoVMSncu2jMIDZX63IkQII=
                                         server_certificate: Some(certificate_path_str.parse().unwrap()),
                                        }),
                                        keep_latest_packages: None,
+                                       download_rate_limit: None,
+                                       state_snapshot: None,
+                                       prefetch: None,
+                                       acme: None,
+                                       extension: None,
+                                       member_id_source: None,
+                                       denylist: Vec::new(),
+                                       swim_timing: Timing::default(),
+                                       rumor_ttl: None,
+                                       observer: false,
+                                       ring_tls: None,
                                        sys_ip:               habitat_core::util::sys::ip().unwrap(), },
                        config,);
         }
@@ -989,7 +1426,7 @@ gpoVMSncu2jMIDZX63IkQII=
                                 two:service2.default --binding-mode relaxed --url http://my_url.com \
                                 --config-from={} --group MyGroup --topology leader \
                                 --strategy rolling --update-condition track-channel --health-check-interval 17 \
-                                --shutdown-timeout=12 core/redis",
+                                --anti-affinity=redis.cache --max-per-zone 2 --strict-deps --leader-affinity=disk=ssd --leader-tie-break --shutdown-timeout=12 core/redis",
                                temp_dir_str);
 
             let mut binds = ServiceBindList::default();
@@ -998,6 +1435,8 @@ gpoVMSncu2jMIDZX63IkQII=
             binds.binds
                  .push(ServiceBind::from_str("two:service2.default").unwrap());
             let health_check_interval = sup_proto::types::HealthCheckInterval { seconds: 17 };
+            let leader_affinity =
+                LeaderAffinityList { preferences: vec![String::from("disk=ssd")], };
 
             let service_load = service_load_from_cmd_str(&args);
             assert_eq!(sup_proto::ctl::SvcLoad { ident:
@@ -1024,7 +1463,15 @@ gpoVMSncu2jMIDZX63IkQII=
                                                      Some(health_check_interval),
                                                  shutdown_timeout:       Some(12),
                                                  update_condition:
-                                                     Some(UpdateCondition::TrackChannel.into()), },
+                                                     Some(UpdateCondition::TrackChannel.into()),
+                                                 anti_affinity:
+                                                     vec![String::from("redis.cache")],
+                                                 max_per_zone:           Some(2),
+                                                 strict_deps:            Some(true),
+                                                 leader_affinity:        Some(leader_affinity),
+                                                 leader_tie_break:       Some(true),
+                                                 revision:               None,
+                                                 follow:                 None, },
                        service_load);
         }
 
@@ -1138,14 +1585,24 @@ sys_ip_address = "7.8.9.0"
                                        ctl_server_certificates: None,
                                        ctl_server_key: None,
                                        ctl_client_ca_certificates: None,
+                                       ctl_grpc_listen: None,
                                        http_listen:
                                            HttpListenAddr::from_str("5.5.5.5:11111").unwrap(),
                                        http_disable: true,
                                        gossip_peers,
                                        gossip_permanent: true,
+                                       gossip_advertise: None,
+                                       gossip_stun_server: None,
+                                       sys_zone: None,
+                                       sys_labels: HashMap::new(),
+                                       fleet_manifest_origin: None,
                                        ring_key: Some(ring_key),
                                        organization: Some(String::from("MY_ORG")),
                                        watch_peer_file: None,
+                                       peer_kubernetes_service: None,
+                                       peer_ec2: None,
+                                       peer_mdns: false,
+                                       peer_consul: None,
                                        tls_config: Some(TLSConfig { cert_path,
                                                                     key_path,
                                                                     ca_cert_path:
@@ -1153,6 +1610,17 @@ sys_ip_address = "7.8.9.0"
                                        feature_flags: FeatureFlag::empty(),
                                        event_stream_config: None,
                                        keep_latest_packages: Some(5),
+                                       download_rate_limit: None,
+                                       state_snapshot: None,
+                                       prefetch: None,
+                                       acme: None,
+                                       extension: None,
+                                       member_id_source: None,
+                                       denylist: Vec::new(),
+                                       swim_timing: Timing::default(),
+                                       rumor_ttl: None,
+                                       observer: false,
+                                       ring_tls: None,
                                        sys_ip: "7.8.9.0".parse().unwrap() },
                        config);
         }
@@ -1174,34 +1642,53 @@ sys_ip_address = "7.8.9.0"
             let args = format!("hab-sup run --config-files {}", config_path_str);
 
             let config = config_from_cmd_str(&args);
-            assert_eq!(ManagerConfig { auto_update:                false,
-                                       auto_update_period:         Duration::from_secs(60),
-                                       service_update_period:      Duration::from_secs(60),
-                                       service_restart_config:     ServiceRestartConfig::default(),
-                                       custom_state_path:          None,
-                                       key_cache:                  KeyCache::new("/cache/key/path"),
-                                       update_url:
-                                           String::from("https://bldr.habitat.sh"),
-                                       update_channel:             ChannelIdent::default(),
+            assert_eq!(ManagerConfig { auto_update: false,
+                                       auto_update_period: Duration::from_secs(60),
+                                       service_update_period: Duration::from_secs(60),
+                                       service_restart_config: ServiceRestartConfig::default(),
+                                       custom_state_path: None,
+                                       key_cache: KeyCache::new("/cache/key/path"),
+                                       update_url: String::from("https://bldr.habitat.sh"),
+                                       update_channel: ChannelIdent::default(),
                                        gossip_listen:
                                            GossipListenAddr::from_str("127.0.0.2:9638").unwrap(),
-                                       ctl_listen:                 ListenCtlAddr::default(),
-                                       ctl_server_certificates:    None,
-                                       ctl_server_key:             None,
+                                       ctl_listen: ListenCtlAddr::default(),
+                                       ctl_server_certificates: None,
+                                       ctl_server_key: None,
                                        ctl_client_ca_certificates: None,
-                                       http_listen:                HttpListenAddr::default(),
-                                       http_disable:               false,
-                                       gossip_peers:               vec![],
-                                       gossip_permanent:           false,
-                                       ring_key:                   None,
-                                       organization:               None,
-                                       watch_peer_file:            None,
-                                       tls_config:                 None,
-                                       feature_flags:              FeatureFlag::empty(),
-                                       event_stream_config:        None,
-                                       keep_latest_packages:       None,
-                                       sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                       ctl_grpc_listen: None,
+                                       http_listen: HttpListenAddr::default(),
+                                       http_disable: false,
+                                       gossip_peers: vec![],
+                                       gossip_permanent: false,
+                                       gossip_advertise: None,
+                                       gossip_stun_server: None,
+                                       sys_zone: None,
+                                       sys_labels: HashMap::new(),
+                                       fleet_manifest_origin: None,
+                                       ring_key: None,
+                                       organization: None,
+                                       watch_peer_file: None,
+                                       peer_kubernetes_service: None,
+                                       peer_ec2: None,
+                                       peer_mdns: false,
+                                       peer_consul: None,
+                                       tls_config: None,
+                                       feature_flags: FeatureFlag::empty(),
+                                       event_stream_config: None,
+                                       keep_latest_packages: None,
+                                       download_rate_limit: None,
+                                       state_snapshot: None,
+                                       prefetch: None,
+                                       acme: None,
+                                       extension: None,
+                                       member_id_source: None,
+                                       denylist: Vec::new(),
+                                       swim_timing: Timing::default(),
+                                       rumor_ttl: None,
+                                       observer: false,
+                                       ring_tls: None,
+                                       sys_ip: habitat_core::util::sys::ip().unwrap(), },
                        config);
         }
 
@@ -1222,33 +1709,52 @@ sys_ip_address = "7.8.9.0"
             let args = format!("hab-sup run --config-files {}", config_path_str);
 
             let config = config_from_cmd_str(&args);
-            assert_eq!(ManagerConfig { auto_update:                false,
-                                       auto_update_period:         Duration::from_secs(60),
-                                       service_update_period:      Duration::from_secs(60),
-                                       service_restart_config:     ServiceRestartConfig::default(),
-                                       custom_state_path:          None,
-                                       key_cache:                  KeyCache::new(&*CACHE_KEY_PATH),
-                                       update_url:
-                                           String::from("https://bldr.habitat.sh"),
-                                       update_channel:             ChannelIdent::default(),
-                                       gossip_listen:              GossipListenAddr::default(),
-                                       ctl_listen:                 ListenCtlAddr::default(),
-                                       ctl_server_certificates:    None,
-                                       ctl_server_key:             None,
+            assert_eq!(ManagerConfig { auto_update: false,
+                                       auto_update_period: Duration::from_secs(60),
+                                       service_update_period: Duration::from_secs(60),
+                                       service_restart_config: ServiceRestartConfig::default(),
+                                       custom_state_path: None,
+                                       key_cache: KeyCache::new(&*CACHE_KEY_PATH),
+                                       update_url: String::from("https://bldr.habitat.sh"),
+                                       update_channel: ChannelIdent::default(),
+                                       gossip_listen: GossipListenAddr::default(),
+                                       ctl_listen: ListenCtlAddr::default(),
+                                       ctl_server_certificates: None,
+                                       ctl_server_key: None,
                                        ctl_client_ca_certificates: None,
-                                       http_listen:                HttpListenAddr::default(),
-                                       http_disable:               false,
-                                       gossip_peers:               vec![],
-                                       gossip_permanent:           false,
-                                       ring_key:                   None,
-                                       organization:               None,
-                                       watch_peer_file:            Some(String::from("/some/path")),
-                                       tls_config:                 None,
-                                       feature_flags:              FeatureFlag::empty(),
-                                       event_stream_config:        None,
-                                       keep_latest_packages:       None,
-                                       sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                       ctl_grpc_listen: None,
+                                       http_listen: HttpListenAddr::default(),
+                                       http_disable: false,
+                                       gossip_peers: vec![],
+                                       gossip_permanent: false,
+                                       gossip_advertise: None,
+                                       gossip_stun_server: None,
+                                       sys_zone: None,
+                                       sys_labels: HashMap::new(),
+                                       fleet_manifest_origin: None,
+                                       ring_key: None,
+                                       organization: None,
+                                       watch_peer_file: Some(String::from("/some/path")),
+                                       peer_kubernetes_service: None,
+                                       peer_ec2: None,
+                                       peer_mdns: false,
+                                       peer_consul: None,
+                                       tls_config: None,
+                                       feature_flags: FeatureFlag::empty(),
+                                       event_stream_config: None,
+                                       keep_latest_packages: None,
+                                       download_rate_limit: None,
+                                       state_snapshot: None,
+                                       prefetch: None,
+                                       acme: None,
+                                       extension: None,
+                                       member_id_source: None,
+                                       denylist: Vec::new(),
+                                       swim_timing: Timing::default(),
+                                       rumor_ttl: None,
+                                       observer: false,
+                                       ring_tls: None,
+                                       sys_ip: habitat_core::util::sys::ip().unwrap(), },
                        config);
         }
 
@@ -1344,13 +1850,23 @@ event_stream_server_certificate = "{}"
                                        ctl_server_certificates: None,
                                        ctl_server_key:          None,
                                        ctl_client_ca_certificates: None,
+                                       ctl_grpc_listen: None,
                                        http_listen:          HttpListenAddr::default(),
                                        http_disable:         false,
                                        gossip_peers:         vec![],
                                        gossip_permanent:     false,
+                                       gossip_advertise: None,
+                                       gossip_stun_server: None,
+                                       sys_zone: None,
+                                       sys_labels: HashMap::new(),
+                                       fleet_manifest_origin: None,
                                        ring_key:             None,
                                        organization:         None,
                                        watch_peer_file:      None,
+                                       peer_kubernetes_service: None,
+                                       peer_ec2: None,
+                                       peer_mdns: false,
+                                       peer_consul: None,
                                        tls_config:           None,
                                        feature_flags:        FeatureFlag::empty(),
                                        event_stream_config:  Some(EventStreamConfig {
@@ -1364,6 +1880,17 @@ event_stream_server_certificate = "{}"
                                         server_certificate: Some(certificate_path_str.parse().unwrap()),
                                        }),
                                        keep_latest_packages: None,
+                                       download_rate_limit: None,
+                                       state_snapshot: None,
+                                       prefetch: None,
+                                       acme: None,
+                                       extension: None,
+                                       member_id_source: None,
+                                       denylist: Vec::new(),
+                                       swim_timing: Timing::default(),
+                                       rumor_ttl: None,
+                                       observer: false,
+                                       ring_tls: None,
                                        sys_ip:               habitat_core::util::sys::ip().unwrap(), },
                        config,);
         }
@@ -1387,6 +1914,11 @@ strategy = "at-once"
 update_condition = "track-channel"
 health_check_interval = 17
 shutdown_timeout = 12
+anti_affinity = ["redis.cache"]
+max_per_zone = 2
+strict_deps = true
+leader_affinity = ["disk=ssd"]
+leader_tie_break = true
 pkg_ident_or_artifact = "core/redis"
 "#,
                                           temp_dir_str.replace("\\", "/")
@@ -1404,6 +1936,8 @@ pkg_ident_or_artifact = "core/redis"
             binds.binds
                  .push(ServiceBind::from_str("two:service2.default").unwrap());
             let health_check_interval = sup_proto::types::HealthCheckInterval { seconds: 17 };
+            let leader_affinity =
+                LeaderAffinityList { preferences: vec![String::from("disk=ssd")], };
 
             let service_load = service_load_from_cmd_str(&args);
             assert_eq!(sup_proto::ctl::SvcLoad { ident:
@@ -1430,7 +1964,15 @@ pkg_ident_or_artifact = "core/redis"
                                                      Some(health_check_interval),
                                                  shutdown_timeout:       Some(12),
                                                  update_condition:
-                                                     Some(UpdateCondition::TrackChannel.into()), },
+                                                     Some(UpdateCondition::TrackChannel.into()),
+                                                 anti_affinity:
+                                                     vec![String::from("redis.cache")],
+                                                 max_per_zone:           Some(2),
+                                                 strict_deps:            Some(true),
+                                                 leader_affinity:        Some(leader_affinity),
+                                                 leader_tie_break:       Some(true),
+                                                 revision:               None,
+                                                 follow:                 None, },
                        service_load);
         }
 
@@ -1517,37 +2059,54 @@ organization = "MY_ORG_FROM_SECOND_CONFG"
                                config1_path_str, config2_path_str);
 
             let config = config_from_cmd_str(&args);
-            assert_eq!(ManagerConfig { auto_update:                false,
-                                       auto_update_period:         Duration::from_secs(60),
-                                       service_update_period:      Duration::from_secs(60),
-                                       service_restart_config:     ServiceRestartConfig::default(),
-                                       custom_state_path:          None,
-                                       key_cache:                  KeyCache::new(&*CACHE_KEY_PATH),
-                                       update_url:
-                                           String::from("https://bldr.habitat.sh"),
-                                       update_channel:             ChannelIdent::default(),
+            assert_eq!(ManagerConfig { auto_update: false,
+                                       auto_update_period: Duration::from_secs(60),
+                                       service_update_period: Duration::from_secs(60),
+                                       service_restart_config: ServiceRestartConfig::default(),
+                                       custom_state_path: None,
+                                       key_cache: KeyCache::new(&*CACHE_KEY_PATH),
+                                       update_url: String::from("https://bldr.habitat.sh"),
+                                       update_channel: ChannelIdent::default(),
                                        gossip_listen:
                                            GossipListenAddr::from_str("1.2.3.4:4321").unwrap(),
-                                       ctl_listen:
-                                           ListenCtlAddr::from_str("7.7.7.7:7777").unwrap(),
-                                       ctl_server_certificates:    None,
-                                       ctl_server_key:             None,
+                                       ctl_listen: ListenCtlAddr::from_str("7.7.7.7:7777").unwrap(),
+                                       ctl_server_certificates: None,
+                                       ctl_server_key: None,
                                        ctl_client_ca_certificates: None,
+                                       ctl_grpc_listen: None,
                                        http_listen:
                                            HttpListenAddr::from_str("3.3.3.3:3333").unwrap(),
-                                       http_disable:               false,
-                                       gossip_peers:               vec![],
-                                       gossip_permanent:           false,
-                                       ring_key:                   None,
-                                       organization:
-                                           Some(String::from("MY_ORG_FROM_SECOND_CONFG")),
-                                       watch_peer_file:            None,
-                                       tls_config:                 None,
-                                       feature_flags:              FeatureFlag::empty(),
-                                       event_stream_config:        None,
-                                       keep_latest_packages:       None,
-                                       sys_ip:
-                                           habitat_core::util::sys::ip().unwrap(), },
+                                       http_disable: false,
+                                       gossip_peers: vec![],
+                                       gossip_permanent: false,
+                                       gossip_advertise: None,
+                                       gossip_stun_server: None,
+                                       sys_zone: None,
+                                       sys_labels: HashMap::new(),
+                                       fleet_manifest_origin: None,
+                                       ring_key: None,
+                                       organization: Some(String::from("MY_ORG_FROM_SECOND_CONFG")),
+                                       watch_peer_file: None,
+                                       peer_kubernetes_service: None,
+                                       peer_ec2: None,
+                                       peer_mdns: false,
+                                       peer_consul: None,
+                                       tls_config: None,
+                                       feature_flags: FeatureFlag::empty(),
+                                       event_stream_config: None,
+                                       keep_latest_packages: None,
+                                       download_rate_limit: None,
+                                       state_snapshot: None,
+                                       prefetch: None,
+                                       acme: None,
+                                       extension: None,
+                                       member_id_source: None,
+                                       denylist: Vec::new(),
+                                       swim_timing: Timing::default(),
+                                       rumor_ttl: None,
+                                       observer: false,
+                                       ring_tls: None,
+                                       sys_ip: habitat_core::util::sys::ip().unwrap(), },
                        config);
         }
 
@@ -1,28 +1,51 @@
+mod acme;
 pub(crate) mod action;
 pub mod service;
 #[macro_use]
 mod debug;
 pub mod commands;
+mod config_reload;
+mod denylist;
 mod file_watcher;
+pub(crate) mod fleet_manifest;
+mod kubernetes_peer_watcher;
+mod member_id;
+mod peer_source;
 mod peer_watcher;
+mod prefetcher;
+mod ring_key_watcher;
+mod ring_status;
 mod self_updater;
+mod service_graph;
 mod service_updater;
 mod spec_dir;
 mod spec_watcher;
+mod state_snapshot;
 mod sup_watcher;
 pub(crate) mod sys;
+mod template_watcher;
+mod tls_watcher;
+pub(crate) mod tombstone;
 mod user_config_watcher;
 
-use self::{action::{ShutdownInput,
+use self::{acme::AcmeClient,
+           action::{ShutdownInput,
                     SupervisorAction},
+           fleet_manifest::FleetManifestReconciler,
+           kubernetes_peer_watcher::KubernetesPeerWatcher,
+           peer_source::{ConsulPeerSource,
+                         Ec2PeerSource,
+                         MdnsPeerSource,
+                         PeerSource},
            peer_watcher::PeerWatcher,
            self_updater::{SelfUpdater,
                           SUP_PKG_IDENT},
-           service::{spec::{RefreshOperation,
+           service::{data_retention,
+                     spec::{RefreshOperation,
                             ServiceOperation},
                      ConfigRendering,
-                     DesiredState,
-                     HealthCheckResult,
+                     DataRetention,
+                     HealthCheckData,
                      PersistentServiceWrapper,
                      Service,
                      ServiceProxy,
@@ -32,20 +55,33 @@ use self::{action::{ShutdownInput,
            service_updater::ServiceUpdater,
            spec_dir::SpecDir,
            spec_watcher::SpecWatcher,
+           state_snapshot::StateSnapshotWriter,
            sys::Sys,
+           template_watcher::TemplateWatcher,
+           tls_watcher::{ReloadableTls,
+                         TlsFiles},
+           tombstone::Tombstone,
            user_config_watcher::UserConfigWatcher};
+pub use self::{acme::AcmeConfig,
+               config_reload::ReloadableConfig,
+               peer_source::ConsulPeerConfig,
+               prefetcher::PrefetchConfig,
+               state_snapshot::StateSnapshotConfig};
 use crate::{census::{CensusRing,
                      CensusRingProxy},
             ctl_gateway::{self,
                           acceptor::CtlAcceptor,
+                          grpc::CtlGatewayGrpcServer,
                           server::CtlGatewayServer,
                           CtlRequest},
             error::{Error,
                     Result},
             event::{self,
                     EventStreamConfig},
+            extension,
             http_gateway,
             lock_file::LockFile,
+            systemd,
             util::pkg,
             VERSION};
 use cpu_time::ProcessTime;
@@ -56,53 +92,69 @@ use futures::{channel::{mpsc as fut_mpsc,
               prelude::*,
               stream::FuturesUnordered};
 use habitat_butterfly::{member::Member,
+                        rumor::departure::Departure,
                         server::{timing::Timing,
                                  ServerProxy,
-                                 Suitability}};
+                                 Suitability},
+                        stun,
+                        tls::{RingTls,
+                              RingTlsConfig}};
 use habitat_common::{liveliness_checker,
                      outputln,
-                     types::{GossipListenAddr,
+                     redaction,
+                     types::{DenylistEntry,
+                             Ec2PeerFilter,
+                             GossipAdvertiseAddr,
+                             GossipListenAddr,
                              HttpListenAddr,
-                             ListenCtlAddr},
+                             KubernetesService,
+                             ListenCtlAddr,
+                             MemberIdSource},
                      FeatureFlag};
 #[cfg(unix)]
 use habitat_core::os::{process::{ShutdownSignal,
                                  Signal},
                        signals};
-use habitat_core::{crypto::keys::{KeyCache,
+use habitat_core::{crypto::keys::{Key,
+                                  KeyCache,
                                   RingKey},
                    env,
                    env::Config,
-                   fs::FS_ROOT_PATH,
+                   fs::{checksummed_read,
+                        checksummed_recover,
+                        checksummed_remove,
+                        checksummed_write,
+                        StagedChecksummedWrite,
+                        FS_ROOT_PATH},
+                   origin::Origin,
                    os::process::{self,
                                  ShutdownTimeout},
                    package::{Identifiable,
                              PackageIdent,
                              PackageInstall},
                    service::ServiceGroup,
+                   tls::rustls_wrapper::{CertificateChainCli,
+                                         PrivateKeyCli,
+                                         RootCertificateStoreCli},
                    util::ToI64,
                    ChannelIdent};
-use habitat_launcher_client::LauncherCli;
-use habitat_sup_protocol::{self};
+use habitat_launcher_client::{LauncherCli,
+                              LauncherFeature};
+use habitat_sup_protocol::{self,
+                           ctl::RingStatus};
+use notify::RecursiveMode;
 use parking_lot::{Mutex,
                   RwLock};
 use prometheus::{HistogramVec,
                  IntGauge};
-use rustls::{internal::pemfile,
-             AllowAnyAuthenticatedClient,
-             Certificate,
-             NoClientAuth,
-             PrivateKey,
-             RootCertStore,
-             ServerConfig};
 use std::{collections::{HashMap,
                         HashSet},
-          ffi::OsStr,
+          ffi::{OsStr,
+                OsString},
           fs::{self,
                File},
-          io::{BufReader,
-               Read,
-               Write},
+          io::{self,
+               Read},
           iter::{FromIterator,
                  IntoIterator},
           net::{IpAddr,
@@ -124,8 +176,9 @@ use std::{collections::{HashMap,
 use winapi::{shared::minwindef::PDWORD,
              um::processthreadsapi};
 
-const MEMBER_ID_FILE: &str = "MEMBER_ID";
+pub const MEMBER_ID_FILE: &str = "MEMBER_ID";
 pub const PROC_LOCK_FILE: &str = "LOCK";
+const DENYLIST_FILE: &str = "denylist";
 
 static LOGKEY: &str = "MR";
 
@@ -201,6 +254,10 @@ enum ShutdownMode {
     /// When the Supervisor has been manually departed from the
     /// Habitat network. All services should come down, as well.
     Departed,
+    /// When the Supervisor departed itself with `hab sup depart --self
+    /// --stop-services`. All services come down, and the Supervisor
+    /// refuses to start again until `hab sup rejoin` is run.
+    DepartedSelf,
     /// A Supervisor is updating itself, or is otherwise simply
     /// restarting. Services _do not_ get shut down.
     Restarting,
@@ -251,6 +308,9 @@ impl FsCfg {
                 proc_lock_file: sup_root.join(PROC_LOCK_FILE),
                 sup_root }
     }
+
+    /// The directory the data of unloaded services is archived to
+    fn unloaded_data_path(&self) -> PathBuf { self.data_path.join("unloaded") }
 }
 
 /// Configuration parameters that control the behaviour of restarts for services
@@ -288,35 +348,80 @@ impl Default for ServiceRestartConfig {
 #[derive(Clone, Debug, Derivative)]
 #[derivative(PartialEq)]
 pub struct ManagerConfig {
-    pub auto_update:                bool,
-    pub auto_update_period:         Duration,
-    pub service_update_period:      Duration,
-    pub service_restart_config:     ServiceRestartConfig,
-    pub custom_state_path:          Option<PathBuf>,
-    pub key_cache:                  KeyCache,
-    pub update_url:                 String,
-    pub update_channel:             ChannelIdent,
-    pub gossip_listen:              GossipListenAddr,
-    pub ctl_listen:                 ListenCtlAddr,
-    pub ctl_server_certificates:    Option<Vec<Certificate>>,
-    pub ctl_server_key:             Option<PrivateKey>,
+    pub auto_update: bool,
+    pub auto_update_period: Duration,
+    pub service_update_period: Duration,
+    pub service_restart_config: ServiceRestartConfig,
+    pub custom_state_path: Option<PathBuf>,
+    pub key_cache: KeyCache,
+    pub update_url: String,
+    pub update_channel: ChannelIdent,
+    pub gossip_listen: GossipListenAddr,
+    pub ctl_listen: ListenCtlAddr,
+    pub ctl_server_certificates: Option<CertificateChainCli>,
+    pub ctl_server_key: Option<PrivateKeyCli>,
     #[derivative(PartialEq = "ignore")]
-    pub ctl_client_ca_certificates: Option<RootCertStore>,
-    pub http_listen:                HttpListenAddr,
-    pub http_disable:               bool,
-    pub gossip_peers:               Vec<SocketAddr>,
-    pub gossip_permanent:           bool,
-    pub ring_key:                   Option<RingKey>,
-    pub organization:               Option<String>,
-    pub watch_peer_file:            Option<String>,
-    pub tls_config:                 Option<TLSConfig>,
-    pub feature_flags:              FeatureFlag,
-    pub event_stream_config:        Option<EventStreamConfig>,
+    pub ctl_client_ca_certificates: Option<RootCertificateStoreCli>,
+    /// The address to also serve the control gateway on over gRPC
+    pub ctl_grpc_listen: Option<SocketAddr>,
+    pub http_listen: HttpListenAddr,
+    pub http_disable: bool,
+    pub gossip_peers: Vec<SocketAddr>,
+    pub gossip_permanent: bool,
+    /// The address to advertise for gossip in place of the address it is received from
+    pub gossip_advertise: Option<GossipAdvertiseAddr>,
+    /// The STUN server used to detect the address to advertise for gossip
+    pub gossip_stun_server: Option<String>,
+    /// The failure domain this Supervisor runs in
+    pub sys_zone: Option<String>,
+    /// Labels describing this Supervisor, used to select the services of the fleet manifest
+    /// loaded on it
+    pub sys_labels: HashMap<String, String>,
+    /// Reconcile loaded services to fleet manifests signed by this origin
+    pub fleet_manifest_origin: Option<Origin>,
+    pub ring_key: Option<RingKey>,
+    /// Secure the ring with mutual TLS, using these certificates, instead of a ring key
+    pub ring_tls: Option<RingTlsConfig>,
+    pub organization: Option<String>,
+    pub watch_peer_file: Option<String>,
+    /// Join the ring through the endpoints of this Kubernetes Service
+    pub peer_kubernetes_service: Option<KubernetesService>,
+    /// Join the ring through the EC2 instances matching this filter
+    pub peer_ec2: Option<Ec2PeerFilter>,
+    /// Advertise this Supervisor and discover its peers on the local network over mDNS
+    pub peer_mdns: bool,
+    /// Join the ring through the healthy instances of a service in the Consul catalog
+    pub peer_consul: Option<ConsulPeerConfig>,
+    pub tls_config: Option<TLSConfig>,
+    pub feature_flags: FeatureFlag,
+    pub event_stream_config: Option<EventStreamConfig>,
     /// If this field is `Some`, keep the indicated number of latest packages and uninstall all
     /// others during service start. If this field is `None`, automatic package cleanup is
     /// disabled.
-    pub keep_latest_packages:       Option<usize>,
-    pub sys_ip:                     IpAddr,
+    pub keep_latest_packages: Option<usize>,
+    /// The maximum combined rate of artifact downloads in bytes per second
+    pub download_rate_limit: Option<u64>,
+    /// Periodically write a JSON snapshot of the Supervisor's services and census to a file
+    pub state_snapshot: Option<StateSnapshotConfig>,
+    /// Periodically download the current releases of the packages of a file into the artifact
+    /// cache
+    pub prefetch: Option<PrefetchConfig>,
+    /// Obtain and renew the TLS certificates services request from an ACME certificate authority
+    pub acme: Option<AcmeConfig>,
+    /// Stream events to the extension at this path, and take commands back from it
+    pub extension: Option<PathBuf>,
+    /// Derive the member ID from this identity of the host instead of generating a random one
+    pub member_id_source: Option<MemberIdSource>,
+    /// Refuse gossip from the members these entries match, in addition to the ones denied with
+    /// `hab sup deny`
+    pub denylist: Vec<DenylistEntry>,
+    /// The timing of the SWIM failure detector
+    pub swim_timing: Timing,
+    /// How long the rumors of members gone from the ring are kept, if they expire at all
+    pub rumor_ttl: Option<Duration>,
+    /// Only observe the ring, never running services or originating rumors about them
+    pub observer: bool,
+    pub sys_ip: IpAddr,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -337,10 +442,26 @@ impl ManagerConfig {
             .join(ServiceSpec::ident_file(ident))
     }
 
+    /// The file the members refused gossip from are persisted to
+    fn denylist_path(&self) -> PathBuf { self.sup_root().join("data").join(DENYLIST_FILE) }
+
+    /// The file recording that the Supervisor departed itself from the ring
+    fn tombstone_path(&self) -> PathBuf { Tombstone::path(&self.sup_root().join("data")) }
+
     pub fn save_spec_for(&self, spec: &ServiceSpec) -> Result<()> {
         spec.to_file(self.spec_path_for(&spec.ident))
     }
 
+    /// Write `spec` without replacing the spec on disk yet (see `ServiceSpec::stage_file`)
+    pub fn stage_spec_for(&self, spec: &ServiceSpec) -> Result<StagedChecksummedWrite> {
+        spec.stage_file(&self.spec_path_for(&spec.ident))
+    }
+
+    pub fn remove_spec_for(&self, ident: &PackageIdent) -> Result<()> {
+        let path = self.spec_path_for(ident);
+        checksummed_remove(&path).map_err(|err| Error::ServiceSpecFileIO(path, err))
+    }
+
     /// Given a `PackageIdent`, return current spec if it exists.
     pub fn spec_for_ident(&self, ident: &PackageIdent) -> Option<ServiceSpec> {
         let spec_file = self.spec_path_for(ident);
@@ -422,13 +543,41 @@ impl ReconciliationFlag {
 pub struct ManagerState {
     /// The configuration used to instantiate this Manager instance
     cfg:            ManagerConfig,
+    /// The settings which can be changed by reloading the Supervisor's configuration
+    reloadable_cfg: Mutex<ReloadableConfig>,
+    /// The arguments of `hab sup run` the Supervisor was started with, which are parsed again,
+    /// along with its config files, when its configuration is reloaded
+    run_args:       Vec<OsString>,
     services:       Arc<sync::ManagerServices>,
+    /// Collects the identifiers of all services that are currently
+    /// doing something asynchronously (like shutting down, or running
+    /// a lifecycle hook). We want to know which to ignore if changes
+    /// in their spec files are detected while they're asynchronously
+    /// doing something else. That will prevent us from getting into
+    /// weird states if spec files change in the middle of us doing
+    /// something else.
+    // Currently, this is just going to be things that are shutting
+    // down, but as more operations become asynchronous, we'll end up
+    // keeping track of services doing other operations as well. At
+    // that point, we might need / want to change from a HashSet to
+    // something else (maybe a HashMap?) in order to cleanly manage
+    // the different operations.
+    busy_services: Arc<Mutex<HashSet<PackageIdent>>>,
     gateway_state:  Arc<sync::GatewayState>,
     should_restart: AtomicBool,
+    /// Held while a spec is checked for being at the revision a CtlGateway request expects and
+    /// written as the next revision, so that no other request, nor the fleet manifest, can change
+    /// it in between
+    spec_writes:    Mutex<()>,
+    /// Held while the denylist is updated and written to disk, so that the file always ends up
+    /// with the result of the last update
+    deny_writes:    Mutex<()>,
+    butterfly:      habitat_butterfly::Server,
 }
 
 pub(crate) mod sync {
     use super::*;
+    use habitat_butterfly::rumor::election::LeaderAffinity;
     use habitat_common::sync::{Lock,
                                ReadGuard,
                                WriteGuard};
@@ -444,8 +593,12 @@ pub(crate) mod sync {
 
         pub fn services_data(&self) -> &str { &self.0.services_data }
 
-        pub fn health_of(&self, service_group: &ServiceGroup) -> Option<HealthCheckResult> {
-            self.0.health_check_data.get(service_group).copied()
+        pub fn graph_data(&self) -> &str { &self.0.graph_data }
+
+        pub fn ring_status(&self) -> &RingStatus { &self.0.ring_status }
+
+        pub fn health_of(&self, service_group: &ServiceGroup) -> Option<&HealthCheckData> {
+            self.0.health_check_data.get(service_group)
         }
     }
 
@@ -460,11 +613,15 @@ pub(crate) mod sync {
 
         pub fn set_services_data(&mut self, new_data: String) { self.0.services_data = new_data }
 
+        pub fn set_graph_data(&mut self, new_data: String) { self.0.graph_data = new_data }
+
+        pub fn set_ring_status(&mut self, new_data: RingStatus) { self.0.ring_status = new_data }
+
         pub fn remove(&mut self, service_group: &ServiceGroup) {
             self.0.health_check_data.remove(service_group);
         }
 
-        pub fn set_health_of(&mut self, service_group: ServiceGroup, value: HealthCheckResult) {
+        pub fn set_health_of(&mut self, service_group: ServiceGroup, value: HealthCheckData) {
             self.0.health_check_data.insert(service_group, value);
         }
     }
@@ -494,9 +651,13 @@ pub(crate) mod sync {
         butterfly_data:    String,
         /// JSON returned by the /services endpoint
         services_data:     String,
+        /// JSON returned by the /services/graph endpoint
+        graph_data:        String,
         /// Data returned by /services/<SERVICE_NAME>/<GROUP_NAME>/health
         /// endpoint
-        health_check_data: HashMap<ServiceGroup, HealthCheckResult>,
+        health_check_data: HashMap<ServiceGroup, HealthCheckData>,
+        /// Summary of the ring returned to `hab ring status`
+        ring_status:       RingStatus,
     }
 
     type ManagerServicesInner = HashMap<PackageIdent, PersistentServiceWrapper>;
@@ -596,6 +757,22 @@ pub(crate) mod sync {
                 .and_then(Service::suitability)
                 .unwrap_or_else(u64::min_value)
         }
+
+        /// # Locking (see locking.md)
+        /// * `ManagerServices::inner` (read)
+        fn leader_affinity_for_msr(&self,
+                                   service_group: &str,
+                                   round_trip: Option<Duration>)
+                                   -> LeaderAffinity {
+            self.lock_msr()
+                .iter()
+                .find_map(|(_, svc_state)| {
+                    svc_state.service()
+                             .filter(|svc| svc.service_group.as_ref() == service_group)
+                })
+                .map(|svc| svc.leader_affinity(round_trip))
+                .unwrap_or_default()
+        }
     }
 }
 
@@ -606,8 +783,12 @@ pub struct Manager {
     fs_cfg:              Arc<FsCfg>,
     launcher:            LauncherCli,
     service_updater:     Arc<Mutex<ServiceUpdater>>,
-    peer_watcher:        Option<PeerWatcher>,
+    peer_source:         Option<Box<dyn PeerSource>>,
     spec_watcher:        SpecWatcher,
+    template_watcher:    TemplateWatcher,
+    fleet_manifest:      Option<FleetManifestReconciler>,
+    state_snapshot:      Option<StateSnapshotWriter>,
+    acme:                Option<AcmeClient>,
     // This Arc<RwLock<>> business is a potentially temporary
     // change. Right now, in order to asynchronously shut down
     // services, we need to be able to have a safe reference to this
@@ -618,6 +799,10 @@ pub struct Manager {
     // other threads (e.g., maybe we subscribe to messages to change
     // the watcher)
     user_config_watcher: UserConfigWatcher,
+    /// Watches the `environment.toml` and `.env` files beside each service's `user.toml`
+    environment_watcher: UserConfigWatcher,
+    /// Whether the clocks of the ring were last found further apart than the threshold
+    clock_skew_exceeded: bool,
     spec_dir:            SpecDir,
     organization:        Option<String>,
     self_updater:        Option<SelfUpdater>,
@@ -633,20 +818,6 @@ pub struct Manager {
     /// Feel free to refactor to something different!
     service_states:      HashMap<PackageIdent, SystemTime>,
 
-    /// Collects the identifiers of all services that are currently
-    /// doing something asynchronously (like shutting down, or running
-    /// a lifecycle hook). We want to know which to ignore if changes
-    /// in their spec files are detected while they're asynchronously
-    /// doing something else. That will prevent us from getting into
-    /// weird states if spec files change in the middle of us doing
-    /// something else.
-    // Currently, this is just going to be things that are shutting
-    // down, but as more operations become asynchronous, we'll end up
-    // keeping track of services doing other operations as well. At
-    // that point, we might need / want to change from a HashSet to
-    // something else (maybe a HashMap?) in order to cleanly manage
-    // the different operations.
-    busy_services:                Arc<Mutex<HashSet<PackageIdent>>>,
     services_need_reconciliation: ReconciliationFlag,
 
     feature_flags: FeatureFlag,
@@ -666,14 +837,21 @@ impl Manager {
     ///
     /// # Locking (see locking.md)
     /// * `MemberList::initial_members` (write)
-    pub async fn load_imlw(cfg: ManagerConfig, launcher: LauncherCli) -> Result<Manager> {
+    pub async fn load_imlw(cfg: ManagerConfig,
+                           run_args: Vec<OsString>,
+                           launcher: LauncherCli)
+                           -> Result<Manager> {
         let state_path = cfg.sup_root();
         let fs_cfg = FsCfg::new(state_path);
         Self::create_state_path_dirs(&fs_cfg)?;
         // The lock file exists within the state directory, so we have to create
         // it first!
         let lock_file = LockFile::acquire()?;
+        if let Some(tombstone) = Tombstone::read(&Tombstone::path(&fs_cfg.data_path))? {
+            return Err(Error::DepartedSelf(tombstone.member_id));
+        }
         Self::clean_dirty_state(&fs_cfg)?;
+        data_retention::prune_archives(&fs_cfg.unloaded_data_path());
         Self::new_imlw(cfg, fs_cfg, lock_file, launcher).await
     }
 
@@ -720,18 +898,58 @@ impl Manager {
                                cfg.ctl_listen,
                                cfg.http_listen,
                                cfg.sys_ip);
-        let member = Self::load_member(&mut sys, &fs_cfg)?;
+        sys.zone = cfg.sys_zone.clone();
+        sys.labels = cfg.sys_labels.clone();
+        let derived_member_id = match &cfg.member_id_source {
+            Some(source) => Some(member_id::derive(source).await?),
+            None => None,
+        };
+        let (mut member, replaced_member_id) =
+            Self::load_member(&mut sys, &fs_cfg, derived_member_id)?;
+        Self::set_advertised_address(&cfg, &mut member);
+        member.zone = cfg.sys_zone.clone();
+        member.version = Some(VERSION.to_string());
         let services = Arc::default();
         let suitability_lookup = Arc::clone(&services) as Arc<dyn Suitability>;
 
-        let server = habitat_butterfly::Server::new(sys.gossip_listen(),
-                                                    sys.gossip_listen(),
-                                                    member,
-                                                    cfg.ring_key,
-                                                    None,
-                                                    Some(&fs_cfg.data_path),
-                                                    suitability_lookup)?;
+        let mut server = habitat_butterfly::Server::new(sys.gossip_listen(),
+                                                        sys.gossip_listen(),
+                                                        member,
+                                                        cfg.ring_key,
+                                                        cfg.organization.clone(),
+                                                        None,
+                                                        Some(&fs_cfg.data_path),
+                                                        suitability_lookup)?;
+        if let Some(config) = &cfg.ring_tls {
+            server.use_tls(RingTls::new(config)?);
+        }
+        if let Some(ttl) = cfg.rumor_ttl {
+            server.expire_rumors_after(ttl);
+        }
+        if cfg.observer {
+            outputln!("Observing the ring; services will not be run");
+            server.observe_only();
+        }
+        let denylist_path = cfg_static.denylist_path();
+        for entry in denylist::read(&denylist_path)?.into_iter()
+                                                    .chain(cfg.denylist.iter().cloned())
+        {
+            server.add_to_denylist_sdlw(entry);
+        }
+        denylist::write(&denylist_path, &server.denylist_sdlr())?;
+        if let Some(ring_key) = &cfg_static.ring_key {
+            ring_key_watcher::load(&server,
+                                   &cfg_static.key_cache,
+                                   ring_key.named_revision().name());
+        }
         outputln!("Supervisor Member-ID {}", sys.member_id);
+        if let Some(replaced_member_id) = replaced_member_id {
+            // The member this host ran as before its member-id was derived would otherwise be
+            // left in the ring until someone departs it
+            outputln!("Departing member {}, which this Supervisor ran as before",
+                      replaced_member_id);
+            server.insert_departure_rsw_mlw_rhw(Departure::new(&replaced_member_id));
+        }
         for peer_addr in &cfg.gossip_peers {
             let peer = Member { address: format!("{}", peer_addr.ip()),
                                 swim_port: peer_addr.port(),
@@ -740,17 +958,35 @@ impl Manager {
             server.member_list.add_initial_member_imlw(peer);
         }
 
-        let peer_watcher = if let Some(path) = cfg.watch_peer_file {
-            Some(PeerWatcher::run(path)?)
-        } else {
-            None
-        };
+        let peer_source: Option<Box<dyn PeerSource>> =
+            match (cfg.watch_peer_file, cfg.peer_kubernetes_service, cfg.peer_ec2, cfg.peer_consul)
+            {
+                (Some(path), ..) => Some(Box::new(PeerWatcher::run(path)?)),
+                (None, Some(service), ..) => Some(Box::new(KubernetesPeerWatcher::run(service)?)),
+                (None, None, Some(filter), _) => {
+                    let gossip_port = cfg.gossip_listen.port();
+                    Some(Box::new(Ec2PeerSource::run(filter, gossip_port).await?))
+                }
+                (None, None, None, Some(consul)) => Some(Box::new(ConsulPeerSource::run(consul)?)),
+                (None, None, None, None) if cfg.peer_mdns => {
+                    let ring = cfg_static.ring_key
+                                         .as_ref()
+                                         .map(|key| key.named_revision().name().as_str());
+                    Some(Box::new(MdnsPeerSource::run(&sys, ring)?))
+                }
+                (None, None, None, None) => None,
+            };
 
         let spec_dir = SpecDir::new(&fs_cfg.specs_path)?;
         spec_dir.migrate_specs();
+        if cfg.observer && !spec_dir.specs().is_empty() {
+            outputln!("Not running the services loaded on this Supervisor, as it is an observer");
+        }
 
-        let spec_watcher = SpecWatcher::run(&spec_dir)?;
+        let mut spec_watcher = SpecWatcher::run(&spec_dir)?;
+        spec_watcher.watch_includes(spec_dir.include_dirs());
         trace!("Created SpecWatcher");
+        let template_watcher = TemplateWatcher::run()?;
 
         if let Some(config) = cfg.event_stream_config {
             // Collect the FQDN of the running machine
@@ -763,10 +999,27 @@ impl Manager {
         let pid_source = ServicePidSource::determine_source(&launcher);
 
         let census_ring = Arc::new(RwLock::new(CensusRing::new(sys.member_id.clone())));
+        let fleet_manifest =
+            cfg.fleet_manifest_origin
+               .clone()
+               .map(|origin| FleetManifestReconciler::new(origin, &fs_cfg.data_path));
+        let acme = match cfg.acme {
+            Some(config) => Some(AcmeClient::run(config, &fs_cfg.data_path)?),
+            None => None,
+        };
+        let reloadable_cfg = ReloadableConfig::new(&cfg_static);
+        reloadable_cfg.apply_download_rate_limit();
+        let reloadable_cfg = Mutex::new(reloadable_cfg);
         Ok(Manager { state: Arc::new(ManagerState { cfg: cfg_static,
+                                                    reloadable_cfg,
+                                                    run_args,
                                                     services,
+                                                    busy_services: Arc::default(),
                                                     gateway_state: Arc::default(),
-                                                    should_restart: AtomicBool::default() }),
+                                                    should_restart: AtomicBool::default(),
+                                                    spec_writes: Mutex::default(),
+                                                    deny_writes: Mutex::default(),
+                                                    butterfly: server.clone() }),
                      self_updater,
                      service_updater:
                          Arc::new(Mutex::new(ServiceUpdater::new(server.clone(),
@@ -775,25 +1028,61 @@ impl Manager {
                      census_ring,
                      butterfly: server,
                      launcher,
-                     peer_watcher,
+                     peer_source,
                      spec_watcher,
+                     template_watcher,
+                     fleet_manifest,
+                     state_snapshot: cfg.state_snapshot.map(StateSnapshotWriter::new),
+                     acme,
                      user_config_watcher: UserConfigWatcher::new(),
+                     environment_watcher: UserConfigWatcher::environment(),
+                     clock_skew_exceeded: false,
                      spec_dir,
                      fs_cfg: Arc::new(fs_cfg),
                      organization: cfg.organization,
                      service_states: HashMap::new(),
                      sys: Arc::new(sys),
                      http_disable: cfg.http_disable,
-                     busy_services: Arc::default(),
                      services_need_reconciliation: ReconciliationFlag::new(false),
                      feature_flags: cfg.feature_flags,
                      pid_source,
                      _lock_file: lock_file })
     }
 
+    /// Set the address the initial Butterfly Member advertises to the rest of the ring, if one
+    /// was given with `--gossip-advertise-addr` or can be detected with a STUN server. Otherwise
+    /// peers use the address our messages are received from.
+    fn set_advertised_address(cfg: &ManagerConfig, member: &mut Member) {
+        let listen_port = cfg.gossip_listen.port();
+        let (swim_addr, gossip_port) = if let Some(addr) = &cfg.gossip_advertise {
+            let addr = addr.socket_addr(listen_port);
+            (addr, addr.port())
+        } else if let Some(server) = &cfg.gossip_stun_server {
+            match stun::mapped_address(*cfg.gossip_listen, server) {
+                // STUN only detects the UDP (SWIM) mapping; the TCP gossip port must be
+                // forwarded unchanged.
+                Ok(addr) => (addr, listen_port),
+                Err(e) => {
+                    warn!("{}; advertising the address gossip is received from instead",
+                          e);
+                    return;
+                }
+            }
+        } else {
+            return;
+        };
+        outputln!("Advertising gossip address {} (gossip port {})",
+                  swim_addr,
+                  gossip_port);
+        member.address = swim_addr.ip().to_string();
+        member.swim_port = swim_addr.port();
+        member.gossip_port = gossip_port;
+    }
+
     /// Load the initial Butterly Member which is used in initializing the Butterfly server. This
     /// will load the member-id for the initial Member from disk if a previous manager has been
-    /// run.
+    /// run, unless a member-id derived from the host's identity is given, which replaces it. The
+    /// member-id it replaces, if different, is returned alongside, so that it can be departed.
     ///
     /// The mutable ref to `Sys` will be configured with Butterfly Member details and will also
     /// populate the initial Member.
@@ -802,33 +1091,47 @@ impl Manager {
     // in there, so splitting the initialization is needlessly
     // confusing. It's also blurs the lines between the manager and
     // Butterfly.
-    fn load_member(sys: &mut Sys, fs_cfg: &FsCfg) -> Result<Member> {
+    fn load_member(sys: &mut Sys,
+                   fs_cfg: &FsCfg,
+                   derived_member_id: Option<String>)
+                   -> Result<(Member, Option<String>)> {
         let mut member = Member::default();
-        match File::open(&fs_cfg.member_id_file) {
-            Ok(mut file) => {
-                let mut member_id = String::new();
-                file.read_to_string(&mut member_id).map_err(|e| {
-                                                        Error::BadDataFile(fs_cfg.member_id_file
-                                                                                 .clone(),
-                                                                           e)
-                                                    })?;
-                member.id = member_id;
-            }
-            Err(_) => {
-                match File::create(&fs_cfg.member_id_file) {
-                    Ok(mut file) => {
-                        file.write(member.id.as_bytes())
-                            .map_err(|e| Error::BadDataFile(fs_cfg.member_id_file.clone(), e))?;
-                    }
-                    Err(err) => {
-                        return Err(Error::BadDataFile(fs_cfg.member_id_file.clone(), err));
-                    }
+        let mut replaced_member_id = None;
+        let bad_data_file = |e| Error::BadDataFile(fs_cfg.member_id_file.clone(), e);
+        if let Some(member_id) = derived_member_id {
+            let previous_id = Self::read_member_id(&fs_cfg.member_id_file).ok();
+            replaced_member_id = previous_id.filter(|id| !id.is_empty() && *id != member_id);
+            // The file always holds the member-id in use, whichever way it was obtained
+            member.id = member_id;
+            checksummed_write(&fs_cfg.member_id_file, &member.id).map_err(bad_data_file)?;
+        } else {
+            match Self::read_member_id(&fs_cfg.member_id_file) {
+                Ok(member_id) => member.id = member_id,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    checksummed_write(&fs_cfg.member_id_file, &member.id).map_err(bad_data_file)?;
                 }
+                Err(e) => return Err(bad_data_file(e)),
             }
         }
         sys.member_id = member.id.to_string();
         member.persistent = sys.permanent;
-        Ok(member)
+        Ok((member, replaced_member_id))
+    }
+
+    /// Read the member ID file, restoring the copy kept from before its last write if it is
+    /// corrupt
+    fn read_member_id(path: &Path) -> io::Result<String> {
+        let data = match checksummed_read(path) {
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                let data = checksummed_recover(path).map_err(|_| e)?;
+                warn!("Member ID file {} was corrupt; restored the last good copy",
+                      path.display());
+                data
+            }
+            result => result?,
+        };
+        String::from_utf8(data).map(|id| id.trim().to_string())
+                               .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
 
     fn clean_dirty_state(fs_cfg: &FsCfg) -> Result<()> {
@@ -869,7 +1172,8 @@ impl Manager {
     }
 
     async fn maybe_uninstall_old_packages(&self, ident: &PackageIdent) {
-        if let Some(number_latest_to_keep) = self.state.cfg.keep_latest_packages {
+        let keep_latest_packages = self.state.reloadable_cfg.lock().keep_latest_packages;
+        if let Some(number_latest_to_keep) = keep_latest_packages {
             match pkg::uninstall_all_but_latest(ident, number_latest_to_keep).await {
                 Ok(uninstalled) => {
                     info!("Uninstalled '{}' '{}' packages keeping the '{}' latest",
@@ -955,17 +1259,30 @@ impl Manager {
             return;
         }
 
+        if let Err(e) = self.environment_watcher.add(&service) {
+            outputln!("Unable to start the environment file watcher for {}: {}",
+                      service.spec_ident(),
+                      e);
+            return;
+        }
+
         self.maybe_uninstall_old_packages(&ident).await;
 
         self.service_updater.lock().register(&service);
 
+        service.record_update_finished();
         event::service_started(&service);
 
+        let service_restart_config = self.state
+                                         .reloadable_cfg
+                                         .lock()
+                                         .service_restart_config
+                                         .clone();
         self.state
             .services
             .lock_msw()
             .insert(service.spec_ident(),
-                    PersistentServiceWrapper::new(service, &self.state.cfg.service_restart_config))
+                    PersistentServiceWrapper::new(service, &service_restart_config))
     }
 
     // If we ever need to modify this function, it would be an excellent opportunity to
@@ -1019,7 +1336,7 @@ impl Manager {
         outputln!("Starting gossip-listener on {}",
                   self.butterfly.gossip_addr());
         self.butterfly
-            .start_rsw_mlw_smw_rhw_msr(&Timing::default())?;
+            .start_rsw_mlw_smw_rhw_msr(&self.state.cfg.swim_timing)?;
         debug!("gossip-listener started");
 
         // Update the census state from the butterfly service rumours.
@@ -1042,24 +1359,53 @@ impl Manager {
         // Ensure that the updated census state is saved to the gateway
         self.persist_state_rsr_mlr_gsw_msr().await;
         let http_listen_addr = self.sys.http_listen();
-        let ctl_gateway_server =
-            CtlGatewayServer { listen_addr: self.sys.ctl_listen(),
-                               secret_key: ctl_gateway::readgen_secret_key(&self.fs_cfg
-                                                                                .sup_root)?,
-                               mgr_sender,
-                               server_certificates: self.state
-                                                        .cfg
-                                                        .ctl_server_certificates
-                                                        .clone(),
-                               server_key: self.state.cfg.ctl_server_key.clone(),
-                               client_certificates: self.state
-                                                        .cfg
-                                                        .ctl_client_ca_certificates
-                                                        .clone() };
+        let ctl_secret_key = ctl_gateway::readgen_secret_key(&self.fs_cfg.sup_root)?;
+        // The gateways whose TLS files are watched, so that rotated certificates are served without
+        // a restart
+        let mut tls_gateways = Vec::new();
+        let ctl_tls = match &self.state.cfg.ctl_server_key {
+            Some(key) => {
+                let cfg = &self.state.cfg;
+                let files = TlsFiles::Ctl { certificates: cfg.ctl_server_certificates.clone(),
+                                            key:          key.clone(),
+                                            client_ca:    cfg.ctl_client_ca_certificates.clone(), };
+                let tls = ReloadableTls::new("ctl-gateway", files)?;
+                tls_gateways.push(Arc::clone(&tls));
+                Some(tls)
+            }
+            None => None,
+        };
+        if let Some(listen_addr) = self.state.cfg.ctl_grpc_listen {
+            let ctl_gateway_grpc_server =
+                CtlGatewayGrpcServer { listen_addr,
+                                       secret_key: ctl_secret_key.clone(),
+                                       mgr_sender: mgr_sender.clone(),
+                                       tls_config: ctl_tls.as_ref()
+                                                          .map(ReloadableTls::server_config) };
+            outputln!("Starting ctl-gateway gRPC on {}", listen_addr);
+            tokio::spawn(ctl_gateway_grpc_server.run());
+        }
+        let ctl_tls_config = ctl_tls.as_ref().map(|tls| Arc::new(tls.server_config()));
+        let ctl_gateway_server = CtlGatewayServer { listen_addr: self.sys.ctl_listen(),
+                                                    secret_key: ctl_secret_key,
+                                                    mgr_sender,
+                                                    tls_config: ctl_tls_config };
         outputln!("Starting ctl-gateway on {}", ctl_gateway_server.listen_addr);
         tokio::spawn(ctl_gateway_server.run());
         debug!("ctl-gateway started");
 
+        if let Some(prefetch) = self.state.cfg.prefetch.clone() {
+            outputln!("Prefetching the packages of {} every {:?}",
+                      prefetch.path.display(),
+                      prefetch.interval);
+            tokio::spawn(prefetcher::run(prefetch, self.state.cfg.update_url.clone()));
+        }
+
+        if let Some(path) = self.state.cfg.extension.clone() {
+            outputln!("Starting extension {}", path.display());
+            extension::init(path);
+        }
+
         if self.http_disable {
             info!("http-gateway disabled");
         } else {
@@ -1069,10 +1415,11 @@ impl Manager {
 
             let tls_server_config = match &self.state.cfg.tls_config {
                 Some(c) => {
-                    match tls_config(c) {
-                        Ok(c) => Some(c),
-                        Err(e) => return Err(e),
-                    }
+                    let tls = ReloadableTls::new("http-gateway", TlsFiles::Http(c.clone()))?;
+                    tls_gateways.push(Arc::clone(&tls));
+                    let mut server_config = tls.server_config();
+                    server_config.ignore_client_order = true;
+                    Some(server_config)
                 }
                 None => None,
             };
@@ -1087,6 +1434,7 @@ impl Manager {
             http_gateway::Server::run(http_listen_addr,
                                       tls_server_config,
                                       self.state.gateway_state.clone(),
+                                      self.butterfly.clone(),
                                       http_gateway::GatewayAuthenticationToken::configured_value(),
                                       self.feature_flags,
                                       pair.clone());
@@ -1136,6 +1484,16 @@ impl Manager {
             debug!("http-gateway started");
         }
 
+        if !tls_gateways.is_empty() {
+            tokio::spawn(tls_watcher::run(tls_gateways));
+        }
+
+        if let Some(ring_key) = &self.state.cfg.ring_key {
+            tokio::spawn(ring_key_watcher::run(self.butterfly.clone(),
+                                               self.state.cfg.key_cache.clone(),
+                                               ring_key.named_revision().name().clone()));
+        }
+
         // Enter the main Supervisor loop. When we break out, it'll be
         // because we've been instructed to shutdown. The value we
         // break out with governs exactly how we shut down.
@@ -1143,6 +1501,11 @@ impl Manager {
         // TODO (CM): Investigate the appropriateness of capturing any
         // errors or panics generated in this loop and performing some
         // kind of controlled shutdown.
+        systemd::notify(systemd::READY);
+        let mut watchdog = systemd::watchdog();
+        // A Launcher that takes heartbeats vouches for the Supervisor to systemd itself, for as
+        // long as they keep coming
+        let send_heartbeats = self.launcher.supports(LauncherFeature::Heartbeat);
         let shutdown_mode = loop {
             // This particular loop isn't truly divergent, but since we're in the main loop
             // if the supervisor process, and everything that comes after is expected to complete
@@ -1151,6 +1514,13 @@ impl Manager {
             // additional mark_thread_alive calls in any subsequent code which has the potential to
             // loop or wait (including futures), but we don't have that capability yet.
             liveliness_checker::mark_thread_alive().and_divergent();
+            if send_heartbeats {
+                if let Err(e) = self.launcher.heartbeat() {
+                    debug!("Unable to send heartbeat to Launcher: {}", e);
+                }
+            } else {
+                watchdog.ping_if_due(Instant::now());
+            }
 
             // time will be recorded automatically by HistogramTimer's drop implementation when
             // this var goes out of scope
@@ -1197,32 +1567,39 @@ impl Manager {
             }
 
             // TODO (CM): eventually, make this a future receiver
+            let mut departed_self = false;
             for action in action_receiver.try_iter() {
                 use SupervisorAction::*;
                 match action {
-                    StopService { mut service_spec,
+                    // The spec was saved as down by the request
+                    StopService { service_spec,
                                   shutdown_input, } => {
-                        service_spec.desired_state = DesiredState::Down;
-                        if let Err(err) = self.state.cfg.save_spec_for(&service_spec) {
-                            warn!("Tried to stop '{}', but couldn't update the spec: {:?}",
-                                  service_spec.ident, err);
-                        }
                         self.stop_service_gsw_msw(&service_spec.ident, &shutdown_input);
                     }
                     UnloadService { service_spec,
-                                    shutdown_input, } => {
+                                    shutdown_input,
+                                    data_retention, } => {
                         self.remove_spec_file(&service_spec.ident).ok();
-                        self.stop_service_gsw_msw(&service_spec.ident, &shutdown_input);
+                        self.unload_service_gsw_msw(&service_spec.ident,
+                                                    &shutdown_input,
+                                                    data_retention);
                     }
-                    UpdateService { service_spec } => {
-                        trace!("Received UpdateService action for {}", service_spec.ident);
-                        if let Err(err) = self.state.cfg.save_spec_for(&service_spec) {
-                            warn!("Tried to update '{}', but couldn't write the spec: {:?}",
-                                  service_spec.ident, err);
-                        }
+                    CoordinateUnload { service_spec,
+                                       id,
+                                       timeout, } => {
+                        self.coordinate_unload_rsw_mlw_rhw_msw(&service_spec.ident, id, timeout);
+                    }
+                    ReloadConfig { config } => self.apply_reloaded_config(config),
+                    DepartSelf { stop_services } => {
+                        outputln!("Departing from butterfly network as requested");
+                        self.butterfly.set_departed_mlw_smw_rhw();
+                        departed_self = stop_services;
                     }
                 }
             }
+            if departed_self {
+                break ShutdownMode::DepartedSelf;
+            }
 
             // Indicates if we need to examine our on-disk specfiles
             // in order to reconcile them with whatever we're
@@ -1232,7 +1609,11 @@ impl Manager {
             // directory, as well as whether or not we need to
             // reexamine specs after finishing some asynchronous
             // operation on a service.
-            if self.spec_watcher.has_events() || self.services_need_reconciliation.is_set() {
+            //
+            // An observer never runs services, whatever its spec files say.
+            if (self.spec_watcher.has_events() || self.services_need_reconciliation.is_set())
+               && !self.state.cfg.observer
+            {
                 // This call *must* come first. If some other future
                 // happens to complete before we get done spawning our
                 // current batch of futures, it could set the flag to
@@ -1246,11 +1627,16 @@ impl Manager {
                 // event in the specs directory is registered, or
                 // another service finishes shutting down).
                 self.services_need_reconciliation.toggle_if_set();
+                self.spec_watcher
+                    .watch_includes(self.spec_dir.include_dirs());
                 self.maybe_spawn_service_futures_rsw_mlw_gsw_rhw_msw().await;
             }
 
             self.update_peers_from_watch_file_mlr_imlw()?;
+            self.reconcile_fleet_manifest_rsr();
             self.update_running_services_from_user_config_watcher_msw();
+            self.update_running_services_from_template_watcher_msw();
+            self.update_acme_certificates_msw();
 
             // Restart all services that need it
             self.restart_services_rsw_mlr_rhw_msw();
@@ -1266,10 +1652,25 @@ impl Manager {
                                             &self.butterfly.service_config_store,
                                             &self.butterfly.service_file_store);
 
-            if self.check_for_changed_services_msr() || self.census_ring.read().changed() {
+            if self.census_ring.read().changed() {
+                extension::census_changed(&self.census_ring.read());
+            }
+            if self.census_ring.read().scheduled_config_activated() {
+                self.check_clock_skew_for_scheduled_config();
+            }
+            let clock_skew_changed = self.check_clock_skew();
+            if self.check_for_changed_services_msr()
+               || self.census_ring.read().changed()
+               || clock_skew_changed
+            {
                 self.persist_state_rsr_mlr_gsw_msr().await;
             }
+            if let Some(state_snapshot) = &mut self.state_snapshot {
+                state_snapshot.write_if_due_gsr(&self.state.gateway_state,
+                                                self.butterfly.member_id());
+            }
 
+            let mut committed_unloads = Vec::new();
             for service_state in self.state.services.lock_msw().services() {
                 // time will be recorded automatically by HistogramTimer's drop implementation when
                 // this var goes out of scope
@@ -1282,6 +1683,17 @@ impl Manager {
                     debug!("Service ready to restart, setting reconciliation flag");
                     self.services_need_reconciliation.set()
                 }
+                if let Some(service) = service_state.service()
+                                                    .filter(|s| s.coordinated_unload_committed())
+                {
+                    committed_unloads.push(service.spec_ident());
+                }
+            }
+            // Every member of their groups drained for these services, which were gossiped as
+            // committed to being unloaded above
+            for ident in committed_unloads {
+                self.remove_spec_file(&ident).ok();
+                self.unload_service_gsw_msw(&ident, &ShutdownInput::default(), DataRetention::Keep);
             }
 
             // This is really only needed until everything is running
@@ -1317,6 +1729,11 @@ impl Manager {
         // user commands as we're trying to shut down.
         ctl_shutdown_tx.send(()).ok();
 
+        // A restarting Supervisor is replaced by the Launcher, so the unit keeps running
+        if !matches!(shutdown_mode, ShutdownMode::Restarting) {
+            systemd::notify(systemd::STOPPING);
+        }
+
         match shutdown_mode {
             ShutdownMode::Restarting => {
                 outputln!("Preparing services for Supervisor restart");
@@ -1324,7 +1741,7 @@ impl Manager {
                     service.detach()
                 }
             }
-            ShutdownMode::Normal | ShutdownMode::Departed => {
+            ShutdownMode::Normal | ShutdownMode::Departed | ShutdownMode::DepartedSelf => {
                 outputln!("Gracefully departing from butterfly network.");
                 self.butterfly.set_departed_mlw_smw_rhw();
 
@@ -1336,7 +1753,7 @@ impl Manager {
                                                     .drain_services()
                                                     .map(|service| {
                                                         self.stop_service_future_gsw(service, None,
-                                                                                     None)
+                                                                                     None, None)
                                                     }));
                 // Wait while all services are stopped
                 service_stop_futures.collect::<Vec<_>>().await;
@@ -1348,6 +1765,9 @@ impl Manager {
         match shutdown_mode {
             ShutdownMode::Normal | ShutdownMode::Restarting => Ok(()),
             ShutdownMode::Departed => Err(Error::Departed),
+            ShutdownMode::DepartedSelf => {
+                Err(Error::DepartedSelf(self.butterfly.member_id().to_string()))
+            }
         }
     }
 
@@ -1374,10 +1794,15 @@ impl Manager {
             // We need to use this has_update flag due to the borrow checker rules
             let mut has_update = false;
             if let Some(service) = service_state.service() {
-                if let Some(new_ident) = service_updater.has_update(&service.service_group) {
+                // An update waits for as long as the extension vetoes it
+                let update =
+                    service_updater.has_update(&service.service_group)
+                                   .filter(|_| !extension::update_vetoed(&service.service_group));
+                if let Some(new_ident) = update {
                     outputln!("Restarting {} with package {}", ident, new_ident);
                     has_update = true;
                     event::service_update_started(service, &new_ident);
+                    service.record_update_started(&new_ident);
                     // The supervisor always runs the latest package on disk. When we have an update
                     // ensure that the lastest package on disk is the package we updated to.
                     idents_to_restart_and_latest_desired_on_restart.push((ident.clone(),
@@ -1410,7 +1835,10 @@ impl Manager {
             // our specfile reconciliation logic to catch the fact that
             // the service needs to be restarted. At that point, this function
             // can be renamed; right now, it says exactly what it's doing.
-            tokio::spawn(self.stop_service_future_gsw(service, latest_desired_on_restart, None));
+            tokio::spawn(self.stop_service_future_gsw(service,
+                                                      latest_desired_on_restart,
+                                                      None,
+                                                      None));
         }
     }
 
@@ -1431,6 +1859,72 @@ impl Manager {
             .insert_service_rsw_mlw_rhw(service.to_rumor(incarnation));
     }
 
+    /// Start coordinating an unload of a service across its group, as the member it was requested
+    /// of.
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (write)
+    /// * `MemberList::entries` (write)
+    /// * `RumorHeat::inner` (write)
+    /// * `ManagerServices::inner` (write)
+    fn coordinate_unload_rsw_mlw_rhw_msw(&self,
+                                         ident: &PackageIdent,
+                                         id: String,
+                                         timeout: Duration) {
+        let mut services = self.state.services.lock_msw();
+        match services.get_mut(ident)
+                      .and_then(PersistentServiceWrapper::service_mut)
+        {
+            Some(service) => {
+                service.request_coordinated_unload(id, timeout);
+                self.gossip_latest_service_rumor_rsw_mlw_rhw(service);
+            }
+            None => {
+                outputln!("Not coordinating the unload of {}, as it is not running",
+                          ident)
+            }
+        }
+    }
+
+    /// Apply settings from a reloaded Supervisor configuration to the running Supervisor.
+    fn apply_reloaded_config(&mut self, config: ReloadableConfig) {
+        let current = self.state.reloadable_cfg.lock().clone();
+        for setting in current.changed_settings(&config) {
+            outputln!("Applying reloaded Supervisor setting '{}'", setting);
+        }
+        config.apply_output_settings();
+        config.apply_download_rate_limit();
+        self.service_updater
+            .lock()
+            .set_period(config.service_update_period);
+        if config.watch_peer_file != current.watch_peer_file {
+            // The previous watcher is stopped before the new one starts watching
+            self.peer_source = None;
+            self.peer_source = match &config.watch_peer_file {
+                Some(path) => {
+                    match PeerWatcher::run(path) {
+                        Ok(watcher) => Some(Box::new(watcher)),
+                        Err(err) => {
+                            outputln!("Unable to watch peer file {}: {}", path, err);
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
+        }
+        if config.event_stream_application != current.event_stream_application
+           || config.event_stream_environment != current.event_stream_environment
+           || config.event_stream_site != current.event_stream_site
+           || config.event_meta != current.event_meta
+        {
+            event::update_metadata(config.event_stream_application.clone(),
+                                   config.event_stream_environment.clone(),
+                                   config.event_stream_site.clone(),
+                                   config.event_meta.clone());
+        }
+        *self.state.reloadable_cfg.lock() = config;
+    }
+
     fn check_for_departure(&self) -> bool { self.butterfly.is_departed() }
 
     fn check_for_restart(&self) -> bool {
@@ -1477,6 +1971,48 @@ impl Manager {
         }
     }
 
+    /// Warn when the clocks of the ring drift further apart than the threshold, and when they come
+    /// back within it. Returns whether either happened.
+    fn check_clock_skew(&mut self) -> bool {
+        let skew = self.butterfly.clock_skew();
+        if skew.exceeds_threshold == self.clock_skew_exceeded {
+            return false;
+        }
+        self.clock_skew_exceeded = skew.exceeds_threshold;
+        if skew.exceeds_threshold {
+            let (member_id, offset_ms) = skew.furthest().unwrap_or_default();
+            outputln!("WARNING: The clocks of the ring are {}ms apart, over the {}ms threshold. \
+                       Member {} is {}ms off. Expirations and timeouts may misbehave until the \
+                       clocks are synchronized.",
+                      skew.spread_ms,
+                      skew.threshold_ms,
+                      member_id,
+                      offset_ms);
+        } else {
+            outputln!("The clocks of the ring are within {}ms of each other again",
+                      skew.threshold_ms);
+        }
+        event::clock_skew(&skew);
+        true
+    }
+
+    /// A scheduled configuration is applied when each member's clock reaches the time it is
+    /// scheduled for, so the members only apply it together if their clocks agree. It is refused
+    /// if they don't when it is scheduled, but they may drift apart before it is due.
+    fn check_clock_skew_for_scheduled_config(&self) {
+        let skew = self.butterfly.clock_skew();
+        if skew.exceeds_threshold {
+            let (member_id, offset_ms) = skew.furthest().unwrap_or_default();
+            outputln!("WARNING: Applied a scheduled configuration while the clocks of the ring \
+                       are {}ms apart, over the {}ms threshold. Member {} is {}ms off and will \
+                       not apply it at the same moment.",
+                      skew.spread_ms,
+                      skew.threshold_ms,
+                      member_id,
+                      offset_ms);
+        }
+    }
+
     /// # Locking (see locking.md)
     /// * `RumorStore::list` (read)
     /// * `MemberList::entries` (read)
@@ -1489,6 +2025,10 @@ impl Manager {
         self.persist_butterfly_state_rsr_mlr_gsw();
         debug!("Updating services state");
         self.persist_services_state_gsw_msr().await;
+        debug!("Updating service graph");
+        self.persist_service_graph_gsw_msr();
+        debug!("Updating ring status");
+        self.persist_ring_status_rsr_mlr_gsw();
     }
 
     /// # Locking (see locking.md)
@@ -1496,7 +2036,7 @@ impl Manager {
     fn persist_census_state_gsw(&self) {
         let census_ring = &self.census_ring.read();
         let crp = CensusRingProxy::new(census_ring);
-        let json = serde_json::to_string(&crp).expect("CensusRingProxy::serialize failure");
+        let json = redaction::to_redacted_json(&crp).expect("CensusRingProxy::serialize failure");
         self.state.gateway_state.lock_gsw().set_census_data(json);
     }
 
@@ -1506,10 +2046,19 @@ impl Manager {
     /// * `GatewayState::inner` (write)
     fn persist_butterfly_state_rsr_mlr_gsw(&self) {
         let bs = ServerProxy::new(&self.butterfly);
-        let json = serde_json::to_string(&bs).expect("ServerProxy::serialize failure");
+        let json = redaction::to_redacted_json(&bs).expect("ServerProxy::serialize failure");
         self.state.gateway_state.lock_gsw().set_butterfly_data(json);
     }
 
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    /// * `MemberList::entries` (read)
+    /// * `GatewayState::inner` (write)
+    fn persist_ring_status_rsr_mlr_gsw(&self) {
+        let status = ring_status::ring_status_rsr_mlr(&self.butterfly, &self.census_ring.read());
+        self.state.gateway_state.lock_gsw().set_ring_status(status);
+    }
+
     /// # Locking (see locking.md)
     /// * `GatewayState::inner` (write)
     /// * `ManagerServices::inner` (read)
@@ -1565,7 +2114,8 @@ impl Manager {
                     Ok(service) => {
                         watched_services.push((service,
                                                ServiceRunState::new(&self.state
-                                                                         .cfg
+                                                                         .reloadable_cfg
+                                                                         .lock()
                                                                          .service_restart_config)))
                     }
                     Err(err) => warn!("Failed to create service '{}' from spec: {:?}", ident, err),
@@ -1593,10 +2143,21 @@ impl Manager {
         services_to_render.extend(watched_service_proxies);
 
         let json =
-            serde_json::to_string(&services_to_render).expect("ServiceProxy::serialize failure");
+            redaction::to_redacted_json(&services_to_render).expect("ServiceProxy::serialize \
+                                                                     failure");
         self.state.gateway_state.lock_gsw().set_services_data(json);
     }
 
+    /// # Locking (see locking.md)
+    /// * `GatewayState::inner` (write)
+    /// * `ManagerServices::inner` (read)
+    fn persist_service_graph_gsw_msr(&self) {
+        let graph = service_graph::service_graph(self.state.services.lock_msr().running_services(),
+                                                 &self.census_ring.read());
+        let json = redaction::to_redacted_json(&graph).expect("ServiceGraph::serialize failure");
+        self.state.gateway_state.lock_gsw().set_graph_data(json);
+    }
+
     /// Check if any elections need restarting.
     ///
     /// # Locking (see locking.md)
@@ -1615,7 +2176,8 @@ impl Manager {
     fn stop_service_gsw_msw(&mut self, ident: &PackageIdent, shutdown_input: &ShutdownInput) {
         if let Some(mut service_state) = self.remove_service_from_state_msw(ident) {
             if let Some(service) = service_state.shutdown(false) {
-                let future = self.stop_service_future_gsw(service, None, Some(shutdown_input));
+                let future =
+                    self.stop_service_future_gsw(service, None, Some(shutdown_input), None);
                 tokio::spawn(future);
             }
         } else {
@@ -1624,6 +2186,34 @@ impl Manager {
         }
     }
 
+    /// Stop a service that is being unloaded, then archive or delete its state. A service that
+    /// is not running has its state cleaned up right away, without running its post-unload hook.
+    /// # Locking (see locking.md)
+    /// * `GatewayState::inner` (write)
+    /// * `ManagerServices::inner` (write)
+    fn unload_service_gsw_msw(&mut self,
+                              ident: &PackageIdent,
+                              shutdown_input: &ShutdownInput,
+                              data_retention: DataRetention) {
+        if let Some(mut service_state) = self.remove_service_from_state_msw(ident) {
+            if let Some(service) = service_state.shutdown(false) {
+                let future = self.stop_service_future_gsw(service,
+                                                          None,
+                                                          Some(shutdown_input),
+                                                          Some(data_retention));
+                tokio::spawn(future);
+            }
+        } else {
+            let svc_path = habitat_core::fs::svc_path(&ident.name);
+            let archive_dir = self.fs_cfg.unloaded_data_path();
+            if let Err(e) =
+                data_retention::apply(data_retention, &ident.name, &svc_path, &archive_dir)
+            {
+                outputln!("Failed to clean up the data of {}: {}", ident, e);
+            }
+        }
+    }
+
     /// Create a future for stopping a Service removing it from the manager. The Service is assumed
     /// to have been removed from the internal list of active services already (see, e.g.,
     /// restart_services_rsw_mlr_rhw_msw and remove_service_from_state).
@@ -1632,13 +2222,16 @@ impl Manager {
     fn stop_service_future_gsw(&self,
                                mut service: Service,
                                latest_desired_on_restart: Option<PackageIdent>,
-                               shutdown_input: Option<&ShutdownInput>)
+                               shutdown_input: Option<&ShutdownInput>,
+                               unload_data_retention: Option<DataRetention>)
                                -> impl Future<Output = ()> {
         let mut user_config_watcher = self.user_config_watcher.clone();
+        let mut environment_watcher = self.environment_watcher.clone();
         let service_updater = Arc::clone(&self.service_updater);
-        let busy_services = Arc::clone(&self.busy_services);
+        let busy_services = Arc::clone(&self.state.busy_services);
         let services_need_reconciliation = self.services_need_reconciliation.clone();
         let shutdown_config = ShutdownConfig::new(shutdown_input, &service);
+        let unloaded_data_path = self.fs_cfg.unloaded_data_path();
 
         // JW TODO: Update service rumor to remove service from
         // cluster
@@ -1647,7 +2240,11 @@ impl Manager {
         let stop_it = async move {
             service.stop_gsw(shutdown_config).await;
             event::service_stopped(&service);
+            if let Some(data_retention) = unload_data_retention {
+                service.unload(data_retention, &unloaded_data_path).await;
+            }
             user_config_watcher.remove(&service);
+            environment_watcher.remove(&service);
             service_updater.lock().remove(&service.service_group);
             // At this point the service process is stopped but the package is still loaded by the
             // Supervisor.
@@ -1686,7 +2283,7 @@ impl Manager {
 
     fn remove_spec_file(&self, ident: &PackageIdent) -> std::io::Result<()> {
         let file = self.state.cfg.spec_path_for(ident);
-        let result = fs::remove_file(&file);
+        let result = checksummed_remove(&file);
         if let Err(ref err) = result {
             warn!("Tried to remove spec file '{}' for '{}': {:?}",
                   file.display(),
@@ -1696,6 +2293,66 @@ impl Manager {
         result
     }
 
+    /// Write or remove spec files so that the services loaded from the fleet manifest match the
+    /// newest manifest gossiped to the ring. The resulting spec file events are picked up by
+    /// the usual spec reconciliation.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    fn reconcile_fleet_manifest_rsr(&mut self) {
+        let reconciler = match self.fleet_manifest.as_mut() {
+            Some(reconciler) => reconciler,
+            None => return,
+        };
+        let (version, manifest) =
+            match reconciler.newer_manifest_rsr(&self.butterfly, &self.state.cfg.key_cache) {
+                Some(newer) => newer,
+                None => return,
+            };
+        outputln!("Reconciling services to fleet manifest version {}", version);
+        let mut loaded = Vec::new();
+        for mut spec in manifest.specs_for(&self.state.cfg.sys_labels) {
+            let previously_loaded = reconciler.loaded(&spec.ident);
+            // Written like the specs of CtlGateway requests, as the revision following the one on
+            // disk, so that a spec changed in between is never taken for an unchanged one
+            let _spec_writes = self.state.spec_writes.lock();
+            let current_revision = self.state
+                                       .cfg
+                                       .spec_for_ident(&spec.ident)
+                                       .map(|s| s.revision);
+            if !previously_loaded && current_revision.is_some() {
+                outputln!("Not loading {} from the fleet manifest, a service of that name was \
+                           loaded on this Supervisor directly",
+                          spec.ident);
+                continue;
+            }
+            spec.revision = current_revision.map_or(1, |revision| revision + 1);
+            match self.state.cfg.save_spec_for(&spec) {
+                Ok(()) => loaded.push(spec.ident),
+                Err(err) => {
+                    outputln!("Unable to load {} from the fleet manifest, {}",
+                              spec.ident,
+                              err);
+                    if previously_loaded {
+                        loaded.push(spec.ident);
+                    }
+                }
+            }
+        }
+        let unloaded = reconciler.loaded_services()
+                                 .iter()
+                                 .filter(|ident| !loaded.iter().any(|l| l.name == ident.name))
+                                 .cloned()
+                                 .collect::<Vec<_>>();
+        reconciler.applied(version, loaded);
+        for ident in unloaded {
+            outputln!("Unloading {}, the fleet manifest no longer selects it",
+                      ident);
+            let _spec_writes = self.state.spec_writes.lock();
+            self.remove_spec_file(&ident).ok();
+        }
+    }
+
     /// Wrap a future that starts, stops, or restarts a service with
     /// logic that marks that service as "busy" for the duration of
     /// the process.
@@ -1790,7 +2447,7 @@ impl Manager {
                         self.remove_service_from_state_msw(&spec.ident)
                             .and_then(|mut service_state| service_state.shutdown(false))
                     {
-                        tokio::spawn(self.stop_service_future_gsw(service, None, None));
+                        tokio::spawn(self.stop_service_future_gsw(service, None, None, None));
                     } else {
                         // We really don't expect this to happen....
                         outputln!("Tried to remove service for {} but could not find it running, \
@@ -1825,6 +2482,8 @@ impl Manager {
                                 RefreshOperation::RestartUpdater => {
                                     self.service_updater.lock().register(service);
                                 }
+                                // The service was gossiped above already
+                                RefreshOperation::Announce => {}
                             }
                         }
                     } else {
@@ -1856,7 +2515,7 @@ impl Manager {
         // Now, figure out what we should compare against, ignoring
         // any services that are currently doing something
         // asynchronously.
-        let busy_services = self.busy_services.lock();
+        let busy_services = self.state.busy_services.lock();
         let on_disk_specs = self.spec_dir
                                 .specs()
                                 .into_iter()
@@ -1910,16 +2569,39 @@ impl Manager {
         if !self.butterfly.need_peer_seeding_mlr() {
             return Ok(());
         }
-        match self.peer_watcher {
-            None => Ok(()),
-            Some(ref watcher) => {
-                if watcher.has_fs_events() {
-                    let members = watcher.get_members()?;
-                    self.butterfly.member_list.set_initial_members_imlw(members);
-                }
-                Ok(())
+        if let Some(ref source) = self.peer_source {
+            if source.has_events() {
+                let members = source.get_members()?;
+                self.butterfly.member_list.set_initial_members_imlw(members);
             }
         }
+        Ok(())
+    }
+
+    /// Pass the certificates the running services request to the ACME client, and install the
+    /// certificates it obtained for them.
+    ///
+    /// # Locking (see locking.md)
+    /// * `ManagerServices::inner` (write)
+    fn update_acme_certificates_msw(&mut self) {
+        let acme = match self.acme {
+            Some(ref mut acme) => acme,
+            None => return,
+        };
+        if !acme.sync_due() {
+            return;
+        }
+        let mut issued = acme.take_issued();
+        let mut requests = HashMap::new();
+        for service in self.state.services.lock_msw().running_services() {
+            if let Some(certificate) = issued.remove(&service.service_group) {
+                service.install_certificate(&certificate);
+            }
+            if let Some(request) = service.certificate_request() {
+                requests.insert(service.service_group.clone(), request);
+            }
+        }
+        acme.set_requests(requests);
     }
 
     /// # Locking (see locking.md)
@@ -1930,51 +2612,48 @@ impl Manager {
                 outputln!("user.toml changes detected for {}", &service.spec_ident());
                 service.user_config_updated = true;
             }
+            if self.environment_watcher.have_events_for(service) {
+                outputln!("Environment file changes detected for {}",
+                          &service.spec_ident());
+                service.environment_updated = true;
+            }
+        }
+    }
+
+    /// # Locking (see locking.md)
+    /// * `ManagerServices::inner` (write)
+    fn update_running_services_from_template_watcher_msw(&mut self) {
+        let dev_mode = self.feature_flags.contains(FeatureFlag::DEV_MODE);
+        let mut services = self.state.services.lock_msw();
+        let mut dirs = HashMap::new();
+        for service in services.running_services() {
+            dirs.insert(service.hook_templates_path(), RecursiveMode::NonRecursive);
+            // Only templates in a `--config-from` directory are edited in place
+            if dev_mode && service.config_from().is_some() {
+                dirs.insert(service.config_template_path(), RecursiveMode::Recursive);
+            }
+        }
+        self.template_watcher.watch_dirs(dirs);
+        let changed = self.template_watcher.changed_dirs();
+        if changed.is_empty() {
+            return;
+        }
+        for service in services.running_services() {
+            if changed.contains(&service.hook_templates_path()) {
+                outputln!("Hook changes detected for {}", &service.spec_ident());
+                service.hooks_updated = true;
+            }
+            if dev_mode && changed.contains(&service.config_template_path()) {
+                outputln!("Configuration template changes detected for {}",
+                          &service.spec_ident());
+                service.templates_updated = true;
+            }
         }
     }
 }
 
 ////////////////////////////////////////////////////////////////////////
 
-fn tls_config(config: &TLSConfig) -> Result<rustls::ServerConfig> {
-    let client_auth = match &config.ca_cert_path {
-        Some(path) => {
-            let mut root_store = RootCertStore::empty();
-            let ca_file = &mut BufReader::new(File::open(path)?);
-            root_store.add_pem_file(ca_file)
-                      .and_then(|(added, _)| {
-                          if added < 1 {
-                              Err(())
-                          } else {
-                              Ok(AllowAnyAuthenticatedClient::new(root_store))
-                          }
-                      })
-                      .map_err(|_| Error::InvalidCertFile(path.clone()))?
-        }
-        None => NoClientAuth::new(),
-    };
-
-    let mut server_config = ServerConfig::new(client_auth);
-    let key_file = &mut BufReader::new(File::open(&config.key_path)?);
-    let cert_file = &mut BufReader::new(File::open(&config.cert_path)?);
-
-    // Note that we must explicitly map these errors because rustls returns () as the error from
-    // both pemfile::certs() as well as pemfile::rsa_private_keys() and we want to return
-    // different errors for each.
-    let cert_chain =
-        pemfile::certs(cert_file).and_then(|c| if c.is_empty() { Err(()) } else { Ok(c) })
-                                 .map_err(|_| Error::InvalidCertFile(config.cert_path.clone()))?;
-
-    let key = pemfile::rsa_private_keys(key_file).and_then(|mut k| k.pop().ok_or(()))
-                                                 .map_err(|_| {
-                                                     Error::InvalidKeyFile(config.key_path.clone())
-                                                 })?;
-
-    server_config.set_single_cert(cert_chain, key)?;
-    server_config.ignore_client_order = true;
-    Ok(server_config)
-}
-
 #[cfg(windows)]
 fn get_fd_count() -> std::io::Result<usize> {
     let mut count: u32 = 0;
@@ -2033,31 +2712,52 @@ mod test {
     // code, so only implement it under test configuration.
     impl Default for ManagerConfig {
         fn default() -> Self {
-            ManagerConfig { auto_update:                false,
-                            auto_update_period:         Duration::from_secs(60),
-                            service_update_period:      Duration::from_secs(60),
-                            service_restart_config:     ServiceRestartConfig::default(),
-                            custom_state_path:          None,
-                            key_cache:                  KeyCache::new(&*CACHE_KEY_PATH),
-                            update_url:                 "".to_string(),
-                            update_channel:             ChannelIdent::default(),
-                            gossip_listen:              GossipListenAddr::default(),
-                            ctl_listen:                 ListenCtlAddr::default(),
-                            ctl_server_certificates:    None,
-                            ctl_server_key:             None,
+            ManagerConfig { auto_update: false,
+                            auto_update_period: Duration::from_secs(60),
+                            service_update_period: Duration::from_secs(60),
+                            service_restart_config: ServiceRestartConfig::default(),
+                            custom_state_path: None,
+                            key_cache: KeyCache::new(&*CACHE_KEY_PATH),
+                            update_url: "".to_string(),
+                            update_channel: ChannelIdent::default(),
+                            gossip_listen: GossipListenAddr::default(),
+                            ctl_listen: ListenCtlAddr::default(),
+                            ctl_server_certificates: None,
+                            ctl_server_key: None,
                             ctl_client_ca_certificates: None,
-                            http_listen:                HttpListenAddr::default(),
-                            http_disable:               false,
-                            gossip_peers:               vec![],
-                            gossip_permanent:           false,
-                            ring_key:                   None,
-                            organization:               None,
-                            watch_peer_file:            None,
-                            tls_config:                 None,
-                            feature_flags:              FeatureFlag::empty(),
-                            event_stream_config:        None,
-                            keep_latest_packages:       None,
-                            sys_ip:                     IpAddr::V4(Ipv4Addr::LOCALHOST), }
+                            ctl_grpc_listen: None,
+                            http_listen: HttpListenAddr::default(),
+                            http_disable: false,
+                            gossip_peers: vec![],
+                            gossip_permanent: false,
+                            gossip_advertise: None,
+                            gossip_stun_server: None,
+                            sys_zone: None,
+                            sys_labels: HashMap::new(),
+                            fleet_manifest_origin: None,
+                            ring_key: None,
+                            organization: None,
+                            watch_peer_file: None,
+                            peer_kubernetes_service: None,
+                            peer_ec2: None,
+                            peer_mdns: false,
+                            peer_consul: None,
+                            tls_config: None,
+                            feature_flags: FeatureFlag::empty(),
+                            event_stream_config: None,
+                            keep_latest_packages: None,
+                            download_rate_limit: None,
+                            state_snapshot: None,
+                            prefetch: None,
+                            extension: None,
+                            member_id_source: None,
+                            denylist: Vec::new(),
+                            swim_timing: Timing::default(),
+                            rumor_ttl: None,
+                            observer: false,
+                            ring_tls: None,
+                            acme: None,
+                            sys_ip: IpAddr::V4(Ipv4Addr::LOCALHOST), }
         }
     }
 
@@ -0,0 +1,309 @@
+//! Obtains and renews TLS certificates from an ACME certificate authority, such as Let's Encrypt,
+//! for the domains services declare in the `acme` table of their configuration:
+//!
+//! ```toml
+//! [acme]
+//! domains = ["www.example.com", "example.com"]
+//! webroot = "/hab/svc/nginx/data/htdocs"
+//! ```
+//!
+//! Domains are validated with the HTTP-01 challenge. The response to each challenge is written
+//! below `webroot/.well-known/acme-challenge/`, which the service, or another web server on port
+//! 80, must serve. Certificates are requested on a thread of their own and handed to the manager,
+//! which installs them in the config directory of their service. A certificate is renewed once it
+//! has fewer than 30 days left. The account and certificates are kept in the Supervisor's data
+//! directory, so a restarted Supervisor reuses them rather than requesting new ones.
+
+use crate::error::{Error,
+                   Result};
+use acme_lib::{create_p384_key,
+               persist::FilePersist,
+               Directory,
+               DirectoryUrl};
+use habitat_common::{liveliness_checker,
+                     outputln};
+use habitat_core::service::ServiceGroup;
+use parking_lot::Mutex;
+use prometheus::IntCounter;
+use std::{collections::HashMap,
+          fs,
+          path::{Path,
+                 PathBuf},
+          sync::Arc,
+          thread::{self,
+                   Builder as ThreadBuilder},
+          time::{Duration,
+                 Instant}};
+use url::Url;
+use webpki::DNSNameRef;
+
+static LOGKEY: &str = "AC";
+
+/// The directory below a service's webroot that challenge responses are written to
+const CHALLENGE_DIR: &str = ".well-known/acme-challenge";
+/// A certificate is renewed once it has fewer days left than this
+const RENEW_DAYS_LEFT: i64 = 30;
+/// How often the certificates are checked for being due for renewal
+const CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+/// The delay before a certificate that could not be obtained is requested again
+const RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How often the requests of the services are read, and looked at for new or changed ones
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// How long to wait between polls of the certificate authority while it validates challenges and
+/// issues certificates
+const VALIDATION_POLL_MS: u64 = 5000;
+
+lazy_static! {
+    static ref ACME_FAILURES: IntCounter = register_int_counter!("hab_sup_acme_failures_total",
+                                                                 "Total number of failed \
+                                                                  certificate requests").unwrap();
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcmeConfig {
+    /// The directory URL of the certificate authority
+    pub directory: Url,
+    /// The contact email of the account
+    pub email:     String,
+}
+
+/// The certificate a service requests in the `acme` table of its configuration
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CertificateRequest {
+    /// The domains of the certificate. The first one is its common name.
+    pub domains: Vec<String>,
+    /// The directory served as the document root of the domains on port 80
+    pub webroot: PathBuf,
+}
+
+impl CertificateRequest {
+    /// The request in the configuration of a service, if it has an `acme` table
+    pub fn from_cfg(cfg: &toml::value::Table) -> std::result::Result<Option<Self>, String> {
+        let table = match cfg.get("acme") {
+            Some(table) => table.clone(),
+            None => return Ok(None),
+        };
+        let request = table.try_into::<CertificateRequest>()
+                           .map_err(|e| e.to_string())?;
+        if request.domains.is_empty() {
+            return Err(String::from("acme.domains is empty"));
+        }
+        // The domains are sent to the CA and name the files the certificates are persisted to
+        if let Some(domain) = request.domains
+                                     .iter()
+                                     .find(|d| DNSNameRef::try_from_ascii_str(d).is_err())
+        {
+            return Err(format!("acme.domains has an invalid DNS name, {}", domain));
+        }
+        Ok(Some(request))
+    }
+}
+
+/// A certificate and its private key, in PEM format
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IssuedCertificate {
+    pub certificate: String,
+    pub private_key: String,
+}
+
+#[derive(Default)]
+struct Certificates {
+    /// The certificates services request, by service group
+    requests: HashMap<ServiceGroup, CertificateRequest>,
+    /// The certificates obtained since they were last taken, by service group
+    issued:   HashMap<ServiceGroup, IssuedCertificate>,
+}
+
+pub struct AcmeClient {
+    certificates: Arc<Mutex<Certificates>>,
+    next_sync:    Instant,
+}
+
+impl AcmeClient {
+    pub fn run(config: AcmeConfig, data_path: &Path) -> Result<Self> {
+        let certificates = Arc::<Mutex<Certificates>>::default();
+        let persist_path = data_path.join("acme");
+        fs::create_dir_all(&persist_path)?;
+        let certificates_for_thread = Arc::clone(&certificates);
+        ThreadBuilder::new().name(String::from("acme-client"))
+                            .spawn(move || {
+                                issue_certificates(&config, &persist_path, &certificates_for_thread)
+                            })?;
+        Ok(AcmeClient { certificates,
+                        next_sync: Instant::now() })
+    }
+
+    /// Whether it is time to exchange requests and certificates with the services again. Reading
+    /// the requests from the configuration of every service is not done on every tick of the
+    /// manager.
+    pub fn sync_due(&mut self) -> bool {
+        let now = Instant::now();
+        if now < self.next_sync {
+            return false;
+        }
+        self.next_sync = now + POLL_INTERVAL;
+        true
+    }
+
+    /// Replace the certificates requested by services
+    pub fn set_requests(&self, requests: HashMap<ServiceGroup, CertificateRequest>) {
+        self.certificates.lock().requests = requests;
+    }
+
+    /// The certificates obtained since this was last called, by service group
+    pub fn take_issued(&self) -> HashMap<ServiceGroup, IssuedCertificate> {
+        std::mem::take(&mut self.certificates.lock().issued)
+    }
+}
+
+fn issue_certificates(config: &AcmeConfig,
+                      persist_path: &Path,
+                      certificates: &Mutex<Certificates>)
+                      -> ! {
+    // The request each service group was last checked for, and when it is due to be checked again
+    let mut next_checks: HashMap<ServiceGroup, (CertificateRequest, Instant)> = HashMap::new();
+    loop {
+        liveliness_checker::mark_thread_alive().and_divergent();
+        let requests = certificates.lock().requests.clone();
+        next_checks.retain(|service_group, _| requests.contains_key(service_group));
+        for (service_group, request) in requests {
+            let now = Instant::now();
+            if let Some((previous, at)) = next_checks.get(&service_group) {
+                if *previous == request && *at > now {
+                    continue;
+                }
+            }
+            let next_check = match certificate(config, persist_path, &request) {
+                Ok(issued) => {
+                    certificates.lock()
+                                .issued
+                                .insert(service_group.clone(), issued);
+                    now + CHECK_INTERVAL
+                }
+                Err(e) => {
+                    ACME_FAILURES.inc();
+                    outputln!(preamble service_group,
+                              "Failed to obtain a certificate for {}, trying again in {}s: {}",
+                              request.domains.join(", "),
+                              RETRY_INTERVAL.as_secs(),
+                              e);
+                    now + RETRY_INTERVAL
+                }
+            };
+            next_checks.insert(service_group, (request, next_check));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// The certificate for the domains of `request`, reusing the last one issued for them unless it
+/// is due for renewal
+fn certificate(config: &AcmeConfig,
+               persist_path: &Path,
+               request: &CertificateRequest)
+               -> Result<IssuedCertificate> {
+    let (primary, alt_names) = request.domains
+                                      .split_first()
+                                      .expect("requests have at least one domain");
+    let alt_names = alt_names.iter().map(String::as_str).collect::<Vec<_>>();
+    let directory =
+        Directory::from_url(FilePersist::new(persist_path),
+                            DirectoryUrl::Other(config.directory.as_str())).map_err(acme_error)?;
+    let account = directory.account(&config.email).map_err(acme_error)?;
+
+    // Certificates are stored by their common name, so the domains they were issued for are
+    // recorded next to them to notice when the alternative names change
+    let domains_file = persist_path.join(format!("{}.domains", primary));
+    let domains = request.domains.join("\n");
+    if fs::read_to_string(&domains_file).ok().as_deref() == Some(domains.as_str()) {
+        if let Some(cert) = account.certificate(primary).map_err(acme_error)? {
+            if cert.valid_days_left() > RENEW_DAYS_LEFT {
+                return Ok(IssuedCertificate { certificate: cert.certificate().to_string(),
+                                              private_key: cert.private_key().to_string(), });
+            }
+        }
+    }
+
+    outputln!("Requesting a certificate for {}",
+              request.domains.join(", "));
+    let mut order = account.new_order(primary, &alt_names).map_err(acme_error)?;
+    let csr = loop {
+        if let Some(csr) = order.confirm_validations() {
+            break csr;
+        }
+        for auth in order.authorizations().map_err(acme_error)? {
+            if !auth.need_challenge() {
+                continue;
+            }
+            let challenge = auth.http_challenge();
+            let response = request.webroot
+                                  .join(CHALLENGE_DIR)
+                                  .join(challenge.http_token());
+            if let Some(dir) = response.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&response, challenge.http_proof())?;
+            let validated = challenge.validate(VALIDATION_POLL_MS);
+            fs::remove_file(&response)?;
+            validated.map_err(acme_error)?;
+        }
+        order.refresh().map_err(acme_error)?;
+    };
+    let cert = csr.finalize_pkey(create_p384_key(), VALIDATION_POLL_MS)
+                  .and_then(|order| order.download_and_save_cert())
+                  .map_err(acme_error)?;
+    fs::write(&domains_file, domains)?;
+    outputln!("Obtained a certificate for {}, valid for {} days",
+              request.domains.join(", "),
+              cert.valid_days_left());
+    Ok(IssuedCertificate { certificate: cert.certificate().to_string(),
+                           private_key: cert.private_key().to_string(), })
+}
+
+fn acme_error(err: acme_lib::Error) -> Error { Error::Acme(err.to_string()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg(toml: &str) -> toml::value::Table { toml::from_str(toml).unwrap() }
+
+    #[test]
+    fn services_request_certificates_in_their_configuration() {
+        assert_eq!(CertificateRequest::from_cfg(&cfg("port = 80")), Ok(None));
+        assert_eq!(
+                   CertificateRequest::from_cfg(&cfg(r#"
+[acme]
+domains = ["www.example.com", "example.com"]
+webroot = "/srv/www"
+"#)),
+                   Ok(Some(CertificateRequest { domains: vec![
+            String::from("www.example.com"),
+            String::from("example.com")
+        ],
+                                                webroot: PathBuf::from("/srv/www"), }))
+        );
+    }
+
+    #[test]
+    fn requests_need_domains_and_a_webroot() {
+        assert!(CertificateRequest::from_cfg(&cfg(r#"
+[acme]
+domains = []
+webroot = "/srv/www"
+"#)).is_err());
+        assert!(CertificateRequest::from_cfg(&cfg(r#"
+[acme]
+domains = ["example.com"]
+"#)).is_err());
+    }
+
+    #[test]
+    fn requested_domains_must_be_dns_names() {
+        for domain in &["../example.com", "example.com/cert", "www..example.com", ""] {
+            let request = format!("[acme]\ndomains = [{:?}]\nwebroot = \"/srv/www\"", domain);
+            assert!(CertificateRequest::from_cfg(&cfg(&request)).is_err());
+        }
+    }
+}
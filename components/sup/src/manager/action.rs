@@ -1,9 +1,12 @@
 //! Defines types for sending information about "actions" from one
 //! part of the Supervisor to another.
 
-use super::service::ServiceSpec;
+use super::{service::{DataRetention,
+                      ServiceSpec},
+            ReloadableConfig};
 use habitat_core::os::process::ShutdownTimeout;
-use std::sync::mpsc;
+use std::{sync::mpsc,
+          time::Duration};
 
 /// Defines the parameters by which a service process is to be shut
 /// down cleanly.
@@ -28,9 +31,22 @@ pub enum SupervisorAction {
     UnloadService {
         service_spec:   ServiceSpec,
         shutdown_input: ShutdownInput,
+        data_retention: DataRetention,
     },
-    UpdateService {
+    /// Coordinate an unload of the service across its group, as requested with
+    /// `hab svc unload --group-coordinated`
+    CoordinateUnload {
         service_spec: ServiceSpec,
+        /// The ID the unload is gossiped with
+        id:           String,
+        timeout:      Duration,
+    },
+    ReloadConfig {
+        config: ReloadableConfig,
+    },
+    /// Depart the ring, as requested with `hab sup depart --self`
+    DepartSelf {
+        stop_services: bool,
     },
 }
 
@@ -2,19 +2,44 @@
 
 use crate::{ctl_gateway::CtlRequest,
             error::Error,
+            event::PackageChangeInitiator,
             manager::{action::{ActionSender,
                                SupervisorAction},
-                      service::{spec::ServiceSpec,
+                      config_reload,
+                      denylist,
+                      fleet_manifest::FleetManifest,
+                      service::{new_unload_id,
+                                spec::ServiceSpec,
+                                DataRetention,
                                 DesiredState,
-                                ProcessState},
-                      ManagerState},
+                                HealthCheckData,
+                                HealthCheckResult,
+                                PersistentServiceWrapper,
+                                ProcessState,
+                                ResourceUsage,
+                                Service,
+                                DEFAULT_COORDINATION_TIMEOUT},
+                      sync::ManagerServices,
+                      tombstone::Tombstone,
+                      ManagerState,
+                      ReloadableConfig},
             util};
-use habitat_butterfly as butterfly;
+use configopt::ConfigOpt;
+use hab::cli::hab::sup::SupRun;
+use habitat_butterfly::{self as butterfly,
+                        member::Capability,
+                        rumor::{service::UnloadPhase,
+                                service_file::{Chunk,
+                                               ServiceFile as ServiceFileRumor,
+                                               Target}}};
 use habitat_common::{command::package::install::InstallSource,
                      outputln,
-                     templating::package::Pkg,
+                     templating::{hooks,
+                                  package::Pkg},
+                     types::DenylistEntry,
                      ui::UIWriter};
-use habitat_core::{package::{Identifiable,
+use habitat_core::{crypto::Blake2bHash,
+                   package::{Identifiable,
                              PackageIdent,
                              PackageTarget},
                    service::ServiceGroup};
@@ -22,15 +47,49 @@ use habitat_sup_protocol::{self as protocol,
                            net::{self,
                                  ErrCode,
                                  NetResult}};
-use std::{convert::TryFrom,
+use parking_lot::Mutex;
+use std::{collections::HashSet,
+          convert::TryFrom,
           fmt,
+          net::IpAddr,
           result,
-          sync::atomic::Ordering,
+          sync::{atomic::Ordering,
+                 Arc},
+          thread::{self,
+                   Builder as ThreadBuilder},
           time::{Duration,
-                 SystemTime}};
+                 Instant,
+                 SystemTime,
+                 UNIX_EPOCH}};
 
 static LOGKEY: &str = "CMD";
 
+/// How often a service is checked for having reached the state a command brings it to, while the
+/// output of its hooks is streamed to the client
+const HOOK_OUTPUT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+habitat_core::env_config_duration!(HookOutputTimeout,
+                                   HAB_CTL_HOOK_OUTPUT_TIMEOUT_SECS => from_secs,
+                                   Duration::from_secs(60));
+
+/// The state a command brings a service to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AwaitedState {
+    /// Running, once its init hook succeeded
+    Started,
+    /// No longer running, once its stop and unload operations finished
+    Stopped,
+}
+
+impl fmt::Display for AwaitedState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AwaitedState::Started => write!(f, "started"),
+            AwaitedState::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
 /// # Locking (see locking.md)
 /// * `ManagerServices::inner` (read)
 pub fn service_cfg_msr(mgr: &ManagerState,
@@ -111,10 +170,13 @@ pub fn service_cfg_validate(_mgr: &ManagerState,
     // ))
 }
 
-pub fn service_cfg_set(mgr: &ManagerState,
-                       req: &mut CtlRequest,
-                       opts: protocol::ctl::SvcSetCfg)
-                       -> NetResult<()> {
+/// # Locking (see locking.md)
+/// * `MemberList::entries` (read)
+pub fn service_cfg_set_mlr(mgr: &ManagerState,
+                           req: &mut CtlRequest,
+                           opts: protocol::ctl::SvcSetCfg)
+                           -> NetResult<()> {
+    refuse_on_observer(mgr, "apply configuration")?;
     let cfg = opts.cfg.ok_or_else(err_update_client)?;
     let is_encrypted = opts.is_encrypted.unwrap_or(false);
     let version = opts.version.ok_or_else(err_update_client)?;
@@ -122,30 +184,71 @@ pub fn service_cfg_set(mgr: &ManagerState,
     if cfg.len() > protocol::butterfly::MAX_SVC_CFG_SIZE {
         return Err(net::err(ErrCode::EntityTooLarge, "Configuration too large."));
     }
-    outputln!("Setting new configuration version {} for {}",
-              version,
-              service_group,);
-    let mut client =
-        match butterfly::client::Client::new(&mgr.cfg.gossip_listen.local_addr().to_string(),
-                                             mgr.cfg.ring_key.clone())
-        {
-            Ok(client) => client,
-            Err(err) => {
-                outputln!("Failed to connect to own gossip server, {}", err);
-                return Err(net::err(ErrCode::Internal, err.to_string()));
-            }
-        };
-    client.send_service_config(service_group, version, &cfg, is_encrypted)
+    let activate_at = opts.activate_at_ms
+                          .map(|ms| UNIX_EPOCH + Duration::from_millis(ms));
+    if activate_at.is_some() {
+        refuse_unschedulable_config_mlr(mgr)?;
+    }
+    match activate_at {
+        Some(activate_at) => {
+            let delay = activate_at.duration_since(SystemTime::now())
+                                   .unwrap_or_default();
+            outputln!("Scheduling new configuration version {} for {} to be applied in {}s",
+                      version,
+                      service_group,
+                      delay.as_secs());
+        }
+        None => {
+            outputln!("Setting new configuration version {} for {}",
+                      version,
+                      service_group,);
+        }
+    }
+    let mut client = match gossip_client(mgr) {
+        Ok(client) => client,
+        Err(err) => {
+            outputln!("Failed to connect to own gossip server, {}", err);
+            return Err(net::err(ErrCode::Internal, err.to_string()));
+        }
+    };
+    client.send_scheduled_service_config(service_group, version, &cfg, is_encrypted, activate_at)
           .map_err(|e| net::err(ErrCode::Internal, e.to_string()))
           .map(|_| {
               req.reply_complete(net::ok());
           })
 }
 
-pub fn service_file_put(mgr: &ManagerState,
-                        req: &mut CtlRequest,
-                        opts: protocol::ctl::SvcFilePut)
-                        -> NetResult<()> {
+/// A scheduled configuration is only held back by the members that advertise
+/// `Capability::ScheduledConfig`, and only applied by all of them at once if their clocks agree,
+/// so it is refused otherwise rather than applied at different times.
+///
+/// # Locking (see locking.md)
+/// * `MemberList::entries` (read)
+fn refuse_unschedulable_config_mlr(mgr: &ManagerState) -> NetResult<()> {
+    if !mgr.butterfly.ring_supports_mlr(Capability::ScheduledConfig) {
+        let message = "Not every member of the ring supports scheduled configuration. Update the \
+                       Supervisors that don't before scheduling one.";
+        return Err(net::err(ErrCode::NotSupported, message));
+    }
+    let skew = mgr.butterfly.clock_skew();
+    if skew.exceeds_threshold {
+        let (member_id, offset_ms) = skew.furthest().unwrap_or_default();
+        let message = format!("The clocks of the ring are {}ms apart, over the {}ms threshold, \
+                               so the members would not apply the configuration at the same \
+                               moment. Member {} is {}ms off.",
+                              skew.spread_ms, skew.threshold_ms, member_id, offset_ms);
+        return Err(net::err(ErrCode::Conflict, message));
+    }
+    Ok(())
+}
+
+/// # Locking (see locking.md)
+/// * `MemberList::entries` (read)
+pub fn service_file_put_mlr(mgr: &ManagerState,
+                            req: &mut CtlRequest,
+                            opts: protocol::ctl::SvcFilePut)
+                            -> NetResult<()> {
+    refuse_on_observer(mgr, "upload files")?;
     let content = opts.content.ok_or_else(err_update_client)?;
     let filename = opts.filename.ok_or_else(err_update_client)?;
     let is_encrypted = opts.is_encrypted.unwrap_or(false);
@@ -154,70 +257,313 @@ pub fn service_file_put(mgr: &ManagerState,
     if content.len() > protocol::butterfly::MAX_FILE_PUT_SIZE_BYTES {
         return Err(net::err(ErrCode::EntityTooLarge, "File content too large."));
     }
-    outputln!("Receiving new version {} of file {} for {}",
-              version,
-              filename,
-              service_group,);
-    let mut client =
-        match butterfly::client::Client::new(&mgr.cfg.gossip_listen.local_addr().to_string(),
-                                             mgr.cfg.ring_key.clone())
-        {
-            Ok(client) => client,
-            Err(err) => {
-                outputln!("Failed to connect to own gossip server, {}", err);
-                return Err(net::err(ErrCode::Internal, err.to_string()));
-            }
-        };
-    client.send_service_file(service_group, filename, version, &content, is_encrypted)
+    // Members that don't know chunks would take each chunk for the whole file
+    let chunks_supported = mgr.butterfly
+                              .ring_supports_mlr(Capability::ChunkedServiceFiles);
+    if opts.chunk_count.is_some() && !chunks_supported {
+        let message = "Not every member of the ring supports files uploaded in chunks. Update the \
+                       Supervisors that don't before uploading a file this large.";
+        return Err(net::err(ErrCode::NotSupported, message));
+    }
+    let chunk = match opts.chunk_count {
+        Some(count) => {
+            Some(service_file_chunk(&content,
+                                    count,
+                                    opts.chunk_index,
+                                    opts.chunk_checksum,
+                                    opts.file_checksum)?)
+        }
+        None => None,
+    };
+    match chunk {
+        Some(ref chunk) => {
+            outputln!("Receiving chunk {} of {} of new version {} of file {} for {}",
+                      chunk.index + 1,
+                      chunk.count,
+                      version,
+                      filename,
+                      service_group,)
+        }
+        None => {
+            outputln!("Receiving new version {} of file {} for {}",
+                      version,
+                      filename,
+                      service_group,)
+        }
+    }
+    if let Some(label) = opts.target_labels.iter().find(|label| !label.contains('=')) {
+        return Err(net::err(ErrCode::InvalidPayload,
+                            format!("Target label {} is not KEY=VALUE.",
+                                    label)));
+    }
+    let mut service_file = match chunk {
+        Some(chunk) => {
+            ServiceFileRumor::new_chunk(butterfly::client::MEMBER_ID,
+                                        service_group,
+                                        filename,
+                                        chunk,
+                                        content)
+        }
+        None => {
+            ServiceFileRumor::new(butterfly::client::MEMBER_ID,
+                                  service_group,
+                                  filename,
+                                  content)
+        }
+    };
+    service_file.incarnation = version;
+    service_file.encrypted = is_encrypted;
+    service_file.target = Target { member_ids: opts.target_member_ids,
+                                   labels:     opts.target_labels, };
+    service_file.template = opts.template.unwrap_or(false);
+    let mut client = match gossip_client(mgr) {
+        Ok(client) => client,
+        Err(err) => {
+            outputln!("Failed to connect to own gossip server, {}", err);
+            return Err(net::err(ErrCode::Internal, err.to_string()));
+        }
+    };
+    client.send(&service_file)
           .map_err(|e| net::err(ErrCode::Internal, e.to_string()))
           .map(|_| {
               req.reply_complete(net::ok());
           })
 }
 
+/// # Locking (see locking.md)
+/// * `ManagerServices::inner` (read)
+pub fn service_file_status_msr(mgr: &ManagerState,
+                               req: &mut CtlRequest,
+                               opts: protocol::ctl::SvcFileStatus)
+                               -> NetResult<()> {
+    let service_group: ServiceGroup = opts.service_group.ok_or_else(err_update_client)?.into();
+    let mut receipts = Vec::new();
+    let mut found = false;
+    for service in mgr.services.lock_msr().running_services() {
+        if service.service_group == service_group {
+            found = true;
+            receipts.extend(service.file_receipts().map(|(filename, receipt)| {
+                                                       receipt.to_proto(&service.sys.member_id,
+                                                                        &service_group,
+                                                                        filename)
+                                                   }));
+        }
+    }
+    if !found {
+        return Err(net::err(ErrCode::NotFound,
+                            format!("Service group not loaded, {}",
+                                    service_group)));
+    }
+    if receipts.is_empty() {
+        req.reply_complete(net::ok());
+    } else {
+        let mut list = receipts.into_iter().peekable();
+        while let Some(receipt) = list.next() {
+            if list.peek().is_some() {
+                req.reply_partial(receipt);
+            } else {
+                req.reply_complete(receipt);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// # Locking (see locking.md)
+/// * `ManagerServices::inner` (read)
+pub fn service_update_history_msr(mgr: &ManagerState,
+                                  req: &mut CtlRequest,
+                                  opts: protocol::ctl::SvcUpdateHistory)
+                                  -> NetResult<()> {
+    let service_group: ServiceGroup = opts.service_group.ok_or_else(err_update_client)?.into();
+    let history = mgr.services
+                     .lock_msr()
+                     .running_services()
+                     .find(|service| service.service_group == service_group)
+                     .map(|service| service.update_history())
+                     .ok_or_else(|| {
+                         net::err(ErrCode::NotFound,
+                                  format!("Service group not loaded, {}", service_group))
+                     })?;
+    let mut records = history.records()
+                             .map(|record| record.to_proto(&service_group))
+                             .peekable();
+    if records.peek().is_none() {
+        req.reply_complete(net::ok());
+    }
+    while let Some(record) = records.next() {
+        if records.peek().is_some() {
+            req.reply_partial(record);
+        } else {
+            req.reply_complete(record);
+        }
+    }
+    Ok(())
+}
+
+/// Validate one chunk of a file uploaded in chunks against its checksum
+fn service_file_chunk(content: &[u8],
+                      count: u32,
+                      index: Option<u32>,
+                      checksum: Option<String>,
+                      file_checksum: Option<String>)
+                      -> NetResult<Chunk> {
+    if count > protocol::butterfly::MAX_FILE_PUT_CHUNKS {
+        return Err(net::err(ErrCode::EntityTooLarge, "File content too large."));
+    }
+    let index = index.ok_or_else(err_update_client)?;
+    let checksum = checksum.ok_or_else(err_update_client)?;
+    let file_checksum = file_checksum.ok_or_else(err_update_client)?;
+    if index >= count {
+        return Err(net::err(ErrCode::InvalidPayload,
+                            format!("Chunk {} of a file of {} chunks does \
+                                     not exist.",
+                                    index, count)));
+    }
+    let checksum_matches = checksum.parse::<Blake2bHash>()
+                                   .map(|expected| Blake2bHash::from_bytes(content) == expected)
+                                   .unwrap_or(false);
+    if !checksum_matches || file_checksum.parse::<Blake2bHash>().is_err() {
+        return Err(net::err(ErrCode::InvalidPayload,
+                            format!("Chunk {} does not match its checksum.",
+                                    index)));
+    }
+    Ok(Chunk { index,
+               count,
+               checksum,
+               file_checksum })
+}
+
 pub async fn service_load(mgr: &ManagerState,
                           req: &mut CtlRequest,
                           opts: protocol::ctl::SvcLoad)
                           -> NetResult<()> {
-    let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
-    let source = InstallSource::Ident(ident.clone(), PackageTarget::active_target());
-    let spec = if let Some(spec) = mgr.cfg.spec_for_ident(source.as_ref()) {
-        // We've seen this service before. Thus `load` acts as a way to edit spec files from the
-        // command line. As a result, we check that you *really* meant to change an existing spec.
-        if !opts.force.unwrap_or(false) {
-            return Err(net::err(ErrCode::Conflict,
-                                format!("Service already loaded. Unload '{}' \
-                                         and try again, or load with the \
-                                         --force flag to reload and restart the \
-                                         service.",
-                                        ident)));
-        }
-        spec.merge_svc_load(opts)?
+    refuse_on_observer(mgr, "load services")?;
+    let follow = opts.follow.unwrap_or(false);
+    let (source, spec, base_revision) = spec_for_load(mgr, opts)?;
+
+    // Services loaded by the Supervisor itself are those it was started with
+    let initiated_by = if req.has_client() {
+        PackageChangeInitiator::ControlGateway
     } else {
-        ServiceSpec::try_from(opts)?
+        PackageChangeInitiator::SupervisorStartup
     };
-
-    let package = util::pkg::satisfy_or_install(req, &source, &spec.bldr_url, &spec.channel).await?;
+    let package = util::pkg::satisfy_or_install(req,
+                                                &source,
+                                                &spec.bldr_url,
+                                                &spec.channel,
+                                                initiated_by,
+                                                req.client()).await?;
     spec.validate(&package)?;
-    mgr.cfg.save_spec_for(&spec)?;
+    let spec = save_spec_revision(mgr, spec, base_revision)?;
 
-    req.info(format!("The {} service was successfully loaded", spec.ident))?;
+    req.info(format!("The {} service was successfully loaded at revision {}",
+                     spec.ident, spec.revision))?;
+    if follow && spec.desired_state == DesiredState::Up {
+        reply_with_hook_output(mgr, req, &spec, AwaitedState::Started)
+    } else {
+        req.reply_complete(net::ok());
+        Ok(())
+    }
+}
+
+/// Load or reload all the services of a bundle as one transaction. The spec files are written
+/// only once the package of every service is installed and every spec is valid, and none of them
+/// is written if another request changed one of the services in the meantime.
+pub async fn service_load_bundle(mgr: &ManagerState,
+                                 req: &mut CtlRequest,
+                                 opts: protocol::ctl::SvcLoadBundle)
+                                 -> NetResult<()> {
+    refuse_on_observer(mgr, "load services")?;
+    if opts.loads.is_empty() {
+        return Err(net::err(ErrCode::InvalidPayload, "The bundle lists no services."));
+    }
+    let mut names = HashSet::new();
+    let mut loads = Vec::with_capacity(opts.loads.len());
+    for load in opts.loads {
+        let (source, spec, base_revision) = spec_for_load(mgr, load)?;
+        // Spec files are named after the service, so a bundle can only load one service of
+        // each name
+        if !names.insert(spec.ident.name.clone()) {
+            let msg = format!("The {} service is listed more than once.", spec.ident.name);
+            return Err(net::err(ErrCode::InvalidPayload, msg));
+        }
+        loads.push((source, spec, base_revision));
+    }
+    let mut specs = Vec::with_capacity(loads.len());
+    for (source, spec, base_revision) in loads {
+        let package = util::pkg::satisfy_or_install(req,
+                                                    &source,
+                                                    &spec.bldr_url,
+                                                    &spec.channel,
+                                                    PackageChangeInitiator::ControlGateway,
+                                                    req.client()).await?;
+        spec.validate(&package)?;
+        specs.push((spec, base_revision));
+    }
+    for spec in save_spec_revisions(mgr, specs)? {
+        req.info(format!("The {} service was successfully loaded at revision {}",
+                         spec.ident, spec.revision))?;
+    }
     req.reply_complete(net::ok());
     Ok(())
 }
 
+/// The package to load for `opts`, along with the spec to load it with and the revision of the
+/// spec it changes, if the service is already loaded
+fn spec_for_load(mgr: &ManagerState,
+                 opts: protocol::ctl::SvcLoad)
+                 -> NetResult<(InstallSource, ServiceSpec, Option<u64>)> {
+    let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
+    let source = InstallSource::Ident(ident.clone(), PackageTarget::active_target());
+    let (spec, base_revision) = if let Some(spec) = mgr.cfg.spec_for_ident(source.as_ref()) {
+        // We've seen this service before. Thus `load` acts as a way to edit spec files from the
+        // command line. As a result, we check that you *really* meant to change an existing spec,
+        // either by forcing it or by naming the revision of the spec you meant to change.
+        match opts.revision {
+            Some(revision) => check_revision(&spec, revision)?,
+            None if opts.force.unwrap_or(false) => {}
+            None => {
+                return Err(net::err(ErrCode::Conflict,
+                                    format!("Service already loaded at revision {}. \
+                                             Unload '{}' and try again, or load \
+                                             with the --revision flag to change \
+                                             that revision, or the --force flag to \
+                                             reload and restart the service.",
+                                            spec.revision, ident)));
+            }
+        }
+        let base_revision = spec.revision;
+        (spec.merge_svc_load(opts)?, Some(base_revision))
+    } else {
+        (ServiceSpec::try_from(opts)?, None)
+    };
+    Ok((source, spec, base_revision))
+}
+
 pub fn service_update(mgr: &ManagerState,
                       req: &mut CtlRequest,
-                      opts: protocol::ctl::SvcUpdate,
-                      action_sender: &ActionSender)
+                      opts: protocol::ctl::SvcUpdate)
                       -> NetResult<()> {
     let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
     if let Some(mut service_spec) = mgr.cfg.spec_for_ident(&ident) {
+        match opts.revision {
+            Some(revision) => check_revision(&service_spec, revision)?,
+            None if opts.force.unwrap_or(false) => {}
+            None => {
+                return Err(net::err(ErrCode::Conflict,
+                                    format!("{} is at revision {}. Update it with \
+                                             the --revision flag to change that \
+                                             revision, or the --force flag to \
+                                             change whatever revision it is at.",
+                                            ident, service_spec.revision)));
+            }
+        }
+        let base_revision = service_spec.revision;
         service_spec.merge_svc_update(opts);
-        let action = SupervisorAction::UpdateService { service_spec };
-        send_action(action, action_sender)?;
+        let service_spec = save_spec_revision(mgr, service_spec, Some(base_revision))?;
 
-        req.info(format!("Updating {}", ident))?;
+        req.info(format!("Updating {} to revision {}", ident, service_spec.revision))?;
         req.reply_complete(net::ok());
         Ok(())
     } else {
@@ -225,21 +571,41 @@ pub fn service_update(mgr: &ManagerState,
     }
 }
 
-pub fn service_unload(mgr: &ManagerState,
-                      req: &mut CtlRequest,
-                      opts: protocol::ctl::SvcUnload,
-                      action_sender: &ActionSender)
-                      -> NetResult<()> {
+/// # Locking (see locking.md)
+/// * `ManagerServices::inner` (read)
+pub fn service_unload_msr(mgr: &ManagerState,
+                          req: &mut CtlRequest,
+                          opts: protocol::ctl::SvcUnload,
+                          action_sender: &ActionSender)
+                          -> NetResult<()> {
     let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
+    if opts.group_coordinated.unwrap_or(false) {
+        return coordinate_service_unload_msr(mgr, req, ident, &opts, action_sender);
+    }
+    let data_retention = match (opts.purge.unwrap_or(false), opts.retain_data_days) {
+        (true, Some(_)) => {
+            return Err(net::err(ErrCode::InvalidPayload,
+                                "A service's data cannot be both purged and \
+                                 retained"));
+        }
+        (true, None) => DataRetention::Purge,
+        (false, Some(days)) => DataRetention::Archive { days },
+        (false, None) => DataRetention::Keep,
+    };
     if let Some(service_spec) = mgr.cfg.spec_for_ident(&ident) {
+        let follow = opts.follow.unwrap_or(false);
         let shutdown_input = opts.into();
-        let action = SupervisorAction::UnloadService { service_spec,
-                                                       shutdown_input };
+        let action = SupervisorAction::UnloadService { service_spec: service_spec.clone(),
+                                                       shutdown_input,
+                                                       data_retention };
         send_action(action, action_sender)?;
 
         // JW TODO: Change this to unloaded from unloading when the Supervisor waits for
         // the work to complete.
         req.info(format!("Unloading {}", ident))?;
+        if follow {
+            return reply_with_hook_output(mgr, req, &service_spec, AwaitedState::Stopped);
+        }
         req.reply_complete(net::ok());
         Ok(())
     } else {
@@ -247,28 +613,157 @@ pub fn service_unload(mgr: &ManagerState,
     }
 }
 
+/// Unload a service from every member of its group at once, with this member coordinating the
+/// unload
+///
+/// # Locking (see locking.md)
+/// * `ManagerServices::inner` (read)
+fn coordinate_service_unload_msr(mgr: &ManagerState,
+                                 req: &mut CtlRequest,
+                                 ident: PackageIdent,
+                                 opts: &protocol::ctl::SvcUnload,
+                                 action_sender: &ActionSender)
+                                 -> NetResult<()> {
+    if opts.purge.unwrap_or(false) || opts.retain_data_days.is_some() {
+        return Err(net::err(ErrCode::InvalidPayload,
+                            "The data of a service unloaded across its group \
+                             is always kept"));
+    }
+    let service_spec = match mgr.cfg.spec_for_ident(&ident) {
+        Some(service_spec) => service_spec,
+        None => return Err(net::err(ErrCode::Internal, Error::ServiceNotLoaded(ident))),
+    };
+    match mgr.services
+             .lock_msr()
+             .get(&ident)
+             .and_then(PersistentServiceWrapper::service)
+    {
+        Some(service) if service.coordinated_unload_in_progress() => {
+            return Err(net::err(ErrCode::Conflict,
+                                format!("An unload of {} is already being \
+                                         coordinated",
+                                        service.service_group)));
+        }
+        Some(_) => {}
+        None => {
+            return Err(net::err(ErrCode::NotFound,
+                                format!("{} is not running, so its unload \
+                                         cannot be coordinated across its group",
+                                        ident)));
+        }
+    }
+    let timeout = opts.coordination_timeout_in_seconds
+                      .map_or(DEFAULT_COORDINATION_TIMEOUT, |secs| {
+                          Duration::from_secs(secs.into())
+                      });
+    let id = new_unload_id();
+    let action = SupervisorAction::CoordinateUnload { service_spec: service_spec.clone(),
+                                                      id: id.clone(),
+                                                      timeout };
+    send_action(action, action_sender)?;
+
+    req.info(format!("Unloading {} from every member of its service group once they have \
+                      drained, or abandoning the unload after {} seconds",
+                     ident,
+                     timeout.as_secs()))?;
+    reply_with_unload_outcome(mgr, req, &service_spec, id, timeout)
+}
+
+/// Reply once the coordinated unload `id` of a service is either committed and the service
+/// unloaded, or aborted, streaming the output of its hooks until then
+///
+/// # Locking (see locking.md)
+/// * `ManagerServices::inner` (read)
+fn reply_with_unload_outcome(mgr: &ManagerState,
+                             req: &mut CtlRequest,
+                             spec: &ServiceSpec,
+                             id: String,
+                             timeout: Duration)
+                             -> NetResult<()> {
+    if !req.has_client() {
+        req.reply_complete(net::ok());
+        return Ok(());
+    }
+
+    let service_group = ServiceGroup::new(&spec.ident.name,
+                                          &spec.group,
+                                          mgr.cfg.organization.as_deref())?;
+    let listener_req = Mutex::new(req.clone());
+    let listener = hooks::listen_to_hook_output(&service_group, move |line| {
+        let msg = protocol::ctl::ConsoleLine { line:  format!("{}\n", line),
+                                               color: None,
+                                               bold:  false, };
+        listener_req.lock().reply_partial(msg);
+    });
+
+    let services = Arc::clone(&mgr.services);
+    let busy_services = Arc::clone(&mgr.busy_services);
+    let ident = spec.ident.clone();
+    let mut req = req.clone();
+    // Once the unload is committed, the service takes a while longer to stop
+    let stop_timeout: Duration = HookOutputTimeout::configured_value().into();
+    let mut deadline = Instant::now() + timeout + stop_timeout;
+    if let Some(req_deadline) = req.deadline() {
+        deadline = deadline.min(req_deadline);
+    }
+    ThreadBuilder::new().name(format!("coordinated-unload-{}", service_group))
+                        .spawn(move || {
+                            loop {
+                                if req.is_cancelled() {
+                                    break;
+                                }
+                                let phase =
+                                    services.lock_msr()
+                                            .get(&ident)
+                                            .and_then(PersistentServiceWrapper::service)
+                                            .map(|service| service.coordinated_unload_phase(&id));
+                                match phase {
+                                    Some(Some(UnloadPhase::Aborted)) => {
+                                        let msg = format!("The unload of {} was abandoned, as \
+                                                           not every member of its service group \
+                                                           drained; it stays loaded",
+                                                          service_group);
+                                        req.reply_complete(net::err(ErrCode::Conflict, msg));
+                                        break;
+                                    }
+                                    None if !busy_services.lock().contains(&ident) => {
+                                        req.info(format!("Unloaded {} from every member of its \
+                                                          service group",
+                                                         service_group))
+                                           .ok();
+                                        req.reply_complete(net::ok());
+                                        break;
+                                    }
+                                    _ if Instant::now() >= deadline => {
+                                        let msg = format!("The unload of {} is still being \
+                                                           coordinated",
+                                                          service_group);
+                                        req.reply_complete(net::err(ErrCode::DeadlineExceeded,
+                                                                    msg));
+                                        break;
+                                    }
+                                    _ => thread::sleep(HOOK_OUTPUT_POLL_INTERVAL),
+                                }
+                            }
+                            drop(listener);
+                        })?;
+    Ok(())
+}
+
 pub fn service_start(mgr: &ManagerState,
                      req: &mut CtlRequest,
                      opts: protocol::ctl::SvcStart)
                      -> NetResult<()> {
     let ident = opts.ident.ok_or_else(err_update_client)?.into();
-    match mgr.cfg.spec_for_ident(&ident) {
-        Some(mut spec) => {
-            if spec.desired_state == DesiredState::Down {
-                spec.desired_state = DesiredState::Up;
-                mgr.cfg.save_spec_for(&spec)?;
-
-                // JW TODO: Change the language of the message below to "started" when we actually
-                // synchronously control services from the ctl gateway.
-                req.info(format!("Supervisor starting {}. See the Supervisor output for more \
-                                  details.",
-                                 &ident))?;
-            }
+    if let Some(spec) = save_desired_state(mgr, &ident, DesiredState::Up)? {
+        // JW TODO: Change the language of the message below to "started" when we actually
+        // synchronously control services from the ctl gateway.
+        req.info(format!("Supervisor starting {}. See the Supervisor output for more details.",
+                         &ident))?;
+        if opts.follow.unwrap_or(false) {
+            return reply_with_hook_output(mgr, req, &spec, AwaitedState::Started);
         }
-        None => {
-            return Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", &ident)));
-        }
-    };
+    }
     req.reply_complete(net::ok());
     Ok(())
 }
@@ -279,53 +774,236 @@ pub fn service_stop(mgr: &ManagerState,
                     action_sender: &ActionSender)
                     -> NetResult<()> {
     let ident: PackageIdent = opts.ident.clone().ok_or_else(err_update_client)?.into();
-    match mgr.cfg.spec_for_ident(&ident) {
-        Some(service_spec) => {
-            if service_spec.desired_state == DesiredState::Up {
-                let shutdown_input = opts.into();
-                let action = SupervisorAction::StopService { service_spec,
-                                                             shutdown_input };
-                send_action(action, action_sender)?;
-
-                // JW TODO: Change the langauge of the message below to "stopped" when we actually
-                // synchronously control services from the ctl gateway.
-                req.info(format!("Supervisor stopping {}. See the Supervisor output for more \
-                                  details.",
-                                 &ident))?;
+    if let Some(service_spec) = save_desired_state(mgr, &ident, DesiredState::Down)? {
+        let follow = opts.follow.unwrap_or(false);
+        let shutdown_input = opts.into();
+        let action = SupervisorAction::StopService { service_spec: service_spec.clone(),
+                                                     shutdown_input };
+        send_action(action, action_sender)?;
+
+        // JW TODO: Change the langauge of the message below to "stopped" when we actually
+        // synchronously control services from the ctl gateway.
+        req.info(format!("Supervisor stopping {}. See the Supervisor output for more details.",
+                         &ident))?;
+        if follow {
+            return reply_with_hook_output(mgr, req, &service_spec, AwaitedState::Stopped);
+        }
+    }
+
+    req.reply_complete(net::ok());
+    Ok(())
+}
+
+/// # Locking (see locking.md)
+/// * `ManagerServices::inner` (read)
+pub fn service_maintenance_msr(mgr: &ManagerState,
+                               req: &mut CtlRequest,
+                               opts: protocol::ctl::SvcMaintenance)
+                               -> NetResult<()> {
+    let service_group: ServiceGroup = opts.service_group.ok_or_else(err_update_client)?.into();
+    let enabled = opts.enabled.unwrap_or(false);
+    let ident = mgr.services
+                   .lock_msr()
+                   .iter()
+                   .find(|(_, state)| {
+                       state.service()
+                            .map_or(false, |service| service.service_group == service_group)
+                   })
+                   .map(|(ident, _)| ident.clone())
+                   .ok_or_else(|| {
+                       net::err(ErrCode::NotFound,
+                                format!("Service not loaded, {}", service_group))
+                   })?;
+
+    let _spec_writes = mgr.spec_writes.lock();
+    let mut spec = mgr.cfg
+                      .spec_for_ident(&ident)
+                      .ok_or_else(|| Error::ServiceNotLoaded(ident.clone()))?;
+    if spec.maintenance != enabled {
+        spec.maintenance = enabled;
+        mgr.cfg.save_spec_for(&spec)?;
+    }
+    if enabled {
+        req.info(format!("{} is in maintenance", service_group))?;
+    } else {
+        req.info(format!("{} is out of maintenance", service_group))?;
+    }
+    req.reply_complete(net::ok());
+    Ok(())
+}
+
+/// Depart a member by its member ID, or the members at an address, which resolves to
+/// `addresses`. The address is resolved before the command is run, so that a slow lookup doesn't
+/// hold up the Supervisor.
+///
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
+/// * `MemberList::entries` (read)
+pub fn supervisor_depart_rsr_mlr(mgr: &ManagerState,
+                                 req: &mut CtlRequest,
+                                 opts: protocol::ctl::SupDepart,
+                                 addresses: &[IpAddr])
+                                 -> NetResult<()> {
+    refuse_on_observer(mgr, "depart other members")?;
+    let member_ids = match (opts.member_id, opts.address) {
+        (Some(member_id), _) => vec![member_id],
+        (None, Some(address)) => {
+            let own_member_id = mgr.butterfly.member_id();
+            let member_ids = mgr.butterfly
+                                .member_ids_at_rsr_mlr(&address, addresses)
+                                .into_iter()
+                                .filter(|member_id| member_id != own_member_id)
+                                .collect::<Vec<_>>();
+            if member_ids.is_empty() {
+                return Err(net::err(ErrCode::NotFound,
+                                    format!("No other members found at {}", address)));
+            }
+            if member_ids.len() > 1 && !opts.all.unwrap_or(false) {
+                return Err(net::err(ErrCode::InvalidPayload,
+                                    format!("{} members found at {}: {}. Depart one \
+                                             by its member-id, or all of them with \
+                                             --all.",
+                                            member_ids.len(),
+                                            address,
+                                            member_ids.join(", "))));
             }
+            member_ids
         }
-        None => {
-            return Err(net::err(ErrCode::NotFound, format!("Service not loaded, {}", &ident)));
+        (None, None) => return Err(err_update_client()),
+    };
+    let mut client = match gossip_client(mgr) {
+        Ok(client) => client,
+        Err(err) => {
+            outputln!("Failed to connect to own gossip server, {}", err);
+            return Err(net::err(ErrCode::Internal, err.to_string()));
         }
     };
+    for member_id in member_ids {
+        outputln!("Attempting to depart member: {}", member_id);
+        client.send_departure(&member_id)
+              .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+        req.info(format!("Departed member {}", member_id))?;
+    }
+    req.reply_complete(net::ok());
+    Ok(())
+}
 
+/// Depart this Supervisor from the ring, recording a tombstone so that it does not start again
+/// as the departed member.
+///
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
+pub fn supervisor_depart_self_rsr(mgr: &ManagerState,
+                                  req: &mut CtlRequest,
+                                  opts: protocol::ctl::SupDepartSelf,
+                                  action_sender: &ActionSender)
+                                  -> NetResult<()> {
+    let stop_services = opts.stop_services.unwrap_or(false);
+    let tombstone_path = mgr.cfg.tombstone_path();
+    if Tombstone::read(&tombstone_path)?.is_some() {
+        return Err(net::err(ErrCode::Conflict,
+                            "This Supervisor has already departed the ring."));
+    }
+    // A departed member is never elected, but the rest of the ring keeps treating it as the
+    // leader of any group whose election it already won, so its services would keep serving
+    // leader duties without the Supervisor taking part in the ring.
+    if !stop_services {
+        let mut led = mgr.butterfly
+                         .led_service_groups_rsr()
+                         .into_iter()
+                         .collect::<Vec<_>>();
+        if !led.is_empty() {
+            led.sort();
+            return Err(net::err(ErrCode::Conflict,
+                                format!("This Supervisor leads {}. Depart with \
+                                         --stop-services, or move leadership \
+                                         elsewhere first.",
+                                        led.join(", "))));
+        }
+    }
+    let member_id = mgr.butterfly.member_id().to_string();
+    Tombstone::new(&member_id, stop_services).write(&tombstone_path)?;
+    outputln!("Departing from the ring as member {}", member_id);
+    send_action(SupervisorAction::DepartSelf { stop_services },
+                action_sender)?;
     req.reply_complete(net::ok());
     Ok(())
 }
 
-pub fn supervisor_depart(mgr: &ManagerState,
-                         req: &mut CtlRequest,
-                         opts: protocol::ctl::SupDepart)
-                         -> NetResult<()> {
-    let member_id = opts.member_id.ok_or_else(err_update_client)?;
-    let mut client =
-        match butterfly::client::Client::new(&mgr.cfg.gossip_listen.local_addr().to_string(),
-                                             mgr.cfg.ring_key.clone())
-        {
-            Ok(client) => client,
-            Err(err) => {
-                outputln!("Failed to connect to own gossip server, {}", err);
-                return Err(net::err(ErrCode::Internal, err.to_string()));
-            }
-        };
-    outputln!("Attempting to depart member: {}", member_id);
-    match client.send_departure(&member_id) {
-        Ok(()) => {
-            req.reply_complete(net::ok());
-            Ok(())
+pub fn supervisor_fleet_manifest_apply(mgr: &ManagerState,
+                                       req: &mut CtlRequest,
+                                       opts: protocol::ctl::SupFleetManifestApply)
+                                       -> NetResult<()> {
+    refuse_on_observer(mgr, "apply fleet manifests")?;
+    let version = opts.version.ok_or_else(err_update_client)?;
+    let signer = opts.signer.ok_or_else(err_update_client)?;
+    let signature = opts.signature.ok_or_else(err_update_client)?;
+    let body = opts.body.ok_or_else(err_update_client)?;
+    if body.len() > protocol::butterfly::MAX_FLEET_MANIFEST_SIZE {
+        return Err(net::err(ErrCode::EntityTooLarge, "Fleet manifest too large."));
+    }
+    // The signature is checked by each Supervisor acting on the manifest, but there is no point
+    // in gossiping a manifest none of them will be able to read.
+    if let Err(err) = std::str::from_utf8(&body).map_err(Error::from)
+                                                .and_then(str::parse::<FleetManifest>)
+    {
+        return Err(net::err(ErrCode::InvalidPayload, err.to_string()));
+    }
+    outputln!("Applying fleet manifest version {} signed by {}",
+              version,
+              signer);
+    let mut client = match gossip_client(mgr) {
+        Ok(client) => client,
+        Err(err) => {
+            outputln!("Failed to connect to own gossip server, {}", err);
+            return Err(net::err(ErrCode::Internal, err.to_string()));
+        }
+    };
+    client.send_fleet_manifest(version, &signer, &signature, &body)
+          .map_err(|e| net::err(ErrCode::Internal, e.to_string()))
+          .map(|_| {
+              req.reply_complete(net::ok());
+          })
+}
+
+/// Deny and allow members, departing the ones now denied, and persist the resulting denylist so
+/// that it outlives a restart.
+///
+/// # Locking (see locking.md)
+/// * `MemberList::entries` (write)
+/// * `Server::denylist` (write)
+/// * `RumorHeat::inner` (write)
+pub fn supervisor_denylist_update_mlw_sdlw_rhw(mgr: &ManagerState,
+                                               req: &mut CtlRequest,
+                                               opts: protocol::ctl::SupDenylistUpdate)
+                                               -> NetResult<()> {
+    refuse_on_observer(mgr, "update the denylist")?;
+    let parse = |entries: &[String]| {
+        entries.iter()
+               .map(|entry| entry.parse::<DenylistEntry>())
+               .collect::<result::Result<Vec<_>, _>>()
+               .map_err(|e| net::err(ErrCode::InvalidPayload, e.to_string()))
+    };
+    let deny = parse(&opts.deny)?;
+    let allow = parse(&opts.allow)?;
+
+    let _deny_writes = mgr.deny_writes.lock();
+    for entry in allow {
+        if mgr.butterfly.remove_from_denylist_sdlw(&entry) {
+            outputln!("Allowing gossip from {}", entry);
         }
-        Err(e) => Err(net::err(ErrCode::Internal, e.to_string())),
     }
+    for entry in deny {
+        outputln!("Denying gossip from {}", entry);
+        mgr.butterfly.deny_mlw_sdlw_rhw(entry);
+    }
+    let entries = mgr.butterfly.denylist_sdlr();
+    denylist::write(&mgr.cfg.denylist_path(), &entries)?;
+
+    let msg =
+        protocol::ctl::SupDenylist { entries: entries.iter().map(ToString::to_string).collect(), };
+    req.reply_complete(msg);
+    Ok(())
 }
 
 #[allow(clippy::needless_pass_by_value)]
@@ -337,19 +1015,84 @@ pub fn supervisor_restart(mgr: &ManagerState,
     Ok(())
 }
 
+/// Re-read the Supervisor's config files and arguments, applying the settings which can be changed
+/// at runtime and reporting the ones which require a restart.
+pub fn supervisor_config_reload(mgr: &ManagerState,
+                                req: &mut CtlRequest,
+                                _opts: protocol::ctl::SupConfigReload,
+                                action_sender: &ActionSender)
+                                -> NetResult<()> {
+    let sup_run = SupRun::try_from_args_with_default_config(&mgr.run_args).map_err(|e| {
+                      net::err(ErrCode::InvalidPayload,
+                               format!("Unable to reload Supervisor configuration, {}", e))
+                  })?;
+    let config = ReloadableConfig::from_sup_run(&sup_run);
+    let applied = mgr.reloadable_cfg.lock().changed_settings(&config);
+    let applies_to_new_services =
+        applied.iter()
+               .filter(|setting| config_reload::applies_to_new_services_only(setting))
+               .map(|setting| setting.to_string())
+               .collect();
+    let requires_restart = config_reload::settings_requiring_restart(&mgr.cfg, &sup_run);
+    send_action(SupervisorAction::ReloadConfig { config }, action_sender)?;
+
+    let msg = protocol::ctl::SupConfigReloadResult { applied: applied.into_iter()
+                                                                     .map(String::from)
+                                                                     .collect(),
+                                                     requires_restart:
+                                                         requires_restart.into_iter()
+                                                                         .map(String::from)
+                                                                         .collect(),
+                                                     applies_to_new_services };
+    req.reply_complete(msg);
+    Ok(())
+}
+
+/// Report the summary of the ring's health gathered the last time the gateway state was refreshed.
+///
+/// # Locking (see locking.md)
+/// * `GatewayState::inner` (read)
+pub fn supervisor_ring_status_gsr(mgr: &ManagerState,
+                                  req: &mut CtlRequest,
+                                  _opts: protocol::ctl::SupRingStatus)
+                                  -> NetResult<()> {
+    let status = mgr.gateway_state.lock_gsr().ring_status().clone();
+    req.reply_complete(status);
+    Ok(())
+}
+
 /// # Locking (see locking.md)
 /// * `GatewayState::inner` (read)
 pub fn service_status_gsr(mgr: &ManagerState,
                           req: &mut CtlRequest,
                           opts: protocol::ctl::SvcStatus)
                           -> NetResult<()> {
+    let gateway_state = mgr.gateway_state.lock_gsr();
     let statuses: Vec<ServiceStatus> =
-        serde_json::from_str(mgr.gateway_state.lock_gsr().services_data()).map_err(Error::ServiceDeserializationError)?;
+        serde_json::from_str(gateway_state.services_data()).map_err(Error::ServiceDeserializationError)?;
+    let detail = opts.detail.unwrap_or(false);
+    let resources = opts.resources.unwrap_or(false);
+    let to_msg = |status: ServiceStatus| {
+        let health_check = gateway_state.health_of(&status.service_group)
+                                        .map(|health| health_check_status(health, detail));
+        let spec_revision = if detail {
+            let ident = PackageIdent::from(status.pkg.ident.clone());
+            mgr.cfg.spec_for_ident(&ident).map(|spec| spec.revision)
+        } else {
+            None
+        };
+        let usage = if resources { status.resources } else { None };
+        let mut msg = protocol::types::ServiceStatus::from(status);
+        msg.health_check = health_check;
+        msg.spec_revision = spec_revision;
+        msg.resources = usage.map(Into::into);
+        msg
+    };
 
     if let Some(ident) = opts.ident {
         for status in statuses {
             if status.pkg.ident.satisfies(&ident) {
-                let msg: protocol::types::ServiceStatus = status.into();
+                let msg = to_msg(status);
                 req.reply_complete(msg);
                 return Ok(());
             }
@@ -363,7 +1106,7 @@ pub fn service_status_gsr(mgr: &ManagerState,
     } else {
         let mut list = statuses.into_iter().peekable();
         while let Some(status) = list.next() {
-            let msg: protocol::types::ServiceStatus = status.into();
+            let msg = to_msg(status);
             if list.peek().is_some() {
                 req.reply_partial(msg);
             } else {
@@ -376,22 +1119,200 @@ pub fn service_status_gsr(mgr: &ManagerState,
 
 ////////////////////////////////////////////////////////////////////////
 // Private helper functions
+/// Connect to our own gossip server, to send it rumors for the ring, with the certificates the
+/// server itself was started with in a ring using TLS
+fn gossip_client(mgr: &ManagerState) -> butterfly::error::Result<butterfly::client::Client> {
+    let addr = mgr.cfg.gossip_listen.local_addr();
+    match mgr.butterfly.tls() {
+        Some(tls) => butterfly::client::Client::with_tls(*addr, tls),
+        None => butterfly::client::Client::new(&addr.to_string(), mgr.cfg.ring_key.clone()),
+    }
+}
+
+/// An observer never originates rumors, so it refuses requests which would have it gossip them
+fn refuse_on_observer(mgr: &ManagerState, request: &str) -> NetResult<()> {
+    if mgr.cfg.observer {
+        Err(net::err(ErrCode::NotSupported,
+                     format!("This Supervisor is an observer, and \
+                              cannot {}.",
+                             request)))
+    } else {
+        Ok(())
+    }
+}
+
 fn err_update_client() -> net::NetErr { net::err(ErrCode::UpdateClient, "client out of date") }
 
+/// Check that `spec` is at the revision a request expects to change
+fn check_revision(spec: &ServiceSpec, expected: u64) -> NetResult<()> {
+    if spec.revision == expected {
+        Ok(())
+    } else {
+        Err(net::err(ErrCode::Conflict,
+                     format!("{} is at revision {}, not {}. Look at \
+                              the changes made since and try again \
+                              with --revision {}.",
+                             spec.ident,
+                             spec.revision,
+                             expected,
+                             spec.revision)))
+    }
+}
+
+/// Save `spec` as the revision following `base_revision`, the revision of the spec it was
+/// changed from, or as the first revision of a spec that did not exist. The spec on disk is read
+/// again while no other request can write it, so that a change saved since the request read it is
+/// not overwritten.
+fn save_spec_revision(mgr: &ManagerState,
+                      mut spec: ServiceSpec,
+                      base_revision: Option<u64>)
+                      -> NetResult<ServiceSpec> {
+    let _spec_writes = mgr.spec_writes.lock();
+    let current_revision = mgr.cfg.spec_for_ident(&spec.ident).map(|s| s.revision);
+    if current_revision != base_revision {
+        return Err(net::err(ErrCode::Conflict,
+                            format!("{} was changed by another request \
+                                     while this one was running. Look at \
+                                     the changes and try again.",
+                                    spec.ident)));
+    }
+    spec.revision = current_revision.map_or(1, |revision| revision + 1);
+    mgr.cfg.save_spec_for(&spec)?;
+    Ok(spec)
+}
+
+/// Set the desired state of the spec of `ident`, reading the spec again while no other request can
+/// write it, so that a change saved since the request started is not overwritten. Starting or
+/// stopping a service does not give its spec a new revision. Returns the spec if its desired state
+/// changed.
+fn save_desired_state(mgr: &ManagerState,
+                      ident: &PackageIdent,
+                      desired_state: DesiredState)
+                      -> NetResult<Option<ServiceSpec>> {
+    let _spec_writes = mgr.spec_writes.lock();
+    let mut spec =
+        mgr.cfg
+           .spec_for_ident(ident)
+           .ok_or_else(|| net::err(ErrCode::NotFound, format!("Service not loaded, {}", ident)))?;
+    if spec.desired_state == desired_state {
+        return Ok(None);
+    }
+    spec.desired_state = desired_state;
+    mgr.cfg.save_spec_for(&spec)?;
+    Ok(Some(spec))
+}
+
+/// Save each of `specs` as the revision following its base revision, like `save_spec_revision`,
+/// but all of them or none. If one of the specs was changed since it was read, or one cannot be
+/// written, none is saved. The specs are all written to temporary files before any of them is
+/// moved into place, and if one cannot be moved, the ones already moved are put back as they
+/// were.
+fn save_spec_revisions(mgr: &ManagerState,
+                       specs: Vec<(ServiceSpec, Option<u64>)>)
+                       -> NetResult<Vec<ServiceSpec>> {
+    let _spec_writes = mgr.spec_writes.lock();
+    let mut previous_specs = Vec::with_capacity(specs.len());
+    for (spec, base_revision) in &specs {
+        let previous_spec = mgr.cfg.spec_for_ident(&spec.ident);
+        if previous_spec.as_ref().map(|s| s.revision) != *base_revision {
+            return Err(net::err(ErrCode::Conflict,
+                                format!("{} was changed by another request \
+                                         while this one was running. Look at \
+                                         the changes and try again.",
+                                        spec.ident)));
+        }
+        previous_specs.push((spec.ident.clone(), previous_spec));
+    }
+    let mut staged = Vec::with_capacity(specs.len());
+    for (mut spec, base_revision) in specs {
+        spec.revision = base_revision.map_or(1, |revision| revision + 1);
+        let writer = mgr.cfg.stage_spec_for(&spec)?;
+        staged.push((spec, writer));
+    }
+    let mut saved = Vec::with_capacity(staged.len());
+    for (spec, writer) in staged {
+        if let Err(err) = writer.finish() {
+            for (ident, previous_spec) in previous_specs.iter().take(saved.len()) {
+                let restored = match previous_spec {
+                    Some(previous_spec) => mgr.cfg.save_spec_for(previous_spec),
+                    None => mgr.cfg.remove_spec_for(ident),
+                };
+                if let Err(err) = restored {
+                    outputln!("Unable to put back the spec of {}, {}", ident, err);
+                }
+            }
+            let path = mgr.cfg.spec_path_for(&spec.ident);
+            return Err(Error::ServiceSpecFileIO(path, err).into());
+        }
+        saved.push(spec);
+    }
+    Ok(saved)
+}
+
 #[derive(Deserialize)]
 struct ServiceStatus {
-    pkg:           Pkg,
-    process:       ProcessStatus,
-    service_group: ServiceGroup,
-    desired_state: DesiredState,
+    pkg:            Pkg,
+    process:        ProcessStatus,
+    service_group:  ServiceGroup,
+    desired_state:  DesiredState,
+    #[serde(default)]
+    blocked_by:     Option<String>,
+    #[serde(default)]
+    maintenance:    bool,
+    #[serde(default)]
+    resources:      Option<ResourceUsage>,
+    #[serde(default)]
+    degraded_binds: Vec<String>,
 }
 
 impl From<ServiceStatus> for protocol::types::ServiceStatus {
     fn from(other: ServiceStatus) -> Self {
-        protocol::types::ServiceStatus { ident:         PackageIdent::from(other.pkg.ident).into(),
-                                         process:       Some(other.process.into()),
-                                         service_group: other.service_group.into(),
-                                         desired_state: Some(other.desired_state.into()), }
+        protocol::types::ServiceStatus { ident:          PackageIdent::from(other.pkg.ident).into(),
+                                         process:        Some(other.process.into()),
+                                         service_group:  other.service_group.into(),
+                                         desired_state:  Some(other.desired_state.into()),
+                                         health_check:   None,
+                                         blocked_by:     other.blocked_by,
+                                         spec_revision:  None,
+                                         maintenance:    Some(other.maintenance),
+                                         resources:      None,
+                                         degraded_binds: other.degraded_binds, }
+    }
+}
+
+impl From<ResourceUsage> for protocol::types::ResourceUsage {
+    fn from(other: ResourceUsage) -> Self {
+        protocol::types::ResourceUsage { cpu_seconds:  Some(other.cpu_seconds),
+                                         cpu_percent:  Some(other.cpu_percent),
+                                         memory_bytes: Some(other.memory_bytes),
+                                         open_fds:     Some(other.open_fds),
+                                         processes:    Some(other.processes), }
+    }
+}
+
+/// The health check part of a `ServiceStatus` reply. The hook's output is only included when
+/// `detail` is requested, to keep the replies to plain `hab svc status` small.
+fn health_check_status(health: &HealthCheckData,
+                       detail: bool)
+                       -> protocol::types::HealthCheckStatus {
+    let result = protocol::types::HealthCheckResult::from(health.result);
+    let mut msg = protocol::types::HealthCheckStatus { result: result as i32,
+                                                       ..Default::default() };
+    if detail {
+        msg.stdout = health.output.stdout.clone();
+        msg.stderr = health.output.stderr.clone();
+    }
+    msg
+}
+
+impl From<HealthCheckResult> for protocol::types::HealthCheckResult {
+    fn from(other: HealthCheckResult) -> Self {
+        match other {
+            HealthCheckResult::Ok => protocol::types::HealthCheckResult::Ok,
+            HealthCheckResult::Warning => protocol::types::HealthCheckResult::Warning,
+            HealthCheckResult::Critical => protocol::types::HealthCheckResult::Critical,
+            HealthCheckResult::Unknown => protocol::types::HealthCheckResult::Unknown,
+        }
     }
 }
 
@@ -457,6 +1378,82 @@ fn duration_from_epoch_offset<'de, D>(d: D) -> result::Result<Duration, D::Error
     d.deserialize_u64(FromEpochOffset)
 }
 
+/// Stream the output of the hooks of the service of `spec` to the client of `req`, interleaved
+/// with the other replies, until the service reaches the `awaited` state, then complete the
+/// request. The request fails if the service hasn't once `HAB_CTL_HOOK_OUTPUT_TIMEOUT_SECS`
+/// passed, and is completed right away if it is 0 or the request has no client.
+fn reply_with_hook_output(mgr: &ManagerState,
+                          req: &mut CtlRequest,
+                          spec: &ServiceSpec,
+                          awaited: AwaitedState)
+                          -> NetResult<()> {
+    let timeout: Duration = HookOutputTimeout::configured_value().into();
+    if !req.has_client() || timeout == Duration::ZERO {
+        req.reply_complete(net::ok());
+        return Ok(());
+    }
+
+    let service_group = ServiceGroup::new(&spec.ident.name,
+                                          &spec.group,
+                                          mgr.cfg.organization.as_deref())?;
+    let listener_req = Mutex::new(req.clone());
+    let listener = hooks::listen_to_hook_output(&service_group, move |line| {
+        let msg = protocol::ctl::ConsoleLine { line:  format!("{}\n", line),
+                                               color: None,
+                                               bold:  false, };
+        listener_req.lock().reply_partial(msg);
+    });
+
+    let services = Arc::clone(&mgr.services);
+    let busy_services = Arc::clone(&mgr.busy_services);
+    let ident = spec.ident.clone();
+    let mut req = req.clone();
+    ThreadBuilder::new().name(format!("hook-output-{}", service_group))
+                        .spawn(move || {
+                            let deadline = Instant::now() + timeout;
+                            let mut reached = false;
+                            while Instant::now() < deadline && !req.is_cancelled() {
+                                reached = has_reached(&services, &busy_services, &ident, awaited);
+                                if reached {
+                                    break;
+                                }
+                                thread::sleep(HOOK_OUTPUT_POLL_INTERVAL);
+                            }
+                            drop(listener);
+                            if reached {
+                                req.reply_complete(net::ok());
+                            } else {
+                                let msg = format!("{} has not {} after {} seconds. See the \
+                                                   Supervisor output for more details.",
+                                                  ident,
+                                                  awaited,
+                                                  timeout.as_secs());
+                                req.reply_complete(net::err(ErrCode::DeadlineExceeded, msg));
+                            }
+                        })?;
+    Ok(())
+}
+
+/// # Locking (see locking.md)
+/// * `ManagerServices::inner` (read)
+fn has_reached(services: &ManagerServices,
+               busy_services: &Mutex<HashSet<PackageIdent>>,
+               ident: &PackageIdent,
+               awaited: AwaitedState)
+               -> bool {
+    match awaited {
+        AwaitedState::Started => {
+            services.lock_msr()
+                    .get(ident)
+                    .and_then(PersistentServiceWrapper::service)
+                    .map_or(false, Service::initialized)
+        }
+        AwaitedState::Stopped => {
+            services.lock_msr().get(ident).is_none() && !busy_services.lock().contains(ident)
+        }
+    }
+}
+
 /// Helper function to ensure that all errors in sending are handled identically.
 fn send_action(action: SupervisorAction, sender: &ActionSender) -> NetResult<()> {
     if sender.send(action).is_err() {
@@ -0,0 +1,344 @@
+//! Reloading the Supervisor's own configuration while it is running.
+//!
+//! Only settings which do not require rebinding a listener or rejoining the ring can be applied
+//! to a running Supervisor. Changes to any other setting are reported back to the caller as
+//! requiring a restart.
+
+use super::{ManagerConfig,
+            ServiceRestartConfig};
+use hab::cli::hab::sup::SupRun;
+use habitat_api_client::bandwidth;
+use habitat_butterfly::server::timing::Timing;
+use habitat_common::{output::{self,
+                              OutputFormat,
+                              OutputVerbosity},
+                     types::{EventStreamMetadata,
+                             GossipListenAddr,
+                             ListenCtlAddr}};
+use std::{collections::HashMap,
+          time::Duration};
+
+/// The subset of the Supervisor's settings which can be changed without a restart
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReloadableConfig {
+    pub verbose:                  bool,
+    pub no_color:                 bool,
+    pub json_logging:             bool,
+    /// Only applies to services loaded after the reload
+    pub service_update_period:    Duration,
+    /// Only applies to services started after the reload
+    pub service_restart_config:   ServiceRestartConfig,
+    pub keep_latest_packages:     Option<usize>,
+    pub download_rate_limit:      Option<u64>,
+    pub watch_peer_file:          Option<String>,
+    pub event_stream_application: Option<String>,
+    pub event_stream_environment: Option<String>,
+    pub event_stream_site:        Option<String>,
+    pub event_meta:               EventStreamMetadata,
+}
+
+impl ReloadableConfig {
+    /// The reloadable settings of a Supervisor started with `cfg`.
+    pub fn new(cfg: &ManagerConfig) -> Self {
+        let format = output::get_format();
+        let event_stream_config = cfg.event_stream_config.as_ref();
+        let event_stream_application = event_stream_config.map(|c| c.application.clone());
+        let event_stream_environment = event_stream_config.map(|c| c.environment.clone());
+        let event_stream_site = event_stream_config.and_then(|c| c.site.clone());
+        let event_meta = event_stream_config.map(|c| c.meta.clone())
+                                            .unwrap_or_default();
+        ReloadableConfig { verbose: output::get_verbosity() == OutputVerbosity::Verbose,
+                           no_color: matches!(format, OutputFormat::NoColor),
+                           json_logging: matches!(format, OutputFormat::Json),
+                           service_update_period: cfg.service_update_period,
+                           service_restart_config: cfg.service_restart_config.clone(),
+                           keep_latest_packages: cfg.keep_latest_packages,
+                           download_rate_limit: cfg.download_rate_limit,
+                           watch_peer_file: cfg.watch_peer_file.clone(),
+                           event_stream_application,
+                           event_stream_environment,
+                           event_stream_site,
+                           event_meta }
+    }
+
+    pub fn from_sup_run(sup_run: &SupRun) -> Self {
+        ReloadableConfig { verbose:                  sup_run.verbose,
+                           no_color:                 sup_run.no_color,
+                           json_logging:             sup_run.json_logging,
+                           service_update_period:    sup_run.service_update_period.into(),
+                           service_restart_config:
+                               ServiceRestartConfig::new(sup_run.service_min_backoff_period.into(),
+                                                         sup_run.service_max_backoff_period.into(),
+                                                         sup_run.service_restart_cooldown_period
+                                                                .into()),
+                           keep_latest_packages:     sup_run.keep_latest_packages,
+                           download_rate_limit:      sup_run.download_rate_limit,
+                           watch_peer_file:
+                               sup_run.peer_watch_file
+                                      .as_ref()
+                                      .map(|p| p.to_string_lossy().to_string()),
+                           event_stream_application: sup_run.event_stream_application.clone(),
+                           event_stream_environment: sup_run.event_stream_environment.clone(),
+                           event_stream_site:        sup_run.event_stream_site.clone(),
+                           event_meta:               sup_run.event_meta.clone().into(), }
+    }
+
+    /// The names of the settings which differ between `self` and `other`.
+    pub fn changed_settings(&self, other: &Self) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        macro_rules! compare {
+            ($($field:ident),*) => {
+                $(
+                    if self.$field != other.$field {
+                        changed.push(stringify!($field));
+                    }
+                )*
+            };
+        }
+        compare!(verbose,
+                 no_color,
+                 json_logging,
+                 service_update_period,
+                 service_restart_config,
+                 keep_latest_packages,
+                 download_rate_limit,
+                 watch_peer_file,
+                 event_stream_application,
+                 event_stream_environment,
+                 event_stream_site,
+                 event_meta);
+        changed
+    }
+
+    /// Apply the download rate limit to all subsequent artifact downloads.
+    pub fn apply_download_rate_limit(&self) {
+        bandwidth::set_download_rate_limit(self.download_rate_limit);
+    }
+
+    /// Apply the output settings to the Supervisor's own output.
+    pub fn apply_output_settings(&self) {
+        output::set_verbosity(if self.verbose {
+                                  OutputVerbosity::Verbose
+                              } else {
+                                  OutputVerbosity::Normal
+                              });
+        if self.json_logging {
+            output::set_format(OutputFormat::Json)
+        } else if self.no_color {
+            output::set_format(OutputFormat::NoColor)
+        } else {
+            output::set_format(OutputFormat::Color(Default::default()))
+        }
+    }
+}
+
+/// Whether the reloadable `setting` only applies to the services loaded after the reload, leaving
+/// the running ones as they are
+pub fn applies_to_new_services_only(setting: &str) -> bool {
+    matches!(setting, "service_update_period" | "service_restart_config")
+}
+
+/// The names of the settings in `sup_run` which differ from the running configuration, `cfg`, but
+/// can only be applied by restarting the Supervisor.
+pub fn settings_requiring_restart(cfg: &ManagerConfig, sup_run: &SupRun) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+    let gossip_listen = if sup_run.local_gossip_mode {
+        GossipListenAddr::local_only()
+    } else {
+        sup_run.listen_gossip
+    };
+    if gossip_listen != cfg.gossip_listen {
+        changed.push("listen_gossip");
+    }
+    if sup_run.listen_http != cfg.http_listen {
+        changed.push("listen_http");
+    }
+    if ListenCtlAddr::from(sup_run.listen_ctl.clone()) != cfg.ctl_listen {
+        changed.push("listen_ctl");
+    }
+    if sup_run.http_disable != cfg.http_disable {
+        changed.push("http_disable");
+    }
+    if sup_run.organization != cfg.organization {
+        changed.push("organization");
+    }
+    if sup_run.peer != cfg.gossip_peers {
+        changed.push("peer");
+    }
+    if sup_run.permanent_peer != cfg.gossip_permanent {
+        changed.push("permanent_peer");
+    }
+    if sup_run.observer != cfg.observer {
+        changed.push("observer");
+    }
+    if sup_run.gossip_advertise_addr != cfg.gossip_advertise {
+        changed.push("gossip_advertise_addr");
+    }
+    if sup_run.gossip_stun_server != cfg.gossip_stun_server {
+        changed.push("gossip_stun_server");
+    }
+    if sup_run.zone != cfg.sys_zone {
+        changed.push("zone");
+    }
+    let labels: HashMap<String, String> = sup_run.label.iter().cloned().map(Into::into).collect();
+    if labels != cfg.sys_labels {
+        changed.push("label");
+    }
+    if sup_run.fleet_manifest_origin != cfg.fleet_manifest_origin {
+        changed.push("fleet_manifest_origin");
+    }
+    if sup_run.auto_update != cfg.auto_update {
+        changed.push("auto_update");
+    }
+    if Duration::from(sup_run.auto_update_period) != cfg.auto_update_period {
+        changed.push("auto_update_period");
+    }
+    if sup_run.sys_ip_address.map_or(false, |ip| ip != cfg.sys_ip) {
+        changed.push("sys_ip_address");
+    }
+    let tls_config = cfg.tls_config.as_ref();
+    if sup_run.key_file.as_ref() != tls_config.map(|c| &c.key_path) {
+        changed.push("key_file");
+    }
+    if sup_run.cert_file.as_ref() != tls_config.map(|c| &c.cert_path) {
+        changed.push("cert_file");
+    }
+    if sup_run.ca_cert_file.as_ref() != tls_config.and_then(|c| c.ca_cert_path.as_ref()) {
+        changed.push("ca_cert_file");
+    }
+    let ring_tls = cfg.ring_tls.as_ref();
+    if sup_run.ring_tls_cert_file.as_ref() != ring_tls.map(|c| &c.cert_path) {
+        changed.push("ring_tls_cert_file");
+    }
+    if sup_run.ring_tls_key_file.as_ref() != ring_tls.map(|c| &c.key_path) {
+        changed.push("ring_tls_key_file");
+    }
+    if sup_run.ring_tls_ca_cert_file.as_ref() != ring_tls.map(|c| &c.ca_cert_path) {
+        changed.push("ring_tls_ca_cert_file");
+    }
+    if ring_tls.map_or(false, |c| sup_run.ring_tls_server_name != c.server_name) {
+        changed.push("ring_tls_server_name");
+    }
+    let event_stream_config = cfg.event_stream_config.as_ref();
+    if sup_run.event_stream_url.as_ref().map(ToString::to_string)
+       != event_stream_config.map(|c| c.url.to_string())
+    {
+        changed.push("event_stream_url");
+    }
+    if sup_run.event_stream_token.as_ref() != event_stream_config.map(|c| &c.token) {
+        changed.push("event_stream_token");
+    }
+    if sup_run.listen_ctl_grpc != cfg.ctl_grpc_listen {
+        changed.push("listen_ctl_grpc");
+    }
+    if sup_run.peer_kubernetes_service != cfg.peer_kubernetes_service {
+        changed.push("peer_kubernetes_service");
+    }
+    if sup_run.peer_ec2 != cfg.peer_ec2 {
+        changed.push("peer_ec2");
+    }
+    if sup_run.peer_mdns != cfg.peer_mdns {
+        changed.push("peer_mdns");
+    }
+    let peer_consul = cfg.peer_consul.as_ref();
+    if sup_run.peer_consul_service.as_ref() != peer_consul.map(|c| &c.service) {
+        changed.push("peer_consul_service");
+    }
+    if peer_consul.map_or(false, |c| {
+                      sup_run.peer_consul_addr != c.address
+                      || sup_run.peer_consul_datacenter != c.datacenter
+                      || sup_run.peer_consul_token != c.token
+                  })
+    {
+        changed.push("peer_consul");
+    }
+    let state_snapshot = cfg.state_snapshot.as_ref();
+    if sup_run.state_snapshot_path.as_ref() != state_snapshot.map(|c| &c.path) {
+        changed.push("state_snapshot_path");
+    }
+    if state_snapshot.map_or(false, |c| {
+                         Duration::from(sup_run.state_snapshot_interval) != c.interval
+                     })
+    {
+        changed.push("state_snapshot_interval");
+    }
+    let prefetch = cfg.prefetch.as_ref();
+    if sup_run.prefetch_file.as_ref() != prefetch.map(|c| &c.path) {
+        changed.push("prefetch_file");
+    }
+    if prefetch.map_or(false, |c| {
+                   Duration::from(sup_run.prefetch_interval) != c.interval
+               })
+    {
+        changed.push("prefetch_interval");
+    }
+    let acme = cfg.acme.as_ref();
+    if sup_run.acme_directory.as_ref() != acme.map(|c| &c.directory) {
+        changed.push("acme_directory");
+    }
+    if sup_run.acme_email.as_ref() != acme.map(|c| &c.email) {
+        changed.push("acme_email");
+    }
+    if sup_run.extension != cfg.extension {
+        changed.push("extension");
+    }
+    if sup_run.member_id_source != cfg.member_id_source {
+        changed.push("member_id_source");
+    }
+    if sup_run.deny != cfg.denylist {
+        changed.push("deny");
+    }
+    let swim_timing = Timing::new(Duration::from_millis(sup_run.swim_protocol_period),
+                                  Duration::from_millis(sup_run.swim_ping_timeout),
+                                  sup_run.swim_ping_req_fanout,
+                                  sup_run.swim_suspicion_timeout);
+    if swim_timing.map_or(true, |timing| timing != cfg.swim_timing) {
+        changed.push("swim_timing");
+    }
+    if sup_run.rumor_ttl.map(Duration::from) != cfg.rumor_ttl {
+        changed.push("rumor_ttl");
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{ffi::OsString,
+              fs};
+    use tempfile::TempDir;
+
+    #[test]
+    fn changed_settings_lists_only_differing_fields() {
+        let cfg = ManagerConfig::default();
+        let current = ReloadableConfig::new(&cfg);
+        assert!(current.changed_settings(&current.clone()).is_empty());
+
+        let new = ReloadableConfig { service_update_period: Duration::from_secs(5),
+                                     keep_latest_packages: Some(2),
+                                     ..current.clone() };
+        assert_eq!(current.changed_settings(&new),
+                   vec!["service_update_period", "keep_latest_packages"]);
+    }
+
+    #[test]
+    fn settings_are_reloaded_from_the_default_config_file() {
+        let tmp = TempDir::new().expect("Could not create temp dir");
+        let config_file = tmp.path().join("sup.toml");
+        fs::write(&config_file,
+                  "listen_gossip = \"1.2.3.4:4321\"\nkeep_latest_packages = 2\n").unwrap();
+        // The Supervisor was started with no arguments, taking its settings from the file
+        let run_args = vec![OsString::from("run")];
+        let sup_run =
+            SupRun::try_from_args_with_config_file_of(&run_args, |_| config_file.clone()).unwrap();
+        let cfg = ManagerConfig { gossip_listen: "1.2.3.4:4321".parse().unwrap(),
+                                  keep_latest_packages: Some(2),
+                                  ..ManagerConfig::default() };
+
+        let reloaded = ReloadableConfig::from_sup_run(&sup_run);
+        assert_eq!(reloaded.keep_latest_packages, Some(2));
+        let changed = ReloadableConfig::new(&cfg).changed_settings(&reloaded);
+        assert!(!changed.contains(&"keep_latest_packages"));
+        assert!(!settings_requiring_restart(&cfg, &sup_run).contains(&"listen_gossip"));
+    }
+}
@@ -0,0 +1,76 @@
+//! Persists the members this Supervisor refuses gossip from, so that a member ejected from the
+//! ring with `hab sup deny` stays out of it across restarts until `hab sup deny --allow` lets it
+//! back.
+//!
+//! The file holds one entry per line. Entries given with `hab sup run --deny` are added to it
+//! every time the Supervisor starts.
+
+use crate::error::{Error,
+                   Result};
+use habitat_common::types::DenylistEntry;
+use habitat_core::fs::AtomicWriter;
+use std::{fs,
+          io::{self,
+               Write},
+          path::Path};
+
+/// Read the entries of the file at `path`. A missing file is an empty denylist.
+pub fn read(path: &Path) -> Result<Vec<DenylistEntry>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::BadDataFile(path.to_path_buf(), e)),
+    };
+    Ok(parse(&contents))
+}
+
+/// Atomically replace the file at `path` with `entries`
+pub fn write(path: &Path, entries: &[DenylistEntry]) -> Result<()> {
+    let w = AtomicWriter::new(path)?;
+    w.with_writer(|f| {
+         for entry in entries {
+             writeln!(f, "{}", entry)?;
+         }
+         Ok::<_, io::Error>(())
+     })
+     .map_err(|e| Error::BadDataFile(path.to_path_buf(), e))
+}
+
+fn parse(contents: &str) -> Vec<DenylistEntry> {
+    contents.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| {
+                line.parse()
+                    .map_err(|e| warn!("Ignoring denylist entry '{}', {}", line, e))
+                    .ok()
+            })
+            .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn entries_round_trip_through_the_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("denylist");
+        assert!(read(&path).unwrap().is_empty());
+
+        let entries: Vec<DenylistEntry> = vec!["bad".parse().unwrap(),
+                                               "10.0.4.0/24".parse().unwrap(),
+                                               "10.0.5.7".parse().unwrap()];
+        write(&path, &entries).unwrap();
+        assert_eq!(read(&path).unwrap(), entries);
+    }
+
+    #[test]
+    fn invalid_entries_are_skipped() {
+        let entries = parse("bad\n\n10.0.4.0/33\n10.0.4.0/24\n");
+        assert_eq!(entries,
+                   vec!["bad".parse::<DenylistEntry>().unwrap(),
+                        "10.0.4.0/24".parse().unwrap()]);
+    }
+}
@@ -26,6 +26,21 @@ use std::{collections::{hash_map::Entry,
 
 pub const WATCHER_DELAY_MS: u64 = 2_000;
 
+habitat_core::env_config_duration!(
+    /// How long to wait to consolidate the filesystem events of a watched file into one
+    ///
+    /// Raising this keeps busy directories, such as ones on a network filesystem, from causing a
+    /// storm of events.
+    FileWatcherDelay,
+    HAB_FILE_WATCHER_DELAY_MS => from_millis,
+    Duration::from_millis(WATCHER_DELAY_MS));
+
+habitat_core::env_config_duration!(
+    /// How often a file watcher checks for consolidated events
+    FileWatcherPollInterval,
+    HAB_FILE_WATCHER_POLL_INTERVAL_MS => from_millis,
+    Duration::from_secs(1));
+
 /// A set of callbacks for the watched file events.
 pub trait Callbacks {
     /// A function that gets called when the watched file shows up.
@@ -1172,10 +1187,8 @@ impl<C: Callbacks, W: Watcher> FileWatcher<C, W> {
         where P: Into<PathBuf>
     {
         let (tx, rx) = channel();
-        let mut watcher =
-            W::new(tx, Duration::from_millis(WATCHER_DELAY_MS)).map_err(|err| {
-                                                                   Error::NotifyCreateError(err)
-                                                               })?;
+        let delay = FileWatcherDelay::configured_value().into();
+        let mut watcher = W::new(tx, delay).map_err(Error::NotifyCreateError)?;
         let start_path = Self::watcher_path(path.into())?;
         // Initialize the Paths struct, which will hold all state
         // relative to file watching.
@@ -1236,12 +1249,13 @@ impl<C: Callbacks, W: Watcher> FileWatcher<C, W> {
     }
 
     pub fn run(&mut self) -> Result<()> {
+        let poll_interval = FileWatcherPollInterval::configured_value().into();
         let loop_value: liveliness_checker::ThreadUnregistered<_, _> = loop {
             let checked_thread = liveliness_checker::mark_thread_alive();
             if let result @ Err(_) = self.single_iteration() {
                 break checked_thread.unregister(result);
             }
-            thread::sleep(Duration::from_secs(1));
+            thread::sleep(poll_interval);
         };
         loop_value.into_result()
     }
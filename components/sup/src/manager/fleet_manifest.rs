@@ -0,0 +1,293 @@
+//! Reconciling the services loaded on this Supervisor to the ring's fleet manifest.
+//!
+//! A fleet manifest is a TOML document listing the services which should run across the ring,
+//! each with a selector of the Supervisor labels it should run on. It is signed with an origin
+//! key and gossiped to the whole ring. Supervisors started with `--fleet-manifest-origin` verify
+//! every newer manifest they hear of, write spec files for the services it selects for them, and
+//! remove the spec files of services an earlier manifest loaded but the new one no longer
+//! selects. Services loaded on a Supervisor directly are never changed by a manifest.
+
+use super::service::spec::ServiceSpec;
+use crate::error::{Error,
+                   Result};
+use habitat_butterfly::{rumor::fleet_manifest::FleetManifest as FleetManifestRumor,
+                        Server};
+use habitat_common::outputln;
+use habitat_core::{crypto::keys::{KeyCache,
+                                  NamedRevision},
+                   fs::{checksummed_read,
+                        checksummed_recover,
+                        checksummed_write},
+                   origin::Origin,
+                   package::PackageIdent};
+use std::{collections::{HashMap,
+                        HashSet},
+          io,
+          path::{Path,
+                 PathBuf},
+          str::{self,
+                FromStr}};
+
+static LOGKEY: &str = "FM";
+pub const APPLIED_MANIFEST_FILE: &str = "fleet_manifest.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FleetManifest {
+    #[serde(default, rename = "service")]
+    services: Vec<ManifestService>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestService {
+    /// The labels a Supervisor must have for the service to be loaded on it. An empty selector
+    /// selects every Supervisor.
+    #[serde(default)]
+    selector: HashMap<String, String>,
+    #[serde(flatten)]
+    spec:     ServiceSpec,
+}
+
+impl ManifestService {
+    fn selects(&self, labels: &HashMap<String, String>) -> bool {
+        self.selector
+            .iter()
+            .all(|(key, value)| labels.get(key) == Some(value))
+    }
+}
+
+impl FromStr for FleetManifest {
+    type Err = Error;
+
+    fn from_str(toml: &str) -> Result<Self> {
+        let manifest: FleetManifest = toml::from_str(toml).map_err(Error::FleetManifestParse)?;
+        let mut names = HashSet::new();
+        for service in &manifest.services {
+            if service.spec.ident == PackageIdent::default() {
+                return Err(Error::MissingRequiredIdent);
+            }
+            // Spec files are named after the service, so a Supervisor can only load one
+            // service of each name
+            if !names.insert(&service.spec.ident.name) {
+                let msg = format!("service '{}' is listed more than once",
+                                  service.spec.ident.name);
+                return Err(Error::FleetManifestInvalid(msg));
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+impl FleetManifest {
+    /// The specs of the services this manifest selects for a Supervisor with `labels`
+    pub fn specs_for(&self, labels: &HashMap<String, String>) -> Vec<ServiceSpec> {
+        self.services
+            .iter()
+            .filter(|service| service.selects(labels))
+            .map(|service| service.spec.clone())
+            .collect()
+    }
+}
+
+/// The fleet manifest this Supervisor last reconciled to. It is persisted so the services it
+/// loaded can be unloaded when a later manifest no longer selects them, even across restarts.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct AppliedManifest {
+    version:  u64,
+    #[serde(default)]
+    services: Vec<PackageIdent>,
+}
+
+impl AppliedManifest {
+    fn read(path: &Path) -> Result<Self> { Self::parse(&checksummed_read(path)?) }
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        toml::from_str(str::from_utf8(data)?).map_err(Error::FleetManifestParse)
+    }
+}
+
+/// Check that the applied fleet manifest at `path` is intact
+pub fn verify_applied(path: &Path) -> Result<()> { AppliedManifest::read(path).map(|_| ()) }
+
+pub struct FleetManifestReconciler {
+    origin:         Origin,
+    path:           PathBuf,
+    applied:        AppliedManifest,
+    update_counter: usize,
+    /// The version of the last manifest that failed verification, so that the failure is only
+    /// reported once while the manifest is verified again on every check
+    rejected:       Option<u64>,
+}
+
+impl FleetManifestReconciler {
+    /// Create a reconciler for manifests signed by `origin`, picking up from the manifest last
+    /// applied by a previous run of the Supervisor with this `data_path`.
+    pub fn new(origin: Origin, data_path: &Path) -> Self {
+        let path = data_path.join(APPLIED_MANIFEST_FILE);
+        let applied = match AppliedManifest::read(&path) {
+            Ok(applied) => applied,
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => AppliedManifest::default(),
+            Err(e) => {
+                match checksummed_recover(&path).map_err(Error::from)
+                                                .and_then(|data| AppliedManifest::parse(&data))
+                {
+                    Ok(applied) => {
+                        outputln!("The applied fleet manifest in {} was corrupt ({}), restored \
+                                   the last good copy",
+                                  path.display(),
+                                  e);
+                        applied
+                    }
+                    Err(_) => {
+                        outputln!("Unable to read the applied fleet manifest from {}, {}",
+                                  path.display(),
+                                  e);
+                        AppliedManifest::default()
+                    }
+                }
+            }
+        };
+        FleetManifestReconciler { origin,
+                                  path,
+                                  applied,
+                                  update_counter: 0,
+                                  rejected: None }
+    }
+
+    /// The version and contents of the newest fleet manifest gossiped to the ring, if it is newer
+    /// than the one last applied and is signed by a key of the trusted origin. A manifest that
+    /// fails verification is verified again on the next check, as the key it is signed with may
+    /// only be missing from the key cache for now.
+    ///
+    /// # Locking (see locking.md)
+    /// * `RumorStore::list` (read)
+    pub fn newer_manifest_rsr(&mut self,
+                              butterfly: &Server,
+                              key_cache: &KeyCache)
+                              -> Option<(u64, FleetManifest)> {
+        let update_counter = butterfly.fleet_manifest_store.get_update_counter();
+        if update_counter == self.update_counter && self.rejected.is_none() {
+            return None;
+        }
+        let rumor = butterfly.fleet_manifest_rsr()?;
+        if rumor.incarnation <= self.applied.version {
+            self.update_counter = update_counter;
+            self.rejected = None;
+            return None;
+        }
+        match self.verify(&rumor, key_cache) {
+            Ok(manifest) => {
+                self.update_counter = update_counter;
+                self.rejected = None;
+                Some((rumor.incarnation, manifest))
+            }
+            Err(e) => {
+                if self.rejected != Some(rumor.incarnation) {
+                    outputln!("Ignoring fleet manifest version {}: {}",
+                              rumor.incarnation,
+                              e);
+                    self.rejected = Some(rumor.incarnation);
+                }
+                None
+            }
+        }
+    }
+
+    fn verify(&self, rumor: &FleetManifestRumor, key_cache: &KeyCache) -> Result<FleetManifest> {
+        let signer: NamedRevision = rumor.signer.parse()?;
+        if signer.name().as_str() != self.origin.as_ref() {
+            return Err(Error::FleetManifestUntrusted(rumor.signer.clone()));
+        }
+        key_cache.public_signing_key(&signer)?
+                 .verify(&rumor.signature, &mut rumor.body.as_slice())?;
+        str::from_utf8(&rumor.body)?.parse()
+    }
+
+    /// Whether the last applied manifest loaded a service with the same name as `ident`
+    pub fn loaded(&self, ident: &PackageIdent) -> bool {
+        self.applied
+            .services
+            .iter()
+            .any(|loaded| loaded.name == ident.name)
+    }
+
+    /// The services the last applied manifest loaded
+    pub fn loaded_services(&self) -> &[PackageIdent] { &self.applied.services }
+
+    /// Record that manifest `version` has been applied, loading `services`.
+    pub fn applied(&mut self, version: u64, services: Vec<PackageIdent>) {
+        self.applied = AppliedManifest { version, services };
+        let result = toml::to_string(&self.applied).map_err(Error::TomlEncode)
+                                                   .and_then(|toml| {
+                                                       checksummed_write(&self.path, toml)
+                                                           .map_err(Error::from)
+                                                   });
+        if let Err(e) = result {
+            outputln!("Unable to save the applied fleet manifest to {}, {}",
+                      self.path.display(),
+                      e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manager::service::Topology;
+
+    const MANIFEST: &str = r#"
+[[service]]
+ident = "core/redis"
+group = "cache"
+topology = "leader"
+[service.selector]
+role = "cache"
+
+[[service]]
+ident = "core/node-exporter"
+"#;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter()
+             .map(|(k, v)| (k.to_string(), v.to_string()))
+             .collect()
+    }
+
+    fn names(specs: Vec<ServiceSpec>) -> Vec<String> {
+        specs.into_iter().map(|s| s.ident.name).collect()
+    }
+
+    #[test]
+    fn services_are_selected_by_label() {
+        let manifest: FleetManifest = MANIFEST.parse().unwrap();
+        assert_eq!(names(manifest.specs_for(&labels(&[("role", "cache"), ("disk", "ssd")]))),
+                   vec!["redis", "node-exporter"]);
+        assert_eq!(names(manifest.specs_for(&labels(&[("role", "web")]))),
+                   vec!["node-exporter"]);
+        assert_eq!(names(manifest.specs_for(&HashMap::new())),
+                   vec!["node-exporter"]);
+    }
+
+    #[test]
+    fn services_take_spec_settings() {
+        let manifest: FleetManifest = MANIFEST.parse().unwrap();
+        let spec = manifest.specs_for(&labels(&[("role", "cache")])).remove(0);
+        assert_eq!(spec.group, "cache");
+        assert_eq!(spec.topology, Topology::Leader);
+    }
+
+    #[test]
+    fn services_must_have_an_ident() {
+        let manifest = "[[service]]\ngroup = \"cache\"\n";
+        assert!(matches!(manifest.parse::<FleetManifest>(),
+                         Err(Error::MissingRequiredIdent)));
+    }
+
+    #[test]
+    fn services_may_only_be_listed_once() {
+        let manifest =
+            "[[service]]\nident = \"core/redis\"\n\n[[service]]\nident = \"other/redis\"\n";
+        assert!(matches!(manifest.parse::<FleetManifest>(),
+                         Err(Error::FleetManifestInvalid(_))));
+    }
+}
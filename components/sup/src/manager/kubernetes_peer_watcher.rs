@@ -0,0 +1,229 @@
+//! Watches the endpoints of a Kubernetes Service, usually a headless Service of a StatefulSet,
+//! for the members to join the ring through.
+//!
+//! The endpoints are read from the API server with the credentials of the pod's service account,
+//! on a task of their own, and a change of the peers is reported like a change of a peer watch
+//! file. The service account token is read again for every request, as the kubelet replaces a
+//! bound token well before it expires. Addresses of pods that are not ready yet are included, as
+//! Supervisors need to gossip with each other before their services can become ready.
+
+use super::peer_source::{DiscoveredPeers,
+                         PeerSource};
+use crate::error::{Error,
+                   Result};
+use habitat_butterfly::member::Member;
+use habitat_common::{outputln,
+                     types::{GossipListenAddr,
+                             KubernetesService}};
+use reqwest::{Certificate,
+              Client};
+use serde::Deserialize;
+use std::{env,
+          fs,
+          net::{IpAddr,
+                SocketAddr},
+          path::{Path,
+                 PathBuf},
+          time::Duration};
+use tokio::time;
+
+static LOGKEY: &str = "KW";
+
+const SERVICE_ACCOUNT_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+const SERVICE_HOST_ENVVAR: &str = "KUBERNETES_SERVICE_HOST";
+const SERVICE_PORT_ENVVAR: &str = "KUBERNETES_SERVICE_PORT";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+habitat_core::env_config_duration!(KubernetesPollInterval,
+                                   HAB_PEER_KUBERNETES_POLL_INTERVAL_SECS => from_secs,
+                                   Duration::from_secs(10));
+
+/// The parts of the `Endpoints` object of a Service we read
+#[derive(Debug, Default, Deserialize)]
+struct Endpoints {
+    #[serde(default)]
+    subsets: Vec<EndpointSubset>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EndpointSubset {
+    #[serde(default)]
+    addresses:           Vec<EndpointAddress>,
+    #[serde(default)]
+    not_ready_addresses: Vec<EndpointAddress>,
+    #[serde(default)]
+    ports:               Vec<EndpointPort>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EndpointAddress {
+    ip: IpAddr,
+}
+
+#[derive(Debug, Deserialize)]
+struct EndpointPort {
+    name: Option<String>,
+    port: u16,
+}
+
+/// A client of the API server, authenticated as the pod's service account
+struct ApiClient {
+    client:     Client,
+    base_url:   String,
+    token_path: PathBuf,
+}
+
+impl ApiClient {
+    fn in_cluster() -> Result<Self> {
+        let dir = Path::new(SERVICE_ACCOUNT_DIR);
+        let host = match env::var(SERVICE_HOST_ENVVAR) {
+            Ok(host) => host,
+            Err(_) => {
+                let msg = format!("{} is not set, the Supervisor is not running in a pod",
+                                  SERVICE_HOST_ENVVAR);
+                return Err(kubernetes_error(msg));
+            }
+        };
+        let port = env::var(SERVICE_PORT_ENVVAR).unwrap_or_else(|_| String::from("443"));
+        let token_path = dir.join("token");
+        // Fail at startup, rather than on every poll, without a token to read
+        fs::metadata(&token_path)?;
+        let ca = Certificate::from_pem(&fs::read(dir.join("ca.crt"))?).map_err(kubernetes_error)?;
+        let client = Client::builder().add_root_certificate(ca)
+                                      .timeout(REQUEST_TIMEOUT)
+                                      .build()
+                                      .map_err(kubernetes_error)?;
+        // IPv6 addresses of the API server must be bracketed in the URL
+        let host = if host.contains(':') {
+            format!("[{}]", host)
+        } else {
+            host
+        };
+        Ok(ApiClient { client,
+                       base_url: format!("https://{}:{}", host, port),
+                       token_path })
+    }
+
+    async fn endpoints(&self, namespace: &str, name: &str) -> Result<Endpoints> {
+        let token = tokio::fs::read_to_string(&self.token_path).await?;
+        let url = format!("{}/api/v1/namespaces/{}/endpoints/{}",
+                          self.base_url, namespace, name);
+        self.client
+            .get(&url)
+            .bearer_auth(token.trim())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(kubernetes_error)?
+            .json()
+            .await
+            .map_err(kubernetes_error)
+    }
+}
+
+pub struct KubernetesPeerWatcher {
+    peers: DiscoveredPeers,
+}
+
+impl KubernetesPeerWatcher {
+    pub fn run(service: KubernetesService) -> Result<Self> {
+        let namespace = match &service.namespace {
+            Some(namespace) => namespace.clone(),
+            None => {
+                let path = Path::new(SERVICE_ACCOUNT_DIR).join("namespace");
+                fs::read_to_string(path)?.trim().to_string()
+            }
+        };
+        let client = ApiClient::in_cluster()?;
+        let peers = DiscoveredPeers::default();
+        let interval = KubernetesPollInterval::configured_value().into();
+
+        let peers_for_task = peers.clone();
+        tokio::spawn(async move {
+            loop {
+                match client.endpoints(&namespace, &service.name).await {
+                    Ok(endpoints) => {
+                        let port_name = service.port_name.as_deref();
+                        peers_for_task.update(peer_addrs(&endpoints, port_name));
+                    }
+                    Err(e) => {
+                        outputln!("Unable to read the endpoints of the Kubernetes Service {}, {}",
+                                  service,
+                                  e)
+                    }
+                }
+                time::sleep(interval).await;
+            }
+        });
+
+        Ok(KubernetesPeerWatcher { peers })
+    }
+}
+
+impl PeerSource for KubernetesPeerWatcher {
+    fn has_events(&self) -> bool { self.peers.has_events() }
+
+    fn get_members(&self) -> Result<Vec<Member>> { Ok(self.peers.members()) }
+}
+
+/// The addresses of the peers behind `endpoints`. The gossip port is the port named `port_name`,
+/// the only port of the Service if it has one, or the default gossip port.
+fn peer_addrs(endpoints: &Endpoints, port_name: Option<&str>) -> Vec<SocketAddr> {
+    let mut addrs = Vec::new();
+    for subset in &endpoints.subsets {
+        let port = match (port_name, subset.ports.as_slice()) {
+            (Some(port_name), ports) => {
+                match ports.iter().find(|p| p.name.as_deref() == Some(port_name)) {
+                    Some(port) => port.port,
+                    None => continue,
+                }
+            }
+            (None, [port]) => port.port,
+            (None, _) => GossipListenAddr::DEFAULT_PORT,
+        };
+        addrs.extend(subset.addresses
+                           .iter()
+                           .chain(subset.not_ready_addresses.iter())
+                           .map(|address| SocketAddr::new(address.ip, port)));
+    }
+    addrs
+}
+
+fn kubernetes_error(e: impl ToString) -> Error { Error::KubernetesPeers(e.to_string()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints() -> Endpoints {
+        serde_json::from_str(
+                             r#"{
+            "kind": "Endpoints",
+            "subsets": [{
+                "addresses": [{"ip": "10.0.0.2"}, {"ip": "10.0.0.1"}],
+                "notReadyAddresses": [{"ip": "10.0.0.3"}],
+                "ports": [{"name": "gossip", "port": 9000}, {"name": "http", "port": 9631}]
+            }]
+        }"#,
+        ).unwrap()
+    }
+
+    #[test]
+    fn peers_use_the_named_port() {
+        let addrs = peer_addrs(&endpoints(), Some("gossip"));
+        assert_eq!(addrs,
+                   vec!["10.0.0.2:9000".parse::<SocketAddr>().unwrap(),
+                        "10.0.0.1:9000".parse().unwrap(),
+                        "10.0.0.3:9000".parse().unwrap()]);
+        assert!(peer_addrs(&endpoints(), Some("ctl")).is_empty());
+    }
+
+    #[test]
+    fn peers_use_the_default_port_without_a_port_name() {
+        let addrs = peer_addrs(&endpoints(), None);
+        assert!(addrs.iter()
+                     .all(|addr| addr.port() == GossipListenAddr::DEFAULT_PORT));
+        assert!(peer_addrs(&Endpoints::default(), None).is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+//! Derives the Supervisor's member ID from a stable identity of its host, rather than generating a
+//! random one, so that a reimaged host rejoins the ring as the member it was instead of leaving a
+//! departed one behind.
+
+use super::peer_source::ec2;
+use crate::error::{Error,
+                   Result};
+use habitat_common::types::MemberIdSource;
+use habitat_core::crypto::Blake2bHash;
+use std::fs;
+
+/// The files the host's machine ID is read from, in order of preference
+const MACHINE_ID_FILES: &[&str] = &["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
+/// Read the identity of `source` and derive a member ID from it
+pub async fn derive(source: &MemberIdSource) -> Result<String> {
+    let identity = match source {
+        MemberIdSource::MachineId => machine_id()?,
+        MemberIdSource::InstanceId => {
+            ec2::instance_metadata("instance-id").await
+                                                 .map_err(|e| member_id_error(source, e))?
+        }
+        MemberIdSource::Value(value) => value.clone(),
+    };
+    if identity.is_empty() {
+        return Err(member_id_error(source, "the identity is empty"));
+    }
+    Ok(member_id(&identity))
+}
+
+/// The member ID of `identity`, which like a random member ID is 32 hexadecimal digits
+fn member_id(identity: &str) -> String {
+    let mut id = Blake2bHash::from_bytes(format!("habitat-member-id:{}", identity)).to_string();
+    id.truncate(32);
+    id
+}
+
+fn machine_id() -> Result<String> {
+    MACHINE_ID_FILES.iter()
+                    .find_map(|path| fs::read_to_string(path).ok())
+                    .map(|id| id.trim().to_string())
+                    .ok_or_else(|| {
+                        member_id_error(&MemberIdSource::MachineId,
+                                        format!("none of {} could be read",
+                                                MACHINE_ID_FILES.join(", ")))
+                    })
+}
+
+fn member_id_error(source: &MemberIdSource, e: impl ToString) -> Error {
+    Error::MemberIdSource(source.to_string(), e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn member_ids_are_derived_deterministically() {
+        let id = member_id("web-01.example.com");
+        assert_eq!(id, member_id("web-01.example.com"));
+        assert_ne!(id, member_id("web-02.example.com"));
+        assert_eq!(id.len(), 32);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[tokio::test]
+    async fn empty_identities_are_rejected() {
+        let source = MemberIdSource::Value(String::new());
+        assert!(derive(&source).await.is_err());
+    }
+}
@@ -0,0 +1,95 @@
+//! Sources of the members a Supervisor joins the ring through while it has no one to gossip with.
+//!
+//! A source is polled from the main loop of the manager, so it discovers peers on a thread or
+//! task of its own and only reports them when asked.
+
+mod consul;
+pub(super) mod ec2;
+mod mdns;
+
+pub use self::{consul::{ConsulPeerConfig,
+                        ConsulPeerSource},
+               ec2::Ec2PeerSource,
+               mdns::MdnsPeerSource};
+use crate::error::Result;
+use habitat_butterfly::member::{self,
+                                Member};
+use parking_lot::Mutex;
+use std::{net::SocketAddr,
+          sync::{atomic::{AtomicBool,
+                          Ordering},
+                 Arc}};
+
+pub trait PeerSource: Send + Sync {
+    /// Whether the peers changed since they were last read
+    fn has_events(&self) -> bool;
+
+    /// The current peers of the source
+    fn get_members(&self) -> Result<Vec<Member>>;
+}
+
+/// The peers found by a source that discovers them in the background, shared between the
+/// discovering thread or task and the manager
+#[derive(Clone, Default)]
+pub struct DiscoveredPeers {
+    have_events: Arc<AtomicBool>,
+    peers:       Arc<Mutex<Vec<SocketAddr>>>,
+}
+
+impl DiscoveredPeers {
+    /// Replace the peers, flagging a change if they differ from the previous ones
+    pub fn update(&self, mut addrs: Vec<SocketAddr>) {
+        addrs.sort();
+        addrs.dedup();
+        let mut peers = self.peers.lock();
+        if *peers != addrs {
+            *peers = addrs;
+            self.have_events.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn has_events(&self) -> bool { self.have_events.load(Ordering::Relaxed) }
+
+    pub fn members(&self) -> Vec<Member> {
+        self.have_events.store(false, Ordering::Relaxed);
+        self.peers
+            .lock()
+            .iter()
+            .map(|addr| member_for(*addr))
+            .collect()
+    }
+}
+
+pub fn member_for(addr: SocketAddr) -> Member {
+    Member { address: member::unmap_ipv4(addr).ip().to_string(),
+             swim_port: addr.port(),
+             gossip_port: addr.port(),
+             ..Default::default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_changes_of_the_peers_are_events() {
+        let peers = DiscoveredPeers::default();
+        let addrs = vec!["10.0.0.2:9638".parse().unwrap(),
+                         "10.0.0.1:9638".parse().unwrap()];
+        assert!(!peers.has_events());
+
+        peers.update(addrs.clone());
+        assert!(peers.has_events());
+        let members = peers.members();
+        assert!(!peers.has_events());
+        assert_eq!(members.iter()
+                          .map(|m| m.address.as_str())
+                          .collect::<Vec<_>>(),
+                   vec!["10.0.0.1", "10.0.0.2"]);
+
+        peers.update(addrs.into_iter().rev().collect());
+        assert!(!peers.has_events());
+        peers.update(Vec::new());
+        assert!(peers.has_events());
+    }
+}
@@ -0,0 +1,179 @@
+//! Discovers the peers of a Supervisor among the healthy instances of a service in the Consul
+//! catalog, so that Supervisors registered in Consul need no peer file that duplicates it.
+//!
+//! The instances are read from the health endpoint of the Consul agent, keeping only the ones
+//! whose checks are all passing. The address of an instance is its service address, or the address
+//! of its node when the service has none.
+
+use super::{DiscoveredPeers,
+            PeerSource};
+use crate::error::{Error,
+                   Result};
+use habitat_butterfly::member::Member;
+use habitat_common::outputln;
+use std::{net::{IpAddr,
+                SocketAddr},
+          time::Duration};
+use tokio::time;
+use url::Url;
+
+static LOGKEY: &str = "CN";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+habitat_core::env_config_duration!(ConsulPollInterval,
+                                   HAB_PEER_CONSUL_POLL_INTERVAL_SECS => from_secs,
+                                   Duration::from_secs(10));
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsulPeerConfig {
+    /// The HTTP API address of the Consul agent
+    pub address:    Url,
+    /// The name of the service the Supervisors are registered as
+    pub service:    String,
+    /// The datacenter to read the service from, instead of the agent's own
+    pub datacenter: Option<String>,
+    /// The ACL token to read the service with
+    pub token:      Option<String>,
+}
+
+/// An entry of the response of the Consul health endpoint
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ServiceEntry {
+    node:    Node,
+    service: Service,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Node {
+    address: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Service {
+    #[serde(default)]
+    address: String,
+    port:    u16,
+}
+
+impl ServiceEntry {
+    fn addr(&self) -> Option<SocketAddr> {
+        let address = if self.service.address.is_empty() {
+            &self.node.address
+        } else {
+            &self.service.address
+        };
+        address.parse::<IpAddr>()
+               .ok()
+               .map(|ip| SocketAddr::new(ip, self.service.port))
+    }
+}
+
+pub struct ConsulPeerSource {
+    peers: DiscoveredPeers,
+}
+
+impl ConsulPeerSource {
+    /// Start polling the healthy instances of the service of `config`
+    pub fn run(config: ConsulPeerConfig) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT)
+                                               .build()
+                                               .map_err(consul_error)?;
+        let url = health_url(&config)?;
+        let peers = DiscoveredPeers::default();
+        let interval = ConsulPollInterval::configured_value().into();
+
+        let peers_for_task = peers.clone();
+        tokio::spawn(async move {
+            loop {
+                match healthy_instances(&client, url.clone(), config.token.as_deref()).await {
+                    Ok(addrs) => peers_for_task.update(addrs),
+                    Err(e) => {
+                        outputln!("Unable to read the Consul instances of {}, {}",
+                                  config.service,
+                                  e)
+                    }
+                }
+                time::sleep(interval).await;
+            }
+        });
+
+        Ok(ConsulPeerSource { peers })
+    }
+}
+
+impl PeerSource for ConsulPeerSource {
+    fn has_events(&self) -> bool { self.peers.has_events() }
+
+    fn get_members(&self) -> Result<Vec<Member>> { Ok(self.peers.members()) }
+}
+
+/// The URL of the passing instances of the service of `config`
+fn health_url(config: &ConsulPeerConfig) -> Result<Url> {
+    let mut url = config.address
+                        .join(&format!("v1/health/service/{}", config.service))
+                        .map_err(consul_error)?;
+    url.query_pairs_mut().append_pair("passing", "true");
+    if let Some(datacenter) = &config.datacenter {
+        url.query_pairs_mut().append_pair("dc", datacenter);
+    }
+    Ok(url)
+}
+
+async fn healthy_instances(client: &reqwest::Client,
+                           url: Url,
+                           token: Option<&str>)
+                           -> Result<Vec<SocketAddr>> {
+    let mut request = client.get(url);
+    if let Some(token) = token {
+        request = request.header("X-Consul-Token", token);
+    }
+    let entries = request.send()
+                         .await
+                         .and_then(|response| response.error_for_status())
+                         .map_err(consul_error)?
+                         .json::<Vec<ServiceEntry>>()
+                         .await
+                         .map_err(consul_error)?;
+    Ok(entries.iter().filter_map(ServiceEntry::addr).collect())
+}
+
+fn consul_error(e: impl ToString) -> Error { Error::ConsulPeers(e.to_string()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_url_selects_passing_instances() {
+        let mut config = ConsulPeerConfig { address:    "http://127.0.0.1:8500".parse().unwrap(),
+                                            service:    String::from("hab-sup"),
+                                            datacenter: None,
+                                            token:      Some(String::from("secret")), };
+        assert_eq!(health_url(&config).unwrap().as_str(),
+                   "http://127.0.0.1:8500/v1/health/service/hab-sup?passing=true");
+
+        config.datacenter = Some(String::from("dc2"));
+        assert_eq!(health_url(&config).unwrap().as_str(),
+                   "http://127.0.0.1:8500/v1/health/service/hab-sup?passing=true&dc=dc2");
+    }
+
+    const HEALTH_RESPONSE: &str = r#"[
+  {"Node": {"Address": "10.0.0.1"}, "Service": {"Address": "", "Port": 9638}},
+  {"Node": {"Address": "10.0.0.2"}, "Service": {"Address": "10.1.0.2", "Port": 9640}},
+  {"Node": {"Address": "consul-node"}, "Service": {"Port": 9638}}
+]"#;
+
+    #[test]
+    fn instances_fall_back_to_the_node_address() {
+        let entries: Vec<ServiceEntry> = serde_json::from_str(HEALTH_RESPONSE).unwrap();
+        assert_eq!(entries.iter()
+                          .filter_map(ServiceEntry::addr)
+                          .collect::<Vec<_>>(),
+                   vec!["10.0.0.1:9638".parse::<SocketAddr>().unwrap(),
+                        "10.1.0.2:9640".parse().unwrap()]);
+    }
+}
@@ -0,0 +1,146 @@
+//! Discovers the peers of a Supervisor running on EC2 among the running instances with a tag, or
+//! in an Auto Scaling group, so that auto-scaling rings bootstrap without a shared peer file.
+//!
+//! The region and the credentials of the instance profile come from the instance metadata
+//! service. The profile must allow `ec2:DescribeInstances`.
+
+use super::{DiscoveredPeers,
+            PeerSource};
+use crate::error::{Error,
+                   Result};
+use habitat_butterfly::member::Member;
+use habitat_common::{outputln,
+                     types::Ec2PeerFilter};
+use rusoto_core::{request::HttpClient,
+                  Region};
+use rusoto_credential::InstanceMetadataProvider;
+use rusoto_ec2::{DescribeInstancesRequest,
+                 Ec2,
+                 Ec2Client,
+                 Filter};
+use std::{net::{IpAddr,
+                SocketAddr},
+          time::Duration};
+use tokio::time;
+
+static LOGKEY: &str = "EC";
+
+const IMDS_URL: &str = "http://169.254.169.254/latest";
+const IMDS_TIMEOUT: Duration = Duration::from_secs(5);
+
+habitat_core::env_config_duration!(Ec2PollInterval,
+                                   HAB_PEER_EC2_POLL_INTERVAL_SECS => from_secs,
+                                   Duration::from_secs(30));
+
+pub struct Ec2PeerSource {
+    peers: DiscoveredPeers,
+}
+
+impl Ec2PeerSource {
+    /// Start discovering the instances of `filter`, whose Supervisors gossip on `gossip_port`
+    pub async fn run(filter: Ec2PeerFilter, gossip_port: u16) -> Result<Self> {
+        let region = instance_region().await?;
+        let dispatcher = HttpClient::new().map_err(ec2_error)?;
+        let client = Ec2Client::new_with(dispatcher, InstanceMetadataProvider::new(), region);
+        let peers = DiscoveredPeers::default();
+        let interval = Ec2PollInterval::configured_value().into();
+
+        let peers_for_task = peers.clone();
+        tokio::spawn(async move {
+            loop {
+                match instance_ips(&client, &filter).await {
+                    Ok(ips) => {
+                        peers_for_task.update(ips.into_iter()
+                                                 .map(|ip| SocketAddr::new(ip, gossip_port))
+                                                 .collect())
+                    }
+                    Err(e) => outputln!("Unable to discover the EC2 peers of {}, {}", filter, e),
+                }
+                time::sleep(interval).await;
+            }
+        });
+
+        Ok(Ec2PeerSource { peers })
+    }
+}
+
+impl PeerSource for Ec2PeerSource {
+    fn has_events(&self) -> bool { self.peers.has_events() }
+
+    fn get_members(&self) -> Result<Vec<Member>> { Ok(self.peers.members()) }
+}
+
+/// The region of this instance
+async fn instance_region() -> Result<Region> {
+    let region = instance_metadata("placement/region").await
+                                                      .map_err(ec2_error)?;
+    region.parse().map_err(ec2_error)
+}
+
+/// The instance metadata at `path`, read from the instance metadata service with a session token
+pub(crate) async fn instance_metadata(path: &str) -> reqwest::Result<String> {
+    let client = reqwest::Client::builder().timeout(IMDS_TIMEOUT).build()?;
+    let token = client.put(format!("{}/api/token", IMDS_URL))
+                      .header("X-aws-ec2-metadata-token-ttl-seconds", "60")
+                      .send()
+                      .await?
+                      .error_for_status()?
+                      .text()
+                      .await?;
+    let metadata = client.get(format!("{}/meta-data/{}", IMDS_URL, path))
+                         .header("X-aws-ec2-metadata-token", token)
+                         .send()
+                         .await?
+                         .error_for_status()?
+                         .text()
+                         .await?;
+    Ok(metadata.trim().to_string())
+}
+
+/// The private IP addresses of the running instances of `filter`
+async fn instance_ips(client: &Ec2Client, filter: &Ec2PeerFilter) -> Result<Vec<IpAddr>> {
+    let mut ips = Vec::new();
+    let mut next_token = None;
+    loop {
+        let request = DescribeInstancesRequest { filters: Some(filters(filter)),
+                                                 next_token,
+                                                 ..Default::default() };
+        let result = client.describe_instances(request)
+                           .await
+                           .map_err(ec2_error)?;
+        ips.extend(result.reservations
+                         .into_iter()
+                         .flatten()
+                         .flat_map(|reservation| reservation.instances.into_iter().flatten())
+                         .filter_map(|instance| instance.private_ip_address)
+                         .filter_map(|ip| ip.parse().ok()));
+        next_token = result.next_token;
+        if next_token.is_none() {
+            return Ok(ips);
+        }
+    }
+}
+
+fn filters(filter: &Ec2PeerFilter) -> Vec<Filter> {
+    let (key, value) = filter.tag();
+    vec![Filter { name:   Some(format!("tag:{}", key)),
+                  values: Some(vec![value.to_string()]), },
+         Filter { name:   Some(String::from("instance-state-name")),
+                  values: Some(vec![String::from("running")]), },]
+}
+
+fn ec2_error(e: impl ToString) -> Error { Error::Ec2Peers(e.to_string()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_scaling_groups_are_found_by_tag() {
+        let filters = filters(&"asg:web-asg".parse().unwrap());
+        assert_eq!(filters[0].name.as_deref(),
+                   Some("tag:aws:autoscaling:groupName"));
+        assert_eq!(filters[0].values, Some(vec![String::from("web-asg")]));
+        assert_eq!(filters[1].values, Some(vec![String::from("running")]));
+    }
+}
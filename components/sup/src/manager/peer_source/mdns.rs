@@ -0,0 +1,106 @@
+//! Discovers the peers of a Supervisor on the local network over multicast DNS, so that small
+//! edge or lab rings form without a peer file or `--peer` options.
+//!
+//! Every Supervisor advertises its gossip listener as an instance of the `_hab-gossip._tcp`
+//! service, named after its member ID, and browses for the instances of the others. Supervisors
+//! only discover each other if they use the same ring key, or both use none, which is advertised
+//! by name in the `ring` TXT property.
+
+use super::{DiscoveredPeers,
+            PeerSource};
+use crate::{error::{Error,
+                    Result},
+            manager::sys::Sys};
+use habitat_butterfly::member::Member;
+use habitat_common::outputln;
+use mdns_sd::{Receiver,
+              ServiceDaemon,
+              ServiceEvent,
+              ServiceInfo};
+use std::{collections::HashMap,
+          net::{IpAddr,
+                SocketAddr},
+          thread::Builder as ThreadBuilder};
+
+static LOGKEY: &str = "MD";
+
+const SERVICE_TYPE: &str = "_hab-gossip._tcp.local.";
+/// The TXT property holding the name of the ring key of a Supervisor
+const RING_PROPERTY: &str = "ring";
+
+pub struct MdnsPeerSource {
+    peers:   DiscoveredPeers,
+    // Advertising and browsing stop when the daemon is dropped
+    _daemon: ServiceDaemon,
+}
+
+impl MdnsPeerSource {
+    /// Advertise this Supervisor, and start discovering the others of the ring whose ring key is
+    /// named `ring`
+    pub fn run(sys: &Sys, ring: Option<&str>) -> Result<Self> {
+        let daemon = ServiceDaemon::new().map_err(mdns_error)?;
+        let ring = ring.unwrap_or_default().to_string();
+        let mut properties = HashMap::new();
+        properties.insert(RING_PROPERTY.to_string(), ring.clone());
+        let own = ServiceInfo::new(SERVICE_TYPE,
+                                   &sys.member_id,
+                                   &format!("{}.local.", sys.member_id),
+                                   sys.ip.to_string().as_str(),
+                                   sys.gossip_port,
+                                   Some(properties)).map_err(mdns_error)?;
+        let own_name = own.get_fullname().to_string();
+        daemon.register(own).map_err(mdns_error)?;
+        let events = daemon.browse(SERVICE_TYPE).map_err(mdns_error)?;
+
+        let peers = DiscoveredPeers::default();
+        let peers_for_thread = peers.clone();
+        ThreadBuilder::new().name(String::from("peer-mdns"))
+                            .spawn(move || {
+                                discover_peers(&events, &own_name, &ring, &peers_for_thread)
+                            })?;
+
+        Ok(MdnsPeerSource { peers,
+                            _daemon: daemon })
+    }
+}
+
+impl PeerSource for MdnsPeerSource {
+    fn has_events(&self) -> bool { self.peers.has_events() }
+
+    fn get_members(&self) -> Result<Vec<Member>> { Ok(self.peers.members()) }
+}
+
+/// Track the Supervisors of the ring named `ring` that are advertised on the local network, other
+/// than this one, until the daemon is dropped
+fn discover_peers(events: &Receiver<ServiceEvent>,
+                  own_name: &str,
+                  ring: &str,
+                  peers: &DiscoveredPeers) {
+    // The gossip listeners of the discovered Supervisors, by instance name
+    let mut instances = HashMap::new();
+    while let Ok(event) = events.recv() {
+        match event {
+            ServiceEvent::ServiceResolved(info) => {
+                let name = info.get_fullname().to_string();
+                let info_ring = info.get_property_val_str(RING_PROPERTY).unwrap_or_default();
+                if name == own_name || info_ring != ring {
+                    continue;
+                }
+                let port = info.get_port();
+                let addrs = info.get_addresses()
+                                .iter()
+                                .map(|ip| SocketAddr::new(IpAddr::from(*ip), port))
+                                .collect::<Vec<_>>();
+                instances.insert(name, addrs);
+            }
+            ServiceEvent::ServiceRemoved(_, name) => {
+                instances.remove(&name);
+            }
+            _ => continue,
+        }
+        peers.update(instances.values().flatten().copied().collect());
+    }
+    outputln!("Stopped discovering peers over mDNS");
+}
+
+fn mdns_error(err: mdns_sd::Error) -> Error { Error::MdnsPeers(err.to_string()) }
@@ -1,25 +1,229 @@
+//! Watches the peer file given with `--peer-watch-file` for the members to join the ring
+//! through.
+//!
+//! Each line of the file is either the address of a peer (`IP[:PORT]` or `HOST[:PORT]`, or
+//! `HOST:SWIM_PORT:GOSSIP_PORT` for a peer whose SWIM and gossip listeners use different ports)
+//! or, when prefixed with `srv:`, the name of a DNS SRV record
+//! (`srv:_hab-gossip._tcp.example.com`) whose targets and ports are the peers. SRV records are
+//! queried again periodically on a thread of their own, and a change of their answers is reported
+//! like a change of the file. Anything after a `#` is a comment.
+//!
+//! A file named `*.toml` or `*.json` is read as a structured peer file instead, an array `peer`
+//! of tables each holding either the `address` of a peer, with optional `swim_port`,
+//! `gossip_port` and `permanent` keys, or the name of an `srv` record. A permanent peer is marked
+//! as persistent, so it keeps being pinged even when it is thought to be gone.
+//!
+//! If the path is a directory when the watcher starts, every file in it is a fragment of the peer
+//! file, and the peers of all fragments are merged. Hidden files are skipped, so that editors and
+//! tools writing fragments atomically through a temporary dotfile don't add partial peers.
+//!
+//! Hostnames are resolved again periodically, so that peers behind DNS names whose addresses
+//! change, like load balancers or round-robin records, are followed without touching the file.
+//! Hostnames and SRV records are resolved with the DNS servers and timeout configured with
+//! `HAB_DNS_SERVERS` and `HAB_DNS_TIMEOUT_SECS`, if any.
+//!
+//! An address that can't be resolved is skipped rather than failing the whole file. It is tried
+//! again after a delay that doubles with every failure, and the failures are counted in the
+//! `hab_sup_peer_file_resolution_failures_total` metric.
+
+use super::peer_source::{member_for,
+                         PeerSource};
 use crate::{error::{Error,
                     Result},
-            manager::file_watcher::{create_file_watcher,
-                                    Callbacks}};
+            manager::{file_watcher::{create_file_watcher,
+                                     Callbacks,
+                                     FileWatcher,
+                                     FileWatcherDelay,
+                                     FileWatcherPollInterval},
+                      sup_watcher::SupWatcher}};
 use habitat_butterfly::member::Member;
 use habitat_common::{liveliness_checker,
                      outputln,
-                     types::GossipListenAddr};
-use std::{fs::File,
+                     types::GossipListenAddr,
+                     util};
+use habitat_core::dns;
+use notify::{DebouncedEvent,
+             RecursiveMode,
+             Watcher};
+use parking_lot::Mutex;
+use prometheus::IntCounter;
+use std::{collections::HashMap,
+          fs::{self,
+               File},
           io::{BufRead,
                BufReader},
-          net::{SocketAddr,
-                ToSocketAddrs},
+          net::{IpAddr,
+                Ipv6Addr,
+                SocketAddr},
           path::{Path,
                  PathBuf},
           sync::{atomic::{AtomicBool,
                           Ordering},
+                 mpsc::{self,
+                        Receiver,
+                        RecvTimeoutError,
+                        Sender,
+                        TryRecvError},
                  Arc},
-          thread::Builder as ThreadBuilder};
+          thread::{self,
+                   Builder as ThreadBuilder,
+                   JoinHandle},
+          time::{Duration,
+                 Instant}};
+use trust_dns_resolver::Resolver;
 
 static LOGKEY: &str = "PW";
 
+const SRV_PREFIX: &str = "srv:";
+/// The delay before an address that failed to resolve is tried again for the first time
+const RETRY_MIN_DELAY: Duration = Duration::from_secs(1);
+/// The longest delay before an address that keeps failing to resolve is tried again
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(5 * 60);
+
+lazy_static! {
+    static ref PEER_RESOLUTION_FAILURES: IntCounter =
+        register_int_counter!("hab_sup_peer_file_resolution_failures_total",
+                              "Total number of failed resolutions of peer file addresses").unwrap();
+}
+
+habitat_core::env_config_duration!(SrvQueryInterval,
+                                   HAB_PEER_SRV_QUERY_INTERVAL_SECS => from_secs,
+                                   Duration::from_secs(30));
+
+habitat_core::env_config_duration!(PeerResolveInterval,
+                                   HAB_PEER_RESOLVE_INTERVAL_SECS => from_secs,
+                                   Duration::from_secs(60));
+
+/// The addresses each SRV record of the peer file resolved to, by record name
+type SrvAnswers = Arc<Mutex<HashMap<String, Vec<SocketAddr>>>>;
+
+/// When an address of the peer file that failed to resolve is tried again
+#[derive(Clone, Copy, Debug)]
+struct Retry {
+    failures: u32,
+    at:       Instant,
+}
+
+impl Retry {
+    fn after_failure(previous: Option<Retry>, now: Instant) -> Self {
+        let failures = previous.map_or(1, |retry| retry.failures.saturating_add(1));
+        Retry { failures,
+                at: now + retry_delay(failures) }
+    }
+}
+
+/// The delay before trying an address again after it failed to resolve `failures` times in a row
+fn retry_delay(failures: u32) -> Duration {
+    let doublings = failures.saturating_sub(1).min(31);
+    RETRY_MIN_DELAY.checked_mul(1 << doublings)
+                   .map_or(RETRY_MAX_DELAY, |delay| delay.min(RETRY_MAX_DELAY))
+}
+
+/// A line of the peer file
+#[derive(Debug, PartialEq, Eq)]
+enum PeerEntry {
+    Address(String),
+    /// The address of a peer that is pinged even when it is thought to be gone
+    Permanent(String),
+    Srv(String),
+}
+
+impl PeerEntry {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            None
+        } else if let Some(name) = line.strip_prefix(SRV_PREFIX) {
+            Some(PeerEntry::Srv(name.trim().to_string()))
+        } else {
+            Some(PeerEntry::Address(line.to_string()))
+        }
+    }
+}
+
+/// A structured peer file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StructuredPeerFile {
+    #[serde(default)]
+    peer: Vec<StructuredPeer>,
+}
+
+/// A peer of a structured peer file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StructuredPeer {
+    address:     Option<String>,
+    srv:         Option<String>,
+    swim_port:   Option<u16>,
+    gossip_port: Option<u16>,
+    #[serde(default)]
+    permanent:   bool,
+}
+
+impl StructuredPeer {
+    /// The entry of the peer, written the way a line of the legacy format would be
+    fn into_entry(self) -> std::result::Result<PeerEntry, &'static str> {
+        match (self.address, self.srv) {
+            (Some(address), None) => {
+                let host = if address.parse::<Ipv6Addr>().is_ok() {
+                    format!("[{}]", address)
+                } else {
+                    address.clone()
+                };
+                let line = match (self.swim_port, self.gossip_port) {
+                    (None, None) => address,
+                    (None, Some(gossip_port)) => format!("{}:{}", host, gossip_port),
+                    (Some(swim_port), gossip_port) => {
+                        format!("{}:{}:{}",
+                                host,
+                                swim_port,
+                                gossip_port.unwrap_or(GossipListenAddr::DEFAULT_PORT))
+                    }
+                };
+                if self.permanent {
+                    Ok(PeerEntry::Permanent(line))
+                } else {
+                    Ok(PeerEntry::Address(line))
+                }
+            }
+            (None, Some(name)) => {
+                if self.swim_port.is_some() || self.gossip_port.is_some() || self.permanent {
+                    Err("an SRV record has no ports of its own and can't be permanent")
+                } else {
+                    Ok(PeerEntry::Srv(name))
+                }
+            }
+            _ => Err("a peer needs either an address or an SRV record"),
+        }
+    }
+}
+
+/// The formats of a structured peer file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StructuredFormat {
+    Toml,
+    Json,
+}
+
+impl StructuredFormat {
+    /// The structured format of the peer file `path`, by its extension
+    fn of(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Some(StructuredFormat::Toml),
+            Some("json") => Some(StructuredFormat::Json),
+            _ => None,
+        }
+    }
+
+    fn parse(self, contents: &str) -> std::result::Result<StructuredPeerFile, String> {
+        match self {
+            StructuredFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string()),
+            StructuredFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string()),
+        }
+    }
+}
+
 pub struct PeerCallbacks {
     have_events: Arc<AtomicBool>,
 }
@@ -32,9 +236,41 @@ impl Callbacks for PeerCallbacks {
     fn file_disappeared(&mut self, _: &Path) { self.have_events.store(true, Ordering::Relaxed) }
 }
 
+/// A thread of the watcher, told to stop and joined when the watcher is dropped
+struct WatcherThread {
+    stop:   Sender<()>,
+    handle: JoinHandle<liveliness_checker::ThreadUnregistered>,
+}
+
+impl WatcherThread {
+    fn spawn<F>(name: String, body: F) -> Result<Self>
+        where F: FnOnce(Receiver<()>) -> liveliness_checker::ThreadUnregistered + Send + 'static
+    {
+        let (stop, stop_running) = mpsc::channel();
+        let handle = ThreadBuilder::new().name(name)
+                                         .spawn(move || body(stop_running))?;
+        Ok(WatcherThread { stop, handle })
+    }
+}
+
+/// Whether the thread was told to stop, or its watcher is gone
+fn stopped(stop_running: &Receiver<()>) -> bool {
+    !matches!(stop_running.try_recv(), Err(TryRecvError::Empty))
+}
+
 pub struct PeerWatcher {
-    path:        PathBuf,
-    have_events: Arc<AtomicBool>,
+    path:             PathBuf,
+    have_events:      Arc<AtomicBool>,
+    srv_answers:      SrvAnswers,
+    /// The addresses that failed to resolve, by line
+    retries:          Mutex<HashMap<String, Retry>>,
+    /// How often hostnames are resolved again. Zero resolves them only when the file changes.
+    resolve_interval: Duration,
+    /// When the hostnames of the peer file are due to be resolved again
+    next_resolution:  Mutex<Option<Instant>>,
+    /// The address each hostname last resolved to, by line
+    resolved:         Mutex<HashMap<String, SocketAddr>>,
+    threads:          Vec<WatcherThread>,
 }
 
 impl PeerWatcher {
@@ -42,96 +278,442 @@ impl PeerWatcher {
         where P: Into<PathBuf>
     {
         let path = path.into();
-        let have_events = Self::setup_watcher(path.clone())?;
+        let (have_events, watcher_thread) = if path.is_dir() {
+            Self::setup_dir_watcher(path.clone())?
+        } else {
+            Self::setup_watcher(path.clone())?
+        };
+        let srv_answers = SrvAnswers::default();
+        let srv_thread = Self::setup_srv_resolver(path.clone(),
+                                                  Arc::clone(&have_events),
+                                                  Arc::clone(&srv_answers))?;
+
+        Ok(PeerWatcher { path,
+                         have_events,
+                         srv_answers,
+                         retries: Mutex::default(),
+                         resolve_interval: PeerResolveInterval::configured_value().into(),
+                         next_resolution: Mutex::default(),
+                         resolved: Mutex::default(),
+                         threads: vec![watcher_thread, srv_thread] })
+    }
+
+    /// Query the SRV records of the peer file every `HAB_PEER_SRV_QUERY_INTERVAL_SECS`, until the
+    /// watcher is dropped
+    fn setup_srv_resolver(path: PathBuf,
+                          have_events: Arc<AtomicBool>,
+                          srv_answers: SrvAnswers)
+                          -> Result<WatcherThread> {
+        WatcherThread::spawn(format!("peer-srv-resolver-[{}]", path.display()),
+                             move |stop_running| {
+                                 Self::resolve_srv_records(&path,
+                                                           &have_events,
+                                                           &srv_answers,
+                                                           &stop_running)
+                             })
+    }
+
+    fn resolve_srv_records(path: &Path,
+                           have_events: &Arc<AtomicBool>,
+                           srv_answers: &SrvAnswers,
+                           stop_running: &Receiver<()>)
+                           -> liveliness_checker::ThreadUnregistered {
+        let interval = SrvQueryInterval::configured_value().into();
+        let mut resolver = None;
+        loop {
+            let checked_thread = liveliness_checker::mark_thread_alive();
+            if Self::query_srv_records(path, &mut resolver, srv_answers) {
+                have_events.store(true, Ordering::Relaxed);
+            }
+            // Waiting on the channel rather than sleeping wakes the thread up as soon as it is
+            // told to stop
+            match stop_running.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => break checked_thread.unregister(Ok(())),
+            }
+        }
+    }
 
-        Ok(PeerWatcher { path, have_events })
+    /// Query the SRV records of the peer file, returning whether their answers changed. A record
+    /// that can't be queried keeps its previous answer, so that a passing DNS outage doesn't
+    /// empty the list of peers.
+    fn query_srv_records(path: &Path,
+                         resolver: &mut Option<Resolver>,
+                         srv_answers: &SrvAnswers)
+                         -> bool {
+        let names = peer_entries(path).into_iter()
+                                      .filter_map(|entry| {
+                                          match entry {
+                                              PeerEntry::Srv(name) => Some(name),
+                                              _ => None,
+                                          }
+                                      })
+                                      .collect::<Vec<_>>();
+        if names.is_empty() {
+            let mut answers = srv_answers.lock();
+            let changed = !answers.is_empty();
+            answers.clear();
+            return changed;
+        }
+        if resolver.is_none() {
+            match dns::resolver() {
+                Ok(r) => *resolver = Some(r),
+                Err(e) => {
+                    outputln!("PeerWatcher({}) could not create a DNS resolver, {}",
+                              path.display(),
+                              e);
+                    return false;
+                }
+            }
+        }
+        let resolver = resolver.as_ref().expect("resolver was just created");
+
+        let mut answers = HashMap::with_capacity(names.len());
+        for name in names {
+            match resolve_srv(resolver, &name) {
+                Ok(addrs) => {
+                    answers.insert(name, addrs);
+                }
+                Err(e) => {
+                    outputln!("Failed to query the SRV record {}: {}", name, e);
+                    if let Some(previous) = srv_answers.lock().get(&name) {
+                        answers.insert(name, previous.clone());
+                    }
+                }
+            }
+        }
+        let mut srv_answers = srv_answers.lock();
+        let changed = *srv_answers != answers;
+        *srv_answers = answers;
+        changed
     }
 
-    fn setup_watcher(path: PathBuf) -> Result<Arc<AtomicBool>> {
+    fn setup_watcher(path: PathBuf) -> Result<(Arc<AtomicBool>, WatcherThread)> {
         let have_events = Arc::new(AtomicBool::new(false));
         let have_events_for_thread = Arc::clone(&have_events);
 
-        ThreadBuilder::new().name(format!("peer-watcher-[{}]", path.display()))
-                            .spawn(move || -> liveliness_checker::ThreadUnregistered {
-                                // debug!("PeerWatcher({}) thread starting", abs_path.display());
-                                loop {
-                                    let checked_thread = liveliness_checker::mark_thread_alive();
-                                    let have_events_for_loop = Arc::clone(&have_events_for_thread);
-                                    if Self::file_watcher_loop_body(&path, have_events_for_loop) {
-                                        break checked_thread.unregister(Ok(()));
-                                    }
-                                }
-                            })?;
-        Ok(have_events)
-    }
-
-    fn file_watcher_loop_body(path: &Path, have_events: Arc<AtomicBool>) -> bool {
-        let callbacks = PeerCallbacks { have_events };
-        let mut file_watcher = match create_file_watcher(&path, callbacks, true) {
-            Ok(watcher) => watcher,
-            Err(e) => {
-                match e {
-                    Error::NotifyError(err) => {
-                        outputln!("PeerWatcher({}) failed to start watching the directories \
-                                   ({}), {}",
-                                  path.display(),
-                                  err,
-                                  "will try again");
-                        return false;
-                    }
-                    _ => {
-                        outputln!("PeerWatcher({}) could not create file watcher, ending thread \
-                                   ({})",
-                                  path.display(),
-                                  e);
-                        return true;
-                    }
+        let thread =
+            WatcherThread::spawn(format!("peer-watcher-[{}]", path.display()),
+                                 move |stop_running| {
+                                     Self::watch_file(&path, &have_events_for_thread, &stop_running)
+                                 })?;
+        Ok((have_events, thread))
+    }
+
+    fn watch_file(path: &Path,
+                  have_events: &Arc<AtomicBool>,
+                  stop_running: &Receiver<()>)
+                  -> liveliness_checker::ThreadUnregistered {
+        let poll_interval = FileWatcherPollInterval::configured_value().into();
+        let mut file_watcher = None;
+        loop {
+            let checked_thread = liveliness_checker::mark_thread_alive();
+            if stopped(stop_running)
+               || Self::file_watcher_loop_body(path, have_events, &mut file_watcher)
+            {
+                break checked_thread.unregister(Ok(()));
+            }
+            thread::sleep(poll_interval);
+        }
+    }
+
+    /// Watch a directory of peer file fragments. The fragments present when the watcher starts
+    /// are reported as an event, like an existing peer file is.
+    fn setup_dir_watcher(path: PathBuf) -> Result<(Arc<AtomicBool>, WatcherThread)> {
+        let have_events = Arc::new(AtomicBool::new(true));
+        let have_events_for_thread = Arc::clone(&have_events);
+        let (tx, rx) = mpsc::channel();
+        let delay: Duration = FileWatcherDelay::configured_value().into();
+        let mut watcher = SupWatcher::new(tx, delay).map_err(Error::NotifyCreateError)?;
+        watcher.watch(&path, RecursiveMode::NonRecursive)?;
+
+        let thread = WatcherThread::spawn(format!("peer-watcher-[{}]", path.display()),
+                                          move |stop_running| {
+                                              // Events are only sent while the watcher is alive
+                                              let _watcher = watcher;
+                                              Self::watch_dir(&rx,
+                                                              delay,
+                                                              &have_events_for_thread,
+                                                              &stop_running)
+                                          })?;
+        Ok((have_events, thread))
+    }
+
+    fn watch_dir(events: &Receiver<DebouncedEvent>,
+                 delay: Duration,
+                 have_events: &Arc<AtomicBool>,
+                 stop_running: &Receiver<()>)
+                 -> liveliness_checker::ThreadUnregistered {
+        loop {
+            let checked_thread = liveliness_checker::mark_thread_alive();
+            if stopped(stop_running) {
+                break checked_thread.unregister(Ok(()));
+            }
+            if events.recv_timeout(delay).is_ok() {
+                have_events.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Watch the peer file for one iteration, creating the file watcher first if there is none.
+    /// Returns whether the thread should end.
+    fn file_watcher_loop_body(path: &Path,
+                              have_events: &Arc<AtomicBool>,
+                              file_watcher: &mut Option<FileWatcher<PeerCallbacks, SupWatcher>>)
+                              -> bool {
+        if file_watcher.is_none() {
+            let callbacks = PeerCallbacks { have_events: Arc::clone(have_events), };
+            match create_file_watcher(&path, callbacks, true) {
+                Ok(watcher) => *file_watcher = Some(watcher),
+                Err(Error::NotifyError(err)) => {
+                    outputln!("PeerWatcher({}) failed to start watching the directories ({}), {}",
+                              path.display(),
+                              err,
+                              "will try again");
+                    return false;
+                }
+                Err(e) => {
+                    outputln!("PeerWatcher({}) could not create file watcher, ending thread ({})",
+                              path.display(),
+                              e);
+                    return true;
                 }
             }
-        };
-        if let Err(err) = file_watcher.run() {
+        }
+        let watcher = file_watcher.as_mut()
+                                  .expect("file watcher was just created");
+        if let Err(err) = watcher.single_iteration() {
             outputln!("PeerWatcher({}) error during watching ({}), restarting",
                       path.display(),
                       err);
+            *file_watcher = None;
         }
         false
     }
 
-    pub fn has_fs_events(&self) -> bool { self.have_events.load(Ordering::Relaxed) }
+    /// Whether the peers changed since they were last read, an address that failed to resolve
+    /// is due to be tried again, or the hostnames are due to be resolved again
+    pub fn has_fs_events(&self) -> bool { self.has_fs_events_at(Instant::now()) }
 
-    pub fn get_members(&self) -> Result<Vec<Member>> {
-        if !self.path.is_file() {
-            self.have_events.store(false, Ordering::Relaxed);
-            return Ok(Vec::new());
-        }
-        let file = File::open(&self.path).map_err(Error::Io)?;
-        let reader = BufReader::new(file);
+    fn has_fs_events_at(&self, now: Instant) -> bool {
+        self.have_events.load(Ordering::Relaxed)
+        || self.retries.lock().values().any(|retry| retry.at <= now)
+        || self.next_resolution.lock().map_or(false, |at| at <= now)
+    }
+
+    pub fn get_members(&self) -> Result<Vec<Member>> { self.get_members_at(Instant::now()) }
+
+    /// The members of the peer file as of `now`, which decides whether the retries of addresses
+    /// that failed to resolve are due
+    fn get_members_at(&self, now: Instant) -> Result<Vec<Member>> {
         let mut members: Vec<Member> = Vec::new();
-        for line in reader.lines().flatten() {
-            let peer_addr = if line.find(':').is_some() {
-                line
-            } else {
-                format!("{}:{}", line, GossipListenAddr::DEFAULT_PORT)
-            };
-            let addrs: Vec<SocketAddr> = match peer_addr.to_socket_addrs() {
-                Ok(addrs) => addrs.collect(),
-                Err(e) => {
-                    outputln!("Failed to resolve peer: {}", peer_addr);
-                    return Err(Error::NameLookup(e));
+        let mut retries = self.retries.lock();
+        let mut previous_retries = std::mem::take(&mut *retries);
+        let mut resolved = self.resolved.lock();
+        let mut previous_resolved = std::mem::take(&mut *resolved);
+        for entry in peer_entries(&self.path) {
+            let (line, permanent) = match entry {
+                PeerEntry::Address(line) => (line, false),
+                PeerEntry::Permanent(line) => (line, true),
+                PeerEntry::Srv(name) => {
+                    // Records that were not answered yet are picked up once they are
+                    if let Some(addrs) = self.srv_answers.lock().get(&name) {
+                        members.extend(addrs.iter().map(|addr| member_for(*addr)));
+                    }
+                    continue;
                 }
             };
-            let addr: SocketAddr = addrs[0];
-            let member = Member { address: format!("{}", addr.ip()),
-                                  swim_port: addr.port(),
-                                  gossip_port: addr.port(),
-                                  ..Default::default() };
+            let previous_retry = previous_retries.remove(&line);
+            if let Some(retry) = previous_retry.filter(|retry| retry.at > now) {
+                retries.insert(line, retry);
+                continue;
+            }
+            let (addr, swim_port) = match split_swim_port(&line) {
+                Some((addr, swim_port)) => (addr, Some(swim_port)),
+                None => (line.clone(), None),
+            };
+            let hostname = is_hostname(&addr);
+            let addr =
+                match util::resolve_socket_addr_with_default_port(&addr,
+                                                                  GossipListenAddr::DEFAULT_PORT)
+                {
+                    Ok((_, addr)) => {
+                        if hostname {
+                            if let Some(previous) =
+                                previous_resolved.remove(&line).filter(|p| *p != addr)
+                            {
+                                outputln!("Peer {} now resolves to {} instead of {}",
+                                          line,
+                                          addr,
+                                          previous);
+                            }
+                            resolved.insert(line.clone(), addr);
+                        }
+                        addr
+                    }
+                    Err(e) => {
+                        PEER_RESOLUTION_FAILURES.inc();
+                        let retry = Retry::after_failure(previous_retry, now);
+                        outputln!("Failed to resolve peer {}, trying again in {}s: {}",
+                                  line,
+                                  retry_delay(retry.failures).as_secs(),
+                                  e);
+                        retries.insert(line, retry);
+                        continue;
+                    }
+                };
+            let mut member = member_for(addr);
+            if let Some(swim_port) = swim_port {
+                member.swim_port = swim_port;
+            }
+            member.persistent = permanent;
             members.push(member);
         }
+        *self.next_resolution.lock() = if resolved.is_empty() || self.resolve_interval.is_zero() {
+            None
+        } else {
+            Some(now + self.resolve_interval)
+        };
         self.have_events.store(false, Ordering::Relaxed);
         Ok(members)
     }
 }
 
+impl Drop for PeerWatcher {
+    fn drop(&mut self) {
+        for thread in &self.threads {
+            let _ = thread.stop.send(());
+        }
+        for thread in self.threads.drain(..) {
+            if thread.handle.join().is_err() {
+                debug!("PeerWatcher({}) thread panicked", self.path.display());
+            }
+        }
+    }
+}
+
+impl PeerSource for PeerWatcher {
+    fn has_events(&self) -> bool { self.has_fs_events() }
+
+    fn get_members(&self) -> Result<Vec<Member>> { PeerWatcher::get_members(self) }
+}
+
+/// The entries of the peer file, or of every fragment of a directory of them in order of their
+/// names. A file that can't be read has no entries.
+fn peer_entries(path: &Path) -> Vec<PeerEntry> {
+    if path.is_dir() {
+        return fragments(path).into_iter()
+                              .flat_map(|fragment| peer_entries(&fragment))
+                              .collect();
+    }
+    if let Some(format) = StructuredFormat::of(path) {
+        return match fs::read_to_string(path) {
+            Ok(contents) => structured_peer_entries(path, format, &contents),
+            Err(_) => Vec::new(),
+        };
+    }
+    match File::open(path) {
+        Ok(file) => {
+            BufReader::new(file).lines()
+                                .flatten()
+                                .filter_map(|line| PeerEntry::parse(&line))
+                                .collect()
+        }
+        Err(_) => Vec::new(),
+    }
+}
+
+/// The entries of a structured peer file. A file that can't be parsed has no entries, and a peer
+/// that is neither an address nor an SRV record is skipped.
+fn structured_peer_entries(path: &Path,
+                           format: StructuredFormat,
+                           contents: &str)
+                           -> Vec<PeerEntry> {
+    let peer_file = match format.parse(contents) {
+        Ok(peer_file) => peer_file,
+        Err(e) => {
+            outputln!("PeerWatcher({}) could not parse the peer file, {}",
+                      path.display(),
+                      e);
+            return Vec::new();
+        }
+    };
+    peer_file.peer
+             .into_iter()
+             .enumerate()
+             .filter_map(|(index, peer)| {
+                 match peer.into_entry() {
+                     Ok(entry) => Some(entry),
+                     Err(e) => {
+                         outputln!("PeerWatcher({}) skipping peer {}, {}",
+                                   path.display(),
+                                   index + 1,
+                                   e);
+                         None
+                     }
+                 }
+             })
+             .collect()
+}
+
+/// Split the address of a peer with separate SWIM and gossip ports, `HOST:SWIM_PORT:GOSSIP_PORT`
+/// or `[IPV6]:SWIM_PORT:GOSSIP_PORT`, into the address of its gossip listener and its SWIM port.
+/// Other addresses, including unbracketed IPv6 addresses, are not split.
+fn split_swim_port(line: &str) -> Option<(String, u16)> {
+    let mut parts = line.rsplitn(3, ':');
+    let gossip_port = parts.next()?.parse::<u16>().ok()?;
+    let swim_port = parts.next()?.parse::<u16>().ok()?;
+    let host = parts.next()?;
+    let bracketed = host.starts_with('[') && host.ends_with(']');
+    if host.is_empty() || (host.contains(':') && !bracketed) {
+        return None;
+    }
+    Some((format!("{}:{}", host, gossip_port), swim_port))
+}
+
+/// The files of a directory of peer file fragments, sorted by name
+fn fragments(dir: &Path) -> Vec<PathBuf> {
+    let mut fragments = match fs::read_dir(dir) {
+        Ok(entries) => {
+            entries.flatten()
+                   .map(|entry| entry.path())
+                   .filter(|path| path.is_file() && !is_hidden(path))
+                   .collect::<Vec<_>>()
+        }
+        Err(_) => Vec::new(),
+    };
+    fragments.sort();
+    fragments
+}
+
+/// Whether the address of a peer is a hostname rather than an IP address, with or without a port.
+/// IPv6 addresses may be enclosed in square brackets either way.
+fn is_hostname(addr: &str) -> bool {
+    let (host, _) = util::split_host_port(addr);
+    host.parse::<IpAddr>().is_err()
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .map_or(false, |name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Resolve the targets of the SRV record `name` to the addresses of peers, in order of priority
+fn resolve_srv(resolver: &Resolver, name: &str) -> Result<Vec<SocketAddr>> {
+    let lookup = resolver.srv_lookup(name)
+                         .map_err(|e| Error::SrvLookup(name.to_string(), e.to_string()))?;
+    let mut records = lookup.iter().collect::<Vec<_>>();
+    records.sort_by_key(|srv| (srv.priority(), std::cmp::Reverse(srv.weight())));
+    let mut addrs = Vec::new();
+    for srv in records {
+        let target = srv.target().to_utf8();
+        let ips = resolver.lookup_ip(target.as_str())
+                          .map_err(|e| Error::SrvLookup(target.clone(), e.to_string()))?;
+        addrs.extend(ips.iter().map(|ip| SocketAddr::new(ip, srv.port())));
+    }
+    Ok(addrs)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -205,7 +787,10 @@ mod tests {
     fn with_file() {
         let tmpdir = TempDir::new().unwrap();
 
-        let peer_lines = vec!["1.2.3.4:5".to_string(), "4.3.2.1".to_string()];
+        let peer_lines = vec!["1.2.3.4:5".to_string(),
+                              "4.3.2.1".to_string(),
+                              "[::1]:6".to_string(),
+                              "::1".to_string()];
 
         let lock = lock_env_var();
         lock.unset();
@@ -224,7 +809,17 @@ mod tests {
                                swim_port: GossipListenAddr::DEFAULT_PORT,
                                gossip_port: GossipListenAddr::DEFAULT_PORT,
                                ..Default::default() };
-        let expected_members = vec![member1, member2];
+        let member3 = Member { id: String::new(),
+                               address: String::from("::1"),
+                               swim_port: 6,
+                               gossip_port: 6,
+                               ..Default::default() };
+        let member4 = Member { id: String::new(),
+                               address: String::from("::1"),
+                               swim_port: GossipListenAddr::DEFAULT_PORT,
+                               gossip_port: GossipListenAddr::DEFAULT_PORT,
+                               ..Default::default() };
+        let expected_members = vec![member1, member2, member3, member4];
         assert_eq!(expected_members, members);
     }
 
@@ -252,4 +847,298 @@ mod tests {
         let expected_members = vec![member1, member2];
         assert_eq!(expected_members, members);
     }
+
+    #[test]
+    fn peer_entries_are_parsed() {
+        assert_eq!(PeerEntry::parse("1.2.3.4:5"),
+                   Some(PeerEntry::Address(String::from("1.2.3.4:5"))));
+        assert_eq!(PeerEntry::parse("srv:_hab-gossip._tcp.example.com"),
+                   Some(PeerEntry::Srv(String::from("_hab-gossip._tcp.example.com"))));
+        assert_eq!(PeerEntry::parse("  "), None);
+        assert_eq!(PeerEntry::parse("# bastion ring"), None);
+        assert_eq!(PeerEntry::parse("1.2.3.4 # bastion"),
+                   Some(PeerEntry::Address(String::from("1.2.3.4"))));
+    }
+
+    #[test]
+    fn structured_peers_are_written_as_lines() {
+        let toml = r#"
+            # The bastion ring
+            [[peer]]
+            address = "10.0.0.1"
+            permanent = true
+
+            [[peer]]
+            address = "fd00::2"
+            swim_port = 9000
+            gossip_port = 9001
+
+            [[peer]]
+            address = "10.0.0.3"
+            gossip_port = 9001
+
+            [[peer]]
+            srv = "_hab-gossip._tcp.example.com"
+
+            [[peer]]
+            swim_port = 9000
+        "#;
+        let entries =
+            structured_peer_entries(Path::new("peers.toml"), StructuredFormat::Toml, toml);
+        assert_eq!(entries,
+                   vec![PeerEntry::Permanent(String::from("10.0.0.1")),
+                        PeerEntry::Address(String::from("[fd00::2]:9000:9001")),
+                        PeerEntry::Address(String::from("10.0.0.3:9001")),
+                        PeerEntry::Srv(String::from("_hab-gossip._tcp.example.com"))]);
+
+        let json = r#"{"peer": [{"address": "10.0.0.1", "swim_port": 9000},
+                                {"srv": "_hab-gossip._tcp.example.com", "permanent": true}]}"#;
+        let entries =
+            structured_peer_entries(Path::new("peers.json"), StructuredFormat::Json, json);
+        assert_eq!(entries,
+                   vec![PeerEntry::Address(format!("10.0.0.1:9000:{}",
+                                                   GossipListenAddr::DEFAULT_PORT))]);
+
+        let unknown_key = "[[peer]]\naddress = \"10.0.0.1\"\nport = 9000\n";
+        assert_eq!(structured_peer_entries(Path::new("peers.toml"),
+                                           StructuredFormat::Toml,
+                                           unknown_key),
+                   vec![]);
+    }
+
+    #[test]
+    fn with_structured_file() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("peers.toml");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "[[peer]]\naddress = \"1.2.3.4\"\npermanent = true").unwrap();
+        writeln!(file, "[[peer]]\naddress = \"4.3.2.1\"\nswim_port = 9000").unwrap();
+
+        let lock = lock_env_var();
+        lock.unset();
+        let watcher = PeerWatcher::run(path).unwrap();
+
+        let members = watcher.get_members()
+                             .unwrap()
+                             .into_iter()
+                             .map(|m| (m.address, m.swim_port, m.gossip_port, m.persistent))
+                             .collect::<Vec<_>>();
+        assert_eq!(members,
+                   vec![(String::from("1.2.3.4"),
+                         GossipListenAddr::DEFAULT_PORT,
+                         GossipListenAddr::DEFAULT_PORT,
+                         true),
+                        (String::from("4.3.2.1"), 9000, GossipListenAddr::DEFAULT_PORT, false)]);
+        assert_eq!(StructuredFormat::of(Path::new("peers.json")),
+                   Some(StructuredFormat::Json));
+        assert_eq!(StructuredFormat::of(Path::new("peers")), None);
+    }
+
+    #[test]
+    fn swim_ports_are_split_from_addresses() {
+        assert_eq!(split_swim_port("1.2.3.4:5:6"),
+                   Some((String::from("1.2.3.4:6"), 5)));
+        assert_eq!(split_swim_port("[::1]:5:6"),
+                   Some((String::from("[::1]:6"), 5)));
+        assert_eq!(split_swim_port("1.2.3.4:5"), None);
+        assert_eq!(split_swim_port("[::1]:6"), None);
+        assert_eq!(split_swim_port("fe80::1:2"), None);
+        assert_eq!(split_swim_port("::1:2:3"), None);
+    }
+
+    #[test]
+    fn with_separate_swim_and_gossip_ports() {
+        let tmpdir = TempDir::new().unwrap();
+        let peer_lines = vec!["1.2.3.4:9000:9001".to_string(),
+                              "[::1]:9002:9003".to_string()];
+
+        let lock = lock_env_var();
+        lock.unset();
+        let members = peer_watcher_member_load_test(tmpdir.path(), &peer_lines).unwrap();
+
+        let ports = members.into_iter()
+                           .map(|m| (m.address, m.swim_port, m.gossip_port))
+                           .collect::<Vec<_>>();
+        assert_eq!(ports,
+                   vec![(String::from("1.2.3.4"), 9000, 9001),
+                        (String::from("::1"), 9002, 9003)]);
+    }
+
+    #[test]
+    fn with_srv_records() {
+        let tmpdir = TempDir::new().unwrap();
+        let peer_lines = vec!["1.2.3.4".to_string(),
+                              "srv:_hab-gossip._tcp.example.com".to_string(),
+                              "srv:_hab-gossip._tcp.unanswered.example.com".to_string()];
+        let path = tmpdir.path().join("some_file");
+        let mut file = File::create(&path).unwrap();
+        for line in &peer_lines {
+            writeln!(file, "{}", line).unwrap();
+        }
+
+        // Answer the SRV records directly rather than querying DNS from a resolver thread
+        let srv_answers = SrvAnswers::default();
+        srv_answers.lock()
+                   .insert(String::from("_hab-gossip._tcp.example.com"),
+                           vec!["10.0.0.1:9000".parse().unwrap(),
+                                "10.0.0.2:9000".parse().unwrap()]);
+        let watcher = PeerWatcher { path,
+                                    have_events: Arc::new(AtomicBool::new(true)),
+                                    srv_answers,
+                                    retries: Mutex::default(),
+                                    resolve_interval: Duration::ZERO,
+                                    next_resolution: Mutex::default(),
+                                    resolved: Mutex::default(),
+                                    threads: Vec::new() };
+
+        let addresses = watcher.get_members()
+                               .unwrap()
+                               .into_iter()
+                               .map(|m| (m.address, m.gossip_port))
+                               .collect::<Vec<_>>();
+        assert_eq!(addresses,
+                   vec![(String::from("1.2.3.4"), GossipListenAddr::DEFAULT_PORT),
+                        (String::from("10.0.0.1"), 9000),
+                        (String::from("10.0.0.2"), 9000)]);
+    }
+
+    #[test]
+    fn hostnames_are_resolved_again_periodically() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("some_file");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "1.2.3.4").unwrap();
+        let watcher = PeerWatcher { path,
+                                    have_events: Arc::new(AtomicBool::new(true)),
+                                    srv_answers: SrvAnswers::default(),
+                                    retries: Mutex::default(),
+                                    resolve_interval: Duration::from_secs(60),
+                                    next_resolution: Mutex::default(),
+                                    resolved: Mutex::default(),
+                                    threads: Vec::new() };
+
+        // Addresses never need to be resolved again
+        watcher.get_members().unwrap();
+        assert!(watcher.next_resolution.lock().is_none());
+        assert!(!watcher.has_fs_events());
+
+        writeln!(file, "localhost:9000").unwrap();
+        watcher.get_members().unwrap();
+        assert!(watcher.resolved.lock().contains_key("localhost:9000"));
+        assert!(watcher.next_resolution.lock().is_some());
+        assert!(!watcher.has_fs_events());
+
+        *watcher.next_resolution.lock() = Some(Instant::now());
+        assert!(watcher.has_fs_events());
+    }
+
+    #[test]
+    fn hostnames_are_told_from_addresses() {
+        assert!(is_hostname("localhost"));
+        assert!(is_hostname("peer.example.com:9638"));
+        assert!(!is_hostname("1.2.3.4"));
+        assert!(!is_hostname("1.2.3.4:9638"));
+        assert!(!is_hostname("::1"));
+        assert!(!is_hostname("[::1]:9638"));
+        assert!(!is_hostname("[2001:db8::1]"));
+        assert!(!is_hostname("2001:db8::1"));
+    }
+
+    #[test]
+    fn unresolvable_peers_are_skipped_until_they_are_retried() {
+        let tmpdir = TempDir::new().unwrap();
+        let peer_lines = vec!["1.2.3.4:not-a-port".to_string(), "4.3.2.1".to_string()];
+
+        let path = tmpdir.path().join("some_file");
+        let mut file = File::create(&path).unwrap();
+        for line in &peer_lines {
+            writeln!(file, "{}", line).unwrap();
+        }
+        let watcher = PeerWatcher { path,
+                                    have_events: Arc::new(AtomicBool::new(true)),
+                                    srv_answers: SrvAnswers::default(),
+                                    retries: Mutex::default(),
+                                    resolve_interval: Duration::ZERO,
+                                    next_resolution: Mutex::default(),
+                                    resolved: Mutex::default(),
+                                    threads: Vec::new() };
+        let start = Instant::now();
+
+        let failures = PEER_RESOLUTION_FAILURES.get();
+        let addresses = watcher.get_members_at(start)
+                               .unwrap()
+                               .into_iter()
+                               .map(|m| m.address)
+                               .collect::<Vec<_>>();
+        assert_eq!(addresses, vec![String::from("4.3.2.1")]);
+        assert_eq!(PEER_RESOLUTION_FAILURES.get(), failures + 1);
+        assert_eq!(watcher.retries.lock()["1.2.3.4:not-a-port"].failures, 1);
+
+        // The address is not tried again before its retry is due
+        let before_retry = start + RETRY_MIN_DELAY - Duration::from_millis(1);
+        assert!(!watcher.has_fs_events_at(before_retry));
+        assert_eq!(watcher.get_members_at(before_retry).unwrap().len(), 1);
+        assert_eq!(watcher.retries.lock()["1.2.3.4:not-a-port"].failures, 1);
+
+        let retry = start + RETRY_MIN_DELAY;
+        assert!(watcher.has_fs_events_at(retry));
+        assert_eq!(watcher.get_members_at(retry).unwrap().len(), 1);
+        assert_eq!(watcher.retries.lock()["1.2.3.4:not-a-port"].failures, 2);
+
+        // The next retry backs off
+        assert!(!watcher.has_fs_events_at(retry + RETRY_MIN_DELAY));
+        assert!(watcher.has_fs_events_at(retry + retry_delay(2)));
+    }
+
+    #[test]
+    fn retries_back_off_exponentially() {
+        assert_eq!(retry_delay(1), RETRY_MIN_DELAY);
+        assert_eq!(retry_delay(2), RETRY_MIN_DELAY * 2);
+        assert_eq!(retry_delay(4), RETRY_MIN_DELAY * 8);
+        assert_eq!(retry_delay(20), RETRY_MAX_DELAY);
+        assert_eq!(retry_delay(u32::MAX), RETRY_MAX_DELAY);
+    }
+
+    #[test]
+    fn with_directory_of_fragments() {
+        let tmpdir = TempDir::new().unwrap();
+        for (name, peer) in &[("web-2", "1.2.3.5"),
+                              ("web-1", "1.2.3.4:5"),
+                              (".web-3.tmp", "1.2.3.6")]
+        {
+            let mut file = File::create(tmpdir.path().join(name)).unwrap();
+            writeln!(file, "{}", peer).unwrap();
+        }
+
+        let lock = lock_env_var();
+        lock.unset();
+        let watcher = PeerWatcher::run(tmpdir.path()).unwrap();
+
+        assert!(watcher.has_fs_events());
+        let addresses = watcher.get_members()
+                               .unwrap()
+                               .into_iter()
+                               .map(|m| (m.address, m.gossip_port))
+                               .collect::<Vec<_>>();
+        assert_eq!(addresses,
+                   vec![(String::from("1.2.3.4"), 5),
+                        (String::from("1.2.3.5"), GossipListenAddr::DEFAULT_PORT)]);
+    }
+
+    #[test]
+    fn dropping_the_watcher_stops_its_threads() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("peers");
+        File::create(&path).unwrap();
+
+        let lock = lock_env_var();
+        lock.unset();
+        let watcher = PeerWatcher::run(path).unwrap();
+        assert_eq!(watcher.threads.len(), 2);
+
+        // The SRV resolver is woken up rather than left to sleep out its query interval
+        let dropped_at = Instant::now();
+        drop(watcher);
+        assert!(dropped_at.elapsed() < SrvQueryInterval::configured_value().into());
+    }
 }
@@ -0,0 +1,35 @@
+//! Keeps the artifact cache warm with the current releases of the packages of a package set file,
+//! so that installing them later, during a scheduled maintenance window or a service update, does
+//! not wait on downloads. The file is read again before each prefetch, so it can be edited while
+//! the Supervisor runs.
+
+use crate::util;
+use habitat_common::outputln;
+use std::{path::PathBuf,
+          time::Duration};
+use tokio::time;
+
+static LOGKEY: &str = "PF";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrefetchConfig {
+    /// The package set file, or file of newline separated package identifiers, to prefetch
+    pub path:     PathBuf,
+    /// How often the packages are prefetched
+    pub interval: Duration,
+}
+
+/// Prefetch the packages of `config` from `bldr_url` every interval, forever
+pub async fn run(config: PrefetchConfig, bldr_url: String) {
+    loop {
+        match util::pkg::prefetch(&bldr_url, &config.path).await {
+            Ok(()) => debug!("Prefetched the packages of {}", config.path.display()),
+            Err(e) => {
+                outputln!("Unable to prefetch the packages of {}, {}",
+                          config.path.display(),
+                          e)
+            }
+        }
+        time::sleep(config.interval).await;
+    }
+}
@@ -0,0 +1,56 @@
+//! Keeps the Butterfly server's keyring in step with the revisions of the ring key in the key
+//! cache, so that the ring key can be rotated without restarting the Supervisor. To rotate the key,
+//! add its new revision to the key cache of every Supervisor in the ring. Each Supervisor accepts
+//! messages encrypted with the new revision as soon as it finds it, and starts sending with it once
+//! it has been in its keyring for `HAB_RING_KEY_ACTIVATION_DELAY_SECS`, or as soon as it restarts.
+//! Once every Supervisor sends with the new revision, the old one can be removed from the key
+//! caches.
+
+use habitat_butterfly::Server;
+use habitat_common::outputln;
+use habitat_core::crypto::keys::{KeyCache,
+                                 RingKey};
+use std::time::Duration;
+use tokio::time;
+
+static LOGKEY: &str = "RK";
+
+habitat_core::env_config_duration!(
+    /// How often the key cache is checked for new or removed revisions of the ring key
+    RingKeyWatcherInterval,
+    HAB_RING_KEY_WATCHER_INTERVAL_SECS => from_secs,
+    Duration::from_secs(30));
+
+/// Load every revision of the ring key `name` in `cache` into the keyring of `server` as it
+/// starts. They are all sent with right away, as the other members are expected to have them.
+pub fn load(server: &Server, cache: &KeyCache, name: &str) {
+    read_revisions(cache, name, |keys| server.load_ring_keys(keys), server);
+}
+
+/// Load every revision of the ring key `name` in `cache` into the keyring of `server`. New ones
+/// are sent with once they are activated.
+pub fn reload(server: &Server, cache: &KeyCache, name: &str) {
+    read_revisions(cache, name, |keys| server.update_ring_keys(keys), server);
+}
+
+fn read_revisions<F>(cache: &KeyCache, name: &str, replace: F, server: &Server)
+    where F: FnOnce(Vec<RingKey>) -> bool
+{
+    match cache.ring_key_revisions(name) {
+        Ok(keys) => {
+            if replace(keys) {
+                outputln!("Ring key revisions are now {}",
+                          server.ring_key_revisions().join(", "));
+            }
+        }
+        Err(e) => outputln!("Unable to read the revisions of ring key {}, {}", name, e),
+    }
+}
+
+pub async fn run(server: Server, cache: KeyCache, name: String) {
+    let interval = RingKeyWatcherInterval::configured_value().into();
+    loop {
+        time::sleep(interval).await;
+        reload(&server, &cache, &name);
+    }
+}
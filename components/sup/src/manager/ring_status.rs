@@ -0,0 +1,140 @@
+//! A summary of the health of the whole ring, as seen by this Supervisor. It is refreshed along
+//! with the HTTP gateway data and served to `hab ring status`.
+
+use crate::census::CensusRing;
+use habitat_butterfly::{member::{Health,
+                                 Membership},
+                        rumor::{Rumor,
+                                RumorStore},
+                        Server};
+use habitat_sup_protocol::ctl::{RingRumorStore,
+                                RingServiceGroup,
+                                RingStatus,
+                                RingVersion};
+use std::collections::{BTreeMap,
+                       BTreeSet,
+                       HashSet};
+
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
+/// * `MemberList::entries` (read)
+pub fn ring_status_rsr_mlr(butterfly: &Server, census_ring: &CensusRing) -> RingStatus {
+    let member_id = butterfly.member_id();
+    let mut status = RingStatus { member_id: Some(member_id.to_string()),
+                                  ..Default::default() };
+    let (mut alive, mut suspect, mut confirmed, mut departed) = (0, 0, 0, 0);
+    let (mut reachable, mut unreachable) = (0, 0);
+    butterfly.member_list
+             .with_memberships_mlr(|Membership { member, health }| {
+                 match health {
+                     Health::Alive => alive += 1,
+                     Health::Suspect => suspect += 1,
+                     Health::Confirmed => confirmed += 1,
+                     Health::Departed => departed += 1,
+                 }
+                 if member.id != member_id {
+                     match health {
+                         Health::Alive => reachable += 1,
+                         Health::Suspect | Health::Confirmed => unreachable += 1,
+                         Health::Departed => (),
+                     }
+                 }
+                 Ok(())
+             })
+             .ok();
+    status.alive = Some(alive);
+    status.suspect = Some(suspect);
+    status.confirmed = Some(confirmed);
+    status.departed = Some(departed);
+    status.flapping = Some(butterfly.member_list.flapping_member_ids_mlr().len() as u32);
+    status.partition_suspected = Some(partition_suspected(reachable, unreachable));
+    let clock_skew = butterfly.clock_skew();
+    status.clock_skew_ms = Some(clock_skew.spread_ms);
+    status.clock_skew_exceeded = Some(clock_skew.exceeds_threshold);
+
+    let mut versions = BTreeMap::<String, HashSet<String>>::new();
+    butterfly.member_list
+             .with_memberships_mlr(|Membership { member, health }| {
+                 if let (Some(version), false) = (member.version, health == Health::Departed) {
+                     versions.entry(version).or_default().insert(member.id);
+                 }
+                 Ok(())
+             })
+             .ok();
+    // Members of older versions only report theirs along with the services they run
+    let counted: HashSet<String> = versions.values().flatten().cloned().collect();
+    for group in census_ring.groups() {
+        for member in group.members().filter(|m| !m.departed()) {
+            if let Some(version) = &member.sys.version {
+                if !counted.contains(member.member_id.as_str()) {
+                    versions.entry(version.clone())
+                            .or_default()
+                            .insert(member.member_id.to_string());
+                }
+            }
+        }
+    }
+    status.supervisor_versions = versions.into_iter()
+                                         .map(|(version, members)| {
+                                             RingVersion { version: Some(version),
+                                                           members: Some(members.len() as u32), }
+                                         })
+                                         .collect();
+
+    status.rumor_stores = vec![rumor_store("service", &butterfly.service_store),
+                               rumor_store("service_config", &butterfly.service_config_store),
+                               rumor_store("service_file", &butterfly.service_file_store),
+                               rumor_store("election", &butterfly.election_store),
+                               rumor_store("election_update", &butterfly.update_store),
+                               rumor_store("departure", &butterfly.departure_store),
+                               rumor_store("fleet_manifest", &butterfly.fleet_manifest_store),];
+
+    let mut groups = census_ring.groups();
+    groups.sort_by_key(|group| group.service_group.to_string());
+    status.service_groups =
+        groups.into_iter()
+              .map(|group| {
+                  let packages: BTreeSet<String> =
+                      group.active_members().map(|m| m.pkg.to_string()).collect();
+                  RingServiceGroup { service_group:          Some(group.service_group.to_string()),
+                                     active_members:         Some(group.active_members().count()
+                                                                  as u32),
+                                     packages:               packages.into_iter().collect(),
+                                     election_status:        Some(group.election_status
+                                                                       .to_string()),
+                                     leader_id:              group.leader_id.clone(),
+                                     update_election_status: Some(group.update_election_status
+                                                                       .to_string()), }
+              })
+              .collect();
+    status
+}
+
+/// # Locking (see locking.md)
+/// * `RumorStore::list` (read)
+fn rumor_store<T: Rumor>(name: &str, store: &RumorStore<T>) -> RingRumorStore {
+    let size = store.size_rsr();
+    RingRumorStore { name:   Some(name.to_string()),
+                     rumors: Some(size.rumors as u32),
+                     bytes:  Some(size.bytes as u64), }
+}
+
+/// When at least half of the other members are unreachable it is more likely that the network has
+/// partitioned than that so many members have failed at once.
+fn partition_suspected(reachable: u32, unreachable: u32) -> bool {
+    unreachable > 0 && unreachable * 2 >= reachable + unreachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partition_is_suspected_when_half_the_ring_is_unreachable() {
+        assert!(!partition_suspected(0, 0));
+        assert!(!partition_suspected(5, 0));
+        assert!(!partition_suspected(5, 2));
+        assert!(partition_suspected(3, 3));
+        assert!(partition_suspected(0, 4));
+    }
+}
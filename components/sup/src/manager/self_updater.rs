@@ -1,7 +1,8 @@
 //! Encapsulates logic required for updating the Habitat Supervisor
 //! itself.
 
-use crate::util;
+use crate::{event::PackageChangeInitiator,
+            util};
 use habitat_common::command::package::install::InstallSource;
 use habitat_core::{package::{PackageIdent,
                              PackageInstall},
@@ -104,7 +105,11 @@ impl SelfUpdater {
                splay.as_secs());
         tokiotime::sleep(splay).await;
         loop {
-            match util::pkg::install_no_ui(&update_url, &install_source, &update_channel).await {
+            match util::pkg::install_no_ui(&update_url,
+                                           &install_source,
+                                           &update_channel,
+                                           PackageChangeInitiator::SelfUpdater).await
+            {
                 Ok(package) => {
                     if &current < package.ident() {
                         debug!("Self updater installing newer Supervisor, {}",
@@ -9,36 +9,60 @@
 // system, and we'd use separate internal types for our core logic.
 
 mod context;
+mod coordinated_unload;
+pub mod data_retention;
+mod environment_file;
+mod file_receipt;
 mod health;
 mod hook_runner;
 mod hooks;
 #[cfg(windows)]
 mod pipe_hook_client;
+mod placement;
+mod resources;
+mod run_manifest;
 pub mod spec;
 mod supervisor;
 mod terminator;
+mod update_history;
 
 use self::{context::RenderContext,
+           coordinated_unload::{Step as UnloadStep,
+                                UnloadCoordination},
+           environment_file,
            hook_runner::HookRunner,
            hooks::{HookCompileTable,
                    HookTable},
+           placement::ConstraintViolation,
+           resources::ResourceSampler,
            supervisor::{PidUpdate,
                         Supervisor}};
-pub use self::{health::{HealthCheckBundle,
+pub use self::{coordinated_unload::{new_unload_id,
+                                    DEFAULT_COORDINATION_TIMEOUT},
+               data_retention::DataRetention,
+               file_receipt::{FileOutcome,
+                              FileReceipt},
+               health::{HealthCheckBundle,
+                        HealthCheckData,
                         HealthCheckHookStatus,
                         HealthCheckResult},
                hooks::{HealthCheckHook,
                        ProcessOutput,
                        StandardStreams},
+               resources::ResourceUsage,
                spec::{DesiredState,
-                      ServiceSpec}};
+                      ServiceSpec},
+               update_history::UpdateHistory};
 use crate::{census::{CensusGroup,
                      CensusRing,
                      ElectionStatus,
                      ServiceFile},
             error::{Error,
                     Result},
-            manager::{event,
+            extension,
+            manager::{acme::{CertificateRequest,
+                             IssuedCertificate},
+                      event,
                       sync::GatewayState,
                       FsCfg,
                       ServicePidSource,
@@ -46,7 +70,9 @@ use crate::{census::{CensusGroup,
                       Sys}};
 use futures::future::{self,
                       AbortHandle};
-use habitat_butterfly::rumor::service::Service as ServiceRumor;
+use habitat_butterfly::rumor::{election::LeaderAffinity,
+                               service::{Service as ServiceRumor,
+                                         UnloadPhase}};
 #[cfg(windows)]
 use habitat_common::templating::package::DEFAULT_USER;
 pub use habitat_common::templating::{config::{Cfg,
@@ -56,7 +82,8 @@ pub use habitat_common::templating::{config::{Cfg,
                                                PkgProxy}};
 use habitat_common::{outputln,
                      templating::{config::CfgRenderer,
-                                  hooks::Hook},
+                                  hooks::Hook,
+                                  TemplateRenderer},
                      FeatureFlag};
 #[cfg(windows)]
 use habitat_core::os::users;
@@ -64,11 +91,13 @@ use habitat_core::{crypto::Blake2bHash,
                    flowcontrol::Backoff,
                    fs::{atomic_write,
                         svc_hooks_path,
+                        svc_update_history_file,
                         SvcDir,
                         FS_ROOT_PATH},
                    os::process::{Pid,
                                  ShutdownTimeout},
-                   package::{metadata::Bind,
+                   package::{metadata::{Bind,
+                                        HookRole},
                              PackageIdent,
                              PackageInstall},
                    service::{ServiceBind,
@@ -88,7 +117,9 @@ use serde::{ser::{Error as _,
             Serialize,
             Serializer};
 use std::{self,
-          collections::HashSet,
+          collections::{BTreeMap,
+                        BTreeSet,
+                        HashSet},
           fmt,
           fs,
           ops::Deref,
@@ -98,6 +129,7 @@ use std::{self,
           sync::{Arc,
                  Mutex},
           time::{Duration,
+                 Instant,
                  SystemTime}};
 
 use super::ServiceRestartConfig;
@@ -107,6 +139,19 @@ static LOGKEY: &str = "SR";
 #[cfg(not(windows))]
 pub const GOSSIP_FILE_PERMISSIONS: u32 = 0o640;
 
+/// The directory of the service's config directory that certificates obtained through ACME are
+/// written to
+const ACME_CONFIG_DIR: &str = "acme";
+/// The variables telling the `bind-degraded` and `bind-restored` hooks which bind changed
+const BIND_NAME_VAR: &str = "HAB_BIND_NAME";
+const BIND_SERVICE_GROUP_VAR: &str = "HAB_BIND_SERVICE_GROUP";
+
+habitat_core::env_config_duration!(
+    /// How often the `backup` hook of a running service is run
+    BackupHookInterval,
+    HAB_BACKUP_HOOK_INTERVAL_SECS => from_secs,
+    Duration::from_secs(24 * 60 * 60));
+
 lazy_static! {
     static ref HOOK_DURATION: HistogramVec =
         register_histogram_vec!("hab_sup_hook_duration_seconds",
@@ -142,13 +187,19 @@ struct TemplateUpdate {
     hooks:                 HookCompileTable,
     config_changed:        bool,
     have_reconfigure_hook: bool,
+    environment_changed:   bool,
 }
 
 impl TemplateUpdate {
-    fn new(hooks: HookCompileTable, config_changed: bool, have_reconfigure_hook: bool) -> Self {
+    fn new(hooks: HookCompileTable,
+           config_changed: bool,
+           have_reconfigure_hook: bool,
+           environment_changed: bool)
+           -> Self {
         Self { hooks,
                config_changed,
-               have_reconfigure_hook }
+               have_reconfigure_hook,
+               environment_changed }
     }
 
     /// Returns `true` if the service needs to be restarted.
@@ -157,6 +208,8 @@ impl TemplateUpdate {
     /// 1. the `init`, `run` or `post-run` hooks have changed. A restart is limited to these hooks
     /// because they are the only hooks that can impact the execution of the service.
     /// 2. `/config` changed and there is no `reconfigure` hook
+    /// 3. the variables of the service's environment files changed, as the running process cannot
+    /// be given them any other way
     fn needs_restart(&self) -> Option<ProcessTerminationReason> {
         if self.hooks.init_changed() {
             Some(ProcessTerminationReason::InitHookUpdated)
@@ -166,6 +219,8 @@ impl TemplateUpdate {
             Some(ProcessTerminationReason::PostRunHookUpdated)
         } else if !self.have_reconfigure_hook && self.config_changed {
             Some(ProcessTerminationReason::AppConfigUpdated)
+        } else if self.environment_changed {
+            Some(ProcessTerminationReason::EnvironmentUpdated)
         } else {
             None
         }
@@ -218,6 +273,8 @@ pub enum ProcessTerminationReason {
     RunHookUpdated,
     #[serde(rename = "post_run_hook_updated")]
     PostRunHookUpdated,
+    #[serde(rename = "environment_updated")]
+    EnvironmentUpdated,
 }
 
 #[derive(Debug, Clone)]
@@ -456,6 +513,12 @@ pub struct Service {
     pub pkg:                 Pkg,
     pub sys:                 Arc<Sys>,
     pub user_config_updated: bool,
+    /// Set when the hook templates of the service changed on disk, to load them again
+    pub hooks_updated:       bool,
+    /// Set when the configuration templates of the service changed on disk, to load them again
+    pub templates_updated:   bool,
+    /// Set when the environment files of the service changed on disk, to load them again
+    pub environment_updated: bool,
     // TODO (DM): The need to track initialization state across ticks would be removed if we
     // migrated away from the event loop architecture to an architecture that had a top level
     // `Service` future. See https://github.com/habitat-sh/habitat/issues/7112
@@ -474,6 +537,19 @@ pub struct Service {
     // :(
     health_check_result:  Arc<Mutex<HealthCheckResult>>,
     last_election_status: ElectionStatus,
+    /// The feature flags the hooks were loaded with, to load them again the same way
+    feature_flags:        FeatureFlag,
+    /// The package's runtime environment, which the variables of the service's environment files
+    /// are added to to make `pkg.env`
+    package_env:          Env,
+    /// This member's role in its leader topology group as of the last tick, which decides
+    /// whether hooks restricted to the leader or the followers run
+    hook_role:            Option<HookRole>,
+    /// Whether the `post-run` hook was skipped because this member didn't hold the role the
+    /// package restricts it to, so that it runs once the member does
+    post_run_pending:     bool,
+    /// When the `backup` hook is next due
+    next_backup:          Option<Instant>,
     /// The binds that the current service package declares, both
     /// required and optional. We don't differentiate because this is
     /// used to validate the user-specified bindings against the
@@ -492,6 +568,29 @@ pub struct Service {
     /// that should be reconciled against the current state of the
     /// census.
     unsatisfied_binds:    HashSet<ServiceBind>,
+    /// The names of the binds mapped to service groups that have members, none of which can serve
+    /// consumers. Like `unsatisfied_binds`, this is runtime information reconciled against the
+    /// census.
+    degraded_binds:       BTreeSet<String>,
+    /// Whether the Supervisor was partitioned from a quorum of the ring when the service last
+    /// acted on the census, running its partition hooks
+    partitioned:          bool,
+    /// The part this member takes in an unload of the service coordinated across its group, if
+    /// any. Like `unsatisfied_binds`, this is runtime information; a coordinated unload does not
+    /// outlive the Supervisor.
+    coordinated_unload:   Option<UnloadCoordination>,
+    /// The placement constraint that currently keeps the service from starting, if any. Like
+    /// `unsatisfied_binds`, this is runtime information reconciled against the census.
+    blocked_by:           Option<ConstraintViolation>,
+    /// What was done with the latest incarnation of each file uploaded to the service group, by
+    /// file name
+    file_receipts:        BTreeMap<String, FileReceipt>,
+    /// Whether a certificate obtained through ACME was installed since the last tick
+    cert_installed:       bool,
+    /// The last error in the service's request for an ACME certificate, to log it only once
+    cert_request_error:   Option<String>,
+    /// The CPU, memory and file descriptors used by the service's processes
+    resources:            ResourceSampler,
     hooks:                HookTable,
     manager_fs_cfg:       Arc<FsCfg>,
     supervisor:           Arc<Mutex<Supervisor>>,
@@ -523,6 +622,145 @@ impl Service {
 
     pub(crate) fn spec(&self) -> ServiceSpec { self.spec.clone() }
 
+    /// The binds the service was loaded with
+    pub(crate) fn binds(&self) -> &[ServiceBind] { &self.spec.binds }
+
+    /// Whether `bind` is mapped to a service group that satisfies its contract, as of the last
+    /// time the binds were validated against the census
+    pub(crate) fn bind_satisfied(&self, bind: &ServiceBind) -> bool {
+        !self.unsatisfied_binds.contains(bind)
+    }
+
+    /// The result of the last health check of the service
+    pub(crate) fn health_check_result(&self) -> HealthCheckResult {
+        *self.health_check_result
+             .lock()
+             .expect("Could not unlock health_check_result")
+    }
+
+    /// The TLS certificate the service requests through ACME in its configuration, if any
+    pub(crate) fn certificate_request(&mut self) -> Option<CertificateRequest> {
+        let request = match toml::Value::try_from(&self.cfg) {
+            Ok(toml::Value::Table(cfg)) => CertificateRequest::from_cfg(&cfg),
+            Ok(_) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        };
+        match request {
+            Ok(request) => {
+                self.cert_request_error = None;
+                request
+            }
+            Err(e) => {
+                if self.cert_request_error.as_ref() != Some(&e) {
+                    outputln!(preamble self.service_group,
+                              "Invalid acme configuration, not requesting a certificate: {}", e);
+                    self.cert_request_error = Some(e);
+                }
+                None
+            }
+        }
+    }
+
+    /// Write a certificate obtained through ACME to the service's config directory. If it
+    /// changed, the reload and reconfigure hooks run on the next tick.
+    pub(crate) fn install_certificate(&mut self, certificate: &IssuedCertificate) {
+        let dir = self.pkg.svc_config_path.join(ACME_CONFIG_DIR);
+        if let Err(e) = fs::create_dir_all(&dir) {
+            outputln!(preamble self.service_group,
+                      "Failed to create the certificate directory {}, {}",
+                      dir.display(), e);
+            return;
+        }
+        let cert_changed =
+            self.write_cache_file(dir.join("cert.pem"), certificate.certificate.as_bytes());
+        let key_changed =
+            self.write_cache_file(dir.join("key.pem"), certificate.private_key.as_bytes());
+        if cert_changed || key_changed {
+            outputln!(preamble self.service_group, "Installed a new TLS certificate");
+            self.cert_installed = true;
+        }
+    }
+
+    /// What was done with the latest incarnation of each file uploaded to the service group
+    pub(crate) fn file_receipts(&self) -> impl Iterator<Item = (&String, &FileReceipt)> {
+        self.file_receipts.iter()
+    }
+
+    /// The latest package updates of the service
+    pub(crate) fn update_history(&self) -> UpdateHistory {
+        UpdateHistory::read(&svc_update_history_file(self.service_group.service()))
+    }
+
+    /// Record in the update history of the service that it is being updated to `new_ident`
+    pub(crate) fn record_update_started(&self, new_ident: &PackageIdent) {
+        let path = svc_update_history_file(self.service_group.service());
+        let mut history = UpdateHistory::read(&path);
+        history.start(self.pkg.ident.as_ref(),
+                      new_ident,
+                      self.update_strategy(),
+                      SystemTime::now());
+        if let Err(e) = history.write(&path) {
+            outputln!(preamble self.service_group,
+                      "Unable to record the update to {}: {}", new_ident, e);
+        }
+    }
+
+    /// Record in the update history of the service that the update underway, if any, is done
+    /// now that the service has started again
+    pub(crate) fn record_update_finished(&self) {
+        let path = svc_update_history_file(self.service_group.service());
+        let mut history = UpdateHistory::read(&path);
+        if history.finish(SystemTime::now()) {
+            if let Err(e) = history.write(&path) {
+                outputln!(preamble self.service_group,
+                          "Unable to record the end of the update: {}", e);
+            }
+        }
+    }
+
+    /// Start coordinating the unload `id` of the service across its group, giving the other
+    /// members `timeout` to drain. This member drains right away.
+    pub(crate) fn request_coordinated_unload(&mut self, id: String, timeout: Duration) {
+        let mut coordination = UnloadCoordination::request(id, timeout);
+        outputln!(preamble self.service_group,
+                  "Coordinating an unload across the service group, {}",
+                  coordination.unload().id);
+        if !self.drain() {
+            outputln!(preamble self.service_group,
+                      "Aborting the coordinated unload, as the service failed to drain");
+            coordination.set_phase(UnloadPhase::Aborted);
+        }
+        self.coordinated_unload = Some(coordination);
+    }
+
+    /// Whether an unload of the service coordinated across its group is yet to be committed or
+    /// aborted
+    pub(crate) fn coordinated_unload_in_progress(&self) -> bool {
+        self.coordinated_unload
+            .as_ref()
+            .map_or(false, UnloadCoordination::in_progress)
+    }
+
+    /// How far the unload `id` of the service coordinated across its group has come, if it is
+    /// the one this member takes part in
+    pub(crate) fn coordinated_unload_phase(&self, id: &str) -> Option<UnloadPhase> {
+        self.coordinated_unload
+            .as_ref()
+            .map(UnloadCoordination::unload)
+            .filter(|unload| unload.id == id)
+            .map(|unload| unload.phase)
+    }
+
+    /// Whether the service is to be unloaded, as every member of its group drained for a
+    /// coordinated unload
+    pub(crate) fn coordinated_unload_committed(&self) -> bool {
+        self.coordinated_unload
+            .as_ref()
+            .map_or(false, |coordination| {
+                coordination.unload().phase == UnloadPhase::Committed
+            })
+    }
+
     pub(crate) fn set_spec(&mut self, spec: ServiceSpec) {
         trace!("Setting spec for {}: {:?}", self.spec.ident, spec);
         self.spec = spec
@@ -545,11 +783,14 @@ impl Service {
         if let Some(timeout) = spec.shutdown_timeout {
             pkg.shutdown_timeout = timeout;
         }
+        pkg.strict_deps = spec.strict_deps;
         let spec_file = manager_fs_cfg.specs_path.join(spec.file());
         let service_group = ServiceGroup::new(&pkg.name, &spec.group, organization)?;
         let config_root = Self::config_root(&pkg, spec.config_from.as_ref());
         let hooks_root = Self::hooks_root(&pkg, spec.config_from.as_ref());
         let cfg = Cfg::new(&pkg, spec.config_from.as_ref())?;
+        let package_env = pkg.env.clone();
+        pkg.env = package_env.with_vars(&environment_file::load(cfg.user_config_path.get_path())?);
         let mut service =
             Service { spec,
                       sys,
@@ -561,7 +802,15 @@ impl Service {
                                              svc_hooks_path(&service_group.service()),
                                              feature_flags),
                       last_election_status: ElectionStatus::None,
+                      feature_flags,
+                      package_env,
+                      hook_role: None,
+                      post_run_pending: false,
+                      next_backup: None,
                       user_config_updated: false,
+                      hooks_updated: false,
+                      templates_updated: false,
+                      environment_updated: false,
                       initialization_state:
                           Arc::new(RwLock::new(InitializationState::Uninitialized)),
                       manager_fs_cfg,
@@ -571,6 +820,14 @@ impl Service {
                       service_group,
                       all_pkg_binds,
                       unsatisfied_binds: HashSet::new(),
+                      degraded_binds: BTreeSet::new(),
+                      partitioned: false,
+                      coordinated_unload: None,
+                      blocked_by: None,
+                      file_receipts: BTreeMap::new(),
+                      cert_installed: false,
+                      cert_request_error: None,
+                      resources: ResourceSampler::default(),
                       spec_file,
                       gateway_state,
                       health_check_handle: None,
@@ -629,6 +886,46 @@ impl Service {
                    .join("hooks")
     }
 
+    /// The directory the hook templates of the service are loaded from
+    pub fn hook_templates_path(&self) -> PathBuf {
+        Self::hooks_root(&self.pkg, self.spec.config_from.as_ref())
+    }
+
+    /// The directory the configuration and hook templates of the service are loaded from instead
+    /// of the package, if any
+    pub fn config_from(&self) -> Option<&Path> { self.spec.config_from.as_deref() }
+
+    /// The directory the configuration templates of the service are loaded from
+    pub fn config_template_path(&self) -> PathBuf {
+        Self::config_root(&self.pkg, self.spec.config_from.as_ref())
+    }
+
+    /// Load the configuration templates of the service again. The previous templates are kept
+    /// if the new ones cannot be loaded.
+    fn reload_config_templates(&mut self) -> bool {
+        match CfgRenderer::new(&self.config_template_path()) {
+            Ok(renderer) => {
+                self.config_renderer = renderer;
+                true
+            }
+            Err(e) => {
+                outputln!(preamble self.service_group,
+                          "Reloading configuration templates failed, keeping the previous ones: {}",
+                          e);
+                false
+            }
+        }
+    }
+
+    /// Load the hook templates of the service again, so that once they are compiled, the next
+    /// run of every hook uses the templates as they are on disk now.
+    fn reload_hooks(&mut self) {
+        self.hooks = HookTable::load(&self.pkg.name,
+                                     self.hook_templates_path(),
+                                     svc_hooks_path(&self.service_group.service()),
+                                     self.feature_flags);
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn new(sys: Arc<Sys>,
                      spec: ServiceSpec,
@@ -679,7 +976,7 @@ impl Service {
         }
     }
 
-    fn initialized(&self) -> bool {
+    pub(crate) fn initialized(&self) -> bool {
         *self.initialization_state.read() == InitializationState::Initialized
     }
 
@@ -704,7 +1001,9 @@ impl Service {
         let gateway_state = Arc::clone(&self.gateway_state);
         // Initialize the gateway_state for this service to Unknown.
         gateway_state.lock_gsw()
-                     .set_health_of(service_group.clone(), HealthCheckResult::Unknown);
+                     .set_health_of(service_group.clone(),
+                                    HealthCheckData::new(HealthCheckResult::Unknown,
+                                                         StandardStreams::default()));
         let f = async move {
             while let Some(HealthCheckBundle { status,
                                                result,
@@ -716,7 +1015,9 @@ impl Service {
                                       .expect("Could not unlock service_health_result") = result;
 
                 gateway_state.lock_gsw()
-                             .set_health_of(service_group.clone(), result);
+                             .set_health_of(service_group.clone(),
+                                            HealthCheckData::new(result,
+                                                                 status.standard_streams()));
 
                 event::health_check(service_event_metadata.clone(), result, status, interval);
             }
@@ -802,6 +1103,23 @@ impl Service {
         }
     }
 
+    /// Archive or delete the state of a stopped service that is being unloaded, and run its
+    /// post-unload hook, if any. Archives are written to `archive_dir`.
+    pub async fn unload(&self, data_retention: DataRetention, archive_dir: &Path) {
+        if let Err(e) = data_retention::apply(data_retention,
+                                              &self.pkg.name,
+                                              &self.pkg.svc_path,
+                                              archive_dir)
+        {
+            outputln!(preamble self.service_group, "Failed to clean up service data: {}", e);
+        }
+        if let Some(hook) = self.post_unload() {
+            if let Err(e) = hook.into_future().await {
+                outputln!(preamble self.service_group, "Service unload failed: {}", e);
+            }
+        }
+    }
+
     /// Only used as a way to see if anything has happened to this
     /// service since the last time we might have checked
     pub fn last_state_change(&self) -> SystemTime {
@@ -813,12 +1131,15 @@ impl Service {
 
     /// Performs updates and executes hooks.
     ///
-    /// Returns `true` if the service was marked to be restarted or reconfigured.
+    /// Returns `true` if the service was marked to be restarted or reconfigured, or if its part in
+    /// a coordinated unload changed, for its rumor to be gossiped again.
     fn tick(&mut self,
             run_state: &mut ServiceRunState,
             census_ring: &CensusRing,
             launcher: &LauncherCli)
             -> bool {
+        let unload_changed = self.update_coordinated_unload(census_ring);
+
         // We may need to block the service from starting until all
         // its binds are satisfied
         if !self.initialized() {
@@ -828,10 +1149,15 @@ impl Service {
                     self.validate_binds(census_ring);
                     if !self.unsatisfied_binds.is_empty() {
                         outputln!(preamble self.service_group, "Waiting for service binds...");
-                        return false;
+                        return unload_changed;
                     }
                 }
             }
+            // Nor may it start where its placement constraints don't allow it to run
+            self.validate_placement(census_ring);
+            if self.blocked_by.is_some() {
+                return unload_changed;
+            }
         }
 
         // Binds may become unsatisfied as a service is running (e.g.,
@@ -840,6 +1166,22 @@ impl Service {
         if census_ring.changed() {
             self.validate_binds(census_ring);
         }
+        self.hook_role = census_ring.census_group_for(&self.service_group)
+                                    .and_then(CensusGroup::me)
+                                    .and_then(|me| {
+                                        if me.leader {
+                                            Some(HookRole::Leader)
+                                        } else if me.follower {
+                                            Some(HookRole::Follower)
+                                        } else {
+                                            None
+                                        }
+                                    });
+
+        // Checked on every tick, as a change seen before the service was initialized is only
+        // acted on once it is
+        self.update_bind_health(census_ring);
+        self.update_partition(census_ring);
 
         // TODO (DM): As a temporary fix, we return this `template_data_changed` boolean which does
         // not account for changes in the census ring. This is needed because when we restart a
@@ -848,6 +1190,10 @@ impl Service {
         if self.update_service_files(census_ring) {
             self.file_updated();
         }
+        // A service that is yet to start reads the certificate when it does
+        if std::mem::take(&mut self.cert_installed) && self.initialized() {
+            self.reconfigure();
+        }
 
         match self.spec.topology {
             Topology::Standalone => self.execute_hooks(run_state, launcher, &template_update),
@@ -894,7 +1240,90 @@ impl Service {
                 }
             }
         };
-        template_data_changed
+        template_data_changed || unload_changed
+    }
+
+    /// Take the next step in an unload of the service coordinated across its group, returning
+    /// whether this member's part in it changed.
+    fn update_coordinated_unload(&mut self, census_ring: &CensusRing) -> bool {
+        let census_group = match census_ring.census_group_for(&self.service_group) {
+            Some(census_group) => census_group,
+            None => return false,
+        };
+        let step = coordinated_unload::next_step(self.coordinated_unload.as_ref(),
+                                                 &self.sys.member_id,
+                                                 census_group.members(),
+                                                 Instant::now());
+        match step {
+            UnloadStep::Wait => return false,
+            UnloadStep::Drain(id) => {
+                outputln!(preamble self.service_group,
+                          "Draining for a coordinated unload of the service group, {}", id);
+                let phase = if self.drain() {
+                    UnloadPhase::Drained
+                } else {
+                    outputln!(preamble self.service_group,
+                              "Refusing the coordinated unload, as the service failed to drain");
+                    UnloadPhase::Refused
+                };
+                self.coordinated_unload = Some(UnloadCoordination::respond(id, phase));
+            }
+            UnloadStep::Commit => {
+                outputln!(preamble self.service_group,
+                          "Every member of the service group drained; unloading");
+                if let Some(ref mut coordination) = self.coordinated_unload {
+                    coordination.set_phase(UnloadPhase::Committed);
+                }
+            }
+            UnloadStep::Abort => {
+                outputln!(preamble self.service_group,
+                          "Aborting the coordinated unload, as not every member of the service \
+                           group drained");
+                if let Some(ref mut coordination) = self.coordinated_unload {
+                    coordination.set_phase(UnloadPhase::Aborted);
+                }
+            }
+            UnloadStep::Forget => {
+                outputln!(preamble self.service_group,
+                          "The coordinated unload was abandoned; keeping the service loaded");
+                self.coordinated_unload = None;
+            }
+        }
+        true
+    }
+
+    /// Run the drain hook, if the service has one and is running, returning whether it succeeded.
+    fn drain(&self) -> bool {
+        match self.hooks.drain {
+            Some(ref hook)
+                if self.initialized() && self.hook_allowed(hooks::DrainHook::FILE_NAME) =>
+            {
+                let _timer = hook_timer(hooks::DrainHook::FILE_NAME);
+                hook.run(&self.service_group,
+                         &self.pkg,
+                         self.spec.svc_encrypted_password.as_ref())
+                    .unwrap_or(false)
+            }
+            _ => true,
+        }
+    }
+
+    /// Check the service's placement constraints against the census,
+    /// recording the one that blocks the service from starting in
+    /// `self.blocked_by`. Only changes are logged, so that a blocked
+    /// service doesn't fill the log while it waits.
+    fn validate_placement(&mut self, census_ring: &CensusRing) {
+        let blocked_by =
+            placement::violated_constraint(&self.spec, &self.service_group, &self.sys, census_ring);
+        if blocked_by != self.blocked_by {
+            match blocked_by {
+                Some(ref constraint) => {
+                    outputln!(preamble self.service_group, "Blocked by constraint: {}", constraint)
+                }
+                None => outputln!(preamble self.service_group, "No longer blocked by constraint"),
+            }
+            self.blocked_by = blocked_by;
+        }
     }
 
     /// Iterate through all the service binds, marking any that are
@@ -968,6 +1397,88 @@ impl Service {
         }
     }
 
+    /// Track the binds mapped to service groups that have members, but none that can serve
+    /// consumers, running the `bind-degraded` hook when a bind becomes degraded and the
+    /// `bind-restored` hook when its group has healthy members again. Groups that are missing
+    /// from the census altogether are left to `validate_binds`. The hooks can't run before the
+    /// service is initialized, so until it is the changes are left to be noticed again.
+    fn update_bind_health(&mut self, census_ring: &CensusRing) {
+        if !self.initialized() {
+            return;
+        }
+        let binds = self.spec.binds.clone();
+        for bind in &binds {
+            let degraded = census_ring.census_group_for(bind.service_group())
+                                      .map_or(false, CensusGroup::is_degraded);
+            if degraded && self.degraded_binds.insert(bind.name().to_string()) {
+                outputln!(preamble self.service_group,
+                          "The group '{}' for binding '{}' has no healthy members",
+                          bind.service_group(),
+                          bind.name());
+                if let Some(ref hook) = self.hooks.bind_degraded {
+                    self.run_bind_hook(hook, bind);
+                }
+            } else if !degraded && self.degraded_binds.remove(bind.name()) {
+                outputln!(preamble self.service_group,
+                          "The group '{}' for binding '{}' has healthy members again",
+                          bind.service_group(),
+                          bind.name());
+                if let Some(ref hook) = self.hooks.bind_restored {
+                    self.run_bind_hook(hook, bind);
+                }
+            }
+        }
+        self.degraded_binds
+            .retain(|name| binds.iter().any(|bind| bind.name() == name));
+    }
+
+    /// Run a `bind-degraded` or `bind-restored` hook, telling it about `bind` through its
+    /// environment
+    fn run_bind_hook<H: Hook>(&self, hook: &H, bind: &ServiceBind) {
+        if !self.hook_allowed(H::FILE_NAME) {
+            return;
+        }
+        let _timer = hook_timer(H::FILE_NAME);
+        let mut vars = BTreeMap::new();
+        vars.insert(BIND_NAME_VAR.to_string(), bind.name().to_string());
+        vars.insert(BIND_SERVICE_GROUP_VAR.to_string(),
+                    bind.service_group().to_string());
+        let mut pkg = self.pkg.clone();
+        pkg.env = pkg.env.with_vars(&vars);
+        hook.run(&self.service_group,
+                 &pkg,
+                 self.spec.svc_encrypted_password.as_ref())
+            .ok();
+    }
+
+    /// Run the `partition-detected` hook when the Supervisor loses contact with a quorum of the
+    /// ring, and the `partition-healed` hook when it regains it. The hooks can't run before the
+    /// service is initialized, so until it is the change is left to be noticed again.
+    fn update_partition(&mut self, census_ring: &CensusRing) {
+        if census_ring.partitioned() == self.partitioned || !self.initialized() {
+            return;
+        }
+        self.partitioned = census_ring.partitioned();
+        if self.partitioned {
+            if let Some(ref hook) = self.hooks.partition_detected {
+                self.run_partition_hook(hook);
+            }
+        } else if let Some(ref hook) = self.hooks.partition_healed {
+            self.run_partition_hook(hook);
+        }
+    }
+
+    fn run_partition_hook<H: Hook>(&self, hook: &H) {
+        if !self.hook_allowed(H::FILE_NAME) {
+            return;
+        }
+        let _timer = hook_timer(H::FILE_NAME);
+        hook.run(&self.service_group,
+                 &self.pkg,
+                 self.spec.svc_encrypted_password.as_ref())
+            .ok();
+    }
+
     /// Evaluate the suitability of the given `ServiceBind` based on
     /// current census information.
     fn current_bind_status<'a>(&'a self,
@@ -1039,7 +1550,7 @@ impl Service {
         self.supervisor
             .lock()
             .expect("Couldn't lock supervisor")
-            .update_process_state(launcher)
+            .update_process_state(&self.pkg, launcher)
     }
 
     /// Updates the service configuration with data from a census group if the census group has
@@ -1060,6 +1571,16 @@ impl Service {
         }
     }
 
+    /// Add the variables of the service's environment files to the package's runtime environment
+    /// again, returning whether that changed `pkg.env`.
+    fn reload_environment(&mut self) -> Result<bool> {
+        let vars = environment_file::load(self.cfg.user_config_path.get_path())?;
+        let env = self.package_env.with_vars(&vars);
+        let changed = *env != *self.pkg.env;
+        self.pkg.env = env;
+        Ok(changed)
+    }
+
     /// Compares the current state of the service to the current state of the census ring and the
     /// user-config, and re-renders all templatable content to disk.
     fn update_templates(&mut self, census_ring: &CensusRing) -> (bool, TemplateUpdate) {
@@ -1067,24 +1588,61 @@ impl Service {
             census_ring.census_group_for(&self.service_group)
                        .expect("Service update failed; unable to find own service group");
         let cfg_updated_from_rumors = self.update_gossip(census_group);
-        let template_data_changed = cfg_updated_from_rumors || self.user_config_updated;
+        let mut user_config_reloaded = false;
 
         if self.user_config_updated {
-            if let Err(e) = self.cfg.reload_user() {
-                outputln!(preamble self.service_group, "Reloading user-config failed: {}", e);
+            match self.cfg.reload_user() {
+                Ok(()) => user_config_reloaded = true,
+                Err(e) => {
+                    outputln!(preamble self.service_group,
+                              "Reloading user-config failed, keeping the previous one: {}", e)
+                }
             }
 
             self.user_config_updated = false;
         }
+        let mut environment_reloaded = false;
+        if self.environment_updated {
+            match self.reload_environment() {
+                Ok(changed) => environment_reloaded = changed,
+                Err(e) => {
+                    outputln!(preamble self.service_group,
+                              "Reloading the environment files failed, keeping the previous \
+                               environment: {}", e)
+                }
+            }
+
+            self.environment_updated = false;
+        }
+        let hooks_reloaded = self.hooks_updated;
+        if self.hooks_updated {
+            self.reload_hooks();
+            self.hooks_updated = false;
+        }
+        let mut config_templates_reloaded = false;
+        if self.templates_updated {
+            config_templates_reloaded = self.reload_config_templates();
+            self.templates_updated = false;
+        }
+        let template_data_changed = cfg_updated_from_rumors
+                                    || user_config_reloaded
+                                    || hooks_reloaded
+                                    || config_templates_reloaded
+                                    || environment_reloaded;
 
         let template_update = if template_data_changed || census_ring.changed() {
             let ctx = self.render_context(census_ring);
             TemplateUpdate::new(self.compile_hooks(&ctx),
                                 self.compile_configuration(&ctx),
-                                self.hooks.reconfigure.is_some() || self.hooks.reload.is_some())
+                                self.hooks.reconfigure.is_some() || self.hooks.reload.is_some(),
+                                environment_reloaded)
         } else {
             TemplateUpdate::default()
         };
+        // The health check future holds on to the hook and environment it was started with
+        if (hooks_reloaded || environment_reloaded) && self.health_check_handle.is_some() {
+            self.restart_health_checks();
+        }
         (template_data_changed, template_update)
     }
 
@@ -1104,6 +1662,10 @@ impl Service {
                                           self.sys.as_sys_info(),
                                           exported);
         rumor.incarnation = incarnation;
+        rumor.maintenance = self.spec.maintenance;
+        rumor.unload = self.coordinated_unload
+                           .as_ref()
+                           .map(|coordination| coordination.unload().clone());
         rumor
     }
 
@@ -1116,12 +1678,16 @@ impl Service {
                                               self.service_group.clone(),
                                               self.pkg.clone(),
                                               self.spec.svc_encrypted_password.clone());
+            let retry = self.pkg
+                            .hook_retries
+                            .get(hooks::InitHook::FILE_NAME)
+                            .copied();
             // These clones are unfortunate. async/await will make this much better.
             let service_group = self.service_group.clone();
             let initialization_state = Arc::clone(&self.initialization_state);
             let initialization_state_for_err = Arc::clone(&self.initialization_state);
             let f = async move {
-                match hook_runner.into_future().await {
+                match hook_runner.retried_future(retry).await {
                     Ok((exit_value, _)) => {
                         *initialization_state.write() = if exit_value {
                             InitializationState::InitializerFinished
@@ -1150,18 +1716,46 @@ impl Service {
         }
     }
 
+    /// Whether the hook named `hook_name` may run on this member. In a leader topology group, a
+    /// hook the package restricts to the leader or the followers only runs on members currently
+    /// holding that role; every other hook always runs.
+    fn hook_allowed(&self, hook_name: &str) -> bool {
+        match self.restricted_hook_role(hook_name) {
+            Some(role) => {
+                debug!("Skipping {} hook for {}; it only runs on the {}",
+                       hook_name, self.service_group, role);
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// The role the package restricts the hook named `hook_name` to, when this member doesn't
+    /// hold it.
+    fn restricted_hook_role(&self, hook_name: &str) -> Option<HookRole> {
+        match (self.spec.topology, self.pkg.hook_roles.get(hook_name)) {
+            (Topology::Leader, Some(role)) if self.hook_role != Some(*role) => Some(*role),
+            _ => None,
+        }
+    }
+
     /// Run reconfigure hook if present.
     fn reconfigure(&mut self) {
         let _timer = hook_timer("reconfigure");
 
         if let Some(ref hook) = self.hooks.reload {
-            hook.run(&self.service_group,
-                     &self.pkg,
-                     self.spec.svc_encrypted_password.as_ref())
-                .ok();
+            if self.hook_allowed(hooks::ReloadHook::FILE_NAME) {
+                hook.run(&self.service_group,
+                         &self.pkg,
+                         self.spec.svc_encrypted_password.as_ref())
+                    .ok();
+            }
         }
 
         if let Some(ref hook) = self.hooks.reconfigure {
+            if !self.hook_allowed(hooks::ReconfigureHook::FILE_NAME) {
+                return;
+            }
             hook.run(&self.service_group,
                      &self.pkg,
                      self.spec.svc_encrypted_password.as_ref())
@@ -1175,17 +1769,65 @@ impl Service {
 
     fn post_run(&mut self) {
         if let Some(ref hook) = self.hooks.post_run {
+            // A member that gains the role later, for example by being elected leader, runs it then
+            self.post_run_pending = !self.hook_allowed(hooks::PostRunHook::FILE_NAME);
+            if self.post_run_pending {
+                return;
+            }
             let hook_runner = HookRunner::new(Arc::clone(hook),
                                               self.service_group.clone(),
                                               self.pkg.clone(),
                                               self.spec.svc_encrypted_password.clone());
-            let f = HookRunner::retryable_future(hook_runner);
+            let retry = self.pkg
+                            .hook_retries
+                            .get(hooks::PostRunHook::FILE_NAME)
+                            .copied();
+            let f = hook_runner.retryable_future(retry);
             let (f, handle) = future::abortable(f);
             self.post_run_handle = Some(handle);
             tokio::spawn(f);
         }
     }
 
+    /// Run the `post-run` hook once this member holds the role it was skipped for.
+    fn post_run_if_pending(&mut self) {
+        if self.post_run_pending
+           && self.restricted_hook_role(hooks::PostRunHook::FILE_NAME)
+                  .is_none()
+        {
+            self.post_run();
+        }
+    }
+
+    /// Start the `backup` hook every `BackupHookInterval` while the service runs, the first time
+    /// one interval after it started or the Supervisor reattached to it.
+    fn backup_if_due(&mut self) {
+        let hook = match self.hooks.backup {
+            Some(ref hook) => Arc::clone(hook),
+            None => return,
+        };
+        let interval: Duration = BackupHookInterval::configured_value().into();
+        let now = Instant::now();
+        let due = self.next_backup
+                      .map_or(false, |next_backup| next_backup <= now);
+        if due || self.next_backup.is_none() {
+            self.next_backup = Some(now + interval);
+        }
+        if !due || !self.hook_allowed(hooks::BackupHook::FILE_NAME) {
+            return;
+        }
+        let hook_runner = HookRunner::new(hook,
+                                          self.service_group.clone(),
+                                          self.pkg.clone(),
+                                          self.spec.svc_encrypted_password.clone());
+        let service_group = self.service_group.clone();
+        tokio::spawn(async move {
+            if let Err(e) = hook_runner.into_future().await {
+                outputln!(preamble service_group, "Backup failed to run: {}", e);
+            }
+        });
+    }
+
     /// Stop the `post-run` retry future. This will stop this retry loop regardless of `post-run`'s
     /// exit code.
     fn stop_post_run(&mut self) {
@@ -1203,6 +1845,25 @@ impl Service {
                                      })
     }
 
+    fn post_unload(&self) -> Option<HookRunner<hooks::PostUnloadHook>> {
+        self.hooks.post_unload.as_ref().map(|hook| {
+                                           HookRunner::new(Arc::clone(hook),
+                                                           self.service_group.clone(),
+                                                           self.pkg.clone(),
+                                                           self.spec.svc_encrypted_password.clone())
+                                       })
+    }
+
+    /// How strongly this member is preferred as the leader of its group, by the leader affinity
+    /// of the service and the zone, labels and mean `round_trip` to the ring of this Supervisor
+    pub fn leader_affinity(&self, round_trip: Option<Duration>) -> LeaderAffinity {
+        LeaderAffinity { score:       self.spec.leader_affinity_score(self.sys.zone.as_deref(),
+                                                                      &self.sys.labels,
+                                                                      round_trip),
+                         breaks_ties: self.spec.leader_tie_break,
+                         preferences: self.spec.leader_affinity.join(","), }
+    }
+
     pub fn suitability(&self) -> Option<u64> {
         let _timer = hook_timer("suitability");
 
@@ -1304,6 +1965,8 @@ impl Service {
         // We copy the current process id to the run state to avoid
         // having to lock the supervisor for this information.
         run_state.current_pid = pid_update.new_pid;
+        self.resources
+            .sample(&self.service_group, pid_update.new_pid);
 
         // It is ok that we do not hold this lock while we are performing the match. If we
         // transistion states while we are matching, we will catch the new state on the next tick.
@@ -1362,6 +2025,10 @@ impl Service {
                     // since the last attempt ended we wipe all state associated with restarting.
                     run_state.reset_backoff();
                 }
+                if pid_update.is_running() {
+                    self.post_run_if_pending();
+                    self.backup_if_due();
+                }
             }
         };
     }
@@ -1372,6 +2039,10 @@ impl Service {
 
         if self.initialized() {
             if let Some(ref hook) = self.hooks.file_updated {
+                // Skipping the hook for this member's role is not a failure
+                if !self.hook_allowed(hooks::FileUpdatedHook::FILE_NAME) {
+                    return true;
+                }
                 return hook.run(&self.service_group,
                                 &self.pkg,
                                 self.spec.svc_encrypted_password.as_ref())
@@ -1395,7 +2066,7 @@ impl Service {
         // may be a non-peered Supervisor running this service for the
         // first time, for instance.
         if let Some(census_group) = census_ring.census_group_for(&self.service_group) {
-            self.write_service_files(census_group, CensusGroup::service_files);
+            self.write_service_files(census_ring, census_group, CensusGroup::service_files);
         }
     }
 
@@ -1409,7 +2080,9 @@ impl Service {
             census_ring.census_group_for(&self.service_group)
                        .expect("Service update service files failed; unable to find own service \
                                 group");
-        self.write_service_files(census_group, CensusGroup::changed_service_files)
+        self.write_service_files(census_ring,
+                                 census_group,
+                                 CensusGroup::changed_service_files)
     }
 
     /// Abstracts the logic for writing out service files for a
@@ -1421,22 +2094,85 @@ impl Service {
     /// `CensusGroup::changed_service_files`, to write out only the
     /// files that have had recent gossip activity.
     ///
-    /// Returns `true` if any service files were written to disk.
-    fn write_service_files<'a, F, I>(&mut self, census_group: &'a CensusGroup, file_fn: F) -> bool
+    /// Files targeted at other members are not written, and files that are templates are
+    /// rendered first. What was done with each file is recorded in `file_receipts`.
+    ///
+    /// Returns `true` if any service files were written to or removed from disk.
+    fn write_service_files<'a, F, I>(&mut self,
+                                     census_ring: &CensusRing,
+                                     census_group: &'a CensusGroup,
+                                     file_fn: F)
+                                     -> bool
         where F: Fn(&'a CensusGroup) -> I,
               I: IntoIterator<Item = &'a ServiceFile>
     {
         let mut updated = false;
         for service_file in file_fn(census_group) {
-            if self.cache_service_file(service_file) {
+            let (outcome, changed) = self.receive_service_file(census_ring, service_file);
+            if changed {
                 outputln!(preamble self.service_group, "Service file updated, {}",
                           service_file.filename);
                 updated = true;
             }
+            match outcome {
+                FileOutcome::NotTargeted => {
+                    debug!("Service file {} is not meant for this member",
+                           service_file.filename)
+                }
+                FileOutcome::Failed(ref e) => {
+                    outputln!(preamble self.service_group,
+                              "Failed to receive service file {}, {}",
+                              service_file.filename, e)
+                }
+                FileOutcome::Written | FileOutcome::Rendered => {}
+            }
+            self.file_receipts.insert(service_file.filename.clone(),
+                                      FileReceipt { incarnation: service_file.incarnation,
+                                                    outcome });
         }
         updated
     }
 
+    /// Write a service file to disk if it is meant for this member, rendering it first if it is
+    /// a template. Returns what was done with the file and whether the file on disk changed.
+    fn receive_service_file(&self,
+                            census_ring: &CensusRing,
+                            service_file: &ServiceFile)
+                            -> (FileOutcome, bool) {
+        let file = self.pkg.svc_files_path.join(&service_file.filename);
+        if !service_file.target
+                        .includes(&self.sys.member_id, &self.sys.labels)
+        {
+            // An earlier incarnation of the file may have been meant for this member
+            let removed = file.is_file() && fs::remove_file(&file).is_ok();
+            return (FileOutcome::NotTargeted, removed);
+        }
+        if !service_file.template {
+            return match self.try_write_cache_file(file, &service_file.body) {
+                Ok(changed) => (FileOutcome::Written, changed),
+                Err(e) => (FileOutcome::Failed(e), false),
+            };
+        }
+        match self.render_service_file(census_ring, service_file)
+                  .and_then(|body| self.try_write_cache_file(file, body.as_bytes()))
+        {
+            Ok(changed) => (FileOutcome::Rendered, changed),
+            Err(e) => (FileOutcome::Failed(e), false),
+        }
+    }
+
+    fn render_service_file(&self,
+                           census_ring: &CensusRing,
+                           service_file: &ServiceFile)
+                           -> result::Result<String, String> {
+        let template = std::str::from_utf8(&service_file.body).map_err(|e| e.to_string())?;
+        let mut renderer = TemplateRenderer::for_service();
+        renderer.register_template_string(&service_file.filename, template)
+                .map_err(|e| e.to_string())?;
+        renderer.render(&service_file.filename, &self.render_context(census_ring))
+                .map_err(|e| e.to_string())
+    }
+
     /// Helper for constructing a new render context for the service.
     fn render_context<'a>(&'a self, census: &'a CensusRing) -> RenderContext<'a> {
         // Unsatisfied binds are filtered out; you only get bind
@@ -1454,13 +2190,20 @@ impl Service {
     }
 
     // Returns `false` if the write fails.
-    fn cache_service_file(&mut self, service_file: &ServiceFile) -> bool {
-        let file = self.pkg.svc_files_path.join(&service_file.filename);
-        self.write_cache_file(file, &service_file.body)
+    fn write_cache_file<T>(&self, file: T, contents: &[u8]) -> bool
+        where T: AsRef<Path>
+    {
+        match self.try_write_cache_file(file, contents) {
+            Ok(changed) => changed,
+            Err(e) => {
+                outputln!(preamble self.service_group, "{}", e);
+                false
+            }
+        }
     }
 
-    // Returns `false` if the write fails.
-    fn write_cache_file<T>(&self, file: T, contents: &[u8]) -> bool
+    // Returns whether the file changed, or why it couldn't be written.
+    fn try_write_cache_file<T>(&self, file: T, contents: &[u8]) -> result::Result<bool, String>
         where T: AsRef<Path>
     {
         let current_checksum = match Blake2bHash::from_file(&file) {
@@ -1476,22 +2219,22 @@ impl Service {
 
         if let Some(current_checksum) = current_checksum {
             if new_checksum == current_checksum {
-                return false;
+                return Ok(false);
             }
         }
 
         if let Err(e) = atomic_write(file.as_ref(), contents) {
-            outputln!(preamble self.service_group,
-                      "Failed to write to cache file {}, {}",
-                      file.as_ref().display(), e);
-            return false;
+            return Err(format!("Failed to write to cache file {}, {}",
+                               file.as_ref().display(),
+                               e));
         }
 
-        self.set_gossip_permissions(&file)
+        self.set_gossip_permissions(&file)?;
+        Ok(true)
     }
 
     #[cfg(not(windows))]
-    fn set_gossip_permissions<T: AsRef<Path>>(&self, path: T) -> bool {
+    fn set_gossip_permissions<T: AsRef<Path>>(&self, path: T) -> result::Result<(), String> {
         use habitat_core::{os::process,
                            util::posix_perm};
 
@@ -1499,33 +2242,30 @@ impl Service {
             let result =
                 posix_perm::set_owner(path.as_ref(), &self.pkg.svc_user, &self.pkg.svc_group);
             if let Err(e) = result {
-                outputln!(preamble self.service_group,
-                          "Failed to set ownership of cache file {}, {}",
-                          path.as_ref().display(), e);
-                return false;
+                return Err(format!("Failed to set ownership of cache file {}, {}",
+                                   path.as_ref().display(),
+                                   e));
             }
         }
 
         if let Err(e) = posix_perm::set_permissions(path.as_ref(), GOSSIP_FILE_PERMISSIONS) {
-            outputln!(preamble self.service_group,
-                      "Failed to set permissions on cache file {}, {}",
-                      path.as_ref().display(), e);
-            return false;
+            return Err(format!("Failed to set permissions on cache file {}, {}",
+                               path.as_ref().display(),
+                               e));
         }
-        true
+        Ok(())
     }
 
     #[cfg(windows)]
-    fn set_gossip_permissions<T: AsRef<Path>>(&self, path: T) -> bool {
+    fn set_gossip_permissions<T: AsRef<Path>>(&self, path: T) -> result::Result<(), String> {
         use habitat_core::util::win_perm;
 
         if let Err(e) = win_perm::harden_path(path.as_ref()) {
-            outputln!(preamble self.service_group,
-                      "Failed to set permissions on cache file {}, {}",
-                      path.as_ref().display(), e);
-            return false;
+            return Err(format!("Failed to set permissions on cache file {}, {}",
+                               path.as_ref().display(),
+                               e));
         }
-        true
+        Ok(())
     }
 }
 
@@ -1582,17 +2322,21 @@ impl<'a> Serialize for ServiceProxy<'a> {
         where S: Serializer
     {
         let num_fields: usize = if self.config_rendering == ConfigRendering::Full {
-            31
+            40
         } else {
-            30
+            39
         };
 
         let s = &self.service;
         let mut strukt = serializer.serialize_struct("service", num_fields)?;
         strukt.serialize_field("all_pkg_binds", &s.all_pkg_binds)?;
+        strukt.serialize_field("annotation", &extension::annotation(&s.service_group))?;
+        strukt.serialize_field("anti_affinity", &s.spec.anti_affinity)?;
         strukt.serialize_field("binding_mode", &s.spec.binding_mode)?;
         strukt.serialize_field("binds", &s.spec.binds)?;
         strukt.serialize_field("bldr_url", &s.spec.bldr_url)?;
+        strukt.serialize_field("blocked_by",
+                               &s.blocked_by.as_ref().map(ToString::to_string))?;
 
         if self.config_rendering == ConfigRendering::Full {
             strukt.serialize_field("cfg", &s.cfg)?;
@@ -1600,12 +2344,17 @@ impl<'a> Serialize for ServiceProxy<'a> {
 
         strukt.serialize_field("channel", &s.spec.channel)?;
         strukt.serialize_field("config_from", &s.spec.config_from)?;
+        strukt.serialize_field("degraded_binds", &s.degraded_binds)?;
         strukt.serialize_field("desired_state", &s.spec.desired_state)?;
         strukt.serialize_field("health_check", &s.health_check_result)?;
         strukt.serialize_field("hooks", &s.hooks)?;
         strukt.serialize_field("initialized", &s.initialized())?;
         strukt.serialize_field("last_election_status", &s.last_election_status)?;
+        strukt.serialize_field("leader_affinity", &s.spec.leader_affinity)?;
+        strukt.serialize_field("leader_tie_break", &s.spec.leader_tie_break)?;
+        strukt.serialize_field("maintenance", &s.spec.maintenance)?;
         strukt.serialize_field("manager_fs_cfg", &s.manager_fs_cfg)?;
+        strukt.serialize_field("max_per_zone", &s.spec.max_per_zone)?;
 
         let pkg_proxy = PkgProxy::new(&s.pkg);
         strukt.serialize_field("pkg", &pkg_proxy)?;
@@ -1617,6 +2366,7 @@ impl<'a> Serialize for ServiceProxy<'a> {
                                 .deref())?;
         strukt.serialize_field("last_process_state",
                                &self.service_run_state.last_process_state)?;
+        strukt.serialize_field("resources", &s.resources.usage())?;
         strukt.serialize_field("next_restart_at",
                                &self.service_run_state
                                     .restart_backoff
@@ -1725,4 +2475,49 @@ mod tests {
                                                                    JSON but failed");
         assert_valid(&json_without_config, "http_gateway_services_schema.json");
     }
+
+    #[tokio::test]
+    async fn bind_health_waits_for_the_service_to_initialize() {
+        use habitat_butterfly::{member::{Health,
+                                         Member,
+                                         MemberList},
+                                rumor::{election::{Election,
+                                                   ElectionUpdate},
+                                        service::{Service as ServiceRumor,
+                                                  SysInfo},
+                                        RumorStore}};
+        use habitat_core::{crypto::keys::KeyCache,
+                           fs::CACHE_KEY_PATH};
+
+        let mut service_wrapper = initialize_test_service().await;
+        let service = service_wrapper.service_mut().unwrap();
+        service.spec.binds = vec![ServiceBind::from_str("db:postgres.default").unwrap()];
+
+        let member_list = MemberList::new();
+        member_list.insert_mlw(Member { id: String::from("member-a"),
+                                        ..Default::default() },
+                               Health::Confirmed);
+        let postgres = ServiceGroup::from_str("postgres.default").unwrap();
+        let service_store: RumorStore<ServiceRumor> = RumorStore::default();
+        service_store.insert_rsw(ServiceRumor::new("member-a",
+                                                   &service.spec.ident,
+                                                   postgres,
+                                                   SysInfo::default(),
+                                                   None));
+        let mut census_ring = CensusRing::new(String::from("member-b"));
+        census_ring.update_from_rumors_rsr_mlr(&KeyCache::new(&*CACHE_KEY_PATH),
+                                               &service_store,
+                                               &RumorStore::<Election>::default(),
+                                               &RumorStore::<ElectionUpdate>::default(),
+                                               &member_list,
+                                               &RumorStore::default(),
+                                               &RumorStore::default());
+
+        // The bind-degraded hook can't run yet, so the bind only counts as degraded once it can
+        service.update_bind_health(&census_ring);
+        assert!(service.degraded_binds.is_empty());
+        *service.initialization_state.write() = InitializationState::Initialized;
+        service.update_bind_health(&census_ring);
+        assert!(service.degraded_binds.contains("db"));
+    }
 }
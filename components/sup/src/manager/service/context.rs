@@ -39,7 +39,8 @@ use crate::{census::{CensusGroup,
                      ElectionStatus},
             manager::Sys};
 use habitat_common::templating::{config::Cfg,
-                                 package::{Env,
+                                 package::{Dependency,
+                                           Env,
                                            Pkg}};
 use habitat_core::{package::{FullyQualifiedPackageIdent,
                              Identifiable,
@@ -73,6 +74,7 @@ type SvcMember<'a> = CensusMemberProxy<'a>;
 pub struct RenderContext<'a> {
     sys:  SystemInfo<'a>,
     pkg:  Package<'a>,
+    pkgs: Pkgs<'a>,
     cfg:  Cow<'a, Cfg>,
     svc:  Svc<'a>,
     bind: Binds<'a>,
@@ -101,6 +103,7 @@ impl<'a> RenderContext<'a> {
                                  .expect("Census Group missing from list!");
         RenderContext { sys:  SystemInfo::from_sys(sys),
                         pkg:  Package::from_pkg(pkg),
+                        pkgs: Pkgs::from_pkg(pkg),
                         cfg:  Cow::Borrowed(cfg),
                         svc:  Svc::new(census_group),
                         bind: Binds::new(bindings, census), }
@@ -159,6 +162,7 @@ impl<'a> SystemInfo<'a> {
 struct Package<'a> {
     ident:           Cow<'a, FullyQualifiedPackageIdent>,
     deps:            Cow<'a, Vec<PackageIdent>>,
+    strict_deps:     Cow<'a, bool>,
     env:             Cow<'a, Env>,
     // TODO (CM): Ideally, this would be Vec<u16>, since they're ports.
     exposes:         Cow<'a, Vec<String>>,
@@ -181,6 +185,7 @@ impl<'a> Package<'a> {
     fn from_pkg(pkg: &'a Pkg) -> Self {
         Package { ident:           Cow::Borrowed(&pkg.ident),
                   deps:            Cow::Borrowed(&pkg.deps),
+                  strict_deps:     Cow::Borrowed(&pkg.strict_deps),
                   env:             Cow::Borrowed(&pkg.env),
                   exposes:         Cow::Borrowed(&pkg.exposes),
                   exports:         Cow::Borrowed(&pkg.exports),
@@ -223,6 +228,7 @@ impl<'a> Serialize for Package<'a> {
         map.serialize_entry("release", &self.ident.release())?;
 
         map.serialize_entry("deps", &self.deps)?;
+        map.serialize_entry("strict_deps", &self.strict_deps)?;
         map.serialize_entry("env", &self.env)?;
 
         map.serialize_entry("exposes", &self.exposes)?;
@@ -246,6 +252,24 @@ impl<'a> Serialize for Package<'a> {
 
 ///////////////////////////////////////////////////////////////////////
 
+/// Templating proxy for the runtime dependencies of a `Pkg`.
+///
+/// Exposed to users under the `pkgs` key, keyed by the origin and
+/// name of each dependency (e.g., `{{pkgs.[core/openssl].path}}`).
+#[derive(Clone, Debug, Serialize)]
+struct Pkgs<'a>(BTreeMap<String, Cow<'a, Dependency>>);
+
+impl<'a> Pkgs<'a> {
+    fn from_pkg(pkg: &'a Pkg) -> Self {
+        Pkgs(pkg.dependencies
+                .iter()
+                .map(|dep| (dep.key(), Cow::Borrowed(dep)))
+                .collect())
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+
 /// Templating proxy around a `census::CensusGroup`.
 ///
 /// Currently exposed to users under the `svc` key.
@@ -353,7 +377,7 @@ impl<'a> BindGroup<'a> {
     fn new(group: &'a CensusGroup) -> Self {
         BindGroup { first:   select_first(group),
                     leader:  group.leader().map(SvcMember::new),
-                    members: group.active_members().map(SvcMember::new).collect(), }
+                    members: group.serving_members().map(SvcMember::new).collect(), }
     }
 }
 
@@ -373,7 +397,11 @@ impl<'a> BindGroup<'a> {
 fn select_first(census_group: &CensusGroup) -> Option<SvcMember<'_>> {
     match census_group.leader() {
         Some(member) => Some(SvcMember::new(member)),
-        None => census_group.members().next().map(SvcMember::new),
+        None => {
+            census_group.members()
+                        .find(|member| !member.maintenance())
+                        .map(SvcMember::new)
+        }
     }
 }
 
@@ -477,6 +505,9 @@ two = 2
                                            suspect: false,
                                            confirmed: false,
                                            departed: false,
+                                           flapping: false,
+                                           maintenance: false,
+                                           unload: None,
                                            cfg: toml::value::Table::new(), };
         SvcMember::new_owned(census_member)
     }
@@ -513,8 +544,20 @@ two = 2
         export_hash.insert("blah".into(), "stuff.thing".into());
         export_hash.insert("port".into(), "test_port".into());
 
+        let pkgs = deps.iter()
+                       .map(|dep| {
+                           let path = PathBuf::from("/hab/pkgs").join(dep.to_string());
+                           let dep = Dependency { ident: dep.clone(),
+                                                  path,
+                                                  exports: BTreeMap::new(),
+                                                  declared: true };
+                           (dep.key(), Cow::Owned(dep))
+                       })
+                       .collect();
+
         let pkg = Package { ident:           Cow::Owned(ident.clone()),
                             deps:            Cow::Owned(deps),
+                            strict_deps:     Cow::Owned(false),
                             env:             Cow::Owned(env_hash.into()),
                             exposes:         Cow::Owned(vec!["1234".into(),
                                                              "8000".into(),
@@ -566,6 +609,7 @@ two = 2
 
         RenderContext { sys: system_info,
                         pkg,
+                        pkgs: Pkgs(pkgs),
                         cfg: Cow::Owned(cfg),
                         svc,
                         bind: binds }
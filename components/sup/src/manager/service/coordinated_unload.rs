@@ -0,0 +1,244 @@
+//! Unloading a service from every member of its group at once, as requested with
+//! `hab svc unload --group-coordinated`.
+//!
+//! The member the unload is requested of coordinates it. It runs its `drain` hook and gossips
+//! that the unload is `Requested`. Every other member of the group that sees the request runs its
+//! own `drain` hook and gossips that it has `Drained`, or that it `Refused` if the hook failed.
+//! Once every member that has not departed has drained, the coordinator gossips that the unload is
+//! `Committed`, and it and every member that drained unload the service, gossiping `Committed` as
+//! they do. A member that is down has to come back and drain first, as it would otherwise keep the
+//! service loaded once it returned. If a member refuses, or some member has not drained by the
+//! deadline, the coordinator gossips that the unload is `Aborted` instead, and the service stays
+//! loaded everywhere.
+
+use crate::census::CensusMember;
+use habitat_butterfly::rumor::service::{CoordinatedUnload,
+                                        UnloadPhase};
+use std::time::{Duration,
+                Instant};
+use uuid::Uuid;
+
+/// How long the coordinator waits for the other members to drain, unless told otherwise
+pub const DEFAULT_COORDINATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A new ID for an unload to coordinate
+pub fn new_unload_id() -> String { Uuid::new_v4().to_string() }
+
+/// The part this member takes in a coordinated unload of its service
+#[derive(Debug)]
+pub struct UnloadCoordination {
+    unload:   CoordinatedUnload,
+    /// When the coordinator gives up on the other members draining. Only the coordinator has one.
+    deadline: Option<Instant>,
+}
+
+impl UnloadCoordination {
+    /// Coordinate a new unload with the ID `id`, giving the other members `timeout` to drain
+    pub fn request(id: String, timeout: Duration) -> Self {
+        UnloadCoordination { unload:   CoordinatedUnload { id,
+                                                           phase: UnloadPhase::Requested },
+                             deadline: Some(Instant::now() + timeout), }
+    }
+
+    /// Take part in the unload requested with `id`, in `phase` as the drain hook had it
+    pub fn respond(id: String, phase: UnloadPhase) -> Self {
+        UnloadCoordination { unload:   CoordinatedUnload { id, phase },
+                             deadline: None, }
+    }
+
+    pub fn unload(&self) -> &CoordinatedUnload { &self.unload }
+
+    pub fn set_phase(&mut self, phase: UnloadPhase) { self.unload.phase = phase; }
+
+    /// Whether the unload is yet to be committed or aborted
+    pub fn in_progress(&self) -> bool {
+        matches!(self.unload.phase,
+                 UnloadPhase::Requested | UnloadPhase::Drained | UnloadPhase::Refused)
+    }
+}
+
+/// What a member does next about the coordinated unload of its service
+#[derive(Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Nothing, for now
+    Wait,
+    /// Run the drain hook for the unload requested with the given ID
+    Drain(String),
+    /// Unload the service, as every member that has not departed has drained
+    Commit,
+    /// Give up on the unload, as the coordinator
+    Abort,
+    /// Forget about an unload that will not happen
+    Forget,
+}
+
+/// The next step of the local member, taking part in the unload `own` if any, given the members
+/// of its service group
+pub fn next_step<'a, I>(own: Option<&UnloadCoordination>,
+                        local_member_id: &str,
+                        members: I,
+                        now: Instant)
+                        -> Step
+    where I: IntoIterator<Item = &'a CensusMember>
+{
+    let mut others = members.into_iter()
+                            .filter(|member| member.member_id != local_member_id);
+    match own {
+        None => {
+            others.filter(|member| member.alive())
+                  .filter_map(|member| member.unload.as_ref())
+                  .find(|theirs| theirs.phase == UnloadPhase::Requested)
+                  .map_or(Step::Wait, |theirs| Step::Drain(theirs.id.clone()))
+        }
+        Some(UnloadCoordination { unload,
+                                  deadline: Some(deadline), }) => {
+            if unload.phase != UnloadPhase::Requested {
+                return Step::Wait;
+            }
+            let mut all_drained = true;
+            for member in others.filter(|member| !member.departed()) {
+                match &member.unload {
+                    Some(theirs) if theirs.id == unload.id => {
+                        match theirs.phase {
+                            UnloadPhase::Drained | UnloadPhase::Committed => {}
+                            UnloadPhase::Refused => return Step::Abort,
+                            _ => all_drained = false,
+                        }
+                    }
+                    // The service was unloaded from the member in an earlier coordinated unload
+                    Some(theirs) if theirs.phase == UnloadPhase::Committed => {}
+                    _ => all_drained = false,
+                }
+            }
+            if all_drained {
+                Step::Commit
+            } else if now >= *deadline {
+                Step::Abort
+            } else {
+                Step::Wait
+            }
+        }
+        Some(UnloadCoordination { unload,
+                                  deadline: None, }) => {
+            if unload.phase == UnloadPhase::Committed {
+                return Step::Wait;
+            }
+            match others.find_map(|member| decision_of(member, &unload.id)) {
+                Some((UnloadPhase::Committed, _)) if unload.phase == UnloadPhase::Drained => {
+                    Step::Commit
+                }
+                Some((UnloadPhase::Requested, coordinator))
+                    if !coordinator.confirmed() && !coordinator.departed() =>
+                {
+                    Step::Wait
+                }
+                // The unload was aborted, the coordinator is gone, or it has moved on from the
+                // unload
+                _ => Step::Forget,
+            }
+        }
+    }
+}
+
+/// Where `member` stands on the unload `id`, if it is the member coordinating it
+fn decision_of<'a>(member: &'a CensusMember, id: &str) -> Option<(UnloadPhase, &'a CensusMember)> {
+    member.unload
+          .as_ref()
+          .filter(|theirs| theirs.id == id)
+          .map(|theirs| theirs.phase)
+          .filter(|phase| {
+              matches!(phase,
+                       UnloadPhase::Requested | UnloadPhase::Committed | UnloadPhase::Aborted)
+          })
+          .map(|phase| (phase, member))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(member_id: &str, unload: Option<(&str, UnloadPhase)>) -> CensusMember {
+        CensusMember { member_id: member_id.to_string(),
+                       alive: true,
+                       unload: unload.map(|(id, phase)| {
+                                         CoordinatedUnload { id: id.to_string(),
+                                                             phase }
+                                     }),
+                       ..Default::default() }
+    }
+
+    fn coordinating(id: &str) -> UnloadCoordination {
+        UnloadCoordination { unload:   CoordinatedUnload { id:    id.to_string(),
+                                                           phase: UnloadPhase::Requested, },
+                             deadline: Some(Instant::now() + DEFAULT_COORDINATION_TIMEOUT), }
+    }
+
+    #[test]
+    fn members_drain_when_an_unload_is_requested() {
+        let members = vec![member("me", None),
+                           member("coordinator", Some(("u1", UnloadPhase::Requested)))];
+        assert_eq!(next_step(None, "me", &members, Instant::now()),
+                   Step::Drain("u1".to_string()));
+
+        let drained = UnloadCoordination::respond("u1".to_string(), UnloadPhase::Drained);
+        assert_eq!(next_step(Some(&drained), "me", &members, Instant::now()),
+                   Step::Wait);
+    }
+
+    #[test]
+    fn the_coordinator_commits_once_every_member_that_has_not_departed_has_drained() {
+        let own = coordinating("u1");
+        let mut members = vec![member("me", Some(("u1", UnloadPhase::Requested))),
+                               member("drained", Some(("u1", UnloadPhase::Drained))),
+                               member("unloaded-before", Some(("u0", UnloadPhase::Committed))),
+                               member("draining", None)];
+        assert_eq!(next_step(Some(&own), "me", &members, Instant::now()),
+                   Step::Wait);
+
+        // A member that is down would keep the service loaded once it came back
+        members[3].alive = false;
+        members[3].confirmed = true;
+        assert_eq!(next_step(Some(&own), "me", &members, Instant::now()),
+                   Step::Wait);
+
+        members[3].departed = true;
+        assert_eq!(next_step(Some(&own), "me", &members, Instant::now()),
+                   Step::Commit);
+    }
+
+    #[test]
+    fn the_coordinator_aborts_when_a_member_refuses_or_time_runs_out() {
+        let own = coordinating("u1");
+        let refused = vec![member("drained", Some(("u1", UnloadPhase::Drained))),
+                           member("refused", Some(("u1", UnloadPhase::Refused)))];
+        assert_eq!(next_step(Some(&own), "me", &refused, Instant::now()),
+                   Step::Abort);
+
+        let slow = vec![member("slow", None)];
+        assert_eq!(next_step(Some(&own), "me", &slow, Instant::now()),
+                   Step::Wait);
+        assert_eq!(next_step(Some(&own),
+                             "me",
+                             &slow,
+                             Instant::now() + DEFAULT_COORDINATION_TIMEOUT),
+                   Step::Abort);
+    }
+
+    #[test]
+    fn drained_members_follow_the_decision_of_the_coordinator() {
+        let drained = UnloadCoordination::respond("u1".to_string(), UnloadPhase::Drained);
+        let committed = vec![member("coordinator", Some(("u1", UnloadPhase::Committed)))];
+        assert_eq!(next_step(Some(&drained), "me", &committed, Instant::now()),
+                   Step::Commit);
+
+        let aborted = vec![member("coordinator", Some(("u1", UnloadPhase::Aborted)))];
+        assert_eq!(next_step(Some(&drained), "me", &aborted, Instant::now()),
+                   Step::Forget);
+
+        let mut gone = vec![member("coordinator", Some(("u1", UnloadPhase::Requested)))];
+        gone[0].alive = false;
+        gone[0].confirmed = true;
+        assert_eq!(next_step(Some(&drained), "me", &gone, Instant::now()),
+                   Step::Forget);
+    }
+}
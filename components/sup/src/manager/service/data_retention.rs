@@ -0,0 +1,210 @@
+//! What happens to the state of a service when it is unloaded with `hab svc unload --purge` or
+//! `--retain-data <DAYS>`.
+//!
+//! Retained state is archived into a gzipped tarball in the Supervisor's data directory, whose
+//! name records when it expires. Expired archives are deleted when the Supervisor starts and
+//! whenever a service's state is archived.
+
+use crate::error::Result;
+use flate2::{write::GzEncoder,
+             Compression};
+use habitat_common::outputln;
+use std::{fs::{self,
+               File},
+          path::{Path,
+                 PathBuf},
+          time::{Duration,
+                 SystemTime,
+                 UNIX_EPOCH}};
+
+static LOGKEY: &str = "DR";
+
+/// The directories of a service that hold its state
+const STATE_DIRS: &[&str] = &["config", "data", "var"];
+const ARCHIVE_EXTENSION: &str = ".tar.gz";
+const EXPIRY_SEPARATOR: &str = ".expires-";
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataRetention {
+    /// Leave the state of the service in place
+    Keep,
+    /// Delete the state of the service
+    Purge,
+    /// Archive the state of the service for a number of days, then delete it
+    Archive { days: u32 },
+}
+
+impl Default for DataRetention {
+    fn default() -> Self { DataRetention::Keep }
+}
+
+/// Archive or delete the state directories under `svc_path` of the service `service_name`.
+/// Archives are written to `archive_dir`.
+pub fn apply(retention: DataRetention,
+             service_name: &str,
+             svc_path: &Path,
+             archive_dir: &Path)
+             -> Result<()> {
+    match retention {
+        DataRetention::Keep => return Ok(()),
+        DataRetention::Purge => {}
+        DataRetention::Archive { days } => {
+            let expires_at =
+                SystemTime::now() + Duration::from_secs(u64::from(days) * SECS_PER_DAY);
+            let archive = archive(service_name, svc_path, archive_dir, expires_at)?;
+            outputln!(preamble service_name,
+                      "Archived the service's data to {} for {} days",
+                      archive.display(),
+                      days);
+            prune_archives(archive_dir);
+        }
+    }
+    for dir in state_dirs(svc_path) {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// Delete the archives of `archive_dir` whose retention has expired
+pub fn prune_archives(archive_dir: &Path) {
+    prune_archives_expired_at(archive_dir, SystemTime::now())
+}
+
+fn prune_archives_expired_at(archive_dir: &Path, now: SystemTime) {
+    let entries = match fs::read_dir(archive_dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let expired = path.file_name()
+                          .and_then(|name| expiry(&name.to_string_lossy()))
+                          .map_or(false, |expires_at| expires_at <= now);
+        if expired {
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    outputln!("Deleted the expired service data archive {}",
+                              path.display())
+                }
+                Err(e) => {
+                    outputln!("Failed to delete the expired service data archive {}, {}",
+                              path.display(),
+                              e)
+                }
+            }
+        }
+    }
+}
+
+fn archive(service_name: &str,
+           svc_path: &Path,
+           archive_dir: &Path,
+           expires_at: SystemTime)
+           -> Result<PathBuf> {
+    fs::create_dir_all(archive_dir)?;
+    let path = archive_dir.join(archive_name(service_name, expires_at));
+    let mut tar = tar::Builder::new(GzEncoder::new(File::create(&path)?, Compression::default()));
+    for dir in state_dirs(svc_path) {
+        let name = dir.file_name().expect("state directories have a name");
+        tar.append_dir_all(name, &dir)?;
+    }
+    tar.into_inner()?.finish()?;
+    Ok(path)
+}
+
+fn archive_name(service_name: &str, expires_at: SystemTime) -> String {
+    let secs = expires_at.duration_since(UNIX_EPOCH)
+                         .unwrap_or_default()
+                         .as_secs();
+    format!("{}{}{}{}",
+            service_name, EXPIRY_SEPARATOR, secs, ARCHIVE_EXTENSION)
+}
+
+fn expiry(archive_name: &str) -> Option<SystemTime> {
+    let (_, secs) = archive_name.strip_suffix(ARCHIVE_EXTENSION)?
+                                .rsplit_once(EXPIRY_SEPARATOR)?;
+    secs.parse()
+        .ok()
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// The state directories under `svc_path` that exist
+fn state_dirs(svc_path: &Path) -> Vec<PathBuf> {
+    STATE_DIRS.iter()
+              .map(|dir| svc_path.join(dir))
+              .filter(|dir| dir.is_dir())
+              .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn svc_dir() -> TempDir {
+        let svc = TempDir::new().unwrap();
+        for dir in &["config", "data", "var", "hooks"] {
+            fs::create_dir(svc.path().join(dir)).unwrap();
+            fs::write(svc.path().join(dir).join("file"), dir).unwrap();
+        }
+        svc
+    }
+
+    #[test]
+    fn purge_deletes_the_state_of_the_service() {
+        let svc = svc_dir();
+        let archives = TempDir::new().unwrap();
+
+        apply(DataRetention::Purge, "redis", svc.path(), archives.path()).unwrap();
+
+        assert!(!svc.path().join("config").exists());
+        assert!(!svc.path().join("data").exists());
+        assert!(!svc.path().join("var").exists());
+        assert!(svc.path().join("hooks").join("file").exists());
+        assert_eq!(fs::read_dir(archives.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn keep_leaves_the_state_of_the_service() {
+        let svc = svc_dir();
+        let archives = TempDir::new().unwrap();
+
+        apply(DataRetention::Keep, "redis", svc.path(), archives.path()).unwrap();
+
+        assert!(svc.path().join("data").join("file").exists());
+    }
+
+    #[test]
+    fn archive_keeps_the_state_of_the_service_until_it_expires() {
+        let svc = svc_dir();
+        let archives = TempDir::new().unwrap();
+
+        apply(DataRetention::Archive { days: 7 },
+              "redis",
+              svc.path(),
+              archives.path()).unwrap();
+
+        assert!(!svc.path().join("data").exists());
+        let archive = fs::read_dir(archives.path()).unwrap()
+                                                   .next()
+                                                   .unwrap()
+                                                   .unwrap()
+                                                   .path();
+        let name = archive.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("redis.expires-"));
+        let expires_at = expiry(&name).unwrap();
+
+        prune_archives_expired_at(archives.path(), expires_at - Duration::from_secs(1));
+        assert!(archive.exists());
+        prune_archives_expired_at(archives.path(), expires_at);
+        assert!(!archive.exists());
+    }
+
+    #[test]
+    fn only_archives_have_an_expiry() {
+        assert_eq!(expiry("redis.expires-60.tar.gz"),
+                   Some(UNIX_EPOCH + Duration::from_secs(60)));
+        assert_eq!(expiry("redis.expires-60"), None);
+        assert_eq!(expiry("redis.tar.gz"), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! The optional environment files of a service, `environment.toml` and `.env`, which sit beside
+//! its `user.toml`. Their variables are added over the package's runtime environment, so the
+//! service's hooks and process run with them and its templates see them in `pkg.env`.
+//!
+//! `environment.toml` is a flat table whose values are strings, numbers or booleans. `.env` has a
+//! `KEY=VALUE` on each line, optionally quoted and preceded by `export`, with `#` comments. When
+//! both files set a variable, the one in `environment.toml` wins.
+
+use crate::error::{Error,
+                   Result};
+use habitat_core::fs::{DOTENV_FILE,
+                       ENVIRONMENT_FILE};
+use std::{collections::BTreeMap,
+          fs,
+          io,
+          path::Path};
+
+/// Read the variables of the environment files in `dir`. A file that does not exist has none.
+pub fn load(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut vars = BTreeMap::new();
+    if let Some(contents) = read(dir, DOTENV_FILE)? {
+        vars.extend(parse_dotenv(&contents).map_err(|e| invalid(dir, DOTENV_FILE, e))?);
+    }
+    if let Some(contents) = read(dir, ENVIRONMENT_FILE)? {
+        vars.extend(parse_toml(&contents).map_err(|e| invalid(dir, ENVIRONMENT_FILE, e))?);
+    }
+    Ok(vars)
+}
+
+fn read(dir: &Path, file: &str) -> Result<Option<String>> {
+    match fs::read_to_string(dir.join(file)) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(invalid(dir, file, e.to_string())),
+    }
+}
+
+fn invalid(dir: &Path, file: &str, reason: String) -> Error {
+    Error::InvalidEnvironmentFile(dir.join(file), reason)
+}
+
+fn parse_toml(contents: &str) -> std::result::Result<BTreeMap<String, String>, String> {
+    let table: toml::value::Table = toml::from_str(contents).map_err(|e| e.to_string())?;
+    table.into_iter()
+         .map(|(key, value)| {
+             let value = match value {
+                 toml::Value::String(s) => s,
+                 toml::Value::Integer(i) => i.to_string(),
+                 toml::Value::Float(f) => f.to_string(),
+                 toml::Value::Boolean(b) => b.to_string(),
+                 _ => return Err(format!("{} is not a string, number or boolean", key)),
+             };
+             Ok((key, value))
+         })
+         .collect()
+}
+
+fn parse_dotenv(contents: &str) -> std::result::Result<BTreeMap<String, String>, String> {
+    let mut vars = BTreeMap::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => (key.trim(), value.trim()),
+            _ => return Err(format!("line {} is not KEY=VALUE", number + 1)),
+        };
+        vars.insert(key.to_string(), unquote(value).to_string());
+    }
+    Ok(vars)
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in &['"', '\''] {
+        if value.len() >= 2 && value.starts_with(*quote) && value.ends_with(*quote) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn dotenv_files_are_parsed() {
+        let contents = "# The database\nexport DB_HOST=db.example.com\nDB_PORT = \
+                        5432\n\nGREETING=\"hello world\"\nEMPTY=\n";
+        let vars = parse_dotenv(contents).unwrap();
+        assert_eq!(vars["DB_HOST"], "db.example.com");
+        assert_eq!(vars["DB_PORT"], "5432");
+        assert_eq!(vars["GREETING"], "hello world");
+        assert_eq!(vars["EMPTY"], "");
+        assert!(parse_dotenv("DB_HOST").is_err());
+        assert!(parse_dotenv("=value").is_err());
+    }
+
+    #[test]
+    fn environment_toml_files_are_flat_tables() {
+        let contents = "DB_HOST = \"db.example.com\"\nDB_PORT = 5432\nDEBUG = true\n";
+        let vars = parse_toml(contents).unwrap();
+        assert_eq!(vars["DB_HOST"], "db.example.com");
+        assert_eq!(vars["DB_PORT"], "5432");
+        assert_eq!(vars["DEBUG"], "true");
+        assert!(parse_toml("[db]\nhost = \"db.example.com\"\n").is_err());
+    }
+
+    #[test]
+    fn environment_toml_overrides_dotenv() {
+        let dir = TempDir::new().unwrap();
+        assert!(load(dir.path()).unwrap().is_empty());
+
+        fs::write(dir.path().join(DOTENV_FILE), "DB_HOST=a\nDB_PORT=1\n").unwrap();
+        fs::write(dir.path().join(ENVIRONMENT_FILE), "DB_HOST = \"b\"\n").unwrap();
+        let vars = load(dir.path()).unwrap();
+        assert_eq!(vars["DB_HOST"], "b");
+        assert_eq!(vars["DB_PORT"], "1");
+    }
+}
@@ -0,0 +1,45 @@
+//! What a service did with each of the files uploaded to its service group with
+//! `hab file upload`, reported to `hab file status`.
+
+use habitat_core::service::ServiceGroup;
+use habitat_sup_protocol::ctl::{service_file_receipt::Outcome,
+                                ServiceFileReceipt};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileOutcome {
+    Written,
+    /// The file was rendered as a template before it was written
+    Rendered,
+    /// The file is meant for other members of the service group
+    NotTargeted,
+    /// The file could not be rendered or written
+    Failed(String),
+}
+
+/// The outcome of the latest incarnation of a file a service received
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileReceipt {
+    pub incarnation: u64,
+    pub outcome:     FileOutcome,
+}
+
+impl FileReceipt {
+    pub fn to_proto(&self,
+                    member_id: &str,
+                    service_group: &ServiceGroup,
+                    filename: &str)
+                    -> ServiceFileReceipt {
+        let (outcome, error) = match self.outcome {
+            FileOutcome::Written => (Outcome::Written, None),
+            FileOutcome::Rendered => (Outcome::Rendered, None),
+            FileOutcome::NotTargeted => (Outcome::NotTargeted, None),
+            FileOutcome::Failed(ref e) => (Outcome::Failed, Some(e.clone())),
+        };
+        ServiceFileReceipt { member_id: Some(member_id.to_string()),
+                             service_group: Some(service_group.clone().into()),
+                             filename: Some(filename.to_string()),
+                             incarnation: Some(self.incarnation),
+                             outcome: Some(outcome as i32),
+                             error }
+    }
+}
@@ -3,7 +3,8 @@ use crate::{error::Error,
                                hooks::HealthCheckHook,
                                supervisor::Supervisor,
                                ProcessOutput,
-                               ProcessState}};
+                               ProcessState,
+                               StandardStreams}};
 use habitat_common::{outputln,
                      templating::package::Pkg};
 use habitat_core::service::{HealthCheckInterval,
@@ -81,6 +82,29 @@ impl HealthCheckHookStatus {
             None
         }
     }
+
+    /// A copy of what the health check hook printed, if it ran
+    pub fn standard_streams(&self) -> StandardStreams {
+        if let Self::Ran(output, _) = self {
+            output.standard_streams_ref().clone()
+        } else {
+            StandardStreams::default()
+        }
+    }
+}
+
+/// The result of the most recent health check of a service along with the output of its hook,
+/// which is limited to `HAB_HOOK_STANDARD_STREAM_BYTE_LIMIT` bytes per stream.
+#[derive(Clone, Debug)]
+pub struct HealthCheckData {
+    pub result: HealthCheckResult,
+    pub output: StandardStreams,
+}
+
+impl HealthCheckData {
+    pub fn new(result: HealthCheckResult, output: StandardStreams) -> Self {
+        HealthCheckData { result, output }
+    }
 }
 
 /// The complete set of information from running a health check
@@ -12,13 +12,18 @@ use super::{hook_timer,
             Pkg};
 use crate::error::{Error,
                    Result};
-use habitat_common::templating::hooks::Hook;
-use habitat_core::service::ServiceGroup;
+use habitat_common::{outputln,
+                     templating::hooks::Hook};
+use habitat_core::{package::metadata::HookRetry,
+                   service::ServiceGroup};
 use std::{clone::Clone,
           sync::Arc,
           time::{Duration,
                  Instant}};
-use tokio::task;
+use tokio::{task,
+            time};
+
+static LOGKEY: &str = "HK";
 
 pub struct HookRunner<H: Hook + Sync> {
     hook:          Arc<H>,
@@ -51,7 +56,21 @@ impl<H> HookRunner<H> where H: Hook + Sync + 'static
                      passwd }
     }
 
-    pub async fn retryable_future(self) {
+    /// Run the hook until it no longer asks to be retried. Without a retry policy, it is run again
+    /// right away every time; with one, it is given up on once the policy's attempts are used up.
+    pub async fn retryable_future(self, retry: Option<HookRetry>) {
+        if let Some(retry) = retry {
+            let service_group = self.service_group.clone();
+            if let Ok((exit_value, _)) = self.retried_future(Some(retry)).await {
+                if H::should_retry(&exit_value) {
+                    outputln!(preamble service_group,
+                              "Giving up on the '{}' hook after {} attempts",
+                              H::FILE_NAME,
+                              retry.attempts);
+                }
+            }
+            return;
+        }
         loop {
             match self.clone().into_future().await {
                 Ok((exit_value, _duration)) => {
@@ -66,6 +85,39 @@ impl<H> HookRunner<H> where H: Hook + Sync + 'static
         }
     }
 
+    /// Run the hook, and run it again after it failed or asked to be retried, as often as `retry`
+    /// allows and waiting for its backoff in between. Without a retry policy the hook is only run
+    /// once. Returns the result of the last attempt.
+    pub async fn retried_future(self,
+                                retry: Option<HookRetry>)
+                                -> Result<(H::ExitValue, Duration)> {
+        let mut attempt = 1;
+        loop {
+            let result = self.clone().into_future().await;
+            let failed = match result {
+                Ok((ref exit_value, _)) => H::should_retry(exit_value),
+                Err(ref e) => {
+                    error!("Error running the '{}' hook: {:?}", H::FILE_NAME, e);
+                    true
+                }
+            };
+            match retry {
+                Some(retry) if failed && attempt < retry.attempts => {
+                    let delay = retry.delay(attempt);
+                    outputln!(preamble self.service_group,
+                              "The '{}' hook failed, running it again in {}s (attempt {} of {})",
+                              H::FILE_NAME,
+                              delay.as_secs(),
+                              attempt + 1,
+                              retry.attempts);
+                    time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => return result,
+            }
+        }
+    }
+
     pub async fn into_future(self) -> Result<(H::ExitValue, Duration)> {
         // TODO (CM): May want to consider adding a configurable
         // timeout to how long this hook is allowed to run.
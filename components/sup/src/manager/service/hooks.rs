@@ -23,7 +23,7 @@ use std::{self,
 
 static LOGKEY: &str = "HK";
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct StandardStreams {
     pub stdout: Option<String>,
     pub stderr: Option<String>,
@@ -50,6 +50,8 @@ impl ProcessOutput {
     pub fn exit_status(&self) -> ExitStatus { self.exit_status }
 
     pub fn standard_streams(self) -> StandardStreams { self.standard_streams }
+
+    pub fn standard_streams_ref(&self) -> &StandardStreams { &self.standard_streams }
 }
 
 #[derive(Debug, Serialize)]
@@ -211,6 +213,8 @@ impl Hook for InitHook {
         }
     }
 
+    fn should_retry(exit_value: &Self::ExitValue) -> bool { !exit_value }
+
     fn path(&self) -> &Path { &self.render_pair.path }
 
     fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
@@ -513,18 +517,326 @@ impl Hook for PostStopHook {
     fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
 }
 
+#[derive(Debug, Serialize)]
+pub struct PostUnloadHook {
+    render_pair:     RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for PostUnloadHook {
+    type ExitValue = bool;
+
+    const FILE_NAME: &'static str = "post-unload";
+
+    fn new(package_name: &str, pair: RenderPair, _feature_flags: FeatureFlag) -> Self {
+        PostUnloadHook { render_pair:     pair,
+                         stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+                         stderr_log_path: hooks::stderr_log_path::<Self>(package_name), }
+    }
+
+    fn handle_exit<'a>(&self, pkg: &Pkg, _: &'a HookOutput, status: ExitStatus) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Post unload failed! '{}' exited with \
+                    status code {}", Self::FILE_NAME, code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path { &self.render_pair.path }
+
+    fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
+
+    fn stdout_log_path(&self) -> &Path { &self.stdout_log_path }
+
+    fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BindDegradedHook {
+    render_pair:     RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for BindDegradedHook {
+    type ExitValue = bool;
+
+    const FILE_NAME: &'static str = "bind-degraded";
+
+    fn new(package_name: &str, pair: RenderPair, _feature_flags: FeatureFlag) -> Self {
+        BindDegradedHook { render_pair:     pair,
+                           stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+                           stderr_log_path: hooks::stderr_log_path::<Self>(package_name), }
+    }
+
+    fn handle_exit<'a>(&self, pkg: &Pkg, _: &'a HookOutput, status: ExitStatus) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Bind degraded failed! '{}' exited with \
+                    status code {}", Self::FILE_NAME, code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path { &self.render_pair.path }
+
+    fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
+
+    fn stdout_log_path(&self) -> &Path { &self.stdout_log_path }
+
+    fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BindRestoredHook {
+    render_pair:     RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for BindRestoredHook {
+    type ExitValue = bool;
+
+    const FILE_NAME: &'static str = "bind-restored";
+
+    fn new(package_name: &str, pair: RenderPair, _feature_flags: FeatureFlag) -> Self {
+        BindRestoredHook { render_pair:     pair,
+                           stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+                           stderr_log_path: hooks::stderr_log_path::<Self>(package_name), }
+    }
+
+    fn handle_exit<'a>(&self, pkg: &Pkg, _: &'a HookOutput, status: ExitStatus) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Bind restored failed! '{}' exited with \
+                    status code {}", Self::FILE_NAME, code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path { &self.render_pair.path }
+
+    fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
+
+    fn stdout_log_path(&self) -> &Path { &self.stdout_log_path }
+
+    fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartitionDetectedHook {
+    render_pair:     RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for PartitionDetectedHook {
+    type ExitValue = bool;
+
+    const FILE_NAME: &'static str = "partition-detected";
+
+    fn new(package_name: &str, pair: RenderPair, _feature_flags: FeatureFlag) -> Self {
+        PartitionDetectedHook { render_pair:     pair,
+                                stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+                                stderr_log_path: hooks::stderr_log_path::<Self>(package_name), }
+    }
+
+    fn handle_exit<'a>(&self, pkg: &Pkg, _: &'a HookOutput, status: ExitStatus) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Partition detected failed! '{}' exited with \
+                    status code {}", Self::FILE_NAME, code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path { &self.render_pair.path }
+
+    fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
+
+    fn stdout_log_path(&self) -> &Path { &self.stdout_log_path }
+
+    fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartitionHealedHook {
+    render_pair:     RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for PartitionHealedHook {
+    type ExitValue = bool;
+
+    const FILE_NAME: &'static str = "partition-healed";
+
+    fn new(package_name: &str, pair: RenderPair, _feature_flags: FeatureFlag) -> Self {
+        PartitionHealedHook { render_pair:     pair,
+                              stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+                              stderr_log_path: hooks::stderr_log_path::<Self>(package_name), }
+    }
+
+    fn handle_exit<'a>(&self, pkg: &Pkg, _: &'a HookOutput, status: ExitStatus) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Partition healed failed! '{}' exited with \
+                    status code {}", Self::FILE_NAME, code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path { &self.render_pair.path }
+
+    fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
+
+    fn stdout_log_path(&self) -> &Path { &self.stdout_log_path }
+
+    fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DrainHook {
+    render_pair:     RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for DrainHook {
+    type ExitValue = bool;
+
+    const FILE_NAME: &'static str = "drain";
+
+    fn new(package_name: &str, pair: RenderPair, _feature_flags: FeatureFlag) -> Self {
+        DrainHook { render_pair:     pair,
+                    stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+                    stderr_log_path: hooks::stderr_log_path::<Self>(package_name), }
+    }
+
+    fn handle_exit<'a>(&self, pkg: &Pkg, _: &'a HookOutput, status: ExitStatus) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Drain failed! '{}' exited with \
+                    status code {}", Self::FILE_NAME, code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path { &self.render_pair.path }
+
+    fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
+
+    fn stdout_log_path(&self) -> &Path { &self.stdout_log_path }
+
+    fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupHook {
+    render_pair:     RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for BackupHook {
+    type ExitValue = bool;
+
+    const FILE_NAME: &'static str = "backup";
+
+    fn new(package_name: &str, pair: RenderPair, _feature_flags: FeatureFlag) -> Self {
+        BackupHook { render_pair:     pair,
+                     stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+                     stderr_log_path: hooks::stderr_log_path::<Self>(package_name), }
+    }
+
+    fn handle_exit<'a>(&self, pkg: &Pkg, _: &'a HookOutput, status: ExitStatus) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Backup failed! '{}' exited with \
+                    status code {}", Self::FILE_NAME, code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path { &self.render_pair.path }
+
+    fn renderer(&self) -> &TemplateRenderer { &self.render_pair.renderer }
+
+    fn stdout_log_path(&self) -> &Path { &self.stdout_log_path }
+
+    fn stderr_log_path(&self) -> &Path { &self.stderr_log_path }
+}
+
 /// A lookup of hooks that have changed after compilation.
 #[derive(Default)]
 pub struct HookCompileTable {
-    health_check: bool,
-    init:         bool,
-    file_updated: bool,
-    reload:       bool,
-    reconfigure:  bool,
-    suitability:  bool,
-    run:          bool,
-    post_run:     bool,
-    post_stop:    bool,
+    health_check:       bool,
+    init:               bool,
+    file_updated:       bool,
+    reload:             bool,
+    reconfigure:        bool,
+    suitability:        bool,
+    run:                bool,
+    post_run:           bool,
+    post_stop:          bool,
+    post_unload:        bool,
+    bind_degraded:      bool,
+    bind_restored:      bool,
+    partition_detected: bool,
+    partition_healed:   bool,
+    drain:              bool,
+    backup:             bool,
 }
 
 impl HookCompileTable {
@@ -549,7 +861,14 @@ impl HookCompileTable {
                    suitability,
                    run,
                    post_run,
-                   post_stop, } = self;
+                   post_stop,
+                   post_unload,
+                   bind_degraded,
+                   bind_restored,
+                   partition_detected,
+                   partition_healed,
+                   drain,
+                   backup, } = self;
         *health_check
         || *init
         || *file_updated
@@ -559,6 +878,13 @@ impl HookCompileTable {
         || *run
         || *post_run
         || *post_stop
+        || *post_unload
+        || *bind_degraded
+        || *bind_restored
+        || *partition_detected
+        || *partition_healed
+        || *drain
+        || *backup
     }
 }
 
@@ -566,15 +892,22 @@ impl HookCompileTable {
 // refactor hooks to be able to run asynchronously.
 #[derive(Debug, Default, Serialize)]
 pub struct HookTable {
-    pub health_check: Option<Arc<HealthCheckHook>>,
-    pub init:         Option<Arc<InitHook>>,
-    pub file_updated: Option<FileUpdatedHook>,
-    pub reload:       Option<ReloadHook>,
-    pub reconfigure:  Option<ReconfigureHook>,
-    pub suitability:  Option<SuitabilityHook>,
-    pub run:          Option<RunHook>,
-    pub post_run:     Option<Arc<PostRunHook>>,
-    pub post_stop:    Option<Arc<PostStopHook>>,
+    pub health_check:       Option<Arc<HealthCheckHook>>,
+    pub init:               Option<Arc<InitHook>>,
+    pub file_updated:       Option<FileUpdatedHook>,
+    pub reload:             Option<ReloadHook>,
+    pub reconfigure:        Option<ReconfigureHook>,
+    pub suitability:        Option<SuitabilityHook>,
+    pub run:                Option<RunHook>,
+    pub post_run:           Option<Arc<PostRunHook>>,
+    pub post_stop:          Option<Arc<PostStopHook>>,
+    pub post_unload:        Option<Arc<PostUnloadHook>>,
+    pub bind_degraded:      Option<BindDegradedHook>,
+    pub bind_restored:      Option<BindRestoredHook>,
+    pub partition_detected: Option<PartitionDetectedHook>,
+    pub partition_healed:   Option<PartitionHealedHook>,
+    pub drain:              Option<DrainHook>,
+    pub backup:             Option<Arc<BackupHook>>,
 }
 
 impl HookTable {
@@ -612,6 +945,25 @@ impl HookTable {
                                                      &hooks_path,
                                                      &templates,
                                                      feature_flags).map(Arc::new);
+                table.post_unload = PostUnloadHook::load(package_name,
+                                                         &hooks_path,
+                                                         &templates,
+                                                         feature_flags).map(Arc::new);
+                table.bind_degraded =
+                    BindDegradedHook::load(package_name, &hooks_path, &templates, feature_flags);
+                table.bind_restored =
+                    BindRestoredHook::load(package_name, &hooks_path, &templates, feature_flags);
+                table.partition_detected = PartitionDetectedHook::load(package_name,
+                                                                       &hooks_path,
+                                                                       &templates,
+                                                                       feature_flags);
+                table.partition_healed =
+                    PartitionHealedHook::load(package_name, &hooks_path, &templates, feature_flags);
+                table.drain = DrainHook::load(package_name, &hooks_path, &templates, feature_flags);
+                table.backup = BackupHook::load(package_name,
+                                                &hooks_path,
+                                                &templates,
+                                                feature_flags).map(Arc::new);
             }
         }
         debug!("{}, Hooks loaded, destination={}, templates={}",
@@ -654,6 +1006,27 @@ impl HookTable {
         if let Some(ref hook) = self.post_stop {
             changed.post_stop = self.compile_one(hook.as_ref(), service_group, ctx);
         }
+        if let Some(ref hook) = self.post_unload {
+            changed.post_unload = self.compile_one(hook.as_ref(), service_group, ctx);
+        }
+        if let Some(ref hook) = self.bind_degraded {
+            changed.bind_degraded = self.compile_one(hook, service_group, ctx);
+        }
+        if let Some(ref hook) = self.bind_restored {
+            changed.bind_restored = self.compile_one(hook, service_group, ctx);
+        }
+        if let Some(ref hook) = self.partition_detected {
+            changed.partition_detected = self.compile_one(hook, service_group, ctx);
+        }
+        if let Some(ref hook) = self.partition_healed {
+            changed.partition_healed = self.compile_one(hook, service_group, ctx);
+        }
+        if let Some(ref hook) = self.drain {
+            changed.drain = self.compile_one(hook, service_group, ctx);
+        }
+        if let Some(ref hook) = self.backup {
+            changed.backup = self.compile_one(hook.as_ref(), service_group, ctx);
+        }
         changed
     }
 
@@ -730,7 +1103,13 @@ mod tests {
                       ReconfigureHook
                       RunHook
                       SuitabilityHook
-                      PostStopHook);
+                      PostStopHook
+                      PostUnloadHook
+                      BindDegradedHook
+                      BindRestoredHook
+                      PartitionDetectedHook
+                      PartitionHealedHook
+                      DrainHook);
 
     fn hook_templates_path() -> PathBuf {
         PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests")
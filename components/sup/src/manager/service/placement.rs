@@ -0,0 +1,115 @@
+//! Placement constraints, which keep a service from starting on a Supervisor where the rest of
+//! the ring says it should not run.
+//!
+//! Constraints are only evaluated before a service starts; a running service is never stopped
+//! because of them. Every Supervisor evaluates them on its own against its view of the census,
+//! so two Supervisors loading a constrained service at the same moment may both start it before
+//! either has heard about the other.
+
+use super::ServiceSpec;
+use crate::{census::{CensusGroup,
+                     CensusMember,
+                     CensusRing},
+            manager::Sys};
+use habitat_core::service::ServiceGroup;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConstraintViolation {
+    /// A member of a group the service is anti-affine with is loaded on this Supervisor
+    AntiAffinity(ServiceGroup),
+    /// The maximum number of members of the service group is already placed in this zone
+    MaxPerZone { zone: Option<String>, max: u32 },
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintViolation::AntiAffinity(sg) => {
+                write!(f,
+                       "anti-affinity with {}, which is loaded on this Supervisor",
+                       sg)
+            }
+            ConstraintViolation::MaxPerZone { zone: Some(zone),
+                                              max, } => {
+                write!(f,
+                       "at most {} member(s) of the group may run in zone {}",
+                       max, zone)
+            }
+            ConstraintViolation::MaxPerZone { zone: None, max } => {
+                write!(f,
+                       "at most {} member(s) of the group may run on Supervisors without a zone",
+                       max)
+            }
+        }
+    }
+}
+
+/// The first of the placement constraints in `spec` that keeps the service in `service_group`
+/// from starting on this Supervisor, if any.
+pub fn violated_constraint(spec: &ServiceSpec,
+                           service_group: &ServiceGroup,
+                           sys: &Sys,
+                           census_ring: &CensusRing)
+                           -> Option<ConstraintViolation> {
+    for sg in &spec.anti_affinity {
+        let colocated = census_ring.census_group_for(sg)
+                                   .and_then(CensusGroup::me)
+                                   .map_or(false, CensusMember::alive);
+        if colocated {
+            return Some(ConstraintViolation::AntiAffinity(sg.clone()));
+        }
+    }
+    if let Some(max) = spec.max_per_zone {
+        let ahead = census_ring.census_group_for(service_group)
+                               .map_or(0, |group| {
+                                   members_ahead_in_zone(group.active_members(),
+                                                         &sys.member_id,
+                                                         sys.zone.as_deref())
+                               });
+        if ahead >= max as usize {
+            return Some(ConstraintViolation::MaxPerZone { zone: sys.zone.clone(),
+                                                          max });
+        }
+    }
+    None
+}
+
+/// The number of `members` in `zone` that take precedence over `member_id` when a zone has room
+/// for fewer members than want to run in it. Ordering by member ID lets every Supervisor reach
+/// the same decision without coordinating. Supervisors without a zone all count as being in the
+/// same one.
+fn members_ahead_in_zone<'a>(members: impl Iterator<Item = &'a CensusMember>,
+                             member_id: &str,
+                             zone: Option<&str>)
+                             -> usize {
+    members.filter(|m| m.sys.zone.as_deref() == zone && m.member_id.as_str() < member_id)
+           .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use habitat_butterfly::rumor::service::SysInfo;
+
+    fn member(member_id: &str, zone: Option<&str>) -> CensusMember {
+        CensusMember { member_id: member_id.to_string(),
+                       sys: SysInfo { zone: zone.map(str::to_string),
+                                      ..Default::default() },
+                       alive: true,
+                       ..Default::default() }
+    }
+
+    #[test]
+    fn only_members_in_the_same_zone_with_lower_ids_are_ahead() {
+        let members = vec![member("a", Some("east")),
+                           member("b", Some("west")),
+                           member("c", Some("east")),
+                           member("d", Some("east")),
+                           member("e", None)];
+        assert_eq!(members_ahead_in_zone(members.iter(), "a", Some("east")), 0);
+        assert_eq!(members_ahead_in_zone(members.iter(), "d", Some("east")), 2);
+        assert_eq!(members_ahead_in_zone(members.iter(), "c", Some("west")), 1);
+        assert_eq!(members_ahead_in_zone(members.iter(), "f", None), 1);
+    }
+}
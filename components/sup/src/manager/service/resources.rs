@@ -0,0 +1,249 @@
+//! The resources used by a service's process and the processes it started: CPU time, resident
+//! memory and open file descriptors. They are read from `/proc`, so they are only available on
+//! Linux, and are sampled at most every `SAMPLE_INTERVAL`. Each sample scans `/proc` on a blocking
+//! task, so that the cost of the scan is kept out of the Supervisor's main loop, and is picked up
+//! on the first tick after it is done.
+
+use habitat_core::{os::process::Pid,
+                   service::ServiceGroup,
+                   util::ToI64};
+use prometheus::{GaugeVec,
+                 IntGaugeVec};
+use serde::{Deserialize,
+            Serialize};
+use std::{sync::mpsc::{self,
+                       Receiver,
+                       TryRecvError},
+          time::{Duration,
+                 Instant}};
+use tokio::task;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    static ref SERVICE_CPU_SECONDS: GaugeVec =
+        register_gauge_vec!("hab_sup_service_cpu_seconds",
+                            "CPU time used by the processes of the service in seconds",
+                            &["service_group"]).unwrap();
+    static ref SERVICE_MEMORY_BYTES: IntGaugeVec =
+        register_int_gauge_vec!("hab_sup_service_memory_bytes",
+                                "Resident memory of the processes of the service in bytes",
+                                &["service_group"]).unwrap();
+    static ref SERVICE_OPEN_FDS: IntGaugeVec =
+        register_int_gauge_vec!("hab_sup_service_open_file_descriptors",
+                                "Open file descriptors of the processes of the service",
+                                &["service_group"]).unwrap();
+}
+
+/// The resources used by a service's processes when they were last sampled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// The CPU time used since the processes started, in seconds
+    pub cpu_seconds:  f64,
+    /// The share of a CPU used since the previous sample, in percent. It is over 100 for
+    /// processes using more than one CPU.
+    pub cpu_percent:  f64,
+    pub memory_bytes: u64,
+    pub open_fds:     u64,
+    pub processes:    u32,
+}
+
+#[derive(Debug, Default)]
+pub struct ResourceSampler {
+    usage:      Option<ResourceUsage>,
+    sampled_at: Option<Instant>,
+    pending:    Option<PendingSample>,
+}
+
+/// A sample being read on a blocking task
+#[derive(Debug)]
+struct PendingSample {
+    pid:        Pid,
+    started_at: Instant,
+    usage:      Receiver<Option<ResourceUsage>>,
+}
+
+impl ResourceSampler {
+    /// The resources used by the service's processes when they were last sampled, if they are
+    /// running and their resources can be read
+    pub fn usage(&self) -> Option<ResourceUsage> { self.usage }
+
+    /// Pick up the sample being read, if it is done, and start sampling the resources used by the
+    /// process `pid` and the processes it started, unless they were sampled less than
+    /// `SAMPLE_INTERVAL` ago. Never blocks on the scan of `/proc`.
+    pub fn sample(&mut self, service_group: &ServiceGroup, pid: Option<Pid>) {
+        if let Some(pending) = self.pending.take() {
+            match pending.usage.try_recv() {
+                // A sample of a process that has since been replaced is dropped
+                Ok(usage) if Some(pending.pid) == pid => {
+                    self.record(service_group, usage, pending.started_at)
+                }
+                Ok(_) | Err(TryRecvError::Disconnected) => {}
+                Err(TryRecvError::Empty) if Some(pending.pid) == pid => {
+                    self.pending = Some(pending);
+                    return;
+                }
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+        let now = Instant::now();
+        let pid = match pid {
+            Some(pid) => pid,
+            None => {
+                self.record(service_group, None, now);
+                return;
+            }
+        };
+        if self.sampled_at
+               .map_or(false, |at| now.duration_since(at) < SAMPLE_INTERVAL)
+        {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        task::spawn_blocking(move || {
+            let _ = tx.send(read_usage(pid));
+        });
+        self.pending = Some(PendingSample { pid,
+                                            started_at: now,
+                                            usage: rx });
+    }
+
+    /// Record the `usage` sampled at `at`
+    fn record(&mut self,
+              service_group: &ServiceGroup,
+              mut usage: Option<ResourceUsage>,
+              at: Instant) {
+        if let (Some(usage), Some(last), Some(last_at)) =
+            (usage.as_mut(), self.usage, self.sampled_at)
+        {
+            usage.cpu_percent = cpu_percent(last.cpu_seconds,
+                                            usage.cpu_seconds,
+                                            at.saturating_duration_since(last_at));
+        }
+        record_metrics(service_group, usage);
+        self.usage = usage;
+        self.sampled_at = Some(at);
+    }
+}
+
+fn cpu_percent(last_cpu_seconds: f64, cpu_seconds: f64, elapsed: Duration) -> f64 {
+    if elapsed.as_secs_f64() <= 0.0 || cpu_seconds < last_cpu_seconds {
+        return 0.0;
+    }
+    (cpu_seconds - last_cpu_seconds) / elapsed.as_secs_f64() * 100.0
+}
+
+fn record_metrics(service_group: &ServiceGroup, usage: Option<ResourceUsage>) {
+    let label = service_group.to_string();
+    match usage {
+        Some(usage) => {
+            SERVICE_CPU_SECONDS.with_label_values(&[&label])
+                               .set(usage.cpu_seconds);
+            SERVICE_MEMORY_BYTES.with_label_values(&[&label])
+                                .set(usage.memory_bytes.to_i64());
+            SERVICE_OPEN_FDS.with_label_values(&[&label])
+                            .set(usage.open_fds.to_i64());
+        }
+        None => {
+            // The labels are only there while the service is running
+            let _ = SERVICE_CPU_SECONDS.remove_label_values(&[&label]);
+            let _ = SERVICE_MEMORY_BYTES.remove_label_values(&[&label]);
+            let _ = SERVICE_OPEN_FDS.remove_label_values(&[&label]);
+        }
+    }
+}
+
+/// The fields of `/proc/<pid>/stat` that resource usage is read from
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+#[derive(Debug, PartialEq)]
+struct ProcStat {
+    ppid:      Pid,
+    /// User and system CPU time, in clock ticks
+    cpu_ticks: u64,
+    /// Resident set size, in pages
+    rss_pages: u64,
+}
+
+/// Parse `/proc/<pid>/stat`. The process name is in parentheses and may itself contain spaces and
+/// parentheses, so the fields are counted from the last closing parenthesis.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_stat(contents: &str) -> Option<ProcStat> {
+    let fields: Vec<&str> = contents[contents.rfind(')')? + 1..].split_whitespace()
+                                                                .collect();
+    // `fields[0]` is the state, the third field of the file
+    let field = |n: usize| fields.get(n - 3).and_then(|f| f.parse::<u64>().ok());
+    Some(ProcStat { ppid:      fields.get(1)?.parse().ok()?,
+                    cpu_ticks: field(14)? + field(15)?,
+                    rss_pages: field(24)?, })
+}
+
+#[cfg(target_os = "linux")]
+fn read_usage(pid: Pid) -> Option<ResourceUsage> {
+    use std::{collections::HashMap,
+              fs};
+
+    let mut stats = HashMap::new();
+    for entry in fs::read_dir("/proc").ok()?.flatten() {
+        if let Ok(p) = entry.file_name().to_string_lossy().parse::<Pid>() {
+            if let Some(stat) = fs::read_to_string(entry.path().join("stat")).ok()
+                                                                             .as_deref()
+                                                                             .and_then(parse_stat)
+            {
+                stats.insert(p, stat);
+            }
+        }
+    }
+    stats.get(&pid)?;
+
+    // The process and every process descended from it
+    let mut tree = vec![pid];
+    let mut i = 0;
+    while i < tree.len() {
+        let parent = tree[i];
+        tree.extend(stats.iter()
+                         .filter(|(_, stat)| stat.ppid == parent)
+                         .map(|(p, _)| *p));
+        i += 1;
+    }
+
+    // Safe because sysconf has no preconditions
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f64;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(0) as u64;
+    let mut usage = ResourceUsage::default();
+    for p in &tree {
+        let stat = &stats[p];
+        usage.cpu_seconds += stat.cpu_ticks as f64 / ticks_per_second;
+        usage.memory_bytes += stat.rss_pages * page_size;
+        usage.open_fds +=
+            fs::read_dir(format!("/proc/{}/fd", p)).map_or(0, |fds| fds.count()) as u64;
+        usage.processes += 1;
+    }
+    Some(usage)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_usage(_pid: Pid) -> Option<ResourceUsage> { None }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stat_fields_are_counted_from_the_process_name() {
+        let contents = "1234 (redis (server)) S 1 1234 1234 0 -1 4194560 2000 0 0 0 150 50 0 0 20 \
+                        0 4 0 100 123456789 2048 18446744073709551615";
+        assert_eq!(parse_stat(contents),
+                   Some(ProcStat { ppid:      1,
+                                   cpu_ticks: 200,
+                                   rss_pages: 2048, }));
+        assert_eq!(parse_stat("1234 (redis) S 1"), None);
+    }
+
+    #[test]
+    fn cpu_percent_is_the_share_of_a_cpu_since_the_last_sample() {
+        assert!((cpu_percent(10.0, 15.0, Duration::from_secs(10)) - 50.0).abs() < f64::EPSILON);
+        assert!((cpu_percent(10.0, 30.0, Duration::from_secs(10)) - 200.0).abs() < f64::EPSILON);
+        // The process restarted between the samples
+        assert!(cpu_percent(10.0, 1.0, Duration::from_secs(10)).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,131 @@
+//! The runtime manifest of a running service, written to `/hab/svc/<name>/run.json` so that node
+//! agents (monitoring, security scanners) can correlate processes with Habitat services without
+//! talking to the Supervisor.
+
+use crate::error::Result;
+use habitat_common::templating::package::Pkg;
+use habitat_core::{fs::{AtomicWriter,
+                        Permissions},
+                   os::process::Pid,
+                   service::ServiceGroup};
+use serde::{Deserialize,
+            Serialize};
+use std::{io::Write,
+          path::Path,
+          time::{Duration,
+                 SystemTime}};
+
+// Like the PID file, the manifest is readable by anyone on the node.
+#[cfg(windows)]
+const RUN_MANIFEST_PERMISSIONS: Permissions = Permissions::Standard;
+#[cfg(not(windows))]
+const RUN_MANIFEST_PERMISSIONS: Permissions = Permissions::Explicit(0o644);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub pid:           Pid,
+    /// The cgroup of the process, relative to the cgroup mount point. Only present on Linux.
+    pub cgroup:        Option<String>,
+    /// Seconds since the UNIX epoch at which the Supervisor started, or first saw, the process
+    pub started_at:    u64,
+    pub package_ident: String,
+    pub service_group: String,
+    /// The ports the package exposes, followed by the host ports mapped into its network
+    /// namespace, if any
+    pub ports:         Vec<u16>,
+}
+
+impl RunManifest {
+    pub fn new(pid: Pid, pkg: &Pkg, service_group: &ServiceGroup, started_at: SystemTime) -> Self {
+        let started_at = started_at.duration_since(SystemTime::UNIX_EPOCH)
+                                   .map(|d| d.as_secs())
+                                   .unwrap_or_default();
+        let exposed = pkg.exposes.iter().filter_map(|port| port.parse().ok());
+        let mapped = pkg.port_mappings
+                        .iter()
+                        .flatten()
+                        .map(|mapping| mapping.host_port);
+        RunManifest { pid,
+                      cgroup: cgroup_of(pid),
+                      started_at,
+                      package_ident: pkg.ident.to_string(),
+                      service_group: service_group.to_string(),
+                      ports: exposed.chain(mapped).collect() }
+    }
+
+    pub fn started_at(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(self.started_at)
+    }
+
+    /// Read the manifest at `path`, if there is a valid one
+    pub fn read(path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Atomically replace the manifest at `path`
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let w = AtomicWriter::new_with_permissions(path, RUN_MANIFEST_PERMISSIONS)?;
+        w.with_writer(|f| {
+             serde_json::to_writer_pretty(&mut *f, self)?;
+             f.write_all(b"\n")
+         })?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn cgroup_of(pid: Pid) -> Option<String> {
+    let contents = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    parse_cgroup(&contents)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cgroup_of(_pid: Pid) -> Option<String> { None }
+
+/// Pick the cgroup path out of `/proc/<pid>/cgroup`. On the unified (v2) hierarchy that is the
+/// only path; on the legacy (v1) hierarchies we use the one of the `pids` controller, falling
+/// back to the first hierarchy listed.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_cgroup(contents: &str) -> Option<String> {
+    let hierarchies = contents.lines()
+                              .filter_map(|line| {
+                                  let mut fields = line.splitn(3, ':');
+                                  let _id = fields.next()?;
+                                  Some((fields.next()?, fields.next()?))
+                              })
+                              .collect::<Vec<_>>();
+    hierarchies.iter()
+               .find(|(controllers, _)| controllers.is_empty())
+               .or_else(|| {
+                   hierarchies.iter()
+                              .find(|(controllers, _)| controllers.split(',').any(|c| c == "pids"))
+               })
+               .or_else(|| hierarchies.first())
+               .map(|(_, path)| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_unified_cgroup() {
+        let contents = "0::/system.slice/hab-sup.service\n";
+        assert_eq!(parse_cgroup(contents).as_deref(),
+                   Some("/system.slice/hab-sup.service"));
+    }
+
+    #[test]
+    fn prefers_the_pids_controller_of_legacy_cgroups() {
+        let contents = "12:cpu,cpuacct:/docker/abc\n4:pids:/docker/abc/redis\n1:name=systemd:/\n";
+        assert_eq!(parse_cgroup(contents).as_deref(), Some("/docker/abc/redis"));
+    }
+
+    #[test]
+    fn falls_back_to_the_first_legacy_cgroup() {
+        let contents = "12:cpu,cpuacct:/docker/abc\n1:name=systemd:/\n";
+        assert_eq!(parse_cgroup(contents).as_deref(), Some("/docker/abc"));
+        assert_eq!(parse_cgroup(""), None);
+    }
+}
@@ -4,12 +4,15 @@ use super::{BindingMode,
             UpdateStrategy};
 use crate::error::{Error,
                    Result};
-use habitat_core::{fs::atomic_write,
+use habitat_core::{fs::{checksummed_read,
+                        AtomicWriter,
+                        StagedChecksummedWrite},
                    os::process::ShutdownTimeout,
                    package::{PackageIdent,
                              PackageInstall},
                    service::{HealthCheckInterval,
-                             ServiceBind},
+                             ServiceBind,
+                             ServiceGroup},
                    url::DEFAULT_BLDR_URL,
                    util,
                    ChannelIdent};
@@ -17,21 +20,92 @@ use habitat_sup_protocol::{self,
                            net};
 use serde::{self,
             Deserialize};
-use std::{collections::HashSet,
+use std::{collections::{HashMap,
+                        HashSet},
           convert::TryFrom,
+          ffi::OsStr,
           fmt,
-          fs::{self,
-               File},
-          io::{BufReader,
-               Read},
+          fs,
+          io,
           path::{Path,
                  PathBuf},
           result,
-          str::FromStr};
+          str::FromStr,
+          time::Duration};
 
 static DEFAULT_GROUP: &str = "default";
 const SPEC_FILE_EXT: &str = "spec";
 
+/// The version of the spec file format written by this Supervisor. Spec files without a
+/// `spec_version` were written before the format was versioned and are version 1.
+pub const SPEC_VERSION: u32 = 2;
+const SPEC_VERSION_KEY: &str = "spec_version";
+
+/// The keys a spec file may contain. Any other key is ignored with a warning.
+const SPEC_KEYS: &[&str] = &[SPEC_VERSION_KEY,
+                             "ident",
+                             "group",
+                             "bldr_url",
+                             "channel",
+                             "topology",
+                             "update_strategy",
+                             "update_condition",
+                             "binds",
+                             "binding_mode",
+                             "config_from",
+                             "desired_state",
+                             "shutdown_timeout",
+                             "svc_encrypted_password",
+                             "anti_affinity",
+                             "max_per_zone",
+                             "strict_deps",
+                             "leader_affinity",
+                             "leader_tie_break",
+                             "include_dir",
+                             "maintenance",
+                             "revision",
+                             "health_check_interval"];
+
+/// The extension of the fragments in a spec's include directory
+const FRAGMENT_EXT: &str = "toml";
+/// The keys a fragment may not set, as they are the Supervisor's to manage
+const FRAGMENT_RESERVED_KEYS: &[&str] = &[SPEC_VERSION_KEY, "ident", "include_dir", "revision"];
+/// The leader affinity key that matches the zone of a Supervisor rather than one of its labels
+const LEADER_AFFINITY_ZONE_KEY: &str = "zone";
+/// The leader affinity preference for the members with the lowest mean round trip to the rest of
+/// the ring, which are in the zone with the lowest latency
+const LEADER_AFFINITY_LOWEST_LATENCY: &str = "latency=lowest";
+/// The bits of the affinity score the latency preference takes, which rank round trips of up to a
+/// minute by the millisecond. Every other preference takes one.
+const LATENCY_AFFINITY_BITS: u32 = 16;
+/// The bits of the affinity score, which the preferences of a service share
+const LEADER_AFFINITY_BITS: u32 = 64;
+
+/// Keys of version 1 spec files that are no longer used. They are dropped without a warning
+/// when the file is migrated.
+const V1_OBSOLETE_KEYS: &[&str] = &["application_environment"];
+
+/// The steps that upgrade the table of a spec file from one version of the format to the next.
+/// The step at index `i` upgrades version `i + 1` to `i + 2`, so there is one for every version
+/// before `SPEC_VERSION`. A change to the format that older Supervisors cannot load as is bumps
+/// `SPEC_VERSION` and adds a step here.
+const MIGRATIONS: &[fn(&mut toml::value::Table)] = &[migrate_v1_to_v2];
+
+fn migrate_v1_to_v2(table: &mut toml::value::Table) {
+    for key in V1_OBSOLETE_KEYS {
+        table.remove(*key);
+    }
+}
+
+/// Upgrade the table of a spec file written in `version` of the format to `SPEC_VERSION`
+fn migrate(table: &mut toml::value::Table, version: u32) {
+    for step in &MIGRATIONS[(version - 1) as usize..] {
+        step(table);
+    }
+    table.insert(SPEC_VERSION_KEY.to_string(),
+                 toml::Value::Integer(i64::from(SPEC_VERSION)));
+}
+
 #[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum DesiredState {
     Down,
@@ -91,6 +165,26 @@ pub struct ServiceSpec {
     pub desired_state:          DesiredState,
     pub shutdown_timeout:       Option<ShutdownTimeout>,
     pub svc_encrypted_password: Option<String>,
+    /// Service groups this service may not start on the same Supervisor as
+    pub anti_affinity:          Vec<ServiceGroup>,
+    /// The most members of this service's group that may run in a single zone
+    pub max_per_zone:           Option<u32>,
+    /// Whether rendering fails when a template refers to a dependency the plan doesn't declare
+    pub strict_deps:            bool,
+    /// The `KEY=VALUE` Supervisor labels, `zone=ZONE`, or `latency=lowest`, that make a member
+    /// preferred as the leader, most preferred first
+    pub leader_affinity:        Vec<String>,
+    /// Whether the leader affinity only breaks ties between equally suitable members
+    pub leader_tie_break:       bool,
+    /// A directory of `.toml` fragments that are merged over the spec when it is read. A relative
+    /// path is relative to the directory of the spec file.
+    pub include_dir:            Option<PathBuf>,
+    /// Whether the service is in maintenance, which leaves this member out of what the service's
+    /// consumers see while it keeps running
+    pub maintenance:            bool,
+    /// Goes up each time the spec is changed through the CtlGateway, so that changes based on an
+    /// outdated spec can be refused
+    pub revision:               u64,
     // it is important that the health check interval
     // is the last field to be serialized because it
     // is serialized as a table. Individual values
@@ -117,7 +211,15 @@ impl ServiceSpec {
                desired_state: DesiredState::default(),
                health_check_interval: HealthCheckInterval::default(),
                svc_encrypted_password: None,
-               shutdown_timeout: None }
+               shutdown_timeout: None,
+               anti_affinity: Vec::default(),
+               max_per_zone: None,
+               strict_deps: false,
+               leader_affinity: Vec::default(),
+               leader_tie_break: false,
+               include_dir: None,
+               maintenance: false,
+               revision: 0 }
     }
 
     // This should only be used to provide a default value when deserializing. We intentially do not
@@ -129,36 +231,206 @@ impl ServiceSpec {
         if self.ident == PackageIdent::default() {
             return Err(Error::MissingRequiredIdent);
         }
-        toml::to_string(self).map_err(Error::ServiceSpecRender)
+        let toml = toml::to_string(self).map_err(Error::ServiceSpecRender)?;
+        Ok(format!("{} = {}\n{}", SPEC_VERSION_KEY, SPEC_VERSION, toml))
+    }
+
+    /// Render the spec without the settings of the fragments of its include directory `dir`,
+    /// which are merged over the file when it is read. A spec that differs from its fragments is
+    /// refused, as its settings would otherwise be lost to those of the fragments.
+    fn to_toml_string_without_fragments(&self, dir: &Path) -> Result<String> {
+        let fragments = read_fragments(dir)?;
+        if fragments.is_empty() {
+            return self.to_toml_string();
+        }
+        if self.ident == PackageIdent::default() {
+            return Err(Error::MissingRequiredIdent);
+        }
+        let mut table = match toml::Value::try_from(self).map_err(Error::ServiceSpecRender)? {
+            toml::Value::Table(table) => table,
+            _ => unreachable!("specs are rendered as tables"),
+        };
+        let mut read_back = table.clone();
+        read_back.extend(fragments.clone());
+        let read_back: ServiceSpec =
+            toml::Value::Table(read_back).try_into()
+                                         .map_err(Error::ServiceSpecParse)?;
+        if read_back != *self {
+            let keys = fragments.iter()
+                                .filter(|(key, value)| table.get(*key) != Some(value))
+                                .map(|(key, _)| key.as_str())
+                                .collect::<Vec<_>>();
+            let msg = format!("{} set by the fragments in {}, which would override the change; \
+                               change the fragments instead",
+                              keys.join(", "),
+                              dir.display());
+            return Err(Error::ServiceSpecInvalid(msg));
+        }
+        table.retain(|key, _| !fragments.contains_key(key));
+        let toml = toml::to_string(&table).map_err(Error::ServiceSpecRender)?;
+        Ok(format!("{} = {}\n{}", SPEC_VERSION_KEY, SPEC_VERSION, toml))
+    }
+
+    /// The include directory of the spec read from `spec_path`, if it has one
+    pub fn include_path(&self, spec_path: &Path) -> Option<PathBuf> {
+        self.include_dir
+            .as_ref()
+            .map(|dir| relative_to(spec_path, dir))
+    }
+
+    /// Parse a spec, returning it along with the version of the spec file format it was written
+    /// in. Unknown keys are ignored with a warning so that spec files written by a newer
+    /// Supervisor of the same format version can still be loaded.
+    pub fn from_str_versioned(toml: &str) -> Result<(Self, u32)> {
+        let table: toml::value::Table = toml::from_str(toml).map_err(Error::ServiceSpecParse)?;
+        Self::from_table_versioned(table, None)
+    }
+
+    /// Build a spec from the table of a spec file. The fragments of its include directory are
+    /// merged over it when the path of the spec file is known.
+    fn from_table_versioned(mut table: toml::value::Table,
+                            path: Option<&Path>)
+                            -> Result<(Self, u32)> {
+        let version = match table.get(SPEC_VERSION_KEY) {
+            None => 1,
+            Some(version) => {
+                version.as_integer()
+                       .and_then(|v| u32::try_from(v).ok())
+                       .filter(|v| *v > 0)
+                       .ok_or_else(|| {
+                           Error::ServiceSpecInvalid(format!("{} must be a positive integer",
+                                                             SPEC_VERSION_KEY))
+                       })?
+            }
+        };
+        if version > SPEC_VERSION {
+            return Err(Error::ServiceSpecVersion(version));
+        }
+        migrate(&mut table, version);
+        if let Some(dir) = path.and_then(|path| include_path(&table, path)) {
+            table.extend(read_fragments(&dir)?);
+        }
+        for key in table.keys().map(String::as_str) {
+            if !SPEC_KEYS.contains(&key) {
+                warn!("Ignoring unknown key '{}' in service spec", key);
+            }
+        }
+
+        let spec: ServiceSpec = toml::Value::Table(table).try_into()
+                                                         .map_err(Error::ServiceSpecParse)?;
+        if spec.ident == PackageIdent::default() {
+            return Err(Error::MissingRequiredIdent);
+        }
+        spec.validate_settings()?;
+        Ok((spec, version))
+    }
+
+    /// Check the settings that cannot be expressed in the types of the spec's fields
+    fn validate_settings(&self) -> Result<()> {
+        ServiceGroup::validate(&format!("{}.{}", self.ident.name, self.group)).map_err(|_| {
+            Error::ServiceSpecInvalid(format!("'{}' is not a valid group name", self.group))
+        })?;
+        if self.max_per_zone == Some(0) {
+            return Err(Error::ServiceSpecInvalid("max_per_zone must be at least 1".to_string()));
+        }
+        self.validate_leader_affinity()
+    }
+
+    fn validate_leader_affinity(&self) -> Result<()> {
+        if let Some(preference) = self.leader_affinity.iter().find(|p| !p.contains('=')) {
+            return Err(Error::ServiceSpecInvalid(format!("leader_affinity '{}' \
+                                                          must be a KEY=VALUE \
+                                                          pair",
+                                                         preference)));
+        }
+        let bits: u32 = self.leader_affinity
+                            .iter()
+                            .map(|preference| leader_affinity_bits(preference))
+                            .sum();
+        if bits > LEADER_AFFINITY_BITS {
+            return Err(Error::ServiceSpecInvalid(format!("leader_affinity may have at most {} \
+                                                          preferences, counting '{}' as {}",
+                                                         LEADER_AFFINITY_BITS,
+                                                         LEADER_AFFINITY_LOWEST_LATENCY,
+                                                         LATENCY_AFFINITY_BITS)));
+        }
+        Ok(())
+    }
+
+    /// Score a Supervisor in `zone` with `labels`, whose mean round trip to the other members is
+    /// `round_trip`, by the leader affinity preferences it matches. Each preference is worth more
+    /// than all of the ones after it together, so the score ranks members by the first preference
+    /// they differ on.
+    pub fn leader_affinity_score(&self,
+                                 zone: Option<&str>,
+                                 labels: &HashMap<String, String>,
+                                 round_trip: Option<Duration>)
+                                 -> u64 {
+        let mut score = 0;
+        for preference in &self.leader_affinity {
+            let value = if preference == LEADER_AFFINITY_LOWEST_LATENCY {
+                // A member that hasn't measured its round trips yet ranks behind the others
+                let max = u64::from(u16::MAX);
+                let millis = round_trip.map(|round_trip| {
+                                           u64::try_from(round_trip.as_millis()).unwrap_or(max)
+                                                                                .min(max)
+                                       });
+                millis.map_or(0, |millis| max - millis)
+            } else {
+                let matches = match preference.split_once('=') {
+                    Some((LEADER_AFFINITY_ZONE_KEY, value)) => zone == Some(value),
+                    Some((key, value)) => labels.get(key).map(String::as_str) == Some(value),
+                    None => false,
+                };
+                u64::from(matches)
+            };
+            score = score << leader_affinity_bits(preference) | value;
+        }
+        score
     }
 
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let file = File::open(&path).map_err(|err| {
-                                        Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err)
-                                    })?;
-        let mut file = BufReader::new(file);
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)
-            .map_err(|err| Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err))?;
-        Self::from_str(&buf)
+        Self::from_file_versioned(path).map(|(spec, _)| spec)
+    }
+
+    /// Read a spec file, returning the spec along with the version of the spec file format it
+    /// was written in.
+    pub fn from_file_versioned<P: AsRef<Path>>(path: P) -> Result<(Self, u32)> {
+        let path = path.as_ref();
+        let io_error = |err| Error::ServiceSpecFileIO(path.to_path_buf(), err);
+        let data = checksummed_read(path).map_err(io_error)?;
+        let buf = String::from_utf8(data).map_err(|err| {
+                                             io_error(io::Error::new(io::ErrorKind::InvalidData,
+                                                                     err))
+                                         })?;
+        let table: toml::value::Table = toml::from_str(&buf).map_err(Error::ServiceSpecParse)?;
+        Self::from_table_versioned(table, Some(path))
     }
 
     pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        debug!("Writing service spec to '{}': {:?}",
-               path.as_ref().display(),
-               &self);
-        let dst_path = path.as_ref()
-                           .parent()
+        let path = path.as_ref();
+        self.stage_file(path)?
+            .finish()
+            .map_err(|err| Error::ServiceSpecFileIO(path.to_path_buf(), err))
+    }
+
+    /// Write the spec next to `path`, to be moved into place with
+    /// `StagedChecksummedWrite::finish`, so that several specs can be written before any of them
+    /// replaces the spec before it.
+    pub fn stage_file(&self, path: &Path) -> Result<StagedChecksummedWrite> {
+        debug!("Writing service spec to '{}': {:?}", path.display(), &self);
+        let dst_path = path.parent()
                            .expect("Cannot determine parent directory for service spec");
-        fs::create_dir_all(dst_path).map_err(|err| {
-                                        Error::ServiceSpecFileIO(path.as_ref().to_path_buf(), err)
-                                    })?;
-        let toml = self.to_toml_string()?;
-        atomic_write(path.as_ref(), toml).map_err(|err| {
-                                             Error::ServiceSpecFileIO(path.as_ref().to_path_buf(),
-                                                                      err)
-                                         })?;
-        Ok(())
+        let io_error = |err| Error::ServiceSpecFileIO(path.to_path_buf(), err);
+        fs::create_dir_all(dst_path).map_err(io_error)?;
+        // The settings of the fragments are left out, so that they keep coming from the fragments
+        // rather than from a copy that would outlive them
+        let toml = match self.include_path(path) {
+            Some(dir) => self.to_toml_string_without_fragments(&dir)?,
+            None => self.to_toml_string()?,
+        };
+        AtomicWriter::new(path).and_then(|w| StagedChecksummedWrite::new(w, toml.as_bytes()))
+                               .map_err(io_error)
     }
 
     pub fn ident_file(ident: &PackageIdent) -> PathBuf {
@@ -274,6 +546,25 @@ impl ServiceSpec {
         if let Some(shutdown_timeout) = svc_load.shutdown_timeout {
             self.shutdown_timeout = Some(ShutdownTimeout::from(shutdown_timeout));
         }
+        if !svc_load.anti_affinity.is_empty() {
+            self.anti_affinity = svc_load.anti_affinity
+                                         .iter()
+                                         .map(|sg| sg.parse())
+                                         .collect::<result::Result<_, habitat_core::Error>>()?;
+        }
+        if let Some(max_per_zone) = svc_load.max_per_zone {
+            self.max_per_zone = Some(max_per_zone);
+        }
+        if let Some(strict_deps) = svc_load.strict_deps {
+            self.strict_deps = strict_deps;
+        }
+        if let Some(list) = svc_load.leader_affinity {
+            self.leader_affinity = list.preferences;
+        }
+        if let Some(leader_tie_break) = svc_load.leader_tie_break {
+            self.leader_tie_break = leader_tie_break;
+        }
+        self.validate_leader_affinity()?;
         Ok(self)
     }
 
@@ -426,6 +717,16 @@ impl ServiceSpec {
                         desired_state: _,
                         shutdown_timeout,
                         svc_encrypted_password,
+                        anti_affinity,
+                        max_per_zone,
+                        strict_deps,
+                        leader_affinity,
+                        leader_tie_break,
+                        // The settings of its fragments are merged into the spec already
+                        include_dir: _,
+                        maintenance,
+                        // The revision only tells writes of the spec apart
+                        revision: _,
                         health_check_interval,
                     } = &running_spec;
 
@@ -454,6 +755,16 @@ impl ServiceSpec {
                         // TODO (CM): This probably doesn't need to be here
                         || shutdown_timeout != &disk_spec.shutdown_timeout
                         || svc_encrypted_password != &disk_spec.svc_encrypted_password
+                        // Placement constraints are only checked before a
+                        // service starts
+                        || anti_affinity != &disk_spec.anti_affinity
+                        || max_per_zone != &disk_spec.max_per_zone
+                        // The setting is only read when the service is created
+                        || strict_deps != &disk_spec.strict_deps
+                        // The restarted service declares its new leader affinity in the
+                        // elections it starts
+                        || leader_affinity != &disk_spec.leader_affinity
+                        || leader_tie_break != &disk_spec.leader_tie_break
                         // TODO (CM): This probably doesn't need to be here, either
                         || health_check_interval != &disk_spec.health_check_interval
                     {
@@ -470,12 +781,17 @@ impl ServiceSpec {
                         {
                             ops.insert(RefreshOperation::RestartUpdater);
                         }
+                        if maintenance != &disk_spec.maintenance {
+                            ops.insert(RefreshOperation::Announce);
+                        }
 
                         // We should have *something* to do down
                         // here, but if we don't, let's be explicit
                         // about it.
                         if ops.is_empty() {
-                            warn!("No refresh operations computed for {}!", ident);
+                            if running_spec.revision == disk_spec.revision {
+                                warn!("No refresh operations computed for {}!", ident);
+                            }
                             None
                         } else {
                             Some(ServiceOperation::Update(disk_spec, ops))
@@ -496,6 +812,9 @@ pub(crate) enum RefreshOperation {
     /// This can happen if a user wants to change the channel a
     /// service is updating from, for instance.
     RestartUpdater,
+    /// Gossip the service again, so that the rest of the ring learns
+    /// of a change it announces, such as it being put in maintenance.
+    Announce,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -520,15 +839,72 @@ pub(crate) enum ServiceOperation {
     },
 }
 
+/// The bits of the affinity score that a leader affinity preference takes
+fn leader_affinity_bits(preference: &str) -> u32 {
+    if preference == LEADER_AFFINITY_LOWEST_LATENCY {
+        LATENCY_AFFINITY_BITS
+    } else {
+        1
+    }
+}
+
+/// `path` resolved against the directory of the spec file at `spec_path`
+fn relative_to(spec_path: &Path, path: &Path) -> PathBuf {
+    match spec_path.parent() {
+        Some(dir) => dir.join(path),
+        None => path.to_path_buf(),
+    }
+}
+
+/// The include directory named in the table of the spec file at `spec_path`, if any
+fn include_path(table: &toml::value::Table, spec_path: &Path) -> Option<PathBuf> {
+    table.get("include_dir")
+         .and_then(toml::Value::as_str)
+         .map(|dir| relative_to(spec_path, Path::new(dir)))
+}
+
+fn is_fragment(path: &Path) -> bool {
+    path.is_file() && path.extension().and_then(OsStr::to_str) == Some(FRAGMENT_EXT)
+}
+
+/// The settings of the fragments in `dir`, merged in the order of their file names so that a later
+/// fragment overrides an earlier one. A directory that does not exist has no fragments.
+fn read_fragments(dir: &Path) -> Result<toml::value::Table> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(toml::value::Table::new()),
+        Err(err) => return Err(Error::ServiceSpecFileIO(dir.to_path_buf(), err)),
+    };
+    let mut fragments = entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                               .filter(|path| is_fragment(path))
+                               .collect::<Vec<_>>();
+    fragments.sort();
+
+    let mut merged = toml::value::Table::new();
+    for fragment in fragments {
+        let content = fs::read_to_string(&fragment).map_err(|err| {
+                                                       Error::ServiceSpecFileIO(fragment.clone(),
+                                                                                err)
+                                                   })?;
+        let table: toml::value::Table = toml::from_str(&content).map_err(Error::ServiceSpecParse)?;
+        if let Some(key) = table.keys()
+                                .find(|key| FRAGMENT_RESERVED_KEYS.contains(&key.as_str()))
+        {
+            return Err(Error::ServiceSpecInvalid(format!("'{}' may not be set \
+                                                          by the fragment {}",
+                                                         key,
+                                                         fragment.display())));
+        }
+        merged.extend(table);
+    }
+    Ok(merged)
+}
+
 impl FromStr for ServiceSpec {
     type Err = Error;
 
     fn from_str(toml: &str) -> result::Result<Self, Self::Err> {
-        let spec: ServiceSpec = toml::from_str(toml).map_err(Error::ServiceSpecParse)?;
-        if spec.ident == PackageIdent::default() {
-            return Err(Error::MissingRequiredIdent);
-        }
-        Ok(spec)
+        Self::from_str_versioned(toml).map(|(spec, _)| spec)
     }
 }
 
@@ -668,6 +1044,137 @@ mod test {
         }
     }
 
+    #[test]
+    fn service_spec_without_a_version_is_version_1() {
+        let toml = r#"
+            ident = "origin/name"
+            application_environment = "theinternet.preprod"
+            "#;
+        let (_, version) = ServiceSpec::from_str_versioned(toml).unwrap();
+        assert_eq!(version, 1);
+    }
+
+    #[test]
+    fn service_spec_is_written_in_the_current_version() {
+        let spec = ServiceSpec::new(PackageIdent::from_str("origin/name").unwrap());
+        let toml = spec.to_toml_string().unwrap();
+        let (parsed, version) = ServiceSpec::from_str_versioned(&toml).unwrap();
+        assert_eq!(version, SPEC_VERSION);
+        assert_eq!(parsed, spec);
+    }
+
+    #[test]
+    fn service_spec_from_a_newer_version_is_an_error() {
+        let toml = format!("spec_version = {}\nident = \"origin/name\"",
+                           SPEC_VERSION + 1);
+        match ServiceSpec::from_str(&toml) {
+            Err(ServiceSpecVersion(version)) => assert_eq!(version, SPEC_VERSION + 1),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn service_spec_version_1_is_migrated() {
+        let toml = r#"
+            ident = "origin/name"
+            application_environment = "theinternet.preprod"
+            "#;
+        let mut table: toml::value::Table = toml::from_str(toml).unwrap();
+        migrate(&mut table, 1);
+        assert!(!table.contains_key("application_environment"));
+        assert_eq!(table.get(SPEC_VERSION_KEY),
+                   Some(&toml::Value::Integer(i64::from(SPEC_VERSION))));
+    }
+
+    #[test]
+    fn there_is_a_migration_for_every_older_spec_version() {
+        assert_eq!(MIGRATIONS.len(), (SPEC_VERSION - 1) as usize);
+    }
+
+    #[test]
+    fn service_spec_version_0_is_invalid() {
+        match ServiceSpec::from_str("spec_version = 0\nident = \"origin/name\"") {
+            Err(ServiceSpecInvalid(_)) => (), // expected outcome
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn service_spec_settings_are_validated() {
+        for toml in &["ident = \"origin/name\"\ngroup = \"\"",
+                      "ident = \"origin/name\"\nmax_per_zone = 0",
+                      "ident = \"origin/name\"\nleader_affinity = [\"ssd\"]"]
+        {
+            match ServiceSpec::from_str(toml) {
+                Err(ServiceSpecInvalid(_)) => (), // expected outcome
+                other => panic!("Unexpected result for {}: {:?}", toml, other),
+            }
+        }
+    }
+
+    #[test]
+    fn leader_affinity_score_weighs_earlier_preferences_more() {
+        let mut spec = ServiceSpec::new(PackageIdent::from_str("origin/name").unwrap());
+        spec.leader_affinity = vec!["disk=ssd".to_string(),
+                                    "zone=us-east-1a".to_string(),
+                                    "rack=r1".to_string()];
+        let score = |zone, pairs: &[(&str, &str)]| {
+            let labels = pairs.iter()
+                              .map(|(k, v)| (k.to_string(), v.to_string()))
+                              .collect::<HashMap<_, _>>();
+            spec.leader_affinity_score(zone, &labels, None)
+        };
+
+        assert_eq!(score(None, &[]), 0);
+        assert_eq!(score(Some("us-east-1a"), &[("rack", "r1")]), 0b011);
+        assert_eq!(score(Some("us-east-1b"), &[("disk", "ssd")]), 0b100);
+        // A label named like the zone key does not match the zone preference
+        assert_eq!(score(None, &[("zone", "us-east-1a")]), 0);
+    }
+
+    #[test]
+    fn leader_affinity_score_prefers_the_lowest_latency() {
+        let mut spec = ServiceSpec::new(PackageIdent::from_str("origin/name").unwrap());
+        spec.leader_affinity = vec!["latency=lowest".to_string(), "disk=ssd".to_string()];
+        let ssd: HashMap<_, _> = vec![("disk".to_string(), "ssd".to_string())].into_iter()
+                                                                              .collect();
+        let score = |labels: &HashMap<String, String>, millis: Option<u64>| {
+            spec.leader_affinity_score(None, labels, millis.map(Duration::from_millis))
+        };
+
+        assert!(score(&HashMap::new(), Some(2)) > score(&ssd, Some(3)));
+        assert!(score(&ssd, Some(2)) > score(&HashMap::new(), Some(2)));
+        assert!(score(&HashMap::new(), Some(120_000)) > score(&ssd, None));
+        assert_eq!(score(&ssd, Some(0)), u64::from(u16::MAX) << 1 | 1);
+
+        spec.leader_affinity = vec![String::from("latency=lowest"); 4];
+        assert!(spec.validate_leader_affinity().is_ok());
+        spec.leader_affinity.push(String::from("disk=ssd"));
+        assert!(spec.validate_leader_affinity().is_err());
+    }
+
+    #[test]
+    fn svc_load_keeps_or_clears_the_leader_affinity() {
+        use habitat_sup_protocol::ctl::{LeaderAffinityList,
+                                        SvcLoad};
+
+        let ident = PackageIdent::from_str("origin/name").unwrap();
+        let mut spec = ServiceSpec::new(ident.clone());
+        spec.leader_affinity = vec!["disk=ssd".to_string()];
+        let load = |leader_affinity| {
+            SvcLoad { ident: Some(ident.clone().into()),
+                      leader_affinity,
+                      ..Default::default() }
+        };
+
+        let spec = spec.merge_svc_load(load(None)).unwrap();
+        assert_eq!(spec.leader_affinity, vec!["disk=ssd".to_string()]);
+
+        let spec = spec.merge_svc_load(load(Some(LeaderAffinityList::default())))
+                       .unwrap();
+        assert!(spec.leader_affinity.is_empty());
+    }
+
     #[test]
     fn service_spec_to_toml_string() {
         let spec =
@@ -688,7 +1195,16 @@ mod test {
                           config_from:            Some(PathBuf::from("/only/for/development")),
                           desired_state:          DesiredState::Down,
                           svc_encrypted_password: None,
-                          shutdown_timeout:       Some(ShutdownTimeout::from_str("10").unwrap()), };
+                          shutdown_timeout:       Some(ShutdownTimeout::from_str("10").unwrap()),
+                          anti_affinity:
+                              vec![ServiceGroup::from_str("redis.default").unwrap()],
+                          max_per_zone:           Some(2),
+                          strict_deps:            true,
+                          leader_affinity:        vec!["disk=ssd".to_string()],
+                          leader_tie_break:       true,
+                          include_dir:            None,
+                          maintenance:            false,
+                          revision:               7, };
         let toml = spec.to_toml_string().unwrap();
 
         assert!(toml.contains(r#"ident = "origin/name/1.2.3/20170223130020""#,));
@@ -706,6 +1222,12 @@ mod test {
         assert!(toml.contains(r#"secs = 123"#));
         assert!(toml.contains(r#"nanos = 0"#));
         assert!(toml.contains(r#"shutdown_timeout = 10"#));
+        assert!(toml.contains(r#"revision = 7"#));
+        assert!(toml.contains(r#"anti_affinity = ["redis.default"]"#));
+        assert!(toml.contains(r#"max_per_zone = 2"#));
+        assert!(toml.contains(r#"strict_deps = true"#));
+        assert!(toml.contains(r#"leader_affinity = ["disk=ssd"]"#));
+        assert!(toml.contains(r#"leader_tie_break = true"#));
     }
 
     #[test]
@@ -785,6 +1307,82 @@ mod test {
         assert_eq!(spec.health_check_interval, HealthCheckInterval::default());
     }
 
+    #[test]
+    fn service_spec_from_file_merges_include_dir() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("name.spec");
+        let toml = r#"
+            ident = "origin/name"
+            include_dir = "name.d"
+            topology = "leader"
+            channel = "stable"
+            "#;
+        file_from_str(&path, toml);
+        let include_dir = tmpdir.path().join("name.d");
+        file_from_str(include_dir.join("10-ops.toml"),
+                      "channel = \"unstable\"\ngroup = \"ops\"");
+        file_from_str(include_dir.join("20-app.toml"), "group = \"app\"");
+        file_from_str(include_dir.join("notes.txt"), "not a fragment");
+
+        let spec = ServiceSpec::from_file(&path).unwrap();
+        assert_eq!(spec.topology, Topology::Leader);
+        assert_eq!(spec.channel, ChannelIdent::unstable());
+        assert_eq!(spec.group, "app");
+        assert_eq!(spec.include_path(&path), Some(include_dir));
+
+        // The settings of the fragments are not copied into the spec file
+        spec.to_file(&path).unwrap();
+        let toml = string_from_file(&path);
+        assert!(!toml.contains("group"));
+        assert!(!toml.contains("channel"));
+        assert!(toml.contains("topology"));
+        assert_eq!(ServiceSpec::from_file(&path).unwrap(), spec);
+    }
+
+    #[test]
+    fn service_spec_to_file_refuses_changes_to_settings_of_include_dir() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("name.spec");
+        let toml = r#"
+            ident = "origin/name"
+            include_dir = "name.d"
+            "#;
+        file_from_str(&path, toml);
+        file_from_str(tmpdir.path().join("name.d").join("10-ops.toml"),
+                      "channel = \"unstable\"");
+
+        let mut spec = ServiceSpec::from_file(&path).unwrap();
+        spec.topology = Topology::Leader;
+        spec.to_file(&path).unwrap();
+
+        spec.channel = ChannelIdent::stable();
+        match spec.to_file(&path) {
+            Err(ServiceSpecInvalid(msg)) => assert!(msg.starts_with("channel set by")),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        let spec = ServiceSpec::from_file(&path).unwrap();
+        assert_eq!(spec.topology, Topology::Leader);
+        assert_eq!(spec.channel, ChannelIdent::unstable());
+    }
+
+    #[test]
+    fn service_spec_from_file_include_dir_reserved_keys() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("name.spec");
+        let toml = r#"
+            ident = "origin/name"
+            include_dir = "name.d"
+            "#;
+        file_from_str(&path, toml);
+        file_from_str(tmpdir.path().join("name.d").join("ident.toml"),
+                      "ident = \"origin/other\"");
+
+        match ServiceSpec::from_file(&path) {
+            Err(ServiceSpecInvalid(_)) => (),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
     #[test]
     fn service_spec_from_file_missing() {
         let tmpdir = TempDir::new().unwrap();
@@ -857,7 +1455,15 @@ mod test {
                           config_from:            Some(PathBuf::from("/only/for/development")),
                           desired_state:          DesiredState::Down,
                           svc_encrypted_password: None,
-                          shutdown_timeout:       Some(ShutdownTimeout::default()), };
+                          shutdown_timeout:       Some(ShutdownTimeout::default()),
+                          anti_affinity:          Vec::new(),
+                          max_per_zone:           None,
+                          strict_deps:            false,
+                          leader_affinity:        Vec::new(),
+                          leader_tie_break:       false,
+                          include_dir:            None,
+                          maintenance:            false,
+                          revision:               0, };
         spec.to_file(&path).unwrap();
         let toml = string_from_file(path);
 
@@ -1152,6 +1758,20 @@ mod test {
                    restart,
                    svc_encrypted_password,
                    Some("monkeys".to_string()));
+        reconcile!(anti_affinity_causes_restart,
+                   restart,
+                   anti_affinity,
+                   vec!["redis.default".parse().unwrap()]);
+        reconcile!(max_per_zone_causes_restart, restart, max_per_zone, Some(2));
+        reconcile!(strict_deps_causes_restart, restart, strict_deps, true);
+        reconcile!(leader_affinity_causes_restart,
+                   restart,
+                   leader_affinity,
+                   vec!["disk=ssd".to_string()]);
+        reconcile!(leader_tie_break_causes_restart,
+                   restart,
+                   leader_tie_break,
+                   true);
         reconcile!(health_check_interval_causes_restart,
                    restart,
                    health_check_interval,
@@ -1177,5 +1797,10 @@ mod test {
                    update_condition,
                    UpdateCondition::TrackChannel,
                    vec![RefreshOperation::RestartUpdater]);
+        reconcile!(maintenance_causes_update,
+                   update,
+                   maintenance,
+                   true,
+                   vec![RefreshOperation::Announce]);
     }
 }
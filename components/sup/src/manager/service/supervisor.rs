@@ -3,7 +3,8 @@
 /// The Supervisor is responsible for running any services we are asked to start. It handles
 /// spawning the new process, watching for failure, and ensuring the service is either up or
 /// down. If the process dies, the Supervisor will restart it.
-use super::{terminator,
+use super::{run_manifest::RunManifest,
+            terminator,
             ProcessState};
 use crate::{error::{Error,
                     Result},
@@ -19,13 +20,15 @@ use habitat_core::{fs,
                         Permissions},
                    os::process::{self,
                                  Pid},
+                   package::metadata::{PortMapping,
+                                       PortProtocol},
                    service::ServiceGroup};
 #[cfg(windows)]
 use habitat_launcher_client::Error as launcher_error;
 use habitat_launcher_client::LauncherCli;
+use habitat_launcher_protocol as protocol;
 #[cfg(windows)]
-use habitat_launcher_protocol::{self as protocol,
-                                Error as launcher_protocol_error};
+use habitat_launcher_protocol::Error as launcher_protocol_error;
 use serde::{ser::SerializeStruct,
             Serialize,
             Serializer};
@@ -69,19 +72,19 @@ impl PidUpdate {
 }
 #[derive(Debug)]
 pub struct Supervisor {
-    service_group: ServiceGroup,
-    state:         ProcessState,
-    pid:           Option<Pid>,
+    service_group:     ServiceGroup,
+    state:             ProcessState,
+    pid:               Option<Pid>,
     /// The time at which the Supervisor's state changed. Absolute
     /// precision is not necessary, but being able to get the seconds
     /// since the UNIX epoch is.
-    state_entered: SystemTime,
+    state_entered:     SystemTime,
     /// If the Supervisor is being run with an newer Launcher that can
     /// provide service PIDs, this will be
     /// `ServicePidSource::Launcher`; otherwise it will be
     /// `ServicePidSource::Files`. Client code should use this as an
     /// indicator of which mode the Supervisor is running in.
-    pid_source:    ServicePidSource,
+    pid_source:        ServicePidSource,
     /// Path at which the currently-running PID of this service is
     /// written to disk.
     ///
@@ -91,7 +94,10 @@ pub struct Supervisor {
     ///
     /// Regardless of the value of `pid_source`, the current PID will
     /// always be written to this path, for use by service hooks.
-    pid_file:      PathBuf,
+    pid_file:          PathBuf,
+    /// Path at which the runtime manifest of the service is written
+    /// whenever its PID changes, for use by node agents.
+    run_manifest_file: PathBuf,
 }
 
 impl Supervisor {
@@ -103,17 +109,19 @@ impl Supervisor {
     /// be removed.
     pub fn new(service_group: &ServiceGroup, pid_source: ServicePidSource) -> Supervisor {
         let pid_file = fs::svc_pid_file(service_group.service());
+        let run_manifest_file = fs::svc_run_manifest_file(service_group.service());
         Supervisor { service_group: service_group.clone(),
                      state: ProcessState::Down,
                      state_entered: SystemTime::now(),
                      pid_source,
                      pid: None,
-                     pid_file }
+                     pid_file,
+                     run_manifest_file }
     }
 
     /// Updates the process state from the pid source and returns a PidUpdate
     /// object containing the details of the change.
-    pub fn update_process_state(&mut self, launcher: &LauncherCli) -> PidUpdate {
+    pub fn update_process_state(&mut self, pkg: &Pkg, launcher: &LauncherCli) -> PidUpdate {
         let mut pid_update = PidUpdate { old_pid:   self.pid,
                                          new_pid:   None,
                                          timestamp: None, };
@@ -140,11 +148,21 @@ impl Supervisor {
                            }
                        });
         pid_update.new_pid = self.pid;
-        if self.pid.is_some() {
+        if let Some(pid) = self.pid {
             pid_update.timestamp = self.change_state(ProcessState::Up);
+            if pid_update.old_pid != Some(pid) {
+                // We've reattached to a running process, so keep the
+                // start time of an existing manifest for it.
+                let started_at = match RunManifest::read(&self.run_manifest_file) {
+                    Some(ref manifest) if manifest.pid == pid => manifest.started_at(),
+                    _ => self.state_entered,
+                };
+                self.write_run_manifest(pkg, pid, started_at);
+            }
         } else {
             pid_update.timestamp = self.change_state(ProcessState::Down);
             Self::cleanup_pidfile(&self.pid_file);
+            Self::cleanup_run_manifest(&self.run_manifest_file);
         }
         pid_update
     }
@@ -282,17 +300,20 @@ impl Supervisor {
         // Launcher versions on Linux (and current Windows versions)
         // will use these, while newer versions will prefer the UID
         // and GID, ignoring the names.
+        let network_namespace = pkg.port_mappings.as_deref().map(network_namespace);
         let pid = launcher.spawn(group,
                                  &pkg.svc_run,
                                  user_info,
                                  svc_password, // Windows optional
-                                 (*pkg.env).clone())?;
+                                 (*pkg.env).clone(),
+                                 network_namespace)?;
         if pid == 0 {
             warn!(target: "pidfile_tracing", "Spawned service for {} has a PID of 0!", group);
         }
         self.pid = Some(pid);
         self.create_pidfile(&self.pid_file)?;
         self.change_state(ProcessState::Up);
+        self.write_run_manifest(pkg, pid, self.state_entered);
         Ok(())
     }
 
@@ -316,6 +337,7 @@ impl Supervisor {
                     };
                 });
                 Self::cleanup_pidfile(&self.pid_file);
+                Self::cleanup_run_manifest(&self.run_manifest_file);
             }
         } else {
             // Not quite sure how we'd get down here without a PID...
@@ -359,6 +381,29 @@ impl Supervisor {
         }
     }
 
+    /// Write the runtime manifest for a running service. Failing to
+    /// do so doesn't affect the service, so errors are only logged.
+    fn write_run_manifest(&self, pkg: &Pkg, pid: Pid, started_at: SystemTime) {
+        let manifest = RunManifest::new(pid, pkg, &self.service_group, started_at);
+        if let Err(err) = manifest.write(&self.run_manifest_file) {
+            warn!("Unable to write runtime manifest {}: {}",
+                  self.run_manifest_file.display(),
+                  err);
+        }
+    }
+
+    fn cleanup_run_manifest(run_manifest_file: &Path) {
+        match std::fs::remove_file(run_manifest_file) {
+            Ok(_) => debug!("Removed runtime manifest {}", run_manifest_file.display()),
+            Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                warn!("Unable to remove runtime manifest {}: {}",
+                      run_manifest_file.display(),
+                      err)
+            }
+        }
+    }
+
     fn change_state(&mut self, state: ProcessState) -> Option<SystemTime> {
         if self.state == state {
             return None;
@@ -436,3 +481,19 @@ fn read_pid<T>(pid_file: T) -> Option<Pid>
         }
     }
 }
+
+/// The network namespace the Launcher should run a service in, given the package's port mappings
+fn network_namespace(port_mappings: &[PortMapping]) -> protocol::NetworkNamespace {
+    let port_mappings = port_mappings.iter()
+                                     .map(|m| {
+                                         let protocol = match m.protocol {
+                                             PortProtocol::Tcp => protocol::PortProtocol::Tcp,
+                                             PortProtocol::Udp => protocol::PortProtocol::Udp,
+                                         };
+                                         protocol::PortMapping { host_port: m.host_port,
+                                                                 service_port: m.service_port,
+                                                                 protocol }
+                                     })
+                                     .collect();
+    protocol::NetworkNamespace { port_mappings }
+}
@@ -0,0 +1,180 @@
+//! The package updates of a service, written to `/hab/svc/<name>/update_history.json` so that
+//! operators have a changelog of what ran when on the node, reported by `hab svc history` and the
+//! HTTP gateway.
+//!
+//! Only the latest `MAX_UPDATE_RECORDS` updates are kept.
+
+use crate::error::Result;
+use habitat_core::{fs::AtomicWriter,
+                   package::PackageIdent,
+                   service::ServiceGroup};
+use habitat_sup_protocol::{ctl::ServiceUpdateRecord,
+                           types::UpdateStrategy};
+use serde::{Deserialize,
+            Serialize};
+use std::{collections::VecDeque,
+          io::Write,
+          path::Path,
+          time::SystemTime};
+
+/// The most updates kept in the history of a service
+pub const MAX_UPDATE_RECORDS: usize = 100;
+
+/// One package update of a service
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateRecord {
+    pub from:          String,
+    pub to:            String,
+    /// The update strategy of the service at the time, `at-once` or `rolling`
+    pub strategy:      String,
+    /// Whether the update went to an older release, as when the release the service tracks in
+    /// its channel is demoted
+    pub rollback:      bool,
+    /// Seconds since the UNIX epoch at which the Supervisor stopped the service to update it
+    pub started_at:    u64,
+    /// How long it took until the service started again with the new package. Missing while the
+    /// update is underway.
+    pub duration_secs: Option<u64>,
+}
+
+impl UpdateRecord {
+    pub fn to_proto(&self, service_group: &ServiceGroup) -> ServiceUpdateRecord {
+        let strategy = self.strategy.parse::<UpdateStrategy>().ok();
+        ServiceUpdateRecord { service_group: Some(service_group.clone().into()),
+                              from_ident:    Some(self.from.clone()),
+                              to_ident:      Some(self.to.clone()),
+                              strategy:      strategy.map(|strategy| strategy as i32),
+                              rollback:      Some(self.rollback),
+                              started_at:    Some(self.started_at),
+                              duration_secs: self.duration_secs, }
+    }
+}
+
+/// The latest package updates of a service, oldest first
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UpdateHistory(VecDeque<UpdateRecord>);
+
+impl UpdateHistory {
+    /// Read the history at `path`. A missing or unreadable history is an empty one.
+    pub fn read(path: &Path) -> Self {
+        std::fs::read_to_string(path).ok()
+                                     .and_then(|contents| serde_json::from_str(&contents).ok())
+                                     .unwrap_or_default()
+    }
+
+    /// Atomically replace the history at `path`
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let w = AtomicWriter::new(path)?;
+        w.with_writer(|f| {
+             serde_json::to_writer_pretty(&mut *f, self)?;
+             f.write_all(b"\n")
+         })?;
+        Ok(())
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &UpdateRecord> { self.0.iter() }
+
+    /// Record the start of an update from `from` to `to`, forgetting the oldest update if there
+    /// are too many
+    pub fn start(&mut self,
+                 from: &PackageIdent,
+                 to: &PackageIdent,
+                 strategy: UpdateStrategy,
+                 at: SystemTime) {
+        if self.0.len() >= MAX_UPDATE_RECORDS {
+            self.0.pop_front();
+        }
+        self.0
+            .push_back(UpdateRecord { from:          from.to_string(),
+                                      to:            to.to_string(),
+                                      strategy:      strategy.to_string(),
+                                      rollback:      to < from,
+                                      started_at:    epoch_secs(at),
+                                      duration_secs: None, });
+    }
+
+    /// Record that the latest update is done, as the service started again at `at`. Returns
+    /// whether there was an update underway.
+    pub fn finish(&mut self, at: SystemTime) -> bool {
+        match self.0.back_mut() {
+            Some(record) if record.duration_secs.is_none() => {
+                record.duration_secs = Some(epoch_secs(at).saturating_sub(record.started_at));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn epoch_secs(at: SystemTime) -> u64 {
+    at.duration_since(SystemTime::UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn ident(ident: &str) -> PackageIdent { ident.parse().unwrap() }
+
+    #[test]
+    fn updates_are_recorded_with_their_duration() {
+        let started = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut history = UpdateHistory::default();
+        history.start(&ident("core/redis/4.0.14/20200421191514"),
+                      &ident("core/redis/4.0.14/20200601100000"),
+                      UpdateStrategy::Rolling,
+                      started);
+        assert!(history.finish(started + Duration::from_secs(42)));
+        assert!(!history.finish(started + Duration::from_secs(60)));
+
+        let record = history.records().next().unwrap();
+        assert_eq!(record.strategy, "rolling");
+        assert!(!record.rollback);
+        assert_eq!(record.started_at, 1_000);
+        assert_eq!(record.duration_secs, Some(42));
+    }
+
+    #[test]
+    fn updates_to_older_releases_are_rollbacks() {
+        let mut history = UpdateHistory::default();
+        history.start(&ident("core/redis/4.0.14/20200601100000"),
+                      &ident("core/redis/4.0.14/20200421191514"),
+                      UpdateStrategy::AtOnce,
+                      SystemTime::now());
+        assert!(history.records().next().unwrap().rollback);
+    }
+
+    #[test]
+    fn only_the_latest_updates_are_kept() {
+        let mut history = UpdateHistory::default();
+        for release in 0..=MAX_UPDATE_RECORDS {
+            history.start(&ident(&format!("core/redis/4.0.14/{}", release)),
+                          &ident(&format!("core/redis/4.0.14/{}", release + 1)),
+                          UpdateStrategy::AtOnce,
+                          SystemTime::now());
+            history.finish(SystemTime::now());
+        }
+        assert_eq!(history.records().count(), MAX_UPDATE_RECORDS);
+        assert_eq!(history.records().next().unwrap().from,
+                   "core/redis/4.0.14/1");
+    }
+
+    #[test]
+    fn histories_survive_a_round_trip_through_a_file() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("update_history.json");
+        assert_eq!(UpdateHistory::read(&path), UpdateHistory::default());
+
+        let mut history = UpdateHistory::default();
+        history.start(&ident("core/redis/4.0.14/1"),
+                      &ident("core/redis/4.0.14/2"),
+                      UpdateStrategy::AtOnce,
+                      SystemTime::now());
+        history.write(&path).unwrap();
+        assert_eq!(UpdateHistory::read(&path), history);
+    }
+}
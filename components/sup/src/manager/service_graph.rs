@@ -0,0 +1,138 @@
+//! The bind relationships between the services loaded on this Supervisor. It is refreshed along
+//! with the HTTP gateway data and served at `/services/graph`, so that topology visualizers can
+//! render what depends on what on a node.
+
+use crate::{census::{CensusGroup,
+                     CensusMember,
+                     CensusRing},
+            manager::service::{HealthCheckResult,
+                               Service}};
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ServiceGraph {
+    /// The services loaded on this Supervisor
+    pub nodes: Vec<ServiceNode>,
+    /// One edge for every bind of a loaded service, from the consumer to the provider group
+    pub edges: Vec<BindEdge>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceNode {
+    pub service_group: String,
+    pub pkg:           String,
+    pub health_check:  HealthCheckResult,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BindEdge {
+    /// The service group of the service that binds to the provider
+    pub consumer:  String,
+    pub bind:      String,
+    /// The service group that is bound to
+    pub provider:  String,
+    /// Whether the provider is loaded on this Supervisor, rather than only found in the census
+    pub local:     bool,
+    /// Whether the provider group satisfies the bind's contract
+    pub satisfied: bool,
+    pub health:    ProviderHealth,
+}
+
+/// The health of a bind's provider group, as far as this Supervisor can tell
+#[derive(Debug, Default, Serialize)]
+pub struct ProviderHealth {
+    /// The result of the last health check of the provider, if it is loaded on this Supervisor
+    pub health_check: Option<HealthCheckResult>,
+    pub alive:        u32,
+    pub suspect:      u32,
+    pub confirmed:    u32,
+    /// The alive members that are not in maintenance, and so can be sent traffic
+    pub serving:      u32,
+}
+
+impl ProviderHealth {
+    fn new(health_check: Option<HealthCheckResult>, group: Option<&CensusGroup>) -> Self {
+        let mut health = ProviderHealth { health_check,
+                                          ..Default::default() };
+        if let Some(group) = group {
+            health.count(group.members());
+        }
+        health
+    }
+
+    fn count<'a>(&mut self, members: impl Iterator<Item = &'a CensusMember>) {
+        for member in members {
+            if member.alive() {
+                self.alive += 1;
+                if !member.maintenance() {
+                    self.serving += 1;
+                }
+            } else if member.suspect() {
+                self.suspect += 1;
+            } else if member.confirmed() {
+                self.confirmed += 1;
+            }
+        }
+    }
+}
+
+/// Build the graph of `services` and their binds, taking the health of remote providers from the
+/// census
+pub fn service_graph<'a>(services: impl Iterator<Item = &'a Service>,
+                         census_ring: &CensusRing)
+                         -> ServiceGraph {
+    let services: Vec<&Service> = services.collect();
+    let health_checks: HashMap<_, _> =
+        services.iter()
+                .map(|service| (&service.service_group, service.health_check_result()))
+                .collect();
+    let mut graph = ServiceGraph::default();
+    for service in &services {
+        graph.nodes
+             .push(ServiceNode { service_group: service.service_group.to_string(),
+                                 pkg:           service.pkg.ident.to_string(),
+                                 health_check:  health_checks[&service.service_group], });
+        for bind in service.binds() {
+            let provider = bind.service_group();
+            let health_check = health_checks.get(provider).copied();
+            let group = census_ring.census_group_for(provider);
+            graph.edges
+                 .push(BindEdge { consumer:  service.service_group.to_string(),
+                                  bind:      bind.name().to_string(),
+                                  provider:  provider.to_string(),
+                                  local:     health_check.is_some(),
+                                  satisfied: service.bind_satisfied(bind),
+                                  health:    ProviderHealth::new(health_check, group), });
+        }
+    }
+    graph.nodes
+         .sort_by(|a, b| a.service_group.cmp(&b.service_group));
+    graph.edges
+         .sort_by(|a, b| (&a.consumer, &a.bind).cmp(&(&b.consumer, &b.bind)));
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(alive: bool, suspect: bool, maintenance: bool) -> CensusMember {
+        CensusMember { alive,
+                       suspect,
+                       maintenance,
+                       ..Default::default() }
+    }
+
+    #[test]
+    fn only_alive_members_out_of_maintenance_are_serving() {
+        let members = vec![member(true, false, false),
+                           member(true, false, true),
+                           member(false, true, false),
+                           member(false, false, false)];
+        let mut health = ProviderHealth::default();
+        health.count(members.iter());
+        assert_eq!(health.alive, 2);
+        assert_eq!(health.suspect, 1);
+        assert_eq!(health.serving, 1);
+    }
+}
@@ -51,6 +51,10 @@ impl ServiceUpdater {
                          period }
     }
 
+    /// Change how often services are checked for updates. Only affects services registered after
+    /// the change.
+    pub fn set_period(&mut self, period: Duration) { self.period = period; }
+
     /// Register a service for updates. If the service has already
     /// been registered, the old worker is removed and a new one is
     /// started in its place.
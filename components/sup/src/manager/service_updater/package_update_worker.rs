@@ -1,4 +1,5 @@
-use crate::{manager::service::Service,
+use crate::{event::PackageChangeInitiator,
+            manager::service::Service,
             util};
 use habitat_core::{self,
                    package::{FullyQualifiedPackageIdent,
@@ -89,10 +90,16 @@ impl PackageUpdateWorker {
             let package_result = match self.update_condition {
                 UpdateCondition::Latest => {
                     let install_source = ident.clone().into();
-                    util::pkg::install_no_ui(&self.builder_url, &install_source, &self.channel).await
+                    util::pkg::install_no_ui(&self.builder_url,
+                                             &install_source,
+                                             &self.channel,
+                                             PackageChangeInitiator::ServiceUpdater).await
                 }
                 UpdateCondition::TrackChannel => {
-                    util::pkg::install_channel_head(&self.builder_url, &ident, &self.channel).await
+                    util::pkg::install_channel_head(&self.builder_url,
+                                                    &ident,
+                                                    &self.channel,
+                                                    PackageChangeInitiator::ServiceUpdater).await
                 }
             };
             match package_result {
@@ -1,8 +1,12 @@
-use super::service::spec::ServiceSpec;
+use super::service::spec::{ServiceSpec,
+                           SPEC_VERSION};
 use crate::error::{Error,
                    Result};
 use habitat_common::outputln;
-use std::{ffi::OsStr,
+use habitat_core::fs::checksummed_recover;
+use std::{collections::HashSet,
+          ffi::OsStr,
+          io,
           iter::IntoIterator,
           path::{Path,
                  PathBuf}};
@@ -31,7 +35,9 @@ impl SpecDir {
     }
 
     /// Read all spec files and rewrite them to disk migrating their format from a previous
-    /// Supervisor's to the one currently running.
+    /// Supervisor's to the one currently running. Spec files written in an older version of the
+    /// spec file format are upgraded to `SPEC_VERSION`. A corrupt spec file is first restored
+    /// from the copy kept from before its most recent write.
     pub fn migrate_specs(&self) {
         // JW: In the future we should write spec files to the Supervisor's DAT file in a more
         // appropriate machine readable format. We'll need to wait until we modify how we load and
@@ -40,7 +46,16 @@ impl SpecDir {
         // Supervisor's main loop through IPC.
 
         for spec_file in self.spec_files() {
-            match ServiceSpec::from_file(&spec_file).map(|spec| spec.to_file(&spec_file)) {
+            match Self::read_or_recover(&spec_file).and_then(|(spec, version)| {
+                                                       spec.to_file(&spec_file)?;
+                                                       Ok(version)
+                                                   }) {
+                Ok(version) if version < SPEC_VERSION => {
+                    outputln!("Migrated service spec, {}, from version {} to {}",
+                              spec_file.display(),
+                              version,
+                              SPEC_VERSION);
+                }
                 Ok(_) => debug!("migrated {:?}", spec_file),
                 Err(err) => {
                     outputln!("Unable to migrate service spec, {}, {}",
@@ -51,6 +66,21 @@ impl SpecDir {
         }
     }
 
+    fn read_or_recover(spec_file: &Path) -> Result<(ServiceSpec, u32)> {
+        match ServiceSpec::from_file_versioned(spec_file) {
+            Err(Error::ServiceSpecFileIO(path, e)) if e.kind() == io::ErrorKind::InvalidData => {
+                if checksummed_recover(spec_file).is_err() {
+                    return Err(Error::ServiceSpecFileIO(path, e));
+                }
+                outputln!("Restored corrupt service spec, {}, from the copy kept before its last \
+                           write",
+                          spec_file.display());
+                ServiceSpec::from_file_versioned(spec_file)
+            }
+            result => result,
+        }
+    }
+
     /// Return a list of all the specs as currently found on disk.
     pub fn specs(&self) -> Vec<ServiceSpec> {
         let mut specs = vec![];
@@ -92,6 +122,17 @@ impl SpecDir {
         specs
     }
 
+    /// Return the include directories of all the specs on disk
+    pub fn include_dirs(&self) -> HashSet<PathBuf> {
+        self.spec_files()
+            .into_iter()
+            .filter_map(|spec_file| {
+                ServiceSpec::from_file(&spec_file).ok()
+                                                  .and_then(|spec| spec.include_path(&spec_file))
+            })
+            .collect()
+    }
+
     /// Return the list of all spec files in the directory
     fn spec_files(&self) -> impl IntoIterator<Item = PathBuf> {
         glob::glob(&self.0.join(SPEC_FILE_GLOB).display().to_string())
@@ -9,7 +9,9 @@ use crate::{error::{Error,
             notify::Watcher};
 use notify::{DebouncedEvent,
              RecursiveMode};
-use std::{sync::mpsc::{self,
+use std::{collections::HashSet,
+          path::PathBuf,
+          sync::mpsc::{self,
                        Receiver},
           thread::Builder,
           time::Duration};
@@ -39,11 +41,12 @@ habitat_core::env_config_duration!(
 /// Provides an abstraction layer over filesystem notifications for
 /// spec files.
 pub struct SpecWatcher {
-    // Not actually used; only holding onto it for lifetime / Drop
-    // purposes (`Drop` kills the threads that the watcher spawns to do
-    // its work).
-    _watcher: SupWatcher,
+    // Dropping it kills the threads that the watcher spawns to do
+    // its work.
+    watcher:  SupWatcher,
     channel:  Receiver<DebouncedEvent>,
+    /// The include directories of specs that are being watched
+    included: HashSet<PathBuf>,
 }
 
 impl SpecWatcher {
@@ -89,8 +92,38 @@ impl SpecWatcher {
         let delay = SpecWatcherDelay::configured_value();
         let mut watcher = SupWatcher::new(tx, delay.0)?;
         watcher.watch(spec_dir, RecursiveMode::NonRecursive)?;
-        Ok(SpecWatcher { _watcher: watcher,
-                         channel:  rx, })
+        Ok(SpecWatcher { watcher,
+                         channel: rx,
+                         included: HashSet::new() })
+    }
+
+    /// Watch the include directories of the specs, `dirs`, as well, and stop watching the ones
+    /// no spec includes anymore. A directory that cannot be watched yet, because it does not
+    /// exist, is tried again the next time.
+    pub fn watch_includes(&mut self, dirs: HashSet<PathBuf>) {
+        for dir in self.included.difference(&dirs) {
+            if let Err(err) = self.watcher.unwatch(dir) {
+                debug!("Unable to stop watching spec include directory {}: {}",
+                       dir.display(),
+                       err);
+            }
+        }
+        self.included.retain(|dir| dirs.contains(dir));
+        for dir in dirs {
+            if self.included.contains(&dir) {
+                continue;
+            }
+            match self.watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    self.included.insert(dir);
+                }
+                Err(err) => {
+                    debug!("Unable to watch spec include directory {}: {}",
+                           dir.display(),
+                           err)
+                }
+            }
+        }
     }
 
     /// Returns `true` if _any_ filesystem events were detected in the
@@ -0,0 +1,105 @@
+//! A JSON snapshot of the Supervisor's services and census view, written periodically to a file
+//! of the user's choosing. External agents can read the Supervisor's state from it without
+//! querying the HTTP gateway, and the last snapshot survives a crash of the Supervisor for later
+//! inspection. The file is replaced atomically, so readers never see a partial snapshot.
+
+use super::sync::GatewayState;
+use habitat_common::outputln;
+use habitat_core::fs::atomic_write;
+use serde_json::{self,
+                 Value as Json};
+use std::{path::PathBuf,
+          time::{Duration,
+                 Instant,
+                 SystemTime,
+                 UNIX_EPOCH}};
+
+static LOGKEY: &str = "SS";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateSnapshotConfig {
+    /// The file the snapshot is written to
+    pub path:     PathBuf,
+    /// How often the snapshot is written
+    pub interval: Duration,
+}
+
+pub struct StateSnapshotWriter {
+    config:     StateSnapshotConfig,
+    next_write: Instant,
+}
+
+impl StateSnapshotWriter {
+    pub fn new(config: StateSnapshotConfig) -> Self {
+        StateSnapshotWriter { config,
+                              next_write: Instant::now() }
+    }
+
+    /// Write a snapshot of the state last persisted to the gateway if the interval since the
+    /// previous one has passed.
+    ///
+    /// # Locking (see locking.md)
+    /// * `GatewayState::inner` (read)
+    pub fn write_if_due_gsr(&mut self, gateway_state: &GatewayState, member_id: &str) {
+        let now = Instant::now();
+        if now < self.next_write {
+            return;
+        }
+        self.next_write = now + self.config.interval;
+
+        let snapshot = {
+            let state = gateway_state.lock_gsr();
+            snapshot(member_id, state.services_data(), state.census_data())
+        };
+        let path = &self.config.path;
+        match snapshot {
+            Ok(json) => {
+                if let Err(e) = atomic_write(path, json) {
+                    outputln!("Unable to write the state snapshot to {}, {}",
+                              path.display(),
+                              e);
+                }
+            }
+            Err(e) => outputln!("Unable to create a state snapshot, {}", e),
+        }
+    }
+}
+
+/// Combine the JSON the gateway serves for `/services` and `/census` into a single document.
+/// Either is `null` if it has not been gathered yet.
+fn snapshot(member_id: &str,
+            services_data: &str,
+            census_data: &str)
+            -> serde_json::Result<Vec<u8>> {
+    let parse = |data: &str| {
+        if data.is_empty() {
+            Ok(Json::Null)
+        } else {
+            serde_json::from_str(data)
+        }
+    };
+    let written_at = SystemTime::now().duration_since(UNIX_EPOCH)
+                                      .map(|d| d.as_secs())
+                                      .unwrap_or_default();
+    serde_json::to_vec_pretty(&json!({
+                                  "written_at": written_at,
+                                  "member_id": member_id,
+                                  "services": parse(services_data)?,
+                                  "census": parse(census_data)?,
+                              }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_combines_services_and_census() {
+        let json = snapshot("abc123", r#"[{"service_group":"redis.default"}]"#, "").unwrap();
+        let json: Json = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["member_id"], "abc123");
+        assert_eq!(json["services"][0]["service_group"], "redis.default");
+        assert_eq!(json["census"], Json::Null);
+        assert!(json["written_at"].as_u64().unwrap() > 0);
+    }
+}
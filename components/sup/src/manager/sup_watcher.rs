@@ -1,5 +1,10 @@
 //! Watcher interface implementation for Habitat Supervisor.
+//!
+//! The native watcher is used where it works. When the host runs out of inotify instances or
+//! watches, the Supervisor falls back to polling the watched paths instead, rather than failing
+//! to watch them at all.
 
+use habitat_common::outputln;
 use habitat_core::package::target::{PackageTarget,
                                     AARCH64_DARWIN};
 use notify::{poll::PollWatcher,
@@ -9,16 +14,54 @@ use notify::{poll::PollWatcher,
              Result,
              Watcher};
 use std::{env,
-          path::Path,
+          path::{Path,
+                 PathBuf},
           str::FromStr,
-          sync::mpsc::Sender,
+          sync::{mpsc::Sender,
+                 Once},
           time::Duration};
 
+static LOGKEY: &str = "FW";
+
+/// Makes sure that falling back to polling is only reported once, however many watchers do
+static FALLBACK_NOTICE: Once = Once::new();
+
 pub enum SupWatcher {
-    Native(RecommendedWatcher),
+    Native(NativeWatcher),
     Fallback(PollWatcher),
 }
 
+/// The native watcher of the platform, along with what it takes to replace it with a polling
+/// watcher
+pub struct NativeWatcher {
+    watcher:  RecommendedWatcher,
+    /// Creates a polling watcher that sends its events where the native watcher does
+    fallback: Box<dyn Fn() -> Result<PollWatcher> + Send>,
+    /// The paths watched so far, to be watched again by the polling watcher
+    watched:  Vec<(PathBuf, RecursiveMode)>,
+}
+
+impl SupWatcher {
+    /// Create a native watcher, or a polling one if the host has no inotify instances left
+    fn native_or_fallback<F>(native: Result<RecommendedWatcher>, fallback: F) -> Result<Self>
+        where F: Fn() -> Result<PollWatcher> + Send + 'static
+    {
+        match native {
+            Ok(watcher) => {
+                let fallback = Box::new(fallback);
+                Ok(SupWatcher::Native(NativeWatcher { watcher,
+                                                      fallback,
+                                                      watched: Vec::new() }))
+            }
+            Err(e) if is_watch_limit(&e) => {
+                notify_fallback(&e);
+                Ok(SupWatcher::Fallback(fallback()?))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
 impl Watcher for SupWatcher {
     fn new_raw(tx: Sender<notify::RawEvent>) -> Result<Self> {
         let target = PackageTarget::from_str(&env::var("HAB_STUDIO_HOST_ARCH").
@@ -27,7 +70,9 @@ impl Watcher for SupWatcher {
         if target == AARCH64_DARWIN {
             Ok(SupWatcher::Fallback(PollWatcher::new_raw(tx).unwrap()))
         } else {
-            Ok(SupWatcher::Native(RecommendedWatcher::new_raw(tx).unwrap()))
+            Self::native_or_fallback(RecommendedWatcher::new_raw(tx.clone()), move || {
+                PollWatcher::new_raw(tx.clone())
+            })
         }
     }
 
@@ -40,25 +85,70 @@ impl Watcher for SupWatcher {
             Ok(SupWatcher::Fallback(PollWatcher::new(tx, delay).unwrap()))
         } else {
             debug!("Using native watcher");
-            Ok(SupWatcher::Native(RecommendedWatcher::new(tx, delay).unwrap()))
+            Self::native_or_fallback(RecommendedWatcher::new(tx.clone(), delay), move || {
+                PollWatcher::new(tx.clone(), delay)
+            })
         }
     }
 
     fn watch<P: AsRef<Path>>(&mut self, path: P, recursive_mode: RecursiveMode) -> Result<()> {
-        match self {
-            SupWatcher::Native(watcher) => watcher.watch(path, recursive_mode),
-            SupWatcher::Fallback(watcher) => watcher.watch(path, recursive_mode),
+        let path = path.as_ref();
+        let native = match self {
+            SupWatcher::Native(native) => native,
+            SupWatcher::Fallback(watcher) => return watcher.watch(path, recursive_mode),
+        };
+        match native.watcher.watch(path, recursive_mode) {
+            Ok(()) => {
+                native.watched.push((path.to_path_buf(), recursive_mode));
+                Ok(())
+            }
+            Err(e) if is_watch_limit(&e) => {
+                notify_fallback(&e);
+                let mut watcher = (native.fallback)()?;
+                for (watched, mode) in &native.watched {
+                    watcher.watch(watched, *mode)?;
+                }
+                watcher.watch(path, recursive_mode)?;
+                *self = SupWatcher::Fallback(watcher);
+                Ok(())
+            }
+            Err(e) => Err(e),
         }
     }
 
     fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         match self {
-            SupWatcher::Native(watcher) => watcher.unwatch(path),
+            SupWatcher::Native(native) => {
+                native.watched
+                      .retain(|(watched, _)| watched != path.as_ref());
+                native.watcher.unwatch(path)
+            }
             SupWatcher::Fallback(watcher) => watcher.unwatch(path),
         }
     }
 }
 
+/// Whether `err` means that the host ran out of inotify instances or watches
+fn is_watch_limit(err: &notify::Error) -> bool {
+    match err {
+        notify::Error::Io(e) => {
+            matches!(e.raw_os_error(), Some(libc::ENOSPC) | Some(libc::EMFILE))
+        }
+        // The inotify watcher reports ENOSPC from adding a watch as a generic error
+        notify::Error::Generic(msg) => msg.contains("limit"),
+        _ => false,
+    }
+}
+
+fn notify_fallback(err: &notify::Error) {
+    FALLBACK_NOTICE.call_once(|| {
+                       outputln!("Watching files by polling, because the native file watcher \
+                                  failed: {}. Raise the inotify limits of the host, such as \
+                                  fs.inotify.max_user_watches, to watch files natively.",
+                                 err)
+                   });
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -105,4 +195,15 @@ mod test {
 
         assert_eq!(watcher_type, "Native");
     }
+
+    #[test]
+    fn running_out_of_inotify_watches_is_a_watch_limit() {
+        let enospc = std::io::Error::from_raw_os_error(libc::ENOSPC);
+        assert!(is_watch_limit(&notify::Error::Io(enospc)));
+        assert!(is_watch_limit(&notify::Error::Generic(String::from("inotify watch limit \
+                                                                     reached"))));
+        assert!(!is_watch_limit(&notify::Error::PathNotFound));
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(!is_watch_limit(&notify::Error::Io(denied)));
+    }
 }
@@ -4,7 +4,8 @@ use habitat_common::{outputln,
                      types::{GossipListenAddr,
                              HttpListenAddr,
                              ListenCtlAddr}};
-use std::{net::{IpAddr,
+use std::{collections::HashMap,
+          net::{IpAddr,
                 SocketAddr},
           str};
 
@@ -23,6 +24,13 @@ pub struct Sys {
     pub http_gateway_ip:   IpAddr,
     pub http_gateway_port: u16,
     pub permanent:         bool,
+    /// The failure domain (e.g. an availability zone) this Supervisor runs in, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zone:              Option<String>,
+    /// The labels describing this Supervisor, which select the services a fleet manifest loads
+    /// on it
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub labels:            HashMap<String, String>,
 }
 
 impl Sys {
@@ -50,7 +58,9 @@ impl Sys {
                ctl_gateway_port: ctl.port(),
                http_gateway_ip: http.ip(),
                http_gateway_port: http.port(),
-               permanent }
+               permanent,
+               zone: None,
+               labels: HashMap::new() }
     }
 
     pub fn as_sys_info(&self) -> SysInfo {
@@ -61,7 +71,10 @@ impl Sys {
                   ctl_gateway_ip:    self.ctl_gateway_ip.to_string(),
                   ctl_gateway_port:  u32::from(self.ctl_gateway_port),
                   http_gateway_ip:   self.http_gateway_ip.to_string(),
-                  http_gateway_port: u32::from(self.http_gateway_port), }
+                  http_gateway_port: u32::from(self.http_gateway_port),
+                  zone:              self.zone.clone(),
+                  labels:            self.labels.clone(),
+                  version:           Some(self.version.clone()), }
     }
 
     pub fn ctl_listen(&self) -> SocketAddr {
@@ -0,0 +1,155 @@
+//! Notices changes to the templates of running services, so that templates edited in place, such
+//! as in a `--config-from` directory during development, are loaded again without reloading the
+//! service.
+
+use crate::{error::{Error,
+                    Result},
+            manager::sup_watcher::SupWatcher,
+            notify::Watcher};
+use notify::{DebouncedEvent,
+             RecursiveMode};
+use std::{collections::{HashMap,
+                        HashSet},
+          path::{Path,
+                 PathBuf},
+          sync::mpsc::{self,
+                       Receiver},
+          thread::Builder,
+          time::Duration};
+
+habitat_core::env_config_duration!(
+    /// How long to wait to consolidate filesystem events in template directories
+    TemplateWatcherDelay,
+    HAB_TEMPLATE_WATCHER_DELAY_MS => from_millis,
+    Duration::from_secs(2));
+
+pub struct TemplateWatcher {
+    // Dropping it kills the threads that the watcher spawns to do its work.
+    watcher: SupWatcher,
+    channel: Receiver<DebouncedEvent>,
+    /// The template directories that are being watched, and whether their subdirectories are
+    /// watched as well
+    watched: HashMap<PathBuf, RecursiveMode>,
+}
+
+impl TemplateWatcher {
+    /// Start watching, on threads named after the watcher. See `SpecWatcher::run`.
+    pub fn run() -> Result<TemplateWatcher> {
+        Builder::new().name(String::from("template-watcher"))
+                      .spawn(Self::new)?
+                      .join()
+                      .map_err(|_| {
+                          error!("TemplateWatcher spawning thread panicked!");
+                          Error::TemplateWatcherNotCreated
+                      })?
+    }
+
+    fn new() -> Result<TemplateWatcher> {
+        let (tx, rx) = mpsc::channel();
+        let delay = TemplateWatcherDelay::configured_value();
+        let watcher = SupWatcher::new(tx, delay.0)?;
+        Ok(TemplateWatcher { watcher,
+                             channel: rx,
+                             watched: HashMap::new() })
+    }
+
+    /// Watch the template directories `dirs`, and stop watching the ones that are not among them
+    /// anymore. A directory that cannot be watched yet, because it does not exist, is tried again
+    /// the next time.
+    pub fn watch_dirs(&mut self, dirs: HashMap<PathBuf, RecursiveMode>) {
+        for (dir, mode) in &self.watched {
+            if dirs.get(dir) == Some(mode) {
+                continue;
+            }
+            if let Err(err) = self.watcher.unwatch(dir) {
+                debug!("Unable to stop watching template directory {}: {}",
+                       dir.display(),
+                       err);
+            }
+        }
+        self.watched.retain(|dir, mode| dirs.get(dir) == Some(mode));
+        for (dir, mode) in dirs {
+            if self.watched.contains_key(&dir) || !dir.is_dir() {
+                continue;
+            }
+            match self.watcher.watch(&dir, mode) {
+                Ok(()) => {
+                    self.watched.insert(dir, mode);
+                }
+                Err(err) => {
+                    debug!("Unable to watch template directory {}: {}",
+                           dir.display(),
+                           err)
+                }
+            }
+        }
+    }
+
+    /// The watched directories in which templates changed since this was last called
+    pub fn changed_dirs(&self) -> HashSet<PathBuf> {
+        let paths = self.channel
+                        .try_iter()
+                        .flat_map(|event| {
+                            match event {
+                                DebouncedEvent::Create(path)
+                                | DebouncedEvent::Write(path)
+                                | DebouncedEvent::Chmod(path)
+                                | DebouncedEvent::Remove(path) => vec![path],
+                                DebouncedEvent::Rename(from, to) => vec![from, to],
+                                _ => vec![],
+                            }
+                        })
+                        .collect::<Vec<_>>();
+        self.watched
+            .iter()
+            .filter(|(dir, mode)| paths.iter().any(|path| contains(dir, **mode, path)))
+            .map(|(dir, _)| dir.clone())
+            .collect()
+    }
+}
+
+/// Whether `path` is in the directory `dir` watched with `mode`
+fn contains(dir: &Path, mode: RecursiveMode, path: &Path) -> bool {
+    match mode {
+        RecursiveMode::NonRecursive => path.parent() == Some(dir),
+        RecursiveMode::Recursive => path.starts_with(dir) && path != dir,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use habitat_core::locked_env_var;
+    use std::{fs,
+              thread};
+    use tempfile::TempDir;
+
+    locked_env_var!(HAB_TEMPLATE_WATCHER_DELAY_MS, lock_delay_var);
+
+    #[test]
+    fn notices_changed_templates_in_watched_dirs() {
+        let delay = lock_delay_var();
+        delay.set("10");
+        let hooks = TempDir::new().unwrap();
+        let config = TempDir::new().unwrap();
+        let other = TempDir::new().unwrap();
+        fs::create_dir(config.path().join("conf.d")).unwrap();
+        let mut watcher = TemplateWatcher::run().unwrap();
+        let dirs = vec![(hooks.path().to_path_buf(), RecursiveMode::NonRecursive),
+                        (config.path().to_path_buf(), RecursiveMode::Recursive)];
+        watcher.watch_dirs(dirs.into_iter().collect());
+
+        fs::write(hooks.path().join("health-check"), "exit 0").unwrap();
+        fs::write(other.path().join("health-check"), "exit 0").unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(watcher.changed_dirs(),
+                   vec![hooks.path().to_path_buf()].into_iter().collect());
+        assert!(watcher.changed_dirs().is_empty());
+
+        fs::write(config.path().join("conf.d").join("site.conf"),
+                  "{{cfg.port}}").unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(watcher.changed_dirs(),
+                   vec![config.path().to_path_buf()].into_iter().collect());
+    }
+}
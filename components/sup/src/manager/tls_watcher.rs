@@ -0,0 +1,366 @@
+//! Reloads the TLS certificates, private keys and client CA certificates of the HTTP and ctl
+//! gateways when their files change, so that certificates rotated on disk are served without
+//! restarting the Supervisor. Connections that are already established keep the certificates they
+//! were made with, and when the changed files cannot be read the gateway keeps serving the ones it
+//! has.
+
+use crate::{error::{Error,
+                    Result},
+            manager::TLSConfig};
+use habitat_common::outputln;
+use habitat_core::tls::rustls_wrapper::{CertificateChainCli,
+                                        PrivateKeyCli,
+                                        RootCertificateStoreCli};
+use rustls::{internal::pemfile,
+             sign::{self,
+                    CertifiedKey},
+             AllowAnyAuthenticatedClient,
+             Certificate,
+             ClientCertVerified,
+             ClientCertVerifier,
+             ClientHello,
+             DistinguishedNames,
+             NoClientAuth,
+             PrivateKey,
+             ResolvesServerCert,
+             RootCertStore,
+             ServerConfig,
+             SignatureScheme,
+             TLSError};
+use std::{fs::{self,
+               File},
+          io::BufReader,
+          path::{Path,
+                 PathBuf},
+          sync::{Arc,
+                 Mutex,
+                 RwLock},
+          time::{Duration,
+                 SystemTime}};
+use tokio::time;
+use webpki::DNSName;
+
+static LOGKEY: &str = "TW";
+
+habitat_core::env_config_duration!(
+    /// How often the TLS files of the gateways are checked for changes
+    TlsWatcherInterval,
+    HAB_TLS_WATCHER_INTERVAL_SECS => from_secs,
+    Duration::from_secs(30));
+
+/// The files that a gateway's TLS configuration is read from
+#[derive(Clone, Debug)]
+pub enum TlsFiles {
+    /// The HTTP gateway's `--cert`, `--key` and `--ca-certs`
+    Http(TLSConfig),
+    /// The ctl gateway's `--ctl-server-certificate`, `--ctl-server-key` and
+    /// `--ctl-client-ca-certificate`, each of which can be a directory whose latest file is used
+    Ctl {
+        certificates: Option<CertificateChainCli>,
+        key:          PrivateKeyCli,
+        client_ca:    Option<RootCertificateStoreCli>,
+    },
+}
+
+impl TlsFiles {
+    fn paths(&self) -> Vec<&Path> {
+        match self {
+            TlsFiles::Http(config) => {
+                let mut paths = vec![config.cert_path.as_path(), config.key_path.as_path()];
+                paths.extend(config.ca_cert_path.as_deref());
+                paths
+            }
+            TlsFiles::Ctl { certificates,
+                            key,
+                            client_ca, } => {
+                let mut paths = vec![key.path()];
+                paths.extend(certificates.as_ref().map(CertificateChainCli::path));
+                paths.extend(client_ca.as_ref().map(RootCertificateStoreCli::path));
+                paths
+            }
+        }
+    }
+
+    /// Read the certificate chain, private key and client CA certificates from the files
+    fn read(&self) -> Result<(CertifiedKey, Option<RootCertStore>)> {
+        let (certificates, key, client_ca, key_path) = match self {
+            TlsFiles::Http(config) => {
+                let (certificates, key, client_ca) = read_http_files(config)?;
+                (certificates, key, client_ca, config.key_path.as_path())
+            }
+            TlsFiles::Ctl { certificates,
+                            key,
+                            client_ca, } => {
+                let certificates = match certificates {
+                    Some(certificates) => certificates.reload()?.into_inner(),
+                    None => Vec::new(),
+                };
+                let client_ca = match client_ca {
+                    Some(client_ca) => Some(client_ca.reload()?.into_inner()),
+                    None => None,
+                };
+                (certificates, key.reload()?.into_inner(), client_ca, key.path())
+            }
+        };
+        let invalid_key = || Error::InvalidKeyFile(key_path.to_path_buf());
+        let signing_key = sign::any_supported_type(&key).map_err(|_| invalid_key())?;
+        let certified_key = CertifiedKey::new(certificates, Arc::new(signing_key));
+        // Checked on every read, so that a certificate rotated without its key, or the other way
+        // around, is not served
+        if !certified_key.cert.is_empty() && !key_matches_certificate(&certified_key) {
+            return Err(Error::KeyCertificateMismatch(key_path.to_path_buf()));
+        }
+        Ok((certified_key, client_ca))
+    }
+}
+
+/// Whether the private key of `certified_key` belongs to its end-entity certificate, which is
+/// the case if a signature made with the key verifies against the certificate's public key
+fn key_matches_certificate(certified_key: &CertifiedKey) -> bool {
+    const MESSAGE: &[u8] = b"habitat tls key check";
+    const SCHEMES: [SignatureScheme; 4] = [SignatureScheme::ED25519,
+                                           SignatureScheme::ECDSA_NISTP256_SHA256,
+                                           SignatureScheme::ECDSA_NISTP384_SHA384,
+                                           SignatureScheme::RSA_PKCS1_SHA256];
+    let verified = || -> Option<bool> {
+        let cert = webpki::EndEntityCert::from(&certified_key.cert.first()?.0).ok()?;
+        let signer = certified_key.key.choose_scheme(&SCHEMES)?;
+        let algorithm = match signer.get_scheme() {
+            SignatureScheme::ED25519 => &webpki::ED25519,
+            SignatureScheme::ECDSA_NISTP256_SHA256 => &webpki::ECDSA_P256_SHA256,
+            SignatureScheme::ECDSA_NISTP384_SHA384 => &webpki::ECDSA_P384_SHA384,
+            SignatureScheme::RSA_PKCS1_SHA256 => &webpki::RSA_PKCS1_2048_8192_SHA256,
+            _ => return None,
+        };
+        let signature = signer.sign(MESSAGE).ok()?;
+        Some(cert.verify_signature(algorithm, MESSAGE, &signature)
+                 .is_ok())
+    };
+    verified().unwrap_or(false)
+}
+
+/// Read the HTTP gateway's certificate chain, RSA private key and client CA certificates
+fn read_http_files(config: &TLSConfig)
+                   -> Result<(Vec<Certificate>, PrivateKey, Option<RootCertStore>)> {
+    let client_ca = match &config.ca_cert_path {
+        Some(path) => {
+            let mut root_store = RootCertStore::empty();
+            let ca_file = &mut BufReader::new(File::open(path)?);
+            root_store.add_pem_file(ca_file)
+                      .and_then(|(added, _)| if added < 1 { Err(()) } else { Ok(()) })
+                      .map_err(|_| Error::InvalidCertFile(path.clone()))?;
+            Some(root_store)
+        }
+        None => None,
+    };
+
+    let key_file = &mut BufReader::new(File::open(&config.key_path)?);
+    let cert_file = &mut BufReader::new(File::open(&config.cert_path)?);
+
+    // Note that we must explicitly map these errors because rustls returns () as the error from
+    // both pemfile::certs() as well as pemfile::rsa_private_keys() and we want to return
+    // different errors for each.
+    let cert_chain =
+        pemfile::certs(cert_file).and_then(|c| if c.is_empty() { Err(()) } else { Ok(c) })
+                                 .map_err(|_| Error::InvalidCertFile(config.cert_path.clone()))?;
+
+    let key = pemfile::rsa_private_keys(key_file).and_then(|mut k| k.pop().ok_or(()))
+                                                 .map_err(|_| {
+                                                     Error::InvalidKeyFile(config.key_path.clone())
+                                                 })?;
+    Ok((cert_chain, key, client_ca))
+}
+
+/// The TLS configuration of a gateway that can be replaced while the gateway is serving. It is
+/// both the certificate resolver and the client certificate verifier of the gateway's
+/// `ServerConfig`, so every new connection uses whatever was read last.
+pub struct ReloadableTls {
+    name:          &'static str,
+    files:         TlsFiles,
+    /// When the files were last modified, as of the last time they were read
+    modified:      Mutex<Vec<Option<SystemTime>>>,
+    certified_key: RwLock<CertifiedKey>,
+    verifier:      RwLock<Arc<dyn ClientCertVerifier>>,
+}
+
+impl ReloadableTls {
+    /// Read the TLS files of the gateway called `name`. Unlike when they are read again later, an
+    /// error here is returned, so that the Supervisor does not start with a gateway it cannot
+    /// serve.
+    pub fn new(name: &'static str, files: TlsFiles) -> Result<Arc<Self>> {
+        let modified = modified_times(&files.paths());
+        let (certified_key, client_ca) = files.read()?;
+        let certified_key = RwLock::new(certified_key);
+        let verifier = RwLock::new(client_verifier(client_ca));
+        Ok(Arc::new(ReloadableTls { name,
+                                    files,
+                                    modified: Mutex::new(modified),
+                                    certified_key,
+                                    verifier }))
+    }
+
+    /// A `ServerConfig` whose certificates and client CA certificates are always the latest ones
+    pub fn server_config(self: &Arc<Self>) -> ServerConfig {
+        let mut config = ServerConfig::new(Arc::clone(self) as Arc<dyn ClientCertVerifier>);
+        config.cert_resolver = Arc::clone(self) as Arc<dyn ResolvesServerCert>;
+        config
+    }
+
+    /// Read the TLS files again if any of them changed since they were last read
+    fn reload_if_changed(&self) {
+        let modified = modified_times(&self.files.paths());
+        {
+            let mut last_modified = self.modified
+                                        .lock()
+                                        .expect("TLS modified times lock poisoned");
+            if *last_modified == modified {
+                return;
+            }
+            *last_modified = modified;
+        }
+        match self.files.read() {
+            Ok((certified_key, client_ca)) => {
+                *self.certified_key
+                     .write()
+                     .expect("TLS certificate lock poisoned") = certified_key;
+                *self.verifier.write().expect("TLS verifier lock poisoned") =
+                    client_verifier(client_ca);
+                outputln!("Reloaded the {} TLS certificates", self.name);
+            }
+            Err(e) => {
+                outputln!("Unable to reload the {} TLS certificates, keeping the current ones, {}",
+                          self.name,
+                          e);
+            }
+        }
+    }
+
+    fn verifier(&self) -> Arc<dyn ClientCertVerifier> {
+        Arc::clone(&self.verifier.read().expect("TLS verifier lock poisoned"))
+    }
+}
+
+impl ResolvesServerCert for ReloadableTls {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<CertifiedKey> {
+        Some(self.certified_key
+                 .read()
+                 .expect("TLS certificate lock poisoned")
+                 .clone())
+    }
+}
+
+impl ClientCertVerifier for ReloadableTls {
+    fn offer_client_auth(&self) -> bool { self.verifier().offer_client_auth() }
+
+    fn client_auth_mandatory(&self, sni: Option<&DNSName>) -> Option<bool> {
+        self.verifier().client_auth_mandatory(sni)
+    }
+
+    fn client_auth_root_subjects(&self, sni: Option<&DNSName>) -> Option<DistinguishedNames> {
+        self.verifier().client_auth_root_subjects(sni)
+    }
+
+    fn verify_client_cert(&self,
+                          presented_certs: &[Certificate],
+                          sni: Option<&DNSName>)
+                          -> std::result::Result<ClientCertVerified, TLSError> {
+        self.verifier().verify_client_cert(presented_certs, sni)
+    }
+}
+
+fn client_verifier(client_ca: Option<RootCertStore>) -> Arc<dyn ClientCertVerifier> {
+    match client_ca {
+        Some(client_ca) => AllowAnyAuthenticatedClient::new(client_ca),
+        None => NoClientAuth::new(),
+    }
+}
+
+/// When each of `paths` was last modified. A directory counts as modified when any of the files
+/// in it are, so that a new certificate added to it or one rewritten in place is noticed.
+fn modified_times(paths: &[&Path]) -> Vec<Option<SystemTime>> {
+    paths.iter()
+         .map(|path| {
+             let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+             if path.is_dir() {
+                 fs::read_dir(path).ok()
+                                   .into_iter()
+                                   .flatten()
+                                   .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+                                   .chain(modified)
+                                   .max()
+             } else {
+                 modified
+             }
+         })
+         .collect()
+}
+
+/// Check the TLS files of `gateways` for changes every `TlsWatcherInterval`, forever
+pub async fn run(gateways: Vec<Arc<ReloadableTls>>) {
+    let interval = TlsWatcherInterval::configured_value().into();
+    loop {
+        time::sleep(interval).await;
+        for gateway in &gateways {
+            gateway.reload_if_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use tempfile::TempDir;
+
+    #[test]
+    fn modified_times_notice_files_changed_in_directories() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("ctl-gateway-20210101000000.crt.pem");
+        fs::write(&file, "first").unwrap();
+        let paths = [dir.path(), file.as_path()];
+        let before = modified_times(&paths);
+        assert!(before.iter().all(Option::is_some));
+        assert_eq!(modified_times(&paths), before);
+
+        thread::sleep(Duration::from_millis(20));
+        fs::write(&file, "second").unwrap();
+        let after = modified_times(&paths);
+        assert!(after[0] > before[0]);
+        assert!(after[1] > before[1]);
+    }
+
+    #[test]
+    fn missing_files_have_no_modified_time() {
+        let dir = TempDir::new().unwrap();
+        let missing = dir.path().join("missing.pem");
+        assert_eq!(modified_times(&[missing.as_path()]), vec![None]);
+    }
+
+    fn certified_key(cert: &str, key: &str) -> CertifiedKey {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../butterfly/tests/fixtures/tls");
+        let certs = pemfile::certs(&mut BufReader::new(File::open(dir.join(cert)).unwrap()));
+        let mut keys =
+            pemfile::pkcs8_private_keys(&mut BufReader::new(File::open(dir.join(key)).unwrap()));
+        let signing_key = sign::any_supported_type(&keys.unwrap().remove(0)).unwrap();
+        CertifiedKey::new(certs.unwrap(), Arc::new(signing_key))
+    }
+
+    #[test]
+    fn keys_are_checked_against_their_certificate() {
+        assert!(key_matches_certificate(&certified_key("member0.crt", "member0.key")));
+        assert!(!key_matches_certificate(&certified_key("member0.crt", "member1.key")));
+    }
+
+    #[test]
+    fn unreadable_files_are_an_error() {
+        let dir = TempDir::new().unwrap();
+        let cert_path = dir.path().join("cert.pem");
+        let key_path = dir.path().join("key.pem");
+        fs::write(&cert_path, "not a certificate").unwrap();
+        fs::write(&key_path, "not a key").unwrap();
+        let files = TlsFiles::Http(TLSConfig { cert_path,
+                                               key_path,
+                                               ca_cert_path: None });
+        assert!(ReloadableTls::new("HTTP gateway", files).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Records that the Supervisor departed the ring with `hab sup depart --self`.
+//!
+//! The member ID of a departed member is banned from the ring for good, so a Supervisor that finds
+//! a tombstone refuses to start until `hab sup rejoin` has checked its state directory and cleared
+//! the departed member ID, letting it join the ring as a new member.
+
+use crate::error::{Error,
+                   Result};
+use habitat_core::fs::AtomicWriter;
+use std::{fs,
+          io::{self,
+               Write},
+          path::{Path,
+                 PathBuf},
+          time::{SystemTime,
+                 UNIX_EPOCH}};
+
+const TOMBSTONE_FILE: &str = "DEPARTED";
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Tombstone {
+    /// The member ID the Supervisor departed the ring as
+    pub member_id:        String,
+    /// When the Supervisor departed, in seconds since the Unix epoch
+    pub departed_at:      u64,
+    /// Whether the services were stopped, rather than left running without a Supervisor
+    pub services_stopped: bool,
+}
+
+impl Tombstone {
+    pub fn new(member_id: &str, services_stopped: bool) -> Self {
+        let departed_at = SystemTime::now().duration_since(UNIX_EPOCH)
+                                           .map(|since_epoch| since_epoch.as_secs())
+                                           .unwrap_or(0);
+        Tombstone { member_id: member_id.to_string(),
+                    departed_at,
+                    services_stopped }
+    }
+
+    /// The tombstone file in the Supervisor's data directory
+    pub fn path(data_path: &Path) -> PathBuf { data_path.join(TOMBSTONE_FILE) }
+
+    /// Read the tombstone at `path`, if the Supervisor has departed
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::BadDataFile(path.to_path_buf(), e)),
+        };
+        toml::from_str(&contents).map(Some)
+                                 .map_err(|e| Error::InvalidTombstone(path.to_path_buf(), e))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let contents = toml::to_string(self)?;
+        let w = AtomicWriter::new(path)?;
+        w.with_writer(|f| f.write_all(contents.as_bytes()))
+         .map_err(|e| Error::BadDataFile(path.to_path_buf(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn tombstones_round_trip_through_the_file() {
+        let dir = TempDir::new().unwrap();
+        let path = Tombstone::path(dir.path());
+        assert_eq!(Tombstone::read(&path).unwrap(), None);
+
+        let tombstone = Tombstone::new("0123456789abcdef0123456789abcdef", false);
+        tombstone.write(&path).unwrap();
+        assert_eq!(Tombstone::read(&path).unwrap(), Some(tombstone));
+    }
+
+    #[test]
+    fn unreadable_tombstones_are_errors() {
+        let dir = TempDir::new().unwrap();
+        let path = Tombstone::path(dir.path());
+        fs::write(&path, "member_id = 42").unwrap();
+        assert!(Tombstone::read(&path).is_err());
+    }
+}
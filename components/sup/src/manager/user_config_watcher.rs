@@ -4,7 +4,9 @@ use crate::manager::service::Service;
 use habitat_common::{liveliness_checker,
                      outputln,
                      templating::config::UserConfigPath};
-use habitat_core::{fs::USER_CONFIG_FILE,
+use habitat_core::{fs::{DOTENV_FILE,
+                        ENVIRONMENT_FILE,
+                        USER_CONFIG_FILE},
                    service::ServiceGroup};
 use std::{collections::HashMap,
           io,
@@ -49,24 +51,27 @@ struct WorkerState {
     // size 1, as we are only interested in the fact that there were
     // events, not how many there were.
     have_events:      Receiver<()>,
-    // This sender is used by the watcher to notify a worker to stop
-    // running.  It is an async channel because we never want the
-    // UserConfigWatcher to block, even if the receiver end of the
-    // channel somehow dies and/or fails to consume the message.
-    stop_running:     Sender<()>,
-    // This receiver is used by the watcher tests to be notified when
-    // a worker finished setting up the watcher and is about to
-    // starting looping it.
+    // These senders are used by the watcher to notify the worker of
+    // each watched file to stop running.  They are async channels
+    // because we never want the UserConfigWatcher to block, even if
+    // the receiver end of a channel somehow dies and/or fails to
+    // consume the message.
+    stop_running:     Vec<Sender<()>>,
+    // These receivers are used by the watcher tests to be notified
+    // when the worker of each watched file finished setting up the
+    // watcher and is about to starting looping it.
     //
     // Silence the dead code warnings from rustc, because it is only
     // used in tests for synchronization purposes.
     #[allow(dead_code)]
-    started_watching: Receiver<()>,
+    started_watching: Vec<Receiver<()>>,
 }
 
 type ServiceName = String;
 #[derive(Clone)]
 pub struct UserConfigWatcher {
+    // The files in each service's user config directory that are watched
+    files:  &'static [&'static str],
     // We use Arc/Mutex here, because this needs to be shareable
     // across threads so we can remove watchers from futures; the
     // Sender and Receiver members of WorkerState aren't themselves
@@ -75,41 +80,55 @@ pub struct UserConfigWatcher {
 }
 
 impl UserConfigWatcher {
-    pub fn new() -> Self { Self { states: Arc::new(Mutex::new(HashMap::new())), } }
+    /// A watcher of each service's `user.toml`
+    pub fn new() -> Self { Self::watching(&[USER_CONFIG_FILE]) }
 
-    /// Adds a service to the User Config Watcher, thereby starting a watcher thread.
+    /// A watcher of each service's environment files, `environment.toml` and `.env`
+    pub fn environment() -> Self { Self::watching(&[ENVIRONMENT_FILE, DOTENV_FILE]) }
+
+    fn watching(files: &'static [&'static str]) -> Self {
+        Self { files,
+               states: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    /// Adds a service to the User Config Watcher, thereby starting a watcher thread for each
+    /// watched file.
     pub fn add<T: Serviceable>(&mut self, service: &T) -> io::Result<()> {
         // It isn't possible to use the `or_insert_with` function here because it can't have a
         // return value, which we need to return the error from `Worker::run`.
         let mut states = self.states.lock().expect("states lock was poisoned");
         if states.get(service.name()).is_none() {
-            let user_toml_path = match service.user_config_path() {
-                UserConfigPath::Recommended(ref p) => p.join(USER_CONFIG_FILE),
+            let dir = match service.user_config_path() {
+                UserConfigPath::Recommended(ref p) => p,
                 UserConfigPath::Deprecated(ref p) => {
                     outputln!(
                         preamble service.service_group(),
                         "Not watching {}, because it is located in deprecated path ({}).",
-                        USER_CONFIG_FILE,
+                        self.files.join(", "),
                         p.display(),
                     );
                     return Ok(());
                 }
             };
-            // Establish bi-directional communication with the worker by creating two channels.
-            // The sync_channel's buffer size is 1 because we want to use it as a boolean, i.e. we
-            // are not interested in the events themselves, but only whether at least one has
-            // happened.
+            // Establish bi-directional communication with the workers by creating two channels
+            // for each of them, and one that they share to report events. The sync_channel's
+            // buffer size is 1 because we want to use it as a boolean, i.e. we are not interested
+            // in the events themselves, but only whether at least one has happened.
             let (events_tx, events_rx) = sync_channel(1);
-            let (running_tx, running_rx) = channel();
-            let (watching_tx, watching_rx) = sync_channel(1);
+            let mut state = WorkerState { have_events:      events_rx,
+                                          stop_running:     Vec::new(),
+                                          started_watching: Vec::new(), };
+            for file in self.files {
+                let (running_tx, running_rx) = channel();
+                let (watching_tx, watching_rx) = sync_channel(1);
 
-            Worker::run(user_toml_path, events_tx, running_rx, watching_tx)?;
+                Worker::run(dir.join(file), events_tx.clone(), running_rx, watching_tx)?;
 
-            outputln!(preamble service.service_group(), "Watching {}", USER_CONFIG_FILE);
+                outputln!(preamble service.service_group(), "Watching {}", file);
 
-            let state = WorkerState { have_events:      events_rx,
-                                      stop_running:     running_tx,
-                                      started_watching: watching_rx, };
+                state.stop_running.push(running_tx);
+                state.started_watching.push(watching_rx);
+            }
 
             states.insert(service.name().to_owned(), state);
         }
@@ -125,10 +144,12 @@ impl UserConfigWatcher {
                                  .expect("states lock was poisoned")
                                  .remove(service.name())
         {
-            if let Err(e) = state.stop_running.send(()) {
-                debug!("Error stopping user-config watcher thread for service {}: {:?}",
-                       service.name(),
-                       e);
+            for stop_running in state.stop_running {
+                if let Err(e) = stop_running.send(()) {
+                    debug!("Error stopping user-config watcher thread for service {}: {:?}",
+                           service.name(),
+                           e);
+                }
             }
         }
     }
@@ -415,26 +436,54 @@ mod tests {
         assert!(wait_for_events(&ucm, &service));
     }
 
+    #[test]
+    fn environment_watcher_has_events_for_each_environment_file() {
+        let lock = lock_env_var();
+        lock.unset();
+
+        let service = TestService::default();
+        let dir = service.user_config_path().get_path().clone();
+        let mut ecm = UserConfigWatcher::environment();
+        ecm.add(&service).expect("adding service");
+        assert!(wait_for_watcher(&ecm, &service));
+        assert!(!ecm.have_events_for(&service));
+
+        File::create(dir.join(DOTENV_FILE)).expect("creating file");
+        assert!(wait_for_events(&ecm, &service));
+
+        File::create(dir.join(ENVIRONMENT_FILE)).expect("creating file");
+        assert!(wait_for_events(&ecm, &service));
+
+        File::create(dir.join(USER_CONFIG_FILE)).expect("creating file");
+        assert!(!wait_for_events(&ecm, &service));
+    }
+
     fn wait_for_watcher<T: Serviceable>(ucm: &UserConfigWatcher, service: &T) -> bool {
         let start = Instant::now();
         let timeout = Duration::from_secs(10);
+        let mut started = 0;
 
         while start.elapsed() < timeout {
             let states = ucm.states.lock().expect("states lock was poisoned");
             let state = states.get(service.name()).expect("service added");
-            match state.started_watching.try_recv() {
-                Ok(_) => {
-                    println!("Received data on the start_watching channel. Returning true.");
-                    return true;
-                }
-                Err(TryRecvError::Empty) => {
-                    println!("Received nothing on the start_watching channel. Returning ().");
-                }
-                Err(TryRecvError::Disconnected) => {
-                    println!("The start_watching channel was disconnected. Returning false.");
-                    return false;
+            for started_watching in &state.started_watching {
+                match started_watching.try_recv() {
+                    Ok(_) => {
+                        println!("Received data on a start_watching channel.");
+                        started += 1;
+                    }
+                    Err(TryRecvError::Empty) => {
+                        println!("Received nothing on a start_watching channel.");
+                    }
+                    Err(TryRecvError::Disconnected) => {
+                        println!("A start_watching channel was disconnected. Returning false.");
+                        return false;
+                    }
                 }
             }
+            if started == state.started_watching.len() {
+                return true;
+            }
 
             thread::sleep(Duration::from_millis(100));
         }
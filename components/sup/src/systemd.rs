@@ -0,0 +1,150 @@
+//! Integration with systemd: gateway listeners from socket activation, and readiness, watchdog
+//! and shutdown notifications through `sd_notify`.
+//!
+//! systemd hands the sockets of a socket-activated unit, and the address of its notification
+//! socket, to the unit's main process. That is the Launcher, which passes the sockets on to the
+//! Supervisor, so sockets meant for either process are taken here. Notifications from the
+//! Supervisor are only accepted by systemd if the unit sets `NotifyAccess=all`.
+
+#[cfg(target_os = "linux")]
+use habitat_common::outputln;
+pub use habitat_core::os::systemd::{notify,
+                                    Watchdog,
+                                    READY,
+                                    STOPPING};
+#[cfg(target_os = "linux")]
+use habitat_launcher_client::LAUNCHER_PID_ENV;
+#[cfg(target_os = "linux")]
+use parking_lot::Mutex;
+#[cfg(target_os = "linux")]
+use std::{env,
+          os::unix::io::{FromRawFd,
+                         IntoRawFd,
+                         RawFd},
+          process};
+use std::{io,
+          net::{SocketAddr,
+                TcpListener}};
+
+#[cfg(target_os = "linux")]
+static LOGKEY: &str = "SN";
+#[cfg(target_os = "linux")]
+const LISTEN_FDS_START: RawFd = 3;
+
+#[cfg(target_os = "linux")]
+lazy_static! {
+    /// The sockets passed by systemd that have not been taken yet
+    static ref LISTEN_FDS: Mutex<Vec<RawFd>> = Mutex::new(listen_fds());
+}
+
+/// Whether the variable `pid_var` names the Supervisor or its Launcher
+#[cfg(target_os = "linux")]
+fn meant_for_us(pid_var: &str) -> bool {
+    let pid = match env::var(pid_var).ok()
+                                     .and_then(|pid| pid.parse::<u32>().ok())
+    {
+        Some(pid) => pid,
+        None => return false,
+    };
+    pid == process::id()
+    || env::var(LAUNCHER_PID_ENV).ok()
+                                 .and_then(|launcher| launcher.parse::<u32>().ok())
+       == Some(pid)
+}
+
+#[cfg(target_os = "linux")]
+fn listen_fds() -> Vec<RawFd> {
+    if !meant_for_us("LISTEN_PID") {
+        return Vec::new();
+    }
+    let count = env::var("LISTEN_FDS").ok()
+                                      .and_then(|count| count.parse::<RawFd>().ok())
+                                      .unwrap_or(0);
+    let fds: Vec<RawFd> = (LISTEN_FDS_START..LISTEN_FDS_START + count).collect();
+    for fd in &fds {
+        // Keep the sockets from being inherited by hooks
+        unsafe {
+            libc::fcntl(*fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+    }
+    debug!("Received {} socket(s) from systemd", fds.len());
+    fds
+}
+
+/// Whether a listener bound to `bound` serves `addr`. A socket systemd listens on for a port
+/// alone is bound to the IPv6 wildcard address, which stands in for the IPv4 one.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn serves(bound: SocketAddr, addr: SocketAddr) -> bool {
+    bound == addr
+    || (bound.port() == addr.port() && bound.ip().is_unspecified() && addr.ip().is_unspecified())
+}
+
+/// Take the socket systemd passed for `addr`, if it passed one, to listen on instead of binding
+/// the address.
+#[cfg(target_os = "linux")]
+pub fn take_listener(addr: SocketAddr) -> Option<TcpListener> {
+    let mut fds = LISTEN_FDS.lock();
+    let index = fds.iter().position(|fd| {
+                               // The descriptor is given up again without being closed, as it may
+                               // be meant for another listener
+                               let listener = unsafe { TcpListener::from_raw_fd(*fd) };
+                               let serves_addr =
+                                   listener.local_addr()
+                                           .map_or(false, |bound| serves(bound, addr));
+                               listener.into_raw_fd();
+                               serves_addr
+                           })?;
+    let listener = unsafe { TcpListener::from_raw_fd(fds.remove(index)) };
+    if let Err(e) = listener.set_nonblocking(true) {
+        outputln!("Not using the socket systemd passed for {}: {}", addr, e);
+        return None;
+    }
+    outputln!("Listening on {} with the socket passed by systemd", addr);
+    Some(listener)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn take_listener(_addr: SocketAddr) -> Option<TcpListener> { None }
+
+/// The listener of a gateway: the socket systemd passed for `addr`, or else a new one bound the way
+/// gossip listeners are, so that one bound to `[::]` accepts IPv4 connections too.
+pub fn gateway_listener(addr: SocketAddr) -> io::Result<TcpListener> {
+    match take_listener(addr) {
+        Some(listener) => Ok(listener),
+        None => {
+            let listener = habitat_butterfly::tls::bind(addr)?;
+            listener.set_nonblocking(true)?;
+            Ok(listener)
+        }
+    }
+}
+
+/// The watchdog of the unit, if it is meant for the Supervisor or its Launcher
+pub fn watchdog() -> Watchdog {
+    #[cfg(target_os = "linux")]
+    let pids = {
+        let launcher = env::var(LAUNCHER_PID_ENV).ok()
+                                                 .and_then(|pid| pid.parse::<u32>().ok());
+        std::iter::once(process::id()).chain(launcher)
+                                      .collect::<Vec<_>>()
+    };
+    #[cfg(not(target_os = "linux"))]
+    let pids = Vec::new();
+    Watchdog::from_env(&pids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_sockets_serve_the_wildcard_address() {
+        let addr: SocketAddr = "0.0.0.0:9631".parse().unwrap();
+        assert!(serves("[::]:9631".parse().unwrap(), addr));
+        assert!(serves("0.0.0.0:9631".parse().unwrap(), addr));
+        assert!(!serves("[::]:9632".parse().unwrap(), addr));
+        assert!(!serves("127.0.0.1:9631".parse().unwrap(), addr));
+        assert!(serves("127.0.0.1:9632".parse().unwrap(),
+                       "127.0.0.1:9632".parse().unwrap()));
+    }
+}
@@ -1,8 +1,11 @@
 use crate::{error::{Error,
                     Result},
+            event::{self,
+                    PackageChangeInitiator},
             PRODUCT,
             VERSION};
 use hab::{command::pkg::{self,
+                         list,
                          uninstall::{self,
                                      UninstallHookMode,
                                      UninstallSafety}},
@@ -25,7 +28,8 @@ use habitat_core::{env as henv,
                              PackageTarget},
                    ChannelIdent,
                    AUTH_TOKEN_ENVVAR};
-use std::path::Path;
+use std::{net::SocketAddr,
+          path::Path};
 
 static LOGKEY: &str = "UT";
 
@@ -36,39 +40,60 @@ fn get_auth_token() -> Option<String> {
 
 /// Helper function for use in the Supervisor to handle lower-level
 /// arguments needed for installing a package.
+///
+/// An event is sent if the package was not installed yet, attributing it to `initiated_by` and
+/// the control gateway `client` it is installed for, if any.
 pub async fn install<T>(ui: &mut T,
                         url: &str,
                         install_source: &InstallSource,
-                        channel: &ChannelIdent)
+                        channel: &ChannelIdent,
+                        initiated_by: PackageChangeInitiator,
+                        client: Option<SocketAddr>)
                         -> Result<PackageInstall>
     where T: UIWriter
 {
     let fs_root_path = Path::new(&*FS_ROOT_PATH);
     let auth_token = get_auth_token();
-    install_cmd::start(ui,
-                       url,
-                       channel,
-                       install_source,
-                       PRODUCT,
-                       VERSION,
-                       fs_root_path,
-                       &fs::cache_artifact_path(None::<String>),
-                       auth_token.as_deref(),
-                       &InstallMode::default(),
-                       &LocalPackageUsage::default(),
-                       // Install hooks are run when the supervisor
-                       // loads the package in add_service so it is
-                       // repetitive to run them here
-                       InstallHookMode::Ignore).await
-                                               .map_err(Error::from)
+    let previous = installed(install_source);
+    let package = install_cmd::start(ui,
+                                     url,
+                                     channel,
+                                     install_source,
+                                     PRODUCT,
+                                     VERSION,
+                                     fs_root_path,
+                                     &fs::cache_artifact_path(None::<String>),
+                                     auth_token.as_deref(),
+                                     &InstallMode::default(),
+                                     &LocalPackageUsage::default(),
+                                     // Install hooks are run when the supervisor
+                                     // loads the package in add_service so it is
+                                     // repetitive to run them here
+                                     InstallHookMode::Ignore).await?;
+    if previous.map_or(true, |previous| previous.ident() != package.ident()) {
+        event::package_installed(package.ident(),
+                                 &package.tdeps().unwrap_or_default(),
+                                 install_source,
+                                 url,
+                                 channel,
+                                 initiated_by,
+                                 client);
+    }
+    Ok(package)
 }
 
 // `install` but with no ui output and the benefit of thread safety
 pub async fn install_no_ui(url: &str,
                            install_source: &InstallSource,
-                           channel: &ChannelIdent)
+                           channel: &ChannelIdent,
+                           initiated_by: PackageChangeInitiator)
                            -> Result<PackageInstall> {
-    install(&mut NullUi::new(), url, install_source, channel).await
+    install(&mut NullUi::new(),
+            url,
+            install_source,
+            channel,
+            initiated_by,
+            None).await
 }
 
 /// Given an InstallSource, install a new package only if an existing
@@ -80,13 +105,15 @@ pub async fn install_no_ui(url: &str,
 pub async fn satisfy_or_install<T>(ui: &mut T,
                                    install_source: &InstallSource,
                                    bldr_url: &str,
-                                   channel: &ChannelIdent)
+                                   channel: &ChannelIdent,
+                                   initiated_by: PackageChangeInitiator,
+                                   client: Option<SocketAddr>)
                                    -> Result<PackageInstall>
     where T: UIWriter
 {
     match installed(install_source) {
         Some(package) => Ok(package),
-        None => install(ui, bldr_url, install_source, channel).await,
+        None => install(ui, bldr_url, install_source, channel, initiated_by, client).await,
     }.and_then(|installed| {
          if installed.is_runnable() {
              Ok(installed)
@@ -105,13 +132,21 @@ pub fn installed<T>(ident: T) -> Option<PackageInstall>
     PackageInstall::load(ident.as_ref(), Some(fs_root_path)).ok()
 }
 
+/// The transitive dependencies of an installed package, for the events sent when it is
+/// uninstalled.
+fn installed_dependencies(ident: &PackageIdent) -> Vec<PackageIdent> {
+    installed(ident).and_then(|package| package.tdeps().ok())
+                    .unwrap_or_default()
+}
+
 /// Install a package but only consider packages from a channel. Do not consider any locally
 /// installed packages.
 ///
 /// This will always return the package at the head of the channel.
 pub async fn install_channel_head(url: &str,
                                   ident: impl AsRef<PackageIdent>,
-                                  channel: &ChannelIdent)
+                                  channel: &ChannelIdent,
+                                  initiated_by: PackageChangeInitiator)
                                   -> Result<PackageInstall> {
     let fs_root_path = Path::new(&*FS_ROOT_PATH);
     let auth_token = get_auth_token();
@@ -123,21 +158,40 @@ pub async fn install_channel_head(url: &str,
                                                        auth_token.as_deref())
                                          .await?;
     // Ensure the latest package from the channel is installed
-    install_no_ui(url, &channel_latest_ident.into(), channel).await
+    install_no_ui(url, &channel_latest_ident.into(), channel, initiated_by).await
 }
 
+/// Uninstall all but the latest `number_latest_to_keep` releases of a package, sending an event
+/// for each release that was garbage collected.
 pub async fn uninstall_all_but_latest(ident: impl AsRef<PackageIdent>,
                                       number_latest_to_keep: usize)
                                       -> HabResult<usize> {
-    uninstall::uninstall_all_but_latest(&mut NullUi::new(),
-                                        ident,
-                                        number_latest_to_keep,
-                                        &*FS_ROOT_PATH,
-                                        pkg::ExecutionStrategy::Run,
-                                        pkg::Scope::PackageAndDependencies,
-                                        &[],
-                                        UninstallHookMode::default(),
-                                        UninstallSafety::Safe).await
+    let ident = ident.as_ref();
+    let releases = || list::package_list(&ident.clone().into()).unwrap_or_default();
+    let previous: Vec<_> = releases().into_iter()
+                                     .map(|release| {
+                                         let dependencies = installed_dependencies(&release);
+                                         (release, dependencies)
+                                     })
+                                     .collect();
+    let result = uninstall::uninstall_all_but_latest(&mut NullUi::new(),
+                                                     ident,
+                                                     number_latest_to_keep,
+                                                     &*FS_ROOT_PATH,
+                                                     pkg::ExecutionStrategy::Run,
+                                                     pkg::Scope::PackageAndDependencies,
+                                                     &[],
+                                                     UninstallHookMode::default(),
+                                                     UninstallSafety::Safe).await;
+    let remaining = releases();
+    for (release, dependencies) in previous.iter()
+                                           .filter(|(release, _)| !remaining.contains(release))
+    {
+        event::package_uninstalled(release,
+                                   dependencies,
+                                   PackageChangeInitiator::GarbageCollection);
+    }
+    result
 }
 
 /// Uninstall a package given a package identifier.
@@ -146,12 +200,34 @@ pub async fn uninstall_all_but_latest(ident: impl AsRef<PackageIdent>,
 /// loaded by the Supervisor. This is needed for service rollback where the package we are
 /// uninstalling is the currently loaded package.
 pub async fn uninstall_even_if_loaded(ident: impl AsRef<PackageIdent>) -> HabResult<()> {
+    let ident = ident.as_ref();
+    let dependencies = installed_dependencies(ident);
     uninstall::uninstall(&mut NullUi::new(),
-                         &ident.as_ref(),
+                         &ident,
                          &*FS_ROOT_PATH,
                          pkg::ExecutionStrategy::Run,
                          pkg::Scope::PackageAndDependencies,
                          &[],
                          UninstallHookMode::default(),
-                         UninstallSafety::Force).await
+                         UninstallSafety::Force).await?;
+    event::package_uninstalled(ident, &dependencies, PackageChangeInitiator::Rollback);
+    Ok(())
+}
+
+/// Download the current releases of the packages of the package set file at `file` into the
+/// artifact cache, without installing them. Identifiers in a plain text file are fetched from the
+/// default channel for the active target.
+pub async fn prefetch(url: &str, file: &Path) -> HabResult<()> {
+    let package_sets = pkg::prefetch::package_sets_from_file(file,
+                                                             &ChannelIdent::default(),
+                                                             PackageTarget::active_target())?;
+    let auth_token = get_auth_token();
+    pkg::prefetch::start(&mut NullUi::new(),
+                         url,
+                         PRODUCT,
+                         VERSION,
+                         &package_sets,
+                         Path::new(&*FS_ROOT_PATH),
+                         auth_token.as_deref(),
+                         false).await
 }
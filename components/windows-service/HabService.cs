@@ -36,6 +36,13 @@ namespace HabService
             CTRL_SHUTDOWN_EVENT
         }
 
+        // The Launcher exits with these when it stops keeping the Supervisor alive
+        private const int ERR_NO_RETRY_EXCODE = 86;
+        private const int SUP_RESTART_LIMIT_EXCODE = 87;
+        // Stopping with this exit code marks the service failed, so that the SCM runs its recovery
+        // actions when they are set to run on failures without a crash
+        private const int ERROR_SERVICE_SPECIFIC_ERROR = 1066;
+
         private Process proc = null;
         private static readonly log4net.ILog log = log4net.LogManager.GetLogger(typeof(HabService));
 
@@ -187,9 +194,31 @@ namespace HabService
                 log.Info("Waiting for Supervisor to exit...");
                 sup.WaitForExit();
             }
+            if (proc.ExitCode != 0)
+            {
+                // The exit code of the Launcher is the event ID, so that alerts can tell the
+                // reasons apart
+                var reason = LauncherFailure(proc.ExitCode);
+                log.Error(String.Format("Chef Habitat Windows Service is failing: {0}", reason));
+                EventLog.WriteEntry(reason, EventLogEntryType.Error, proc.ExitCode);
+                ExitCode = ERROR_SERVICE_SPECIFIC_ERROR;
+            }
             Stop();
         }
 
+        private static string LauncherFailure(int exitCode)
+        {
+            switch (exitCode)
+            {
+                case ERR_NO_RETRY_EXCODE:
+                    return "The Chef Habitat Supervisor exited and asked not to be restarted";
+                case SUP_RESTART_LIMIT_EXCODE:
+                    return "The Chef Habitat Supervisor failed after being restarted too many times";
+                default:
+                    return String.Format("The Chef Habitat Launcher exited with code {0}", exitCode);
+            }
+        }
+
         protected override void OnStop()
         {
             try
@@ -52,7 +52,7 @@ Describe "hab svc update" {
 
         # This is the same as saying "remove binds"... we might
         # find a better way to express this, though.
-        hab svc update habitat-testing/test-probe --bind
+        hab svc update habitat-testing/test-probe --bind --force
 
         # Currently, test-probe has some long-running post-stop
         # and init hooks. They should be done within 30 seconds,
@@ -81,7 +81,7 @@ Describe "hab svc update" {
 
         $proc = Get-Process test-probe
 
-        hab svc update habitat-testing/test-probe --strategy=at-once
+        hab svc update habitat-testing/test-probe --strategy=at-once --force
 
         # This shouldn't take too long to register, and shouldn't
         # trigger a restart, but to catch potential regressions
@@ -147,7 +147,7 @@ Describe "hab svc update" {
             ((Invoke-WebRequest "http://localhost:9631/services/nginx/default" -UseBasicParsing).content | ConvertFrom-Json).channel | Should -Be $testChannelOne
         }
 
-        hab svc update $nginx_pkg --channel $testChannelTwo
+        hab svc update $nginx_pkg --channel $testChannelTwo --force
 
         # Give *plenty* of time to pick up the new spec (as well as
         # time for a service to restart, if things are broken and